@@ -280,6 +280,70 @@ impl FromStr for DefaultApplicationsSettingPath {
 }
 
 
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AudioSettingPath {
+    AppProfiles,
+}
+
+impl fmt::Display for AudioSettingPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", match self {
+            AudioSettingPath::AppProfiles => "app-profiles",
+        })
+    }
+}
+
+impl FromStr for AudioSettingPath {
+    type Err = SettingPathParseError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "app-profiles" => Ok(AudioSettingPath::AppProfiles),
+            _ => Err(SettingPathParseError::UnknownSegment { segment: s.to_string(), path_str: s.to_string() }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum NotificationSettingPath {
+    MaxActivePopups,
+    MaxHistoryItems,
+    DigestBurstThreshold,
+    DigestWindowSecs,
+    HistoryRetention,
+    FocusModes,
+}
+
+impl fmt::Display for NotificationSettingPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", match self {
+            NotificationSettingPath::MaxActivePopups => "max-active-popups",
+            NotificationSettingPath::MaxHistoryItems => "max-history-items",
+            NotificationSettingPath::DigestBurstThreshold => "digest-burst-threshold",
+            NotificationSettingPath::DigestWindowSecs => "digest-window-secs",
+            NotificationSettingPath::HistoryRetention => "history-retention",
+            NotificationSettingPath::FocusModes => "focus-modes",
+        })
+    }
+}
+
+impl FromStr for NotificationSettingPath {
+    type Err = SettingPathParseError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "max-active-popups" => Ok(NotificationSettingPath::MaxActivePopups),
+            "max-history-items" => Ok(NotificationSettingPath::MaxHistoryItems),
+            "digest-burst-threshold" => Ok(NotificationSettingPath::DigestBurstThreshold),
+            "digest-window-secs" => Ok(NotificationSettingPath::DigestWindowSecs),
+            "history-retention" => Ok(NotificationSettingPath::HistoryRetention),
+            "focus-modes" => Ok(NotificationSettingPath::FocusModes),
+            _ => Err(SettingPathParseError::UnknownSegment { segment: s.to_string(), path_str: s.to_string() }),
+        }
+    }
+}
+
+
 // --- Main SettingPath Enum ---
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -291,11 +355,15 @@ pub enum SettingPath {
     InputBehaviorRoot,
     PowerManagementPolicyRoot,
     DefaultApplicationsRoot,
+    AudioRoot,
+    NotificationsRoot,
     Appearance(AppearanceSettingPath),
     Workspaces(WorkspaceSettingPath),
     InputBehavior(InputBehaviorSettingPath),
     PowerManagementPolicy(PowerManagementPolicySettingPath),
     DefaultApplications(DefaultApplicationsSettingPath),
+    Audio(AudioSettingPath),
+    Notifications(NotificationSettingPath),
 }
 
 impl fmt::Display for SettingPath {
@@ -307,11 +375,15 @@ impl fmt::Display for SettingPath {
             SettingPath::InputBehaviorRoot => write!(f, "input-behavior"),
             SettingPath::PowerManagementPolicyRoot => write!(f, "power-management-policy"),
             SettingPath::DefaultApplicationsRoot => write!(f, "default-applications"),
+            SettingPath::AudioRoot => write!(f, "audio"),
+            SettingPath::NotificationsRoot => write!(f, "notifications"),
             SettingPath::Appearance(sub_path) => write!(f, "appearance.{}", sub_path),
             SettingPath::Workspaces(sub_path) => write!(f, "workspaces.{}", sub_path),
             SettingPath::InputBehavior(sub_path) => write!(f, "input-behavior.{}", sub_path),
             SettingPath::PowerManagementPolicy(sub_path) => write!(f, "power-management-policy.{}", sub_path),
             SettingPath::DefaultApplications(sub_path) => write!(f, "default-applications.{}", sub_path),
+            SettingPath::Audio(sub_path) => write!(f, "audio.{}", sub_path),
+            SettingPath::Notifications(sub_path) => write!(f, "notifications.{}", sub_path),
         }
     }
 }
@@ -325,6 +397,8 @@ impl FromStr for SettingPath {
         if s == "input-behavior" { return Ok(SettingPath::InputBehaviorRoot); }
         if s == "power-management-policy" { return Ok(SettingPath::PowerManagementPolicyRoot); }
         if s == "default-applications" { return Ok(SettingPath::DefaultApplicationsRoot); }
+        if s == "audio" { return Ok(SettingPath::AudioRoot); }
+        if s == "notifications" { return Ok(SettingPath::NotificationsRoot); }
         
         let mut parts = s.splitn(2, '.');
         let top_level_segment = parts.next().ok_or_else(|| SettingPathParseError::InvalidFormat(s.to_string()))?;
@@ -351,6 +425,14 @@ impl FromStr for SettingPath {
                  SettingPathParseError::UnknownSegment { segment, .. } => SettingPathParseError::UnknownSegment { segment, path_str: s.to_string() },
                 _ => SettingPathParseError::IncompletePath(s.to_string()),
             })?)),
+            "audio" => Ok(SettingPath::Audio(AudioSettingPath::from_str(rest).map_err(|e| match e {
+                 SettingPathParseError::UnknownSegment { segment, .. } => SettingPathParseError::UnknownSegment { segment, path_str: s.to_string() },
+                _ => SettingPathParseError::IncompletePath(s.to_string()),
+            })?)),
+            "notifications" => Ok(SettingPath::Notifications(NotificationSettingPath::from_str(rest).map_err(|e| match e {
+                 SettingPathParseError::UnknownSegment { segment, .. } => SettingPathParseError::UnknownSegment { segment, path_str: s.to_string() },
+                _ => SettingPathParseError::IncompletePath(s.to_string()),
+            })?)),
             _ => Err(SettingPathParseError::UnknownSegment { segment: top_level_segment.to_string(), path_str: s.to_string() }),
         }
     }
@@ -393,6 +475,17 @@ mod tests {
         
         assert_eq!(SettingPath::WorkspacesRoot.to_string(), "workspaces");
         assert_eq!("workspaces".parse::<SettingPath>().unwrap(), SettingPath::WorkspacesRoot);
+
+        assert_eq!(SettingPath::AudioRoot.to_string(), "audio");
+        assert_eq!("audio".parse::<SettingPath>().unwrap(), SettingPath::AudioRoot);
+
+        assert_eq!(SettingPath::NotificationsRoot.to_string(), "notifications");
+        assert_eq!("notifications".parse::<SettingPath>().unwrap(), SettingPath::NotificationsRoot);
+        let window = SettingPath::Notifications(NotificationSettingPath::DigestWindowSecs);
+        assert_eq!(window.to_string(), "notifications.digest-window-secs");
+        assert_eq!("notifications.digest-window-secs".parse::<SettingPath>().unwrap(), window);
+        assert_eq!(SettingPath::Audio(AudioSettingPath::AppProfiles).to_string(), "audio.app-profiles");
+        assert_eq!("audio.app-profiles".parse::<SettingPath>().unwrap(), SettingPath::Audio(AudioSettingPath::AppProfiles));
     }
 
 
@@ -5,7 +5,7 @@ use tokio::sync::{broadcast, RwLock};
 use tracing::{debug, error, warn};
 
 use super::types::GlobalDesktopSettings;
-use super::paths::{SettingPath, AppearanceSettingPath, FontSettingPath, WorkspaceSettingPath, InputBehaviorSettingPath, PowerManagementPolicySettingPath, DefaultApplicationsSettingPath};
+use super::paths::{SettingPath, AppearanceSettingPath, FontSettingPath, WorkspaceSettingPath, InputBehaviorSettingPath, PowerManagementPolicySettingPath, DefaultApplicationsSettingPath, AudioSettingPath, NotificationSettingPath};
 use super::errors::GlobalSettingsError;
 use super::events::{SettingChangedEvent, SettingsLoadedEvent, SettingsSavedEvent};
 use super::persistence_iface::SettingsPersistenceProvider;
@@ -155,8 +155,20 @@ impl GlobalSettingsService for DefaultGlobalSettingsService {
                 DefaultApplicationsSettingPath::ImageViewer => update_field!(new_settings.default_applications, image_viewer, value, path, "String"),
                 DefaultApplicationsSettingPath::TextEditor => update_field!(new_settings.default_applications, text_editor, value, path, "String"),
             },
+            SettingPath::Audio(ref au_path) => match au_path {
+                AudioSettingPath::AppProfiles => update_field!(new_settings.audio, app_profiles, value, path, "Vec<AppAudioProfile>"),
+            },
+            SettingPath::Notifications(ref no_path) => match no_path {
+                NotificationSettingPath::MaxActivePopups => update_field!(new_settings.notifications, max_active_popups, value, path, "usize"),
+                NotificationSettingPath::MaxHistoryItems => update_field!(new_settings.notifications, max_history_items, value, path, "usize"),
+                NotificationSettingPath::DigestBurstThreshold => update_field!(new_settings.notifications, digest_burst_threshold, value, path, "usize"),
+                NotificationSettingPath::DigestWindowSecs => update_field!(new_settings.notifications, digest_window_secs, value, path, "u64"),
+                NotificationSettingPath::HistoryRetention => update_field!(new_settings.notifications, history_retention, value, path, "Option<HistoryRetention>"),
+                NotificationSettingPath::FocusModes => update_field!(new_settings.notifications, focus_modes, value, path, "Option<Vec<FocusMode>>"),
+            },
             SettingPath::Root | SettingPath::AppearanceRoot | SettingPath::WorkspacesRoot | 
-            SettingPath::InputBehaviorRoot | SettingPath::PowerManagementPolicyRoot | SettingPath::DefaultApplicationsRoot => {
+            SettingPath::InputBehaviorRoot | SettingPath::PowerManagementPolicyRoot | SettingPath::DefaultApplicationsRoot |
+            SettingPath::AudioRoot | SettingPath::NotificationsRoot => {
                 return Err(GlobalSettingsError::InvalidValueType {
                     path: path.clone(),
                     expected_type: "Specific setting path".to_string(),
@@ -168,6 +180,8 @@ impl GlobalSettingsService for DefaultGlobalSettingsService {
         new_settings.validate_recursive()?;
         
         *settings_guard = new_settings;
+        // save_settings reads the settings again.
+        drop(settings_guard);
         debug!("Service: Setting updated and validated successfully for path: {:?}", path);
 
         if let Err(e) = self.event_sender.send(SettingChangedEvent { path, new_value: value }) {
@@ -245,11 +259,24 @@ impl GlobalSettingsService for DefaultGlobalSettingsService {
                 DefaultApplicationsSettingPath::ImageViewer => get_json_value!(&settings_guard.default_applications.image_viewer),
                 DefaultApplicationsSettingPath::TextEditor => get_json_value!(&settings_guard.default_applications.text_editor),
             },
+            SettingPath::Audio(au_path) => match au_path {
+                AudioSettingPath::AppProfiles => get_json_value!(&settings_guard.audio.app_profiles),
+            },
+            SettingPath::Notifications(no_path) => match no_path {
+                NotificationSettingPath::MaxActivePopups => get_json_value!(&settings_guard.notifications.max_active_popups),
+                NotificationSettingPath::MaxHistoryItems => get_json_value!(&settings_guard.notifications.max_history_items),
+                NotificationSettingPath::DigestBurstThreshold => get_json_value!(&settings_guard.notifications.digest_burst_threshold),
+                NotificationSettingPath::DigestWindowSecs => get_json_value!(&settings_guard.notifications.digest_window_secs),
+                NotificationSettingPath::HistoryRetention => get_json_value!(&settings_guard.notifications.history_retention),
+                NotificationSettingPath::FocusModes => get_json_value!(&settings_guard.notifications.focus_modes),
+            },
             SettingPath::AppearanceRoot => get_json_value!(&settings_guard.appearance),
             SettingPath::WorkspacesRoot => get_json_value!(&settings_guard.workspaces),
             SettingPath::InputBehaviorRoot => get_json_value!(&settings_guard.input_behavior),
             SettingPath::PowerManagementPolicyRoot => get_json_value!(&settings_guard.power_management_policy),
             SettingPath::DefaultApplicationsRoot => get_json_value!(&settings_guard.default_applications),
+            SettingPath::AudioRoot => get_json_value!(&settings_guard.audio),
+            SettingPath::NotificationsRoot => get_json_value!(&settings_guard.notifications),
             SettingPath::Root => get_json_value!(&*settings_guard),
         }
     }
@@ -260,6 +287,7 @@ impl GlobalSettingsService for DefaultGlobalSettingsService {
         let defaults = GlobalDesktopSettings::default();
         
         *settings_guard = defaults.clone();
+        drop(settings_guard);
 
         let paths_to_notify = [
             (SettingPath::AppearanceRoot, serde_json::to_value(&defaults.appearance).unwrap_or(JsonValue::Null)),
@@ -267,6 +295,8 @@ impl GlobalSettingsService for DefaultGlobalSettingsService {
             (SettingPath::InputBehaviorRoot, serde_json::to_value(&defaults.input_behavior).unwrap_or(JsonValue::Null)),
             (SettingPath::PowerManagementPolicyRoot, serde_json::to_value(&defaults.power_management_policy).unwrap_or(JsonValue::Null)),
             (SettingPath::DefaultApplicationsRoot, serde_json::to_value(&defaults.default_applications).unwrap_or(JsonValue::Null)),
+            (SettingPath::AudioRoot, serde_json::to_value(&defaults.audio).unwrap_or(JsonValue::Null)),
+            (SettingPath::NotificationsRoot, serde_json::to_value(&defaults.notifications).unwrap_or(JsonValue::Null)),
         ];
 
         for (path, new_value) in paths_to_notify {
@@ -398,6 +428,30 @@ mod tests {
         assert!(matches!(update_result.unwrap_err(), GlobalSettingsError::ValidationError { path: SettingPath::AppearanceRoot, .. }));
     }
 
+    #[tokio::test]
+    async fn test_update_audio_app_profiles() {
+        let mut mock_persistence = MockSettingsPersistenceProvider::new();
+        mock_persistence.expect_load_global_settings().returning(|| Ok(GlobalDesktopSettings::default()));
+        mock_persistence.expect_save_global_settings().times(1).returning(|_| Ok(()));
+
+        let service = DefaultGlobalSettingsService::new(Arc::new(mock_persistence), 5);
+        service.load_settings().await.unwrap();
+
+        let path = SettingPath::Audio(AudioSettingPath::AppProfiles);
+        let profiles = serde_json::json!([
+            { "app-id": "spotify", "volume": 0.4, "output-devices": ["bluez_output.headphones"] }
+        ]);
+        service.update_setting(path.clone(), profiles).await.unwrap();
+        let spotify = service.get_current_settings().audio.app_profile("spotify").cloned().unwrap();
+        assert_eq!(spotify.volume, Some(0.4));
+        assert_eq!(spotify.output_devices, ["bluez_output.headphones"]);
+        assert!(spotify.input_devices.is_empty());
+
+        let duplicates = serde_json::json!([{ "app-id": "zoom" }, { "app-id": "zoom" }]);
+        let update_result = service.update_setting(path, duplicates).await;
+        assert!(matches!(update_result.unwrap_err(), GlobalSettingsError::ValidationError { path: SettingPath::AudioRoot, .. }));
+    }
+
     #[tokio::test]
     async fn test_get_setting_successful() {
         let mock_persistence = MockSettingsPersistenceProvider::new(); 
@@ -2,6 +2,7 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use super::paths::SettingPath; // For validate_recursive
 use super::errors::GlobalSettingsError; // For validate_recursive
+use crate::user_centric_services::notifications_core::{focus::FocusMode, grouping, history, service as notification_service};
 
 // --- Enums ---
 
@@ -358,6 +359,120 @@ impl DefaultApplicationsSettings {
 }
 
 
+/// Remembered audio preferences of one application, keyed by its app id.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "kebab-case")]
+pub struct AppAudioProfile {
+    pub app_id: String,
+    /// Playback volume between 0.0 and 1.0; `None` leaves the volume alone.
+    #[serde(default)]
+    pub volume: Option<f64>,
+    /// Preferred output devices, most preferred first.
+    #[serde(default)]
+    pub output_devices: Vec<String>,
+    /// Preferred input devices, most preferred first.
+    #[serde(default)]
+    pub input_devices: Vec<String>,
+}
+
+impl AppAudioProfile {
+    pub fn new(app_id: impl Into<String>) -> Self {
+        Self { app_id: app_id.into(), ..Default::default() }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.app_id.trim().is_empty() {
+            return Err("Audio profile app id cannot be empty.".to_string());
+        }
+        if let Some(volume) = self.volume {
+            if !(0.0..=1.0).contains(&volume) {
+                return Err(format!("Volume {} of audio profile '{}' must be between 0.0 and 1.0.", volume, self.app_id));
+            }
+        }
+        if self.output_devices.iter().chain(&self.input_devices).any(|device| device.is_empty()) {
+            return Err(format!("Audio profile '{}' contains an empty device name.", self.app_id));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "kebab-case")]
+pub struct AudioSettings {
+    #[serde(default)]
+    pub app_profiles: Vec<AppAudioProfile>,
+}
+
+impl AudioSettings {
+    pub fn app_profile(&self, app_id: &str) -> Option<&AppAudioProfile> {
+        self.app_profiles.iter().find(|profile| profile.app_id == app_id)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let mut seen = std::collections::HashSet::new();
+        for profile in &self.app_profiles {
+            profile.validate()?;
+            if !seen.insert(profile.app_id.as_str()) {
+                return Err(format!("Duplicate audio profile for app '{}'.", profile.app_id));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Limits and policies of the notification service.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct NotificationSettings {
+    /// Popups shown at once; further notifications wait in the notification center.
+    pub max_active_popups: usize,
+    /// Most history entries kept.
+    pub max_history_items: usize,
+    /// Notifications of a burst that pop up on their own before a digest takes over.
+    pub digest_burst_threshold: usize,
+    /// Longest gap in seconds between two notifications of the same burst.
+    pub digest_window_secs: u64,
+    /// Full history retention policy; when set it wins over `max_history_items`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub history_retention: Option<history::HistoryRetention>,
+    /// Configured focus modes; `None` keeps the built-in ones.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub focus_modes: Option<Vec<FocusMode>>,
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        Self {
+            max_active_popups: notification_service::DEFAULT_MAX_ACTIVE_POPUPS,
+            max_history_items: history::DEFAULT_HISTORY_MAX_ITEMS,
+            digest_burst_threshold: grouping::DEFAULT_DIGEST_BURST_THRESHOLD,
+            digest_window_secs: grouping::DEFAULT_DIGEST_WINDOW_SECS as u64,
+            history_retention: None,
+            focus_modes: None,
+        }
+    }
+}
+
+impl NotificationSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_active_popups == 0 {
+            return Err("At least one popup must be allowed.".to_string());
+        }
+        if self.digest_burst_threshold == 0 {
+            return Err("Digest burst threshold must be at least 1.".to_string());
+        }
+        if !(1..=86_400).contains(&self.digest_window_secs) {
+            return Err(format!("Digest window of {}s must be between one second and a day.", self.digest_window_secs));
+        }
+        let mut ids = std::collections::HashSet::new();
+        if let Some(mode) = self.focus_modes.iter().flatten().find(|mode| mode.id.trim().is_empty() || !ids.insert(mode.id.as_str())) {
+            return Err(format!("Focus mode ids must be unique and non-empty, got '{}'.", mode.id));
+        }
+        Ok(())
+    }
+}
+
+
 // --- Main GlobalDesktopSettings Struct ---
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
@@ -373,6 +488,10 @@ pub struct GlobalDesktopSettings {
     pub power_management_policy: PowerManagementPolicySettings,
     #[serde(default)]
     pub default_applications: DefaultApplicationsSettings,
+    #[serde(default)]
+    pub audio: AudioSettings,
+    #[serde(default)]
+    pub notifications: NotificationSettings,
 }
 
 impl GlobalDesktopSettings {
@@ -382,6 +501,8 @@ impl GlobalDesktopSettings {
         self.input_behavior.validate().map_err(|e| format!("Input behavior settings: {}", e))?;
         self.power_management_policy.validate().map_err(|e| format!("Power management policy settings: {}", e))?;
         self.default_applications.validate().map_err(|e| format!("Default applications settings: {}", e))?;
+        self.audio.validate().map_err(|e| format!("Audio settings: {}", e))?;
+        self.notifications.validate().map_err(|e| format!("Notification settings: {}", e))?;
         Ok(())
     }
 
@@ -392,6 +513,8 @@ impl GlobalDesktopSettings {
         self.input_behavior.validate().map_err(|e| GlobalSettingsError::ValidationError { path: SettingPath::InputBehaviorRoot, reason: e })?;
         self.power_management_policy.validate().map_err(|e| GlobalSettingsError::ValidationError { path: SettingPath::PowerManagementPolicyRoot, reason: e })?;
         self.default_applications.validate().map_err(|e| GlobalSettingsError::ValidationError { path: SettingPath::DefaultApplicationsRoot, reason: e })?;
+        self.audio.validate().map_err(|e| GlobalSettingsError::ValidationError { path: SettingPath::AudioRoot, reason: e })?;
+        self.notifications.validate().map_err(|e| GlobalSettingsError::ValidationError { path: SettingPath::NotificationsRoot, reason: e })?;
         Ok(())
     }
 }
@@ -474,6 +597,45 @@ mod tests {
         assert!(das.validate().is_ok());
     }
 
+    #[test]
+    fn audio_settings_validation() {
+        let mut audio = AudioSettings::default();
+        let mut spotify = AppAudioProfile::new("spotify");
+        spotify.volume = Some(0.4);
+        spotify.output_devices = vec!["bluez_output.headphones".to_string()];
+        audio.app_profiles.push(spotify.clone());
+        assert!(audio.validate().is_ok());
+        assert_eq!(audio.app_profile("spotify"), Some(&spotify));
+
+        audio.app_profiles.push(AppAudioProfile::new("spotify"));
+        assert!(audio.validate().is_err(), "Should fail on duplicate app ids");
+        audio.app_profiles.pop();
+
+        audio.app_profiles[0].volume = Some(1.5);
+        assert!(audio.validate().is_err());
+        audio.app_profiles[0].volume = None;
+        audio.app_profiles[0].app_id = " ".to_string();
+        assert!(audio.validate().is_err());
+    }
+
+    #[test]
+    fn notification_settings_validation() {
+        let mut notifications = NotificationSettings::default();
+        assert!(notifications.validate().is_ok());
+        let parsed: NotificationSettings = serde_json::from_str(r#"{"digest-window-secs": 30}"#).unwrap();
+        assert_eq!(parsed.digest_window_secs, 30);
+        assert_eq!(parsed.max_active_popups, notifications.max_active_popups);
+
+        notifications.max_active_popups = 0;
+        assert!(notifications.validate().is_err());
+        notifications.max_active_popups = 3;
+        notifications.digest_window_secs = 0;
+        assert!(notifications.validate().is_err());
+        notifications.digest_window_secs = 60;
+        notifications.focus_modes = Some(vec![FocusMode::new("sleep", "Sleep"), FocusMode::new("sleep", "Nap")]);
+        assert!(notifications.validate().is_err(), "Should fail on duplicate focus mode ids");
+    }
+
     #[test]
     fn test_color_scheme_serde() {
         let cs = ColorScheme::Dark;
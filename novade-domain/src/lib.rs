@@ -34,7 +34,8 @@ pub use global_settings::{
         FontSettings, 
         WorkspaceSettings as GlobalWorkspaceSettings, // Aliased for clarity
         InputBehaviorSettings, 
-        PowerManagementPolicySettings, DefaultApplicationsSettings
+        PowerManagementPolicySettings, DefaultApplicationsSettings,
+        AudioSettings, AppAudioProfile, NotificationSettings,
     },
     paths::SettingPath,
     events::{SettingChangedEvent, SettingsLoadedEvent, SettingsSavedEvent},
@@ -52,6 +53,11 @@ pub use notifications_rules::{
         SimpleRuleCondition, NotificationRuleSet
     },
     engine::RuleProcessingResult as EngineRuleProcessingResult, // If RuleProcessingResult is also in engine
+    context::{RuleContext, RuleContextChange},
+    simulation::{
+        validate_rules, ConditionTrace, RuleSimulation, RuleTrace, RuleTraceStatus,
+        RuleValidationIssue, SimulationOutcome,
+    },
 };
 
 pub use shared_types::{ApplicationId, UserSessionState, ResourceIdentifier};
@@ -87,9 +93,24 @@ pub use user_centric_services::{
         // persistence::FilesystemNotificationPersistenceProvider, // If defined & public
         types::{
             Notification, NotificationInput, NotificationAction, NotificationUrgency, 
-            NotificationActionType, NotificationStats, DismissReason, 
-            NotificationFilterCriteria, NotificationSortOrder
+            NotificationActionType, NotificationImage, NotificationStats, DismissReason, 
+            NotificationFilterCriteria, NotificationSortOrder, RuleSimulationSample,
+            NotificationChannel, CHANNEL_HINT, SNOOZE_UNTIL_HINT, INLINE_REPLY_ACTION_KEY,
+            REPLY_PLACEHOLDER_HINT, PROGRESS_HINT, ATTACHMENT_URLS_HINT, NotificationSound,
+            SOUND_NAME_HINT, SOUND_FILE_HINT, SUPPRESS_SOUND_HINT
+        },
+        grouping::{
+            group_key, group_notifications, NotificationGroup, NotificationDigest, DigestPolicy,
+            GROUP_KEY_HINT, THREAD_ID_HINT,
+        },
+        focus::{
+            FocusMode, FocusSchedule, FocusTrigger, FocusActivation, ActiveFocus, MissedSummary,
+            DO_NOT_DISTURB_MODE_ID,
+        },
+        history::{
+            HistoryRetention, AppRetention, HistoryExport, HISTORY_EXPORT_VERSION, search_terms,
         },
+        persistence_iface::NotificationHistoryStore,
     },
     events::{UserCentricEvent, AIInteractionEventEnum, NotificationEventEnum},
 };
@@ -113,7 +134,7 @@ pub use workspaces::{
         FilesystemConfigProvider as FilesystemWorkspaceConfigProvider, // Aliased for clarity
         WorkspaceSnapshot, WorkspaceSetSnapshot
     },
-    core::types::{WorkspaceId, WindowIdentifier, WorkspaceLayoutType as CoreWorkspaceLayoutType},
+    core::types::{WorkspaceId, WindowIdentifier, WorkspaceLayoutType as CoreWorkspaceLayoutType, WorkspaceOutputPolicy},
     core::Workspace,
     core::errors::WorkspaceCoreError, 
     assignment::errors::WindowAssignmentError, 
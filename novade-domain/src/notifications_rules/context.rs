@@ -0,0 +1,51 @@
+//! Desktop state notification rules match on besides the notification itself.
+//!
+//! The system layer reports changes as [`RuleContextChange`]s through
+//! [`NotificationRulesEngine::update_context`](super::NotificationRulesEngine::update_context);
+//! conditions such as [`RuleCondition::Fullscreen`](super::RuleCondition::Fullscreen) read the
+//! resulting [`RuleContext`].
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct RuleContext {
+    /// App id of the focused window.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub focused_app: Option<String>,
+    /// Whether any window is fullscreen.
+    #[serde(default)]
+    pub fullscreen: bool,
+    /// Name of the workspace shown on the focused output.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub active_workspace: Option<String>,
+    /// Charge of the battery in percent; `None` without a battery.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub battery_percent: Option<u8>,
+    /// Whether the system runs on its battery rather than on line power.
+    #[serde(default)]
+    pub on_battery: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RuleContextChange {
+    FocusedApp(Option<String>),
+    Fullscreen(bool),
+    ActiveWorkspace(Option<String>),
+    Battery { percent: Option<u8>, on_battery: bool },
+}
+
+impl RuleContext {
+    pub fn apply(&mut self, change: RuleContextChange) {
+        match change {
+            RuleContextChange::FocusedApp(app) => self.focused_app = app,
+            RuleContextChange::Fullscreen(fullscreen) => self.fullscreen = fullscreen,
+            RuleContextChange::ActiveWorkspace(workspace) => self.active_workspace = workspace,
+            RuleContextChange::Battery { percent, on_battery } => {
+                self.battery_percent = percent;
+                self.on_battery = on_battery;
+            }
+        }
+    }
+}
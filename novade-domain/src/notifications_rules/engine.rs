@@ -1,20 +1,39 @@
 use async_trait::async_trait;
-use std::collections::HashMap;
+use chrono::{DateTime, Duration, Local, NaiveTime, TimeZone, Utc};
+use futures_util::future::{BoxFuture, FutureExt};
+use std::collections::{HashMap, VecDeque};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use uuid::Uuid;
 use regex::Regex;
 use tracing::{debug, error, info, warn};
 
-use crate::user_centric_services::notifications_core::types::{Notification, NotificationAction as CoreNotificationAction, NotificationUrgency};
+use crate::user_centric_services::notifications_core::types::{Notification, NotificationAction as CoreNotificationAction, NotificationChannel, NotificationUrgency, CHANNEL_HINT, SNOOZE_UNTIL_HINT};
+use crate::user_centric_services::notifications_core::grouping::GROUP_KEY_HINT;
 use crate::global_settings::{GlobalSettingsService, paths::SettingPath, errors::GlobalSettingsError};
 
 use super::types::{
     NotificationRuleSet, NotificationRule, RuleCondition, RuleAction, 
     RuleConditionField, RuleConditionOperator, RuleConditionValue, SimpleRuleCondition,
 };
+use super::context::{RuleContext, RuleContextChange};
 use super::errors::NotificationRulesError;
 use super::persistence_iface::NotificationRulesProvider;
+use super::simulation::{self, ConditionTrace, RuleSimulation, RuleTrace, RuleTraceStatus, RuleValidationIssue, SimulationOutcome};
+
+/// Compiled regexes by pattern; patterns that failed to compile keep their error.
+type RegexCache = HashMap<String, Result<Regex, NotificationRulesError>>;
+
+/// Recent arrival times per rule and app, for `RuleCondition::RateAbove`.
+type RateWindows = HashMap<(Uuid, String), VecDeque<DateTime<Utc>>>;
+
+/// What a condition is evaluated against besides the notification and the engine's state.
+struct Evaluation<'a> {
+    regexes: &'a RegexCache,
+    /// Notifications not yet in the rate windows that still count towards them; 1 while
+    /// simulating, as the sample is never recorded.
+    unrecorded: usize,
+}
 
 // --- RuleProcessingResult Enum ---
 #[derive(Debug, Clone, PartialEq)]
@@ -29,7 +48,17 @@ pub trait NotificationRulesEngine: Send + Sync {
     async fn reload_rules(&self) -> Result<(), NotificationRulesError>;
     async fn process_notification(&self, notification: Notification) -> Result<RuleProcessingResult, NotificationRulesError>;
     async fn get_rules(&self) -> Result<NotificationRuleSet, NotificationRulesError>;
+    /// Replaces the rules; rejected with every issue [`Self::validate_rules`] reports if any.
     async fn update_rules(&self, new_rules: NotificationRuleSet) -> Result<(), NotificationRulesError>;
+    fn validate_rules(&self, rules: &NotificationRuleSet) -> Vec<RuleValidationIssue>;
+    /// Runs `notification` through `rules`, or the active rules if `None`, without side effects
+    /// and traces every step. Draft rules need not be valid; broken conditions show up in the
+    /// trace.
+    async fn simulate(&self, notification: Notification, rules: Option<NotificationRuleSet>) -> Result<RuleSimulation, NotificationRulesError>;
+    /// Tells the engine the active focus mode, for `RuleCondition::FocusModeActive`.
+    async fn set_active_focus_mode(&self, mode_id: Option<String>);
+    /// Tells the engine about a change to the desktop state context conditions match on.
+    async fn update_context(&self, change: RuleContextChange);
 }
 
 // --- DefaultNotificationRulesEngine Struct ---
@@ -37,7 +66,10 @@ pub struct DefaultNotificationRulesEngine {
     rules: Arc<RwLock<NotificationRuleSet>>,
     rules_provider: Arc<dyn NotificationRulesProvider>,
     settings_service: Arc<dyn GlobalSettingsService>,
-    regex_cache: Arc<RwLock<HashMap<String, Result<Regex, NotificationRulesError>>>>,
+    regex_cache: Arc<RwLock<RegexCache>>,
+    active_focus_mode: Arc<RwLock<Option<String>>>,
+    context: Arc<RwLock<RuleContext>>,
+    rate_windows: Arc<RwLock<RateWindows>>,
 }
 
 impl DefaultNotificationRulesEngine {
@@ -50,6 +82,9 @@ impl DefaultNotificationRulesEngine {
             rules_provider,
             settings_service,
             regex_cache: Arc::new(RwLock::new(HashMap::new())),
+            active_focus_mode: Arc::new(RwLock::new(None)),
+            context: Arc::new(RwLock::new(RuleContext::default())),
+            rate_windows: Arc::new(RwLock::new(HashMap::new())),
         });
         engine.load_rules_internal(false).await?;
         Ok(engine)
@@ -83,7 +118,7 @@ impl DefaultNotificationRulesEngine {
     fn validate_and_cache_regex_in_condition_recursive(
         &self,
         condition: &RuleCondition,
-        regex_cache: &mut HashMap<String, Result<Regex, NotificationRulesError>>, // Pass mutable ref to the cache
+        regex_cache: &mut RegexCache, // Pass mutable ref to the cache
     ) -> Result<(), NotificationRulesError> {
         match condition {
             RuleCondition::Simple(simple_cond) => {
@@ -121,9 +156,9 @@ impl DefaultNotificationRulesEngine {
         Ok(())
     }
 
-    async fn evaluate_condition_recursive(&self, condition: &RuleCondition, notification: &Notification, rule_name_for_error: &str, rule_id_for_error: Option<Uuid>) -> Result<bool, NotificationRulesError> {
+    async fn evaluate_condition_recursive(&self, condition: &RuleCondition, notification: &Notification, env: &Evaluation<'_>, rule_name_for_error: &str, rule_id_for_error: Option<Uuid>) -> Result<bool, NotificationRulesError> {
         match condition {
-            RuleCondition::Simple(simple_cond) => self.evaluate_simple_condition(simple_cond, notification, rule_name_for_error, rule_id_for_error).await,
+            RuleCondition::Simple(simple_cond) => self.evaluate_simple_condition(simple_cond, notification, env, rule_name_for_error, rule_id_for_error).await,
             RuleCondition::SettingIsTrue(setting_path) => {
                 match self.settings_service.get_setting(setting_path).await {
                     Ok(serde_json::Value::Bool(true)) => Ok(true),
@@ -138,19 +173,42 @@ impl DefaultNotificationRulesEngine {
                     }
                 }
             }
+            RuleCondition::FocusModeActive(mode_id) => {
+                let active = self.active_focus_mode.read().await;
+                Ok(match mode_id {
+                    Some(id) => active.as_deref() == Some(id.as_str()),
+                    None => active.is_some(),
+                })
+            }
+            RuleCondition::TimeWindow(schedule) => Ok(schedule.is_active_at(notification.timestamp.with_timezone(&Local).naive_local())),
+            RuleCondition::FocusedApp(app_id) => Ok(self.context.read().await.focused_app.as_deref() == Some(app_id.as_str())),
+            RuleCondition::Fullscreen => Ok(self.context.read().await.fullscreen),
+            RuleCondition::ActiveWorkspace(name) => Ok(self.context.read().await.active_workspace.as_deref() == Some(name.as_str())),
+            RuleCondition::BatteryAtMost(percent) => {
+                let context = self.context.read().await;
+                Ok(context.on_battery && context.battery_percent.map_or(false, |charge| charge <= *percent))
+            }
+            RuleCondition::RateAbove { count, minutes } => {
+                // Windows are kept per rule, so the rule id is part of the key here.
+                let key = (rule_id_for_error.unwrap_or_default(), notification.application_name.clone());
+                let horizon = notification.timestamp - Duration::minutes(i64::from(*minutes));
+                let windows = self.rate_windows.read().await;
+                let recorded = windows.get(&key).map_or(0, |stamps| stamps.iter().filter(|t| **t > horizon && **t <= notification.timestamp).count());
+                Ok(recorded + env.unrecorded > *count as usize)
+            }
             RuleCondition::And(conditions) => {
-                for cond in conditions { if !self.evaluate_condition_recursive(cond, notification, rule_name_for_error, rule_id_for_error).await? { return Ok(false); } }
+                for cond in conditions { if !self.evaluate_condition_recursive(cond, notification, env, rule_name_for_error, rule_id_for_error).await? { return Ok(false); } }
                 Ok(true)
             }
             RuleCondition::Or(conditions) => {
-                for cond in conditions { if self.evaluate_condition_recursive(cond, notification, rule_name_for_error, rule_id_for_error).await? { return Ok(true); } }
+                for cond in conditions { if self.evaluate_condition_recursive(cond, notification, env, rule_name_for_error, rule_id_for_error).await? { return Ok(true); } }
                 Ok(false)
             }
-            RuleCondition::Not(condition) => Ok(!self.evaluate_condition_recursive(condition.as_ref(), notification, rule_name_for_error, rule_id_for_error).await?),
+            RuleCondition::Not(condition) => Ok(!self.evaluate_condition_recursive(condition.as_ref(), notification, env, rule_name_for_error, rule_id_for_error).await?),
         }
     }
     
-    async fn evaluate_simple_condition(&self, simple_cond: &SimpleRuleCondition, notification: &Notification, rule_name_for_error: &str, rule_id_for_error: Option<Uuid>) -> Result<bool, NotificationRulesError> {
+    async fn evaluate_simple_condition(&self, simple_cond: &SimpleRuleCondition, notification: &Notification, env: &Evaluation<'_>, rule_name_for_error: &str, rule_id_for_error: Option<Uuid>) -> Result<bool, NotificationRulesError> {
         let field_str_value_opt: Option<String> = match &simple_cond.field {
             RuleConditionField::ApplicationName => Some(notification.application_name.clone()),
             RuleConditionField::Summary => Some(notification.summary.clone()),
@@ -180,8 +238,7 @@ impl DefaultNotificationRulesEngine {
                 }
             }
             RuleConditionValue::Regex(pattern_str) => {
-                let regex_cache_guard = self.regex_cache.read().await;
-                let regex_result = regex_cache_guard.get(pattern_str).ok_or_else(|| NotificationRulesError::InternalError(format!("Regex pattern '{}' not pre-compiled/cached. Rule: '{}' (ID: {:?})", pattern_str, rule_name_for_error, rule_id_for_error)))?;
+                let regex_result = env.regexes.get(pattern_str).ok_or_else(|| NotificationRulesError::InternalError(format!("Regex pattern '{}' not pre-compiled/cached. Rule: '{}' (ID: {:?})", pattern_str, rule_name_for_error, rule_id_for_error)))?;
                 match regex_result {
                     Ok(re) => match simple_cond.operator {
                         RuleConditionOperator::MatchesRegex => Ok(re.is_match(&field_str_value)),
@@ -227,6 +284,93 @@ impl DefaultNotificationRulesEngine {
         }
     }
 
+    /// Compiles every regex of `rules` for a simulation, keeping failures instead of bailing out.
+    fn compile_regexes(rules: &[NotificationRule]) -> RegexCache {
+        fn walk(condition: &RuleCondition, cache: &mut RegexCache) {
+            match condition {
+                RuleCondition::Simple(SimpleRuleCondition { value: RuleConditionValue::Regex(pattern), .. }) => {
+                    cache.entry(pattern.clone()).or_insert_with(|| Regex::new(pattern).map_err(|source| NotificationRulesError::InvalidRegex { pattern: pattern.clone(), source }));
+                }
+                RuleCondition::And(conditions) | RuleCondition::Or(conditions) => conditions.iter().for_each(|c| walk(c, cache)),
+                RuleCondition::Not(condition) => walk(condition, cache),
+                _ => {}
+            }
+        }
+        let mut cache = HashMap::new();
+        for rule in rules { walk(&rule.condition, &mut cache); }
+        cache
+    }
+
+    /// Evaluates `condition` like `evaluate_condition_recursive`, but visits every nested
+    /// condition so the trace is complete. The combined result still short-circuits in order, so
+    /// it matches what the engine decides.
+    fn trace_condition<'a>(&'a self, condition: &'a RuleCondition, notification: &'a Notification, env: &'a Evaluation<'a>, rule: &'a NotificationRule) -> BoxFuture<'a, ConditionTrace> {
+        async move {
+            let description = simulation::describe_condition(condition);
+            let children = match condition {
+                RuleCondition::And(conditions) | RuleCondition::Or(conditions) => {
+                    let mut children = Vec::with_capacity(conditions.len());
+                    for cond in conditions { children.push(self.trace_condition(cond, notification, env, rule).await); }
+                    children
+                }
+                RuleCondition::Not(cond) => vec![self.trace_condition(cond, notification, env, rule).await],
+                _ => {
+                    return match self.evaluate_condition_recursive(condition, notification, env, &rule.name, Some(rule.id)).await {
+                        Ok(result) => ConditionTrace { description, result: Some(result), error: None, children: Vec::new() },
+                        Err(e) => ConditionTrace { description, result: None, error: Some(e.to_string()), children: Vec::new() },
+                    };
+                }
+            };
+            let decisive = match condition {
+                RuleCondition::And(_) => children.iter().map(|c| c.result).find(|r| *r != Some(true)).unwrap_or(Some(true)),
+                RuleCondition::Or(_) => children.iter().map(|c| c.result).find(|r| *r != Some(false)).unwrap_or(Some(false)),
+                _ => children[0].result.map(|r| !r),
+            };
+            let error = if decisive.is_none() { Some("a nested condition could not be evaluated".to_string()) } else { None };
+            ConditionTrace { description, result: decisive, error, children }
+        }
+        .boxed()
+    }
+
+    /// The longest `RateAbove` window in `condition`, in minutes.
+    fn longest_rate_window(condition: &RuleCondition) -> Option<u32> {
+        match condition {
+            RuleCondition::RateAbove { minutes, .. } => Some(*minutes),
+            RuleCondition::And(conditions) | RuleCondition::Or(conditions) => conditions.iter().filter_map(Self::longest_rate_window).max(),
+            RuleCondition::Not(condition) => Self::longest_rate_window(condition),
+            _ => None,
+        }
+    }
+
+    /// Adds `notification` to the window of every enabled rule with a rate condition and drops
+    /// what has slid out of them, along with the windows of rules that no longer have one.
+    async fn record_rates(&self, rules: &[NotificationRule], notification: &Notification) {
+        let longest: HashMap<Uuid, u32> = rules.iter().filter(|r| r.is_enabled).filter_map(|r| Some((r.id, Self::longest_rate_window(&r.condition)?))).collect();
+        let mut windows = self.rate_windows.write().await;
+        for rule_id in longest.keys() {
+            windows.entry((*rule_id, notification.application_name.clone())).or_default().push_back(notification.timestamp);
+        }
+        windows.retain(|(rule_id, _), stamps| {
+            let Some(minutes) = longest.get(rule_id) else { return false };
+            let horizon = notification.timestamp - Duration::minutes(i64::from(*minutes));
+            while stamps.front().map_or(false, |t| *t <= horizon) { stamps.pop_front(); }
+            !stamps.is_empty()
+        });
+    }
+
+    /// The first moment after `after` at which the local clock reads `time`. A time skipped by a
+    /// daylight saving change is taken on the following day.
+    fn next_local_occurrence(after: DateTime<Utc>, time: NaiveTime) -> DateTime<Utc> {
+        let local = after.with_timezone(&Local);
+        let mut date = local.date_naive();
+        if local.time() >= time { date = date.succ_opt().unwrap_or(date); }
+        for _ in 0..2 {
+            if let Some(at) = Local.from_local_datetime(&date.and_time(time)).earliest() { return at.with_timezone(&Utc); }
+            date = date.succ_opt().unwrap_or(date);
+        }
+        after + Duration::days(1)
+    }
+
     async fn apply_actions_internal(&self, actions: &[RuleAction], notification: &mut Notification, rule: &NotificationRule) -> Result<bool, NotificationRulesError> {
         let mut stop_processing = false;
         for action in actions {
@@ -246,6 +390,18 @@ impl DefaultNotificationRulesEngine {
                     if let Some(c) = color_opt { notification.hints.insert("accent-color".to_string(), serde_json::Value::String(c.to_hex_string())); } 
                     else { notification.hints.remove("accent-color"); }
                 },
+                RuleAction::SetGroupKey(key) => {
+                    if key.trim().is_empty() { notification.hints.remove(GROUP_KEY_HINT); }
+                    else { notification.hints.insert(GROUP_KEY_HINT.to_string(), serde_json::Value::String(key.clone())); }
+                },
+                RuleAction::SnoozeUntil(time) => {
+                    let until = Self::next_local_occurrence(notification.timestamp, *time);
+                    notification.hints.insert(SNOOZE_UNTIL_HINT.to_string(), serde_json::Value::String(until.to_rfc3339()));
+                },
+                RuleAction::RouteTo(channel) => {
+                    if *channel == NotificationChannel::Normal { notification.hints.remove(CHANNEL_HINT); }
+                    else { notification.hints.insert(CHANNEL_HINT.to_string(), serde_json::Value::String(channel.as_str().to_string())); }
+                },
                 RuleAction::LogMessage(message) => { info!("Rule Action (Rule: '{}' ID: {:?}): {}", rule.name, rule.id, message); }
                 RuleAction::StopProcessingFurtherRules => { stop_processing = true; break; }
             }
@@ -263,11 +419,14 @@ impl NotificationRulesEngine for DefaultNotificationRulesEngine {
         let rules_snapshot = rules_guard.clone(); 
         drop(rules_guard); 
 
+        self.record_rates(&rules_snapshot, &notification).await;
         let mut current_notification = notification;
+        let regexes = self.regex_cache.read().await;
+        let env = Evaluation { regexes: &regexes, unrecorded: 0 };
 
         for rule in rules_snapshot.iter().filter(|r| r.is_enabled) {
             debug!("Processing rule: '{}' (ID: {:?}, Prio: {}) for notif ID {}", rule.name, rule.id, rule.priority, current_notification.id);
-            match self.evaluate_condition_recursive(&rule.condition, &current_notification, &rule.name, Some(rule.id)).await {
+            match self.evaluate_condition_recursive(&rule.condition, &current_notification, &env, &rule.name, Some(rule.id)).await {
                 Ok(true) => {
                     debug!("Rule condition MET for rule: '{}'", rule.name);
                     if rule.actions.contains(&RuleAction::SuppressNotification) {
@@ -288,6 +447,14 @@ impl NotificationRulesEngine for DefaultNotificationRulesEngine {
 
     async fn update_rules(&self, mut new_rules: NotificationRuleSet) -> Result<(), NotificationRulesError> {
         debug!("Updating notification rules. New rule count: {}", new_rules.len());
+        let issues = simulation::validate_rules(&new_rules);
+        if let Some(first) = issues.first() {
+            return Err(NotificationRulesError::InvalidRuleDefinition {
+                rule_id: Some(first.rule_id),
+                rule_name: first.rule_name.clone(),
+                reason: issues.iter().map(|issue| format!("'{}': {}", issue.rule_name, issue.reason)).collect::<Vec<_>>().join("; "),
+            });
+        }
         let mut temp_regex_cache = HashMap::new(); 
         for rule in &new_rules {
             self.validate_and_cache_regex_in_condition_recursive(&rule.condition, &mut temp_regex_cache).map_err(|e| {
@@ -307,6 +474,68 @@ impl NotificationRulesEngine for DefaultNotificationRulesEngine {
         info!("Notification rules updated and saved successfully.");
         Ok(())
     }
+
+    fn validate_rules(&self, rules: &NotificationRuleSet) -> Vec<RuleValidationIssue> { simulation::validate_rules(rules) }
+
+    async fn simulate(&self, notification: Notification, rules: Option<NotificationRuleSet>) -> Result<RuleSimulation, NotificationRulesError> {
+        let draft_regexes;
+        let active_regexes;
+        let (rules, regexes) = match rules {
+            Some(mut draft) => {
+                draft.sort_by(|a, b| b.priority.cmp(&a.priority).then_with(|| a.name.cmp(&b.name)));
+                draft_regexes = Self::compile_regexes(&draft);
+                (draft, &draft_regexes)
+            }
+            None => {
+                active_regexes = self.regex_cache.read().await;
+                (self.rules.read().await.clone(), &*active_regexes)
+            }
+        };
+
+        let env = Evaluation { regexes, unrecorded: 1 };
+        let mut current_notification = notification.clone();
+        let mut suppressed_by = None;
+        let mut stopped = false;
+        let mut traces = Vec::with_capacity(rules.len());
+        for rule in &rules {
+            let mut trace = RuleTrace { rule_id: rule.id, rule_name: rule.name.clone(), priority: rule.priority, status: RuleTraceStatus::NotReached, condition: None, applied_actions: Vec::new() };
+            if suppressed_by.is_some() || stopped {
+                traces.push(trace);
+                continue;
+            }
+            let condition = self.trace_condition(&rule.condition, &current_notification, &env, rule).await;
+            trace.status = match condition.result {
+                _ if !rule.is_enabled => RuleTraceStatus::Disabled,
+                Some(true) => RuleTraceStatus::Matched,
+                Some(false) => RuleTraceStatus::NotMatched,
+                None => RuleTraceStatus::Error,
+            };
+            trace.condition = Some(condition);
+            if trace.status == RuleTraceStatus::Matched {
+                if rule.actions.contains(&RuleAction::SuppressNotification) {
+                    trace.applied_actions.push(RuleAction::SuppressNotification);
+                    suppressed_by = Some((rule.id, rule.name.clone()));
+                } else {
+                    // Log actions only matter when the rule really runs.
+                    let actions: Vec<RuleAction> = rule.actions.iter().filter(|a| !matches!(a, RuleAction::LogMessage(_))).cloned().collect();
+                    stopped = self.apply_actions_internal(&actions, &mut current_notification, rule).await?;
+                    let applied = rule.actions.iter().position(|a| *a == RuleAction::StopProcessingFurtherRules).map_or(rule.actions.len(), |i| i + 1);
+                    trace.applied_actions = rule.actions[..applied].to_vec();
+                }
+            }
+            traces.push(trace);
+        }
+
+        let outcome = match suppressed_by {
+            Some((rule_id, rule_name)) => SimulationOutcome::Suppress { rule_id, rule_name },
+            None => SimulationOutcome::Allow { notification: Box::new(current_notification) },
+        };
+        Ok(RuleSimulation { input: notification, rules: traces, outcome })
+    }
+
+    async fn set_active_focus_mode(&self, mode_id: Option<String>) { *self.active_focus_mode.write().await = mode_id; }
+
+    async fn update_context(&self, change: RuleContextChange) { self.context.write().await.apply(change); }
 }
 
 // Helper for NotificationRulesError for caching, made more robust
@@ -423,4 +652,126 @@ mod tests {
         let result = engine.process_notification(notif).await.unwrap();
         match result { RuleProcessingResult::Allow(modified_notif) => { assert_eq!(modified_notif.urgency, NotificationUrgency::Critical); assert_eq!(modified_notif.summary, original_notif_clone.summary); } _ => panic!("Expected Allow"), }
     }
+
+    #[tokio::test]
+    async fn test_process_notification_set_group_key() {
+        let mock_rules_provider = Arc::new(MockNotificationRulesProvider::new());
+        let mock_settings_service = Arc::new(TestMockGlobalSettingsService::new());
+        let rules = vec![NotificationRule {name: "WorkChat".into(), is_enabled: true, condition: RuleCondition::Simple(SimpleRuleCondition { field: RuleConditionField::ApplicationName, operator: RuleConditionOperator::Is, value: RuleConditionValue::String("Chat".into())}), actions: vec![RuleAction::SetGroupKey("work".into())], ..Default::default()}];
+        mock_rules_provider.expect_load_rules().times(1).returning(move || Ok(rules.clone()));
+        let engine = DefaultNotificationRulesEngine::new(mock_rules_provider, mock_settings_service).await.unwrap();
+        let notif = Notification::new("Chat".into(), "Alice".into(), NotificationUrgency::Normal);
+        match engine.process_notification(notif).await.unwrap() {
+            RuleProcessingResult::Allow(grouped) => assert_eq!(crate::user_centric_services::notifications_core::grouping::group_key(&grouped), "work"),
+            _ => panic!("Expected Allow"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_process_notification_focus_mode_active_condition() {
+        let mock_rules_provider = Arc::new(MockNotificationRulesProvider::new());
+        let mock_settings_service = Arc::new(TestMockGlobalSettingsService::new());
+        let rule_id = Uuid::new_v4();
+        let rules = vec![NotificationRule {id: rule_id, name: "QuietMeetings".into(), is_enabled: true, condition: RuleCondition::FocusModeActive(Some("meeting".into())), actions: vec![RuleAction::SuppressNotification], ..Default::default()}];
+        mock_rules_provider.expect_load_rules().times(1).returning(move || Ok(rules.clone()));
+        let engine = DefaultNotificationRulesEngine::new(mock_rules_provider, mock_settings_service).await.unwrap();
+        let notif = Notification::new("Chat".into(), "Alice".into(), NotificationUrgency::Normal);
+        assert!(matches!(engine.process_notification(notif.clone()).await.unwrap(), RuleProcessingResult::Allow(_)));
+        engine.set_active_focus_mode(Some("sleep".into())).await;
+        assert!(matches!(engine.process_notification(notif.clone()).await.unwrap(), RuleProcessingResult::Allow(_)));
+        engine.set_active_focus_mode(Some("meeting".into())).await;
+        assert_eq!(engine.process_notification(notif).await.unwrap(), RuleProcessingResult::Suppress { rule_id });
+    }
+
+    #[tokio::test]
+    async fn test_simulate_draft_rules_traces_every_rule() {
+        let mock_rules_provider = Arc::new(MockNotificationRulesProvider::new());
+        let mock_settings_service = Arc::new(TestMockGlobalSettingsService::new());
+        mock_rules_provider.expect_load_rules().times(1).returning(|| Ok(Vec::new()));
+        let engine = DefaultNotificationRulesEngine::new(mock_rules_provider, mock_settings_service).await.unwrap();
+
+        let is_chat = RuleCondition::Simple(SimpleRuleCondition { field: RuleConditionField::ApplicationName, operator: RuleConditionOperator::Is, value: RuleConditionValue::String("Chat".into()) });
+        let broken = RuleCondition::Simple(SimpleRuleCondition { field: RuleConditionField::Body, operator: RuleConditionOperator::MatchesRegex, value: RuleConditionValue::Regex("(unclosed".into()) });
+        let raise = NotificationRule { name: "Raise".into(), priority: 10, is_enabled: true, condition: RuleCondition::Or(vec![broken.clone(), is_chat.clone()]), actions: vec![RuleAction::SetUrgency(NotificationUrgency::Critical), RuleAction::StopProcessingFurtherRules], ..Default::default() };
+        let mute = NotificationRule { name: "Mute".into(), priority: 5, is_enabled: true, condition: is_chat, actions: vec![RuleAction::SuppressNotification], ..Default::default() };
+        let draft = vec![mute.clone(), raise.clone()];
+        assert_eq!(engine.validate_rules(&draft).len(), 1);
+        assert!(engine.update_rules(draft.clone()).await.is_err());
+
+        let notif = Notification::new("Chat".into(), "Alice".into(), NotificationUrgency::Normal);
+        let simulation = engine.simulate(notif.clone(), Some(draft)).await.unwrap();
+        assert_eq!(simulation.input, notif);
+        assert_eq!(simulation.rules.len(), 2);
+        let raised = &simulation.rules[0];
+        assert_eq!((raised.rule_id, raised.status), (raise.id, RuleTraceStatus::Error));
+        let condition = raised.condition.as_ref().unwrap();
+        assert_eq!(condition.children[0].result, None);
+        assert_eq!(condition.children[1].result, Some(true));
+        assert_eq!((simulation.rules[1].rule_id, simulation.rules[1].status), (mute.id, RuleTraceStatus::Matched));
+        assert_eq!(simulation.outcome, SimulationOutcome::Suppress { rule_id: mute.id, rule_name: "Mute".into() });
+
+        // Fixed, the first rule stops processing before the second is reached.
+        let greeting = RuleCondition::Simple(SimpleRuleCondition { field: RuleConditionField::Summary, operator: RuleConditionOperator::MatchesRegex, value: RuleConditionValue::Regex("^Al".into()) });
+        let fixed = NotificationRule { condition: greeting, ..raise };
+        let simulation = engine.simulate(notif, Some(vec![mute.clone(), fixed])).await.unwrap();
+        assert_eq!(simulation.matched_rules().count(), 1);
+        assert_eq!(simulation.rules[1].status, RuleTraceStatus::NotReached);
+        match simulation.outcome {
+            SimulationOutcome::Allow { notification } => assert_eq!(notification.urgency, NotificationUrgency::Critical),
+            other => panic!("Expected Allow, got {:?}", other),
+        }
+        assert!(engine.get_rules().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_rate_above_counts_per_app_within_the_window() {
+        let mock_rules_provider = Arc::new(MockNotificationRulesProvider::new());
+        let mock_settings_service = Arc::new(TestMockGlobalSettingsService::new());
+        let rule_id = Uuid::new_v4();
+        let rules = vec![NotificationRule {id: rule_id, name: "Bursts".into(), is_enabled: true, condition: RuleCondition::RateAbove { count: 2, minutes: 5 }, actions: vec![RuleAction::RouteTo(NotificationChannel::HistoryOnly)], ..Default::default()}];
+        mock_rules_provider.expect_load_rules().times(1).returning(move || Ok(rules.clone()));
+        let engine = DefaultNotificationRulesEngine::new(mock_rules_provider, mock_settings_service).await.unwrap();
+
+        let start = Utc::now();
+        let at = |app: &str, minutes: i64| {
+            let mut notif = Notification::new(app.into(), "Ping".into(), NotificationUrgency::Normal);
+            notif.timestamp = start + Duration::minutes(minutes);
+            notif
+        };
+        let channel = |result: RuleProcessingResult| match result { RuleProcessingResult::Allow(n) => n.channel(), _ => panic!("Expected Allow") };
+        assert_eq!(channel(engine.process_notification(at("Chat", 0)).await.unwrap()), NotificationChannel::Normal);
+        assert_eq!(channel(engine.process_notification(at("Mail", 1)).await.unwrap()), NotificationChannel::Normal);
+        assert_eq!(channel(engine.process_notification(at("Chat", 1)).await.unwrap()), NotificationChannel::Normal);
+        assert_eq!(channel(engine.process_notification(at("Chat", 2)).await.unwrap()), NotificationChannel::HistoryOnly);
+        // The first one has slid out of the window by then.
+        assert_eq!(channel(engine.process_notification(at("Chat", 5)).await.unwrap()), NotificationChannel::HistoryOnly);
+        assert_eq!(channel(engine.process_notification(at("Chat", 9)).await.unwrap()), NotificationChannel::Normal);
+
+        // A simulation counts its sample without recording it.
+        for _ in 0..2 {
+            let simulation = engine.simulate(at("Chat", 6), None).await.unwrap();
+            assert_eq!(simulation.rules[0].status, RuleTraceStatus::Matched);
+        }
+        let simulation = engine.simulate(at("Chat", 10), None).await.unwrap();
+        assert_eq!(simulation.rules[0].status, RuleTraceStatus::NotMatched);
+    }
+
+    #[tokio::test]
+    async fn test_context_conditions_and_snooze() {
+        let mock_rules_provider = Arc::new(MockNotificationRulesProvider::new());
+        let mock_settings_service = Arc::new(TestMockGlobalSettingsService::new());
+        let wake = NaiveTime::from_hms_opt(8, 0, 0).unwrap();
+        let rules = vec![NotificationRule {name: "Presenting".into(), is_enabled: true, condition: RuleCondition::And(vec![RuleCondition::Fullscreen, RuleCondition::ActiveWorkspace("Slides".into())]), actions: vec![RuleAction::SnoozeUntil(wake)], ..Default::default()}];
+        mock_rules_provider.expect_load_rules().times(1).returning(move || Ok(rules.clone()));
+        let engine = DefaultNotificationRulesEngine::new(mock_rules_provider, mock_settings_service).await.unwrap();
+        let notif = Notification::new("Chat".into(), "Alice".into(), NotificationUrgency::Normal);
+        let snoozed_until = |result: RuleProcessingResult| match result { RuleProcessingResult::Allow(n) => n.snoozed_until(), _ => panic!("Expected Allow") };
+
+        engine.update_context(RuleContextChange::Fullscreen(true)).await;
+        assert_eq!(snoozed_until(engine.process_notification(notif.clone()).await.unwrap()), None);
+        engine.update_context(RuleContextChange::ActiveWorkspace(Some("Slides".into()))).await;
+        let until = snoozed_until(engine.process_notification(notif.clone()).await.unwrap()).unwrap();
+        assert!(until > notif.timestamp && until <= notif.timestamp + Duration::days(1) + Duration::hours(1));
+        assert_eq!(until.with_timezone(&Local).time(), wake);
+    }
 }
@@ -5,6 +5,8 @@ pub mod errors;
 pub mod persistence_iface; // Placeholder for persistence trait
 pub mod persistence;       // Placeholder for persistence implementation
 pub mod engine;            // Placeholder for the NotificationRulesEngine trait and its impl
+pub mod simulation;
+pub mod context;
 
 // Re-exports for easier access by consumers of the crate.
 // These will be populated as the types and service trait are defined.
@@ -16,3 +18,5 @@ pub use errors::NotificationRulesError;
 pub use persistence_iface::NotificationRulesProvider;
 pub use persistence::FilesystemNotificationRulesProvider;
 pub use engine::{NotificationRulesEngine, DefaultNotificationRulesEngine, RuleProcessingResult}; // Updated
+pub use context::{RuleContext, RuleContextChange};
+pub use simulation::{validate_rules, ConditionTrace, RuleSimulation, RuleTrace, RuleTraceStatus, RuleValidationIssue, SimulationOutcome};
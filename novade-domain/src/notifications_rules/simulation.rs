@@ -0,0 +1,289 @@
+//! Dry runs of notification rules.
+//!
+//! [`NotificationRulesEngine::simulate`](super::NotificationRulesEngine::simulate) runs a rule
+//! set, the active one or a draft being edited, against a sample notification without posting
+//! anything. The resulting [`RuleSimulation`] records for every rule whether it was reached and
+//! matched, how each part of its condition evaluated, and what finally happens to the
+//! notification.
+//!
+//! [`validate_rules`] checks a rule set up front, so that invalid regexes and operators that do
+//! not fit their values are reported all at once rather than when a notification hits them.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use uuid::Uuid;
+
+use crate::user_centric_services::notifications_core::types::Notification;
+
+use super::types::{NotificationRule, RuleAction, RuleCondition, RuleConditionField, RuleConditionOperator, RuleConditionValue, SimpleRuleCondition};
+
+/// How one condition, and the conditions nested in it, evaluated.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ConditionTrace {
+    /// The condition in words, e.g. `summary contains "urgent"`.
+    pub description: String,
+    /// `None` if the condition could not be evaluated, see `error`.
+    pub result: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Every nested condition is evaluated for the trace, even where the engine would stop
+    /// early.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<ConditionTrace>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RuleTraceStatus {
+    /// The condition held and the rule's actions were applied.
+    Matched,
+    NotMatched,
+    /// Disabled rules are evaluated for the trace but never applied.
+    Disabled,
+    /// The condition failed to evaluate; the engine skips such rules.
+    Error,
+    /// An earlier rule suppressed the notification or stopped processing.
+    NotReached,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct RuleTrace {
+    pub rule_id: Uuid,
+    pub rule_name: String,
+    pub priority: i32,
+    pub status: RuleTraceStatus,
+    /// `None` for rules that were not reached.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub condition: Option<ConditionTrace>,
+    /// The actions applied to the notification, in order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub applied_actions: Vec<RuleAction>,
+}
+
+/// What the rules do with the notification in the end.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SimulationOutcome {
+    /// Posted as `notification`, with every action applied.
+    Allow { notification: Box<Notification> },
+    Suppress { rule_id: Uuid, rule_name: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct RuleSimulation {
+    /// The sample as it entered the rules.
+    pub input: Notification,
+    /// One trace per rule, in the order the engine processes them.
+    pub rules: Vec<RuleTrace>,
+    pub outcome: SimulationOutcome,
+}
+
+impl RuleSimulation {
+    pub fn matched_rules(&self) -> impl Iterator<Item = &RuleTrace> {
+        self.rules.iter().filter(|trace| trace.status == RuleTraceStatus::Matched)
+    }
+}
+
+/// A problem [`validate_rules`] found in one rule.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct RuleValidationIssue {
+    pub rule_id: Uuid,
+    pub rule_name: String,
+    pub reason: String,
+}
+
+/// Checks every rule of `rules` and returns all problems found; empty if the set is valid.
+pub fn validate_rules(rules: &[NotificationRule]) -> Vec<RuleValidationIssue> {
+    let mut issues = Vec::new();
+    let mut ids = HashSet::new();
+    for rule in rules {
+        let mut report = |reason: String| issues.push(RuleValidationIssue { rule_id: rule.id, rule_name: rule.name.clone(), reason });
+        if !ids.insert(rule.id) {
+            report(format!("duplicate rule id {}", rule.id));
+        }
+        validate_condition(&rule.condition, &mut report);
+    }
+    issues
+}
+
+fn validate_condition(condition: &RuleCondition, report: &mut impl FnMut(String)) {
+    match condition {
+        RuleCondition::Simple(simple) => {
+            if let Err(reason) = validate_simple_condition(simple) {
+                report(format!("{}: {}", describe_condition(condition), reason));
+            }
+        }
+        RuleCondition::And(conditions) | RuleCondition::Or(conditions) => {
+            for condition in conditions {
+                validate_condition(condition, report);
+            }
+        }
+        RuleCondition::Not(condition) => validate_condition(condition, report),
+        RuleCondition::BatteryAtMost(percent) if *percent > 100 => report(format!("{}: not a percentage", describe_condition(condition))),
+        RuleCondition::RateAbove { minutes: 0, .. } => report(format!("{}: the window must be at least a minute", describe_condition(condition))),
+        RuleCondition::SettingIsTrue(_)
+        | RuleCondition::FocusModeActive(_)
+        | RuleCondition::TimeWindow(_)
+        | RuleCondition::FocusedApp(_)
+        | RuleCondition::Fullscreen
+        | RuleCondition::ActiveWorkspace(_)
+        | RuleCondition::BatteryAtMost(_)
+        | RuleCondition::RateAbove { .. } => {}
+    }
+}
+
+fn validate_simple_condition(simple: &SimpleRuleCondition) -> Result<(), String> {
+    use RuleConditionOperator::*;
+    // The engine checks hint existence regardless of operator and value.
+    if let RuleConditionField::HintExists(_) = simple.field {
+        return Ok(());
+    }
+    let fits = match &simple.value {
+        RuleConditionValue::String(_) => matches!(simple.operator, Is | IsNot | Contains | NotContains | StartsWith | EndsWith),
+        RuleConditionValue::Regex(pattern) => {
+            if let Err(e) = Regex::new(pattern) {
+                return Err(format!("invalid regex /{}/: {}", pattern, e));
+            }
+            matches!(simple.operator, MatchesRegex | NotMatchesRegex)
+        }
+        RuleConditionValue::Urgency(_) => {
+            if simple.field != RuleConditionField::Urgency {
+                return Err("only the urgency field compares with an urgency".to_string());
+            }
+            matches!(simple.operator, Is | IsNot)
+        }
+        RuleConditionValue::Integer(_) | RuleConditionValue::Boolean(_) => {
+            if !matches!(simple.field, RuleConditionField::HintValue(_)) {
+                return Err("only hint values compare with numbers and booleans".to_string());
+            }
+            match simple.value {
+                RuleConditionValue::Integer(_) => matches!(simple.operator, Is | IsNot | GreaterThan | LessThan | GreaterThanOrEqual | LessThanOrEqual),
+                _ => matches!(simple.operator, Is | IsNot),
+            }
+        }
+    };
+    if fits {
+        Ok(())
+    } else {
+        Err(format!("operator '{}' does not apply to this value", describe_operator(simple.operator)))
+    }
+}
+
+/// `condition` in words, as shown in traces and validation issues.
+pub fn describe_condition(condition: &RuleCondition) -> String {
+    match condition {
+        RuleCondition::Simple(simple) => match &simple.field {
+            RuleConditionField::HintExists(key) => format!("hint \"{}\" exists", key),
+            field => format!("{} {} {}", describe_field(field), describe_operator(simple.operator), describe_value(&simple.value)),
+        },
+        RuleCondition::SettingIsTrue(path) => format!("setting {} is true", path),
+        RuleCondition::FocusModeActive(Some(mode_id)) => format!("focus mode \"{}\" is active", mode_id),
+        RuleCondition::FocusModeActive(None) => "a focus mode is active".to_string(),
+        RuleCondition::TimeWindow(schedule) => {
+            let days = if schedule.days.is_empty() {
+                "every day".to_string()
+            } else {
+                format!("on {}", schedule.days.iter().map(|day| day.to_string()).collect::<Vec<_>>().join(", "))
+            };
+            format!("between {} and {} {}", schedule.start.format("%H:%M"), schedule.end.format("%H:%M"), days)
+        }
+        RuleCondition::FocusedApp(app_id) => format!("focused app is \"{}\"", app_id),
+        RuleCondition::Fullscreen => "a window is fullscreen".to_string(),
+        RuleCondition::ActiveWorkspace(name) => format!("workspace \"{}\" is active", name),
+        RuleCondition::BatteryAtMost(percent) => format!("on battery at or below {}%", percent),
+        RuleCondition::RateAbove { count, minutes } => format!("more than {} from this app in {} minutes", count, minutes),
+        RuleCondition::And(conditions) if conditions.is_empty() => "always".to_string(),
+        RuleCondition::And(_) => "all of".to_string(),
+        RuleCondition::Or(_) => "any of".to_string(),
+        RuleCondition::Not(_) => "not".to_string(),
+    }
+}
+
+fn describe_field(field: &RuleConditionField) -> String {
+    match field {
+        RuleConditionField::ApplicationName => "application name".to_string(),
+        RuleConditionField::Summary => "summary".to_string(),
+        RuleConditionField::Body => "body".to_string(),
+        RuleConditionField::Urgency => "urgency".to_string(),
+        RuleConditionField::Category => "category".to_string(),
+        RuleConditionField::HintExists(key) | RuleConditionField::HintValue(key) => format!("hint \"{}\"", key),
+    }
+}
+
+fn describe_operator(operator: RuleConditionOperator) -> &'static str {
+    match operator {
+        RuleConditionOperator::Is => "is",
+        RuleConditionOperator::IsNot => "is not",
+        RuleConditionOperator::Contains => "contains",
+        RuleConditionOperator::NotContains => "does not contain",
+        RuleConditionOperator::StartsWith => "starts with",
+        RuleConditionOperator::EndsWith => "ends with",
+        RuleConditionOperator::MatchesRegex => "matches",
+        RuleConditionOperator::NotMatchesRegex => "does not match",
+        RuleConditionOperator::GreaterThan => ">",
+        RuleConditionOperator::LessThan => "<",
+        RuleConditionOperator::GreaterThanOrEqual => ">=",
+        RuleConditionOperator::LessThanOrEqual => "<=",
+    }
+}
+
+fn describe_value(value: &RuleConditionValue) -> String {
+    match value {
+        RuleConditionValue::String(s) => format!("\"{}\"", s),
+        RuleConditionValue::Integer(n) => n.to_string(),
+        RuleConditionValue::Boolean(b) => b.to_string(),
+        RuleConditionValue::Urgency(urgency) => serde_json::to_string(urgency).unwrap_or_default().trim_matches('"').to_string(),
+        RuleConditionValue::Regex(pattern) => format!("/{}/", pattern),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::user_centric_services::notifications_core::types::NotificationUrgency;
+
+    fn simple(field: RuleConditionField, operator: RuleConditionOperator, value: RuleConditionValue) -> RuleCondition {
+        RuleCondition::Simple(SimpleRuleCondition { field, operator, value })
+    }
+
+    #[test]
+    fn test_describe_condition() {
+        let condition = simple(RuleConditionField::Summary, RuleConditionOperator::Contains, RuleConditionValue::String("urgent".to_string()));
+        assert_eq!(describe_condition(&condition), "summary contains \"urgent\"");
+        let condition = simple(RuleConditionField::Urgency, RuleConditionOperator::IsNot, RuleConditionValue::Urgency(NotificationUrgency::Low));
+        assert_eq!(describe_condition(&condition), "urgency is not low");
+        let condition = simple(RuleConditionField::HintExists("sound-file".to_string()), RuleConditionOperator::Is, RuleConditionValue::Boolean(true));
+        assert_eq!(describe_condition(&condition), "hint \"sound-file\" exists");
+        assert_eq!(describe_condition(&RuleCondition::FocusModeActive(None)), "a focus mode is active");
+        assert_eq!(describe_condition(&RuleCondition::RateAbove { count: 5, minutes: 10 }), "more than 5 from this app in 10 minutes");
+    }
+
+    #[test]
+    fn test_validate_rules_reports_every_issue() {
+        let bad_regex = simple(RuleConditionField::Body, RuleConditionOperator::MatchesRegex, RuleConditionValue::Regex("(unclosed".to_string()));
+        let wrong_operator = simple(RuleConditionField::Summary, RuleConditionOperator::GreaterThan, RuleConditionValue::String("x".to_string()));
+        let valid = simple(RuleConditionField::Summary, RuleConditionOperator::MatchesRegex, RuleConditionValue::Regex("^re:".to_string()));
+        let first = NotificationRule {
+            name: "Broken".to_string(),
+            condition: RuleCondition::Or(vec![bad_regex, RuleCondition::Not(Box::new(wrong_operator)), valid.clone()]),
+            ..Default::default()
+        };
+        let duplicate = NotificationRule { id: first.id, name: "Copy".to_string(), condition: valid, ..Default::default() };
+
+        let issues = validate_rules(&[first.clone(), duplicate]);
+        let reasons: Vec<&str> = issues.iter().map(|issue| issue.reason.as_str()).collect();
+        assert_eq!(issues.len(), 3, "{:?}", reasons);
+        assert!(reasons[0].starts_with("body matches /(unclosed/: invalid regex"));
+        assert_eq!(reasons[1], "summary > \"x\": operator '>' does not apply to this value");
+        assert!(reasons[2].starts_with("duplicate rule id"));
+        assert_eq!(issues[2].rule_name, "Copy");
+        assert!(validate_rules(&[NotificationRule { name: "Fine".to_string(), ..Default::default() }]).is_empty());
+        let no_window = NotificationRule { name: "Bursts".to_string(), condition: RuleCondition::RateAbove { count: 3, minutes: 0 }, ..Default::default() };
+        assert_eq!(validate_rules(&[no_window])[0].reason, "more than 3 from this app in 0 minutes: the window must be at least a minute");
+    }
+}
@@ -1,6 +1,8 @@
+use chrono::NaiveTime;
 use serde::{Serialize, Deserialize};
 use uuid::Uuid;
-use crate::user_centric_services::notifications_core::types::{NotificationUrgency, NotificationAction as CoreNotificationAction};
+use crate::user_centric_services::notifications_core::types::{NotificationUrgency, NotificationAction as CoreNotificationAction, NotificationChannel};
+use crate::user_centric_services::notifications_core::focus::FocusSchedule;
 use crate::global_settings::paths::SettingPath;
 use novade_core::types::Color as CoreColor;
 
@@ -60,6 +62,21 @@ pub struct SimpleRuleCondition {
 pub enum RuleCondition {
     Simple(SimpleRuleCondition),
     SettingIsTrue(SettingPath),
+    /// True while the given focus mode is active, or any focus mode for `None`.
+    FocusModeActive(Option<String>),
+    /// True if the notification arrives within the window, in local time.
+    TimeWindow(FocusSchedule),
+    /// True while the focused window belongs to this app id.
+    FocusedApp(String),
+    /// True while any window is fullscreen.
+    Fullscreen,
+    /// True while the workspace of this name is shown on the focused output.
+    ActiveWorkspace(String),
+    /// True while running on battery with at most this charge in percent.
+    BatteryAtMost(u8),
+    /// True if more than `count` notifications from the notification's app arrived within the
+    /// last `minutes`, this one included. Each rule keeps its own sliding window.
+    RateAbove { count: u32, minutes: u32 },
     And(Vec<RuleCondition>),
     Or(Vec<RuleCondition>),
     Not(Box<RuleCondition>),
@@ -81,6 +98,12 @@ pub enum RuleAction {
     SetBody(String),
     SetIcon(String),
     SetAccentColor(Option<CoreColor>),
+    /// Sets the key the notification is grouped under, overriding the thread and category
+    /// heuristics. An empty key restores them.
+    SetGroupKey(String),
+    /// Holds the notification back until the next occurrence of this local time.
+    SnoozeUntil(NaiveTime),
+    RouteTo(NotificationChannel),
     StopProcessingFurtherRules,
     LogMessage(String),
 }
@@ -200,6 +223,23 @@ mod tests {
         assert_eq!(cond, de);
     }
 
+    #[test]
+    fn rule_condition_focus_mode_active_serde() {
+        let cond = RuleCondition::FocusModeActive(Some("meeting".to_string()));
+        assert_eq!(serde_json::to_string(&cond).unwrap(), r#"{"focus-mode-active":"meeting"}"#);
+        let any: RuleCondition = serde_json::from_str(r#"{"focus-mode-active":null}"#).unwrap();
+        assert_eq!(any, RuleCondition::FocusModeActive(None));
+    }
+
+    #[test]
+    fn rule_condition_context_and_rate_serde() {
+        let rate = RuleCondition::RateAbove { count: 5, minutes: 10 };
+        assert_eq!(serde_json::to_string(&rate).unwrap(), r#"{"rate-above":{"count":5,"minutes":10}}"#);
+        assert_eq!(serde_json::from_str::<RuleCondition>(r#""fullscreen""#).unwrap(), RuleCondition::Fullscreen);
+        let battery: RuleCondition = serde_json::from_str(r#"{"battery-at-most":20}"#).unwrap();
+        assert_eq!(battery, RuleCondition::BatteryAtMost(20));
+    }
+
     #[test]
     fn rule_action_serde() {
         let action_suppress = RuleAction::SuppressNotification;
@@ -212,6 +252,18 @@ mod tests {
         assert_eq!(ser_hint, r#"{"set-hint":["color","#FF0000"]}"#);
         assert_eq!(serde_json::from_str::<RuleAction>(&ser_hint).unwrap(), action_hint);
         
+        let action_route = RuleAction::RouteTo(NotificationChannel::HistoryOnly);
+        let ser_route = serde_json::to_string(&action_route).unwrap();
+        assert_eq!(ser_route, r#"{"route-to":"history-only"}"#);
+        assert_eq!(serde_json::from_str::<RuleAction>(&ser_route).unwrap(), action_route);
+        let snooze: RuleAction = serde_json::from_str(r#"{"snooze-until":"08:30:00"}"#).unwrap();
+        assert_eq!(snooze, RuleAction::SnoozeUntil(NaiveTime::from_hms_opt(8, 30, 0).unwrap()));
+
+        let action_group = RuleAction::SetGroupKey("work-chat".to_string());
+        let ser_group = serde_json::to_string(&action_group).unwrap();
+        assert_eq!(ser_group, r#"{"set-group-key":"work-chat"}"#);
+        assert_eq!(serde_json::from_str::<RuleAction>(&ser_group).unwrap(), action_group);
+
         let action_color = RuleAction::SetAccentColor(Some(CoreColor::from_hex("#123456").unwrap()));
         let ser_color = serde_json::to_string(&action_color).unwrap();
         // CoreColor's Serialize impl will determine this exact string.
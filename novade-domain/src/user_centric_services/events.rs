@@ -1,9 +1,12 @@
 use serde::{Serialize, Deserialize};
 use uuid::Uuid;
+use chrono::{DateTime, Utc};
 
 // These will cause errors until their respective types.rs files are created.
 use super::ai_interaction::types::{AIInteractionContext, AIDataCategory, AIConsentStatus, AIConsentScope};
-use super::notifications_core::types::{Notification, DismissReason}; // Path to be created
+use super::notifications_core::types::{Notification, DismissReason, NotificationChannel}; // Path to be created
+use super::notifications_core::grouping::NotificationDigest;
+use super::notifications_core::focus::{ActiveFocus, MissedSummary};
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum AIInteractionEventEnum {
@@ -41,7 +44,15 @@ pub enum NotificationEventEnum {
     },
     NotificationActionInvoked { 
         notification_id: Uuid, 
-        action_key: String 
+        action_key: String,
+        /// XDG activation token of the click, for the application to raise its window.
+        #[serde(default)]
+        activation_token: Option<String>,
+    },
+    /// The user answered a notification's inline reply action.
+    NotificationReplied {
+        notification_id: Uuid,
+        text: String,
     },
     DoNotDisturbModeChanged { 
         dnd_enabled: bool 
@@ -53,6 +64,30 @@ pub enum NotificationEventEnum {
     NotificationUpdated { 
         notification: Notification // From notifications_core::types
     },
+    /// Posted during a burst in its group: shown in the notification center, but presented by
+    /// updating the group's digest popup instead of a popup of its own.
+    NotificationDigested {
+        notification: Notification,
+        digest: NotificationDigest,
+    },
+    /// The active focus mode changed; `None` once focus is off. `DoNotDisturbModeChanged`
+    /// follows when focus as a whole turned on or off.
+    FocusModeChanged {
+        active: Option<ActiveFocus>,
+    },
+    FocusModeEnded {
+        summary: MissedSummary,
+    },
+    /// A rule snoozed the notification; it is posted at `until`.
+    NotificationSnoozed {
+        notification: Notification,
+        until: DateTime<Utc>,
+    },
+    /// A rule routed the notification past popups and the notification center.
+    NotificationRouted {
+        notification: Notification,
+        channel: NotificationChannel,
+    },
     NotificationSuppressedByRule { // Added this variant
         original_notification_id: Uuid,
         original_summary: String,
@@ -0,0 +1,441 @@
+//! Focus modes: named, scheduled or triggered variants of Do Not Disturb.
+//!
+//! While a [`FocusMode`] is active, notifications it does not allow neither pop up nor make a
+//! sound. Depending on the mode they wait in the notification center or go straight to history,
+//! and when the mode ends a [`MissedSummary`] tells the user what came in meanwhile.
+//!
+//! A mode becomes active in one of three ways, by priority:
+//! 1. manually, from quick settings or [`FocusController::activate`];
+//! 2. through a [`FocusTrigger`] such as a fullscreen window or a screencast;
+//! 3. through one of its [`FocusSchedule`]s.
+//!
+//! A manual choice, including turning focus off, holds until the automatic candidate changes,
+//! e.g. a schedule starts or ends or a trigger goes away. The built-in mode
+//! [`DO_NOT_DISTURB_MODE_ID`] backs the plain Do Not Disturb toggle.
+
+use chrono::{DateTime, Datelike, NaiveDateTime, NaiveTime, TimeZone, Utc, Weekday};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+
+use super::types::{Notification, NotificationUrgency};
+
+/// The mode behind `NotificationService::set_do_not_disturb`; always present.
+pub const DO_NOT_DISTURB_MODE_ID: &str = "dnd";
+
+/// Desktop states that can switch a focus mode on by themselves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FocusTrigger {
+    /// A window is fullscreen, e.g. a game, a video or a presentation.
+    Fullscreen,
+    /// The screen is being recorded or shared.
+    Screencast,
+}
+
+/// A time-of-day window on some weekdays, in local time.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct FocusSchedule {
+    /// Days the window starts on; empty means every day.
+    #[serde(default)]
+    pub days: Vec<Weekday>,
+    pub start: NaiveTime,
+    /// Exclusive. An end before `start` runs past midnight into the next day; an end equal to
+    /// `start` covers the whole day.
+    pub end: NaiveTime,
+}
+
+impl FocusSchedule {
+    pub fn is_active_at(&self, at: NaiveDateTime) -> bool {
+        let time = at.time();
+        let starts_on = |day: Weekday| self.days.is_empty() || self.days.contains(&day);
+        let today = at.weekday();
+        if self.start < self.end {
+            starts_on(today) && self.start <= time && time < self.end
+        } else if self.start > self.end {
+            (starts_on(today) && time >= self.start) || (starts_on(today.pred()) && time < self.end)
+        } else {
+            starts_on(today)
+        }
+    }
+}
+
+fn default_allowed_urgencies() -> Vec<NotificationUrgency> {
+    vec![NotificationUrgency::Critical]
+}
+
+/// A named focus mode and what it lets through.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct FocusMode {
+    pub id: String,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+    #[serde(default)]
+    pub schedules: Vec<FocusSchedule>,
+    #[serde(default)]
+    pub triggers: Vec<FocusTrigger>,
+    /// Applications whose notifications still pop up, by application name.
+    #[serde(default)]
+    pub allowed_apps: Vec<String>,
+    /// Urgencies that still pop up; critical notifications unless configured otherwise.
+    #[serde(default = "default_allowed_urgencies")]
+    pub allowed_urgencies: Vec<NotificationUrgency>,
+    /// Whether held-back notifications go straight to history instead of waiting in the
+    /// notification center.
+    #[serde(default)]
+    pub suppressed_to_history: bool,
+}
+
+impl FocusMode {
+    pub fn new(id: impl Into<String>, name: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            name: name.into(),
+            icon: None,
+            schedules: Vec::new(),
+            triggers: Vec::new(),
+            allowed_apps: Vec::new(),
+            allowed_urgencies: default_allowed_urgencies(),
+            suppressed_to_history: false,
+        }
+    }
+
+    /// The built-in mode behind the Do Not Disturb toggle, which keeps its historical behavior.
+    pub fn do_not_disturb() -> Self {
+        Self {
+            icon: Some("notifications-disabled-symbolic".to_string()),
+            suppressed_to_history: true,
+            ..Self::new(DO_NOT_DISTURB_MODE_ID, "Do Not Disturb")
+        }
+    }
+
+    /// Whether `notification` may pop up while this mode is active.
+    pub fn allows(&self, notification: &Notification) -> bool {
+        self.allowed_urgencies.contains(&notification.urgency)
+            || self.allowed_apps.iter().any(|app| app.eq_ignore_ascii_case(&notification.application_name))
+    }
+
+    pub fn is_scheduled_at(&self, at: NaiveDateTime) -> bool {
+        self.schedules.iter().any(|schedule| schedule.is_active_at(at))
+    }
+}
+
+/// The modes offered out of the box. None of them is scheduled or triggered until the user
+/// configures it.
+pub fn default_focus_modes() -> Vec<FocusMode> {
+    vec![
+        FocusMode::do_not_disturb(),
+        FocusMode { icon: Some("x-office-calendar-symbolic".to_string()), ..FocusMode::new("meeting", "Meeting") },
+        FocusMode { icon: Some("emblem-documents-symbolic".to_string()), ..FocusMode::new("deep-work", "Deep work") },
+        FocusMode {
+            icon: Some("weather-clear-night-symbolic".to_string()),
+            suppressed_to_history: true,
+            ..FocusMode::new("sleep", "Sleep")
+        },
+    ]
+}
+
+/// Why a focus mode is active.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FocusActivation {
+    Manual,
+    Schedule,
+    Trigger(FocusTrigger),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ActiveFocus {
+    pub mode_id: String,
+    pub mode_name: String,
+    pub activation: FocusActivation,
+    pub since: DateTime<Utc>,
+}
+
+/// What a focus mode held back while it was active.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct MissedSummary {
+    pub mode_id: String,
+    pub mode_name: String,
+    pub since: DateTime<Utc>,
+    pub until: DateTime<Utc>,
+    pub total: usize,
+    /// Held-back notifications per application name.
+    pub by_app: BTreeMap<String, usize>,
+}
+
+/// The result of a change of the active focus mode.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FocusChange {
+    pub active: Option<ActiveFocus>,
+    /// The summary of the mode that ended, if one did.
+    pub ended: Option<MissedSummary>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum ManualOverride {
+    On(String),
+    Off,
+}
+
+/// Decides which focus mode is active and counts what it holds back.
+#[derive(Debug)]
+pub struct FocusController {
+    modes: Vec<FocusMode>,
+    manual: Option<ManualOverride>,
+    triggers: HashSet<FocusTrigger>,
+    /// The automatic candidate at the last resolution; a manual choice holds until it changes.
+    automatic: Option<(String, FocusActivation)>,
+    active: Option<ActiveFocus>,
+    missed: BTreeMap<String, usize>,
+}
+
+impl Default for FocusController {
+    fn default() -> Self {
+        Self::new(default_focus_modes())
+    }
+}
+
+impl FocusController {
+    pub fn new(modes: Vec<FocusMode>) -> Self {
+        let mut controller = Self {
+            modes: Vec::new(),
+            manual: None,
+            triggers: HashSet::new(),
+            automatic: None,
+            active: None,
+            missed: BTreeMap::new(),
+        };
+        controller.modes = Self::with_do_not_disturb(modes);
+        controller
+    }
+
+    fn with_do_not_disturb(mut modes: Vec<FocusMode>) -> Vec<FocusMode> {
+        if !modes.iter().any(|mode| mode.id == DO_NOT_DISTURB_MODE_ID) {
+            modes.insert(0, FocusMode::do_not_disturb());
+        }
+        modes
+    }
+
+    pub fn modes(&self) -> &[FocusMode] {
+        &self.modes
+    }
+
+    pub fn mode(&self, id: &str) -> Option<&FocusMode> {
+        self.modes.iter().find(|mode| mode.id == id)
+    }
+
+    pub fn active(&self) -> Option<&ActiveFocus> {
+        self.active.as_ref()
+    }
+
+    pub fn active_mode(&self) -> Option<&FocusMode> {
+        self.active.as_ref().and_then(|active| self.mode(&active.mode_id))
+    }
+
+    /// Replaces the configured modes. The built-in Do Not Disturb mode is kept if missing.
+    pub fn set_modes<Tz: TimeZone>(&mut self, modes: Vec<FocusMode>, now: &DateTime<Tz>) -> Option<FocusChange> {
+        self.modes = Self::with_do_not_disturb(modes);
+        if matches!(&self.manual, Some(ManualOverride::On(id)) if self.mode(id).is_none()) {
+            self.manual = None;
+        }
+        self.resolve(now)
+    }
+
+    /// Manually activates mode `mode_id`, or turns focus off for `None`. Returns `None` for an
+    /// unknown mode as well as when nothing changed; check [`Self::mode`] to tell them apart.
+    pub fn activate<Tz: TimeZone>(&mut self, mode_id: Option<&str>, now: &DateTime<Tz>) -> Option<FocusChange> {
+        self.manual = match mode_id {
+            Some(id) if self.mode(id).is_none() => return None,
+            Some(id) => Some(ManualOverride::On(id.to_string())),
+            None => Some(ManualOverride::Off),
+        };
+        self.resolve(now)
+    }
+
+    pub fn set_trigger<Tz: TimeZone>(&mut self, trigger: FocusTrigger, active: bool, now: &DateTime<Tz>) -> Option<FocusChange> {
+        let changed = if active { self.triggers.insert(trigger) } else { self.triggers.remove(&trigger) };
+        if !changed {
+            return None;
+        }
+        self.resolve(now)
+    }
+
+    /// Re-evaluates schedules; call it periodically, e.g. once a minute.
+    pub fn refresh<Tz: TimeZone>(&mut self, now: &DateTime<Tz>) -> Option<FocusChange> {
+        self.resolve(now)
+    }
+
+    /// Whether the active mode holds `notification` back.
+    pub fn suppresses(&self, notification: &Notification) -> bool {
+        self.active_mode().is_some_and(|mode| !mode.allows(notification))
+    }
+
+    /// Counts a notification the active mode held back, for the summary when it ends.
+    pub fn record_missed(&mut self, notification: &Notification) {
+        if self.active.is_some() {
+            *self.missed.entry(notification.application_name.clone()).or_default() += 1;
+        }
+    }
+
+    fn automatic_candidate(&self, at: NaiveDateTime) -> Option<(String, FocusActivation)> {
+        let triggered = self.modes.iter().find_map(|mode| {
+            let trigger = mode.triggers.iter().find(|trigger| self.triggers.contains(trigger))?;
+            Some((mode.id.clone(), FocusActivation::Trigger(*trigger)))
+        });
+        triggered.or_else(|| {
+            let mode = self.modes.iter().find(|mode| mode.is_scheduled_at(at))?;
+            Some((mode.id.clone(), FocusActivation::Schedule))
+        })
+    }
+
+    fn resolve<Tz: TimeZone>(&mut self, now: &DateTime<Tz>) -> Option<FocusChange> {
+        let candidate = self.automatic_candidate(now.naive_local());
+        if candidate != self.automatic {
+            self.manual = None;
+            self.automatic = candidate.clone();
+        }
+        let target = match &self.manual {
+            Some(ManualOverride::On(id)) => Some((id.clone(), FocusActivation::Manual)),
+            Some(ManualOverride::Off) => None,
+            None => candidate,
+        };
+
+        let now = now.with_timezone(&Utc);
+        if let (Some(active), Some((mode_id, activation))) = (self.active.as_mut(), target.as_ref()) {
+            // The same mode staying on for another reason neither restarts nor ends it.
+            if active.mode_id == *mode_id {
+                active.activation = *activation;
+                return None;
+            }
+        }
+        if self.active.is_none() && target.is_none() {
+            return None;
+        }
+        let ended = self.active.take().map(|previous| MissedSummary {
+            mode_id: previous.mode_id,
+            mode_name: previous.mode_name,
+            since: previous.since,
+            until: now,
+            total: self.missed.values().sum(),
+            by_app: std::mem::take(&mut self.missed),
+        });
+        self.active = target.and_then(|(mode_id, activation)| {
+            let mode_name = self.mode(&mode_id)?.name.clone();
+            Some(ActiveFocus { mode_id, mode_name, activation, since: now })
+        });
+        Some(FocusChange { active: self.active.clone(), ended })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    // A Monday.
+    fn at(hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 6, 3, hour, minute, 0).unwrap()
+    }
+
+    fn time(hour: u32, minute: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    fn sleep_mode() -> FocusMode {
+        FocusMode {
+            schedules: vec![FocusSchedule { days: vec![Weekday::Sun, Weekday::Mon], start: time(22, 0), end: time(7, 0) }],
+            ..FocusMode::new("sleep", "Sleep")
+        }
+    }
+
+    #[test]
+    fn test_schedules_span_midnight_and_weekdays() {
+        let workday = FocusSchedule { days: vec![Weekday::Mon], start: time(9, 0), end: time(12, 0) };
+        assert!(workday.is_active_at(at(9, 0).naive_utc()));
+        assert!(!workday.is_active_at(at(12, 0).naive_utc()));
+        assert!(!workday.is_active_at((at(10, 0) + Duration::days(1)).naive_utc()));
+
+        let sleep = &sleep_mode().schedules[0];
+        assert!(sleep.is_active_at(at(23, 0).naive_utc()));
+        // Monday early morning belongs to Sunday night, Tuesday early morning to Monday night.
+        assert!(sleep.is_active_at(at(6, 59).naive_utc()));
+        assert!(sleep.is_active_at((at(3, 0) + Duration::days(1)).naive_utc()));
+        assert!(!sleep.is_active_at((at(3, 0) + Duration::days(2)).naive_utc()));
+        assert!(!sleep.is_active_at(at(7, 0).naive_utc()));
+
+        let all_day = FocusSchedule { days: Vec::new(), start: time(0, 0), end: time(0, 0) };
+        assert!(all_day.is_active_at(at(15, 0).naive_utc()));
+    }
+
+    #[test]
+    fn test_modes_allow_apps_and_urgencies() {
+        let mode = FocusMode { allowed_apps: vec!["Calendar".to_string()], ..FocusMode::new("meeting", "Meeting") };
+        assert!(mode.allows(&Notification::new("calendar".to_string(), "Standup".to_string(), NotificationUrgency::Normal)));
+        assert!(mode.allows(&Notification::new("Chat".to_string(), "Outage".to_string(), NotificationUrgency::Critical)));
+        assert!(!mode.allows(&Notification::new("Chat".to_string(), "Hi".to_string(), NotificationUrgency::Normal)));
+
+        let parsed: FocusMode = serde_json::from_str(r#"{"id":"x","name":"X","triggers":["screencast"]}"#).unwrap();
+        assert_eq!(parsed.allowed_urgencies, vec![NotificationUrgency::Critical]);
+        assert_eq!(parsed.triggers, vec![FocusTrigger::Screencast]);
+        assert!(FocusController::new(vec![parsed]).mode(DO_NOT_DISTURB_MODE_ID).is_some());
+    }
+
+    #[test]
+    fn test_priority_and_manual_override() {
+        let presenting = FocusMode { triggers: vec![FocusTrigger::Screencast], ..FocusMode::new("presenting", "Presenting") };
+        let mut controller = FocusController::new(vec![sleep_mode(), presenting]);
+        assert!(controller.refresh(&at(20, 0)).is_none());
+
+        let change = controller.refresh(&at(22, 0)).unwrap();
+        let active = change.active.unwrap();
+        assert_eq!((active.mode_id.as_str(), active.activation), ("sleep", FocusActivation::Schedule));
+
+        // A trigger outranks a schedule; the schedule takes over again once it goes away.
+        let change = controller.set_trigger(FocusTrigger::Screencast, true, &at(22, 30)).unwrap();
+        assert_eq!(change.active.unwrap().mode_id, "presenting");
+        assert_eq!(change.ended.unwrap().mode_id, "sleep");
+        assert!(controller.set_trigger(FocusTrigger::Screencast, true, &at(22, 31)).is_none());
+        let change = controller.set_trigger(FocusTrigger::Screencast, false, &at(22, 40)).unwrap();
+        assert_eq!(change.active.unwrap().mode_id, "sleep");
+
+        // Turning focus off holds while the schedule lasts; the next night it is back.
+        assert_eq!(controller.activate(None, &at(23, 0)).unwrap().active, None);
+        assert!(controller.refresh(&at(23, 30)).is_none());
+        assert!(controller.refresh(&(at(7, 30) + Duration::days(1))).is_none());
+        let change = controller.refresh(&(at(22, 0) + Duration::days(6))).unwrap();
+        assert_eq!(change.active.unwrap().mode_id, "sleep");
+
+        // Manual activation wins, and unknown modes are rejected.
+        let change = controller.activate(Some(DO_NOT_DISTURB_MODE_ID), &(at(22, 5) + Duration::days(6))).unwrap();
+        assert_eq!(change.active.unwrap().activation, FocusActivation::Manual);
+        assert!(controller.activate(Some("nope"), &at(23, 0)).is_none());
+        assert_eq!(controller.active().unwrap().mode_id, DO_NOT_DISTURB_MODE_ID);
+    }
+
+    #[test]
+    fn test_missed_summary_when_mode_ends() {
+        let mut controller = FocusController::default();
+        let chat = Notification::new("Chat".to_string(), "Hi".to_string(), NotificationUrgency::Normal);
+        let alarm = Notification::new("Battery".to_string(), "Low".to_string(), NotificationUrgency::Critical);
+        controller.record_missed(&chat);
+
+        controller.activate(Some("deep-work"), &at(9, 0)).unwrap();
+        assert!(controller.suppresses(&chat));
+        assert!(!controller.suppresses(&alarm));
+        for _ in 0..3 {
+            controller.record_missed(&chat);
+        }
+        controller.record_missed(&Notification::new("Mail".to_string(), "Re: Plan".to_string(), NotificationUrgency::Low));
+
+        let summary = controller.activate(None, &at(11, 0)).unwrap().ended.unwrap();
+        assert_eq!((summary.mode_name.as_str(), summary.total), ("Deep work", 4));
+        assert_eq!((summary.since, summary.until), (at(9, 0), at(11, 0)));
+        assert_eq!(summary.by_app, BTreeMap::from([("Chat".to_string(), 3), ("Mail".to_string(), 1)]));
+        assert!(!controller.suppresses(&chat));
+    }
+}
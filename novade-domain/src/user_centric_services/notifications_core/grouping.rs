@@ -0,0 +1,314 @@
+//! Grouping of notifications into stacks, and digests for bursts of them.
+//!
+//! Every notification has a group key. By priority it comes from:
+//! 1. the [`GROUP_KEY_HINT`] hint, which notification rules set or override;
+//! 2. the [`THREAD_ID_HINT`] hint an application sends to thread its notifications;
+//! 3. for chat and mail categories (`im.*`, `email.*`), the summary, which names the
+//!    conversation or sender in practice;
+//! 4. the application name.
+//!
+//! Groups are shown as stacks in the notification center. When one group receives many
+//! notifications in a short time, [`DigestTracker`] lets the first few pop up and folds the
+//! rest into a single digest popup per group.
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::types::{Notification, NotificationUrgency};
+
+/// Hint carrying an explicit group key. Set by rules; applications may send it too.
+pub const GROUP_KEY_HINT: &str = "x-novade-group-key";
+/// Hint by which an application threads its notifications, e.g. one id per conversation.
+pub const THREAD_ID_HINT: &str = "x-novade-thread-id";
+
+/// Category families whose summary identifies a conversation.
+const CONVERSATION_CATEGORIES: [&str; 2] = ["im", "email"];
+
+/// The group key of `notification`; see the module documentation for the order of sources.
+pub fn group_key(notification: &Notification) -> String {
+    if let Some(key) = hint_string(notification, GROUP_KEY_HINT) {
+        return key;
+    }
+    let app = &notification.application_name;
+    if let Some(thread) = hint_string(notification, THREAD_ID_HINT) {
+        return format!("{}/thread/{}", app, thread);
+    }
+    if let Some(family) = notification.category.as_deref().and_then(|c| c.split('.').next()) {
+        if CONVERSATION_CATEGORIES.contains(&family) && !notification.summary.trim().is_empty() {
+            return format!("{}/{}/{}", app, family, notification.summary.trim());
+        }
+    }
+    app.clone()
+}
+
+/// String and number hints both work as keys; anything else, or an empty string, does not.
+fn hint_string(notification: &Notification, key: &str) -> Option<String> {
+    match notification.hints.get(key)? {
+        serde_json::Value::String(s) if !s.trim().is_empty() => Some(s.trim().to_string()),
+        serde_json::Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+/// Notifications sharing a group key, shown as one stack.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct NotificationGroup {
+    pub key: String,
+    pub application_name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub application_icon: Option<String>,
+    /// The conversation for threaded groups, otherwise the application name.
+    pub title: String,
+    /// Newest first; never empty.
+    pub notifications: Vec<Notification>,
+    pub unread_count: usize,
+    /// The highest urgency in the group.
+    pub urgency: NotificationUrgency,
+}
+
+impl NotificationGroup {
+    pub fn len(&self) -> usize {
+        self.notifications.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.notifications.is_empty()
+    }
+
+    pub fn latest(&self) -> &Notification {
+        &self.notifications[0]
+    }
+
+    fn title_for(key: &str, notification: &Notification) -> String {
+        if key == notification.application_name {
+            notification.application_name.clone()
+        } else {
+            notification.summary.clone()
+        }
+    }
+}
+
+/// Groups `notifications` by [`group_key`]. Groups with a critical notification come first, the
+/// rest by their latest notification, newest first.
+pub fn group_notifications(notifications: impl IntoIterator<Item = Notification>) -> Vec<NotificationGroup> {
+    let mut order: Vec<String> = Vec::new();
+    let mut by_key: HashMap<String, Vec<Notification>> = HashMap::new();
+    for notification in notifications {
+        let key = group_key(&notification);
+        if !by_key.contains_key(&key) {
+            order.push(key.clone());
+        }
+        by_key.entry(key).or_default().push(notification);
+    }
+
+    let mut groups: Vec<NotificationGroup> = order
+        .into_iter()
+        .map(|key| {
+            let mut notifications = by_key.remove(&key).unwrap_or_default();
+            notifications.sort_by_key(|n| std::cmp::Reverse(n.timestamp));
+            let latest = &notifications[0];
+            NotificationGroup {
+                title: NotificationGroup::title_for(&key, latest),
+                application_name: latest.application_name.clone(),
+                application_icon: latest.application_icon.clone(),
+                unread_count: notifications.iter().filter(|n| !n.is_read).count(),
+                urgency: notifications.iter().map(|n| n.urgency).max().unwrap_or_default(),
+                key,
+                notifications,
+            }
+        })
+        .collect();
+    let critical = |group: &NotificationGroup| group.urgency == NotificationUrgency::Critical;
+    groups.sort_by(|a, b| critical(b).cmp(&critical(a)).then(b.latest().timestamp.cmp(&a.latest().timestamp)));
+    groups
+}
+
+/// Stands in for the popups of a burst in one group.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct NotificationDigest {
+    pub group_key: String,
+    pub application_name: String,
+    pub title: String,
+    /// Notifications in the burst so far, including those that popped up on their own.
+    pub count: usize,
+    pub latest_summary: String,
+    pub since: DateTime<Utc>,
+}
+
+/// When a burst turns into a digest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DigestPolicy {
+    /// Notifications of a burst that pop up on their own before the digest takes over.
+    pub burst_threshold: usize,
+    /// Longest gap between two notifications of the same burst.
+    pub window: Duration,
+}
+
+pub const DEFAULT_DIGEST_BURST_THRESHOLD: usize = 3;
+pub const DEFAULT_DIGEST_WINDOW_SECS: i64 = 60;
+
+impl Default for DigestPolicy {
+    fn default() -> Self {
+        Self {
+            burst_threshold: DEFAULT_DIGEST_BURST_THRESHOLD,
+            window: Duration::seconds(DEFAULT_DIGEST_WINDOW_SECS),
+        }
+    }
+}
+
+/// How a new notification is presented.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PopupDecision {
+    Show,
+    /// Update the group's digest popup instead.
+    Digest(NotificationDigest),
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Burst {
+    since: DateTime<Utc>,
+    last: DateTime<Utc>,
+    count: usize,
+}
+
+/// Tracks recent notifications per group to detect bursts.
+#[derive(Debug, Default)]
+pub struct DigestTracker {
+    policy: DigestPolicy,
+    bursts: HashMap<String, Burst>,
+}
+
+impl DigestTracker {
+    pub fn new(policy: DigestPolicy) -> Self {
+        Self { policy, bursts: HashMap::new() }
+    }
+
+    pub fn policy(&self) -> DigestPolicy {
+        self.policy
+    }
+
+    pub fn set_policy(&mut self, policy: DigestPolicy) {
+        self.policy = policy;
+    }
+
+    /// Records `notification`, posted at `now` into group `key`, and decides how to present it.
+    /// Critical notifications always pop up but still count towards the burst.
+    pub fn observe(&mut self, key: &str, notification: &Notification, now: DateTime<Utc>) -> PopupDecision {
+        let window = self.policy.window;
+        self.bursts.retain(|_, burst| now - burst.last <= window);
+        let burst = self.bursts.entry(key.to_string()).or_insert(Burst { since: now, last: now, count: 0 });
+        burst.count += 1;
+        burst.last = now;
+
+        if burst.count <= self.policy.burst_threshold || notification.urgency == NotificationUrgency::Critical {
+            return PopupDecision::Show;
+        }
+        PopupDecision::Digest(NotificationDigest {
+            group_key: key.to_string(),
+            application_name: notification.application_name.clone(),
+            title: NotificationGroup::title_for(key, notification),
+            count: burst.count,
+            latest_summary: notification.summary.clone(),
+            since: burst.since,
+        })
+    }
+
+    /// Forgets the burst of group `key`, e.g. once the group is dismissed.
+    pub fn reset(&mut self, key: &str) {
+        self.bursts.remove(key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn chat(summary: &str) -> Notification {
+        let mut notification = Notification::new("Chat".to_string(), summary.to_string(), NotificationUrgency::Normal);
+        notification.category = Some("im.received".to_string());
+        notification
+    }
+
+    #[test]
+    fn test_group_key_sources() {
+        let plain = Notification::new("Files".to_string(), "Copied".to_string(), NotificationUrgency::Normal);
+        assert_eq!(group_key(&plain), "Files");
+
+        let mut threaded = plain.clone();
+        threaded.hints.insert(THREAD_ID_HINT.to_string(), json!(42));
+        assert_eq!(group_key(&threaded), "Files/thread/42");
+
+        assert_eq!(group_key(&chat("Alice")), "Chat/im/Alice");
+        let mut mail = chat("Bob");
+        mail.category = Some("email.arrived".to_string());
+        assert_eq!(group_key(&mail), "Chat/email/Bob");
+
+        // A key set by a rule wins over everything else.
+        let mut ruled = chat("Alice");
+        ruled.hints.insert(THREAD_ID_HINT.to_string(), json!("t1"));
+        ruled.hints.insert(GROUP_KEY_HINT.to_string(), json!("work"));
+        assert_eq!(group_key(&ruled), "work");
+        ruled.hints.insert(GROUP_KEY_HINT.to_string(), json!(""));
+        assert_eq!(group_key(&ruled), "Chat/thread/t1");
+    }
+
+    #[test]
+    fn test_group_notifications_orders_and_counts() {
+        let now = Utc::now();
+        let mut alice_old = chat("Alice");
+        alice_old.timestamp = now - Duration::seconds(30);
+        alice_old.is_read = true;
+        let mut alice_new = chat("Alice");
+        alice_new.timestamp = now - Duration::seconds(10);
+        let mut files = Notification::new("Files".to_string(), "Copied".to_string(), NotificationUrgency::Low);
+        files.timestamp = now;
+        let mut alarm = Notification::new("Battery".to_string(), "Low".to_string(), NotificationUrgency::Critical);
+        alarm.timestamp = now - Duration::seconds(60);
+
+        let groups = group_notifications(vec![alice_old.clone(), files, alarm, alice_new.clone()]);
+        let keys: Vec<&str> = groups.iter().map(|g| g.key.as_str()).collect();
+        assert_eq!(keys, ["Battery", "Files", "Chat/im/Alice"]);
+
+        let alice = &groups[2];
+        assert_eq!(alice.title, "Alice");
+        assert_eq!(alice.len(), 2);
+        assert_eq!(alice.unread_count, 1);
+        assert_eq!(alice.latest().id, alice_new.id);
+        assert_eq!(groups[1].title, "Files");
+    }
+
+    #[test]
+    fn test_burst_turns_into_digest() {
+        let mut tracker = DigestTracker::new(DigestPolicy { burst_threshold: 2, window: Duration::seconds(60) });
+        let start = Utc::now();
+        let key = "Chat/im/Alice";
+        let decisions: Vec<PopupDecision> = (0..30)
+            .map(|i| tracker.observe(key, &chat(&format!("message {}", i)), start + Duration::seconds(i)))
+            .collect();
+        assert_eq!(decisions[0], PopupDecision::Show);
+        assert_eq!(decisions[1], PopupDecision::Show);
+        match &decisions[29] {
+            PopupDecision::Digest(digest) => {
+                assert_eq!((digest.count, digest.since), (30, start));
+                assert_eq!(digest.latest_summary, "message 29");
+            }
+            other => panic!("expected a digest, got {:?}", other),
+        }
+
+        // Critical notifications still pop up; other groups are unaffected.
+        let mut critical = chat("urgent");
+        critical.urgency = NotificationUrgency::Critical;
+        assert_eq!(tracker.observe(key, &critical, start + Duration::seconds(31)), PopupDecision::Show);
+        assert_eq!(tracker.observe("Files", &chat("x"), start + Duration::seconds(31)), PopupDecision::Show);
+
+        // A quiet spell or a reset ends the burst.
+        assert_eq!(tracker.observe(key, &chat("later"), start + Duration::seconds(200)), PopupDecision::Show);
+        tracker.observe(key, &chat("again"), start + Duration::seconds(201));
+        tracker.reset(key);
+        assert_eq!(tracker.observe(key, &chat("fresh"), start + Duration::seconds(202)), PopupDecision::Show);
+    }
+}
@@ -0,0 +1,175 @@
+//! Retention of the notification history, full-text matching over it and its JSON export.
+//!
+//! The service keeps the history in memory and writes every change through to a
+//! [`NotificationHistoryStore`](super::persistence_iface::NotificationHistoryStore) when the
+//! system layer attached one, so it survives restarts. [`HistoryRetention`] decides what is kept:
+//! entries age out, the history and each application are capped in size, and applications can
+//! opt out entirely so that e.g. a password manager never lands on disk.
+//!
+//! Full-text search matches words by prefix: "2fa cod" finds "Your 2FA code is 123456". The
+//! store's index uses the same [`search_terms`], so both give the same results.
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use uuid::Uuid;
+
+use super::types::Notification;
+
+pub const DEFAULT_HISTORY_MAX_AGE_DAYS: u32 = 30;
+pub const DEFAULT_HISTORY_MAX_ITEMS: usize = 1000;
+/// Version of the [`HistoryExport`] format.
+pub const HISTORY_EXPORT_VERSION: u32 = 1;
+
+/// Missing fields take their default; `max-age-days: null` keeps entries regardless of age.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct HistoryRetention {
+    /// Days after which entries are dropped; `None` keeps them regardless of age.
+    pub max_age_days: Option<u32>,
+    /// Most entries kept; 0 keeps no history at all.
+    pub max_items: usize,
+    /// Overrides by application name.
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub apps: HashMap<String, AppRetention>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct AppRetention {
+    /// Keeps the application out of the history altogether.
+    #[serde(default)]
+    pub excluded: bool,
+    /// Replaces the global maximum age for this application.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_age_days: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_items: Option<usize>,
+}
+
+impl Default for HistoryRetention {
+    fn default() -> Self {
+        Self { max_age_days: Some(DEFAULT_HISTORY_MAX_AGE_DAYS), max_items: DEFAULT_HISTORY_MAX_ITEMS, apps: HashMap::new() }
+    }
+}
+
+impl HistoryRetention {
+    /// Whether notifications of `application_name` go into the history at all.
+    pub fn records(&self, application_name: &str) -> bool {
+        self.max_items > 0 && !self.apps.get(application_name).map_or(false, |app| app.excluded)
+    }
+
+    /// Ids of the entries of `history`, oldest first, that the policy drops at `now`.
+    pub fn expired(&self, history: &VecDeque<Notification>, now: DateTime<Utc>) -> Vec<Uuid> {
+        let mut expired = HashSet::new();
+        let mut per_app: HashMap<&str, usize> = HashMap::new();
+        let mut kept = 0;
+        for notification in history.iter().rev() {
+            let app = self.apps.get(&notification.application_name);
+            let max_age = app.and_then(|app| app.max_age_days).or(self.max_age_days);
+            let too_old = max_age.map_or(false, |days| notification.timestamp < now - Duration::days(days.into()));
+            let app_count = per_app.entry(notification.application_name.as_str()).or_default();
+            let over_app_limit = app.and_then(|app| app.max_items).map_or(false, |max| *app_count >= max);
+            if !self.records(&notification.application_name) || too_old || over_app_limit || kept >= self.max_items {
+                expired.insert(notification.id);
+                continue;
+            }
+            *app_count += 1;
+            kept += 1;
+        }
+        history.iter().filter(|n| expired.contains(&n.id)).map(|n| n.id).collect()
+    }
+}
+
+/// The lowercased words of `text`; searches and the store's index both work on these.
+pub fn search_terms(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric()).filter(|word| !word.is_empty()).map(str::to_lowercase).collect()
+}
+
+/// Whether every term of `query` starts a word of the notification's summary or body. An empty
+/// query matches everything.
+pub fn matches_text(notification: &Notification, query: &str) -> bool {
+    let mut words = search_terms(&notification.summary);
+    words.extend(search_terms(notification.body.as_deref().unwrap_or("")));
+    search_terms(query).iter().all(|term| words.iter().any(|word| word.starts_with(term.as_str())))
+}
+
+/// The history as exported to a file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct HistoryExport {
+    pub version: u32,
+    pub exported_at: DateTime<Utc>,
+    /// Oldest first.
+    pub notifications: Vec<Notification>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::user_centric_services::notifications_core::types::NotificationUrgency;
+
+    fn entry(app: &str, summary: &str, age_days: i64, now: DateTime<Utc>) -> Notification {
+        let mut notification = Notification::new(app.to_string(), summary.to_string(), NotificationUrgency::Normal);
+        notification.timestamp = now - Duration::days(age_days);
+        notification
+    }
+
+    #[test]
+    fn test_retention_by_age_count_and_app() {
+        let now = Utc::now();
+        let history: VecDeque<Notification> = vec![
+            entry("Mail", "ancient", 40, now),
+            entry("Chat", "old chat", 10, now),
+            entry("Vault", "secret", 2, now),
+            entry("Chat", "recent chat", 1, now),
+            entry("Mail", "new mail", 0, now),
+        ]
+        .into();
+        let ids: Vec<Uuid> = history.iter().map(|n| n.id).collect();
+
+        let mut retention = HistoryRetention::default();
+        assert_eq!(retention.expired(&history, now), [ids[0]]);
+
+        retention.apps.insert("Vault".to_string(), AppRetention { excluded: true, ..Default::default() });
+        retention.apps.insert("Chat".to_string(), AppRetention { max_items: Some(1), ..Default::default() });
+        assert!(!retention.records("Vault") && retention.records("Chat"));
+        assert_eq!(retention.expired(&history, now), [ids[0], ids[1], ids[2]]);
+
+        // An app's own age limit wins over the global one, and the overall cap keeps the newest.
+        retention.apps.insert("Mail".to_string(), AppRetention { max_age_days: Some(365), ..Default::default() });
+        retention.max_items = 2;
+        assert_eq!(retention.expired(&history, now), [ids[0], ids[1], ids[2]]);
+        retention.max_items = 3;
+        assert_eq!(retention.expired(&history, now), [ids[1], ids[2]]);
+
+        retention.max_items = 0;
+        assert!(!retention.records("Mail"));
+        assert_eq!(retention.expired(&history, now).len(), 5);
+    }
+
+    #[test]
+    fn test_full_text_matching() {
+        let mut notification = Notification::new("Bank".to_string(), "Your 2FA code".to_string(), NotificationUrgency::Normal);
+        notification.body = Some("<b>Code:</b> 491-204, valid for 10 minutes".to_string());
+        assert!(matches_text(&notification, "2fa"));
+        assert!(matches_text(&notification, "CODE 491"));
+        assert!(!matches_text(&notification, "4912"));
+        assert!(matches_text(&notification, "valid min"));
+        assert!(matches_text(&notification, ""));
+        assert!(!matches_text(&notification, "ode"));
+        assert!(!matches_text(&notification, "code expired"));
+        assert_eq!(search_terms("2FA-Code: 491"), ["2fa", "code", "491"]);
+    }
+
+    #[test]
+    fn test_retention_serde() {
+        let retention: HistoryRetention = serde_json::from_str(r#"{"max-items": 50, "apps": {"Vault": {"excluded": true}}}"#).unwrap();
+        assert_eq!(retention.max_age_days, Some(DEFAULT_HISTORY_MAX_AGE_DAYS));
+        assert!(!retention.records("Vault"));
+        let forever: HistoryRetention = serde_json::from_str(r#"{"max-age-days": null}"#).unwrap();
+        assert_eq!((forever.max_age_days, forever.max_items), (None, DEFAULT_HISTORY_MAX_ITEMS));
+        let round_trip: HistoryRetention = serde_json::from_str(&serde_json::to_string(&retention).unwrap()).unwrap();
+        assert_eq!(round_trip, retention);
+    }
+}
@@ -5,6 +5,9 @@ pub mod errors;
 pub mod persistence_iface; // For notification history persistence
 pub mod persistence;       // For filesystem implementation of persistence
 pub mod service;           // For the NotificationService trait and its impl
+pub mod grouping;          // Group keys, stacks and burst digests
+pub mod focus;             // Focus modes extending Do Not Disturb
+pub mod history;           // History retention, full-text search and export
 
 // Re-exports for easier access by consumers of this submodule or parent modules.
 pub use types::{
@@ -13,15 +16,53 @@ pub use types::{
     NotificationUrgency,
     NotificationAction,
     NotificationActionType,
+    NotificationImage,
     NotificationStats,
     DismissReason,
     NotificationFilterCriteria,
     NotificationSortOrder,
+    RuleSimulationSample,
+    NotificationChannel,
+    CHANNEL_HINT,
+    SNOOZE_UNTIL_HINT,
+    INLINE_REPLY_ACTION_KEY,
+    REPLY_PLACEHOLDER_HINT,
+    PROGRESS_HINT,
+    ATTACHMENT_URLS_HINT,
+    NotificationSound,
+    SOUND_NAME_HINT,
+    SOUND_FILE_HINT,
+    SUPPRESS_SOUND_HINT,
 };
 pub use errors::NotificationError;
+pub use grouping::{
+    group_key,
+    group_notifications,
+    NotificationGroup,
+    NotificationDigest,
+    DigestPolicy,
+    GROUP_KEY_HINT,
+    THREAD_ID_HINT,
+};
+pub use focus::{
+    FocusMode,
+    FocusSchedule,
+    FocusTrigger,
+    FocusActivation,
+    ActiveFocus,
+    MissedSummary,
+    DO_NOT_DISTURB_MODE_ID,
+};
+
+pub use history::{
+    HistoryRetention,
+    AppRetention,
+    HistoryExport,
+    HISTORY_EXPORT_VERSION,
+};
 
-// When service.rs and persistence_iface.rs are implemented, re-export their main traits:
-// pub use service::NotificationService;
+pub use service::{NotificationService, DefaultNotificationService};
 // pub use persistence_iface::NotificationHistoryProvider;
+pub use persistence_iface::NotificationHistoryStore;
 
 // No unit tests in this mod.rs file. Tests are in respective files.
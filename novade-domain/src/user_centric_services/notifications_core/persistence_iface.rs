@@ -1,5 +1,6 @@
 use async_trait::async_trait;
 use std::collections::VecDeque;
+use uuid::Uuid;
 use super::types::Notification;
 use super::errors::NotificationError;
 
@@ -9,3 +10,19 @@ pub trait NotificationHistoryProvider: Send + Sync {
     async fn save_history(&self, history: &VecDeque<Notification>) -> Result<(), NotificationError>;
     // Optional: async fn clear_history_storage(&self) -> Result<(), NotificationError>;
 }
+
+/// Durable, searchable storage for the notification history. Unlike a
+/// [`NotificationHistoryProvider`] it is updated entry by entry; see
+/// [`NotificationService::attach_history_store`](super::NotificationService::attach_history_store).
+#[async_trait]
+pub trait NotificationHistoryStore: Send + Sync {
+    /// Every stored entry, oldest first.
+    async fn load(&self) -> Result<Vec<Notification>, NotificationError>;
+    /// Inserts the entries, replacing stored ones with the same id.
+    async fn upsert(&self, notifications: &[Notification]) -> Result<(), NotificationError>;
+    async fn remove(&self, ids: &[Uuid]) -> Result<(), NotificationError>;
+    async fn clear(&self) -> Result<(), NotificationError>;
+    /// Ids of the entries whose summary or body has a word starting with each of `terms` (see
+    /// [`search_terms`](super::history::search_terms)), best match first.
+    async fn search(&self, terms: &[String], limit: usize) -> Result<Vec<Uuid>, NotificationError>;
+}
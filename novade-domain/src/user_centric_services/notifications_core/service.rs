@@ -1,37 +1,33 @@
 use async_trait::async_trait;
 use serde_json::Value as JsonValue;
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::sync::Arc;
 use tokio::sync::{broadcast, RwLock};
 use uuid::Uuid;
-use chrono::Utc;
+use chrono::{Local, Utc};
 use tracing::{debug, error, info, warn};
 
 use super::types::{
     Notification, NotificationInput, NotificationAction, NotificationUrgency, NotificationStats,
-    DismissReason, NotificationFilterCriteria, NotificationSortOrder,
+    DismissReason, NotificationFilterCriteria, NotificationSortOrder, RuleSimulationSample,
+    NotificationChannel, SNOOZE_UNTIL_HINT, INLINE_REPLY_ACTION_KEY,
 };
 use super::errors::NotificationError;
+use super::grouping::{self, DigestPolicy, DigestTracker, NotificationGroup, PopupDecision};
+use super::focus::{ActiveFocus, FocusChange, FocusController, FocusMode, FocusTrigger, DO_NOT_DISTURB_MODE_ID};
+use super::history::{self, HistoryExport, HistoryRetention, HISTORY_EXPORT_VERSION};
+use super::persistence_iface::NotificationHistoryStore;
 use crate::user_centric_services::events::NotificationEventEnum;
-use crate::notifications_rules::{NotificationRulesEngine, RuleProcessingResult, errors::NotificationRulesError};
+use crate::notifications_rules::{NotificationRuleSet, NotificationRulesEngine, RuleContextChange, RuleProcessingResult, RuleSimulation, RuleValidationIssue, errors::NotificationRulesError};
 use crate::global_settings::{
-    GlobalSettingsService, 
-    paths::SettingPath, // Assuming SettingPath can represent notification settings paths
-    // types::GlobalDesktopSettings, // Not directly used if paths are specific enough
+    GlobalSettingsService,
+    paths::{NotificationSettingPath, SettingPath},
+    types::NotificationSettings,
 };
 use crate::shared_types::ApplicationId;
 
-// Placeholder for where notification-specific settings might live within GlobalSettings
-// For now, these are effectively constants if not read from GlobalSettingsService
-const DEFAULT_MAX_ACTIVE_POPUPS: usize = 5;
-const DEFAULT_MAX_HISTORY_ITEMS: usize = 100;
-
-// Placeholder for setting paths if they were to be defined in global_settings::paths
-// For example:
-// pub enum NotificationsCoreSettingPath { MaxActivePopups, MaxHistoryItems, DefaultTimeout }
-// Then SettingPath::NotificationsCore(NotificationsCoreSettingPath::MaxActivePopups)
-// For this implementation, we'll assume settings_service.get_setting can take a string path
-// or these values are managed differently (e.g. hardcoded or via a dedicated config struct).
+/// Popups shown at once unless `notifications.max-active-popups` says otherwise.
+pub const DEFAULT_MAX_ACTIVE_POPUPS: usize = 5;
 
 
 // --- NotificationService Trait ---
@@ -45,11 +41,51 @@ pub trait NotificationService: Send + Sync {
     async fn get_active_notifications(&self, filter: Option<&NotificationFilterCriteria>, sort_order: Option<NotificationSortOrder>) -> Result<Vec<Notification>, NotificationError>;
     async fn get_notification_history(&self, limit: Option<usize>, offset: Option<usize>, filter: Option<&NotificationFilterCriteria>, sort_order: Option<NotificationSortOrder>) -> Result<Vec<Notification>, NotificationError>;
     async fn clear_history(&self) -> Result<(), NotificationError>;
+    /// Full-text search of the history over summary and body, see [`history`]. Best matches come
+    /// first when a history store is attached, the newest otherwise.
+    async fn search_history(&self, query: &str, limit: Option<usize>, offset: Option<usize>) -> Result<Vec<Notification>, NotificationError>;
+    /// The history entries matching `filter`, oldest first, ready to be written to a file.
+    async fn export_history(&self, filter: Option<&NotificationFilterCriteria>) -> Result<HistoryExport, NotificationError>;
+    async fn get_history_retention(&self) -> Result<HistoryRetention, NotificationError>;
+    /// Replaces the retention policy and applies it to the history right away.
+    async fn set_history_retention(&self, retention: HistoryRetention) -> Result<(), NotificationError>;
+    /// Makes `store` the durable home of the history: its entries are loaded, those recorded so
+    /// far are written to it and every later change goes through to it. Returns how many entries
+    /// the history holds afterwards.
+    async fn attach_history_store(&self, store: Arc<dyn NotificationHistoryStore>) -> Result<usize, NotificationError>;
     async fn clear_all_for_app(&self, app_id: &ApplicationId, reason: DismissReason) -> Result<usize, NotificationError>;
+    /// Toggles the built-in Do Not Disturb focus mode; turning it off ends any focus mode.
     async fn set_do_not_disturb(&self, enabled: bool) -> Result<(), NotificationError>;
+    /// Whether any focus mode is active.
     async fn is_do_not_disturb_enabled(&self) -> Result<bool, NotificationError>;
-    async fn invoke_action(&self, notification_id: Uuid, action_key: &str) -> Result<(), NotificationError>;
+    async fn get_focus_modes(&self) -> Result<Vec<FocusMode>, NotificationError>;
+    async fn set_focus_modes(&self, modes: Vec<FocusMode>) -> Result<(), NotificationError>;
+    /// Manually activates a focus mode, or turns focus off for `None`, see [`super::focus`].
+    async fn activate_focus_mode(&self, mode_id: Option<&str>) -> Result<(), NotificationError>;
+    async fn get_active_focus(&self) -> Result<Option<ActiveFocus>, NotificationError>;
+    /// Reports whether a desktop state that can trigger focus modes currently holds.
+    async fn set_focus_trigger(&self, trigger: FocusTrigger, active: bool) -> Result<(), NotificationError>;
+    /// Re-evaluates focus schedules; the system layer calls this periodically.
+    async fn refresh_focus(&self) -> Result<(), NotificationError>;
+    /// `activation_token` is the XDG activation token of the click that triggered the action, if any.
+    async fn invoke_action(&self, notification_id: Uuid, action_key: &str, activation_token: Option<String>) -> Result<(), NotificationError>;
+    /// Answers the notification's inline reply action with `text`.
+    async fn reply(&self, notification_id: Uuid, text: &str) -> Result<(), NotificationError>;
     async fn get_stats(&self) -> Result<NotificationStats, NotificationError>;
+    /// Active notifications grouped into stacks, see [`grouping`].
+    async fn get_notification_groups(&self, filter: Option<&NotificationFilterCriteria>) -> Result<Vec<NotificationGroup>, NotificationError>;
+    /// Dismisses every active notification of the group; returns how many there were.
+    async fn dismiss_group(&self, group_key: &str, reason: DismissReason) -> Result<usize, NotificationError>;
+    async fn get_rules(&self) -> Result<NotificationRuleSet, NotificationError>;
+    async fn update_rules(&self, rules: NotificationRuleSet) -> Result<(), NotificationError>;
+    fn validate_rules(&self, rules: &NotificationRuleSet) -> Vec<RuleValidationIssue>;
+    /// Dry-runs `rules`, or the active rules if `None`, against `sample` without posting it.
+    async fn simulate_rules(&self, sample: RuleSimulationSample, rules: Option<NotificationRuleSet>) -> Result<RuleSimulation, NotificationError>;
+    /// Reports a change of the desktop state rule conditions match on.
+    async fn update_rule_context(&self, change: RuleContextChange) -> Result<(), NotificationError>;
+    /// Delivers the snoozed notifications that are due; returns how many there were. The system
+    /// layer calls this periodically.
+    async fn release_snoozed(&self) -> Result<usize, NotificationError>;
     fn subscribe_to_notification_events(&self) -> broadcast::Receiver<NotificationEventEnum>;
 }
 
@@ -58,12 +94,19 @@ pub trait NotificationService: Send + Sync {
 pub struct DefaultNotificationService {
     active_notifications: Arc<RwLock<VecDeque<Notification>>>,
     history: Arc<RwLock<VecDeque<Notification>>>,
-    dnd_enabled: Arc<RwLock<bool>>,
+    focus: Arc<RwLock<FocusController>>,
     rules_engine: Arc<dyn NotificationRulesEngine>,
     settings_service: Arc<dyn GlobalSettingsService>,
     event_publisher: broadcast::Sender<NotificationEventEnum>,
     max_active_popups_cache: Arc<RwLock<usize>>,
-    max_history_items_cache: Arc<RwLock<usize>>,
+    retention: Arc<RwLock<HistoryRetention>>,
+    /// Where the history is written through to; `None` keeps it in memory only.
+    history_store: Arc<RwLock<Option<Arc<dyn NotificationHistoryStore>>>>,
+    digests: Arc<RwLock<DigestTracker>>,
+    /// Active notifications without a popup of their own: digested, or held back by a focus mode.
+    silent_ids: Arc<RwLock<HashSet<Uuid>>>,
+    /// Notifications a rule snoozed, until they are due.
+    snoozed: Arc<RwLock<Vec<Notification>>>,
 }
 
 impl DefaultNotificationService {
@@ -76,12 +119,16 @@ impl DefaultNotificationService {
         let service = Self {
             active_notifications: Arc::new(RwLock::new(VecDeque::new())),
             history: Arc::new(RwLock::new(VecDeque::new())),
-            dnd_enabled: Arc::new(RwLock::new(false)),
+            focus: Arc::new(RwLock::new(FocusController::default())),
             rules_engine,
             settings_service,
             event_publisher,
             max_active_popups_cache: Arc::new(RwLock::new(DEFAULT_MAX_ACTIVE_POPUPS)),
-            max_history_items_cache: Arc::new(RwLock::new(DEFAULT_MAX_HISTORY_ITEMS)),
+            retention: Arc::new(RwLock::new(HistoryRetention::default())),
+            history_store: Arc::new(RwLock::new(None)),
+            digests: Arc::new(RwLock::new(DigestTracker::default())),
+            silent_ids: Arc::new(RwLock::new(HashSet::new())),
+            snoozed: Arc::new(RwLock::new(Vec::new())),
         };
         service.load_settings_cache().await?;
         Ok(service)
@@ -89,44 +136,89 @@ impl DefaultNotificationService {
 
     async fn load_settings_cache(&self) -> Result<(), NotificationError> {
         debug!("Loading notification settings cache...");
-        // Using string paths for get_setting as SettingPath::NotificationsCore(...) isn't defined yet.
-        // This assumes GlobalSettingsService can handle string paths or these are mapped.
-        // If these settings are not found, defaults are used.
-        
-        // Path for MaxActivePopups (example string path)
-        let max_popups_path_str = "notifications.max_active_popups"; 
-        match self.settings_service.get_setting(&SettingPath::from_str_unsafe_for_testing(max_popups_path_str)).await {
-            Ok(JsonValue::Number(num)) => {
-                if let Some(val) = num.as_u64() { *self.max_active_popups_cache.write().await = val as usize; }
-            }
-            Ok(_) | Err(_) => { warn!("Could not read '{}' from global settings or invalid type, using default: {}", max_popups_path_str, DEFAULT_MAX_ACTIVE_POPUPS); }
+        let settings = match read_setting(&self.settings_service, SettingPath::NotificationsRoot).await {
+            Some(value) => serde_json::from_value::<NotificationSettings>(value).unwrap_or_else(|e| {
+                warn!("Invalid notification settings, using the defaults: {}", e);
+                NotificationSettings::default()
+            }),
+            None => NotificationSettings::default(),
+        };
+        *self.max_active_popups_cache.write().await = settings.max_active_popups;
+        self.retention.write().await.max_items = settings.max_history_items;
+
+        if let Some(retention) = settings.history_retention {
+            *self.retention.write().await = retention;
         }
 
-        // Path for MaxHistoryItems (example string path)
-        let max_history_path_str = "notifications.max_history_items";
-        match self.settings_service.get_setting(&SettingPath::from_str_unsafe_for_testing(max_history_path_str)).await {
-            Ok(JsonValue::Number(num)) => {
-                if let Some(val) = num.as_u64() { *self.max_history_items_cache.write().await = val as usize; }
-            }
-            Ok(_) | Err(_) => { warn!("Could not read '{}' from global settings or invalid type, using default: {}", max_history_path_str, DEFAULT_MAX_HISTORY_ITEMS); }
+        let policy = DigestPolicy {
+            burst_threshold: settings.digest_burst_threshold,
+            window: chrono::Duration::seconds(settings.digest_window_secs as i64),
+        };
+        self.digests.write().await.set_policy(policy);
+
+        if let Some(modes) = settings.focus_modes {
+            self.focus.write().await.set_modes(modes, &Local::now());
         }
-        
-        debug!("Notification settings cache loaded: max_popups={}, max_history={}", 
-               *self.max_active_popups_cache.read().await, *self.max_history_items_cache.read().await);
+
+        debug!("Notification settings cache loaded: max_popups={}, retention={:?}, digest={:?}", 
+               *self.max_active_popups_cache.read().await, *self.retention.read().await, policy);
         Ok(())
     }
 
+    /// Saves a setting changed through the service so it survives restarts. Failures are only
+    /// logged: the change already applies.
+    async fn save_setting<T: serde::Serialize>(&self, path: NotificationSettingPath, value: &T) {
+        let path = SettingPath::Notifications(path);
+        let value = match serde_json::to_value(value) {
+            Ok(value) => value,
+            Err(e) => {
+                error!("Could not serialize '{}': {}", path, e);
+                return;
+            }
+        };
+        if let Err(e) = self.settings_service.update_setting(path.clone(), value).await {
+            warn!("Could not save '{}' to global settings: {}", path, e);
+        }
+    }
+
     async fn add_to_history(&self, notification: Notification) {
+        let retention = self.retention.read().await.clone();
+        if !retention.records(&notification.application_name) { return; }
         let mut history_guard = self.history.write().await;
-        let max_history = *self.max_history_items_cache.read().await;
-        if max_history > 0 && history_guard.len() >= max_history { // Only pop if max_history > 0
-            history_guard.pop_front();
+        // A replacement updates the entry of the notification it replaces.
+        match history_guard.iter_mut().find(|n| n.id == notification.id) {
+            Some(existing) => *existing = notification.clone(),
+            None => history_guard.push_back(notification.clone()),
+        }
+        let expired = Self::prune(&mut history_guard, &retention);
+        drop(history_guard);
+        self.persist(&[notification], &expired).await;
+    }
+
+    /// Drops the entries `retention` no longer keeps; returns their ids.
+    fn prune(history: &mut VecDeque<Notification>, retention: &HistoryRetention) -> Vec<Uuid> {
+        let expired = retention.expired(history, Utc::now());
+        if !expired.is_empty() { history.retain(|n| !expired.contains(&n.id)); }
+        expired
+    }
+
+    /// Writes history changes through to the attached store. Failures are only logged: the
+    /// in-memory history stays complete for this session either way.
+    async fn persist(&self, upserted: &[Notification], removed: &[Uuid]) {
+        let Some(store) = self.history_store.read().await.clone() else { return; };
+        if !upserted.is_empty() {
+            if let Err(e) = store.upsert(upserted).await { error!("Could not store {} history entries: {}", upserted.len(), e); }
         }
-        if max_history > 0 || notification.transient == false { // Add if history is enabled or not transient
-             history_guard.push_back(notification);
+        if !removed.is_empty() {
+            if let Err(e) = store.remove(removed).await { error!("Could not remove {} expired history entries from the store: {}", removed.len(), e); }
         }
     }
 
+    fn page(items: Vec<Notification>, limit: Option<usize>, offset: Option<usize>) -> Vec<Notification> {
+        let start = offset.unwrap_or(0);
+        items.into_iter().skip(start).take(limit.unwrap_or(usize::MAX)).collect()
+    }
+
     fn apply_filters_and_sort(
         notifications: Vec<Notification>,
         filter: Option<&NotificationFilterCriteria>,
@@ -161,6 +253,7 @@ impl DefaultNotificationService {
             NotificationFilterCriteria::HasActionWithKey(key) => notification.actions.iter().any(|a| a.key == *key),
             NotificationFilterCriteria::BodyContains(text) => notification.body.as_deref().unwrap_or("").contains(text),
             NotificationFilterCriteria::SummaryContains(text) => notification.summary.contains(text),
+            NotificationFilterCriteria::Text(query) => history::matches_text(notification, query),
             NotificationFilterCriteria::IsTransient(transient) => notification.transient == *transient,
             NotificationFilterCriteria::TimeRange{start, end} => {
                 let after_start = start.map_or(true, |s| notification.timestamp >= s);
@@ -173,92 +266,219 @@ impl DefaultNotificationService {
         }
     }
 
-    fn publish_event(&self, event: NotificationEventEnum) {
-        if self.event_publisher.send(event.clone()).is_err() { // Clone event for logging if send fails
-            error!("Failed to send NotificationEventEnum: {:?}", event);
+    /// Announces a change of the active focus mode and tells the rules engine about it.
+    async fn apply_focus_change(&self, change: Option<FocusChange>) {
+        let Some(change) = change else { return; };
+        let mode_id = change.active.as_ref().map(|active| active.mode_id.clone());
+        self.rules_engine.set_active_focus_mode(mode_id.clone()).await;
+        if let Some(summary) = change.ended.clone() {
+            info!("Focus mode '{}' ended; {} notifications were held back", summary.mode_id, summary.total);
+            self.publish_event(NotificationEventEnum::FocusModeEnded { summary });
+        }
+        let was_enabled = change.ended.is_some();
+        let is_enabled = change.active.is_some();
+        if let Some(active) = &change.active {
+            info!("Focus mode '{}' active ({:?})", active.mode_id, active.activation);
+        }
+        self.publish_event(NotificationEventEnum::FocusModeChanged { active: change.active });
+        if was_enabled != is_enabled {
+            self.publish_event(NotificationEventEnum::DoNotDisturbModeChanged { dnd_enabled: is_enabled });
         }
     }
-}
 
-#[async_trait]
-impl NotificationService for DefaultNotificationService {
-    async fn post_notification(&self, notification_input: NotificationInput) -> Result<Uuid, NotificationError> {
-        let original_id = notification_input.replaces_id.unwrap_or_else(Uuid::new_v4);
-        let mut notification = Notification {
-            id: original_id, application_name: notification_input.application_name.clone(),
+    fn notification_from_input(notification_input: &NotificationInput) -> Notification {
+        Notification {
+            id: notification_input.replaces_id.unwrap_or_else(Uuid::new_v4), application_name: notification_input.application_name.clone(),
             application_icon: notification_input.application_icon.clone(), summary: notification_input.summary.clone(),
             body: notification_input.body.clone(), actions: notification_input.actions.clone().unwrap_or_default(),
             urgency: notification_input.urgency.unwrap_or_default(), timestamp: Utc::now(),
             is_read: false, is_dismissed: false, transient: notification_input.transient.unwrap_or(false),
             category: notification_input.category.clone(), hints: notification_input.hints.clone().unwrap_or_default(),
-            timeout_ms: notification_input.timeout_ms,
-        };
-
-        let rule_result = self.rules_engine.process_notification(&notification).await.map_err(NotificationError::RuleEngineError)?;
-        
-        match rule_result {
-            RuleProcessingResult::Suppress { rule_id } => {
-                debug!("Notification ID {} suppressed by rule ID {}", notification.id, rule_id);
-                if !notification.transient { self.add_to_history(notification.clone()).await; }
-                self.publish_event(NotificationEventEnum::NotificationSuppressedByRule { 
-                    original_notification_id: notification.id, original_summary: notification.summary.clone(),
-                    app_name: notification.application_name.clone(), rule_id,
-                });
-                return Ok(notification.id);
-            }
-            RuleProcessingResult::Modify(modified_notification) => { notification = modified_notification; }
-            RuleProcessingResult::Allow => {}
+            timeout_ms: notification_input.timeout_ms, image: notification_input.image.clone(),
         }
+    }
 
-        let dnd_is_enabled = *self.dnd_enabled.read().await;
-        let suppressed_by_dnd = dnd_is_enabled && notification.urgency != NotificationUrgency::Critical;
+    /// Shows a notification the rules let through, honouring the focus mode, its channel and
+    /// the popup limit.
+    async fn deliver(&self, notification: Notification) -> Uuid {
+        let mut focus = self.focus.write().await;
+        let held_back = focus.suppresses(&notification);
+        if held_back { focus.record_missed(&notification); }
+        let to_history = held_back && focus.active_mode().map_or(true, |mode| mode.suppressed_to_history);
+        drop(focus);
 
-        if suppressed_by_dnd {
-            debug!("Notification ID {} suppressed by DND mode", notification.id);
+        if to_history {
+            debug!("Notification ID {} held back by the focus mode and sent to history", notification.id);
             if !notification.transient { self.add_to_history(notification.clone()).await; }
             self.publish_event(NotificationEventEnum::NotificationPosted { notification: notification.clone(), suppressed_by_dnd: true });
-            return Ok(notification.id);
+            return notification.id;
+        }
+
+        let mut channel = notification.channel();
+        // Focus silences sound-only notifications too; they are left to the history.
+        if held_back && channel == NotificationChannel::SoundOnly { channel = NotificationChannel::HistoryOnly; }
+        if channel != NotificationChannel::Normal {
+            debug!("Notification ID {} routed to the {} channel", notification.id, channel.as_str());
+            if !notification.transient { self.add_to_history(notification.clone()).await; }
+            self.publish_event(NotificationEventEnum::NotificationRouted { notification: notification.clone(), channel });
+            return notification.id;
         }
 
         let mut active_guard = self.active_notifications.write().await;
+        if let Some(existing) = active_guard.iter_mut().find(|n| n.id == notification.id) {
+            *existing = notification.clone();
+            drop(active_guard);
+            if !notification.transient { self.add_to_history(notification.clone()).await; }
+            self.publish_event(NotificationEventEnum::NotificationUpdated { notification: notification.clone() });
+            info!("Notification ID {} replaced. Summary: {}", notification.id, notification.summary);
+            return notification.id;
+        }
+        let key = grouping::group_key(&notification);
+        let decision = self.digests.write().await.observe(&key, &notification, notification.timestamp);
+        let shows_popup = !held_back && decision == PopupDecision::Show;
+        // Silent notifications do not pop up, so they neither count towards nor are pushed out by
+        // the popup limit.
         let max_popups = *self.max_active_popups_cache.read().await;
-        if max_popups > 0 && active_guard.len() >= max_popups {
-            if let Some(expired_notif) = active_guard.pop_front() {
+        let digested = self.silent_ids.read().await.clone();
+        let popups = active_guard.iter().filter(|n| !digested.contains(&n.id)).count();
+        if shows_popup && max_popups > 0 && popups >= max_popups {
+            let oldest_popup = active_guard.iter().position(|n| !digested.contains(&n.id));
+            if let Some(expired_notif) = oldest_popup.and_then(|idx| active_guard.remove(idx)) {
                 self.publish_event(NotificationEventEnum::NotificationPopupExpired { notification_id: expired_notif.id });
                 if !expired_notif.transient { drop(active_guard); self.add_to_history(expired_notif).await; active_guard = self.active_notifications.write().await; }
             }
         }
+        if !shows_popup { self.silent_ids.write().await.insert(notification.id); }
         active_guard.push_back(notification.clone());
         drop(active_guard);
 
         if !notification.transient { self.add_to_history(notification.clone()).await; }
         
-        self.publish_event(NotificationEventEnum::NotificationPosted { notification: notification.clone(), suppressed_by_dnd: false });
+        match decision {
+            _ if held_back => {
+                debug!("Notification ID {} held back by the focus mode", notification.id);
+                self.publish_event(NotificationEventEnum::NotificationPosted { notification: notification.clone(), suppressed_by_dnd: true });
+            }
+            PopupDecision::Show => {
+                self.publish_event(NotificationEventEnum::NotificationPosted { notification: notification.clone(), suppressed_by_dnd: false });
+            }
+            PopupDecision::Digest(digest) => {
+                debug!("Notification ID {} folded into the digest of group '{}' ({} so far)", notification.id, digest.group_key, digest.count);
+                self.publish_event(NotificationEventEnum::NotificationDigested { notification: notification.clone(), digest });
+            }
+        }
         info!("Notification ID {} posted. Summary: {}", notification.id, notification.summary);
-        Ok(notification.id)
+        notification.id
+    }
+
+    fn publish_event(&self, event: NotificationEventEnum) {
+        if self.event_publisher.send(event.clone()).is_err() { // Clone event for logging if send fails
+            error!("Failed to send NotificationEventEnum: {:?}", event);
+        }
+    }
+}
+
+/// Reads a setting off the runtime's threads, as the settings service reads blockingly; `None`
+/// if it cannot be read.
+async fn read_setting(settings_service: &Arc<dyn GlobalSettingsService>, path: SettingPath) -> Option<JsonValue> {
+    let settings_service = settings_service.clone();
+    let description = path.to_string();
+    match tokio::task::spawn_blocking(move || settings_service.get_setting(&path)).await {
+        Ok(Ok(value)) => Some(value),
+        Ok(Err(e)) => {
+            warn!("Could not read '{}' from global settings: {}", description, e);
+            None
+        }
+        Err(e) => {
+            error!("Reading '{}' from global settings failed: {}", description, e);
+            None
+        }
+    }
+}
+
+#[async_trait]
+impl NotificationService for DefaultNotificationService {
+    async fn post_notification(&self, notification_input: NotificationInput) -> Result<Uuid, NotificationError> {
+        let mut notification = Self::notification_from_input(&notification_input);
+
+        let rule_result = self.rules_engine.process_notification(notification.clone()).await.map_err(NotificationError::RuleEngineError)?;
+        
+        match rule_result {
+            RuleProcessingResult::Suppress { rule_id } => {
+                debug!("Notification ID {} suppressed by rule ID {}", notification.id, rule_id);
+                if !notification.transient { self.add_to_history(notification.clone()).await; }
+                self.publish_event(NotificationEventEnum::NotificationSuppressedByRule { 
+                    original_notification_id: notification.id, original_summary: notification.summary.clone(),
+                    app_name: notification.application_name.clone(), rule_id: rule_id.to_string(),
+                });
+                return Ok(notification.id);
+            }
+            // Rules may have modified the notification, including its group key.
+            RuleProcessingResult::Allow(processed_notification) => { notification = processed_notification; }
+        }
+
+        if let Some(until) = notification.snoozed_until().filter(|until| *until > notification.timestamp) {
+            debug!("Notification ID {} snoozed until {}", notification.id, until);
+            let mut snoozed = self.snoozed.write().await;
+            snoozed.retain(|n| n.id != notification.id);
+            snoozed.push(notification.clone());
+            drop(snoozed);
+            self.publish_event(NotificationEventEnum::NotificationSnoozed { notification: notification.clone(), until });
+            return Ok(notification.id);
+        }
+        Ok(self.deliver(notification).await)
     }
 
     async fn get_notification(&self, id: Uuid) -> Result<Option<Notification>, NotificationError> {
         if let Some(n) = self.active_notifications.read().await.iter().find(|n| n.id == id) { return Ok(Some(n.clone())); }
+        if let Some(n) = self.snoozed.read().await.iter().find(|n| n.id == id) { return Ok(Some(n.clone())); }
         Ok(self.history.read().await.iter().find(|n| n.id == id).cloned())
     }
 
     async fn mark_as_read(&self, id: Uuid) -> Result<(), NotificationError> {
         if let Some(n) = self.active_notifications.write().await.iter_mut().find(|n| n.id == id) { if !n.is_read { n.mark_as_read(); self.publish_event(NotificationEventEnum::NotificationRead { notification_id: id }); } return Ok(()); }
-        if let Some(n) = self.history.write().await.iter_mut().find(|n| n.id == id) { if !n.is_read { n.mark_as_read(); self.publish_event(NotificationEventEnum::NotificationRead { notification_id: id }); } return Ok(()); }
+        let mut history_guard = self.history.write().await;
+        if let Some(n) = history_guard.iter_mut().find(|n| n.id == id) {
+            if !n.is_read {
+                n.mark_as_read();
+                let updated = n.clone();
+                drop(history_guard);
+                self.persist(&[updated], &[]).await;
+                self.publish_event(NotificationEventEnum::NotificationRead { notification_id: id });
+            }
+            return Ok(());
+        }
         Err(NotificationError::NotFound(id))
     }
 
     async fn dismiss_notification(&self, id: Uuid, reason: DismissReason) -> Result<(), NotificationError> {
         if let Some(idx) = self.active_notifications.read().await.iter().position(|n| n.id == id) {
             let mut notification = self.active_notifications.write().await.remove(idx).unwrap();
+            self.silent_ids.write().await.remove(&id);
             notification.dismiss();
             if !notification.transient { self.add_to_history(notification).await; }
             self.publish_event(NotificationEventEnum::NotificationDismissed { notification_id: id, reason });
             return Ok(());
         }
-        if let Some(n) = self.history.write().await.iter_mut().find(|n| n.id == id) {
-            if !n.is_dismissed { n.dismiss(); self.publish_event(NotificationEventEnum::NotificationDismissed { notification_id: id, reason }); }
+        let mut snoozed = self.snoozed.write().await;
+        if let Some(idx) = snoozed.iter().position(|n| n.id == id) {
+            // Never shown yet, so its display timeout has not started.
+            if reason == DismissReason::Expired { return Ok(()); }
+            snoozed.remove(idx);
+            drop(snoozed);
+            self.publish_event(NotificationEventEnum::NotificationDismissed { notification_id: id, reason });
+            return Ok(());
+        }
+        drop(snoozed);
+        let mut history_guard = self.history.write().await;
+        if let Some(n) = history_guard.iter_mut().find(|n| n.id == id) {
+            if !n.is_dismissed {
+                n.dismiss();
+                let updated = n.clone();
+                drop(history_guard);
+                self.persist(&[updated], &[]).await;
+                self.publish_event(NotificationEventEnum::NotificationDismissed { notification_id: id, reason });
+            }
             return Ok(());
         }
         Err(NotificationError::NotFound(id))
@@ -270,12 +490,72 @@ impl NotificationService for DefaultNotificationService {
 
     async fn get_notification_history(&self, limit: Option<usize>, offset: Option<usize>, filter: Option<&NotificationFilterCriteria>, sort: Option<NotificationSortOrder>) -> Result<Vec<Notification>, NotificationError> {
         let processed = Self::apply_filters_and_sort(self.history.read().await.iter().cloned().collect(), filter, sort);
-        let start = offset.unwrap_or(0); if start >= processed.len() { return Ok(Vec::new()); }
-        let end = limit.map_or(processed.len(), |l| (start + l).min(processed.len()));
-        Ok(processed.into_iter().skip(start).take(end - start).collect())
+        Ok(Self::page(processed, limit, offset))
+    }
+
+    async fn clear_history(&self) -> Result<(), NotificationError> {
+        self.history.write().await.clear();
+        if let Some(store) = self.history_store.read().await.clone() { store.clear().await?; }
+        self.publish_event(NotificationEventEnum::NotificationHistoryCleared);
+        Ok(())
+    }
+
+    async fn search_history(&self, query: &str, limit: Option<usize>, offset: Option<usize>) -> Result<Vec<Notification>, NotificationError> {
+        let terms = history::search_terms(query);
+        let store = self.history_store.read().await.clone();
+        if let (Some(store), false) = (store, terms.is_empty()) {
+            let wanted = offset.unwrap_or(0).saturating_add(limit.unwrap_or(usize::MAX));
+            match store.search(&terms, wanted).await {
+                Ok(ids) => {
+                    let history_guard = self.history.read().await;
+                    let ranked = ids.iter().filter_map(|id| history_guard.iter().find(|n| n.id == *id).cloned()).collect();
+                    return Ok(Self::page(ranked, limit, offset));
+                }
+                Err(e) => warn!("History store search for '{}' failed, searching in memory: {}", query, e),
+            }
+        }
+        let filter = NotificationFilterCriteria::Text(query.to_string());
+        self.get_notification_history(limit, offset, Some(&filter), Some(NotificationSortOrder::TimestampDescending)).await
+    }
+
+    async fn export_history(&self, filter: Option<&NotificationFilterCriteria>) -> Result<HistoryExport, NotificationError> {
+        let notifications = self.get_notification_history(None, None, filter, Some(NotificationSortOrder::TimestampAscending)).await?;
+        Ok(HistoryExport { version: HISTORY_EXPORT_VERSION, exported_at: Utc::now(), notifications })
     }
 
-    async fn clear_history(&self) -> Result<(), NotificationError> { self.history.write().await.clear(); self.publish_event(NotificationEventEnum::NotificationHistoryCleared); Ok(()) }
+    async fn get_history_retention(&self) -> Result<HistoryRetention, NotificationError> {
+        Ok(self.retention.read().await.clone())
+    }
+
+    async fn set_history_retention(&self, retention: HistoryRetention) -> Result<(), NotificationError> {
+        let expired = Self::prune(&mut *self.history.write().await, &retention);
+        *self.retention.write().await = retention.clone();
+        info!("History retention updated, {} entries dropped", expired.len());
+        self.persist(&[], &expired).await;
+        self.save_setting(NotificationSettingPath::HistoryRetention, &Some(retention)).await;
+        Ok(())
+    }
+
+    async fn attach_history_store(&self, store: Arc<dyn NotificationHistoryStore>) -> Result<usize, NotificationError> {
+        let stored = store.load().await?;
+        let retention = self.retention.read().await.clone();
+        let mut history_guard = self.history.write().await;
+        // What was recorded since startup is newer than anything stored.
+        let recorded: Vec<Notification> = history_guard.drain(..).collect();
+        let mut merged: VecDeque<Notification> = stored.into_iter().filter(|n| !recorded.iter().any(|r| r.id == n.id)).collect();
+        merged.extend(recorded.iter().cloned());
+        let expired = Self::prune(&mut merged, &retention);
+        let count = merged.len();
+        *history_guard = merged;
+        *self.history_store.write().await = Some(store.clone());
+        drop(history_guard);
+
+        let recorded: Vec<Notification> = recorded.into_iter().filter(|n| !expired.contains(&n.id)).collect();
+        store.upsert(&recorded).await?;
+        store.remove(&expired).await?;
+        info!("History store attached: {} entries, {} expired", count, expired.len());
+        Ok(count)
+    }
     async fn clear_all_for_app(&self, app_id: &ApplicationId, reason: DismissReason) -> Result<usize, NotificationError> {
         let mut dismissed_count = 0;
         let mut active_guard = self.active_notifications.write().await;
@@ -283,64 +563,176 @@ impl NotificationService for DefaultNotificationService {
         while i < active_guard.len() {
             if active_guard[i].application_name == app_id.as_str() {
                 let mut notif = active_guard.remove(i).unwrap(); notif.dismiss(); let notif_id = notif.id;
+                self.silent_ids.write().await.remove(&notif_id);
                 if !notif.transient { drop(active_guard); self.add_to_history(notif).await; active_guard = self.active_notifications.write().await; } // Re-acquire
                 self.publish_event(NotificationEventEnum::NotificationDismissed { notification_id: notif_id, reason }); dismissed_count += 1;
             } else { i += 1; }
         }
         drop(active_guard);
         let mut history_guard = self.history.write().await;
+        let mut updated = Vec::new();
         for notif in history_guard.iter_mut() {
-            if notif.application_name == app_id.as_str() && !notif.is_dismissed { notif.dismiss(); dismissed_count += 1; /* No event for already historical items */ }
+            if notif.application_name == app_id.as_str() && !notif.is_dismissed { notif.dismiss(); dismissed_count += 1; updated.push(notif.clone()); /* No event for already historical items */ }
         }
+        drop(history_guard);
+        self.persist(&updated, &[]).await;
         Ok(dismissed_count)
     }
 
     async fn set_do_not_disturb(&self, enabled: bool) -> Result<(), NotificationError> {
-        let mut dnd = self.dnd_enabled.write().await; if *dnd != enabled { *dnd = enabled; self.publish_event(NotificationEventEnum::DoNotDisturbModeChanged { dnd_enabled: enabled }); } Ok(())
+        self.activate_focus_mode(enabled.then_some(DO_NOT_DISTURB_MODE_ID)).await
+    }
+    async fn is_do_not_disturb_enabled(&self) -> Result<bool, NotificationError> { Ok(self.focus.read().await.active().is_some()) }
+
+    async fn get_focus_modes(&self) -> Result<Vec<FocusMode>, NotificationError> { Ok(self.focus.read().await.modes().to_vec()) }
+
+    async fn set_focus_modes(&self, modes: Vec<FocusMode>) -> Result<(), NotificationError> {
+        let mut ids = HashSet::new();
+        if let Some(mode) = modes.iter().find(|mode| mode.id.trim().is_empty() || !ids.insert(mode.id.as_str())) {
+            return Err(NotificationError::InvalidInputData { field: "focus_modes".to_string(), reason: format!("focus mode ids must be unique and non-empty, got '{}'", mode.id) });
+        }
+        let change = self.focus.write().await.set_modes(modes.clone(), &Local::now());
+        self.apply_focus_change(change).await;
+        self.save_setting(NotificationSettingPath::FocusModes, &Some(modes)).await;
+        Ok(())
+    }
+
+    async fn activate_focus_mode(&self, mode_id: Option<&str>) -> Result<(), NotificationError> {
+        let mut focus = self.focus.write().await;
+        if let Some(id) = mode_id.filter(|id| focus.mode(id).is_none()) {
+            return Err(NotificationError::InvalidInputData { field: "mode_id".to_string(), reason: format!("no focus mode '{}'", id) });
+        }
+        let change = focus.activate(mode_id, &Local::now());
+        drop(focus);
+        self.apply_focus_change(change).await;
+        Ok(())
+    }
+
+    async fn get_active_focus(&self) -> Result<Option<ActiveFocus>, NotificationError> { Ok(self.focus.read().await.active().cloned()) }
+
+    async fn set_focus_trigger(&self, trigger: FocusTrigger, active: bool) -> Result<(), NotificationError> {
+        let change = self.focus.write().await.set_trigger(trigger, active, &Local::now());
+        self.apply_focus_change(change).await;
+        Ok(())
+    }
+
+    async fn refresh_focus(&self) -> Result<(), NotificationError> {
+        let change = self.focus.write().await.refresh(&Local::now());
+        self.apply_focus_change(change).await;
+        Ok(())
     }
-    async fn is_do_not_disturb_enabled(&self) -> Result<bool, NotificationError> { Ok(*self.dnd_enabled.read().await) }
 
-    async fn invoke_action(&self, id: Uuid, key: &str) -> Result<(), NotificationError> {
+    async fn invoke_action(&self, id: Uuid, key: &str, activation_token: Option<String>) -> Result<(), NotificationError> {
         let notif = self.get_notification(id).await?.ok_or(NotificationError::NotFound(id))?;
-        if notif.actions.iter().any(|a| a.key == key) { self.publish_event(NotificationEventEnum::NotificationActionInvoked { notification_id: id, action_key: key.to_string() }); Ok(()) }
+        if notif.actions.iter().any(|a| a.key == key) { self.publish_event(NotificationEventEnum::NotificationActionInvoked { notification_id: id, action_key: key.to_string(), activation_token }); Ok(()) }
         else { Err(NotificationError::ActionNotFound { notification_id: id, action_key: key.to_string() }) }
     }
 
+    async fn reply(&self, id: Uuid, text: &str) -> Result<(), NotificationError> {
+        let notif = self.get_notification(id).await?.ok_or(NotificationError::NotFound(id))?;
+        if notif.reply_action().is_none() {
+            return Err(NotificationError::ActionNotFound { notification_id: id, action_key: INLINE_REPLY_ACTION_KEY.to_string() });
+        }
+        if text.trim().is_empty() {
+            return Err(NotificationError::InvalidInputData { field: "reply".to_string(), reason: "Cannot be empty".to_string() });
+        }
+        self.publish_event(NotificationEventEnum::NotificationReplied { notification_id: id, text: text.to_string() });
+        Ok(())
+    }
+
     async fn get_stats(&self) -> Result<NotificationStats, NotificationError> {
         let active = self.active_notifications.read().await;
         Ok(NotificationStats { num_active: active.len(), num_history: self.history.read().await.len(), num_unread_active: active.iter().filter(|n| !n.is_read).count() })
     }
+
+    async fn get_notification_groups(&self, filter: Option<&NotificationFilterCriteria>) -> Result<Vec<NotificationGroup>, NotificationError> {
+        let active = Self::apply_filters_and_sort(self.active_notifications.read().await.iter().cloned().collect(), filter, None);
+        Ok(grouping::group_notifications(active))
+    }
+
+    async fn dismiss_group(&self, group_key: &str, reason: DismissReason) -> Result<usize, NotificationError> {
+        let ids: Vec<Uuid> = self.active_notifications.read().await.iter()
+            .filter(|n| grouping::group_key(n) == group_key).map(|n| n.id).collect();
+        for id in &ids {
+            self.dismiss_notification(*id, reason).await?;
+        }
+        self.digests.write().await.reset(group_key);
+        debug!("Dismissed {} notifications of group '{}'", ids.len(), group_key);
+        Ok(ids.len())
+    }
+
+    async fn get_rules(&self) -> Result<NotificationRuleSet, NotificationError> { Ok(self.rules_engine.get_rules().await?) }
+
+    async fn update_rules(&self, rules: NotificationRuleSet) -> Result<(), NotificationError> { Ok(self.rules_engine.update_rules(rules).await?) }
+
+    fn validate_rules(&self, rules: &NotificationRuleSet) -> Vec<RuleValidationIssue> { self.rules_engine.validate_rules(rules) }
+
+    async fn simulate_rules(&self, sample: RuleSimulationSample, rules: Option<NotificationRuleSet>) -> Result<RuleSimulation, NotificationError> {
+        let notification = match sample {
+            RuleSimulationSample::Input(input) => Self::notification_from_input(&input),
+            RuleSimulationSample::History(id) => {
+                let stored = self.get_notification(id).await?.ok_or(NotificationError::NotFound(id))?;
+                Notification { is_read: false, is_dismissed: false, ..stored }
+            }
+        };
+        Ok(self.rules_engine.simulate(notification, rules).await?)
+    }
+
+    async fn update_rule_context(&self, change: RuleContextChange) -> Result<(), NotificationError> {
+        self.rules_engine.update_context(change).await;
+        Ok(())
+    }
+
+    async fn release_snoozed(&self) -> Result<usize, NotificationError> {
+        let now = Utc::now();
+        let due: Vec<Notification> = {
+            let mut snoozed = self.snoozed.write().await;
+            let (due, waiting) = snoozed.drain(..).partition(|n| n.snoozed_until().map_or(true, |until| until <= now));
+            *snoozed = waiting;
+            due
+        };
+        let count = due.len();
+        for mut notification in due {
+            debug!("Snooze of notification ID {} is over", notification.id);
+            notification.hints.remove(SNOOZE_UNTIL_HINT);
+            self.deliver(notification).await;
+        }
+        Ok(count)
+    }
+
     fn subscribe_to_notification_events(&self) -> broadcast::Receiver<NotificationEventEnum> { self.event_publisher.subscribe() }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use super::super::types::NotificationActionType;
     use crate::notifications_rules::{MockNotificationRulesEngine, RuleProcessingResult};
     use crate::global_settings::{MockGlobalSettingsService, SettingPathParseError}; // Assuming this mock exists
+    use crate::global_settings::{DefaultGlobalSettingsService, GlobalDesktopSettings, GlobalSettingsError, SettingsPersistenceProvider};
     use tokio::sync::broadcast::error::RecvError;
     use crate::user_centric_services::events::NotificationEventEnum as Event;
 
-    // Helper for SettingPath in tests, as it's not fully defined for notifications yet.
-    // This mirrors the unsafe helper in GlobalSettingsService tests if that was introduced.
-    // If SettingPath has a proper variant for notifications, this won't be needed.
-    impl SettingPath {
-        fn from_str_unsafe_for_testing(s: &str) -> Self {
-            // This is a simplified parser for test purposes only.
-            // It does not represent the full complexity of SettingPath.
-            // It assumes a simple dot-separated path.
-            // A real SettingPath would have proper variants.
-            // Example: "notifications.max_active_popups"
-            // For now, we'll just return a placeholder that might not match real paths.
-            // This is a known limitation due to SettingPath not having notification-specific variants.
-            warn!("Using unsafe SettingPath::from_str_unsafe_for_testing. This is for testing only.");
-            // In a real scenario, SettingPath would have a variant like:
-            // SettingPath::NotificationsCore(NotificationsCorePath::MaxActivePopups)
-            // For now, we'll use a root path as a placeholder.
-            SettingPath::Root 
+    /// Global settings kept in memory, for tests that need real settings values.
+    struct MemorySettings(std::sync::Mutex<GlobalDesktopSettings>);
+
+    #[async_trait]
+    impl SettingsPersistenceProvider for MemorySettings {
+        async fn load_global_settings(&self) -> Result<GlobalDesktopSettings, GlobalSettingsError> {
+            Ok(self.0.lock().unwrap().clone())
+        }
+
+        async fn save_global_settings(&self, settings: &GlobalDesktopSettings) -> Result<(), GlobalSettingsError> {
+            *self.0.lock().unwrap() = settings.clone();
+            Ok(())
         }
     }
 
+    async fn settings_with(settings: GlobalDesktopSettings) -> Arc<DefaultGlobalSettingsService> {
+        let service = Arc::new(DefaultGlobalSettingsService::new(Arc::new(MemorySettings(std::sync::Mutex::new(settings))), 16));
+        service.load_settings().await.unwrap();
+        service
+    }
 
     fn create_test_notification_input(summary: &str) -> NotificationInput {
         NotificationInput { application_name: "TestApp".to_string(), summary: summary.to_string(), ..Default::default() }
@@ -353,7 +745,7 @@ mod tests {
     async fn test_post_notification_simple_flow() {
         let rules_engine = Arc::new(MockNotificationRulesEngine::new());
         let settings_service = Arc::new(MockGlobalSettingsService::new());
-        rules_engine.expect_process_notification().times(1).returning(|_| Ok(RuleProcessingResult::Allow));
+        rules_engine.expect_process_notification().times(1).returning(|n| Ok(RuleProcessingResult::Allow(n)));
         let service = DefaultNotificationService::new(rules_engine, settings_service, 5).await.unwrap();
         let mut rx = service.subscribe_to_notification_events();
 
@@ -367,7 +759,8 @@ mod tests {
     async fn test_post_notification_suppressed_by_rule() {
         let rules_engine = Arc::new(MockNotificationRulesEngine::new());
         let settings_service = Arc::new(MockGlobalSettingsService::new());
-        rules_engine.expect_process_notification().times(1).returning(|_| Ok(RuleProcessingResult::Suppress { rule_id: "rule1".to_string() }));
+        let suppressing_rule = Uuid::new_v4();
+        rules_engine.expect_process_notification().times(1).returning(move |_| Ok(RuleProcessingResult::Suppress { rule_id: suppressing_rule }));
         let service = DefaultNotificationService::new(rules_engine, settings_service, 5).await.unwrap();
         let mut rx = service.subscribe_to_notification_events();
 
@@ -375,14 +768,15 @@ mod tests {
         assert!(result.is_ok());
         assert!(service.get_active_notifications(None, None).await.unwrap().is_empty());
         assert_eq!(service.get_notification_history(None, None, None, None).await.unwrap().len(), 1);
-        match rx.try_recv() { Ok(Event::NotificationSuppressedByRule { rule_id, .. }) => assert_eq!(rule_id, "rule1"), e => panic!("{:?}", e) }
+        match rx.try_recv() { Ok(Event::NotificationSuppressedByRule { rule_id, .. }) => assert_eq!(rule_id, suppressing_rule.to_string()), e => panic!("{:?}", e) }
     }
 
     #[tokio::test]
     async fn test_post_notification_dnd_suppression() {
         let rules_engine = Arc::new(MockNotificationRulesEngine::new());
         let settings_service = Arc::new(MockGlobalSettingsService::new());
-        rules_engine.expect_process_notification().returning(|_| Ok(RuleProcessingResult::Allow));
+        rules_engine.expect_process_notification().returning(|n| Ok(RuleProcessingResult::Allow(n)));
+        rules_engine.expect_set_active_focus_mode().returning(|_| ());
         let service = DefaultNotificationService::new(rules_engine, settings_service, 5).await.unwrap();
         service.set_do_not_disturb(true).await.unwrap();
         let mut rx = service.subscribe_to_notification_events(); drain_events(&mut rx).await;
@@ -396,7 +790,7 @@ mod tests {
     async fn test_dismiss_notification() {
         let rules_engine = Arc::new(MockNotificationRulesEngine::new());
         let settings_service = Arc::new(MockGlobalSettingsService::new());
-        rules_engine.expect_process_notification().returning(|_| Ok(RuleProcessingResult::Allow));
+        rules_engine.expect_process_notification().returning(|n| Ok(RuleProcessingResult::Allow(n)));
         let service = DefaultNotificationService::new(rules_engine, settings_service, 5).await.unwrap();
         let mut rx = service.subscribe_to_notification_events();
 
@@ -407,4 +801,288 @@ mod tests {
         assert!(service.get_active_notifications(None, None).await.unwrap().is_empty());
         match rx.try_recv() { Ok(Event::NotificationDismissed { notification_id, reason, .. }) => { assert_eq!(notification_id, notif_id); assert_eq!(reason, DismissReason::ByUser); }, e => panic!("{:?}", e) }
     }
+
+    #[tokio::test]
+    async fn test_replacement_updates_in_place() {
+        let rules_engine = Arc::new(MockNotificationRulesEngine::new());
+        let settings_service = Arc::new(MockGlobalSettingsService::new());
+        rules_engine.expect_process_notification().returning(|n| Ok(RuleProcessingResult::Allow(n)));
+        let service = DefaultNotificationService::new(rules_engine, settings_service, 5).await.unwrap();
+        let mut rx = service.subscribe_to_notification_events();
+
+        let notif_id = service.post_notification(create_test_notification_input("Downloading 10%")).await.unwrap();
+        drain_events(&mut rx).await;
+        let replacement = NotificationInput { replaces_id: Some(notif_id), ..create_test_notification_input("Downloading 50%") };
+        assert_eq!(service.post_notification(replacement).await.unwrap(), notif_id);
+
+        let active = service.get_active_notifications(None, None).await.unwrap();
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0].summary, "Downloading 50%");
+        assert_eq!(service.get_notification_history(None, None, None, None).await.unwrap().len(), 1);
+        match rx.try_recv() { Ok(Event::NotificationUpdated { notification }) => assert_eq!(notification.id, notif_id), e => panic!("{:?}", e) }
+    }
+
+    #[tokio::test]
+    async fn test_groups_digest_and_dismiss_together() {
+        let rules_engine = Arc::new(MockNotificationRulesEngine::new());
+        let settings_service = Arc::new(MockGlobalSettingsService::new());
+        rules_engine.expect_process_notification().returning(|n| Ok(RuleProcessingResult::Allow(n)));
+        let service = DefaultNotificationService::new(rules_engine, settings_service, 64).await.unwrap();
+        let mut rx = service.subscribe_to_notification_events();
+
+        let chat = |summary: &str| NotificationInput {
+            application_name: "Chat".to_string(), summary: summary.to_string(),
+            category: Some("im.received".to_string()), ..Default::default()
+        };
+        for _ in 0..30 { service.post_notification(chat("Alice")).await.unwrap(); }
+        service.post_notification(create_test_notification_input("Other")).await.unwrap();
+
+        // A burst neither evicts other notifications nor pops up past the threshold.
+        let (mut posted, mut digested) = (0, 0);
+        while let Ok(event) = rx.try_recv() {
+            match event { Event::NotificationPosted { .. } => posted += 1, Event::NotificationDigested { digest, .. } => { digested += 1; assert_eq!(digest.group_key, "Chat/im/Alice"); } _ => {} }
+        }
+        assert_eq!((posted, digested), (grouping::DEFAULT_DIGEST_BURST_THRESHOLD + 1, 30 - grouping::DEFAULT_DIGEST_BURST_THRESHOLD));
+
+        let groups = service.get_notification_groups(None).await.unwrap();
+        assert_eq!(groups.len(), 2);
+        let alice = groups.iter().find(|g| g.key == "Chat/im/Alice").unwrap();
+        assert_eq!((alice.len(), alice.title.as_str()), (30, "Alice"));
+
+        assert_eq!(service.dismiss_group("Chat/im/Alice", DismissReason::ByUser).await.unwrap(), 30);
+        assert_eq!(service.get_active_notifications(None, None).await.unwrap().len(), 1);
+        assert_eq!(service.dismiss_group("Chat/im/Alice", DismissReason::ByUser).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_digest_policy_comes_from_settings() {
+        let rules_engine = Arc::new(MockNotificationRulesEngine::new());
+        rules_engine.expect_process_notification().returning(|n| Ok(RuleProcessingResult::Allow(n)));
+        let mut settings = GlobalDesktopSettings::default();
+        settings.notifications.digest_burst_threshold = 1;
+        let service = DefaultNotificationService::new(rules_engine, settings_with(settings).await, 64).await.unwrap();
+        let mut rx = service.subscribe_to_notification_events();
+
+        for _ in 0..3 {
+            service.post_notification(NotificationInput { category: Some("im.received".to_string()), ..create_test_notification_input("Alice") }).await.unwrap();
+        }
+        let (mut posted, mut digested) = (0, 0);
+        while let Ok(event) = rx.try_recv() {
+            match event { Event::NotificationPosted { .. } => posted += 1, Event::NotificationDigested { .. } => digested += 1, _ => {} }
+        }
+        assert_eq!((posted, digested), (1, 2));
+    }
+
+    #[tokio::test]
+    async fn test_history_retention_comes_from_and_goes_to_settings() {
+        let rules_engine = Arc::new(MockNotificationRulesEngine::new());
+        let mut settings = GlobalDesktopSettings::default();
+        settings.notifications.max_history_items = 10;
+        settings.notifications.history_retention = Some(HistoryRetention { max_age_days: None, max_items: 20, ..Default::default() });
+        let settings = settings_with(settings).await;
+        let service = DefaultNotificationService::new(rules_engine, settings.clone(), 16).await.unwrap();
+        assert_eq!(service.get_history_retention().await.unwrap().max_items, 20);
+
+        let retention = HistoryRetention { max_age_days: Some(7), max_items: 50, ..Default::default() };
+        service.set_history_retention(retention.clone()).await.unwrap();
+        let path = SettingPath::Notifications(NotificationSettingPath::HistoryRetention);
+        let stored = tokio::task::spawn_blocking(move || settings.get_setting(&path)).await.unwrap().unwrap();
+        assert_eq!(serde_json::from_value::<Option<HistoryRetention>>(stored).unwrap(), Some(retention));
+    }
+
+    #[tokio::test]
+    async fn test_focus_modes_come_from_and_go_to_settings() {
+        let rules_engine = Arc::new(MockNotificationRulesEngine::new());
+        rules_engine.expect_set_active_focus_mode().returning(|_| ());
+        let mut settings = GlobalDesktopSettings::default();
+        settings.notifications.focus_modes = Some(vec![FocusMode::new("sleep", "Sleep")]);
+        let settings = settings_with(settings).await;
+        let service = DefaultNotificationService::new(rules_engine, settings.clone(), 16).await.unwrap();
+        let ids = |modes: Vec<FocusMode>| modes.into_iter().map(|mode| mode.id).collect::<Vec<_>>();
+        assert!(ids(service.get_focus_modes().await.unwrap()).contains(&"sleep".to_string()));
+
+        service.set_focus_modes(vec![FocusMode::new("meeting", "Meeting")]).await.unwrap();
+        let path = SettingPath::Notifications(NotificationSettingPath::FocusModes);
+        let stored = tokio::task::spawn_blocking(move || settings.get_setting(&path)).await.unwrap().unwrap();
+        let stored: Option<Vec<FocusMode>> = serde_json::from_value(stored).unwrap();
+        assert_eq!(ids(stored.unwrap()), ["meeting"]);
+    }
+
+    #[tokio::test]
+    async fn test_focus_mode_holds_back_and_summarizes() {
+        let rules_engine = Arc::new(MockNotificationRulesEngine::new());
+        let settings_service = Arc::new(MockGlobalSettingsService::new());
+        rules_engine.expect_process_notification().returning(|n| Ok(RuleProcessingResult::Allow(n)));
+        rules_engine.expect_set_active_focus_mode().returning(|_| ());
+        let service = DefaultNotificationService::new(rules_engine, settings_service, 64).await.unwrap();
+        let mut meeting = FocusMode::new("meeting", "Meeting");
+        meeting.allowed_apps.push("Calendar".to_string());
+        service.set_focus_modes(vec![meeting]).await.unwrap();
+        assert!(service.activate_focus_mode(Some("unknown")).await.is_err());
+        service.activate_focus_mode(Some("meeting")).await.unwrap();
+        assert!(service.is_do_not_disturb_enabled().await.unwrap());
+        let mut rx = service.subscribe_to_notification_events();
+
+        service.post_notification(create_test_notification_input("Held back")).await.unwrap();
+        service.post_notification(NotificationInput { application_name: "Calendar".to_string(), ..create_test_notification_input("Standup") }).await.unwrap();
+        match rx.try_recv() { Ok(Event::NotificationPosted { suppressed_by_dnd, .. }) => assert!(suppressed_by_dnd), e => panic!("{:?}", e) }
+        match rx.try_recv() { Ok(Event::NotificationPosted { suppressed_by_dnd, .. }) => assert!(!suppressed_by_dnd), e => panic!("{:?}", e) }
+        // The meeting mode keeps held-back notifications in the notification center.
+        assert_eq!(service.get_active_notifications(None, None).await.unwrap().len(), 2);
+
+        service.activate_focus_mode(None).await.unwrap();
+        match rx.try_recv() { Ok(Event::FocusModeEnded { summary }) => { assert_eq!(summary.total, 1); assert_eq!(summary.by_app.get("TestApp"), Some(&1)); }, e => panic!("{:?}", e) }
+        match rx.try_recv() { Ok(Event::FocusModeChanged { active }) => assert!(active.is_none()), e => panic!("{:?}", e) }
+        match rx.try_recv() { Ok(Event::DoNotDisturbModeChanged { dnd_enabled }) => assert!(!dnd_enabled), e => panic!("{:?}", e) }
+        assert!(service.get_focus_modes().await.unwrap().iter().any(|mode| mode.id == DO_NOT_DISTURB_MODE_ID));
+    }
+
+    #[tokio::test]
+    async fn test_simulate_rules_replays_history_without_posting() {
+        use crate::notifications_rules::SimulationOutcome;
+        let rules_engine = Arc::new(MockNotificationRulesEngine::new());
+        let settings_service = Arc::new(MockGlobalSettingsService::new());
+        rules_engine.expect_process_notification().times(1).returning(|n| Ok(RuleProcessingResult::Allow(n)));
+        rules_engine.expect_simulate().times(1).returning(|n, rules| {
+            assert!(rules.is_none());
+            Ok(RuleSimulation { input: n.clone(), rules: Vec::new(), outcome: SimulationOutcome::Allow { notification: Box::new(n) } })
+        });
+        let service = DefaultNotificationService::new(rules_engine, settings_service, 5).await.unwrap();
+        let id = service.post_notification(create_test_notification_input("Replay me")).await.unwrap();
+        service.mark_as_read(id).await.unwrap();
+        let mut rx = service.subscribe_to_notification_events();
+
+        let simulation = service.simulate_rules(RuleSimulationSample::History(id), None).await.unwrap();
+        assert_eq!(simulation.input.id, id);
+        assert_eq!(simulation.input.summary, "Replay me");
+        assert!(!simulation.input.is_read);
+        assert!(rx.try_recv().is_err());
+        assert_eq!(service.get_active_notifications(None, None).await.unwrap().len(), 1);
+        assert!(matches!(service.simulate_rules(RuleSimulationSample::History(Uuid::new_v4()), None).await, Err(NotificationError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_snoozed_and_routed_notifications() {
+        use super::super::types::CHANNEL_HINT;
+        let rules_engine = Arc::new(MockNotificationRulesEngine::new());
+        let settings_service = Arc::new(MockGlobalSettingsService::new());
+        rules_engine.expect_process_notification().returning(|mut n| {
+            match n.summary.as_str() {
+                "Later" => { n.hints.insert(SNOOZE_UNTIL_HINT.to_string(), JsonValue::String((n.timestamp + chrono::Duration::hours(1)).to_rfc3339())); }
+                "Quiet" => { n.hints.insert(CHANNEL_HINT.to_string(), JsonValue::String("history-only".to_string())); }
+                "Chime" => { n.hints.insert(CHANNEL_HINT.to_string(), JsonValue::String("sound-only".to_string())); }
+                _ => {}
+            }
+            Ok(RuleProcessingResult::Allow(n))
+        });
+        rules_engine.expect_update_context().times(1).withf(|change| *change == RuleContextChange::Fullscreen(true)).returning(|_| ());
+        rules_engine.expect_set_active_focus_mode().returning(|_| ());
+        let service = DefaultNotificationService::new(rules_engine, settings_service, 16).await.unwrap();
+        let mut rx = service.subscribe_to_notification_events();
+
+        let later = service.post_notification(create_test_notification_input("Later")).await.unwrap();
+        let quiet = service.post_notification(create_test_notification_input("Quiet")).await.unwrap();
+        match rx.try_recv() { Ok(Event::NotificationSnoozed { notification, until }) => { assert_eq!(notification.id, later); assert!(until > notification.timestamp); } e => panic!("{:?}", e) }
+        match rx.try_recv() { Ok(Event::NotificationRouted { notification, channel }) => { assert_eq!(notification.id, quiet); assert_eq!(channel, NotificationChannel::HistoryOnly); } e => panic!("{:?}", e) }
+        assert!(service.get_active_notifications(None, None).await.unwrap().is_empty());
+        assert_eq!(service.get_notification_history(None, None, None, None).await.unwrap().len(), 1);
+        assert!(service.get_notification(later).await.unwrap().is_some());
+        // Not due for another hour.
+        assert_eq!(service.release_snoozed().await.unwrap(), 0);
+        service.dismiss_notification(later, DismissReason::Expired).await.unwrap();
+        assert!(service.get_notification(later).await.unwrap().is_some());
+        service.dismiss_notification(later, DismissReason::ByUser).await.unwrap();
+        assert!(service.get_notification(later).await.unwrap().is_none());
+
+        service.update_rule_context(RuleContextChange::Fullscreen(true)).await.unwrap();
+
+        // A sound-only notification stays silent while focus holds it back.
+        service.post_notification(create_test_notification_input("Chime")).await.unwrap();
+        match rx.try_recv() { Ok(Event::NotificationRouted { channel, .. }) => assert_eq!(channel, NotificationChannel::SoundOnly), e => panic!("{:?}", e) }
+        service.set_focus_modes(vec![FocusMode::new("meeting", "Meeting")]).await.unwrap();
+        service.activate_focus_mode(Some("meeting")).await.unwrap();
+        service.post_notification(create_test_notification_input("Chime")).await.unwrap();
+        let routed = std::iter::from_fn(|| rx.try_recv().ok()).find_map(|event| match event { Event::NotificationRouted { channel, .. } => Some(channel), _ => None });
+        assert_eq!(routed, Some(NotificationChannel::HistoryOnly));
+    }
+
+    /// Keeps stored entries in a map; searches match like the in-memory history does.
+    #[derive(Default)]
+    struct MemoryHistoryStore {
+        entries: std::sync::Mutex<Vec<Notification>>,
+    }
+
+    #[async_trait]
+    impl NotificationHistoryStore for MemoryHistoryStore {
+        async fn load(&self) -> Result<Vec<Notification>, NotificationError> { Ok(self.entries.lock().unwrap().clone()) }
+        async fn upsert(&self, notifications: &[Notification]) -> Result<(), NotificationError> {
+            let mut entries = self.entries.lock().unwrap();
+            for n in notifications { entries.retain(|e| e.id != n.id); entries.push(n.clone()); }
+            Ok(())
+        }
+        async fn remove(&self, ids: &[Uuid]) -> Result<(), NotificationError> { self.entries.lock().unwrap().retain(|e| !ids.contains(&e.id)); Ok(()) }
+        async fn clear(&self) -> Result<(), NotificationError> { self.entries.lock().unwrap().clear(); Ok(()) }
+        async fn search(&self, terms: &[String], limit: usize) -> Result<Vec<Uuid>, NotificationError> {
+            let query = terms.join(" ");
+            Ok(self.entries.lock().unwrap().iter().rev().filter(|n| history::matches_text(n, &query)).map(|n| n.id).take(limit).collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_history_store_retention_search_and_export() {
+        use super::super::history::AppRetention;
+        let rules_engine = Arc::new(MockNotificationRulesEngine::new());
+        let settings_service = Arc::new(MockGlobalSettingsService::new());
+        rules_engine.expect_process_notification().returning(|n| Ok(RuleProcessingResult::Allow(n)));
+        let service = DefaultNotificationService::new(rules_engine, settings_service, 16).await.unwrap();
+
+        let stored = Notification::new("Mail".to_string(), "Yesterday's invoice".to_string(), NotificationUrgency::Normal);
+        let store = Arc::new(MemoryHistoryStore::default());
+        store.upsert(&[stored.clone()]).await.unwrap();
+        let before = service.post_notification(create_test_notification_input("Posted before")).await.unwrap();
+        assert_eq!(service.attach_history_store(store.clone()).await.unwrap(), 2);
+        assert_eq!(store.entries.lock().unwrap().len(), 2);
+
+        let code = service.post_notification(NotificationInput {
+            application_name: "Bank".to_string(), body: Some("Your 2FA code is 491204".to_string()), ..create_test_notification_input("Sign-in")
+        }).await.unwrap();
+        assert_eq!(service.search_history("2fa code", None, None).await.unwrap().iter().map(|n| n.id).collect::<Vec<_>>(), [code]);
+        assert_eq!(service.search_history("invoice", Some(5), Some(1)).await.unwrap().len(), 0);
+        let paged = service.get_notification_history(Some(1), Some(1), None, Some(NotificationSortOrder::TimestampAscending)).await.unwrap();
+        assert_eq!(paged[0].id, before);
+
+        // Opting an app out drops what it already left behind, in memory and in the store.
+        let mut retention = service.get_history_retention().await.unwrap();
+        retention.apps.insert("Bank".to_string(), AppRetention { excluded: true, ..Default::default() });
+        service.set_history_retention(retention).await.unwrap();
+        assert!(service.search_history("2fa", None, None).await.unwrap().is_empty());
+        assert!(store.entries.lock().unwrap().iter().all(|n| n.id != code));
+
+        let export = service.export_history(None).await.unwrap();
+        assert_eq!(export.version, HISTORY_EXPORT_VERSION);
+        assert_eq!(export.notifications.iter().map(|n| n.id).collect::<Vec<_>>(), [stored.id, before]);
+        service.clear_history().await.unwrap();
+        assert!(store.entries.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_reply_requires_inline_reply_action() {
+        let rules_engine = Arc::new(MockNotificationRulesEngine::new());
+        let settings_service = Arc::new(MockGlobalSettingsService::new());
+        rules_engine.expect_process_notification().returning(|n| Ok(RuleProcessingResult::Allow(n)));
+        let service = DefaultNotificationService::new(rules_engine, settings_service, 5).await.unwrap();
+        let mut rx = service.subscribe_to_notification_events();
+
+        let plain = service.post_notification(create_test_notification_input("Plain")).await.unwrap();
+        let chat = service.post_notification(NotificationInput {
+            actions: vec![NotificationAction { key: INLINE_REPLY_ACTION_KEY.to_string(), label: "Send".to_string(), action_type: NotificationActionType::InlineReply }],
+            ..create_test_notification_input("Chat")
+        }).await.unwrap();
+        drain_events(&mut rx).await;
+
+        assert!(matches!(service.reply(plain, "hi").await, Err(NotificationError::ActionNotFound { .. })));
+        assert!(matches!(service.reply(chat, "  ").await, Err(NotificationError::InvalidInputData { .. })));
+        service.reply(chat, "on my way").await.unwrap();
+        match rx.try_recv() { Ok(Event::NotificationReplied { notification_id, text }) => { assert_eq!(notification_id, chat); assert_eq!(text, "on my way"); } e => panic!("{:?}", e) }
+    }
 }
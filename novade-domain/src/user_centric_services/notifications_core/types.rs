@@ -3,10 +3,12 @@ use uuid::Uuid;
 use chrono::{DateTime, Utc};
 use std::collections::HashMap;
 use crate::shared_types::ApplicationId;
+use super::errors::NotificationError;
 
 // --- Enums ---
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
+/// Ordered from least to most urgent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, Default)]
 #[serde(rename_all = "kebab-case")]
 pub enum NotificationUrgency {
     Low,
@@ -21,6 +23,65 @@ pub enum NotificationActionType {
     #[default]
     Callback,
     OpenLink,
+    /// Asks the user for a line of text, the `inline-reply` extension; answered with
+    /// [`NotificationService::reply`](super::NotificationService::reply).
+    InlineReply,
+}
+
+/// Key of the action that marks a notification as accepting inline replies; its label is the
+/// label of the send button.
+pub const INLINE_REPLY_ACTION_KEY: &str = "inline-reply";
+/// Hint with placeholder text for the inline reply field.
+pub const REPLY_PLACEHOLDER_HINT: &str = "x-kde-reply-placeholder-text";
+/// Hint with the progress of the operation the notification is about, in percent.
+pub const PROGRESS_HINT: &str = "value";
+/// Hint with the URLs of files the notification is about, shown as thumbnails.
+pub const ATTACHMENT_URLS_HINT: &str = "x-kde-urls";
+
+/// Where a notification is presented. Rules route notifications with the [`CHANNEL_HINT`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum NotificationChannel {
+    /// Popup, notification center and history.
+    #[default]
+    Normal,
+    /// Only recorded in the history.
+    HistoryOnly,
+    /// Only plays the notification's sound; recorded in the history, but never shown.
+    SoundOnly,
+}
+
+impl NotificationChannel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NotificationChannel::Normal => "normal",
+            NotificationChannel::HistoryOnly => "history-only",
+            NotificationChannel::SoundOnly => "sound-only",
+        }
+    }
+}
+
+/// Hint naming the [`NotificationChannel`] a notification is routed to; absent for `normal`.
+pub const CHANNEL_HINT: &str = "x-novade-channel";
+/// Hint with the RFC 3339 time a snoozed notification is delivered at.
+pub const SNOOZE_UNTIL_HINT: &str = "x-novade-snooze-until";
+/// Hint with a freedesktop sound theme name to play, e.g. `message-new-instant`.
+pub const SOUND_NAME_HINT: &str = "sound-name";
+/// Hint with the path of a sound file to play; rules' `PlaySound` sets it too.
+pub const SOUND_FILE_HINT: &str = "sound-file";
+/// Hint asking for no sound at all, whatever the other hints or the server default say.
+pub const SUPPRESS_SOUND_HINT: &str = "suppress-sound";
+
+/// What a notification asks to hear, see [`Notification::sound`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum NotificationSound {
+    /// The server's default notification sound.
+    Default,
+    /// A name looked up in the sound theme.
+    Named(String),
+    File(std::path::PathBuf),
+    Silent,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -31,6 +92,60 @@ pub struct NotificationAction {
     pub action_type: NotificationActionType,
 }
 
+/// An image attached through the `image-data` hint, converted to tightly packed,
+/// non-premultiplied RGBA with 8 bits per channel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NotificationImage {
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+}
+
+impl NotificationImage {
+    /// Converts the raw `(iiibiiay)` layout from the notification spec: `height` rows of
+    /// `rowstride` bytes holding RGB or RGBA pixels with `bits_per_sample` 8.
+    pub fn from_raw(
+        width: i32,
+        height: i32,
+        rowstride: i32,
+        has_alpha: bool,
+        bits_per_sample: i32,
+        channels: i32,
+        data: &[u8],
+    ) -> Result<Self, NotificationError> {
+        let invalid = |reason: String| NotificationError::InvalidInputData { field: "image-data".to_string(), reason };
+        if width <= 0 || height <= 0 {
+            return Err(invalid(format!("invalid size {}x{}", width, height)));
+        }
+        if bits_per_sample != 8 {
+            return Err(invalid(format!("unsupported {} bits per sample", bits_per_sample)));
+        }
+        let expected_channels = if has_alpha { 4 } else { 3 };
+        if channels != expected_channels {
+            return Err(invalid(format!("{} channels with has_alpha {}", channels, has_alpha)));
+        }
+        let (width, height, channels) = (width as usize, height as usize, channels as usize);
+        let row_bytes = width * channels;
+        if rowstride < 0 || (rowstride as usize) < row_bytes {
+            return Err(invalid(format!("rowstride {} shorter than a row of {} bytes", rowstride, row_bytes)));
+        }
+        // The last row need not be padded to the full rowstride.
+        let needed = (height - 1) * rowstride as usize + row_bytes;
+        if data.len() < needed {
+            return Err(invalid(format!("{} bytes of pixel data, expected {}", data.len(), needed)));
+        }
+
+        let mut rgba = Vec::with_capacity(width * height * 4);
+        for row in data.chunks(rowstride as usize).take(height) {
+            for pixel in row[..row_bytes].chunks_exact(channels) {
+                rgba.extend_from_slice(&pixel[..3]);
+                rgba.push(if has_alpha { pixel[3] } else { 0xff });
+            }
+        }
+        Ok(Self { width: width as u32, height: height as u32, rgba })
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Notification {
     pub id: Uuid,
@@ -57,6 +172,9 @@ pub struct Notification {
     pub hints: HashMap<String, serde_json::Value>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub timeout_ms: Option<u32>,
+    /// Pixel data is kept in memory only; history stores the rest of the notification.
+    #[serde(skip)]
+    pub image: Option<NotificationImage>,
 }
 
 impl Notification {
@@ -76,6 +194,7 @@ impl Notification {
             category: None,
             hints: HashMap::new(),
             timeout_ms: None,
+            image: None,
         }
     }
 
@@ -86,6 +205,59 @@ impl Notification {
     pub fn dismiss(&mut self) {
         self.is_dismissed = true;
     }
+
+    pub fn channel(&self) -> NotificationChannel {
+        self.hints
+            .get(CHANNEL_HINT)
+            .and_then(|value| serde_json::from_value(value.clone()).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn snoozed_until(&self) -> Option<DateTime<Utc>> {
+        let until = self.hints.get(SNOOZE_UNTIL_HINT)?.as_str()?;
+        DateTime::parse_from_rfc3339(until).ok().map(|until| until.with_timezone(&Utc))
+    }
+
+    /// The sound the hints ask for. `suppress-sound` wins; a `sound-file` that is not a path or
+    /// `file://` URL is taken as a theme name, as rules may name either.
+    pub fn sound(&self) -> NotificationSound {
+        let suppressed = match self.hints.get(SUPPRESS_SOUND_HINT) {
+            Some(serde_json::Value::Bool(suppressed)) => *suppressed,
+            Some(value) => value.as_i64().map_or(false, |value| value != 0),
+            None => false,
+        };
+        if suppressed {
+            return NotificationSound::Silent;
+        }
+        let hint = |key| self.hints.get(key).and_then(|value| value.as_str()).filter(|value| !value.is_empty());
+        if let Some(file) = hint(SOUND_FILE_HINT) {
+            let path = file.strip_prefix("file://").unwrap_or(file);
+            return if path.starts_with('/') { NotificationSound::File(path.into()) } else { NotificationSound::Named(file.to_string()) };
+        }
+        hint(SOUND_NAME_HINT).map_or(NotificationSound::Default, |name| NotificationSound::Named(name.to_string()))
+    }
+
+    pub fn reply_action(&self) -> Option<&NotificationAction> {
+        self.actions.iter().find(|action| action.action_type == NotificationActionType::InlineReply)
+    }
+
+    pub fn reply_placeholder(&self) -> Option<&str> {
+        self.hints.get(REPLY_PLACEHOLDER_HINT)?.as_str().filter(|text| !text.is_empty())
+    }
+
+    /// The `value` hint clamped to 0–100; `None` without one.
+    pub fn progress(&self) -> Option<u8> {
+        let value = self.hints.get(PROGRESS_HINT)?.as_i64()?;
+        Some(value.clamp(0, 100) as u8)
+    }
+
+    pub fn attachment_urls(&self) -> Vec<&str> {
+        match self.hints.get(ATTACHMENT_URLS_HINT) {
+            Some(serde_json::Value::Array(urls)) => urls.iter().filter_map(|url| url.as_str()).collect(),
+            Some(serde_json::Value::String(url)) => vec![url.as_str()],
+            _ => Vec::new(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
@@ -111,6 +283,18 @@ pub struct NotificationInput {
     pub timeout_ms: Option<u32>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub replaces_id: Option<Uuid>,
+    #[serde(skip)]
+    pub image: Option<NotificationImage>,
+}
+
+/// The notification a rule simulation runs against.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RuleSimulationSample {
+    Input(NotificationInput),
+    /// A notification still active or in the history, replayed as stored, i.e. with the rules
+    /// that applied when it was posted already applied.
+    History(Uuid),
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
@@ -141,6 +325,9 @@ pub enum NotificationFilterCriteria {
     HasActionWithKey(String),
     BodyContains(String),
     SummaryContains(String),
+    /// Full-text search over summary and body: every word of the query has to start a word of
+    /// either, case-insensitively.
+    Text(String),
     IsTransient(bool),
     TimeRange {
         #[serde(default, skip_serializing_if = "Option::is_none")]
@@ -193,6 +380,41 @@ mod tests {
         assert_eq!(deserialized, action_type);
     }
 
+    #[test]
+    fn notification_reply_and_rich_content() {
+        let mut notif = Notification::new("Chat".to_string(), "Alice".to_string(), NotificationUrgency::Normal);
+        assert!(notif.reply_action().is_none());
+        assert_eq!((notif.progress(), notif.attachment_urls().len()), (None, 0));
+        notif.actions.push(NotificationAction {
+            key: INLINE_REPLY_ACTION_KEY.to_string(), label: "Send".to_string(),
+            action_type: NotificationActionType::InlineReply,
+        });
+        notif.hints.insert(REPLY_PLACEHOLDER_HINT.to_string(), serde_json::json!("Reply to Alice"));
+        notif.hints.insert(PROGRESS_HINT.to_string(), serde_json::json!(140));
+        notif.hints.insert(ATTACHMENT_URLS_HINT.to_string(), serde_json::json!(["file:///tmp/cat.png", 3]));
+        assert_eq!(notif.reply_action().map(|a| a.label.as_str()), Some("Send"));
+        assert_eq!(notif.reply_placeholder(), Some("Reply to Alice"));
+        assert_eq!(notif.progress(), Some(100));
+        assert_eq!(notif.attachment_urls(), ["file:///tmp/cat.png"]);
+        assert_eq!(serde_json::to_string(&NotificationActionType::InlineReply).unwrap(), "\"inline-reply\"");
+    }
+
+    #[test]
+    fn notification_sound_hints() {
+        let mut notif = Notification::new("Chat".to_string(), "Alice".to_string(), NotificationUrgency::Normal);
+        assert_eq!(notif.sound(), NotificationSound::Default);
+        notif.hints.insert(SOUND_NAME_HINT.to_string(), serde_json::json!("message-new-instant"));
+        assert_eq!(notif.sound(), NotificationSound::Named("message-new-instant".to_string()));
+        notif.hints.insert(SOUND_FILE_HINT.to_string(), serde_json::json!("file:///usr/share/sounds/ping.oga"));
+        assert_eq!(notif.sound(), NotificationSound::File("/usr/share/sounds/ping.oga".into()));
+        notif.hints.insert(SOUND_FILE_HINT.to_string(), serde_json::json!("bell"));
+        assert_eq!(notif.sound(), NotificationSound::Named("bell".to_string()));
+        notif.hints.insert(SUPPRESS_SOUND_HINT.to_string(), serde_json::json!(1));
+        assert_eq!(notif.sound(), NotificationSound::Silent);
+        notif.hints.insert(SUPPRESS_SOUND_HINT.to_string(), serde_json::json!(false));
+        assert_eq!(notif.sound(), NotificationSound::Named("bell".to_string()));
+    }
+
     #[test]
     fn notification_action_serde() {
         let action = NotificationAction {
@@ -204,6 +426,24 @@ mod tests {
         assert_eq!(action, deserialized);
     }
 
+    #[test]
+    fn notification_image_from_raw() {
+        // 2x2 RGB with two bytes of row padding; the last row is not padded.
+        let data = [1, 2, 3, 4, 5, 6, 0, 0, 7, 8, 9, 10, 11, 12];
+        let image = NotificationImage::from_raw(2, 2, 8, false, 8, 3, &data).unwrap();
+        assert_eq!((image.width, image.height), (2, 2));
+        assert_eq!(image.rgba, vec![1, 2, 3, 255, 4, 5, 6, 255, 7, 8, 9, 255, 10, 11, 12, 255]);
+
+        let rgba = NotificationImage::from_raw(1, 1, 4, true, 8, 4, &[9, 8, 7, 6]).unwrap();
+        assert_eq!(rgba.rgba, vec![9, 8, 7, 6]);
+
+        assert!(NotificationImage::from_raw(2, 2, 8, false, 8, 3, &data[..13]).is_err());
+        assert!(NotificationImage::from_raw(2, 2, 5, false, 8, 3, &data).is_err());
+        assert!(NotificationImage::from_raw(1, 1, 4, true, 8, 3, &data).is_err());
+        assert!(NotificationImage::from_raw(1, 1, 8, false, 16, 3, &data).is_err());
+        assert!(NotificationImage::from_raw(0, 1, 3, false, 8, 3, &data).is_err());
+    }
+
     #[test]
     fn notification_new_and_methods() {
         let mut notif = Notification::new("MyApp".to_string(), "Test Summary".to_string(), NotificationUrgency::Low);
@@ -213,6 +453,17 @@ mod tests {
         notif.dismiss(); assert_eq!(notif.is_dismissed, true);
     }
 
+    #[test]
+    fn notification_channel_and_snooze_hints() {
+        let mut notif = Notification::new("MyApp".to_string(), "Later".to_string(), NotificationUrgency::Normal);
+        assert_eq!(notif.channel(), NotificationChannel::Normal);
+        assert_eq!(notif.snoozed_until(), None);
+        notif.hints.insert(CHANNEL_HINT.to_string(), json!(NotificationChannel::SoundOnly.as_str()));
+        notif.hints.insert(SNOOZE_UNTIL_HINT.to_string(), json!("2024-05-06T07:30:00+02:00"));
+        assert_eq!(notif.channel(), NotificationChannel::SoundOnly);
+        assert_eq!(notif.snoozed_until().unwrap().to_rfc3339(), "2024-05-06T05:30:00+00:00");
+    }
+
     #[test]
     fn notification_serde() {
         let notif = Notification::new("TestApp".to_string(), "Hello".to_string(), NotificationUrgency::Critical);
@@ -270,6 +521,8 @@ mod tests {
         let deserialized: NotificationFilterCriteria = serde_json::from_str(&serialized).unwrap();
         assert_eq!(deserialized, criteria);
 
+        assert_eq!(serde_json::to_string(&NotificationFilterCriteria::Text("2fa code".to_string())).unwrap(), r#"{"text":"2fa code"}"#);
+
         let criteria_complex = NotificationFilterCriteria::And(vec![ NotificationFilterCriteria::Urgency(NotificationUrgency::Critical), NotificationFilterCriteria::Not(Box::new(NotificationFilterCriteria::IsTransient(true))), ]);
         let serialized_complex = serde_json::to_string_pretty(&criteria_complex).unwrap();
         let deserialized_complex: NotificationFilterCriteria = serde_json::from_str(&serialized_complex).unwrap();
@@ -176,6 +176,7 @@ mod tests {
                 layout_type: WorkspaceLayoutType::default(),
                 icon_name: None,
                 accent_color_hex: None,
+                output: None,
             }
         }
     }
@@ -189,6 +190,7 @@ mod tests {
                 WorkspaceSnapshot { persistent_id: "pid1".to_string(), name: "WS1".to_string(), ..Default::default() }
             ],
             active_workspace_persistent_id: Some("pid1".to_string()),
+            ..Default::default()
         };
         let toml_content = toml::to_string_pretty(&snapshot).unwrap();
 
@@ -233,6 +235,7 @@ mod tests {
                 WorkspaceSnapshot { persistent_id: "pid1".to_string(), name: "WS2".to_string(), ..Default::default() },
             ],
             active_workspace_persistent_id: Some("pid1".to_string()),
+            ..Default::default()
         };
         let toml_content = toml::to_string_pretty(&snapshot).unwrap();
         mock_config_service.expect_read_config_file_string().returning(move |_| Ok(toml_content.clone()));
@@ -250,6 +253,7 @@ mod tests {
                 WorkspaceSnapshot { persistent_id: "pid1".to_string(), name: "WS1".to_string(), ..Default::default() },
             ],
             active_workspace_persistent_id: Some("pid_non_existent".to_string()),
+            ..Default::default()
         };
         let toml_content = toml::to_string_pretty(&snapshot).unwrap();
         mock_config_service.expect_read_config_file_string().returning(move |_| Ok(toml_content.clone()));
@@ -267,6 +271,7 @@ mod tests {
                 WorkspaceSnapshot { persistent_id: "".to_string(), name: "WS1".to_string(), ..Default::default() },
             ],
             active_workspace_persistent_id: None,
+            ..Default::default()
         };
         let toml_content = toml::to_string_pretty(&snapshot).unwrap();
         mock_config_service.expect_read_config_file_string().returning(move |_| Ok(toml_content.clone()));
@@ -284,6 +289,7 @@ mod tests {
                 WorkspaceSnapshot { persistent_id: "pid1".to_string(), name: "WS1".to_string(), ..Default::default() }
             ],
             active_workspace_persistent_id: Some("pid1".to_string()),
+            ..Default::default()
         };
         let expected_toml_content = toml::to_string_pretty(&snapshot).unwrap();
 
@@ -305,6 +311,7 @@ mod tests {
                 WorkspaceSnapshot { persistent_id: "".to_string(), name: "WS1".to_string(), ..Default::default() }
             ],
             active_workspace_persistent_id: None,
+            ..Default::default()
         };
         let provider = FilesystemConfigProvider::new(mock_config_service, "ws.toml".to_string());
         let result = provider.save_workspace_config(&snapshot).await;
@@ -319,6 +326,7 @@ mod tests {
                 WorkspaceSnapshot { persistent_id: "pid1".to_string(), name: "WS1".to_string(), ..Default::default() }
             ],
             active_workspace_persistent_id: Some("".to_string()), // Invalid: Some("")
+            ..Default::default()
         };
         let provider = FilesystemConfigProvider::new(mock_config_service, "ws.toml".to_string());
         let result = provider.save_workspace_config(&snapshot).await;
@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use crate::workspaces::core::WorkspaceLayoutType; // Ensure this path is correct
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -10,6 +11,9 @@ pub struct WorkspaceSnapshot {
     pub icon_name: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub accent_color_hex: Option<String>,
+    /// Output the workspace belongs to, so it can be restored there when that output connects.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
@@ -18,6 +22,9 @@ pub struct WorkspaceSetSnapshot {
     pub workspaces: Vec<WorkspaceSnapshot>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub active_workspace_persistent_id: Option<String>,
+    /// Persistent ID of the workspace last shown on each output, keyed by output name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub active_workspace_per_output: BTreeMap<String, String>,
 }
 
 
@@ -33,6 +40,7 @@ mod tests {
             layout_type: WorkspaceLayoutType::TilingVertical,
             icon_name: Some("icon-arch".to_string()),
             accent_color_hex: Some("#FF00FF".to_string()),
+            output: Some("DP-1".to_string()),
         };
         let serialized = serde_json::to_string_pretty(&snapshot).unwrap();
         let deserialized: WorkspaceSnapshot = serde_json::from_str(&serialized).unwrap();
@@ -47,10 +55,12 @@ mod tests {
             layout_type: WorkspaceLayoutType::Floating,
             icon_name: None,
             accent_color_hex: None,
+            output: None,
         };
         let serialized = serde_json::to_string_pretty(&snapshot).unwrap();
         assert!(!serialized.contains("icon_name"));
         assert!(!serialized.contains("accent_color_hex"));
+        assert!(!serialized.contains("output"));
 
         let deserialized: WorkspaceSnapshot = serde_json::from_str(&serialized).unwrap();
         assert_eq!(snapshot, deserialized);
@@ -75,6 +85,7 @@ mod tests {
                     layout_type: WorkspaceLayoutType::Maximized,
                     icon_name: None,
                     accent_color_hex: None,
+                    output: Some("DP-1".to_string()),
                 },
                 WorkspaceSnapshot {
                     persistent_id: "dev".to_string(),
@@ -82,9 +93,14 @@ mod tests {
                     layout_type: WorkspaceLayoutType::TilingHorizontal,
                     icon_name: Some("code-icon".to_string()),
                     accent_color_hex: None,
+                    output: Some("HDMI-A-1".to_string()),
                 },
             ],
             active_workspace_persistent_id: Some("main".to_string()),
+            active_workspace_per_output: BTreeMap::from([
+                ("DP-1".to_string(), "main".to_string()),
+                ("HDMI-A-1".to_string(), "dev".to_string()),
+            ]),
         };
         let serialized = serde_json::to_string_pretty(&set_snapshot).unwrap();
         let deserialized: WorkspaceSetSnapshot = serde_json::from_str(&serialized).unwrap();
@@ -48,6 +48,22 @@ pub struct WorkspaceAccentChangedData {
     pub new_color_hex: Option<String>,
 }
 
+/// A workspace was moved to another output, either explicitly or because its output was
+/// unplugged (`new_output` is `None` if no output is left to take it).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct WorkspaceOutputChangedData {
+    pub id: WorkspaceId,
+    pub old_output: Option<String>,
+    pub new_output: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ActiveWorkspaceOnOutputChangedData {
+    pub output_name: String,
+    pub old_id: Option<WorkspaceId>,
+    pub new_id: WorkspaceId,
+}
+
 
 #[cfg(test)]
 mod tests {
@@ -144,4 +160,16 @@ mod tests {
         let deserialized: WorkspaceAccentChangedData = serde_json::from_str(&serialized).unwrap();
         assert_eq!(data, deserialized);
     }
+
+    #[test]
+    fn test_workspace_output_changed_data_serde() {
+        let data = WorkspaceOutputChangedData {
+            id: Uuid::new_v4(),
+            old_output: Some("DP-1".to_string()),
+            new_output: None,
+        };
+        let serialized = serde_json::to_string(&data).unwrap();
+        let deserialized: WorkspaceOutputChangedData = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(data, deserialized);
+    }
 }
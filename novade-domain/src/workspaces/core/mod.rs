@@ -6,7 +6,7 @@ pub mod workspace;
 pub mod event_data;
 
 // Re-exports for easier access from parent modules
-pub use types::{WorkspaceId, WindowIdentifier, WorkspaceLayoutType};
+pub use types::{WorkspaceId, WindowIdentifier, WorkspaceLayoutType, WorkspaceOutputPolicy};
 pub use errors::{WorkspaceCoreError, MAX_WORKSPACE_NAME_LENGTH};
 pub use workspace::Workspace;
 
@@ -15,4 +15,5 @@ pub use event_data::{
     WorkspaceRenamedData, WorkspaceLayoutChangedData, WindowAddedToWorkspaceData,
     WindowRemovedFromWorkspaceData, WorkspacePersistentIdChangedData,
     WorkspaceIconChangedData, WorkspaceAccentChangedData,
+    WorkspaceOutputChangedData, ActiveWorkspaceOnOutputChangedData,
 };
@@ -63,6 +63,18 @@ pub enum WorkspaceLayoutType {
     Maximized,
 }
 
+// --- WorkspaceOutputPolicy ---
+/// Chooses the output a newly created workspace is placed on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum WorkspaceOutputPolicy {
+    /// The output of the focused window (falls back to the output under the pointer).
+    #[default]
+    FollowFocus,
+    /// The output under the pointer (falls back to the focused output).
+    FollowMouse,
+}
+
 
 #[cfg(test)]
 mod tests {
@@ -72,6 +84,7 @@ mod tests {
     // Ensure types implement expected traits
     assert_impl_all!(WindowIdentifier: Send, Sync);
     assert_impl_all!(WorkspaceLayoutType: Send, Sync);
+    assert_impl_all!(WorkspaceOutputPolicy: Send, Sync);
 
 
     #[test]
@@ -147,4 +160,13 @@ mod tests {
         let deserialized_default: WorkspaceLayoutType = serde_json::from_str(&serialized_default).unwrap();
         assert_eq!(deserialized_default, default_layout);
     }
+
+    #[test]
+    fn workspace_output_policy_serde() {
+        assert_eq!(WorkspaceOutputPolicy::default(), WorkspaceOutputPolicy::FollowFocus);
+        let serialized = serde_json::to_string(&WorkspaceOutputPolicy::FollowMouse).unwrap();
+        assert_eq!(serialized, "\"follow-mouse\"");
+        let deserialized: WorkspaceOutputPolicy = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(deserialized, WorkspaceOutputPolicy::FollowMouse);
+    }
 }
@@ -27,6 +27,13 @@ pub struct Workspace {
     icon_name: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     accent_color_hex: Option<String>,
+    /// Output the workspace is currently shown on; `None` while no output is connected.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    output_name: Option<String>,
+    /// Output the workspace belongs to. Differs from `output_name` while the workspace is
+    /// parked on another output because its own output is unplugged.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    preferred_output: Option<String>,
 }
 
 impl Workspace {
@@ -71,6 +78,8 @@ impl Workspace {
             created_at: Utc::now(),
             icon_name,
             accent_color_hex,
+            output_name: None,
+            preferred_output: None,
         })
     }
 
@@ -83,6 +92,8 @@ impl Workspace {
     pub fn created_at(&self) -> DateTime<Utc> { self.created_at }
     pub fn icon_name(&self) -> Option<&str> { self.icon_name.as_deref() }
     pub fn accent_color_hex(&self) -> Option<&str> { self.accent_color_hex.as_deref() }
+    pub fn output_name(&self) -> Option<&str> { self.output_name.as_deref() }
+    pub fn preferred_output(&self) -> Option<&str> { self.preferred_output.as_deref() }
 
     // Setters & Methods
     pub fn rename(&mut self, new_name: String) -> Result<(), WorkspaceCoreError> {
@@ -112,6 +123,19 @@ impl Workspace {
         self.window_ids.remove(window_id)
     }
 
+    pub(crate) fn set_output_name(&mut self, output_name: Option<String>) {
+        self.output_name = output_name;
+    }
+
+    pub(crate) fn set_preferred_output(&mut self, output_name: Option<String>) {
+        self.preferred_output = output_name;
+    }
+
+    /// Returns `true` if the workspace is shown on an output other than its own.
+    pub fn is_parked(&self) -> bool {
+        self.preferred_output.is_some() && self.output_name != self.preferred_output
+    }
+
     pub fn set_persistent_id(&mut self, pid: Option<String>) -> Result<(), WorkspaceCoreError> {
         if let Some(p) = &pid {
             if p.is_empty() || !PERSISTENT_ID_REGEX.is_match(p) {
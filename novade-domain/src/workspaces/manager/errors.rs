@@ -42,6 +42,9 @@ pub enum WorkspaceManagerError {
     #[error("Invalid workspace index: {0}. Must be within the current range of workspaces.")]
     InvalidWorkspaceIndex(usize),
 
+    #[error("Output '{0}' is not connected.")]
+    OutputNotConnected(String),
+
     #[error("Internal error in workspace manager: {context}")]
     Internal { context: String },
 }
@@ -102,6 +105,10 @@ mod tests {
             format!("{}", WorkspaceManagerError::InvalidWorkspaceIndex(5)),
             "Invalid workspace index: 5. Must be within the current range of workspaces."
         );
+        assert_eq!(
+            format!("{}", WorkspaceManagerError::OutputNotConnected("DP-2".to_string())),
+            "Output 'DP-2' is not connected."
+        );
         assert_eq!(
             format!("{}", WorkspaceManagerError::Internal { context: "Critical failure".to_string() }),
             "Internal error in workspace manager: Critical failure"
@@ -5,6 +5,7 @@ use crate::workspaces::core::{
         WorkspaceRenamedData, WorkspaceLayoutChangedData, WindowAddedToWorkspaceData,
         WindowRemovedFromWorkspaceData, WorkspacePersistentIdChangedData,
         WorkspaceIconChangedData, WorkspaceAccentChangedData,
+        WorkspaceOutputChangedData, ActiveWorkspaceOnOutputChangedData,
     }
 };
 
@@ -38,6 +39,19 @@ pub enum WorkspaceEvent {
     WorkspacePersistentIdChanged(WorkspacePersistentIdChangedData),
     WorkspaceIconChanged(WorkspaceIconChangedData),
     WorkspaceAccentChanged(WorkspaceAccentChangedData),
+    WorkspaceOutputChanged(WorkspaceOutputChangedData),
+    ActiveWorkspaceOnOutputChanged(ActiveWorkspaceOnOutputChangedData),
+    OutputConnected {
+        output_name: String,
+    },
+    OutputDisconnected {
+        output_name: String,
+        workspaces_moved_to: Option<String>,
+    },
+    FocusedOutputChanged {
+        old_output: Option<String>,
+        new_output: Option<String>,
+    },
 }
 
 
@@ -136,4 +150,15 @@ mod tests {
         let deserialized: WorkspaceEvent = serde_json::from_str(&serialized).unwrap();
         assert_eq!(event, deserialized);
     }
+
+    #[test]
+    fn workspace_event_output_disconnected_serde() {
+        let event = WorkspaceEvent::OutputDisconnected {
+            output_name: "HDMI-A-1".to_string(),
+            workspaces_moved_to: Some("eDP-1".to_string()),
+        };
+        let serialized = serde_json::to_string(&event).unwrap();
+        let deserialized: WorkspaceEvent = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(event, deserialized);
+    }
 }
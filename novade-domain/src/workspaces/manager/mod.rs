@@ -1,12 +1,12 @@
 use async_trait::async_trait;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
 use tokio::sync::{broadcast, Mutex};
 use tracing::{debug, error, info, warn};
 use uuid::Uuid;
 
 use crate::workspaces::core::{
-    Workspace, WorkspaceId, WindowIdentifier, WorkspaceLayoutType,
+    Workspace, WorkspaceId, WindowIdentifier, WorkspaceLayoutType, WorkspaceOutputPolicy,
     event_data::*, // Import all event data structs
 };
 use crate::workspaces::assignment;
@@ -40,6 +40,27 @@ pub trait WorkspaceManagerService: Send + Sync {
     fn subscribe_to_workspace_events(&self) -> broadcast::Receiver<WorkspaceEvent>;
     async fn reorder_workspace(&self, workspace_id: WorkspaceId, new_index: usize) -> Result<(), WorkspaceManagerError>;
 
+    // --- Outputs ---
+    // Every connected output shows one active workspace; `active_workspace_id()` is the one on
+    // the focused output. Before any output is connected all workspaces are unplaced.
+
+    /// Registers a connected output. Workspaces that belong to it come back from the outputs
+    /// they were parked on, and the output gets a workspace of its own if it has none.
+    async fn output_connected(&self, output_name: &str) -> Result<(), WorkspaceManagerError>;
+    /// Unregisters an output and parks its workspaces on a surviving output until it returns.
+    async fn output_disconnected(&self, output_name: &str) -> Result<(), WorkspaceManagerError>;
+    /// Moves a workspace to another output and shows it there; it now belongs to that output.
+    async fn move_workspace_to_output(&self, workspace_id: WorkspaceId, output_name: &str) -> Result<(), WorkspaceManagerError>;
+    async fn set_focused_output(&self, output_name: &str) -> Result<(), WorkspaceManagerError>;
+    /// Records the output under the pointer, used by [`WorkspaceOutputPolicy::FollowMouse`].
+    async fn set_pointer_output(&self, output_name: Option<String>);
+    async fn set_workspace_output_policy(&self, policy: WorkspaceOutputPolicy);
+    async fn connected_outputs(&self) -> Vec<String>;
+    async fn focused_output(&self) -> Option<String>;
+    /// Workspaces currently shown on `output_name`, in workspace order.
+    async fn workspaces_on_output(&self, output_name: &str) -> Vec<Workspace>;
+    async fn active_workspace_on_output(&self, output_name: &str) -> Option<WorkspaceId>;
+
     // TODO: Assistant Integration - Needed by Smart Assistant
     // Consider methods like:
     // fn get_active_workspace_details(&self) -> Option<SomeWorkspaceDetailStruct>; // Currently active_workspace_id() and get_workspace() can be combined.
@@ -56,6 +77,14 @@ pub struct WorkspaceManagerInternalState {
     config_provider: Arc<dyn WorkspaceConfigProvider>,
     event_publisher: broadcast::Sender<WorkspaceEvent>,
     ensure_unique_window_assignment: bool,
+    /// Connected outputs in connection order.
+    outputs: Vec<String>,
+    active_workspace_per_output: HashMap<String, WorkspaceId>,
+    /// Workspace to show again when an unplugged (or not yet connected) output comes back.
+    remembered_active_per_output: HashMap<String, WorkspaceId>,
+    focused_output: Option<String>,
+    pointer_output: Option<String>,
+    output_policy: WorkspaceOutputPolicy,
 }
 
 impl WorkspaceManagerInternalState {
@@ -64,26 +93,30 @@ impl WorkspaceManagerInternalState {
         for ws_id in &self.ordered_workspace_ids {
             if let Some(ws) = self.workspaces.get(ws_id) {
                 ws_snapshots.push(WorkspaceSnapshot {
-                    persistent_id: ws.persistent_id().map_or_else(
-                        || format!("{}{}",crate::workspaces::core::DEFAULT_PERSISTENT_ID_PREFIX, ws.id()), // Fallback to auto-PID using ID
-                        |s| s.to_string()
-                    ),
+                    persistent_id: snapshot_persistent_id(ws),
                     name: ws.name().to_string(),
                     layout_type: ws.layout_type(),
                     icon_name: ws.icon_name().map(String::from),
                     accent_color_hex: ws.accent_color_hex().map(String::from),
+                    output: ws.preferred_output().map(String::from),
                 });
             }
         }
         
         let active_pid = self.active_workspace_id
             .and_then(|active_id| self.workspaces.get(&active_id))
-            .and_then(|ws| ws.persistent_id().map(String::from)
-            .or_else(|| Some(format!("{}{}",crate::workspaces::core::DEFAULT_PERSISTENT_ID_PREFIX, ws.id()))));
+            .map(snapshot_persistent_id);
+
+        // Outputs that are unplugged keep the workspace they showed last.
+        let active_workspace_per_output: BTreeMap<String, String> = self.remembered_active_per_output.iter()
+            .chain(self.active_workspace_per_output.iter())
+            .filter_map(|(output, id)| self.workspaces.get(id).map(|ws| (output.clone(), snapshot_persistent_id(ws))))
+            .collect();
 
         let snapshot = WorkspaceSetSnapshot {
             workspaces: ws_snapshots,
             active_workspace_persistent_id: active_pid,
+            active_workspace_per_output,
         };
         self.config_provider.save_workspace_config(&snapshot).await
     }
@@ -126,6 +159,116 @@ impl WorkspaceManagerInternalState {
         
         Ok(new_id)
     }
+
+    fn workspaces_on_output_locked(&self, output_name: &str) -> Vec<WorkspaceId> {
+        self.ordered_workspace_ids.iter().copied()
+            .filter(|id| self.workspaces.get(id).and_then(|ws| ws.output_name()) == Some(output_name))
+            .collect()
+    }
+
+    /// Picks the output a new workspace goes to, according to `output_policy`.
+    fn output_for_new_workspace_locked(&self) -> Option<String> {
+        let (first, second) = match self.output_policy {
+            WorkspaceOutputPolicy::FollowFocus => (&self.focused_output, &self.pointer_output),
+            WorkspaceOutputPolicy::FollowMouse => (&self.pointer_output, &self.focused_output),
+        };
+        first.iter().chain(second.iter())
+            .find(|output| self.outputs.contains(output))
+            .or_else(|| self.outputs.first())
+            .cloned()
+    }
+
+    /// Shows a workspace on `output_name` (or on no output). With `claim` the workspace also
+    /// starts to belong to that output; otherwise it is only parked there.
+    fn place_workspace_locked(&mut self, id: WorkspaceId, output_name: Option<String>, claim: bool) {
+        let Some(ws) = self.workspaces.get_mut(&id) else { return };
+        if claim {
+            ws.set_preferred_output(output_name.clone());
+        }
+        let old_output = ws.output_name().map(String::from);
+        if old_output == output_name { return; }
+        ws.set_output_name(output_name.clone());
+        let _ = self.event_publisher.send(WorkspaceEvent::WorkspaceOutputChanged(WorkspaceOutputChangedData { id, old_output, new_output: output_name }));
+    }
+
+    fn set_active_on_output_locked(&mut self, output_name: &str, id: WorkspaceId) {
+        let old_id = self.active_workspace_per_output.insert(output_name.to_string(), id);
+        if old_id != Some(id) {
+            let _ = self.event_publisher.send(WorkspaceEvent::ActiveWorkspaceOnOutputChanged(ActiveWorkspaceOnOutputChangedData { output_name: output_name.to_string(), old_id, new_id: id }));
+        }
+        self.sync_active_workspace_locked();
+    }
+
+    fn set_focused_output_locked(&mut self, output_name: Option<String>) {
+        if self.focused_output == output_name { return; }
+        let old_output = std::mem::replace(&mut self.focused_output, output_name.clone());
+        let _ = self.event_publisher.send(WorkspaceEvent::FocusedOutputChanged { old_output, new_output: output_name });
+        self.sync_active_workspace_locked();
+    }
+
+    /// Keeps `active_workspace_id` pointing at the workspace shown on the focused output.
+    fn sync_active_workspace_locked(&mut self) {
+        let Some(new_id) = self.focused_output.as_ref().and_then(|o| self.active_workspace_per_output.get(o)).copied() else { return };
+        if self.active_workspace_id == Some(new_id) { return; }
+        let old_id = self.active_workspace_id.replace(new_id);
+        let _ = self.event_publisher.send(WorkspaceEvent::ActiveWorkspaceChanged { old_id, new_id });
+    }
+
+    /// Makes sure `output_name` shows one of its workspaces, creating one if it has none.
+    fn ensure_active_on_output_locked(&mut self, output_name: &str) -> Result<(), WorkspaceManagerError> {
+        let on_output = self.workspaces_on_output_locked(output_name);
+        if self.active_workspace_per_output.get(output_name).map_or(false, |id| on_output.contains(id)) {
+            return Ok(());
+        }
+        let remembered = self.remembered_active_per_output.remove(output_name).filter(|id| on_output.contains(id));
+        let id = match remembered.or_else(|| on_output.first().copied()) {
+            Some(id) => id,
+            None => {
+                let id = self.create_workspace_locked(None, None, None, None)?;
+                self.place_workspace_locked(id, Some(output_name.to_string()), true);
+                id
+            }
+        };
+        self.set_active_on_output_locked(output_name, id);
+        Ok(())
+    }
+
+    /// Brings home the workspaces that belong to a (re)connected output and adopts workspaces
+    /// that are not shown anywhere.
+    fn attach_output_locked(&mut self, output_name: &str) -> Result<(), WorkspaceManagerError> {
+        let returning: Vec<(WorkspaceId, Option<String>, bool)> = self.ordered_workspace_ids.iter()
+            .filter_map(|id| self.workspaces.get(id))
+            .filter(|ws| ws.output_name().is_none() || (ws.preferred_output() == Some(output_name) && ws.output_name() != Some(output_name)))
+            .map(|ws| (ws.id(), ws.output_name().map(String::from), ws.preferred_output().is_none()))
+            .collect();
+
+        let mut left_outputs = Vec::new();
+        for (id, old_output, unclaimed) in returning {
+            self.place_workspace_locked(id, Some(output_name.to_string()), unclaimed);
+            if let Some(old_output) = old_output {
+                if self.active_workspace_per_output.get(&old_output) == Some(&id) {
+                    self.active_workspace_per_output.remove(&old_output);
+                    left_outputs.push(old_output);
+                }
+            }
+        }
+        self.ensure_active_on_output_locked(output_name)?;
+        for old_output in left_outputs {
+            self.ensure_active_on_output_locked(&old_output)?;
+        }
+        if self.focused_output.is_none() {
+            self.set_focused_output_locked(Some(output_name.to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// Persistent ID written to the configuration; workspaces without one get an auto-PID.
+fn snapshot_persistent_id(ws: &Workspace) -> String {
+    ws.persistent_id().map_or_else(
+        || format!("{}{}", crate::workspaces::core::DEFAULT_PERSISTENT_ID_PREFIX, ws.id()),
+        |s| s.to_string(),
+    )
 }
 
 // --- DefaultWorkspaceManager Implementation ---
@@ -144,6 +287,8 @@ impl DefaultWorkspaceManager {
         let internal_state = WorkspaceManagerInternalState {
             workspaces: HashMap::new(), active_workspace_id: None, ordered_workspace_ids: Vec::new(),
             next_workspace_number: 1, config_provider, event_publisher, ensure_unique_window_assignment,
+            outputs: Vec::new(), active_workspace_per_output: HashMap::new(), remembered_active_per_output: HashMap::new(),
+            focused_output: None, pointer_output: None, output_policy: WorkspaceOutputPolicy::default(),
         };
         Self { internal: Arc::new(Mutex::new(internal_state)) }
     }
@@ -161,6 +306,9 @@ impl WorkspaceManagerService for DefaultWorkspaceManager {
                 let default_ws_id = guard.create_workspace_locked(None, None, None, None)?;
                 guard.active_workspace_id = Some(default_ws_id);
                 let _ = guard.event_publisher.send(WorkspaceEvent::ActiveWorkspaceChanged { old_id: None, new_id: default_ws_id });
+                for output_name in guard.outputs.clone() {
+                    guard.attach_output_locked(&output_name)?;
+                }
                 guard.save_configuration().await?;
             }
             Ok(snapshot) => {
@@ -174,7 +322,8 @@ impl WorkspaceManagerService for DefaultWorkspaceManager {
                     } else {
                         Some(ws_snapshot.persistent_id.clone())
                     };
-                    let ws = Workspace::new(ws_snapshot.name.clone(), effective_pid, ws_snapshot.icon_name.clone(), ws_snapshot.accent_color_hex.clone())?;
+                    let mut ws = Workspace::new(ws_snapshot.name.clone(), effective_pid, ws_snapshot.icon_name.clone(), ws_snapshot.accent_color_hex.clone())?;
+                    ws.set_preferred_output(ws_snapshot.output.clone());
                     let ws_id = ws.id();
                     guard.workspaces.insert(ws_id, ws);
                     guard.ordered_workspace_ids.push(ws_id);
@@ -191,6 +340,10 @@ impl WorkspaceManagerService for DefaultWorkspaceManager {
                 if guard.active_workspace_id.is_none() && !guard.ordered_workspace_ids.is_empty() {
                     guard.active_workspace_id = Some(guard.ordered_workspace_ids[0]);
                 }
+                guard.active_workspace_per_output.clear();
+                guard.remembered_active_per_output = snapshot.active_workspace_per_output.iter()
+                    .filter_map(|(output, pid)| temp_pid_to_id_map.get(pid).map(|id| (output.clone(), *id)))
+                    .collect();
 
                 let mut max_num = 0;
                 for ws in guard.workspaces.values() {
@@ -204,6 +357,10 @@ impl WorkspaceManagerService for DefaultWorkspaceManager {
                 if let Some(active_id) = guard.active_workspace_id {
                      let _ = guard.event_publisher.send(WorkspaceEvent::ActiveWorkspaceChanged { old_id: None, new_id: active_id });
                 }
+                // Outputs may have been connected before the configuration was loaded.
+                for output_name in guard.outputs.clone() {
+                    guard.attach_output_locked(&output_name)?;
+                }
                 info!("Workspaces reloaded. Active: {:?}. Order: {:?}", guard.active_workspace_id, guard.ordered_workspace_ids);
             }
             Err(e) => {
@@ -212,6 +369,9 @@ impl WorkspaceManagerService for DefaultWorkspaceManager {
                 let default_ws_id = guard.create_workspace_locked(None, None, None, None)?;
                 guard.active_workspace_id = Some(default_ws_id);
                 let _ = guard.event_publisher.send(WorkspaceEvent::ActiveWorkspaceChanged { old_id: None, new_id: default_ws_id });
+                for output_name in guard.outputs.clone() {
+                    guard.attach_output_locked(&output_name)?;
+                }
                 if let Err(save_err) = guard.save_configuration().await {
                      error!("CRITICAL: Failed to save emergency default config: {:?}", save_err);
                      return Err(WorkspaceManagerError::ConfigError(save_err));
@@ -224,6 +384,10 @@ impl WorkspaceManagerService for DefaultWorkspaceManager {
     async fn create_workspace(&self, name: Option<String>, persistent_id: Option<String>, icon_name: Option<String>, accent_color_hex: Option<String>) -> Result<WorkspaceId, WorkspaceManagerError> {
         let mut guard = self.internal.lock().await;
         let new_id = guard.create_workspace_locked(name, persistent_id, icon_name, accent_color_hex)?;
+        if let Some(output_name) = guard.output_for_new_workspace_locked() {
+            guard.place_workspace_locked(new_id, Some(output_name.clone()), true);
+            guard.ensure_active_on_output_locked(&output_name)?;
+        }
         if guard.active_workspace_id.is_none() {
             guard.active_workspace_id = Some(new_id);
             let _ = guard.event_publisher.send(WorkspaceEvent::ActiveWorkspaceChanged { old_id: None, new_id });
@@ -259,8 +423,15 @@ impl WorkspaceManagerService for DefaultWorkspaceManager {
             }
         }
 
-        guard.workspaces.remove(&id);
+        let deleted_output = guard.workspaces.remove(&id).and_then(|ws| ws.output_name().map(String::from));
         guard.ordered_workspace_ids.retain(|ws_id| *ws_id != id);
+        guard.remembered_active_per_output.retain(|_, ws_id| *ws_id != id);
+        if let Some(output_name) = deleted_output {
+            if guard.active_workspace_per_output.get(&output_name) == Some(&id) {
+                guard.active_workspace_per_output.remove(&output_name);
+                guard.ensure_active_on_output_locked(&output_name)?;
+            }
+        }
         let old_active_id = guard.active_workspace_id;
         if guard.active_workspace_id == Some(id) {
             guard.active_workspace_id = guard.ordered_workspace_ids.first().cloned();
@@ -289,6 +460,12 @@ impl WorkspaceManagerService for DefaultWorkspaceManager {
         let mut guard = self.internal.lock().await;
         if !guard.workspaces.contains_key(&id) { return Err(WorkspaceManagerError::SetActiveWorkspaceNotFound(id)); }
         let old_id = guard.active_workspace_id; if old_id == Some(id) { return Ok(()); }
+        // A placed workspace is shown on its output, which becomes the focused one.
+        if let Some(output_name) = guard.workspaces.get(&id).and_then(|ws| ws.output_name().map(String::from)) {
+            guard.set_focused_output_locked(Some(output_name.clone()));
+            guard.set_active_on_output_locked(&output_name, id);
+            return guard.save_configuration().await.map_err(Into::into);
+        }
         guard.active_workspace_id = Some(id);
         let _ = guard.event_publisher.send(WorkspaceEvent::ActiveWorkspaceChanged { old_id, new_id: id });
         guard.save_configuration().await?; Ok(())
@@ -384,6 +561,87 @@ impl WorkspaceManagerService for DefaultWorkspaceManager {
         let _ = guard.event_publisher.send(WorkspaceEvent::WorkspaceOrderChanged(guard.ordered_workspace_ids.clone()));
         guard.save_configuration().await?; Ok(())
     }
+
+    async fn output_connected(&self, output_name: &str) -> Result<(), WorkspaceManagerError> {
+        let mut guard = self.internal.lock().await;
+        if guard.outputs.iter().any(|o| o == output_name) { return Ok(()); }
+        info!("Output {} connected.", output_name);
+        guard.outputs.push(output_name.to_string());
+        let _ = guard.event_publisher.send(WorkspaceEvent::OutputConnected { output_name: output_name.to_string() });
+        guard.attach_output_locked(output_name)?;
+        guard.save_configuration().await?; Ok(())
+    }
+
+    async fn output_disconnected(&self, output_name: &str) -> Result<(), WorkspaceManagerError> {
+        let mut guard = self.internal.lock().await;
+        if !guard.outputs.iter().any(|o| o == output_name) { return Ok(()); }
+        guard.outputs.retain(|o| o != output_name);
+        if guard.pointer_output.as_deref() == Some(output_name) { guard.pointer_output = None; }
+        if guard.focused_output.as_deref() == Some(output_name) { guard.focused_output = None; }
+
+        let target = guard.output_for_new_workspace_locked();
+        if let Some(active_id) = guard.active_workspace_per_output.remove(output_name) {
+            guard.remembered_active_per_output.insert(output_name.to_string(), active_id);
+        }
+        for id in guard.workspaces_on_output_locked(output_name) {
+            guard.place_workspace_locked(id, target.clone(), false);
+        }
+        info!("Output {} disconnected, its workspaces moved to {:?}.", output_name, target);
+        let _ = guard.event_publisher.send(WorkspaceEvent::OutputDisconnected { output_name: output_name.to_string(), workspaces_moved_to: target.clone() });
+        if guard.focused_output.is_none() {
+            let old_output = Some(output_name.to_string());
+            guard.focused_output = target.clone();
+            let _ = guard.event_publisher.send(WorkspaceEvent::FocusedOutputChanged { old_output, new_output: target });
+            guard.sync_active_workspace_locked();
+        }
+        guard.save_configuration().await?; Ok(())
+    }
+
+    async fn move_workspace_to_output(&self, workspace_id: WorkspaceId, output_name: &str) -> Result<(), WorkspaceManagerError> {
+        let mut guard = self.internal.lock().await;
+        if !guard.outputs.iter().any(|o| o == output_name) { return Err(WorkspaceManagerError::OutputNotConnected(output_name.to_string())); }
+        let source = guard.workspaces.get(&workspace_id).ok_or(WorkspaceManagerError::WorkspaceNotFound(workspace_id))?
+            .output_name().map(String::from);
+
+        guard.place_workspace_locked(workspace_id, Some(output_name.to_string()), true);
+        if let Some(source) = source.filter(|s| s != output_name) {
+            if guard.active_workspace_per_output.get(&source) == Some(&workspace_id) {
+                guard.active_workspace_per_output.remove(&source);
+                guard.ensure_active_on_output_locked(&source)?;
+            }
+        }
+        guard.set_focused_output_locked(Some(output_name.to_string()));
+        guard.set_active_on_output_locked(output_name, workspace_id);
+        guard.save_configuration().await?; Ok(())
+    }
+
+    async fn set_focused_output(&self, output_name: &str) -> Result<(), WorkspaceManagerError> {
+        let mut guard = self.internal.lock().await;
+        if !guard.outputs.iter().any(|o| o == output_name) { return Err(WorkspaceManagerError::OutputNotConnected(output_name.to_string())); }
+        guard.set_focused_output_locked(Some(output_name.to_string()));
+        Ok(())
+    }
+
+    async fn set_pointer_output(&self, output_name: Option<String>) {
+        self.internal.lock().await.pointer_output = output_name;
+    }
+
+    async fn set_workspace_output_policy(&self, policy: WorkspaceOutputPolicy) {
+        self.internal.lock().await.output_policy = policy;
+    }
+
+    async fn connected_outputs(&self) -> Vec<String> { self.internal.lock().await.outputs.clone() }
+
+    async fn focused_output(&self) -> Option<String> { self.internal.lock().await.focused_output.clone() }
+
+    async fn workspaces_on_output(&self, output_name: &str) -> Vec<Workspace> {
+        let guard = self.internal.lock().await;
+        guard.workspaces_on_output_locked(output_name).iter().filter_map(|id| guard.workspaces.get(id).cloned()).collect()
+    }
+
+    async fn active_workspace_on_output(&self, output_name: &str) -> Option<WorkspaceId> {
+        self.internal.lock().await.active_workspace_per_output.get(output_name).copied()
+    }
 }
 
 // --- Unit Tests ---
@@ -438,7 +696,7 @@ mod tests {
             workspaces: vec![
                 WorkspaceSnapshot { persistent_id: ws1_pid.clone(), name: "First WS".to_string(), ..Default::default() },
                 WorkspaceSnapshot { persistent_id: ws2_pid.clone(), name: "Second WS".to_string(), ..Default::default() },
-            ], active_workspace_persistent_id: Some(ws2_pid.clone()),
+            ], active_workspace_persistent_id: Some(ws2_pid.clone()), ..Default::default()
         };
         mock_provider.expect_load_workspace_config().times(1).returning(move || Ok(existing_snapshot.clone()));
 
@@ -534,4 +792,141 @@ mod tests {
         }
         assert!(got_removed && got_added);
     }
+
+    /// Keeps the last saved snapshot so that save/load round trips can be checked.
+    #[derive(Default)]
+    struct InMemoryConfigProvider {
+        snapshot: std::sync::Mutex<WorkspaceSetSnapshot>,
+    }
+
+    #[async_trait]
+    impl WorkspaceConfigProvider for InMemoryConfigProvider {
+        async fn load_workspace_config(&self) -> Result<WorkspaceSetSnapshot, crate::workspaces::config::WorkspaceConfigError> {
+            Ok(self.snapshot.lock().unwrap().clone())
+        }
+        async fn save_workspace_config(&self, snapshot: &WorkspaceSetSnapshot) -> Result<(), crate::workspaces::config::WorkspaceConfigError> {
+            *self.snapshot.lock().unwrap() = snapshot.clone();
+            Ok(())
+        }
+    }
+
+    async fn manager_with_outputs(provider: Arc<InMemoryConfigProvider>, outputs: &[&str]) -> DefaultWorkspaceManager {
+        let manager = DefaultWorkspaceManager::new(provider, 64, true);
+        manager.load_or_initialize_workspaces().await.unwrap();
+        for output in outputs {
+            manager.output_connected(output).await.unwrap();
+        }
+        manager
+    }
+
+    fn ids(workspaces: &[Workspace]) -> Vec<WorkspaceId> {
+        workspaces.iter().map(|ws| ws.id()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_outputs_have_independent_active_workspaces() {
+        let manager = manager_with_outputs(Arc::default(), &["DP-1", "HDMI-A-1"]).await;
+
+        let dp = manager.active_workspace_on_output("DP-1").await.unwrap();
+        let hdmi = manager.active_workspace_on_output("HDMI-A-1").await.unwrap();
+        assert_ne!(dp, hdmi);
+        assert_eq!(manager.focused_output().await.as_deref(), Some("DP-1"));
+
+        manager.set_active_workspace(hdmi).await.unwrap();
+        assert_eq!(manager.focused_output().await.as_deref(), Some("HDMI-A-1"));
+        assert_eq!(manager.active_workspace_on_output("DP-1").await, Some(dp));
+        assert_eq!(manager.active_workspace_on_output("HDMI-A-1").await, Some(hdmi));
+    }
+
+    #[tokio::test]
+    async fn test_new_workspace_output_follows_policy() {
+        let manager = manager_with_outputs(Arc::default(), &["DP-1", "HDMI-A-1"]).await;
+        manager.set_pointer_output(Some("HDMI-A-1".to_string())).await;
+
+        let on_focus = manager.create_workspace(Some("Focus".to_string()), None, None, None).await.unwrap();
+        assert!(ids(&manager.workspaces_on_output("DP-1").await).contains(&on_focus));
+
+        manager.set_workspace_output_policy(WorkspaceOutputPolicy::FollowMouse).await;
+        let on_pointer = manager.create_workspace(Some("Mouse".to_string()), None, None, None).await.unwrap();
+        assert!(ids(&manager.workspaces_on_output("HDMI-A-1").await).contains(&on_pointer));
+        // Creating a workspace does not switch away from the one shown.
+        assert_ne!(manager.active_workspace_on_output("HDMI-A-1").await, Some(on_pointer));
+    }
+
+    #[tokio::test]
+    async fn test_move_workspace_to_output() {
+        let manager = manager_with_outputs(Arc::default(), &["DP-1", "HDMI-A-1"]).await;
+        let first = manager.active_workspace_on_output("DP-1").await.unwrap();
+        let second = manager.create_workspace(Some("Second".to_string()), None, None, None).await.unwrap();
+
+        manager.move_workspace_to_output(first, "HDMI-A-1").await.unwrap();
+        assert_eq!(manager.active_workspace_on_output("HDMI-A-1").await, Some(first));
+        assert_eq!(manager.active_workspace_on_output("DP-1").await, Some(second));
+        assert_eq!(manager.focused_output().await.as_deref(), Some("HDMI-A-1"));
+        let moved = manager.workspaces_on_output("HDMI-A-1").await.into_iter().find(|ws| ws.id() == first).unwrap();
+        assert_eq!(moved.preferred_output(), Some("HDMI-A-1"));
+        assert!(!moved.is_parked());
+
+        // Moving the last workspace away leaves the output with a fresh one.
+        manager.move_workspace_to_output(second, "HDMI-A-1").await.unwrap();
+        let replacement = manager.active_workspace_on_output("DP-1").await.unwrap();
+        assert!(replacement != first && replacement != second);
+
+        assert!(matches!(
+            manager.move_workspace_to_output(first, "DP-9").await,
+            Err(WorkspaceManagerError::OutputNotConnected(name)) if name == "DP-9"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_unplugged_output_workspaces_migrate_and_return() {
+        let manager = manager_with_outputs(Arc::default(), &["DP-1", "HDMI-A-1"]).await;
+        let dp = manager.active_workspace_on_output("DP-1").await.unwrap();
+        manager.set_focused_output("HDMI-A-1").await.unwrap();
+        let hdmi_first = manager.active_workspace_on_output("HDMI-A-1").await.unwrap();
+        let hdmi_second = manager.create_workspace(Some("Docs".to_string()), None, None, None).await.unwrap();
+        manager.set_active_workspace(hdmi_second).await.unwrap();
+
+        let mut events = manager.subscribe_to_workspace_events();
+        manager.output_disconnected("HDMI-A-1").await.unwrap();
+
+        let on_dp = manager.workspaces_on_output("DP-1").await;
+        assert_eq!(ids(&on_dp), vec![dp, hdmi_first, hdmi_second]);
+        assert!(on_dp.iter().filter(|ws| ws.id() != dp).all(|ws| ws.is_parked() && ws.preferred_output() == Some("HDMI-A-1")));
+        assert_eq!(manager.active_workspace_on_output("DP-1").await, Some(dp));
+        assert_eq!(manager.active_workspace_id(), Some(dp));
+        assert_eq!(manager.focused_output().await.as_deref(), Some("DP-1"));
+
+        let mut saw_disconnect = false;
+        while let Ok(event) = events.try_recv() {
+            if let WorkspaceEvent::OutputDisconnected { output_name, workspaces_moved_to } = event {
+                assert_eq!(output_name, "HDMI-A-1");
+                assert_eq!(workspaces_moved_to.as_deref(), Some("DP-1"));
+                saw_disconnect = true;
+            }
+        }
+        assert!(saw_disconnect);
+
+        manager.output_connected("HDMI-A-1").await.unwrap();
+        assert_eq!(ids(&manager.workspaces_on_output("DP-1").await), vec![dp]);
+        assert_eq!(ids(&manager.workspaces_on_output("HDMI-A-1").await), vec![hdmi_first, hdmi_second]);
+        assert_eq!(manager.active_workspace_on_output("HDMI-A-1").await, Some(hdmi_second));
+    }
+
+    #[tokio::test]
+    async fn test_output_placement_is_persisted() {
+        let provider = Arc::new(InMemoryConfigProvider::default());
+        let manager = manager_with_outputs(provider.clone(), &["DP-1", "HDMI-A-1"]).await;
+        let hdmi = manager.active_workspace_on_output("HDMI-A-1").await.unwrap();
+        let hdmi_name = manager.workspaces_on_output("HDMI-A-1").await[0].name().to_string();
+        drop(manager);
+
+        // Outputs connect in a different order after a restart.
+        let restored = manager_with_outputs(provider, &["HDMI-A-1", "DP-1"]).await;
+        let on_hdmi = restored.workspaces_on_output("HDMI-A-1").await;
+        assert_eq!(on_hdmi.len(), 1);
+        assert_eq!(on_hdmi[0].name(), hdmi_name);
+        assert_ne!(on_hdmi[0].id(), hdmi); // IDs are regenerated on load.
+        assert_eq!(restored.workspaces_on_output("DP-1").await.len(), 1);
+    }
 }
@@ -0,0 +1,35 @@
+// ANCHOR [ColorLutSampling]
+// Shared by color_lut_pass.wgsl and the color-managed textured quad; appended to both sources.
+// `lut` is an Rgba32Float 3D texture (red along x) over encoded RGB in 0..1. Float32 textures
+// are not filterable everywhere, so the trilinear interpolation is done by hand.
+fn sample_lut(lut: texture_3d<f32>, rgb: vec3<f32>) -> vec3<f32> {
+    let size = textureDimensions(lut);
+    let max_index = vec3<f32>(size - vec3<u32>(1u));
+    let x = clamp(rgb, vec3<f32>(0.0), vec3<f32>(1.0)) * max_index;
+    let base = min(floor(x), max_index - vec3<f32>(1.0));
+    let f = x - base;
+    let i = vec3<i32>(base);
+
+    let c000 = textureLoad(lut, i, 0).rgb;
+    let c100 = textureLoad(lut, i + vec3<i32>(1, 0, 0), 0).rgb;
+    let c010 = textureLoad(lut, i + vec3<i32>(0, 1, 0), 0).rgb;
+    let c110 = textureLoad(lut, i + vec3<i32>(1, 1, 0), 0).rgb;
+    let c001 = textureLoad(lut, i + vec3<i32>(0, 0, 1), 0).rgb;
+    let c101 = textureLoad(lut, i + vec3<i32>(1, 0, 1), 0).rgb;
+    let c011 = textureLoad(lut, i + vec3<i32>(0, 1, 1), 0).rgb;
+    let c111 = textureLoad(lut, i + vec3<i32>(1, 1, 1), 0).rgb;
+
+    let c00 = mix(c000, c100, f.x);
+    let c10 = mix(c010, c110, f.x);
+    let c01 = mix(c001, c101, f.x);
+    let c11 = mix(c011, c111, f.x);
+    return mix(mix(c00, c10, f.y), mix(c01, c11, f.y), f.z);
+}
+
+// Applies the LUT to a premultiplied colour.
+fn convert_premultiplied(lut: texture_3d<f32>, color: vec4<f32>) -> vec4<f32> {
+    if (color.a <= 0.0) {
+        return color;
+    }
+    return vec4<f32>(sample_lut(lut, color.rgb / color.a) * color.a, color.a);
+}
@@ -0,0 +1,10 @@
+// ANCHOR [ColorLutFragmentShader]
+// Output colour conversion; `sample_lut` and `convert_premultiplied` come from color_lut.wgsl.
+@group(0) @binding(0) var t_source: texture_2d<f32>;
+@group(0) @binding(1) var s_source: sampler;
+@group(1) @binding(0) var t_lut: texture_3d<f32>;
+
+@fragment
+fn fs_main(@location(0) tex_coords: vec2<f32>) -> @location(0) vec4<f32> {
+    return convert_premultiplied(t_lut, textureSample(t_source, s_source, tex_coords));
+}
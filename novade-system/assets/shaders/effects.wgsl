@@ -0,0 +1,119 @@
+// ANCHOR [EffectsShader]
+// Drop shadows and dual-Kawase backdrop blur. Mirrors src/compositor/effects/shape.rs and the
+// software renderer's blur (see src/compositor/effects/blur.rs for the kernels); keep them in
+// step. Rectangles are (x, y, width, height) in pixels of the render target.
+
+struct EffectUniforms {
+    // Area covered by the quad.
+    bounds: vec4<f32>,
+    // Casting rectangle of a shadow, blurred area of a blur.
+    rect: vec4<f32>,
+    // Shadow shape: `rect` moved by the offset and grown by the spread.
+    shadow_shape: vec4<f32>,
+    // Premultiplied shadow colour.
+    color: vec4<f32>,
+    target_size: vec2<f32>,
+    radius: f32,
+    shadow_radius: f32,
+    // Standard deviation of the shadow's Gaussian.
+    sigma: f32,
+    // Blur sample distance in texels of the sampled level.
+    offset: f32,
+    _padding: vec2<f32>,
+};
+
+@group(0) @binding(0) var<uniform> u: EffectUniforms;
+@group(1) @binding(0) var t_source: texture_2d<f32>;
+@group(1) @binding(1) var s_source: sampler;
+
+@vertex
+fn vs_main(@builtin(vertex_index) in_vertex_index: u32) -> @builtin(position) vec4<f32> {
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(0.0, 1.0), vec2<f32>(1.0, 1.0), vec2<f32>(0.0, 0.0),
+        vec2<f32>(1.0, 1.0), vec2<f32>(1.0, 0.0), vec2<f32>(0.0, 0.0)
+    );
+    let pixel = u.bounds.xy + corners[in_vertex_index] * u.bounds.zw;
+    let ndc = pixel / u.target_size * 2.0 - vec2<f32>(1.0);
+    return vec4<f32>(ndc.x, -ndc.y, 0.0, 1.0);
+}
+
+fn rounded_rect_distance(p: vec2<f32>, rect: vec4<f32>, radius: f32) -> f32 {
+    let half = rect.zw / 2.0;
+    let r = clamp(radius, 0.0, max(min(half.x, half.y), 0.0));
+    let q = abs(p - rect.xy - half) - half + vec2<f32>(r);
+    return length(max(q, vec2<f32>(0.0))) + min(max(q.x, q.y), 0.0) - r;
+}
+
+fn rounded_rect_coverage(p: vec2<f32>, rect: vec4<f32>, radius: f32) -> f32 {
+    return clamp(0.5 - rounded_rect_distance(p, rect, radius), 0.0, 1.0);
+}
+
+fn erf(x: f32) -> f32 {
+    let t = 1.0 / (1.0 + 0.3275911 * abs(x));
+    let poly = t * (0.2548296 + t * (-0.28449674 + t * (1.4214137 + t * (-1.453152 + t * 1.0614054))));
+    return sign(x) * (1.0 - poly * exp(-x * x));
+}
+
+@fragment
+fn fs_shadow(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let p = position.xy;
+    let distance = rounded_rect_distance(p, u.shadow_shape, u.shadow_radius);
+    var shadow: f32;
+    if (u.sigma > 0.0) {
+        shadow = 0.5 - 0.5 * erf(distance / (u.sigma * 1.4142135));
+    } else {
+        shadow = clamp(0.5 - distance, 0.0, 1.0);
+    }
+    return u.color * (shadow * (1.0 - rounded_rect_coverage(p, u.rect, u.radius)));
+}
+
+// `pixel` is in texels of `t_source`.
+fn sample_texels(pixel: vec2<f32>) -> vec4<f32> {
+    return textureSample(t_source, s_source, pixel / vec2<f32>(textureDimensions(t_source)));
+}
+
+fn blur_down(p: vec2<f32>) -> vec4<f32> {
+    let o = u.offset;
+    var sum = sample_texels(p) * 4.0;
+    sum += sample_texels(p + vec2<f32>(-o, -o));
+    sum += sample_texels(p + vec2<f32>(o, -o));
+    sum += sample_texels(p + vec2<f32>(-o, o));
+    sum += sample_texels(p + vec2<f32>(o, o));
+    return sum / 8.0;
+}
+
+fn blur_up(p: vec2<f32>) -> vec4<f32> {
+    let o = u.offset;
+    let d = 2.0 * o;
+    var sum = sample_texels(p + vec2<f32>(-d, 0.0));
+    sum += sample_texels(p + vec2<f32>(d, 0.0));
+    sum += sample_texels(p + vec2<f32>(0.0, -d));
+    sum += sample_texels(p + vec2<f32>(0.0, d));
+    sum += sample_texels(p + vec2<f32>(-o, -o)) * 2.0;
+    sum += sample_texels(p + vec2<f32>(o, -o)) * 2.0;
+    sum += sample_texels(p + vec2<f32>(-o, o)) * 2.0;
+    sum += sample_texels(p + vec2<f32>(o, o)) * 2.0;
+    return sum / 12.0;
+}
+
+// Position of the current pixel in texels of `t_source`.
+fn source_position(position: vec4<f32>) -> vec2<f32> {
+    return position.xy / u.target_size * vec2<f32>(textureDimensions(t_source));
+}
+
+@fragment
+fn fs_blur_down(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    return blur_down(source_position(position));
+}
+
+@fragment
+fn fs_blur_up(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    return blur_up(source_position(position));
+}
+
+// Last upsample, straight into the scene behind the surface. Blended as premultiplied "over",
+// which matches the software renderer's mix wherever the scene is opaque.
+@fragment
+fn fs_blur_composite(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    return blur_up(source_position(position)) * rounded_rect_coverage(position.xy, u.rect, u.radius);
+}
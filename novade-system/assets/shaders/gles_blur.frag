@@ -0,0 +1,96 @@
+#version 100
+
+//_DEFINES_
+
+#if defined(EXTERNAL)
+#extension GL_OES_EGL_image_external : require
+#endif
+
+// ANCHOR [GlesBlurShader]
+// One dual-Kawase pass as a GLES texture shader, with the kernels of
+// src/compositor/effects/blur.rs and effects.wgsl; keep them in step.
+precision highp float;
+
+#if defined(EXTERNAL)
+uniform samplerExternalOES tex;
+#else
+uniform sampler2D tex;
+#endif
+
+uniform float alpha;
+varying vec2 v_coords;
+
+// 0: downsample, 1: upsample, 2: upsample and cut out `rect` with rounded corners.
+uniform float mode;
+// Size of one texel of `tex` in texture coordinates.
+uniform vec2 texel;
+// Sample distance in texels of `tex`.
+uniform float offset;
+// Blurred window and its corner radius, in pixels of the target.
+uniform vec4 rect;
+uniform float radius;
+uniform vec2 target_size;
+
+#if defined(DEBUG_FLAGS)
+uniform float tint;
+#endif
+
+vec4 sample_texels(vec2 o) {
+    vec4 color = texture2D(tex, v_coords + o * texel);
+#if defined(NO_ALPHA)
+    color = vec4(color.rgb, 1.0);
+#endif
+    return color;
+}
+
+vec4 blur_down() {
+    float o = offset;
+    vec4 sum = sample_texels(vec2(0.0)) * 4.0;
+    sum += sample_texels(vec2(-o, -o));
+    sum += sample_texels(vec2(o, -o));
+    sum += sample_texels(vec2(-o, o));
+    sum += sample_texels(vec2(o, o));
+    return sum / 8.0;
+}
+
+vec4 blur_up() {
+    float o = offset;
+    float d = 2.0 * o;
+    vec4 sum = sample_texels(vec2(-d, 0.0));
+    sum += sample_texels(vec2(d, 0.0));
+    sum += sample_texels(vec2(0.0, -d));
+    sum += sample_texels(vec2(0.0, d));
+    sum += sample_texels(vec2(-o, -o)) * 2.0;
+    sum += sample_texels(vec2(o, -o)) * 2.0;
+    sum += sample_texels(vec2(-o, o)) * 2.0;
+    sum += sample_texels(vec2(o, o)) * 2.0;
+    return sum / 12.0;
+}
+
+float rounded_rect_distance(vec2 p, vec4 r, float corner) {
+    vec2 half_size = r.zw / 2.0;
+    float c = clamp(corner, 0.0, max(min(half_size.x, half_size.y), 0.0));
+    vec2 q = abs(p - r.xy - half_size) - half_size + vec2(c);
+    return length(max(q, vec2(0.0))) + min(max(q.x, q.y), 0.0) - c;
+}
+
+void main() {
+    vec4 color;
+    if (mode < 0.5) {
+        color = blur_down();
+    } else {
+        color = blur_up();
+    }
+    if (mode > 1.5) {
+        vec2 p = v_coords * target_size;
+        color = color * clamp(0.5 - rounded_rect_distance(p, rect, radius), 0.0, 1.0);
+    }
+    color = color * alpha;
+
+#if defined(DEBUG_FLAGS)
+    if (tint == 1.0)
+        color = vec4(0.0, 0.2, 0.0, 0.2) + color * 0.8;
+#endif
+
+    gl_FragColor = color;
+}
@@ -0,0 +1,96 @@
+#version 100
+
+//_DEFINES_
+
+#if defined(EXTERNAL)
+#extension GL_OES_EGL_image_external : require
+#endif
+
+// ANCHOR [GlesColorLutShader]
+// Texture shader converting colours through a LUT, optionally cutting out rounded corners
+// like gles_rounded.frag. The LUT is a 2D texture with the blue slices side by side (see
+// `Lut3d::to_tiled_rgba_f16`); the trilinear interpolation is done by hand as in
+// color_lut.wgsl, so the texture needs no filtering.
+precision highp float;
+
+#if defined(EXTERNAL)
+uniform samplerExternalOES tex;
+#else
+uniform sampler2D tex;
+#endif
+
+uniform float alpha;
+varying vec2 v_coords;
+
+uniform sampler2D lut;
+// Points per axis.
+uniform float lut_size;
+
+// Where the drawn texture lies relative to the window geometry; no corners are cut with a
+// radius of 0.
+uniform vec2 element_offset;
+uniform vec2 element_size;
+uniform vec2 geo_size;
+uniform float corner_radius;
+
+#if defined(DEBUG_FLAGS)
+uniform float tint;
+#endif
+
+vec3 lut_entry(vec3 i) {
+    vec2 texel = vec2(i.z * lut_size + i.x, i.y) + vec2(0.5);
+    return texture2D(lut, texel / vec2(lut_size * lut_size, lut_size)).rgb;
+}
+
+vec3 sample_lut(vec3 rgb) {
+    float max_index = lut_size - 1.0;
+    vec3 x = clamp(rgb, 0.0, 1.0) * max_index;
+    vec3 base = min(floor(x), vec3(max_index - 1.0));
+    vec3 f = x - base;
+
+    vec3 c000 = lut_entry(base);
+    vec3 c100 = lut_entry(base + vec3(1.0, 0.0, 0.0));
+    vec3 c010 = lut_entry(base + vec3(0.0, 1.0, 0.0));
+    vec3 c110 = lut_entry(base + vec3(1.0, 1.0, 0.0));
+    vec3 c001 = lut_entry(base + vec3(0.0, 0.0, 1.0));
+    vec3 c101 = lut_entry(base + vec3(1.0, 0.0, 1.0));
+    vec3 c011 = lut_entry(base + vec3(0.0, 1.0, 1.0));
+    vec3 c111 = lut_entry(base + vec3(1.0, 1.0, 1.0));
+
+    vec3 c00 = mix(c000, c100, f.x);
+    vec3 c10 = mix(c010, c110, f.x);
+    vec3 c01 = mix(c001, c101, f.x);
+    vec3 c11 = mix(c011, c111, f.x);
+    return mix(mix(c00, c10, f.y), mix(c01, c11, f.y), f.z);
+}
+
+float rounded_rect_distance(vec2 p, vec4 r, float corner) {
+    vec2 half_size = r.zw / 2.0;
+    float c = clamp(corner, 0.0, max(min(half_size.x, half_size.y), 0.0));
+    vec2 q = abs(p - r.xy - half_size) - half_size + vec2(c);
+    return length(max(q, vec2(0.0))) + min(max(q.x, q.y), 0.0) - c;
+}
+
+void main() {
+    vec4 color = texture2D(tex, v_coords);
+#if defined(NO_ALPHA)
+    color = vec4(color.rgb, 1.0);
+#endif
+
+    // The LUT works on straight colours.
+    if (color.a > 0.0) {
+        color = vec4(sample_lut(color.rgb / color.a) * color.a, color.a);
+    }
+    if (corner_radius > 0.0) {
+        vec2 p = element_offset + v_coords * element_size;
+        color = color * clamp(0.5 - rounded_rect_distance(p, vec4(vec2(0.0), geo_size), corner_radius), 0.0, 1.0);
+    }
+    color = color * alpha;
+
+#if defined(DEBUG_FLAGS)
+    if (tint == 1.0)
+        color = vec4(0.0, 0.2, 0.0, 0.2) + color * 0.8;
+#endif
+
+    gl_FragColor = color;
+}
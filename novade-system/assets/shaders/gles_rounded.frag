@@ -0,0 +1,56 @@
+#version 100
+
+//_DEFINES_
+
+#if defined(EXTERNAL)
+#extension GL_OES_EGL_image_external : require
+#endif
+
+// ANCHOR [GlesRoundedCornersShader]
+// Texture shader masking everything outside a window's rounded geometry. Positions are in
+// pixels from the window geometry's top left corner.
+precision highp float;
+
+#if defined(EXTERNAL)
+uniform samplerExternalOES tex;
+#else
+uniform sampler2D tex;
+#endif
+
+uniform float alpha;
+varying vec2 v_coords;
+
+// Where the drawn texture lies relative to the window geometry.
+uniform vec2 element_offset;
+uniform vec2 element_size;
+uniform vec2 geo_size;
+uniform float corner_radius;
+
+#if defined(DEBUG_FLAGS)
+uniform float tint;
+#endif
+
+float rounded_rect_distance(vec2 p, vec4 r, float corner) {
+    vec2 half_size = r.zw / 2.0;
+    float c = clamp(corner, 0.0, max(min(half_size.x, half_size.y), 0.0));
+    vec2 q = abs(p - r.xy - half_size) - half_size + vec2(c);
+    return length(max(q, vec2(0.0))) + min(max(q.x, q.y), 0.0) - c;
+}
+
+void main() {
+    vec4 color = texture2D(tex, v_coords);
+#if defined(NO_ALPHA)
+    color = vec4(color.rgb, 1.0);
+#endif
+
+    vec2 p = element_offset + v_coords * element_size;
+    float coverage = clamp(0.5 - rounded_rect_distance(p, vec4(vec2(0.0), geo_size), corner_radius), 0.0, 1.0);
+    color = color * coverage * alpha;
+
+#if defined(DEBUG_FLAGS)
+    if (tint == 1.0)
+        color = vec4(0.0, 0.2, 0.0, 0.2) + color * 0.8;
+#endif
+
+    gl_FragColor = color;
+}
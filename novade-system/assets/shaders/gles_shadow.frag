@@ -0,0 +1,64 @@
+#version 100
+
+//_DEFINES_
+
+// ANCHOR [GlesShadowShader]
+// Drop shadow as a GLES pixel shader. Mirrors src/compositor/effects/shape.rs and
+// effects.wgsl; keep them in step. Rectangles are (x, y, width, height) in pixels of the
+// element, measured from its top left corner.
+precision highp float;
+
+uniform float alpha;
+uniform vec2 size;
+varying vec2 v_coords;
+
+// Casting rectangle and its corner radius.
+uniform vec4 rect;
+uniform float radius;
+// Shadow shape: `rect` moved by the offset and grown by the spread.
+uniform vec4 shadow_shape;
+uniform float shadow_radius;
+// Standard deviation of the Gaussian.
+uniform float sigma;
+// Premultiplied shadow colour.
+uniform vec4 color;
+
+#if defined(DEBUG_FLAGS)
+uniform float tint;
+#endif
+
+float rounded_rect_distance(vec2 p, vec4 r, float corner) {
+    vec2 half_size = r.zw / 2.0;
+    float c = clamp(corner, 0.0, max(min(half_size.x, half_size.y), 0.0));
+    vec2 q = abs(p - r.xy - half_size) - half_size + vec2(c);
+    return length(max(q, vec2(0.0))) + min(max(q.x, q.y), 0.0) - c;
+}
+
+float rounded_rect_coverage(vec2 p, vec4 r, float corner) {
+    return clamp(0.5 - rounded_rect_distance(p, r, corner), 0.0, 1.0);
+}
+
+float erf(float x) {
+    float t = 1.0 / (1.0 + 0.3275911 * abs(x));
+    float poly = t * (0.2548296 + t * (-0.28449674 + t * (1.4214137 + t * (-1.453152 + t * 1.0614054))));
+    return sign(x) * (1.0 - poly * exp(-x * x));
+}
+
+void main() {
+    vec2 p = v_coords * size;
+    float distance = rounded_rect_distance(p, shadow_shape, shadow_radius);
+    float shadow;
+    if (sigma > 0.0) {
+        shadow = 0.5 - 0.5 * erf(distance / (sigma * 1.4142135));
+    } else {
+        shadow = clamp(0.5 - distance, 0.0, 1.0);
+    }
+    vec4 result = color * (shadow * (1.0 - rounded_rect_coverage(p, rect, radius))) * alpha;
+
+#if defined(DEBUG_FLAGS)
+    if (tint == 1.0)
+        result = vec4(0.0, 0.2, 0.0, 0.2) + result * 0.8;
+#endif
+
+    gl_FragColor = result;
+}
@@ -0,0 +1,44 @@
+//! Occlusion culling with the grid index versus a single-cell index, where every layer is
+//! compared against every layer above it.
+//!
+//! Run with `cargo bench -p novade-system --bench occlusion`.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use novade_core::types::geometry::Rect;
+use novade_system::compositor::spatial_index::{compute_visible_regions, OcclusionLayer, SpatialIndex};
+
+const OUTPUT: Rect<f32> = Rect::from_coords(0.0, 0.0, 3840.0, 2160.0);
+
+/// Opaque windows cascaded across the output, wrapping around so several stacks overlap.
+fn cascaded_layers(count: usize) -> Vec<OcclusionLayer<u32>> {
+    (0..count)
+        .map(|i| {
+            let x = (i * 37 % 3200) as f32;
+            let y = (i * 23 % 1700) as f32;
+            let bounds = Rect::from_coords(x, y, 640.0, 480.0)
+                .intersection(&OUTPUT)
+                .unwrap_or(OUTPUT);
+            OcclusionLayer { key: i as u32, bounds, opaque: bounds }
+        })
+        .collect()
+}
+
+fn bench_occlusion(c: &mut Criterion) {
+    let mut group = c.benchmark_group("occlusion");
+    for count in [200usize, 500, 1000] {
+        let layers = cascaded_layers(count);
+        for (name, cell_size) in [("grid_256", 256.0f32), ("single_cell", 4096.0)] {
+            let mut index = SpatialIndex::new(&OUTPUT, cell_size);
+            group.bench_with_input(BenchmarkId::new(name, count), &layers, |b, layers| {
+                b.iter(|| {
+                    index.rebuild(layers.iter().map(|l| (l.key, l.bounds)), &OUTPUT);
+                    black_box(compute_visible_regions(layers, &index))
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_occlusion);
+criterion_main!(benches);
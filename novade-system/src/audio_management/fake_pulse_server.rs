@@ -0,0 +1,392 @@
+// novade-system/src/audio_management/fake_pulse_server.rs
+
+//! A sound server speaking enough of the PulseAudio protocol to test the manager against.
+//!
+//! It listens on a socket in a temporary directory and keeps two sinks, a microphone with the
+//! monitor of the speakers, and one playback stream. Requests change that state and notify
+//! subscribed connections the way PulseAudio does; tests change it "from another mixer",
+//! start streams and plug devices through the methods below.
+
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use tempfile::TempDir;
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use super::pulse::{
+    command, error_code, read_packet, subscription, write_device_info, write_packet, write_server_info, write_sink_input_info,
+    write_source_output_info, DeviceInfo, ServerInfo, SinkInputInfo, SourceOutputInfo, COOKIE_LENGTH, INVALID_INDEX, PROTOCOL_VERSION,
+};
+use super::tagstruct::{CVolume, Proplist, TagReader, TagWriter};
+
+pub(crate) const SPEAKERS: &str = "alsa_output.speakers";
+pub(crate) const HDMI: &str = "alsa_output.hdmi";
+pub(crate) const MICROPHONE: &str = "alsa_input.microphone";
+
+pub(crate) struct FakePulseServer {
+    _dir: TempDir,
+    path: PathBuf,
+    state: Arc<Mutex<FakeState>>,
+    task: JoinHandle<()>,
+}
+
+struct FakeState {
+    server: ServerInfo,
+    sinks: Vec<DeviceInfo>,
+    sources: Vec<DeviceInfo>,
+    inputs: Vec<SinkInputInfo>,
+    outputs: Vec<SourceOutputInfo>,
+    next_index: u32,
+    /// Subscription masks of the connections and where their packets go.
+    subscribers: Vec<(u32, mpsc::UnboundedSender<Vec<u8>>)>,
+}
+
+fn device(index: u32, name: &str, description: &str, volume: f64, mute: bool) -> DeviceInfo {
+    DeviceInfo { index, name: name.to_string(), description: description.to_string(), volume: CVolume::uniform(2, volume), mute, ..Default::default() }
+}
+
+fn stream(index: u32, application: &str, sink: u32) -> SinkInputInfo {
+    let mut proplist = Proplist::default();
+    proplist.set_str("application.name", application);
+    SinkInputInfo { index, name: "Playback".to_string(), sink, volume: CVolume::uniform(2, 0.8), mute: false, has_volume: true, proplist }
+}
+
+fn recording(index: u32, application: &str, source: u32) -> SourceOutputInfo {
+    let mut proplist = Proplist::default();
+    proplist.set_str("application.name", application);
+    SourceOutputInfo { index, name: "Capture".to_string(), source, volume: CVolume::uniform(1, 1.0), mute: false, has_volume: true, proplist }
+}
+
+impl FakePulseServer {
+    pub(crate) async fn start() -> Self {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("native");
+        let listener = UnixListener::bind(&path).unwrap();
+        let monitor = DeviceInfo { monitor_of_sink: Some(0), ..device(1, "alsa_output.speakers.monitor", "Monitor of Built-in Speakers", 1.0, false) };
+        let state = Arc::new(Mutex::new(FakeState {
+            server: ServerInfo {
+                server_name: "pulseaudio".to_string(),
+                server_version: "16.1".to_string(),
+                default_sink: Some(SPEAKERS.to_string()),
+                default_source: Some(MICROPHONE.to_string()),
+            },
+            sinks: vec![device(0, SPEAKERS, "Built-in Speakers", 0.75, false), device(1, HDMI, "HDMI Audio", 0.5, true)],
+            sources: vec![device(0, MICROPHONE, "Built-in Microphone", 0.8, false), monitor],
+            inputs: vec![stream(0, "Music Player", 0)],
+            outputs: Vec::new(),
+            next_index: 10,
+            subscribers: Vec::new(),
+        }));
+        let accept_state = state.clone();
+        let task = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(serve_connection(stream, accept_state.clone()));
+            }
+        });
+        Self { _dir: dir, path, state, task }
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub(crate) fn sink(&self, name: &str) -> DeviceInfo {
+        self.state.lock().unwrap().sinks.iter().find(|sink| sink.name == name).cloned().unwrap()
+    }
+
+    pub(crate) fn source(&self, name: &str) -> DeviceInfo {
+        self.state.lock().unwrap().sources.iter().find(|source| source.name == name).cloned().unwrap()
+    }
+
+    pub(crate) fn stream(&self, index: u32) -> Option<SinkInputInfo> {
+        self.state.lock().unwrap().inputs.iter().find(|input| input.index == index).cloned()
+    }
+
+    pub(crate) fn recording(&self, index: u32) -> Option<SourceOutputInfo> {
+        self.state.lock().unwrap().outputs.iter().find(|output| output.index == index).cloned()
+    }
+
+    pub(crate) fn default_sink(&self) -> Option<String> {
+        self.state.lock().unwrap().server.default_sink.clone()
+    }
+
+    /// Starts a stream of `application` on the sink `sink`; returns its index.
+    pub(crate) fn add_stream(&self, application: &str, sink: u32) -> u32 {
+        let mut state = self.state.lock().unwrap();
+        let index = state.next_index;
+        state.next_index += 1;
+        state.inputs.push(stream(index, application, sink));
+        state.notify(subscription::FACILITY_SINK_INPUT, subscription::TYPE_NEW, index);
+        index
+    }
+
+    pub(crate) fn remove_stream(&self, index: u32) {
+        let mut state = self.state.lock().unwrap();
+        state.inputs.retain(|input| input.index != index);
+        state.notify(subscription::FACILITY_SINK_INPUT, subscription::TYPE_REMOVE, index);
+    }
+
+    /// Starts a recording of `application` from the source `source`; returns its index.
+    pub(crate) fn add_recording(&self, application: &str, source: u32) -> u32 {
+        let mut state = self.state.lock().unwrap();
+        let index = state.next_index;
+        state.next_index += 1;
+        state.outputs.push(recording(index, application, source));
+        state.notify(subscription::FACILITY_SOURCE_OUTPUT, subscription::TYPE_NEW, index);
+        index
+    }
+
+    /// Plugs in a sink, or a source if `source`; returns its index.
+    pub(crate) fn add_device(&self, source: bool, name: &str, description: &str) -> u32 {
+        let mut state = self.state.lock().unwrap();
+        let index = state.next_index;
+        state.next_index += 1;
+        let devices = if source { &mut state.sources } else { &mut state.sinks };
+        devices.push(device(index, name, description, 1.0, false));
+        state.notify_device_event(source, subscription::TYPE_NEW, index);
+        index
+    }
+
+    /// Unplugs a device; like PulseAudio, its streams move to the default device, or the
+    /// first one left if it was the default.
+    pub(crate) fn remove_device(&self, source: bool, name: &str) {
+        let mut state = self.state.lock().unwrap();
+        let state = &mut *state;
+        let devices = if source { &mut state.sources } else { &mut state.sinks };
+        let index = devices.iter().find(|device| device.name == name).unwrap().index;
+        devices.retain(|device| device.index != index);
+        let default = if source { &mut state.server.default_source } else { &mut state.server.default_sink };
+        let default_removed = default.as_deref() == Some(name);
+        if default_removed {
+            *default = devices.iter().find(|device| device.monitor_of_sink.is_none()).map(|device| device.name.clone());
+        }
+        let rescue = default.as_ref().and_then(|default| devices.iter().find(|device| &device.name == default)).map(|device| device.index);
+        let moved: Vec<u32> = if source {
+            state.outputs.iter_mut().filter(|output| output.source == index).map(|output| {
+                output.source = rescue.unwrap_or(INVALID_INDEX);
+                output.index
+            }).collect()
+        } else {
+            state.inputs.iter_mut().filter(|input| input.sink == index).map(|input| {
+                input.sink = rescue.unwrap_or(INVALID_INDEX);
+                input.index
+            }).collect()
+        };
+        state.notify_device_event(source, subscription::TYPE_REMOVE, index);
+        if default_removed {
+            state.notify(subscription::FACILITY_SERVER, subscription::TYPE_CHANGE, INVALID_INDEX);
+        }
+        let facility = if source { subscription::FACILITY_SOURCE_OUTPUT } else { subscription::FACILITY_SINK_INPUT };
+        for stream in moved {
+            state.notify(facility, subscription::TYPE_CHANGE, stream);
+        }
+    }
+
+    /// Changes the volume of a sink as another mixer would.
+    pub(crate) fn set_sink_volume(&self, name: &str, volume: f64) {
+        let mut state = self.state.lock().unwrap();
+        let sink = state.sinks.iter_mut().find(|sink| sink.name == name).unwrap();
+        sink.volume = sink.volume.scaled_to(volume);
+        let index = sink.index;
+        state.notify(subscription::FACILITY_SINK, subscription::TYPE_CHANGE, index);
+    }
+}
+
+impl Drop for FakePulseServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn serve_connection(stream: UnixStream, state: Arc<Mutex<FakeState>>) {
+    let (mut reader, mut writer) = stream.into_split();
+    let (sender, mut outgoing) = mpsc::unbounded_channel::<Vec<u8>>();
+    tokio::spawn(async move {
+        while let Some(packet) = outgoing.recv().await {
+            if write_packet(&mut writer, &packet).await.is_err() {
+                break;
+            }
+        }
+    });
+    while let Ok(packet) = read_packet(&mut reader).await {
+        let mut args = TagReader::new(packet);
+        let (Ok(command), Ok(tag)) = (args.get_u32(), args.get_u32()) else { break };
+        let mut body = TagWriter::new();
+        let result = state.lock().unwrap().handle(command, &mut args, &mut body, &sender);
+        let mut reply = TagWriter::new();
+        match result {
+            Ok(()) => {
+                reply.put_u32(command::REPLY).put_u32(tag);
+            }
+            Err(code) => {
+                reply.put_u32(command::ERROR).put_u32(tag).put_u32(code);
+                body = TagWriter::new();
+            }
+        }
+        let mut packet = reply.into_bytes();
+        packet.extend(body.into_bytes());
+        if sender.send(packet).is_err() {
+            break;
+        }
+    }
+}
+
+impl FakeState {
+    fn handle(&mut self, command: u32, args: &mut TagReader, reply: &mut TagWriter, sender: &mpsc::UnboundedSender<Vec<u8>>) -> Result<(), u32> {
+        let invalid = |_| error_code::INVALID;
+        match command {
+            command::AUTH => {
+                args.get_u32().map_err(invalid)?;
+                if args.get_arbitrary().map_err(invalid)?.len() != COOKIE_LENGTH {
+                    return Err(error_code::INVALID);
+                }
+                reply.put_u32(PROTOCOL_VERSION);
+            }
+            command::SET_CLIENT_NAME => {
+                args.get_proplist().map_err(invalid)?;
+                reply.put_u32(self.next_index);
+            }
+            command::GET_SERVER_INFO => write_server_info(reply, &self.server),
+            command::GET_SINK_INFO_LIST => self.sinks.iter().for_each(|sink| write_device_info(reply, sink)),
+            command::GET_SOURCE_INFO_LIST => self.sources.iter().for_each(|source| write_device_info(reply, source)),
+            command::GET_SINK_INPUT_INFO_LIST => self.inputs.iter().for_each(|input| write_sink_input_info(reply, input)),
+            command::GET_SOURCE_OUTPUT_INFO_LIST => self.outputs.iter().for_each(|output| write_source_output_info(reply, output)),
+            command::GET_SINK_INFO | command::GET_SOURCE_INFO => {
+                let source = command == command::GET_SOURCE_INFO;
+                let index = self.device_index(source, args)?;
+                let devices = if source { &self.sources } else { &self.sinks };
+                write_device_info(reply, devices.iter().find(|device| device.index == index).unwrap());
+            }
+            command::GET_SINK_INPUT_INFO => {
+                let index = args.get_u32().map_err(invalid)?;
+                write_sink_input_info(reply, self.input(index)?);
+            }
+            command::GET_SOURCE_OUTPUT_INFO => {
+                let index = args.get_u32().map_err(invalid)?;
+                write_source_output_info(reply, self.output(index)?);
+            }
+            command::SUBSCRIBE => {
+                let mask = args.get_u32().map_err(invalid)?;
+                self.subscribers.retain(|(_, subscriber)| !subscriber.same_channel(sender));
+                self.subscribers.push((mask, sender.clone()));
+            }
+            command::SET_SINK_VOLUME | command::SET_SOURCE_VOLUME => {
+                let source = command == command::SET_SOURCE_VOLUME;
+                let index = self.device_index(source, args)?;
+                let volume = args.get_cvolume().map_err(invalid)?;
+                self.device_mut(source, index).volume = volume;
+                self.notify_device(source, index);
+            }
+            command::SET_SINK_MUTE | command::SET_SOURCE_MUTE => {
+                let source = command == command::SET_SOURCE_MUTE;
+                let index = self.device_index(source, args)?;
+                let mute = args.get_bool().map_err(invalid)?;
+                self.device_mut(source, index).mute = mute;
+                self.notify_device(source, index);
+            }
+            command::SET_DEFAULT_SINK | command::SET_DEFAULT_SOURCE => {
+                let source = command == command::SET_DEFAULT_SOURCE;
+                let name = args.get_string().map_err(invalid)?.ok_or(error_code::INVALID)?;
+                let devices = if source { &self.sources } else { &self.sinks };
+                if !devices.iter().any(|device| device.name == name) {
+                    return Err(error_code::NO_ENTITY);
+                }
+                if source {
+                    self.server.default_source = Some(name);
+                } else {
+                    self.server.default_sink = Some(name);
+                }
+                self.notify(subscription::FACILITY_SERVER, subscription::TYPE_CHANGE, INVALID_INDEX);
+            }
+            command::SET_SINK_INPUT_VOLUME => {
+                let index = args.get_u32().map_err(invalid)?;
+                let volume = args.get_cvolume().map_err(invalid)?;
+                self.input_mut(index)?.volume = volume;
+                self.notify(subscription::FACILITY_SINK_INPUT, subscription::TYPE_CHANGE, index);
+            }
+            command::SET_SINK_INPUT_MUTE => {
+                let index = args.get_u32().map_err(invalid)?;
+                let mute = args.get_bool().map_err(invalid)?;
+                self.input_mut(index)?.mute = mute;
+                self.notify(subscription::FACILITY_SINK_INPUT, subscription::TYPE_CHANGE, index);
+            }
+            command::MOVE_SINK_INPUT => {
+                let index = args.get_u32().map_err(invalid)?;
+                let sink = self.device_index(false, args)?;
+                self.input_mut(index)?.sink = sink;
+                self.notify(subscription::FACILITY_SINK_INPUT, subscription::TYPE_CHANGE, index);
+            }
+            command::SET_SOURCE_OUTPUT_VOLUME => {
+                let index = args.get_u32().map_err(invalid)?;
+                let volume = args.get_cvolume().map_err(invalid)?;
+                self.output_mut(index)?.volume = volume;
+                self.notify(subscription::FACILITY_SOURCE_OUTPUT, subscription::TYPE_CHANGE, index);
+            }
+            command::SET_SOURCE_OUTPUT_MUTE => {
+                let index = args.get_u32().map_err(invalid)?;
+                let mute = args.get_bool().map_err(invalid)?;
+                self.output_mut(index)?.mute = mute;
+                self.notify(subscription::FACILITY_SOURCE_OUTPUT, subscription::TYPE_CHANGE, index);
+            }
+            command::MOVE_SOURCE_OUTPUT => {
+                let index = args.get_u32().map_err(invalid)?;
+                let source = self.device_index(true, args)?;
+                self.output_mut(index)?.source = source;
+                self.notify(subscription::FACILITY_SOURCE_OUTPUT, subscription::TYPE_CHANGE, index);
+            }
+            _ => return Err(error_code::COMMAND),
+        }
+        Ok(())
+    }
+
+    /// Reads a device addressed by index, or by name with an invalid index.
+    fn device_index(&self, source: bool, args: &mut TagReader) -> Result<u32, u32> {
+        let index = args.get_u32().map_err(|_| error_code::INVALID)?;
+        let name = args.get_string().map_err(|_| error_code::INVALID)?;
+        let devices = if source { &self.sources } else { &self.sinks };
+        devices
+            .iter()
+            .find(|device| if index == INVALID_INDEX { Some(&device.name) == name.as_ref() } else { device.index == index })
+            .map(|device| device.index)
+            .ok_or(error_code::NO_ENTITY)
+    }
+
+    fn device_mut(&mut self, source: bool, index: u32) -> &mut DeviceInfo {
+        let devices = if source { &mut self.sources } else { &mut self.sinks };
+        devices.iter_mut().find(|device| device.index == index).unwrap()
+    }
+
+    fn input(&self, index: u32) -> Result<&SinkInputInfo, u32> {
+        self.inputs.iter().find(|input| input.index == index).ok_or(error_code::NO_ENTITY)
+    }
+
+    fn input_mut(&mut self, index: u32) -> Result<&mut SinkInputInfo, u32> {
+        self.inputs.iter_mut().find(|input| input.index == index).ok_or(error_code::NO_ENTITY)
+    }
+
+    fn output(&self, index: u32) -> Result<&SourceOutputInfo, u32> {
+        self.outputs.iter().find(|output| output.index == index).ok_or(error_code::NO_ENTITY)
+    }
+
+    fn output_mut(&mut self, index: u32) -> Result<&mut SourceOutputInfo, u32> {
+        self.outputs.iter_mut().find(|output| output.index == index).ok_or(error_code::NO_ENTITY)
+    }
+
+    fn notify_device(&mut self, source: bool, index: u32) {
+        self.notify_device_event(source, subscription::TYPE_CHANGE, index);
+    }
+
+    fn notify_device_event(&mut self, source: bool, kind: u32, index: u32) {
+        let facility = if source { subscription::FACILITY_SOURCE } else { subscription::FACILITY_SINK };
+        self.notify(facility, kind, index);
+    }
+
+    fn notify(&mut self, facility: u32, kind: u32, index: u32) {
+        let mut event = TagWriter::new();
+        event.put_u32(command::SUBSCRIBE_EVENT).put_u32(u32::MAX).put_u32(facility | kind).put_u32(index);
+        let packet = event.into_bytes();
+        self.subscribers.retain(|(mask, subscriber)| mask & (1 << facility) == 0 || subscriber.send(packet.clone()).is_ok());
+    }
+}
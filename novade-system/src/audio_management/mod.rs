@@ -0,0 +1,1279 @@
+//! Audio management module for the NovaDE system layer.
+//!
+//! This module provides audio management functionality for the NovaDE desktop environment,
+//! controlling system audio. [`PulseAudioManager`] drives the user's sound server through the
+//! PulseAudio native protocol client in [`pulse`]. There is no PipeWire protocol client: on
+//! PipeWire systems this needs `pipewire-pulse`, PipeWire's PulseAudio server.
+
+pub mod pulse;
+pub mod tagstruct;
+#[cfg(test)]
+pub(crate) mod fake_pulse_server;
+
+use async_trait::async_trait;
+use std::sync::Mutex;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use crate::error::{SystemError, SystemResult, to_system_error, SystemErrorKind};
+use self::pulse::{DeviceInfo, EventKind, PulseConnection, PulseError, SinkInputInfo, SourceOutputInfo, SubscriptionEvent, subscription, INVALID_INDEX};
+use self::tagstruct::VOLUME_NORM;
+
+/// Audio device type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioDeviceType {
+    /// Output device (speakers, headphones).
+    Output,
+    /// Input device (microphone).
+    Input,
+}
+
+/// Audio device.
+#[derive(Debug, Clone)]
+pub struct AudioDevice {
+    /// The device ID.
+    id: String,
+    /// The device name.
+    name: String,
+    /// The device type.
+    device_type: AudioDeviceType,
+    /// Whether the device is the default for its type.
+    is_default: bool,
+    /// The device volume (0.0-1.0).
+    volume: f64,
+    /// Whether the device is muted.
+    muted: bool,
+}
+
+impl AudioDevice {
+    /// Creates a new audio device.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The device ID
+    /// * `name` - The device name
+    /// * `device_type` - The device type
+    /// * `is_default` - Whether the device is the default for its type
+    /// * `volume` - The device volume (0.0-1.0)
+    /// * `muted` - Whether the device is muted
+    ///
+    /// # Returns
+    ///
+    /// A new audio device.
+    pub fn new(
+        id: impl Into<String>,
+        name: impl Into<String>,
+        device_type: AudioDeviceType,
+        is_default: bool,
+        volume: f64,
+        muted: bool,
+    ) -> Self {
+        AudioDevice {
+            id: id.into(),
+            name: name.into(),
+            device_type,
+            is_default,
+            volume,
+            muted,
+        }
+    }
+
+    /// Gets the device ID.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Gets the device name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Gets the device type.
+    pub fn device_type(&self) -> AudioDeviceType {
+        self.device_type
+    }
+
+    /// Checks if the device is the default for its type.
+    pub fn is_default(&self) -> bool {
+        self.is_default
+    }
+
+    /// Gets the device volume.
+    pub fn volume(&self) -> f64 {
+        self.volume
+    }
+
+    /// Checks if the device is muted.
+    pub fn muted(&self) -> bool {
+        self.muted
+    }
+}
+
+// TODO: Assistant Integration: This module's functionality (e.g., set_device_volume, set_device_mute, get_devices)
+// might be exposed through the SystemSettingsService or a direct D-Bus interface
+// for the Smart Assistant to control audio settings.
+
+/// Audio stream.
+#[derive(Debug, Clone)]
+pub struct AudioStream {
+    /// The stream ID.
+    id: String,
+    /// The stream name.
+    name: String,
+    /// The application name.
+    application: String,
+    /// The ID of the application, stable across its runs.
+    app_id: String,
+    /// The type of device the stream uses: output for playback, input for recording.
+    stream_type: AudioDeviceType,
+    /// The stream volume (0.0-1.0).
+    volume: f64,
+    /// Whether the stream is muted.
+    muted: bool,
+    /// The ID of the device the stream plays on or records from, if known.
+    device_id: Option<String>,
+}
+
+impl AudioStream {
+    /// Creates a new audio stream.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The stream ID
+    /// * `name` - The stream name
+    /// * `application` - The application name
+    /// * `volume` - The stream volume (0.0-1.0)
+    /// * `muted` - Whether the stream is muted
+    ///
+    /// # Returns
+    ///
+    /// A new playback stream whose application ID is its application name.
+    pub fn new(
+        id: impl Into<String>,
+        name: impl Into<String>,
+        application: impl Into<String>,
+        volume: f64,
+        muted: bool,
+    ) -> Self {
+        let application = application.into();
+        AudioStream {
+            id: id.into(),
+            name: name.into(),
+            app_id: application.clone(),
+            application,
+            stream_type: AudioDeviceType::Output,
+            volume,
+            muted,
+            device_id: None,
+        }
+    }
+
+    /// Sets the ID of the device the stream plays on or records from.
+    pub fn with_device(mut self, device_id: impl Into<String>) -> Self {
+        self.device_id = Some(device_id.into());
+        self
+    }
+
+    /// Sets the ID of the application.
+    pub fn with_app_id(mut self, app_id: impl Into<String>) -> Self {
+        self.app_id = app_id.into();
+        self
+    }
+
+    /// Sets the type of device the stream uses.
+    pub fn with_type(mut self, stream_type: AudioDeviceType) -> Self {
+        self.stream_type = stream_type;
+        self
+    }
+
+    /// Gets the stream ID.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Gets the stream name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Gets the application name.
+    pub fn application(&self) -> &str {
+        &self.application
+    }
+
+    /// Gets the ID of the application, e.g. its binary name.
+    pub fn app_id(&self) -> &str {
+        &self.app_id
+    }
+
+    /// Gets the type of device the stream uses.
+    pub fn stream_type(&self) -> AudioDeviceType {
+        self.stream_type
+    }
+
+    /// Gets the stream volume.
+    pub fn volume(&self) -> f64 {
+        self.volume
+    }
+
+    /// Checks if the stream is muted.
+    pub fn muted(&self) -> bool {
+        self.muted
+    }
+
+    /// Gets the ID of the device the stream plays on or records from, if known.
+    pub fn device_id(&self) -> Option<&str> {
+        self.device_id.as_deref()
+    }
+}
+
+/// What a stream plays, for volumes that apply per kind of sound rather than per stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AudioRole {
+    /// System event sounds (e.g. the volume change feedback).
+    Event,
+    /// Notification sounds.
+    Notification,
+}
+
+impl AudioRole {
+    /// The `media.role` property of streams with this role.
+    pub fn media_role(&self) -> &'static str {
+        match self {
+            AudioRole::Event => "event",
+            AudioRole::Notification => "notification",
+        }
+    }
+}
+
+/// A change on the sound server, made by NovaDE or by any other client.
+#[derive(Debug, Clone)]
+pub enum AudioEvent {
+    /// A device was plugged in.
+    DeviceAdded(AudioDevice),
+    /// The volume, mute state or default state of a device changed.
+    DeviceChanged(AudioDevice),
+    /// The device with this ID went away.
+    DeviceRemoved(String),
+    /// A stream started.
+    StreamAdded(AudioStream),
+    /// The volume, mute state or device of a stream changed.
+    StreamChanged(AudioStream),
+    /// The stream with this ID ended.
+    StreamRemoved(String),
+}
+
+/// Audio manager interface.
+#[async_trait]
+pub trait AudioManager: Send + Sync {
+    /// Gets all audio devices.
+    ///
+    /// # Returns
+    ///
+    /// A vector of all audio devices.
+    async fn get_devices(&self) -> SystemResult<Vec<AudioDevice>>;
+    
+    /// Gets an audio device by ID.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The device ID
+    ///
+    /// # Returns
+    ///
+    /// The audio device, or an error if it doesn't exist.
+    async fn get_device(&self, id: &str) -> SystemResult<AudioDevice>;
+    
+    /// Gets the default device for a device type.
+    ///
+    /// # Arguments
+    ///
+    /// * `device_type` - The device type
+    ///
+    /// # Returns
+    ///
+    /// The default audio device, or an error if there is no default.
+    async fn get_default_device(&self, device_type: AudioDeviceType) -> SystemResult<AudioDevice>;
+    
+    /// Sets the default device for a device type.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The device ID
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())` if the default device was set, or an error if it failed.
+    async fn set_default_device(&self, id: &str) -> SystemResult<()>;
+    
+    /// Sets the volume for a device.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The device ID
+    /// * `volume` - The volume level (0.0-1.0)
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())` if the volume was set, or an error if it failed.
+    async fn set_device_volume(&self, id: &str, volume: f64) -> SystemResult<()>;
+    
+    /// Sets the mute state for a device.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The device ID
+    /// * `muted` - Whether the device should be muted
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())` if the mute state was set, or an error if it failed.
+    async fn set_device_mute(&self, id: &str, muted: bool) -> SystemResult<()>;
+    
+    /// Gets all audio streams.
+    ///
+    /// # Returns
+    ///
+    /// A vector of all audio streams.
+    async fn get_streams(&self) -> SystemResult<Vec<AudioStream>>;
+    
+    /// Gets an audio stream by ID.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The stream ID
+    ///
+    /// # Returns
+    ///
+    /// The audio stream, or an error if it doesn't exist.
+    async fn get_stream(&self, id: &str) -> SystemResult<AudioStream>;
+    
+    /// Sets the volume for a stream.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The stream ID
+    /// * `volume` - The volume level (0.0-1.0)
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())` if the volume was set, or an error if it failed.
+    async fn set_stream_volume(&self, id: &str, volume: f64) -> SystemResult<()>;
+    
+    /// Sets the mute state for a stream.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The stream ID
+    /// * `muted` - Whether the stream should be muted
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())` if the mute state was set, or an error if it failed.
+    async fn set_stream_mute(&self, id: &str, muted: bool) -> SystemResult<()>;
+
+    /// Moves a stream to another device of its type.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The stream ID
+    /// * `device_id` - The ID of the output device for playback streams, of the input device
+    ///   for recording streams
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())` if the stream was moved, or an error if it failed.
+    async fn move_stream(&self, id: &str, device_id: &str) -> SystemResult<()>;
+
+    /// Subscribes to changes of devices and streams.
+    ///
+    /// # Returns
+    ///
+    /// A receiver of the changes from now on.
+    async fn subscribe(&self) -> SystemResult<broadcast::Receiver<AudioEvent>>;
+
+    /// Plays a sound file on the default output.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The sound file
+    /// * `role` - The role, whose volume the sound plays at
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())` once the sound finished playing, or an error if it could not be played.
+    async fn play_sound(&self, path: &Path, role: AudioRole) -> SystemResult<()>;
+
+    /// Gets the volume of a role.
+    ///
+    /// # Arguments
+    ///
+    /// * `role` - The role
+    ///
+    /// # Returns
+    ///
+    /// The role volume (0.0-1.0).
+    async fn get_role_volume(&self, role: AudioRole) -> SystemResult<f64>;
+
+    /// Sets the volume of a role.
+    ///
+    /// # Arguments
+    ///
+    /// * `role` - The role
+    /// * `volume` - The volume level (0.0-1.0)
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())` if the volume was set, or an error if it failed.
+    async fn set_role_volume(&self, role: AudioRole, volume: f64) -> SystemResult<()>;
+}
+
+/// Name we introduce ourselves with to the sound server.
+const CLIENT_NAME: &str = "NovaDE";
+/// Events kept for subscribers that fall behind.
+const EVENT_CAPACITY: usize = 64;
+/// How long to wait before reconnecting the event connection after the server went away.
+const RECONNECT_DELAY: Duration = Duration::from_secs(2);
+
+/// PulseAudio manager implementation.
+///
+/// Talks to the user's sound server over the PulseAudio native protocol, which PipeWire
+/// serves through `pipewire-pulse` as well. Requests go over one connection, reconnecting
+/// after the server restarts; a second connection follows the server's changes and
+/// broadcasts them as [`AudioEvent`]s.
+///
+/// Device IDs are the server's device names (e.g. `alsa_output.pci-0000_00_1f.3.analog-stereo`),
+/// stream IDs `sink-input:<index>` for playback and `source-output:<index>` for recording
+/// streams. Monitor sources are not listed.
+pub struct PulseAudioManager {
+    /// The request connection.
+    connection: tokio::sync::Mutex<PulseRequests>,
+    /// Where changes are broadcast.
+    events: broadcast::Sender<AudioEvent>,
+    /// The task following the server's changes.
+    event_task: JoinHandle<()>,
+    /// The role volumes; roles without one play at full volume.
+    role_volumes: Mutex<HashMap<AudioRole, f64>>,
+}
+
+impl PulseAudioManager {
+    /// Creates a PulseAudio manager connected to the user's sound server.
+    ///
+    /// # Returns
+    ///
+    /// A new PulseAudio manager, or an error if the sound server could not be reached.
+    pub async fn new() -> SystemResult<Self> {
+        Self::connect(pulse::default_socket_path()).await
+    }
+
+    /// Creates a PulseAudio manager connected to the sound server listening on `socket_path`.
+    ///
+    /// # Arguments
+    ///
+    /// * `socket_path` - The server's Unix socket
+    ///
+    /// # Returns
+    ///
+    /// A new PulseAudio manager, or an error if the sound server could not be reached.
+    pub async fn connect(socket_path: impl Into<PathBuf>) -> SystemResult<Self> {
+        let path = socket_path.into();
+        let mut requests = PulseRequests { path: path.clone(), connection: None };
+        let result = async { requests.get().await?.server_info().await }.await;
+        let server = requests.check(result)?;
+        tracing::info!("Connected to sound server {} {} at {}", server.server_name, server.server_version, path.display());
+
+        // Subscribe before returning so that no change made from here on is missed.
+        let watcher = EventWatcher::start(&path).await.map_err(pulse_error)?;
+        let (events, _) = broadcast::channel(EVENT_CAPACITY);
+        let event_task = tokio::spawn(watcher.run(path, events.clone()));
+
+        Ok(PulseAudioManager {
+            connection: tokio::sync::Mutex::new(requests),
+            events,
+            event_task,
+            role_volumes: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Finds a sink or, failing that, a source by name.
+    async fn find_device(connection: &mut PulseConnection, id: &str) -> Result<Option<(AudioDeviceType, DeviceInfo)>, PulseError> {
+        if let Some(sink) = found(connection.sink(INVALID_INDEX, Some(id)).await)? {
+            return Ok(Some((AudioDeviceType::Output, sink)));
+        }
+        let source = found(connection.source(INVALID_INDEX, Some(id)).await)?;
+        Ok(source.filter(|source| source.monitor_of_sink.is_none()).map(|source| (AudioDeviceType::Input, source)))
+    }
+}
+
+impl Drop for PulseAudioManager {
+    fn drop(&mut self) {
+        self.event_task.abort();
+    }
+}
+
+#[async_trait]
+impl AudioManager for PulseAudioManager {
+    async fn get_devices(&self) -> SystemResult<Vec<AudioDevice>> {
+        let mut requests = self.connection.lock().await;
+        let result = async {
+            let connection = requests.get().await?;
+            let server = connection.server_info().await?;
+            let sinks = connection.sinks().await?;
+            let sources = connection.sources().await?;
+            Ok::<_, PulseError>((server, sinks, sources))
+        }
+        .await;
+        let (server, sinks, sources) = requests.check(result)?;
+
+        let outputs = sinks.iter().map(|sink| audio_device(sink, AudioDeviceType::Output, server.default_sink.as_deref()));
+        let inputs = sources
+            .iter()
+            .filter(|source| source.monitor_of_sink.is_none())
+            .map(|source| audio_device(source, AudioDeviceType::Input, server.default_source.as_deref()));
+        Ok(outputs.chain(inputs).collect())
+    }
+
+    async fn get_device(&self, id: &str) -> SystemResult<AudioDevice> {
+        self.get_devices()
+            .await?
+            .into_iter()
+            .find(|d| d.id() == id)
+            .ok_or_else(|| to_system_error(format!("Audio device not found: {}", id), SystemErrorKind::AudioManagement))
+    }
+
+    async fn get_default_device(&self, device_type: AudioDeviceType) -> SystemResult<AudioDevice> {
+        self.get_devices()
+            .await?
+            .into_iter()
+            .find(|d| d.device_type() == device_type && d.is_default())
+            .ok_or_else(|| to_system_error(format!("No default device found for type: {:?}", device_type), SystemErrorKind::AudioManagement))
+    }
+
+    async fn set_default_device(&self, id: &str) -> SystemResult<()> {
+        let mut requests = self.connection.lock().await;
+        let result = async {
+            let connection = requests.get().await?;
+            let Some((device_type, _)) = Self::find_device(connection, id).await? else {
+                return Ok(false);
+            };
+            connection.set_default_device(device_type == AudioDeviceType::Input, id).await?;
+            Ok::<_, PulseError>(true)
+        }
+        .await;
+        if !requests.check(result)? {
+            return Err(to_system_error(format!("Audio device not found: {}", id), SystemErrorKind::AudioManagement));
+        }
+        Ok(())
+    }
+
+    async fn set_device_volume(&self, id: &str, volume: f64) -> SystemResult<()> {
+        validate_volume(volume)?;
+        let mut requests = self.connection.lock().await;
+        let result = async {
+            let connection = requests.get().await?;
+            let Some((device_type, info)) = Self::find_device(connection, id).await? else {
+                return Ok(false);
+            };
+            // Keep the balance between the channels.
+            let volume = info.volume.scaled_to(volume);
+            connection.set_device_volume(device_type == AudioDeviceType::Input, info.index, &volume).await?;
+            Ok::<_, PulseError>(true)
+        }
+        .await;
+        if !requests.check(result)? {
+            return Err(to_system_error(format!("Audio device not found: {}", id), SystemErrorKind::AudioManagement));
+        }
+        Ok(())
+    }
+
+    async fn set_device_mute(&self, id: &str, muted: bool) -> SystemResult<()> {
+        let mut requests = self.connection.lock().await;
+        let result = async {
+            let connection = requests.get().await?;
+            let Some((device_type, info)) = Self::find_device(connection, id).await? else {
+                return Ok(false);
+            };
+            connection.set_device_mute(device_type == AudioDeviceType::Input, info.index, muted).await?;
+            Ok::<_, PulseError>(true)
+        }
+        .await;
+        if !requests.check(result)? {
+            return Err(to_system_error(format!("Audio device not found: {}", id), SystemErrorKind::AudioManagement));
+        }
+        Ok(())
+    }
+
+    async fn get_streams(&self) -> SystemResult<Vec<AudioStream>> {
+        let mut requests = self.connection.lock().await;
+        let result = async {
+            let connection = requests.get().await?;
+            let sinks = connection.sinks().await?;
+            let sources = connection.sources().await?;
+            let inputs = connection.sink_inputs().await?;
+            let outputs = connection.source_outputs().await?;
+            Ok::<_, PulseError>((sinks, sources, inputs, outputs))
+        }
+        .await;
+        let (sinks, sources, inputs, outputs) = requests.check(result)?;
+
+        let sink_names: HashMap<u32, &str> = sinks.iter().map(|sink| (sink.index, sink.name.as_str())).collect();
+        let source_names: HashMap<u32, &str> = sources
+            .iter()
+            .filter(|source| source.monitor_of_sink.is_none())
+            .map(|source| (source.index, source.name.as_str()))
+            .collect();
+        let playback = inputs.iter().map(|input| playback_stream(input, sink_names.get(&input.sink).copied()));
+        let recording = outputs.iter().map(|output| recording_stream(output, source_names.get(&output.source).copied()));
+        Ok(playback.chain(recording).collect())
+    }
+
+    async fn get_stream(&self, id: &str) -> SystemResult<AudioStream> {
+        self.get_streams()
+            .await?
+            .into_iter()
+            .find(|s| s.id() == id)
+            .ok_or_else(|| to_system_error(format!("Audio stream not found: {}", id), SystemErrorKind::AudioManagement))
+    }
+
+    async fn set_stream_volume(&self, id: &str, volume: f64) -> SystemResult<()> {
+        validate_volume(volume)?;
+        let (stream_type, index) = parse_stream_id(id)?;
+        let mut requests = self.connection.lock().await;
+        let result = async {
+            let connection = requests.get().await?;
+            match stream_type {
+                AudioDeviceType::Output => {
+                    let Some(input) = found(connection.sink_input(index).await)? else {
+                        return Ok(false);
+                    };
+                    connection.set_sink_input_volume(index, &input.volume.scaled_to(volume)).await?;
+                }
+                AudioDeviceType::Input => {
+                    let Some(output) = found(connection.source_output(index).await)? else {
+                        return Ok(false);
+                    };
+                    connection.set_source_output_volume(index, &output.volume.scaled_to(volume)).await?;
+                }
+            }
+            Ok::<_, PulseError>(true)
+        }
+        .await;
+        if !requests.check(result)? {
+            return Err(to_system_error(format!("Audio stream not found: {}", id), SystemErrorKind::AudioManagement));
+        }
+        Ok(())
+    }
+
+    async fn set_stream_mute(&self, id: &str, muted: bool) -> SystemResult<()> {
+        let (stream_type, index) = parse_stream_id(id)?;
+        let mut requests = self.connection.lock().await;
+        let result = async {
+            let connection = requests.get().await?;
+            match stream_type {
+                AudioDeviceType::Output => found(connection.set_sink_input_mute(index, muted).await),
+                AudioDeviceType::Input => found(connection.set_source_output_mute(index, muted).await),
+            }
+        }
+        .await;
+        requests
+            .check(result)?
+            .ok_or_else(|| to_system_error(format!("Audio stream not found: {}", id), SystemErrorKind::AudioManagement))
+    }
+
+    async fn move_stream(&self, id: &str, device_id: &str) -> SystemResult<()> {
+        let (stream_type, index) = parse_stream_id(id)?;
+        let mut requests = self.connection.lock().await;
+        let result = async {
+            let connection = requests.get().await?;
+            let moved = match stream_type {
+                AudioDeviceType::Output => {
+                    if found(connection.sink(INVALID_INDEX, Some(device_id)).await)?.is_none() {
+                        return Ok(Err(format!("Output device not found: {}", device_id)));
+                    }
+                    found(connection.move_sink_input(index, device_id).await)?
+                }
+                AudioDeviceType::Input => {
+                    let source = found(connection.source(INVALID_INDEX, Some(device_id)).await)?;
+                    if !source.is_some_and(|source| source.monitor_of_sink.is_none()) {
+                        return Ok(Err(format!("Input device not found: {}", device_id)));
+                    }
+                    found(connection.move_source_output(index, device_id).await)?
+                }
+            };
+            Ok::<_, PulseError>(moved.ok_or_else(|| format!("Audio stream not found: {}", id)))
+        }
+        .await;
+        requests.check(result)?.map_err(|message| to_system_error(message, SystemErrorKind::AudioManagement))
+    }
+
+    async fn subscribe(&self) -> SystemResult<broadcast::Receiver<AudioEvent>> {
+        Ok(self.events.subscribe())
+    }
+
+    async fn play_sound(&self, path: &Path, role: AudioRole) -> SystemResult<()> {
+        let volume = self.get_role_volume(role).await?;
+        if volume <= 0.0 {
+            return Ok(());
+        }
+
+        // paplay talks to PulseAudio and to PipeWire's PulseAudio server alike; its volume
+        // goes from 0 to 65536 (100%).
+        let status = tokio::process::Command::new("paplay")
+            .arg(format!("--property=media.role={}", role.media_role()))
+            .arg(format!("--volume={}", (volume * VOLUME_NORM as f64).round() as u32))
+            .arg(path)
+            .status()
+            .await
+            .map_err(|e| to_system_error(format!("Failed to run paplay: {}", e), SystemErrorKind::AudioManagement))?;
+        if !status.success() {
+            return Err(to_system_error(
+                format!("paplay could not play {}: {}", path.display(), status),
+                SystemErrorKind::AudioManagement,
+            ));
+        }
+        Ok(())
+    }
+
+    async fn get_role_volume(&self, role: AudioRole) -> SystemResult<f64> {
+        Ok(self.role_volumes.lock().unwrap().get(&role).copied().unwrap_or(1.0))
+    }
+
+    async fn set_role_volume(&self, role: AudioRole, volume: f64) -> SystemResult<()> {
+        validate_volume(volume)?;
+        self.role_volumes.lock().unwrap().insert(role, volume);
+        Ok(())
+    }
+}
+
+/// The request connection of a [`PulseAudioManager`], dropped after fatal errors so the next
+/// request reconnects.
+struct PulseRequests {
+    /// The server's socket.
+    path: PathBuf,
+    /// The connection, if it is usable.
+    connection: Option<PulseConnection>,
+}
+
+impl PulseRequests {
+    /// Gets the connection, connecting first if there is none.
+    async fn get(&mut self) -> Result<&mut PulseConnection, PulseError> {
+        if self.connection.is_none() {
+            self.connection = Some(PulseConnection::connect(&self.path, CLIENT_NAME).await?);
+        }
+        Ok(self.connection.as_mut().unwrap())
+    }
+
+    /// Converts the result of requests made on the connection, dropping it if it broke.
+    fn check<T>(&mut self, result: Result<T, PulseError>) -> SystemResult<T> {
+        result.map_err(|e| {
+            if e.is_fatal() {
+                self.connection = None;
+            }
+            pulse_error(e)
+        })
+    }
+}
+
+/// Follows the server's changes over a connection of its own.
+///
+/// It remembers the names of devices by index, since removal events only carry the index,
+/// and the default devices, whose change flips `is_default` of two devices.
+struct EventWatcher {
+    connection: PulseConnection,
+    default_sink: Option<String>,
+    default_source: Option<String>,
+    sinks: HashMap<u32, String>,
+    sources: HashMap<u32, String>,
+}
+
+impl EventWatcher {
+    /// Connects, subscribes and reads the current state.
+    async fn start(path: &Path) -> Result<Self, PulseError> {
+        let mut connection = PulseConnection::connect(path, CLIENT_NAME).await?;
+        connection
+            .subscribe(
+                subscription::MASK_SINK
+                    | subscription::MASK_SOURCE
+                    | subscription::MASK_SINK_INPUT
+                    | subscription::MASK_SOURCE_OUTPUT
+                    | subscription::MASK_SERVER,
+            )
+            .await?;
+        let server = connection.server_info().await?;
+        let sinks = connection.sinks().await?.into_iter().map(|sink| (sink.index, sink.name)).collect();
+        let sources = connection
+            .sources()
+            .await?
+            .into_iter()
+            .filter(|source| source.monitor_of_sink.is_none())
+            .map(|source| (source.index, source.name))
+            .collect();
+        Ok(Self { connection, default_sink: server.default_sink, default_source: server.default_source, sinks, sources })
+    }
+
+    /// Broadcasts the server's changes, reconnecting whenever the server goes away.
+    async fn run(mut self, path: PathBuf, events: broadcast::Sender<AudioEvent>) {
+        loop {
+            if let Err(e) = self.forward(&events).await {
+                tracing::warn!("Lost the sound server's change events: {}", e);
+            }
+            loop {
+                tokio::time::sleep(RECONNECT_DELAY).await;
+                match Self::start(&path).await {
+                    Ok(watcher) => {
+                        self = watcher;
+                        break;
+                    }
+                    Err(e) => tracing::debug!("Sound server still unavailable: {}", e),
+                }
+            }
+        }
+    }
+
+    /// Broadcasts changes until the connection breaks.
+    async fn forward(&mut self, events: &broadcast::Sender<AudioEvent>) -> Result<(), PulseError> {
+        loop {
+            let event = self.connection.next_event().await?;
+            match self.handle(event).await {
+                Ok(changes) => {
+                    for change in changes {
+                        // Nobody listening is fine.
+                        let _ = events.send(change);
+                    }
+                }
+                Err(e) if e.is_fatal() => return Err(e),
+                Err(e) => tracing::debug!("Could not follow sound server event {:?}: {}", event, e),
+            }
+        }
+    }
+
+    /// The changes a subscription event stands for.
+    async fn handle(&mut self, event: SubscriptionEvent) -> Result<Vec<AudioEvent>, PulseError> {
+        match event.facility {
+            subscription::FACILITY_SINK | subscription::FACILITY_SOURCE => {
+                let device_type = if event.facility == subscription::FACILITY_SOURCE { AudioDeviceType::Input } else { AudioDeviceType::Output };
+                if event.kind == EventKind::Remove {
+                    return Ok(self.names_mut(device_type).remove(&event.index).map(AudioEvent::DeviceRemoved).into_iter().collect());
+                }
+                let Some(device) = self.device(device_type, event.index, None).await? else {
+                    return Ok(Vec::new());
+                };
+                let known = self.names_mut(device_type).insert(event.index, device.id().to_string()).is_some();
+                Ok(vec![if known { AudioEvent::DeviceChanged(device) } else { AudioEvent::DeviceAdded(device) }])
+            }
+            subscription::FACILITY_SINK_INPUT | subscription::FACILITY_SOURCE_OUTPUT => {
+                let stream_type = if event.facility == subscription::FACILITY_SOURCE_OUTPUT { AudioDeviceType::Input } else { AudioDeviceType::Output };
+                if event.kind == EventKind::Remove {
+                    return Ok(vec![AudioEvent::StreamRemoved(stream_id(stream_type, event.index))]);
+                }
+                let stream = match stream_type {
+                    AudioDeviceType::Output => found(self.connection.sink_input(event.index).await)?
+                        .map(|input| playback_stream(&input, self.sinks.get(&input.sink).map(String::as_str))),
+                    AudioDeviceType::Input => found(self.connection.source_output(event.index).await)?
+                        .map(|output| recording_stream(&output, self.sources.get(&output.source).map(String::as_str))),
+                };
+                let Some(stream) = stream else {
+                    return Ok(Vec::new());
+                };
+                Ok(vec![if event.kind == EventKind::New { AudioEvent::StreamAdded(stream) } else { AudioEvent::StreamChanged(stream) }])
+            }
+            subscription::FACILITY_SERVER => {
+                let server = self.connection.server_info().await?;
+                let mut changes = Vec::new();
+                for (device_type, new_default) in [(AudioDeviceType::Output, server.default_sink), (AudioDeviceType::Input, server.default_source)] {
+                    let default = if device_type == AudioDeviceType::Output { &mut self.default_sink } else { &mut self.default_source };
+                    if *default == new_default {
+                        continue;
+                    }
+                    let old_default = std::mem::replace(default, new_default.clone());
+                    // The old default first, so a listener never sees two defaults at once.
+                    for name in old_default.iter().chain(new_default.iter()) {
+                        if let Some(device) = self.device(device_type, INVALID_INDEX, Some(name)).await? {
+                            changes.push(AudioEvent::DeviceChanged(device));
+                        }
+                    }
+                }
+                Ok(changes)
+            }
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    /// Looks up a device by index, or by name with an invalid index; monitors and devices
+    /// that went away in the meantime are `None`.
+    async fn device(&mut self, device_type: AudioDeviceType, index: u32, name: Option<&str>) -> Result<Option<AudioDevice>, PulseError> {
+        let (info, default) = match device_type {
+            AudioDeviceType::Output => (found(self.connection.sink(index, name).await)?, &self.default_sink),
+            AudioDeviceType::Input => (found(self.connection.source(index, name).await)?, &self.default_source),
+        };
+        Ok(info.filter(|info| info.monitor_of_sink.is_none()).map(|info| audio_device(&info, device_type, default.as_deref())))
+    }
+
+    fn names_mut(&mut self, device_type: AudioDeviceType) -> &mut HashMap<u32, String> {
+        match device_type {
+            AudioDeviceType::Output => &mut self.sinks,
+            AudioDeviceType::Input => &mut self.sources,
+        }
+    }
+}
+
+/// Converts a sink or source of the server.
+fn audio_device(info: &DeviceInfo, device_type: AudioDeviceType, default: Option<&str>) -> AudioDevice {
+    let name = if info.description.is_empty() { &info.name } else { &info.description };
+    // Volumes above 100% are reported as 100%.
+    let volume = info.volume.linear().min(1.0);
+    AudioDevice::new(&info.name, name, device_type, default == Some(info.name.as_str()), volume, info.mute)
+}
+
+/// Converts a sink input of the server, playing on the sink named `sink`.
+fn playback_stream(info: &SinkInputInfo, sink: Option<&str>) -> AudioStream {
+    let stream = AudioStream::new(stream_id(AudioDeviceType::Output, info.index), &info.name, info.application(), info.volume.linear().min(1.0), info.mute)
+        .with_app_id(info.app_id());
+    match sink {
+        Some(sink) => stream.with_device(sink),
+        None => stream,
+    }
+}
+
+/// Converts a source output of the server, recording from the source named `source`.
+fn recording_stream(info: &SourceOutputInfo, source: Option<&str>) -> AudioStream {
+    let stream = AudioStream::new(stream_id(AudioDeviceType::Input, info.index), &info.name, info.application(), info.volume.linear().min(1.0), info.mute)
+        .with_app_id(info.app_id())
+        .with_type(AudioDeviceType::Input);
+    match source {
+        Some(source) => stream.with_device(source),
+        None => stream,
+    }
+}
+
+const PLAYBACK_STREAM_PREFIX: &str = "sink-input:";
+const RECORDING_STREAM_PREFIX: &str = "source-output:";
+
+/// The ID of the sink input (output) or source output (input) `index`.
+fn stream_id(stream_type: AudioDeviceType, index: u32) -> String {
+    match stream_type {
+        AudioDeviceType::Output => format!("{}{}", PLAYBACK_STREAM_PREFIX, index),
+        AudioDeviceType::Input => format!("{}{}", RECORDING_STREAM_PREFIX, index),
+    }
+}
+
+/// Parses a stream ID into its type and the index of its sink input or source output.
+fn parse_stream_id(id: &str) -> SystemResult<(AudioDeviceType, u32)> {
+    let parsed = if let Some(index) = id.strip_prefix(PLAYBACK_STREAM_PREFIX) {
+        index.parse().ok().map(|index| (AudioDeviceType::Output, index))
+    } else if let Some(index) = id.strip_prefix(RECORDING_STREAM_PREFIX) {
+        index.parse().ok().map(|index| (AudioDeviceType::Input, index))
+    } else {
+        None
+    };
+    parsed.ok_or_else(|| to_system_error(format!("Audio stream not found: {}", id), SystemErrorKind::AudioManagement))
+}
+
+/// Turns the server's "no such entity" into `None`.
+fn found<T>(result: Result<T, PulseError>) -> Result<Option<T>, PulseError> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(PulseError::Server(pulse::error_code::NO_ENTITY)) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+fn pulse_error(e: PulseError) -> SystemError {
+    to_system_error(e.to_string(), SystemErrorKind::AudioManagement)
+}
+
+/// Audio manager without audio hardware, for tests and sessions without a sound server.
+///
+/// It has no devices or streams, keeps role volumes and records the sounds it is asked to play,
+/// each taking the configured playback duration.
+#[derive(Default)]
+pub struct NullAudioManager {
+    /// How long each sound takes to play.
+    playback_duration: Duration,
+    /// The role volumes.
+    role_volumes: Mutex<HashMap<AudioRole, f64>>,
+    /// The sounds played so far.
+    played: Mutex<Vec<(PathBuf, AudioRole)>>,
+}
+
+impl NullAudioManager {
+    /// Creates a null audio manager whose sounds finish right away.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a null audio manager whose sounds take `playback_duration` to play.
+    pub fn with_playback_duration(playback_duration: Duration) -> Self {
+        NullAudioManager { playback_duration, ..Self::default() }
+    }
+
+    /// Gets the sounds played so far, oldest first.
+    pub fn played(&self) -> Vec<(PathBuf, AudioRole)> {
+        self.played.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl AudioManager for NullAudioManager {
+    async fn get_devices(&self) -> SystemResult<Vec<AudioDevice>> {
+        Ok(Vec::new())
+    }
+
+    async fn get_device(&self, id: &str) -> SystemResult<AudioDevice> {
+        Err(to_system_error(format!("Audio device not found: {}", id), SystemErrorKind::AudioManagement))
+    }
+
+    async fn get_default_device(&self, device_type: AudioDeviceType) -> SystemResult<AudioDevice> {
+        Err(to_system_error(format!("No default device found for type: {:?}", device_type), SystemErrorKind::AudioManagement))
+    }
+
+    async fn set_default_device(&self, id: &str) -> SystemResult<()> {
+        self.get_device(id).await.map(|_| ())
+    }
+
+    async fn set_device_volume(&self, id: &str, _volume: f64) -> SystemResult<()> {
+        self.get_device(id).await.map(|_| ())
+    }
+
+    async fn set_device_mute(&self, id: &str, _muted: bool) -> SystemResult<()> {
+        self.get_device(id).await.map(|_| ())
+    }
+
+    async fn get_streams(&self) -> SystemResult<Vec<AudioStream>> {
+        Ok(Vec::new())
+    }
+
+    async fn get_stream(&self, id: &str) -> SystemResult<AudioStream> {
+        Err(to_system_error(format!("Audio stream not found: {}", id), SystemErrorKind::AudioManagement))
+    }
+
+    async fn set_stream_volume(&self, id: &str, _volume: f64) -> SystemResult<()> {
+        self.get_stream(id).await.map(|_| ())
+    }
+
+    async fn set_stream_mute(&self, id: &str, _muted: bool) -> SystemResult<()> {
+        self.get_stream(id).await.map(|_| ())
+    }
+
+    async fn move_stream(&self, id: &str, _device_id: &str) -> SystemResult<()> {
+        self.get_stream(id).await.map(|_| ())
+    }
+
+    async fn subscribe(&self) -> SystemResult<broadcast::Receiver<AudioEvent>> {
+        // Nothing ever changes; the receiver reports the channel as closed.
+        Ok(broadcast::channel(1).1)
+    }
+
+    async fn play_sound(&self, path: &Path, role: AudioRole) -> SystemResult<()> {
+        self.played.lock().unwrap().push((path.to_path_buf(), role));
+        tokio::time::sleep(self.playback_duration).await;
+        Ok(())
+    }
+
+    async fn get_role_volume(&self, role: AudioRole) -> SystemResult<f64> {
+        Ok(self.role_volumes.lock().unwrap().get(&role).copied().unwrap_or(1.0))
+    }
+
+    async fn set_role_volume(&self, role: AudioRole, volume: f64) -> SystemResult<()> {
+        validate_volume(volume)?;
+        self.role_volumes.lock().unwrap().insert(role, volume);
+        Ok(())
+    }
+}
+
+/// Checks that a volume level is within 0.0-1.0.
+fn validate_volume(volume: f64) -> SystemResult<()> {
+    if !(0.0..=1.0).contains(&volume) {
+        return Err(to_system_error(
+            format!("Invalid volume level: {}", volume),
+            SystemErrorKind::AudioManagement,
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::fake_pulse_server::{FakePulseServer, HDMI, MICROPHONE, SPEAKERS};
+
+    async fn next_event(events: &mut broadcast::Receiver<AudioEvent>) -> AudioEvent {
+        tokio::time::timeout(Duration::from_secs(5), events.recv()).await.expect("no audio event").unwrap()
+    }
+
+    fn assert_volume(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 0.001, "volume {} instead of {}", actual, expected);
+    }
+
+    #[tokio::test]
+    async fn test_pulseaudio_manager() {
+        let server = FakePulseServer::start().await;
+        let manager = PulseAudioManager::connect(server.path()).await.unwrap();
+
+        // The monitor of the speakers is not a device of its own.
+        let devices = manager.get_devices().await.unwrap();
+        let mut ids: Vec<&str> = devices.iter().map(AudioDevice::id).collect();
+        ids.sort();
+        assert_eq!(ids, [MICROPHONE, HDMI, SPEAKERS]);
+
+        let speakers = manager.get_device(SPEAKERS).await.unwrap();
+        assert_eq!(speakers.name(), "Built-in Speakers");
+        assert_volume(speakers.volume(), 0.75);
+        assert!(manager.get_device(HDMI).await.unwrap().muted());
+        assert_eq!(manager.get_default_device(AudioDeviceType::Output).await.unwrap().id(), SPEAKERS);
+        assert_eq!(manager.get_default_device(AudioDeviceType::Input).await.unwrap().id(), MICROPHONE);
+
+        manager.set_device_volume(HDMI, 0.3).await.unwrap();
+        assert_volume(server.sink(HDMI).volume.linear(), 0.3);
+        manager.set_device_mute(HDMI, false).await.unwrap();
+        assert!(!server.sink(HDMI).mute);
+        manager.set_device_mute(MICROPHONE, true).await.unwrap();
+        assert!(manager.get_device(MICROPHONE).await.unwrap().muted());
+        manager.set_default_device(HDMI).await.unwrap();
+        assert_eq!(server.default_sink().as_deref(), Some(HDMI));
+        assert_eq!(manager.get_default_device(AudioDeviceType::Output).await.unwrap().id(), HDMI);
+
+        assert!(manager.set_default_device("alsa_output.missing").await.is_err());
+        assert!(manager.set_device_volume(SPEAKERS, 1.5).await.is_err());
+        assert!(manager.get_device("alsa_output.speakers.monitor").await.is_err());
+
+        let streams = manager.get_streams().await.unwrap();
+        assert_eq!(streams.len(), 1);
+        let stream = &streams[0];
+        assert_eq!(stream.id(), "sink-input:0");
+        assert_eq!(stream.application(), "Music Player");
+        assert_eq!(stream.stream_type(), AudioDeviceType::Output);
+        assert_eq!(stream.device_id(), Some(SPEAKERS));
+        assert_volume(stream.volume(), 0.8);
+
+        manager.set_stream_volume(stream.id(), 0.5).await.unwrap();
+        manager.set_stream_mute(stream.id(), true).await.unwrap();
+        manager.move_stream(stream.id(), HDMI).await.unwrap();
+        let moved = manager.get_stream(stream.id()).await.unwrap();
+        assert_volume(moved.volume(), 0.5);
+        assert!(moved.muted());
+        assert_eq!(moved.device_id(), Some(HDMI));
+        assert_eq!(server.stream(0).unwrap().sink, server.sink(HDMI).index);
+
+        assert!(manager.get_stream("42").await.is_err());
+        assert!(manager.set_stream_mute("42", true).await.is_err());
+        assert!(manager.move_stream(stream.id(), "alsa_output.missing").await.is_err());
+        assert!(manager.move_stream(stream.id(), MICROPHONE).await.is_err());
+
+        assert_eq!(manager.get_role_volume(AudioRole::Notification).await.unwrap(), 1.0);
+        manager.set_role_volume(AudioRole::Notification, 0.4).await.unwrap();
+        assert_eq!(manager.get_role_volume(AudioRole::Notification).await.unwrap(), 0.4);
+        assert!(manager.set_role_volume(AudioRole::Event, 1.5).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_pulseaudio_manager_recording_streams() {
+        let server = FakePulseServer::start().await;
+        let manager = PulseAudioManager::connect(server.path()).await.unwrap();
+        let index = server.add_recording("Video Call", server.source(MICROPHONE).index);
+        let headset = "alsa_input.usb-headset";
+        server.add_device(true, headset, "USB Headset");
+
+        let id = format!("source-output:{}", index);
+        let stream = manager.get_stream(&id).await.unwrap();
+        assert_eq!(stream.stream_type(), AudioDeviceType::Input);
+        assert_eq!(stream.app_id(), "Video Call");
+        assert_eq!(stream.device_id(), Some(MICROPHONE));
+
+        manager.move_stream(&id, headset).await.unwrap();
+        manager.set_stream_volume(&id, 0.6).await.unwrap();
+        manager.set_stream_mute(&id, true).await.unwrap();
+        let recording = server.recording(index).unwrap();
+        assert_eq!(recording.source, server.source(headset).index);
+        assert_volume(recording.volume.linear(), 0.6);
+        assert!(recording.mute);
+
+        // Neither outputs nor monitors can be recorded from.
+        assert!(manager.move_stream(&id, SPEAKERS).await.is_err());
+        assert!(manager.move_stream(&id, "alsa_output.speakers.monitor").await.is_err());
+        assert!(manager.get_stream(&format!("sink-input:{}", index)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_pulseaudio_manager_live_events() {
+        let server = FakePulseServer::start().await;
+        let manager = PulseAudioManager::connect(server.path()).await.unwrap();
+        let mut events = manager.subscribe().await.unwrap();
+
+        let index = server.add_stream("Video Player", server.sink(HDMI).index);
+        match next_event(&mut events).await {
+            AudioEvent::StreamAdded(stream) => {
+                assert_eq!(stream.id(), format!("sink-input:{}", index));
+                assert_eq!(stream.application(), "Video Player");
+                assert_eq!(stream.device_id(), Some(HDMI));
+            }
+            event => panic!("unexpected event {:?}", event),
+        }
+
+        // Another mixer changes the volume.
+        server.set_sink_volume(SPEAKERS, 0.25);
+        match next_event(&mut events).await {
+            AudioEvent::DeviceChanged(device) => {
+                assert_eq!(device.id(), SPEAKERS);
+                assert_volume(device.volume(), 0.25);
+            }
+            event => panic!("unexpected event {:?}", event),
+        }
+
+        // The old default is announced first.
+        manager.set_default_device(HDMI).await.unwrap();
+        match (next_event(&mut events).await, next_event(&mut events).await) {
+            (AudioEvent::DeviceChanged(old), AudioEvent::DeviceChanged(new)) => {
+                assert_eq!((old.id(), old.is_default()), (SPEAKERS, false));
+                assert_eq!((new.id(), new.is_default()), (HDMI, true));
+            }
+            events => panic!("unexpected events {:?}", events),
+        }
+
+        server.remove_stream(index);
+        match next_event(&mut events).await {
+            AudioEvent::StreamRemoved(id) => assert_eq!(id, format!("sink-input:{}", index)),
+            event => panic!("unexpected event {:?}", event),
+        }
+
+        // Unplugging a device moves its streams to the default one.
+        let recording = server.add_recording("Video Call", server.source(MICROPHONE).index);
+        assert!(matches!(next_event(&mut events).await, AudioEvent::StreamAdded(stream) if stream.stream_type() == AudioDeviceType::Input));
+        let headset = "alsa_input.usb-headset";
+        server.add_device(true, headset, "USB Headset");
+        assert!(matches!(next_event(&mut events).await, AudioEvent::DeviceAdded(device) if device.id() == headset));
+        server.remove_device(true, MICROPHONE);
+        assert!(matches!(next_event(&mut events).await, AudioEvent::DeviceRemoved(id) if id == MICROPHONE));
+        // The old default is gone, so only the new one is announced.
+        assert!(matches!(next_event(&mut events).await, AudioEvent::DeviceChanged(device) if device.id() == headset && device.is_default()));
+        match next_event(&mut events).await {
+            AudioEvent::StreamChanged(stream) => {
+                assert_eq!(stream.id(), format!("source-output:{}", recording));
+                assert_eq!(stream.device_id(), Some(headset));
+            }
+            event => panic!("unexpected event {:?}", event),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pulseaudio_manager_without_server() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(PulseAudioManager::connect(dir.path().join("native")).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_null_audio_manager() {
+        let manager = NullAudioManager::new();
+        assert!(manager.get_devices().await.unwrap().is_empty());
+        assert!(manager.get_default_device(AudioDeviceType::Output).await.is_err());
+        assert!(manager.move_stream("1", "speakers").await.is_err());
+
+        manager.play_sound(Path::new("/tmp/bell.oga"), AudioRole::Notification).await.unwrap();
+        assert_eq!(manager.played(), [(PathBuf::from("/tmp/bell.oga"), AudioRole::Notification)]);
+        manager.set_role_volume(AudioRole::Notification, 0.0).await.unwrap();
+        assert_eq!(manager.get_role_volume(AudioRole::Notification).await.unwrap(), 0.0);
+        assert_eq!(manager.get_role_volume(AudioRole::Event).await.unwrap(), 1.0);
+    }
+}
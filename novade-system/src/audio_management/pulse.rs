@@ -0,0 +1,767 @@
+// novade-system/src/audio_management/pulse.rs
+
+//! A client of the PulseAudio native protocol over its Unix socket.
+//!
+//! This is the only protocol spoken. PipeWire is reached through `pipewire-pulse`, its
+//! PulseAudio server, not through PipeWire's own protocol; without it a PipeWire session has no
+//! audio control. No sound server library is linked. Only the control commands are
+//! implemented: server, sink, source, sink input and source output introspection, volume, mute,
+//! default devices, moving streams and change subscriptions. Playback goes through `paplay`.
+//!
+//! Packets are a 20-byte descriptor followed by a tagstruct holding the command, a tag
+//! matching replies to requests, and the arguments.
+
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::UnixStream;
+
+use super::tagstruct::{CVolume, Proplist, TagReader, TagStructError, TagWriter};
+#[cfg(test)]
+use super::tagstruct::{ChannelMap, FormatInfo, SampleSpec};
+
+/// The protocol version we speak; the layouts below are those of version 32.
+pub const PROTOCOL_VERSION: u32 = 32;
+/// Length of the authentication cookie.
+pub const COOKIE_LENGTH: usize = 256;
+/// Index meaning "none", e.g. to address a device by name instead.
+pub const INVALID_INDEX: u32 = u32::MAX;
+
+const DESCRIPTOR_LENGTH: usize = 20;
+/// Channel of control packets; others carry audio.
+const CONTROL_CHANNEL: u32 = u32::MAX;
+const MAX_PACKET_LENGTH: usize = 16 * 1024 * 1024;
+
+pub mod command {
+    pub const ERROR: u32 = 0;
+    pub const REPLY: u32 = 2;
+    pub const AUTH: u32 = 8;
+    pub const SET_CLIENT_NAME: u32 = 9;
+    pub const GET_SERVER_INFO: u32 = 20;
+    pub const GET_SINK_INFO: u32 = 21;
+    pub const GET_SINK_INFO_LIST: u32 = 22;
+    pub const GET_SOURCE_INFO: u32 = 23;
+    pub const GET_SOURCE_INFO_LIST: u32 = 24;
+    pub const GET_SINK_INPUT_INFO: u32 = 29;
+    pub const GET_SINK_INPUT_INFO_LIST: u32 = 30;
+    pub const GET_SOURCE_OUTPUT_INFO: u32 = 31;
+    pub const GET_SOURCE_OUTPUT_INFO_LIST: u32 = 32;
+    pub const SUBSCRIBE: u32 = 35;
+    pub const SET_SINK_VOLUME: u32 = 36;
+    pub const SET_SINK_INPUT_VOLUME: u32 = 37;
+    pub const SET_SOURCE_VOLUME: u32 = 38;
+    pub const SET_SINK_MUTE: u32 = 39;
+    pub const SET_SOURCE_MUTE: u32 = 40;
+    pub const SET_DEFAULT_SINK: u32 = 44;
+    pub const SET_DEFAULT_SOURCE: u32 = 45;
+    pub const SUBSCRIBE_EVENT: u32 = 66;
+    pub const MOVE_SINK_INPUT: u32 = 67;
+    pub const MOVE_SOURCE_OUTPUT: u32 = 68;
+    pub const SET_SINK_INPUT_MUTE: u32 = 69;
+    pub const SET_SOURCE_OUTPUT_VOLUME: u32 = 97;
+    pub const SET_SOURCE_OUTPUT_MUTE: u32 = 98;
+}
+
+/// Error codes of `ERROR` replies.
+pub mod error_code {
+    pub const COMMAND: u32 = 2;
+    pub const INVALID: u32 = 3;
+    pub const NO_ENTITY: u32 = 5;
+    pub const PROTOCOL: u32 = 7;
+    pub const VERSION: u32 = 17;
+}
+
+/// Subscription masks and the facilities of subscription events.
+pub mod subscription {
+    pub const MASK_SINK: u32 = 0x0001;
+    pub const MASK_SOURCE: u32 = 0x0002;
+    pub const MASK_SINK_INPUT: u32 = 0x0004;
+    pub const MASK_SOURCE_OUTPUT: u32 = 0x0008;
+    pub const MASK_SERVER: u32 = 0x0080;
+
+    pub const FACILITY_MASK: u32 = 0x000F;
+    pub const FACILITY_SINK: u32 = 0;
+    pub const FACILITY_SOURCE: u32 = 1;
+    pub const FACILITY_SINK_INPUT: u32 = 2;
+    pub const FACILITY_SOURCE_OUTPUT: u32 = 3;
+    pub const FACILITY_SERVER: u32 = 7;
+
+    pub const TYPE_MASK: u32 = 0x0030;
+    pub const TYPE_NEW: u32 = 0x0000;
+    pub const TYPE_CHANGE: u32 = 0x0010;
+    pub const TYPE_REMOVE: u32 = 0x0020;
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum PulseError {
+    #[error("sound server connection failed: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("sound server protocol error: {0}")]
+    Protocol(String),
+    #[error("sound server refused the request: {}", error_name(*.0))]
+    Server(u32),
+}
+
+impl PulseError {
+    /// Whether the connection is unusable after this error.
+    pub fn is_fatal(&self) -> bool {
+        !matches!(self, PulseError::Server(_))
+    }
+}
+
+impl From<TagStructError> for PulseError {
+    fn from(e: TagStructError) -> Self {
+        PulseError::Protocol(e.to_string())
+    }
+}
+
+fn error_name(code: u32) -> String {
+    match code {
+        error_code::COMMAND => "unknown command".to_string(),
+        error_code::INVALID => "invalid argument".to_string(),
+        error_code::NO_ENTITY => "no such entity".to_string(),
+        error_code::PROTOCOL => "protocol error".to_string(),
+        error_code::VERSION => "unsupported protocol version".to_string(),
+        code => format!("error {}", code),
+    }
+}
+
+/// What changed, from a subscription event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventKind {
+    New,
+    Change,
+    Remove,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SubscriptionEvent {
+    /// One of the `subscription::FACILITY_*` values.
+    pub facility: u32,
+    pub kind: EventKind,
+    pub index: u32,
+}
+
+impl SubscriptionEvent {
+    pub fn from_raw(event: u32, index: u32) -> Self {
+        let kind = match event & subscription::TYPE_MASK {
+            subscription::TYPE_NEW => EventKind::New,
+            subscription::TYPE_REMOVE => EventKind::Remove,
+            _ => EventKind::Change,
+        };
+        Self { facility: event & subscription::FACILITY_MASK, kind, index }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ServerInfo {
+    pub server_name: String,
+    pub server_version: String,
+    pub default_sink: Option<String>,
+    pub default_source: Option<String>,
+}
+
+impl ServerInfo {
+    fn read(reader: &mut TagReader) -> Result<Self, PulseError> {
+        let server_name = reader.get_string()?.unwrap_or_default();
+        let server_version = reader.get_string()?.unwrap_or_default();
+        reader.get_string()?; // user name
+        reader.get_string()?; // host name
+        reader.get_sample_spec()?;
+        let default_sink = reader.get_string()?;
+        let default_source = reader.get_string()?;
+        reader.get_u32()?; // cookie
+        reader.get_channel_map()?;
+        Ok(Self { server_name, server_version, default_sink, default_source })
+    }
+}
+
+/// A sink or a source.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DeviceInfo {
+    pub index: u32,
+    pub name: String,
+    pub description: String,
+    pub volume: CVolume,
+    pub mute: bool,
+    /// For sources, the sink they monitor.
+    pub monitor_of_sink: Option<u32>,
+    pub proplist: Proplist,
+}
+
+impl DeviceInfo {
+    fn read_sink(reader: &mut TagReader) -> Result<Self, PulseError> {
+        Self::read(reader, false)
+    }
+
+    fn read_source(reader: &mut TagReader) -> Result<Self, PulseError> {
+        Self::read(reader, true)
+    }
+
+    /// Sinks and sources share their layout except for the monitor fields.
+    fn read(reader: &mut TagReader, source: bool) -> Result<Self, PulseError> {
+        let index = reader.get_u32()?;
+        let name = reader.get_string()?.unwrap_or_default();
+        let description = reader.get_string()?.unwrap_or_default();
+        reader.get_sample_spec()?;
+        reader.get_channel_map()?;
+        reader.get_u32()?; // owner module
+        let volume = reader.get_cvolume()?;
+        let mute = reader.get_bool()?;
+        let monitor = reader.get_u32()?;
+        reader.get_string()?; // monitor source name, or name of the monitored sink
+        reader.get_usec()?; // latency
+        reader.get_string()?; // driver
+        reader.get_u32()?; // flags
+        let proplist = reader.get_proplist()?;
+        reader.get_usec()?; // configured latency
+        reader.get_volume()?; // base volume
+        reader.get_u32()?; // state
+        reader.get_u32()?; // volume steps
+        reader.get_u32()?; // card
+        let ports = reader.get_u32()?;
+        for _ in 0..ports {
+            reader.get_string()?; // name
+            reader.get_string()?; // description
+            reader.get_u32()?; // priority
+            reader.get_u32()?; // availability
+        }
+        reader.get_string()?; // active port
+        let formats = reader.get_u8()?;
+        for _ in 0..formats {
+            reader.get_format_info()?;
+        }
+        let monitor_of_sink = Some(monitor).filter(|&sink| source && sink != INVALID_INDEX);
+        Ok(Self { index, name, description, volume, mute, monitor_of_sink, proplist })
+    }
+}
+
+/// A playback stream.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SinkInputInfo {
+    pub index: u32,
+    pub name: String,
+    pub sink: u32,
+    pub volume: CVolume,
+    pub mute: bool,
+    pub has_volume: bool,
+    pub proplist: Proplist,
+}
+
+impl SinkInputInfo {
+    fn read(reader: &mut TagReader) -> Result<Self, PulseError> {
+        let index = reader.get_u32()?;
+        let name = reader.get_string()?.unwrap_or_default();
+        reader.get_u32()?; // owner module
+        reader.get_u32()?; // client
+        let sink = reader.get_u32()?;
+        reader.get_sample_spec()?;
+        reader.get_channel_map()?;
+        let volume = reader.get_cvolume()?;
+        reader.get_usec()?; // buffer latency
+        reader.get_usec()?; // sink latency
+        reader.get_string()?; // resample method
+        reader.get_string()?; // driver
+        let mute = reader.get_bool()?;
+        let proplist = reader.get_proplist()?;
+        reader.get_bool()?; // corked
+        let has_volume = reader.get_bool()?;
+        reader.get_bool()?; // volume writable
+        reader.get_format_info()?;
+        Ok(Self { index, name, sink, volume, mute, has_volume, proplist })
+    }
+
+    /// The application playing the stream, as it named itself.
+    pub fn application(&self) -> &str {
+        application(&self.proplist, &self.name)
+    }
+
+    /// A name of the application that stays the same across its runs.
+    pub fn app_id(&self) -> &str {
+        app_id(&self.proplist, &self.name)
+    }
+}
+
+/// A recording stream.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SourceOutputInfo {
+    pub index: u32,
+    pub name: String,
+    pub source: u32,
+    pub volume: CVolume,
+    pub mute: bool,
+    pub has_volume: bool,
+    pub proplist: Proplist,
+}
+
+impl SourceOutputInfo {
+    fn read(reader: &mut TagReader) -> Result<Self, PulseError> {
+        let index = reader.get_u32()?;
+        let name = reader.get_string()?.unwrap_or_default();
+        reader.get_u32()?; // owner module
+        reader.get_u32()?; // client
+        let source = reader.get_u32()?;
+        reader.get_sample_spec()?;
+        reader.get_channel_map()?;
+        reader.get_usec()?; // buffer latency
+        reader.get_usec()?; // source latency
+        reader.get_string()?; // resample method
+        reader.get_string()?; // driver
+        let proplist = reader.get_proplist()?;
+        reader.get_bool()?; // corked
+        let volume = reader.get_cvolume()?;
+        let mute = reader.get_bool()?;
+        let has_volume = reader.get_bool()?;
+        reader.get_bool()?; // volume writable
+        reader.get_format_info()?;
+        Ok(Self { index, name, source, volume, mute, has_volume, proplist })
+    }
+
+    /// The application recording, as it named itself.
+    pub fn application(&self) -> &str {
+        application(&self.proplist, &self.name)
+    }
+
+    /// A name of the application that stays the same across its runs.
+    pub fn app_id(&self) -> &str {
+        app_id(&self.proplist, &self.name)
+    }
+}
+
+fn application<'a>(proplist: &'a Proplist, stream_name: &'a str) -> &'a str {
+    proplist
+        .get_str("application.name")
+        .or_else(|| proplist.get_str("application.process.binary"))
+        .unwrap_or(stream_name)
+}
+
+/// The explicit id if the application sets one, else its binary, whose name survives
+/// translations and version numbers in the display name.
+fn app_id<'a>(proplist: &'a Proplist, stream_name: &'a str) -> &'a str {
+    proplist
+        .get_str("application.id")
+        .or_else(|| proplist.get_str("application.process.binary"))
+        .unwrap_or_else(|| application(proplist, stream_name))
+}
+
+/// Writes one control packet.
+pub async fn write_packet<W: AsyncWrite + Unpin>(stream: &mut W, payload: &[u8]) -> std::io::Result<()> {
+    let mut packet = Vec::with_capacity(DESCRIPTOR_LENGTH + payload.len());
+    for word in [payload.len() as u32, CONTROL_CHANNEL, 0, 0, 0] {
+        packet.extend_from_slice(&word.to_be_bytes());
+    }
+    packet.extend_from_slice(payload);
+    stream.write_all(&packet).await
+}
+
+/// Reads the next control packet, skipping audio data.
+pub async fn read_packet<R: AsyncRead + Unpin>(stream: &mut R) -> Result<Vec<u8>, PulseError> {
+    loop {
+        let mut descriptor = [0u8; DESCRIPTOR_LENGTH];
+        stream.read_exact(&mut descriptor).await?;
+        let word = |i: usize| u32::from_be_bytes(descriptor[i * 4..i * 4 + 4].try_into().unwrap());
+        let length = word(0) as usize;
+        if length > MAX_PACKET_LENGTH {
+            return Err(PulseError::Protocol(format!("packet of {} bytes", length)));
+        }
+        let mut payload = vec![0u8; length];
+        stream.read_exact(&mut payload).await?;
+        if word(1) == CONTROL_CHANNEL {
+            return Ok(payload);
+        }
+    }
+}
+
+/// The socket of the user's sound server: `$PULSE_SERVER` if it names a local socket, else
+/// `pulse/native` in the runtime directory, where `pipewire-pulse` listens as well.
+pub fn default_socket_path() -> PathBuf {
+    if let Ok(server) = std::env::var("PULSE_SERVER") {
+        let path = server.strip_prefix("unix:").unwrap_or(&server);
+        if path.starts_with('/') {
+            return PathBuf::from(path);
+        }
+        tracing::warn!("PULSE_SERVER '{}' is not a local socket, using the default one", server);
+    }
+    let runtime_dir = std::env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(format!("/run/user/{}", unsafe { libc::getuid() })));
+    runtime_dir.join("pulse/native")
+}
+
+/// The authentication cookie; servers accepting the peer's credentials ignore its contents,
+/// so a missing one is sent as zeros.
+pub fn read_cookie() -> Vec<u8> {
+    let home = std::env::var_os("HOME").map(PathBuf::from);
+    let config = std::env::var_os("XDG_CONFIG_HOME").map(PathBuf::from).or_else(|| home.as_ref().map(|home| home.join(".config")));
+    let candidates = std::env::var_os("PULSE_COOKIE")
+        .map(PathBuf::from)
+        .into_iter()
+        .chain(config.map(|dir| dir.join("pulse/cookie")))
+        .chain(home.map(|home| home.join(".pulse-cookie")));
+    for path in candidates {
+        if let Ok(cookie) = std::fs::read(&path) {
+            if cookie.len() == COOKIE_LENGTH {
+                return cookie;
+            }
+            tracing::debug!("Ignoring the sound server cookie {}: {} bytes", path.display(), cookie.len());
+        }
+    }
+    vec![0; COOKIE_LENGTH]
+}
+
+/// An authenticated connection; requests are answered in order.
+pub struct PulseConnection {
+    stream: UnixStream,
+    next_tag: u32,
+    /// Subscription events that arrived while waiting for a reply.
+    pending_events: VecDeque<SubscriptionEvent>,
+}
+
+impl PulseConnection {
+    /// Connects to the server at `path` and introduces us as `client_name`.
+    pub async fn connect(path: &Path, client_name: &str) -> Result<Self, PulseError> {
+        let stream = UnixStream::connect(path).await?;
+        let mut connection = Self { stream, next_tag: 0, pending_events: VecDeque::new() };
+        let cookie = read_cookie();
+        let mut reply = connection
+            .request(command::AUTH, |args| {
+                args.put_u32(PROTOCOL_VERSION).put_arbitrary(&cookie);
+            })
+            .await?;
+        let server_version = reply.get_u32()? & 0xFFFF;
+        if server_version < PROTOCOL_VERSION {
+            return Err(PulseError::Protocol(format!("server speaks protocol version {}, {} is needed", server_version, PROTOCOL_VERSION)));
+        }
+        let mut props = Proplist::default();
+        props.set_str("application.name", client_name);
+        props.set_str("application.id", "org.novade.System");
+        connection.request(command::SET_CLIENT_NAME, |args| {
+            args.put_proplist(&props);
+        }).await?;
+        Ok(connection)
+    }
+
+    /// Sends `command` with the arguments `args` writes and waits for its reply.
+    pub async fn request(&mut self, command: u32, args: impl FnOnce(&mut TagWriter)) -> Result<TagReader, PulseError> {
+        let tag = self.next_tag;
+        self.next_tag = self.next_tag.wrapping_add(1) & 0x7FFF_FFFF;
+        let mut writer = TagWriter::new();
+        writer.put_u32(command).put_u32(tag);
+        args(&mut writer);
+        write_packet(&mut self.stream, &writer.into_bytes()).await?;
+
+        loop {
+            let mut reader = TagReader::new(read_packet(&mut self.stream).await?);
+            let (reply_command, reply_tag) = (reader.get_u32()?, reader.get_u32()?);
+            match reply_command {
+                command::SUBSCRIBE_EVENT => {
+                    let event = SubscriptionEvent::from_raw(reader.get_u32()?, reader.get_u32()?);
+                    self.pending_events.push_back(event);
+                }
+                command::REPLY if reply_tag == tag => return Ok(reader),
+                command::ERROR if reply_tag == tag => return Err(PulseError::Server(reader.get_u32()?)),
+                _ => tracing::debug!("Sound server: ignoring command {} with tag {}", reply_command, reply_tag),
+            }
+        }
+    }
+
+    /// Asks for events of the facilities in `mask`, see `subscription::MASK_*`.
+    pub async fn subscribe(&mut self, mask: u32) -> Result<(), PulseError> {
+        self.request(command::SUBSCRIBE, |args| {
+            args.put_u32(mask);
+        })
+        .await
+        .map(|_| ())
+    }
+
+    /// Waits for the next subscription event.
+    pub async fn next_event(&mut self) -> Result<SubscriptionEvent, PulseError> {
+        if let Some(event) = self.pending_events.pop_front() {
+            return Ok(event);
+        }
+        loop {
+            let mut reader = TagReader::new(read_packet(&mut self.stream).await?);
+            if reader.get_u32()? == command::SUBSCRIBE_EVENT {
+                reader.get_u32()?; // tag
+                return Ok(SubscriptionEvent::from_raw(reader.get_u32()?, reader.get_u32()?));
+            }
+        }
+    }
+
+    pub async fn server_info(&mut self) -> Result<ServerInfo, PulseError> {
+        let mut reply = self.request(command::GET_SERVER_INFO, |_| {}).await?;
+        ServerInfo::read(&mut reply)
+    }
+
+    pub async fn sinks(&mut self) -> Result<Vec<DeviceInfo>, PulseError> {
+        let mut reply = self.request(command::GET_SINK_INFO_LIST, |_| {}).await?;
+        read_list(&mut reply, DeviceInfo::read_sink)
+    }
+
+    pub async fn sources(&mut self) -> Result<Vec<DeviceInfo>, PulseError> {
+        let mut reply = self.request(command::GET_SOURCE_INFO_LIST, |_| {}).await?;
+        read_list(&mut reply, DeviceInfo::read_source)
+    }
+
+    pub async fn sink_inputs(&mut self) -> Result<Vec<SinkInputInfo>, PulseError> {
+        let mut reply = self.request(command::GET_SINK_INPUT_INFO_LIST, |_| {}).await?;
+        read_list(&mut reply, SinkInputInfo::read)
+    }
+
+    pub async fn source_outputs(&mut self) -> Result<Vec<SourceOutputInfo>, PulseError> {
+        let mut reply = self.request(command::GET_SOURCE_OUTPUT_INFO_LIST, |_| {}).await?;
+        read_list(&mut reply, SourceOutputInfo::read)
+    }
+
+    /// The sink with `index`, or with `name` if the index is `INVALID_INDEX`.
+    pub async fn sink(&mut self, index: u32, name: Option<&str>) -> Result<DeviceInfo, PulseError> {
+        let mut reply = self.request(command::GET_SINK_INFO, |args| {
+            args.put_u32(index).put_string(name);
+        }).await?;
+        DeviceInfo::read_sink(&mut reply)
+    }
+
+    /// The source with `index`, or with `name` if the index is `INVALID_INDEX`.
+    pub async fn source(&mut self, index: u32, name: Option<&str>) -> Result<DeviceInfo, PulseError> {
+        let mut reply = self.request(command::GET_SOURCE_INFO, |args| {
+            args.put_u32(index).put_string(name);
+        }).await?;
+        DeviceInfo::read_source(&mut reply)
+    }
+
+    pub async fn sink_input(&mut self, index: u32) -> Result<SinkInputInfo, PulseError> {
+        let mut reply = self.request(command::GET_SINK_INPUT_INFO, |args| {
+            args.put_u32(index);
+        }).await?;
+        SinkInputInfo::read(&mut reply)
+    }
+
+    pub async fn source_output(&mut self, index: u32) -> Result<SourceOutputInfo, PulseError> {
+        let mut reply = self.request(command::GET_SOURCE_OUTPUT_INFO, |args| {
+            args.put_u32(index);
+        }).await?;
+        SourceOutputInfo::read(&mut reply)
+    }
+
+    /// Sets the volume of the sink or source `index`.
+    pub async fn set_device_volume(&mut self, source: bool, index: u32, volume: &CVolume) -> Result<(), PulseError> {
+        let command = if source { command::SET_SOURCE_VOLUME } else { command::SET_SINK_VOLUME };
+        self.request(command, |args| {
+            args.put_u32(index).put_string(None).put_cvolume(volume);
+        })
+        .await
+        .map(|_| ())
+    }
+
+    pub async fn set_device_mute(&mut self, source: bool, index: u32, muted: bool) -> Result<(), PulseError> {
+        let command = if source { command::SET_SOURCE_MUTE } else { command::SET_SINK_MUTE };
+        self.request(command, |args| {
+            args.put_u32(index).put_string(None).put_bool(muted);
+        })
+        .await
+        .map(|_| ())
+    }
+
+    pub async fn set_default_device(&mut self, source: bool, name: &str) -> Result<(), PulseError> {
+        let command = if source { command::SET_DEFAULT_SOURCE } else { command::SET_DEFAULT_SINK };
+        self.request(command, |args| {
+            args.put_string(Some(name));
+        })
+        .await
+        .map(|_| ())
+    }
+
+    pub async fn set_sink_input_volume(&mut self, index: u32, volume: &CVolume) -> Result<(), PulseError> {
+        self.request(command::SET_SINK_INPUT_VOLUME, |args| {
+            args.put_u32(index).put_cvolume(volume);
+        })
+        .await
+        .map(|_| ())
+    }
+
+    pub async fn set_sink_input_mute(&mut self, index: u32, muted: bool) -> Result<(), PulseError> {
+        self.request(command::SET_SINK_INPUT_MUTE, |args| {
+            args.put_u32(index).put_bool(muted);
+        })
+        .await
+        .map(|_| ())
+    }
+
+    /// Moves the sink input `index` to the sink named `sink`.
+    pub async fn move_sink_input(&mut self, index: u32, sink: &str) -> Result<(), PulseError> {
+        self.request(command::MOVE_SINK_INPUT, |args| {
+            args.put_u32(index).put_u32(INVALID_INDEX).put_string(Some(sink));
+        })
+        .await
+        .map(|_| ())
+    }
+
+    pub async fn set_source_output_volume(&mut self, index: u32, volume: &CVolume) -> Result<(), PulseError> {
+        self.request(command::SET_SOURCE_OUTPUT_VOLUME, |args| {
+            args.put_u32(index).put_cvolume(volume);
+        })
+        .await
+        .map(|_| ())
+    }
+
+    pub async fn set_source_output_mute(&mut self, index: u32, muted: bool) -> Result<(), PulseError> {
+        self.request(command::SET_SOURCE_OUTPUT_MUTE, |args| {
+            args.put_u32(index).put_bool(muted);
+        })
+        .await
+        .map(|_| ())
+    }
+
+    /// Moves the source output `index` to the source named `source`.
+    pub async fn move_source_output(&mut self, index: u32, source: &str) -> Result<(), PulseError> {
+        self.request(command::MOVE_SOURCE_OUTPUT, |args| {
+            args.put_u32(index).put_u32(INVALID_INDEX).put_string(Some(source));
+        })
+        .await
+        .map(|_| ())
+    }
+}
+
+fn read_list<T>(reader: &mut TagReader, read: fn(&mut TagReader) -> Result<T, PulseError>) -> Result<Vec<T>, PulseError> {
+    let mut items = Vec::new();
+    while !reader.is_empty() {
+        items.push(read(reader)?);
+    }
+    Ok(items)
+}
+
+// Used by the fake server of the tests to answer with the layouts read above.
+#[cfg(test)]
+pub(crate) fn write_server_info(writer: &mut TagWriter, info: &ServerInfo) {
+    writer
+        .put_string(Some(&info.server_name))
+        .put_string(Some(&info.server_version))
+        .put_string(Some("novade"))
+        .put_string(Some("localhost"))
+        .put_sample_spec(&SampleSpec { format: 3, channels: 2, rate: 48000 })
+        .put_string(info.default_sink.as_deref())
+        .put_string(info.default_source.as_deref())
+        .put_u32(0)
+        .put_channel_map(&ChannelMap(vec![1, 2]));
+}
+
+#[cfg(test)]
+pub(crate) fn write_device_info(writer: &mut TagWriter, info: &DeviceInfo) {
+    let channels = info.volume.0.len() as u8;
+    writer
+        .put_u32(info.index)
+        .put_string(Some(&info.name))
+        .put_string(Some(&info.description))
+        .put_sample_spec(&SampleSpec { format: 3, channels, rate: 48000 })
+        .put_channel_map(&ChannelMap((1..=channels).collect()))
+        .put_u32(INVALID_INDEX)
+        .put_cvolume(&info.volume)
+        .put_bool(info.mute)
+        .put_u32(info.monitor_of_sink.unwrap_or(INVALID_INDEX))
+        .put_string(None)
+        .put_usec(0)
+        .put_string(Some("fake"))
+        .put_u32(0)
+        .put_proplist(&info.proplist)
+        .put_usec(0)
+        .put_volume(super::tagstruct::VOLUME_NORM)
+        .put_u32(0)
+        .put_u32(65537)
+        .put_u32(INVALID_INDEX)
+        .put_u32(1)
+        .put_string(Some("analog-output"))
+        .put_string(Some("Analog Output"))
+        .put_u32(100)
+        .put_u32(0)
+        .put_string(Some("analog-output"))
+        .put_u8(1)
+        .put_format_info(&FormatInfo { encoding: 1, props: Proplist::default() });
+}
+
+#[cfg(test)]
+pub(crate) fn write_sink_input_info(writer: &mut TagWriter, info: &SinkInputInfo) {
+    let channels = info.volume.0.len() as u8;
+    writer
+        .put_u32(info.index)
+        .put_string(Some(&info.name))
+        .put_u32(INVALID_INDEX)
+        .put_u32(INVALID_INDEX)
+        .put_u32(info.sink)
+        .put_sample_spec(&SampleSpec { format: 3, channels, rate: 44100 })
+        .put_channel_map(&ChannelMap((1..=channels).collect()))
+        .put_cvolume(&info.volume)
+        .put_usec(0)
+        .put_usec(0)
+        .put_string(None)
+        .put_string(Some("fake"))
+        .put_bool(info.mute)
+        .put_proplist(&info.proplist)
+        .put_bool(false)
+        .put_bool(info.has_volume)
+        .put_bool(info.has_volume)
+        .put_format_info(&FormatInfo { encoding: 1, props: Proplist::default() });
+}
+
+#[cfg(test)]
+pub(crate) fn write_source_output_info(writer: &mut TagWriter, info: &SourceOutputInfo) {
+    let channels = info.volume.0.len() as u8;
+    writer
+        .put_u32(info.index)
+        .put_string(Some(&info.name))
+        .put_u32(INVALID_INDEX)
+        .put_u32(INVALID_INDEX)
+        .put_u32(info.source)
+        .put_sample_spec(&SampleSpec { format: 3, channels, rate: 48000 })
+        .put_channel_map(&ChannelMap((1..=channels).collect()))
+        .put_usec(0)
+        .put_usec(0)
+        .put_string(None)
+        .put_string(Some("fake"))
+        .put_proplist(&info.proplist)
+        .put_bool(false)
+        .put_cvolume(&info.volume)
+        .put_bool(info.mute)
+        .put_bool(info.has_volume)
+        .put_bool(info.has_volume)
+        .put_format_info(&FormatInfo { encoding: 1, props: Proplist::default() });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subscription_event_from_raw() {
+        let event = SubscriptionEvent::from_raw(subscription::TYPE_REMOVE | subscription::FACILITY_SINK_INPUT, 12);
+        assert_eq!(event, SubscriptionEvent { facility: subscription::FACILITY_SINK_INPUT, kind: EventKind::Remove, index: 12 });
+        assert_eq!(SubscriptionEvent::from_raw(subscription::TYPE_CHANGE | subscription::FACILITY_SERVER, 0).kind, EventKind::Change);
+    }
+
+    #[test]
+    fn test_info_layouts_round_trip() {
+        let mut proplist = Proplist::default();
+        proplist.set_str("application.name", "Music");
+        let sink = DeviceInfo { index: 3, name: "hdmi".into(), description: "HDMI".into(), volume: CVolume(vec![0x8000, 0x10000]), mute: true, monitor_of_sink: None, proplist: Proplist::default() };
+        let input = SinkInputInfo { index: 9, name: "Playback".into(), sink: 3, volume: CVolume(vec![0x10000; 2]), mute: false, has_volume: true, proplist };
+        let mut recorder = Proplist::default();
+        recorder.set_str("application.name", "Video Call 5.2");
+        recorder.set_str("application.process.binary", "videocall");
+        let output = SourceOutputInfo { index: 4, name: "Capture".into(), source: 1, volume: CVolume(vec![0x4000]), mute: true, has_volume: true, proplist: recorder };
+        let mut writer = TagWriter::new();
+        write_device_info(&mut writer, &sink);
+        write_sink_input_info(&mut writer, &input);
+        write_source_output_info(&mut writer, &output);
+        let mut reader = TagReader::new(writer.into_bytes());
+        assert_eq!(DeviceInfo::read_sink(&mut reader).unwrap(), sink);
+        let read_input = SinkInputInfo::read(&mut reader).unwrap();
+        assert_eq!(read_input, input);
+        assert_eq!(read_input.application(), "Music");
+        assert_eq!(read_input.app_id(), "Music");
+        let read_output = SourceOutputInfo::read(&mut reader).unwrap();
+        assert_eq!(read_output, output);
+        assert_eq!(read_output.application(), "Video Call 5.2");
+        assert_eq!(read_output.app_id(), "videocall");
+        assert!(reader.is_empty());
+    }
+}
@@ -0,0 +1,414 @@
+// novade-system/src/audio_management/tagstruct.rs
+
+//! The tagged value encoding of the PulseAudio native protocol.
+//!
+//! Every value is written as a one-byte tag followed by its big-endian payload, so a reader
+//! can tell a malformed packet from a value of the wrong type. PipeWire's PulseAudio server
+//! speaks the same encoding.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+const TAG_STRING: u8 = b't';
+const TAG_STRING_NULL: u8 = b'N';
+const TAG_U32: u8 = b'L';
+const TAG_U8: u8 = b'B';
+const TAG_U64: u8 = b'R';
+const TAG_S64: u8 = b'r';
+const TAG_SAMPLE_SPEC: u8 = b'a';
+const TAG_ARBITRARY: u8 = b'x';
+const TAG_BOOLEAN_TRUE: u8 = b'1';
+const TAG_BOOLEAN_FALSE: u8 = b'0';
+const TAG_USEC: u8 = b'U';
+const TAG_CHANNEL_MAP: u8 = b'm';
+const TAG_CVOLUME: u8 = b'v';
+const TAG_PROPLIST: u8 = b'P';
+const TAG_VOLUME: u8 = b'V';
+const TAG_FORMAT_INFO: u8 = b'f';
+
+/// Volume of a channel at 100%.
+pub const VOLUME_NORM: u32 = 0x10000;
+/// The loudest volume the server accepts.
+pub const VOLUME_MAX: u32 = u32::MAX / 2;
+
+/// A value that does not match what the reader expected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagStructError(pub String);
+
+impl fmt::Display for TagStructError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "malformed tagstruct: {}", self.0)
+    }
+}
+
+impl std::error::Error for TagStructError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SampleSpec {
+    pub format: u8,
+    pub channels: u8,
+    pub rate: u32,
+}
+
+/// Channel positions, one byte each.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ChannelMap(pub Vec<u8>);
+
+/// Per-channel volumes, `VOLUME_NORM` being 100%.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct CVolume(pub Vec<u32>);
+
+impl CVolume {
+    /// All `channels` at `linear` (1.0 being 100%).
+    pub fn uniform(channels: usize, linear: f64) -> Self {
+        CVolume(vec![to_raw(linear); channels.max(1)])
+    }
+
+    /// The volume of the loudest channel, 1.0 being 100%.
+    pub fn linear(&self) -> f64 {
+        self.0.iter().copied().max().unwrap_or(0) as f64 / VOLUME_NORM as f64
+    }
+
+    /// These volumes with the loudest channel at `linear`, keeping the balance between
+    /// channels.
+    pub fn scaled_to(&self, linear: f64) -> Self {
+        let max = self.0.iter().copied().max().unwrap_or(0);
+        if max == 0 {
+            return Self::uniform(self.0.len(), linear);
+        }
+        let target = to_raw(linear) as u64;
+        CVolume(self.0.iter().map(|&channel| (channel as u64 * target / max as u64) as u32).collect())
+    }
+}
+
+fn to_raw(linear: f64) -> u32 {
+    (linear * VOLUME_NORM as f64).round().clamp(0.0, VOLUME_MAX as f64) as u32
+}
+
+/// Properties of a client, device or stream, such as `application.name`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Proplist(pub BTreeMap<String, Vec<u8>>);
+
+impl Proplist {
+    /// The value of `key` if it is a string.
+    pub fn get_str(&self, key: &str) -> Option<&str> {
+        let value = self.0.get(key)?;
+        std::str::from_utf8(value.strip_suffix(&[0]).unwrap_or(value)).ok()
+    }
+
+    /// Sets `key` to a string; string values carry their NUL on the wire.
+    pub fn set_str(&mut self, key: &str, value: &str) {
+        let mut bytes = value.as_bytes().to_vec();
+        bytes.push(0);
+        self.0.insert(key.to_string(), bytes);
+    }
+}
+
+/// A stream format, e.g. PCM or a passthrough encoding.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct FormatInfo {
+    pub encoding: u8,
+    pub props: Proplist,
+}
+
+#[derive(Debug, Default)]
+pub struct TagWriter {
+    buf: Vec<u8>,
+}
+
+impl TagWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.buf
+    }
+
+    pub fn put_u32(&mut self, value: u32) -> &mut Self {
+        self.buf.push(TAG_U32);
+        self.buf.extend_from_slice(&value.to_be_bytes());
+        self
+    }
+
+    pub fn put_u8(&mut self, value: u8) -> &mut Self {
+        self.buf.extend_from_slice(&[TAG_U8, value]);
+        self
+    }
+
+    pub fn put_u64(&mut self, value: u64) -> &mut Self {
+        self.buf.push(TAG_U64);
+        self.buf.extend_from_slice(&value.to_be_bytes());
+        self
+    }
+
+    pub fn put_s64(&mut self, value: i64) -> &mut Self {
+        self.buf.push(TAG_S64);
+        self.buf.extend_from_slice(&value.to_be_bytes());
+        self
+    }
+
+    pub fn put_usec(&mut self, value: u64) -> &mut Self {
+        self.buf.push(TAG_USEC);
+        self.buf.extend_from_slice(&value.to_be_bytes());
+        self
+    }
+
+    pub fn put_bool(&mut self, value: bool) -> &mut Self {
+        self.buf.push(if value { TAG_BOOLEAN_TRUE } else { TAG_BOOLEAN_FALSE });
+        self
+    }
+
+    pub fn put_string(&mut self, value: Option<&str>) -> &mut Self {
+        match value {
+            Some(value) => {
+                self.buf.push(TAG_STRING);
+                self.buf.extend_from_slice(value.as_bytes());
+                self.buf.push(0);
+            }
+            None => self.buf.push(TAG_STRING_NULL),
+        }
+        self
+    }
+
+    pub fn put_arbitrary(&mut self, data: &[u8]) -> &mut Self {
+        self.buf.push(TAG_ARBITRARY);
+        self.buf.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        self.buf.extend_from_slice(data);
+        self
+    }
+
+    pub fn put_sample_spec(&mut self, spec: &SampleSpec) -> &mut Self {
+        self.buf.extend_from_slice(&[TAG_SAMPLE_SPEC, spec.format, spec.channels]);
+        self.buf.extend_from_slice(&spec.rate.to_be_bytes());
+        self
+    }
+
+    pub fn put_channel_map(&mut self, map: &ChannelMap) -> &mut Self {
+        self.buf.extend_from_slice(&[TAG_CHANNEL_MAP, map.0.len() as u8]);
+        self.buf.extend_from_slice(&map.0);
+        self
+    }
+
+    pub fn put_cvolume(&mut self, volume: &CVolume) -> &mut Self {
+        self.buf.extend_from_slice(&[TAG_CVOLUME, volume.0.len() as u8]);
+        for channel in &volume.0 {
+            self.buf.extend_from_slice(&channel.to_be_bytes());
+        }
+        self
+    }
+
+    pub fn put_volume(&mut self, volume: u32) -> &mut Self {
+        self.buf.push(TAG_VOLUME);
+        self.buf.extend_from_slice(&volume.to_be_bytes());
+        self
+    }
+
+    pub fn put_proplist(&mut self, proplist: &Proplist) -> &mut Self {
+        self.buf.push(TAG_PROPLIST);
+        for (key, value) in &proplist.0 {
+            self.put_string(Some(key));
+            self.put_u32(value.len() as u32);
+            self.put_arbitrary(value);
+        }
+        self.put_string(None)
+    }
+
+    pub fn put_format_info(&mut self, format: &FormatInfo) -> &mut Self {
+        self.buf.push(TAG_FORMAT_INFO);
+        self.put_u8(format.encoding);
+        self.put_proplist(&format.props)
+    }
+}
+
+/// Reads the values of a packet in order.
+#[derive(Debug)]
+pub struct TagReader {
+    data: Vec<u8>,
+    pos: usize,
+}
+
+impl TagReader {
+    pub fn new(data: Vec<u8>) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    /// Whether every value was read.
+    pub fn is_empty(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+
+    fn take(&mut self, len: usize) -> Result<&[u8], TagStructError> {
+        let end = self.pos.checked_add(len).filter(|&end| end <= self.data.len()).ok_or_else(|| TagStructError("truncated value".to_string()))?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn expect(&mut self, tag: u8) -> Result<(), TagStructError> {
+        let found = self.take(1)?[0];
+        if found != tag {
+            return Err(TagStructError(format!("expected '{}', found '{}'", tag as char, found as char)));
+        }
+        Ok(())
+    }
+
+    fn raw_u32(&mut self) -> Result<u32, TagStructError> {
+        Ok(u32::from_be_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn raw_u64(&mut self) -> Result<u64, TagStructError> {
+        Ok(u64::from_be_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    pub fn get_u32(&mut self) -> Result<u32, TagStructError> {
+        self.expect(TAG_U32)?;
+        self.raw_u32()
+    }
+
+    pub fn get_u8(&mut self) -> Result<u8, TagStructError> {
+        self.expect(TAG_U8)?;
+        Ok(self.take(1)?[0])
+    }
+
+    pub fn get_u64(&mut self) -> Result<u64, TagStructError> {
+        self.expect(TAG_U64)?;
+        self.raw_u64()
+    }
+
+    pub fn get_s64(&mut self) -> Result<i64, TagStructError> {
+        self.expect(TAG_S64)?;
+        Ok(self.raw_u64()? as i64)
+    }
+
+    pub fn get_usec(&mut self) -> Result<u64, TagStructError> {
+        self.expect(TAG_USEC)?;
+        self.raw_u64()
+    }
+
+    pub fn get_bool(&mut self) -> Result<bool, TagStructError> {
+        match self.take(1)?[0] {
+            TAG_BOOLEAN_TRUE => Ok(true),
+            TAG_BOOLEAN_FALSE => Ok(false),
+            found => Err(TagStructError(format!("expected a boolean, found '{}'", found as char))),
+        }
+    }
+
+    pub fn get_string(&mut self) -> Result<Option<String>, TagStructError> {
+        match self.take(1)?[0] {
+            TAG_STRING_NULL => Ok(None),
+            TAG_STRING => {
+                let rest = &self.data[self.pos..];
+                let len = rest.iter().position(|&b| b == 0).ok_or_else(|| TagStructError("unterminated string".to_string()))?;
+                let value = String::from_utf8_lossy(&rest[..len]).into_owned();
+                self.pos += len + 1;
+                Ok(Some(value))
+            }
+            found => Err(TagStructError(format!("expected a string, found '{}'", found as char))),
+        }
+    }
+
+    pub fn get_arbitrary(&mut self) -> Result<Vec<u8>, TagStructError> {
+        self.expect(TAG_ARBITRARY)?;
+        let len = self.raw_u32()? as usize;
+        Ok(self.take(len)?.to_vec())
+    }
+
+    pub fn get_sample_spec(&mut self) -> Result<SampleSpec, TagStructError> {
+        self.expect(TAG_SAMPLE_SPEC)?;
+        let bytes = self.take(2)?;
+        let (format, channels) = (bytes[0], bytes[1]);
+        Ok(SampleSpec { format, channels, rate: self.raw_u32()? })
+    }
+
+    pub fn get_channel_map(&mut self) -> Result<ChannelMap, TagStructError> {
+        self.expect(TAG_CHANNEL_MAP)?;
+        let channels = self.take(1)?[0] as usize;
+        Ok(ChannelMap(self.take(channels)?.to_vec()))
+    }
+
+    pub fn get_cvolume(&mut self) -> Result<CVolume, TagStructError> {
+        self.expect(TAG_CVOLUME)?;
+        let channels = self.take(1)?[0];
+        (0..channels).map(|_| self.raw_u32()).collect::<Result<_, _>>().map(CVolume)
+    }
+
+    pub fn get_volume(&mut self) -> Result<u32, TagStructError> {
+        self.expect(TAG_VOLUME)?;
+        self.raw_u32()
+    }
+
+    pub fn get_proplist(&mut self) -> Result<Proplist, TagStructError> {
+        self.expect(TAG_PROPLIST)?;
+        let mut proplist = Proplist::default();
+        while let Some(key) = self.get_string()? {
+            let len = self.get_u32()? as usize;
+            let value = self.get_arbitrary()?;
+            if value.len() != len {
+                return Err(TagStructError(format!("property {} is {} bytes, announced {}", key, value.len(), len)));
+            }
+            proplist.0.insert(key, value);
+        }
+        Ok(proplist)
+    }
+
+    pub fn get_format_info(&mut self) -> Result<FormatInfo, TagStructError> {
+        self.expect(TAG_FORMAT_INFO)?;
+        Ok(FormatInfo { encoding: self.get_u8()?, props: self.get_proplist()? })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let mut props = Proplist::default();
+        props.set_str("application.name", "Music");
+        let mut writer = TagWriter::new();
+        writer
+            .put_u32(7)
+            .put_string(Some("alsa_output.speakers"))
+            .put_string(None)
+            .put_bool(true)
+            .put_sample_spec(&SampleSpec { format: 3, channels: 2, rate: 48000 })
+            .put_channel_map(&ChannelMap(vec![1, 2]))
+            .put_cvolume(&CVolume(vec![VOLUME_NORM, VOLUME_NORM / 2]))
+            .put_usec(1500)
+            .put_proplist(&props)
+            .put_format_info(&FormatInfo { encoding: 1, props: Proplist::default() });
+        let bytes = writer.into_bytes();
+        assert_eq!(&bytes[..5], &[b'L', 0, 0, 0, 7]);
+
+        let mut reader = TagReader::new(bytes);
+        assert_eq!(reader.get_u32().unwrap(), 7);
+        assert_eq!(reader.get_string().unwrap().as_deref(), Some("alsa_output.speakers"));
+        assert_eq!(reader.get_string().unwrap(), None);
+        assert!(reader.get_bool().unwrap());
+        assert_eq!(reader.get_sample_spec().unwrap().rate, 48000);
+        assert_eq!(reader.get_channel_map().unwrap(), ChannelMap(vec![1, 2]));
+        assert_eq!(reader.get_cvolume().unwrap().linear(), 1.0);
+        assert_eq!(reader.get_usec().unwrap(), 1500);
+        assert_eq!(reader.get_proplist().unwrap().get_str("application.name"), Some("Music"));
+        assert_eq!(reader.get_format_info().unwrap().encoding, 1);
+        assert!(reader.is_empty());
+        assert!(reader.get_u32().is_err());
+    }
+
+    #[test]
+    fn test_wrong_tag_and_truncation() {
+        let mut reader = TagReader::new(TagWriter::new().put_u8(1).buf.clone());
+        assert!(reader.get_u32().is_err());
+        assert!(TagReader::new(vec![b'L', 0, 0]).get_u32().is_err());
+        assert!(TagReader::new(vec![b't', b'a']).get_string().is_err());
+    }
+
+    #[test]
+    fn test_cvolume_scaling_keeps_balance() {
+        let volume = CVolume(vec![VOLUME_NORM, VOLUME_NORM / 2]);
+        assert_eq!(volume.scaled_to(0.5), CVolume(vec![VOLUME_NORM / 2, VOLUME_NORM / 4]));
+        assert_eq!(CVolume(vec![0, 0]).scaled_to(0.25).linear(), 0.25);
+        assert_eq!(CVolume::uniform(0, 1.0), CVolume(vec![VOLUME_NORM]));
+    }
+}
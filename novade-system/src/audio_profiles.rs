@@ -0,0 +1,396 @@
+// novade-system/src/audio_profiles.rs
+
+//! Per-application audio profiles: remembered volumes and preferred devices, keyed by app id.
+//!
+//! [`AudioProfileRouter`] keeps the `audio.app-profiles` setting of the [`GlobalSettingsService`]
+//! and applies it through the [`AudioManager`]. A stream that starts gets the volume of its
+//! application and moves to the first of the application's preferred devices that is plugged
+//! in. When devices come or go, all streams are routed again: "music to the headphones" follows
+//! the headphones, and unplugging them sends the music to the next preference rather than to
+//! whatever the server picked. Applications without a profile, or without a preferred device
+//! present, stay where the server put them.
+//!
+//! Profiles change through the router (the shell's mixer calls it over D-Bus) or the settings
+//! service. Streams moved by other mixers are not learned from, since the server moves streams
+//! on its own when devices are unplugged.
+
+use std::sync::Arc;
+
+use novade_domain::global_settings::paths::AudioSettingPath;
+use novade_domain::{AppAudioProfile, AudioSettings, GlobalSettingsService, SettingChangedEvent, SettingPath};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::Mutex;
+
+use crate::audio_management::{AudioDevice, AudioDeviceType, AudioEvent, AudioManager, AudioStream};
+use crate::error::{to_system_error, SystemErrorKind, SystemResult};
+
+/// Devices remembered per application and direction, most recently chosen first.
+const MAX_PREFERRED_DEVICES: usize = 4;
+/// Volumes closer than this are left alone rather than set again.
+const VOLUME_TOLERANCE: f64 = 0.005;
+
+pub struct AudioProfileRouter {
+    audio: Arc<dyn AudioManager>,
+    settings: Arc<dyn GlobalSettingsService>,
+    /// The profiles as last saved or announced by the settings service.
+    profiles: Mutex<Vec<AppAudioProfile>>,
+}
+
+impl AudioProfileRouter {
+    /// Creates a router with the profiles currently in `settings`.
+    pub async fn new(audio: Arc<dyn AudioManager>, settings: Arc<dyn GlobalSettingsService>) -> Self {
+        let profiles = load_profiles(settings.clone()).await;
+        Self { audio, settings, profiles: Mutex::new(profiles) }
+    }
+
+    pub async fn profiles(&self) -> Vec<AppAudioProfile> {
+        self.profiles.lock().await.clone()
+    }
+
+    pub async fn profile(&self, app_id: &str) -> Option<AppAudioProfile> {
+        self.profiles.lock().await.iter().find(|profile| profile.app_id == app_id).cloned()
+    }
+
+    /// Sets the volume of `app_id`'s playback streams and remembers it for its next ones.
+    pub async fn set_app_volume(&self, app_id: &str, volume: f64) -> SystemResult<()> {
+        if !(0.0..=1.0).contains(&volume) {
+            return Err(to_system_error(format!("Invalid volume level: {}", volume), SystemErrorKind::AudioManagement));
+        }
+        for stream in self.app_streams(app_id, AudioDeviceType::Output).await? {
+            self.audio.set_stream_volume(stream.id(), volume).await?;
+        }
+        self.update_profile(app_id, |profile| profile.volume = Some(volume)).await
+    }
+
+    /// Moves `app_id`'s streams to `device_id`, playback streams for an output and recording
+    /// streams for an input, and makes it the application's preferred device of that type.
+    pub async fn set_app_device(&self, app_id: &str, device_id: &str) -> SystemResult<()> {
+        let device_type = self.audio.get_device(device_id).await?.device_type();
+        for stream in self.app_streams(app_id, device_type).await? {
+            if stream.device_id() != Some(device_id) {
+                self.audio.move_stream(stream.id(), device_id).await?;
+            }
+        }
+        self.update_profile(app_id, |profile| {
+            let preferred = preferred_devices_mut(profile, device_type);
+            preferred.retain(|id| id != device_id);
+            preferred.insert(0, device_id.to_string());
+            preferred.truncate(MAX_PREFERRED_DEVICES);
+        })
+        .await
+    }
+
+    /// Forgets the profile of `app_id`; its streams stay where they are.
+    pub async fn forget_app(&self, app_id: &str) -> SystemResult<()> {
+        let mut profiles = self.profiles.lock().await;
+        let mut updated = profiles.clone();
+        updated.retain(|profile| profile.app_id != app_id);
+        if updated.len() == profiles.len() {
+            return Ok(());
+        }
+        self.save(&updated).await?;
+        *profiles = updated;
+        Ok(())
+    }
+
+    /// Gives a new stream the volume and the device of its application's profile.
+    pub async fn apply_to_stream(&self, stream: &AudioStream) -> SystemResult<()> {
+        let Some(profile) = self.profile(stream.app_id()).await else {
+            return Ok(());
+        };
+        if stream.stream_type() == AudioDeviceType::Output {
+            if let Some(volume) = profile.volume.filter(|volume| (volume - stream.volume()).abs() > VOLUME_TOLERANCE) {
+                self.audio.set_stream_volume(stream.id(), volume).await?;
+            }
+        }
+        let devices = self.audio.get_devices().await?;
+        self.route(&profile, stream, &devices).await
+    }
+
+    /// Moves every stream with a profile to its preferred device that is present. Volumes are
+    /// left alone, as they may have been changed since the streams started.
+    pub async fn route_all(&self) -> SystemResult<()> {
+        let profiles = self.profiles().await;
+        if profiles.is_empty() {
+            return Ok(());
+        }
+        let devices = self.audio.get_devices().await?;
+        for stream in self.audio.get_streams().await? {
+            let Some(profile) = profiles.iter().find(|profile| profile.app_id == stream.app_id()) else {
+                continue;
+            };
+            // The stream may have ended in the meantime.
+            if let Err(e) = self.route(profile, &stream, &devices).await {
+                tracing::debug!("AudioProfiles: could not route stream {} of '{}': {}", stream.id(), stream.app_id(), e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Takes the profiles announced by the settings service; returns whether they changed.
+    pub async fn settings_changed(&self, event: &SettingChangedEvent) -> bool {
+        let profiles = match &event.path {
+            SettingPath::Audio(AudioSettingPath::AppProfiles) => serde_json::from_value::<Vec<AppAudioProfile>>(event.new_value.clone()),
+            SettingPath::AudioRoot => serde_json::from_value::<AudioSettings>(event.new_value.clone()).map(|audio| audio.app_profiles),
+            _ => return false,
+        };
+        match profiles {
+            Ok(profiles) => {
+                let mut current = self.profiles.lock().await;
+                let changed = *current != profiles;
+                *current = profiles;
+                changed
+            }
+            Err(e) => {
+                tracing::warn!("AudioProfiles: ignoring unreadable {} setting: {}", event.path, e);
+                false
+            }
+        }
+    }
+
+    async fn route(&self, profile: &AppAudioProfile, stream: &AudioStream, devices: &[AudioDevice]) -> SystemResult<()> {
+        let stream_type = stream.stream_type();
+        let target = preferred_devices(profile, stream_type)
+            .iter()
+            .find(|id| devices.iter().any(|device| device.device_type() == stream_type && device.id() == id.as_str()));
+        match target {
+            Some(target) if stream.device_id() != Some(target.as_str()) => {
+                tracing::debug!("AudioProfiles: moving stream {} of '{}' to {}", stream.id(), profile.app_id, target);
+                self.audio.move_stream(stream.id(), target).await
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// The running streams of `app_id` using devices of `stream_type`.
+    async fn app_streams(&self, app_id: &str, stream_type: AudioDeviceType) -> SystemResult<Vec<AudioStream>> {
+        let mut streams = self.audio.get_streams().await?;
+        streams.retain(|stream| stream.app_id() == app_id && stream.stream_type() == stream_type);
+        Ok(streams)
+    }
+
+    /// Changes the profile of `app_id`, creating it if needed, and saves the profiles.
+    async fn update_profile(&self, app_id: &str, change: impl FnOnce(&mut AppAudioProfile)) -> SystemResult<()> {
+        let mut profiles = self.profiles.lock().await;
+        let mut updated = profiles.clone();
+        let index = match updated.iter().position(|profile| profile.app_id == app_id) {
+            Some(index) => index,
+            None => {
+                updated.push(AppAudioProfile::new(app_id));
+                updated.len() - 1
+            }
+        };
+        change(&mut updated[index]);
+        self.save(&updated).await?;
+        *profiles = updated;
+        Ok(())
+    }
+
+    async fn save(&self, profiles: &[AppAudioProfile]) -> SystemResult<()> {
+        let value = serde_json::to_value(profiles)
+            .map_err(|e| to_system_error(format!("Could not serialize audio profiles: {}", e), SystemErrorKind::AudioManagement))?;
+        self.settings
+            .update_setting(SettingPath::Audio(AudioSettingPath::AppProfiles), value)
+            .await
+            .map_err(|e| to_system_error(format!("Could not save audio profiles: {}", e), SystemErrorKind::AudioManagement))
+    }
+}
+
+fn preferred_devices(profile: &AppAudioProfile, device_type: AudioDeviceType) -> &[String] {
+    match device_type {
+        AudioDeviceType::Output => &profile.output_devices,
+        AudioDeviceType::Input => &profile.input_devices,
+    }
+}
+
+fn preferred_devices_mut(profile: &mut AppAudioProfile, device_type: AudioDeviceType) -> &mut Vec<String> {
+    match device_type {
+        AudioDeviceType::Output => &mut profile.output_devices,
+        AudioDeviceType::Input => &mut profile.input_devices,
+    }
+}
+
+/// Reads the profiles off the runtime's threads, as the settings service reads blockingly.
+async fn load_profiles(settings: Arc<dyn GlobalSettingsService>) -> Vec<AppAudioProfile> {
+    let path = SettingPath::Audio(AudioSettingPath::AppProfiles);
+    let value = match tokio::task::spawn_blocking(move || settings.get_setting(&path)).await {
+        Ok(Ok(value)) => value,
+        Ok(Err(e)) => {
+            tracing::warn!("AudioProfiles: could not read the audio profiles: {}", e);
+            return Vec::new();
+        }
+        Err(e) => {
+            tracing::error!("AudioProfiles: reading the audio profiles failed: {}", e);
+            return Vec::new();
+        }
+    };
+    serde_json::from_value(value).unwrap_or_else(|e| {
+        tracing::warn!("AudioProfiles: ignoring unreadable audio profiles: {}", e);
+        Vec::new()
+    })
+}
+
+/// Starts applying `router`'s profiles to the streams and devices of its audio manager.
+pub fn spawn_audio_profiles(router: Arc<AudioProfileRouter>) {
+    let mut settings_events = router.settings.subscribe_to_setting_changes();
+    tokio::spawn(async move {
+        let mut audio_events = match router.audio.subscribe().await {
+            Ok(events) => events,
+            Err(e) => {
+                tracing::warn!("AudioProfiles: cannot follow the sound server, profiles disabled: {}", e);
+                return;
+            }
+        };
+        // Streams that were already running.
+        if let Err(e) = router.route_all().await {
+            tracing::warn!("AudioProfiles: could not route the running streams: {}", e);
+        }
+        loop {
+            let result = tokio::select! {
+                event = audio_events.recv() => match event {
+                    Ok(AudioEvent::StreamAdded(stream)) => router.apply_to_stream(&stream).await,
+                    Ok(AudioEvent::DeviceAdded(_)) | Ok(AudioEvent::DeviceRemoved(_)) => router.route_all().await,
+                    Ok(_) => Ok(()),
+                    Err(RecvError::Lagged(skipped)) => {
+                        tracing::warn!("AudioProfiles: missed {} audio events", skipped);
+                        router.route_all().await
+                    }
+                    Err(RecvError::Closed) => break,
+                },
+                event = settings_events.recv() => match event {
+                    Ok(event) if router.settings_changed(&event).await => router.route_all().await,
+                    Ok(_) => Ok(()),
+                    Err(RecvError::Lagged(skipped)) => {
+                        tracing::warn!("AudioProfiles: missed {} setting changes", skipped);
+                        Ok(())
+                    }
+                    Err(RecvError::Closed) => break,
+                },
+            };
+            if let Err(e) = result {
+                tracing::warn!("AudioProfiles: {}", e);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio_management::fake_pulse_server::{FakePulseServer, HDMI, MICROPHONE, SPEAKERS};
+    use crate::audio_management::PulseAudioManager;
+    use async_trait::async_trait;
+    use novade_domain::{DefaultGlobalSettingsService, GlobalDesktopSettings, GlobalSettingsError, SettingsPersistenceProvider};
+    use std::time::Duration;
+
+    const HEADPHONES: &str = "bluez_output.headphones";
+    const HEADSET: &str = "alsa_input.usb-headset";
+
+    /// Keeps the saved settings in memory.
+    struct MemoryPersistence {
+        saved: std::sync::Mutex<GlobalDesktopSettings>,
+    }
+
+    #[async_trait]
+    impl SettingsPersistenceProvider for MemoryPersistence {
+        async fn load_global_settings(&self) -> Result<GlobalDesktopSettings, GlobalSettingsError> {
+            Ok(self.saved.lock().unwrap().clone())
+        }
+
+        async fn save_global_settings(&self, settings: &GlobalDesktopSettings) -> Result<(), GlobalSettingsError> {
+            *self.saved.lock().unwrap() = settings.clone();
+            Ok(())
+        }
+    }
+
+    async fn settings_with(profiles: Vec<AppAudioProfile>) -> (Arc<MemoryPersistence>, Arc<DefaultGlobalSettingsService>) {
+        let mut initial = GlobalDesktopSettings::default();
+        initial.audio.app_profiles = profiles;
+        let persistence = Arc::new(MemoryPersistence { saved: std::sync::Mutex::new(initial) });
+        let service = Arc::new(DefaultGlobalSettingsService::new(persistence.clone(), 16));
+        service.load_settings().await.unwrap();
+        (persistence, service)
+    }
+
+    async fn eventually(what: &str, condition: impl Fn() -> bool) {
+        for _ in 0..200 {
+            if condition() {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("timed out waiting for {}", what);
+    }
+
+    #[tokio::test]
+    async fn test_streams_follow_preferred_devices() {
+        let server = Arc::new(FakePulseServer::start().await);
+        let mut music = AppAudioProfile::new("Music Player");
+        music.volume = Some(0.4);
+        music.output_devices = vec![HEADPHONES.to_string(), HDMI.to_string()];
+        let (_, settings) = settings_with(vec![music]).await;
+        let audio = Arc::new(PulseAudioManager::connect(server.path()).await.unwrap());
+        let router = Arc::new(AudioProfileRouter::new(audio, settings).await);
+        spawn_audio_profiles(router.clone());
+
+        // Without the headphones, the running stream goes to the second choice.
+        let hdmi = server.sink(HDMI).index;
+        eventually("the running stream on HDMI", || server.stream(0).unwrap().sink == hdmi).await;
+
+        let headphones = server.add_device(false, HEADPHONES, "Headphones");
+        eventually("the running stream on the headphones", || server.stream(0).unwrap().sink == headphones).await;
+
+        let index = server.add_stream("Music Player", server.sink(SPEAKERS).index);
+        eventually("the new stream on the headphones", || server.stream(index).unwrap().sink == headphones).await;
+        assert!((server.stream(index).unwrap().volume.linear() - 0.4).abs() < 0.001);
+        // Streams started before were not turned down.
+        assert!((server.stream(0).unwrap().volume.linear() - 0.8).abs() < 0.001);
+
+        // The server rescues the streams to the speakers; the profile prefers HDMI.
+        server.remove_device(false, HEADPHONES);
+        eventually("the streams back on HDMI", || server.stream(0).unwrap().sink == hdmi && server.stream(index).unwrap().sink == hdmi).await;
+
+        // Other applications stay where the server puts them.
+        let other = server.add_stream("Browser", server.sink(SPEAKERS).index);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(server.stream(other).unwrap().sink, server.sink(SPEAKERS).index);
+    }
+
+    #[tokio::test]
+    async fn test_mixer_choices_are_remembered() {
+        let server = FakePulseServer::start().await;
+        let (persistence, settings) = settings_with(Vec::new()).await;
+        let audio = Arc::new(PulseAudioManager::connect(server.path()).await.unwrap());
+        let router = AudioProfileRouter::new(audio, settings.clone()).await;
+        let recording = server.add_recording("Video Call", server.source(MICROPHONE).index);
+        let headset = server.add_device(true, HEADSET, "USB Headset");
+
+        router.set_app_device("Video Call", HEADSET).await.unwrap();
+        assert_eq!(server.recording(recording).unwrap().source, headset);
+        router.set_app_device("Music Player", HDMI).await.unwrap();
+        router.set_app_device("Music Player", SPEAKERS).await.unwrap();
+        assert_eq!(server.stream(0).unwrap().sink, server.sink(SPEAKERS).index);
+        router.set_app_volume("Music Player", 0.3).await.unwrap();
+        assert!((server.stream(0).unwrap().volume.linear() - 0.3).abs() < 0.001);
+        assert!(router.set_app_volume("Music Player", 1.2).await.is_err());
+        assert!(router.set_app_device("Music Player", "alsa_output.missing").await.is_err());
+
+        let saved = persistence.saved.lock().unwrap().audio.clone();
+        let call = saved.app_profile("Video Call").unwrap();
+        assert_eq!(call.input_devices, [HEADSET]);
+        assert!(call.output_devices.is_empty());
+        let music = saved.app_profile("Music Player").unwrap();
+        assert_eq!(music.output_devices, [SPEAKERS, HDMI]);
+        assert_eq!(music.volume, Some(0.3));
+
+        // A new router starts from the saved profiles.
+        let audio = Arc::new(PulseAudioManager::connect(server.path()).await.unwrap());
+        let restarted = AudioProfileRouter::new(audio, settings).await;
+        assert_eq!(restarted.profiles().await, router.profiles().await);
+
+        router.forget_app("Video Call").await.unwrap();
+        assert!(router.profile("Video Call").await.is_none());
+        assert!(persistence.saved.lock().unwrap().audio.app_profile("Video Call").is_none());
+        assert_eq!(server.recording(recording).unwrap().source, headset);
+    }
+}
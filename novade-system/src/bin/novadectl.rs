@@ -0,0 +1,233 @@
+// novade-system/src/bin/novadectl.rs
+//! Command line client for the NovaDE compositor IPC socket.
+//!
+//! ```text
+//! novadectl [--socket PATH] [--json] <command>
+//!
+//!   version                      compositor and protocol version
+//!   outputs                      connected outputs
+//!   workspaces                   workspaces per output
+//!   windows                      toplevel windows
+//!   action <name> [key=value…]   run an action, e.g. `action switch_workspace index=2`
+//!   action '<json>'              run an action given as JSON
+//!   reload                       reload compositor.toml
+//!   subscribe [kind,…]           stream events (window, workspace, output, mode)
+//! ```
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use serde_json::{Map, Value};
+
+use novade_system::compositor::ipc::protocol::{
+    default_socket_path, IpcEventKind, IpcRequest, IpcWindow, OutputInfo, VersionInfo, WorkspaceSummary,
+};
+use novade_system::compositor::ipc::server::IpcClient;
+use novade_system::compositor::keybindings::KeybindingAction;
+
+const USAGE: &str = "usage: novadectl [--socket PATH] [--json] \
+<version|outputs|workspaces|windows|action <name> [key=value...]|reload|subscribe [kind,...]>";
+
+enum Command {
+    Request(IpcRequest),
+    Subscribe(Vec<IpcEventKind>),
+}
+
+struct Args {
+    socket: Option<PathBuf>,
+    json: bool,
+    command: Command,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut socket = None;
+    let mut json = false;
+    let mut positional = Vec::new();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--socket" | "-s" => socket = Some(PathBuf::from(args.next().ok_or("--socket needs a path")?)),
+            "--json" | "-j" => json = true,
+            "--help" | "-h" => return Err(USAGE.to_string()),
+            _ => positional.push(arg),
+        }
+    }
+
+    let (name, rest) = positional.split_first().ok_or_else(|| USAGE.to_string())?;
+    let command = match name.as_str() {
+        "version" => Command::Request(IpcRequest::GetVersion),
+        "outputs" => Command::Request(IpcRequest::GetOutputs),
+        "workspaces" => Command::Request(IpcRequest::GetWorkspaces),
+        "windows" => Command::Request(IpcRequest::GetWindows),
+        "reload" => Command::Request(IpcRequest::ReloadConfig),
+        "action" => Command::Request(IpcRequest::RunAction { action: parse_action(rest)? }),
+        "subscribe" => {
+            let kinds = rest
+                .iter()
+                .flat_map(|arg| arg.split(','))
+                .filter(|kind| !kind.is_empty())
+                .map(|kind| {
+                    serde_json::from_value(Value::String(kind.to_string()))
+                        .map_err(|_| format!("unknown event kind '{}'", kind))
+                })
+                .collect::<Result<Vec<IpcEventKind>, String>>()?;
+            Command::Subscribe(kinds)
+        }
+        other => return Err(format!("unknown command '{}'\n{}", other, USAGE)),
+    };
+    Ok(Args { socket, json, command })
+}
+
+/// Builds an action from either a JSON object or `<name> [key=value…]`. Values that parse as
+/// JSON (numbers, booleans, quoted strings) are used as such, anything else as a string.
+fn parse_action(args: &[String]) -> Result<KeybindingAction, String> {
+    let (name, fields) = args.split_first().ok_or("action needs a name")?;
+    let value = if name.trim_start().starts_with('{') {
+        serde_json::from_str(name).map_err(|e| format!("invalid action JSON: {}", e))?
+    } else {
+        let mut object = Map::new();
+        object.insert("action".to_string(), Value::String(name.replace('-', "_")));
+        for field in fields {
+            let (key, raw) = field.split_once('=').ok_or_else(|| format!("expected key=value, got '{}'", field))?;
+            let value = serde_json::from_str(raw).unwrap_or_else(|_| Value::String(raw.to_string()));
+            object.insert(key.to_string(), value);
+        }
+        Value::Object(object)
+    };
+    serde_json::from_value(value).map_err(|e| format!("invalid action: {}", e))
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(message) => {
+            eprintln!("{}", message);
+            return ExitCode::from(2);
+        }
+    };
+    let socket = match args.socket.or_else(default_socket_path) {
+        Some(path) => path,
+        None => {
+            eprintln!("novadectl: cannot determine the IPC socket, set NOVADE_SOCK or pass --socket");
+            return ExitCode::from(2);
+        }
+    };
+    let client = match IpcClient::connect(&socket) {
+        Ok(client) => client,
+        Err(e) => {
+            eprintln!("novadectl: cannot connect to {}: {}", socket.display(), e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match args.command {
+        Command::Subscribe(kinds) => subscribe(client, kinds),
+        Command::Request(request) => run_request(client, request, args.json),
+    }
+}
+
+fn subscribe(client: IpcClient, kinds: Vec<IpcEventKind>) -> ExitCode {
+    let events = match client.subscribe(kinds) {
+        Ok(events) => events,
+        Err(e) => {
+            eprintln!("novadectl: subscribe failed: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    // Events are always printed as JSON lines so they can be piped into other tools.
+    for event in events {
+        match event.map(|event| serde_json::to_string(&event)) {
+            Ok(Ok(line)) => println!("{}", line),
+            Ok(Err(e)) => eprintln!("novadectl: {}", e),
+            Err(e) => {
+                eprintln!("novadectl: {}", e);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+fn run_request(mut client: IpcClient, request: IpcRequest, json: bool) -> ExitCode {
+    let response = match client.request(&request) {
+        Ok(response) => response,
+        Err(e) => {
+            eprintln!("novadectl: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    if json {
+        println!("{}", serde_json::to_string_pretty(&response).unwrap_or_default());
+        return if response.success { ExitCode::SUCCESS } else { ExitCode::FAILURE };
+    }
+    if !response.success {
+        eprintln!("novadectl: {}", response.error.unwrap_or_else(|| "request failed".to_string()));
+        return ExitCode::FAILURE;
+    }
+
+    let printed = match request {
+        IpcRequest::GetVersion => response.into_data().map(|v: VersionInfo| print_version(&v)),
+        IpcRequest::GetOutputs => response.into_data().map(|o: Vec<OutputInfo>| print_outputs(&o)),
+        IpcRequest::GetWorkspaces => response.into_data().map(|w: Vec<WorkspaceSummary>| print_workspaces(&w)),
+        IpcRequest::GetWindows => response.into_data().map(|w: Vec<IpcWindow>| print_windows(&w)),
+        _ => Ok(()),
+    };
+    match printed {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("novadectl: unexpected reply: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn print_version(version: &VersionInfo) {
+    println!("{} {} (IPC protocol {})", version.name, version.version, version.protocol_version);
+}
+
+fn print_outputs(outputs: &[OutputInfo]) {
+    for output in outputs {
+        let marker = if output.focused { "*" } else { " " };
+        println!(
+            "{} {} {}x{}@{:.2}Hz at {},{} scale {} {}{}",
+            marker,
+            output.name,
+            output.width,
+            output.height,
+            output.refresh_mhz as f64 / 1000.0,
+            output.x,
+            output.y,
+            output.scale,
+            output.description,
+            if output.primary { " (primary)" } else { "" },
+        );
+    }
+}
+
+fn print_workspaces(workspaces: &[WorkspaceSummary]) {
+    for ws in workspaces {
+        let marker = if ws.focused { "*" } else if ws.active { "+" } else { " " };
+        let position = match (&ws.output, ws.index) {
+            (Some(output), Some(index)) => format!("{}:{}", output, index),
+            _ => "-".to_string(),
+        };
+        println!("{} {:<12} {:<20} {:<12} {} windows  {}", marker, position, ws.name, ws.layout, ws.windows.len(), ws.id);
+    }
+}
+
+fn print_windows(windows: &[IpcWindow]) {
+    for window in windows {
+        let marker = if window.focused { "*" } else { " " };
+        println!(
+            "{} {}  {:<24} {:<10} {}x{}+{}+{}  {}",
+            marker,
+            window.info.id,
+            window.app_id.as_deref().unwrap_or("-"),
+            format!("{:?}", window.info.state),
+            window.info.rect.size.width,
+            window.info.rect.size.height,
+            window.info.rect.origin.x,
+            window.info.rect.origin.y,
+            window.info.title,
+        );
+    }
+}
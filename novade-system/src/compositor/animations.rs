@@ -12,13 +12,30 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-//! Basic animation system for the compositor.
+//! Animation system for the compositor.
+//!
+//! Animations are scalar [`Animation`]s keyed by window and [`AnimationType`]. Besides the
+//! original linear [`FadeAnimation`], [`PropertyAnimation`] animates any window property with an
+//! [`Easing`] curve (linear, cubic-bezier or spring) and can be retargeted while running.
+//!
+//! Geometry animations (`PositionX`, `PositionY`, `Width`, `Height`) are expressed as deltas
+//! relative to the window's real geometry and always settle at `0.0`, so a window can be moved
+//! or resized immediately while the render path draws it at
+//! [`AnimationManager::render_geometry`]. The manager is advanced once per frame from the
+//! frame clock via [`AnimationManager::update_animations`].
 
 use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 use novade_domain::workspaces::core::WindowId; // Assuming this is the correct WindowId
+use serde::{Deserialize, Serialize};
+use smithay::utils::{Logical, Rectangle};
 use tracing::trace;
 
+use crate::compositor::shell::xdg_shell::types::{DomainWindowIdentifier, ManagedWindow};
+use crate::compositor::state::DesktopState;
+
 /// State of an animation.
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum AnimationState {
@@ -42,6 +59,22 @@ pub trait Animation: Send + Sync + std::fmt::Debug {
     /// A way to identify the type of animation, e.g., for opacity, position.
     /// This helps in managing multiple animations of different types on the same window.
     fn animation_type(&self) -> AnimationType;
+
+    /// The value the animation settles at.
+    fn target_value(&self) -> f32;
+
+    /// Redirects a running animation to a new target, continuing from its current value.
+    /// Returns `false` if the animation does not support retargeting; the manager then
+    /// replaces it with a new animation starting at the current value.
+    fn retarget(&mut self, _now: Instant, _target: f32) -> bool {
+        false
+    }
+
+    /// Moves the animation's whole value range by `delta` without affecting its progress or
+    /// velocity. Used to rebase relative animations when their reference changes.
+    fn shift(&mut self, _delta: f32) -> bool {
+        false
+    }
 }
 
 /// Enum to identify different types of animations.
@@ -49,9 +82,170 @@ pub trait Animation: Send + Sync + std::fmt::Debug {
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum AnimationType {
     Opacity,
-    // ANCHOR: Add other types like PositionX, PositionY, Scale, etc.
+    /// Horizontal offset from the real window position, in logical pixels.
+    PositionX,
+    /// Vertical offset from the real window position, in logical pixels.
+    PositionY,
+    /// Width difference to the real window size, in logical pixels.
+    Width,
+    /// Height difference to the real window size, in logical pixels.
+    Height,
+    /// Uniform scale around the window center.
+    Scale,
+    /// Corner radius in logical pixels.
+    CornerRadius,
 }
 
+impl AnimationType {
+    /// Returns `true` for animations that move or resize content on screen.
+    /// These are skipped entirely when "reduce motion" is enabled.
+    pub fn is_motion(&self) -> bool {
+        !matches!(self, AnimationType::Opacity | AnimationType::CornerRadius)
+    }
+
+    /// Value used when no animation of this type is running.
+    pub fn resting_value(&self) -> f32 {
+        match self {
+            AnimationType::Opacity | AnimationType::Scale => 1.0,
+            _ => 0.0,
+        }
+    }
+}
+
+// ANCHOR: EasingCurves
+/// Easing curve of a [`PropertyAnimation`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Easing {
+    Linear,
+    /// CSS-style cubic bezier with control points `(x1, y1)` and `(x2, y2)`.
+    CubicBezier { x1: f32, y1: f32, x2: f32, y2: f32 },
+    /// Damped spring. The animation runs until the spring has settled, for at most
+    /// `MAX_SPRING_DURATION`. The configured duration does not shorten it; a zero duration
+    /// still skips the animation.
+    Spring { stiffness: f32, damping: f32, mass: f32 },
+}
+
+impl Easing {
+    pub const EASE: Easing = Easing::CubicBezier { x1: 0.25, y1: 0.1, x2: 0.25, y2: 1.0 };
+    pub const EASE_OUT: Easing = Easing::CubicBezier { x1: 0.0, y1: 0.0, x2: 0.58, y2: 1.0 };
+    pub const EASE_IN_OUT: Easing = Easing::CubicBezier { x1: 0.42, y1: 0.0, x2: 0.58, y2: 1.0 };
+    pub const SPRING: Easing = Easing::Spring { stiffness: 300.0, damping: 30.0, mass: 1.0 };
+
+    /// Maps linear progress `t` in `[0, 1]` to eased progress. Springs are integrated over
+    /// time by [`PropertyAnimation`] instead and fall back to linear here.
+    pub fn apply(&self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match *self {
+            Easing::Linear | Easing::Spring { .. } => t,
+            Easing::CubicBezier { x1, y1, x2, y2 } => cubic_bezier(x1, y1, x2, y2, t),
+        }
+    }
+}
+
+impl Default for Easing {
+    fn default() -> Self {
+        Easing::EASE_OUT
+    }
+}
+
+/// Evaluates a cubic bezier timing function at `x` (CSS semantics).
+fn cubic_bezier(x1: f32, y1: f32, x2: f32, y2: f32, x: f32) -> f32 {
+    let bezier = |t: f32, p1: f32, p2: f32| {
+        let u = 1.0 - t;
+        3.0 * u * u * t * p1 + 3.0 * u * t * t * p2 + t * t * t
+    };
+    let derivative = |t: f32, p1: f32, p2: f32| {
+        let u = 1.0 - t;
+        3.0 * u * u * p1 + 6.0 * u * t * (p2 - p1) + 3.0 * t * t * (1.0 - p2)
+    };
+
+    // Newton-Raphson first, bisection as fallback for flat regions.
+    let mut t = x;
+    for _ in 0..8 {
+        let err = bezier(t, x1, x2) - x;
+        if err.abs() < 1e-5 {
+            return bezier(t, y1, y2);
+        }
+        let d = derivative(t, x1, x2);
+        if d.abs() < 1e-6 {
+            break;
+        }
+        t = (t - err / d).clamp(0.0, 1.0);
+    }
+    let (mut lo, mut hi) = (0.0f32, 1.0f32);
+    t = x;
+    for _ in 0..32 {
+        let v = bezier(t, x1, x2);
+        if (v - x).abs() < 1e-5 {
+            break;
+        }
+        if v < x {
+            lo = t;
+        } else {
+            hi = t;
+        }
+        t = (lo + hi) / 2.0;
+    }
+    bezier(t, y1, y2)
+}
+// ANCHOR_END: EasingCurves
+
+// ANCHOR: AnimationSettings
+/// Duration and easing used for one kind of window animation.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AnimationCurve {
+    pub duration_ms: u64,
+    #[serde(default)]
+    pub easing: Easing,
+}
+
+impl AnimationCurve {
+    pub const fn new(duration_ms: u64, easing: Easing) -> Self {
+        Self { duration_ms, easing }
+    }
+}
+
+/// User-facing animation configuration, read from `VisualConfig::animation_settings`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AnimationSettings {
+    /// Master switch. When disabled, every property jumps to its target immediately.
+    pub enabled: bool,
+    /// Accessibility switch: skips all motion (position, size, scale) and keeps only
+    /// opacity and corner radius transitions.
+    pub reduce_motion: bool,
+    /// Multiplier applied to all durations (`2.0` is twice as slow).
+    pub duration_scale: f32,
+    pub window_open: AnimationCurve,
+    pub window_close: AnimationCurve,
+    pub window_layout: AnimationCurve,
+    pub workspace_switch: AnimationCurve,
+    pub maximize: AnimationCurve,
+}
+
+impl Default for AnimationSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            reduce_motion: false,
+            duration_scale: 1.0,
+            window_open: AnimationCurve::new(200, Easing::EASE_OUT),
+            window_close: AnimationCurve::new(150, Easing::EASE_IN_OUT),
+            window_layout: AnimationCurve::new(250, Easing::SPRING),
+            workspace_switch: AnimationCurve::new(300, Easing::EASE_IN_OUT),
+            maximize: AnimationCurve::new(220, Easing::EASE_OUT),
+        }
+    }
+}
+
+impl AnimationSettings {
+    fn duration(&self, curve: &AnimationCurve) -> Duration {
+        Duration::from_millis((curve.duration_ms as f64 * self.duration_scale.max(0.0) as f64).round() as u64)
+    }
+}
+// ANCHOR_END: AnimationSettings
+
 
 /// A simple linear fade animation.
 #[derive(Debug)]
@@ -212,6 +406,138 @@ mod tests {
         // Progress on the *new* animation (0.5 to 1.0 over 100ms, so at 50ms it's 0.75)
         assert!(거의_같음(manager.get_window_opacity(window_id1).unwrap(), 0.75, 0.001));
     }
+
+    #[test]
+    fn test_cubic_bezier_easing() {
+        let ease_out = Easing::EASE_OUT;
+        assert!(거의_같음(ease_out.apply(0.0), 0.0, 0.001));
+        assert!(거의_같음(ease_out.apply(1.0), 1.0, 0.001));
+        assert!(ease_out.apply(0.5) > 0.5, "ease-out should be ahead of linear at the midpoint");
+
+        // A linear bezier must match linear easing.
+        let linear = Easing::CubicBezier { x1: 0.0, y1: 0.0, x2: 1.0, y2: 1.0 };
+        let mut previous = 0.0;
+        for i in 0..=20 {
+            let t = i as f32 / 20.0;
+            assert!(거의_같음(linear.apply(t), t, 0.01));
+            let v = Easing::EASE_IN_OUT.apply(t);
+            assert!(v >= previous - 1e-4, "ease-in-out must be monotonic");
+            previous = v;
+        }
+    }
+
+    #[test]
+    fn test_spring_settles_at_target() {
+        let now = Instant::now();
+        let mut anim = PropertyAnimation::starting_at(now, AnimationType::PositionX, 100.0, 0.0, Duration::from_millis(300), Easing::SPRING);
+        assert_eq!(anim.update(now + Duration::from_millis(16)), AnimationState::Running);
+        assert!(anim.current_value() < 100.0);
+        assert!(anim.velocity() < 0.0);
+
+        let mut t = now;
+        let mut state = AnimationState::Running;
+        while state == AnimationState::Running && t < now + MAX_SPRING_DURATION {
+            t += Duration::from_millis(16);
+            state = anim.update(t);
+        }
+        assert_eq!(state, AnimationState::Completed);
+        assert_eq!(anim.current_value(), 0.0);
+        assert!(t < now + Duration::from_secs(1), "spring should settle well before the cap");
+    }
+
+    #[test]
+    fn test_spring_retarget_keeps_velocity() {
+        let now = Instant::now();
+        let mut anim = PropertyAnimation::starting_at(now, AnimationType::Width, 0.0, 100.0, Duration::from_millis(300), Easing::SPRING);
+        let mid = now + Duration::from_millis(50);
+        anim.update(mid);
+        let (value, velocity) = (anim.current_value(), anim.velocity());
+        assert!(velocity > 0.0);
+
+        assert!(anim.retarget(mid, -50.0));
+        assert_eq!(anim.current_value(), value, "retargeting must not jump");
+        assert_eq!(anim.velocity(), velocity);
+        assert_eq!(anim.target_value(), -50.0);
+    }
+
+    #[test]
+    fn test_manager_animate_retargets_without_jump() {
+        let mut manager = AnimationManager::new();
+        let window = WindowId::new();
+        let now = Instant::now();
+        let curve = AnimationCurve::new(100, Easing::Linear);
+
+        manager.animate(now, window, AnimationType::Opacity, 0.0, 1.0, curve);
+        let mid = now + Duration::from_millis(50);
+        manager.update_animations(mid);
+        assert!(거의_같음(manager.get_window_opacity(window).unwrap(), 0.5, 0.001));
+
+        // Interrupt: fade back out from the current value.
+        manager.animate(mid, window, AnimationType::Opacity, 1.0, 0.0, curve);
+        assert!(거의_같음(manager.get_window_opacity(window).unwrap(), 0.5, 0.001));
+        manager.update_animations(mid + Duration::from_millis(50));
+        assert!(거의_같음(manager.get_window_opacity(window).unwrap(), 0.25, 0.001));
+    }
+
+    #[test]
+    fn test_reduce_motion_skips_motion_but_keeps_fades() {
+        let settings = AnimationSettings { reduce_motion: true, ..AnimationSettings::default() };
+        let mut manager = AnimationManager::with_settings(settings);
+        let window = WindowId::new();
+        let now = Instant::now();
+
+        manager.animate_map(now, window);
+        assert!(manager.get_value(window, AnimationType::Opacity).is_some());
+        assert!(manager.get_value(window, AnimationType::Scale).is_none());
+
+        let from = Rectangle::from_loc_and_size((0, 0), (100, 100));
+        let to = Rectangle::from_loc_and_size((500, 0), (200, 100));
+        manager.animate_layout(now, window, from, to);
+        assert_eq!(manager.render_geometry(window, to), to);
+
+        // Disabling animations finishes everything immediately.
+        manager.set_settings(AnimationSettings { enabled: false, ..AnimationSettings::default() });
+        assert!(!manager.has_active_animations(None));
+    }
+
+    #[test]
+    fn test_layout_animation_starts_at_old_geometry() {
+        let mut manager = AnimationManager::new();
+        let window = WindowId::new();
+        let now = Instant::now();
+        let curve = AnimationCurve::new(100, Easing::Linear);
+        let from = Rectangle::from_loc_and_size((0, 0), (400, 300));
+        let to = Rectangle::from_loc_and_size((400, 100), (200, 300));
+
+        manager.animate_geometry(now, window, from, to, curve);
+        assert_eq!(manager.render_geometry(window, to), from);
+
+        manager.update_animations(now + Duration::from_millis(50));
+        let halfway = manager.render_geometry(window, to);
+        assert_eq!(halfway, Rectangle::from_loc_and_size((200, 50), (300, 300)));
+
+        // Interrupt with a new relayout: the drawn geometry must not jump.
+        let to2 = Rectangle::from_loc_and_size((0, 0), (800, 600));
+        manager.animate_geometry(now + Duration::from_millis(50), window, to, to2, curve);
+        assert_eq!(manager.render_geometry(window, to2), halfway);
+
+        assert!(!manager.update_animations(now + Duration::from_millis(150)));
+        assert_eq!(manager.render_geometry(window, to2), to2);
+    }
+
+    #[test]
+    fn test_animation_settings_roundtrip_through_toml() {
+        let settings = AnimationSettings::default();
+        let text = toml::to_string(&settings).unwrap();
+        let parsed: AnimationSettings = toml::from_str(&text).unwrap();
+        assert_eq!(parsed, settings);
+
+        let partial: AnimationSettings = toml::from_str("reduce_motion = true\n[maximize]\nduration_ms = 100\n").unwrap();
+        assert!(partial.reduce_motion);
+        assert_eq!(partial.maximize.duration_ms, 100);
+        assert_eq!(partial.maximize.easing, Easing::default());
+        assert_eq!(partial.window_open, AnimationSettings::default().window_open);
+    }
 }
 
 impl Animation for FadeAnimation {
@@ -242,24 +568,201 @@ impl Animation for FadeAnimation {
     fn animation_type(&self) -> AnimationType {
         AnimationType::Opacity
     }
+
+    fn target_value(&self) -> f32 {
+        self.final_opacity
+    }
+}
+
+// ANCHOR: PropertyAnimation
+/// Upper bound for spring animations, in case a spring is configured to never settle.
+const MAX_SPRING_DURATION: Duration = Duration::from_secs(3);
+/// Integration step for spring animations.
+const SPRING_STEP: f32 = 1.0 / 1000.0;
+
+/// Animates a single property from one value to another with an [`Easing`] curve.
+#[derive(Debug)]
+pub struct PropertyAnimation {
+    property: AnimationType,
+    easing: Easing,
+    start_time: Instant,
+    duration: Duration,
+    from: f32,
+    to: f32,
+    current: f32,
+    /// Units per second; only maintained for springs.
+    velocity: f32,
+    last_update: Instant,
+    completed: bool,
+}
+
+impl PropertyAnimation {
+    pub fn new(property: AnimationType, from: f32, to: f32, duration: Duration, easing: Easing) -> Self {
+        Self::starting_at(Instant::now(), property, from, to, duration, easing)
+    }
+
+    /// Creates an animation with an explicit start time (e.g., the frame clock's time).
+    pub fn starting_at(now: Instant, property: AnimationType, from: f32, to: f32, duration: Duration, easing: Easing) -> Self {
+        Self {
+            property,
+            easing,
+            start_time: now,
+            duration,
+            from,
+            to,
+            current: from,
+            velocity: 0.0,
+            last_update: now,
+            completed: false,
+        }
+    }
+
+    pub fn velocity(&self) -> f32 {
+        self.velocity
+    }
+
+    fn step_spring(&mut self, now: Instant, stiffness: f32, damping: f32, mass: f32) -> AnimationState {
+        let mut remaining = now.saturating_duration_since(self.last_update).as_secs_f32();
+        self.last_update = self.last_update.max(now);
+        let mass = mass.max(f32::EPSILON);
+        while remaining > 0.0 {
+            let dt = remaining.min(SPRING_STEP);
+            // Semi-implicit Euler is stable enough for the stiffness range we use.
+            let force = -stiffness * (self.current - self.to) - damping * self.velocity;
+            self.velocity += force / mass * dt;
+            self.current += self.velocity * dt;
+            remaining -= dt;
+        }
+
+        let range = (self.to - self.from).abs().max(1.0);
+        let settled = (self.current - self.to).abs() < range * 1e-3 && self.velocity.abs() < range * 1e-2;
+        if settled || now.saturating_duration_since(self.start_time) >= MAX_SPRING_DURATION {
+            self.current = self.to;
+            self.velocity = 0.0;
+            AnimationState::Completed
+        } else {
+            AnimationState::Running
+        }
+    }
 }
 
+impl Animation for PropertyAnimation {
+    fn start_time(&self) -> Instant {
+        self.start_time
+    }
+
+    fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    fn update(&mut self, now: Instant) -> AnimationState {
+        if self.completed {
+            return AnimationState::Completed;
+        }
+        let state = match self.easing {
+            Easing::Spring { stiffness, damping, mass } => self.step_spring(now, stiffness, damping, mass),
+            easing => {
+                let elapsed = now.saturating_duration_since(self.start_time);
+                if elapsed >= self.duration || self.duration.is_zero() {
+                    self.current = self.to;
+                    AnimationState::Completed
+                } else {
+                    let progress = easing.apply(elapsed.as_secs_f32() / self.duration.as_secs_f32());
+                    self.current = self.from + (self.to - self.from) * progress;
+                    AnimationState::Running
+                }
+            }
+        };
+        self.completed = state == AnimationState::Completed;
+        state
+    }
+
+    fn current_value(&self) -> f32 {
+        match self.property {
+            AnimationType::Opacity => self.current.clamp(0.0, 1.0),
+            AnimationType::Scale | AnimationType::CornerRadius => self.current.max(0.0),
+            _ => self.current,
+        }
+    }
+
+    fn animation_type(&self) -> AnimationType {
+        self.property
+    }
+
+    fn target_value(&self) -> f32 {
+        self.to
+    }
+
+    fn shift(&mut self, delta: f32) -> bool {
+        self.from += delta;
+        self.to += delta;
+        self.current += delta;
+        true
+    }
+
+    fn retarget(&mut self, now: Instant, target: f32) -> bool {
+        // Springs keep their velocity, so interrupting one is seamless.
+        self.from = self.current;
+        self.to = target;
+        self.start_time = now;
+        self.last_update = now;
+        self.completed = false;
+        if !matches!(self.easing, Easing::Spring { .. }) {
+            self.velocity = 0.0;
+        }
+        true
+    }
+}
+// ANCHOR_END: PropertyAnimation
+
 /// Manages all active animations in the compositor.
-#[derive(Debug, Default)]
-pub struct AnimationManager {
+///
+/// Keyed by `WindowId` by default; the compositor uses its own `DomainWindowIdentifier`.
+#[derive(Debug)]
+pub struct AnimationManager<K = WindowId> {
     // Using AnimationType as key within the Vec allows replacing an animation of the same type.
     // Or, if only one animation of each type is allowed, HashMap<AnimationType, Box<dyn Animation>>
-    active_animations: HashMap<WindowId, Vec<Box<dyn Animation>>>,
+    active_animations: HashMap<K, Vec<Box<dyn Animation>>>,
+    settings: AnimationSettings,
+}
+
+impl<K> Default for AnimationManager<K> {
+    fn default() -> Self {
+        Self {
+            active_animations: HashMap::new(),
+            settings: AnimationSettings::default(),
+        }
+    }
 }
 
-impl AnimationManager {
+impl<K: Copy + Eq + Hash + std::fmt::Debug> AnimationManager<K> {
     pub fn new() -> Self {
         Self::default()
     }
 
+    pub fn with_settings(settings: AnimationSettings) -> Self {
+        Self { active_animations: HashMap::new(), settings }
+    }
+
+    pub fn settings(&self) -> &AnimationSettings {
+        &self.settings
+    }
+
+    /// Applies new settings. Running motion animations are finished immediately when
+    /// animations get disabled or "reduce motion" is switched on.
+    pub fn set_settings(&mut self, settings: AnimationSettings) {
+        let disabled = !settings.enabled;
+        let reduce_motion = settings.reduce_motion;
+        self.settings = settings;
+        self.active_animations.retain(|_, animations| {
+            animations.retain(|anim| !(disabled || (reduce_motion && anim.animation_type().is_motion())));
+            !animations.is_empty()
+        });
+    }
+
     /// Adds an animation for a specific window.
     /// If an animation of the same type already exists for this window, it's replaced.
-    pub fn add_animation(&mut self, window_id: WindowId, animation: Box<dyn Animation>) {
+    pub fn add_animation(&mut self, window_id: K, animation: Box<dyn Animation>) {
         let anim_type = animation.animation_type();
         let window_anims = self.active_animations.entry(window_id).or_default();
         // Remove existing animation of the same type
@@ -289,25 +792,162 @@ impl AnimationManager {
         any_running
     }
 
+    /// Animates `property` of a window towards `to`.
+    ///
+    /// If an animation of the same property is running it is retargeted, so interrupted
+    /// animations continue smoothly from their current value; otherwise a new animation starts
+    /// at `from`. Respects the enabled and "reduce motion" switches.
+    pub fn animate(&mut self, now: Instant, window_id: K, property: AnimationType, from: f32, to: f32, curve: AnimationCurve) {
+        let skip = !self.settings.enabled || (self.settings.reduce_motion && property.is_motion());
+        let duration = self.settings.duration(&curve);
+        let window_anims = self.active_animations.entry(window_id).or_default();
+
+        if let Some(existing) = window_anims.iter_mut().find(|a| a.animation_type() == property) {
+            if skip {
+                window_anims.retain(|a| a.animation_type() != property);
+            } else if !existing.retarget(now, to) {
+                let current = existing.current_value();
+                *existing = Box::new(PropertyAnimation::starting_at(now, property, current, to, duration, curve.easing));
+            }
+        } else if !skip && (from - to).abs() > f32::EPSILON && !duration.is_zero() {
+            trace!("Animating {:?} of window {:?} from {} to {}", property, window_id, from, to);
+            window_anims.push(Box::new(PropertyAnimation::starting_at(now, property, from, to, duration, curve.easing)));
+        }
+        if window_anims.is_empty() {
+            self.active_animations.remove(&window_id);
+        }
+    }
+
+    /// Gets the current value of an animated property, if an animation for it is active.
+    pub fn get_value(&self, window_id: K, property: AnimationType) -> Option<f32> {
+        self.active_animations.get(&window_id).and_then(|animations| {
+            animations
+                .iter()
+                .find(|anim| anim.animation_type() == property)
+                .map(|anim| anim.current_value())
+        })
+    }
+
+    /// Gets the current value of a property, or its resting value if it is not animated.
+    pub fn value_or_resting(&self, window_id: K, property: AnimationType) -> f32 {
+        self.get_value(window_id, property).unwrap_or_else(|| property.resting_value())
+    }
+
     /// Gets the current opacity for a window if a fade animation is active.
     /// Returns `None` if no opacity animation is running (implying full opacity or externally managed).
-    pub fn get_window_opacity(&self, window_id: WindowId) -> Option<f32> {
-        self.active_animations.get(&window_id)
-            .and_then(|animations| {
-                animations.iter().find_map(|anim| {
-                    if anim.animation_type() == AnimationType::Opacity {
-                        Some(anim.current_value())
-                    } else {
-                        None
-                    }
-                })
-            })
+    pub fn get_window_opacity(&self, window_id: K) -> Option<f32> {
+        self.get_value(window_id, AnimationType::Opacity)
+    }
+
+    /// Cancels all animations of a window, e.g., when it is destroyed.
+    pub fn remove_window(&mut self, window_id: K) {
+        self.active_animations.remove(&window_id);
+    }
+
+    // ANCHOR: WindowAnimationEvents
+    /// Window mapped: fade in and grow slightly.
+    pub fn animate_map(&mut self, now: Instant, window_id: K) {
+        let curve = self.settings.window_open;
+        self.animate(now, window_id, AnimationType::Opacity, 0.0, 1.0, curve);
+        self.animate(now, window_id, AnimationType::Scale, 0.9, 1.0, curve);
+    }
+
+    /// Window unmapped: fade out and shrink. The window is only drawn while this runs if it
+    /// was unmapped through [`DesktopState::animate_window_close`].
+    pub fn animate_unmap(&mut self, now: Instant, window_id: K) {
+        let curve = self.settings.window_close;
+        let opacity = self.value_or_resting(window_id, AnimationType::Opacity);
+        let scale = self.value_or_resting(window_id, AnimationType::Scale);
+        self.animate(now, window_id, AnimationType::Opacity, opacity, 0.0, curve);
+        self.animate(now, window_id, AnimationType::Scale, scale, 0.9, curve);
+    }
+
+    /// Window geometry changed from `from` to `to` (tiling relayout, maximize, ...).
+    ///
+    /// The window's real geometry is already `to`; the animation starts at the currently
+    /// drawn geometry so interrupted animations don't jump.
+    pub fn animate_geometry(&mut self, now: Instant, window_id: K, from: Rectangle<i32, Logical>, to: Rectangle<i32, Logical>, curve: AnimationCurve) {
+        // Deltas are relative to the real geometry, so running ones are rebased onto `to`.
+        let shifts = [
+            (AnimationType::PositionX, (from.loc.x - to.loc.x) as f32),
+            (AnimationType::PositionY, (from.loc.y - to.loc.y) as f32),
+            (AnimationType::Width, (from.size.w - to.size.w) as f32),
+            (AnimationType::Height, (from.size.h - to.size.h) as f32),
+        ];
+        for (property, shift) in shifts {
+            let rebased = self
+                .active_animations
+                .get_mut(&window_id)
+                .and_then(|anims| anims.iter_mut().find(|a| a.animation_type() == property))
+                .map_or(false, |anim| anim.shift(shift));
+            if rebased {
+                self.animate(now, window_id, property, 0.0, 0.0, curve);
+            } else {
+                let start = self.value_or_resting(window_id, property) + shift;
+                self.remove_property(window_id, property);
+                self.animate(now, window_id, property, start, 0.0, curve);
+            }
+        }
+    }
+
+    /// Window entering the screen during a workspace switch. `direction` is `1` when
+    /// switching to a workspace to the right, `-1` to the left.
+    pub fn animate_workspace_enter(&mut self, now: Instant, window_id: K, output_width: i32, direction: i32) {
+        let curve = self.settings.workspace_switch;
+        let offset = (output_width * direction.signum()) as f32;
+        self.remove_property(window_id, AnimationType::PositionX);
+        self.animate(now, window_id, AnimationType::PositionX, offset, 0.0, curve);
+        if self.settings.reduce_motion {
+            // Crossfade instead of sliding.
+            self.animate(now, window_id, AnimationType::Opacity, 0.0, 1.0, curve);
+        }
+    }
+
+    /// Convenience for maximize/unmaximize transitions.
+    pub fn animate_maximize(&mut self, now: Instant, window_id: K, from: Rectangle<i32, Logical>, to: Rectangle<i32, Logical>) {
+        let curve = self.settings.maximize;
+        self.animate_geometry(now, window_id, from, to, curve);
+    }
+
+    /// Convenience for tiling relayouts.
+    pub fn animate_layout(&mut self, now: Instant, window_id: K, from: Rectangle<i32, Logical>, to: Rectangle<i32, Logical>) {
+        let curve = self.settings.window_layout;
+        self.animate_geometry(now, window_id, from, to, curve);
+    }
+    // ANCHOR_END: WindowAnimationEvents
+
+    /// Returns the geometry a window should be drawn at, given its real geometry.
+    /// Applies position/size deltas and scales around the center.
+    pub fn render_geometry(&self, window_id: K, real: Rectangle<i32, Logical>) -> Rectangle<i32, Logical> {
+        if !self.active_animations.contains_key(&window_id) {
+            return real;
+        }
+        let dx = self.value_or_resting(window_id, AnimationType::PositionX);
+        let dy = self.value_or_resting(window_id, AnimationType::PositionY);
+        let dw = self.value_or_resting(window_id, AnimationType::Width);
+        let dh = self.value_or_resting(window_id, AnimationType::Height);
+        let scale = self.value_or_resting(window_id, AnimationType::Scale);
+
+        let w = (real.size.w as f32 + dw).max(1.0);
+        let h = (real.size.h as f32 + dh).max(1.0);
+        let x = real.loc.x as f32 + dx;
+        let y = real.loc.y as f32 + dy;
+        let (sw, sh) = (w * scale, h * scale);
+        Rectangle::from_loc_and_size(
+            ((x + (w - sw) / 2.0).round() as i32, (y + (h - sh) / 2.0).round() as i32),
+            (sw.round().max(1.0) as i32, sh.round().max(1.0) as i32),
+        )
+    }
+
+    fn remove_property(&mut self, window_id: K, property: AnimationType) {
+        if let Some(animations) = self.active_animations.get_mut(&window_id) {
+            animations.retain(|a| a.animation_type() != property);
+        }
     }
 
-    // ANCHOR: Add methods to get other animated values, e.g., get_window_position_offset.
     // ANCHOR: Add method to check if any animation is running for a specific window or globally,
     // to help decide if a repaint is needed.
-    pub fn has_active_animations(&self, window_id: Option<WindowId>) -> bool {
+    pub fn has_active_animations(&self, window_id: Option<K>) -> bool {
         if let Some(id) = window_id {
             self.active_animations.get(&id).map_or(false, |anims| !anims.is_empty())
         } else {
@@ -315,3 +955,57 @@ impl AnimationManager {
         }
     }
 }
+
+// ANCHOR: DesktopStateAnimationIntegration
+impl DesktopState {
+    /// Applies animation settings from `VisualConfig::animation_settings`.
+    ///
+    /// The overview transition follows the same switches: it is instant when animations are
+    /// disabled or "reduce motion" is on.
+    pub fn apply_animation_settings(&mut self, settings: AnimationSettings) {
+        let overview_ms = if settings.enabled && !settings.reduce_motion {
+            (crate::compositor::overview::DEFAULT_OVERVIEW_TRANSITION_MS as f64 * settings.duration_scale.max(0.0) as f64).round() as u64
+        } else {
+            0
+        };
+        self.overview.set_transition_duration(Duration::from_millis(overview_ms));
+        self.animation_manager.set_settings(settings);
+    }
+
+    /// Advances all window animations to the frame time `now`.
+    /// Returns `true` if another frame should be scheduled.
+    pub fn advance_animations(&mut self, now: Instant) -> bool {
+        let windows_animating = self.animation_manager.update_animations(now);
+        let overview_animating = self.overview.tick(now);
+        let (animations, windows) = (&self.animation_manager, &self.windows);
+        self.closing_windows.retain(|window_id, _| {
+            animations.has_active_animations(Some(*window_id))
+                && windows.get(window_id).is_some_and(|window| !window.state.read().unwrap().is_mapped)
+        });
+        windows_animating || overview_animating
+    }
+
+    /// Starts the close animation of a window that is being unmapped from `geometry`. Its
+    /// surface must stay alive: the render path keeps drawing it there until the animation
+    /// has finished, the window is mapped again or it is destroyed.
+    pub fn animate_window_close(&mut self, window_id: DomainWindowIdentifier, geometry: Rectangle<i32, Logical>) {
+        self.animation_manager.animate_unmap(Instant::now(), window_id);
+        if self.animation_manager.has_active_animations(Some(window_id)) {
+            self.closing_windows.insert(window_id, geometry);
+        }
+    }
+
+    /// Windows still playing their close animation on `output_name`, with the geometry they
+    /// were unmapped from.
+    pub fn closing_windows_on(&self, output_name: &str) -> Vec<(Arc<ManagedWindow>, Rectangle<i32, Logical>)> {
+        self.closing_windows
+            .iter()
+            .filter_map(|(window_id, geometry)| {
+                let window = self.windows.get(window_id)?;
+                let on_output = window.output_name.read().unwrap().as_deref() == Some(output_name);
+                (on_output && !window.state.read().unwrap().is_mapped).then(|| (window.clone(), *geometry))
+            })
+            .collect()
+    }
+}
+// ANCHOR_END: DesktopStateAnimationIntegration
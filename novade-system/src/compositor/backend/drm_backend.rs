@@ -1,14 +1,47 @@
 // novade-system/src/compositor/backend/drm_backend.rs
 
+use std::collections::{HashMap, HashSet};
+
 use anyhow::{Result, anyhow};
 use calloop::LoopHandle;
-use smithay::reexports::wayland_server::DisplayHandle;
+use libc::dev_t;
+use smithay::backend::allocator::gbm::GbmDevice;
+use smithay::backend::allocator::Fourcc;
+use smithay::backend::drm::{DrmDeviceFd, DrmNode};
+use smithay::backend::renderer::gles::GlesRenderer;
+use smithay::backend::renderer::multigpu::gbm::GbmGlesBackend;
+use smithay::backend::renderer::multigpu::{GpuManager, MultiRenderer};
+use smithay::backend::renderer::ImportDma;
+use smithay::backend::udev::UdevEvent;
+use smithay::reexports::wayland_protocols::wp::linux_dmabuf::zv1::server::zwp_linux_dmabuf_feedback_v1::TrancheFlags;
+use smithay::reexports::wayland_server::backend::ObjectId;
+use smithay::reexports::wayland_server::{DisplayHandle, Resource};
+use smithay::utils::{Physical, Rectangle};
+use smithay::wayland::compositor::with_states;
+use smithay::wayland::dmabuf::{DmabufFeedback, DmabufFeedbackBuilder, SurfaceDmabufFeedbackState};
 
+use crate::compositor::ipc::protocol::{IpcEvent, OutputChange};
+use crate::compositor::night_light::GammaDevice;
 use crate::compositor::state::DesktopState;
 use super::CompositorBackend; // Super refers to novade-system/src/compositor/backend/mod.rs
+use super::gpu::{CopyMode, FeedbackPlan, GpuChange, GpuRegistry, SystemUdev, DEFAULT_SEAT};
+use super::planes::{KmsDevice, PlaneAssigner, PlaneAssignment, ScanoutCandidate};
+
+/// Graphics API of the multi-GPU renderer: GLES on GBM devices.
+pub type MultiGpuApi = GbmGlesBackend<GlesRenderer, DrmDeviceFd>;
 
 pub struct DrmBackend {
     event_loop_handle: LoopHandle<'static, DesktopState>,
+    /// Chooses direct scanout and overlay planes per frame, keyed by the client surface.
+    plane_assigner: PlaneAssigner<ObjectId>,
+    /// The seat's GPUs, the one compositing and which GPU drives each output.
+    gpus: GpuRegistry,
+    /// Renders on the primary GPU and copies frames to outputs of the others.
+    gpu_manager: Option<GpuManager<MultiGpuApi>>,
+    /// Render nodes of the GPUs handed to `gpu_manager`.
+    gpu_nodes: HashMap<dev_t, DrmNode>,
+    /// Output whose DMA-BUF feedback each surface got last.
+    surface_feedback: HashMap<ObjectId, Option<String>>,
     // display_handle: DisplayHandle, // Store if needed for run()
     // Add DRM specific fields here later, e.g.:
     // session: Option<DirectSession>, // Or SessionNotifier from smithay::backend::session
@@ -21,7 +54,7 @@ impl CompositorBackend for DrmBackend {
     fn init(
         event_loop_handle: LoopHandle<'static, DesktopState>,
         _display_handle: DisplayHandle, // Mark as unused for now
-        _desktop_state: &mut DesktopState, // Mark as unused for now
+        desktop_state: &mut DesktopState,
     ) -> Result<Self>
     where
         Self: Sized,
@@ -35,8 +68,24 @@ impl CompositorBackend for DrmBackend {
         // 5. Setting up input (e.g., LibinputInputBackend).
         // For now, this is a placeholder.
         tracing::warn!("DRM backend is a placeholder and not functional.");
+        let mut gpus = GpuRegistry::new(&desktop_state.config.gpu, DEFAULT_SEAT);
+        match gpus.scan(&SystemUdev) {
+            Ok(_) => match gpus.primary() {
+                Some(primary) => tracing::info!("Compositing on GPU {} ({:?}).", primary.name, primary.driver),
+                None => tracing::warn!("No GPU that can render was found."),
+            },
+            Err(e) => tracing::warn!("Failed to enumerate GPUs: {}", e),
+        }
+        let gpu_manager = GpuManager::new(MultiGpuApi::default())
+            .map_err(|e| tracing::warn!("Multi-GPU rendering unavailable: {}", e))
+            .ok();
         Ok(DrmBackend {
             event_loop_handle,
+            plane_assigner: PlaneAssigner::new(&desktop_state.config.performance),
+            gpus,
+            gpu_manager,
+            gpu_nodes: HashMap::new(),
+            surface_feedback: HashMap::new(),
             // display_handle,
         })
     }
@@ -59,3 +108,182 @@ impl CompositorBackend for DrmBackend {
         self.event_loop_handle.clone()
     }
 }
+
+impl DrmBackend {
+    /// Splits a frame of `output` between hardware planes and the renderer. Only
+    /// `PlaneAssignment::composited` needs rendering; when a fullscreen client is scanned out
+    /// directly the renderer is skipped for this output. While the overview is shown everything
+    /// is composited, since it draws the windows scaled into its grid.
+    ///
+    /// Not called yet: the backend has no CRTCs to render to (see `run`) and no [`KmsDevice`]
+    /// implementation.
+    pub fn assign_planes(
+        &mut self,
+        desktop_state: &DesktopState,
+        device: &mut dyn KmsDevice,
+        output: Rectangle<i32, Physical>,
+        candidates: &[ScanoutCandidate<ObjectId>],
+    ) -> PlaneAssignment<ObjectId> {
+        // Picks up configuration reloads.
+        self.plane_assigner.configure(&desktop_state.config.performance);
+        if desktop_state.overview.is_active() {
+            let composited = candidates.iter().map(|candidate| candidate.key.clone()).collect();
+            return PlaneAssignment { primary: None, planes: Vec::new(), composited, rejected: Vec::new() };
+        }
+        self.plane_assigner.assign(device, output, candidates)
+    }
+
+    /// Reports the CRTC gamma LUT of `output_name` and programs the ramp night light or a
+    /// wlr-gamma-control client queued for it, if any.
+    ///
+    /// Not called yet: the backend creates no outputs (see `run`), so there is no CRTC to
+    /// program and night light has no visible effect.
+    pub fn apply_gamma(&mut self, desktop_state: &mut DesktopState, output_name: &str, device: &mut dyn GammaDevice) {
+        desktop_state.set_gamma_size(output_name, device.gamma_size());
+        if let Some(ramp) = desktop_state.night_light.take_gamma_update(output_name) {
+            if let Err(e) = device.set_gamma(&ramp) {
+                tracing::warn!("Failed to set gamma LUT of {}: {}", output_name, e);
+            }
+        }
+    }
+
+    pub fn gpus(&self) -> &GpuRegistry {
+        &self.gpus
+    }
+
+    /// Makes GPU `id`, opened through the session, available for rendering and cross-GPU
+    /// copies. GPUs without a render node are left to CPU copies.
+    pub fn gpu_opened(&mut self, id: dev_t, fd: DrmDeviceFd) -> Result<()> {
+        let device = self.gpus.device(id).ok_or_else(|| anyhow!("unknown GPU {}", id))?;
+        let Some(render_id) = device.render_id else {
+            return Ok(());
+        };
+        let node = DrmNode::from_dev_id(render_id)?;
+        let manager = self.gpu_manager.as_mut().ok_or_else(|| anyhow!("multi-GPU rendering is unavailable"))?;
+        manager.as_mut().add_node(node, GbmDevice::new(fd)?).map_err(|e| anyhow!("failed to add GPU {}: {}", node, e))?;
+        let formats: HashSet<_> = manager
+            .single_renderer(&node)
+            .map_err(|e| anyhow!("failed to create a renderer on {}: {}", node, e))?
+            .dmabuf_formats()
+            .into_iter()
+            .collect();
+        self.gpus.set_render_formats(id, formats);
+        self.gpu_nodes.insert(id, node);
+        Ok(())
+    }
+
+    /// Records that connector `output` belongs to GPU `device`, whose primary plane supports
+    /// `scanout_formats`.
+    pub fn output_connected(&mut self, output: &str, device: dev_t, scanout_formats: HashSet<smithay::backend::allocator::Format>) {
+        self.gpus.add_output(output, device, scanout_formats);
+        match self.gpus.copy_mode(output) {
+            Some(CopyMode::Cpu) => tracing::warn!("{} is on a GPU that cannot import frames; using CPU copies.", output),
+            Some(mode) => tracing::debug!("Frames reach {} by {:?}.", output, mode),
+            None => {}
+        }
+    }
+
+    /// A renderer compositing frames for `output` on the primary GPU, which copies them to the
+    /// output's GPU in `copy_format` when that is another one.
+    pub fn renderer_for_output(&mut self, output: &str, copy_format: Fourcc) -> Result<MultiRenderer<'_, '_, MultiGpuApi, MultiGpuApi>> {
+        let primary = self.gpus.primary().ok_or_else(|| anyhow!("no GPU to render on"))?;
+        let target = self.gpus.output_device(output).ok_or_else(|| anyhow!("{} is not on a known GPU", output))?;
+        let render_node = *self.gpu_nodes.get(&primary.id).ok_or_else(|| anyhow!("GPU {} is not opened", primary.name))?;
+        let target_node = *self
+            .gpu_nodes
+            .get(&target.id)
+            .ok_or_else(|| anyhow!("GPU {} of {} is not opened or cannot render", target.name, output))?;
+        let manager = self.gpu_manager.as_mut().ok_or_else(|| anyhow!("multi-GPU rendering is unavailable"))?;
+        manager
+            .renderer(&render_node, &target_node, copy_format)
+            .map_err(|e| anyhow!("failed to create a renderer for {}: {}", output, e))
+    }
+
+    /// Rescans the GPUs on a udev event of the `drm` subsystem, e.g. an eGPU being plugged in
+    /// or out. The session opens added GPUs and passes them to [`Self::gpu_opened`].
+    pub fn handle_udev_event(&mut self, desktop_state: &mut DesktopState, event: &UdevEvent) {
+        if let UdevEvent::Changed { .. } = event {
+            // Connector changes, handled with the outputs.
+            return;
+        }
+        self.gpus.configure(&desktop_state.config.gpu);
+        let changes = match self.gpus.scan(&SystemUdev) {
+            Ok(changes) => changes,
+            Err(e) => {
+                tracing::warn!("Failed to rescan GPUs: {}", e);
+                return;
+            }
+        };
+        for change in changes {
+            match change {
+                GpuChange::Added(id) => {
+                    let name = self.gpus.device(id).map_or_else(|| id.to_string(), |gpu| gpu.name.clone());
+                    tracing::info!("GPU {} was added.", name);
+                }
+                GpuChange::Removed(id) => {
+                    tracing::info!("GPU {} was removed.", id);
+                    if let (Some(node), Some(manager)) = (self.gpu_nodes.remove(&id), self.gpu_manager.as_mut()) {
+                        manager.as_mut().remove_node(&node);
+                    }
+                }
+                GpuChange::PrimaryChanged { from, to } => {
+                    tracing::warn!("Compositing GPU changed from {:?} to {:?}; client buffers are imported again.", from, to);
+                }
+                GpuChange::OutputsLost(outputs) => {
+                    for name in outputs {
+                        desktop_state.migrate_workspaces_from_output(&name);
+                        let mut space = desktop_state.space.lock().unwrap();
+                        if let Some(output) = space.outputs().find(|o| o.name() == name).cloned() {
+                            space.unmap_output(&output);
+                        }
+                        drop(space);
+                        desktop_state.emit_ipc_event(IpcEvent::Output { change: OutputChange::Removed, name });
+                    }
+                }
+            }
+        }
+        // The main device or the output GPUs may have changed.
+        self.surface_feedback.clear();
+        self.update_surface_feedback(desktop_state);
+    }
+
+    /// Sends each window the DMA-BUF feedback of the output it is on, when that changed, so
+    /// clients allocate buffers the compositing GPU can import and the output's GPU can scan
+    /// out.
+    pub fn update_surface_feedback(&mut self, desktop_state: &DesktopState) {
+        let mut feedbacks: HashMap<Option<String>, Option<DmabufFeedback>> = HashMap::new();
+        let mut alive = HashSet::new();
+        for window in desktop_state.windows.values() {
+            let Some(surface) = window.wl_surface_ref() else {
+                continue;
+            };
+            alive.insert(surface.id());
+            let output = window.output_name.read().unwrap().clone();
+            if self.surface_feedback.get(&surface.id()) == Some(&output) {
+                continue;
+            }
+            let feedback = feedbacks
+                .entry(output.clone())
+                .or_insert_with(|| self.gpus.feedback(output.as_deref()).and_then(|plan| build_feedback(&plan)));
+            let Some(feedback) = feedback else {
+                continue;
+            };
+            with_states(surface, |states| {
+                if let Some(state) = SurfaceDmabufFeedbackState::from_states(states) {
+                    state.set_feedback(feedback);
+                }
+            });
+            self.surface_feedback.insert(surface.id(), output);
+        }
+        self.surface_feedback.retain(|id, _| alive.contains(id));
+    }
+}
+
+fn build_feedback(plan: &FeedbackPlan) -> Option<DmabufFeedback> {
+    let mut builder = DmabufFeedbackBuilder::new(plan.main_device, plan.formats.iter().copied());
+    for tranche in &plan.preferred {
+        let flags = tranche.scanout.then_some(TrancheFlags::Scanout);
+        builder = builder.add_preference_tranche(tranche.target_device, flags, tranche.formats.iter().copied());
+    }
+    builder.build().map_err(|e| tracing::warn!("Failed to build DMA-BUF feedback: {}", e)).ok()
+}
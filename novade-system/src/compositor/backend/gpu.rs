@@ -0,0 +1,512 @@
+// novade-system/src/compositor/backend/gpu.rs
+
+//! Multi-GPU device handling.
+//!
+//! On hybrid laptops and with eGPUs, displays hang off more than one DRM device. NovaDE
+//! composites on one *primary* GPU. Outputs of other GPUs get the composited frame as a
+//! DMA-BUF that their GPU imports and copies into its own scanout buffer, or through a CPU copy
+//! when it cannot import it; the copies are done by smithay's multi-GPU renderer. This module
+//! keeps track of the devices, picks the primary one, decides the copy path of each output and
+//! plans the DMA-BUF feedback that steers clients to allocate buffers the right GPU can use.
+//!
+//! Devices are read from udev through [`UdevSource`], so enumeration and selection can be
+//! tested with recorded udev data. Hotplugged devices (eGPUs) are picked up by rescanning with
+//! [`GpuRegistry::scan`].
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ffi::OsStr;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use libc::dev_t;
+use smithay::backend::allocator::Format;
+
+use crate::compositor::config::GpuConfig;
+
+/// Seat whose devices NovaDE uses when udev assigns none.
+pub const DEFAULT_SEAT: &str = "seat0";
+
+// ANCHOR: UdevSource
+/// A device of the udev `drm` subsystem, as far as GPU selection needs it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UdevRecord {
+    /// Kernel name, e.g. `card1`, `renderD129` or the connector `card1-DP-2`.
+    pub sysname: String,
+    pub devnode: Option<PathBuf>,
+    pub devnum: Option<dev_t>,
+    /// `ID_SEAT`; unset means `seat0`.
+    pub seat: Option<String>,
+    /// Sysfs path of the GPU the node belongs to, shared by its card and render node.
+    pub parent_syspath: Option<PathBuf>,
+    /// Kernel name of the GPU, the slot for PCI devices (`0000:01:00.0`).
+    pub parent_sysname: Option<String>,
+    /// Kernel driver of the GPU.
+    pub driver: Option<String>,
+    /// The firmware used the GPU for the boot console.
+    pub boot_vga: bool,
+    /// The GPU can be unplugged, e.g. behind Thunderbolt.
+    pub removable: bool,
+}
+
+/// Where DRM devices are read from: udev itself, or recorded data in tests.
+pub trait UdevSource {
+    /// All devices of the `drm` subsystem.
+    fn drm_devices(&self) -> io::Result<Vec<UdevRecord>>;
+}
+
+/// The system's udev database.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemUdev;
+
+impl UdevSource for SystemUdev {
+    fn drm_devices(&self) -> io::Result<Vec<UdevRecord>> {
+        let text = |value: Option<&OsStr>| value.map(|v| v.to_string_lossy().into_owned());
+        let mut enumerator = udev::Enumerator::new()?;
+        enumerator.match_subsystem("drm")?;
+        let records = enumerator
+            .scan_devices()?
+            .map(|device| {
+                let parent = device.parent();
+                let attribute = |name: &str| parent.as_ref().and_then(|p| p.attribute_value(name).map(OsStr::to_os_string));
+                UdevRecord {
+                    sysname: device.sysname().to_string_lossy().into_owned(),
+                    devnode: device.devnode().map(Path::to_path_buf),
+                    devnum: device.devnum(),
+                    seat: text(device.property_value("ID_SEAT")),
+                    parent_syspath: parent.as_ref().map(|p| p.syspath().to_path_buf()),
+                    parent_sysname: parent.as_ref().map(|p| p.sysname().to_string_lossy().into_owned()),
+                    driver: parent.as_ref().and_then(|p| text(p.driver())),
+                    boot_vga: attribute("boot_vga").is_some_and(|v| v == "1"),
+                    removable: attribute("removable").is_some_and(|v| v == "removable"),
+                }
+            })
+            .collect();
+        Ok(records)
+    }
+}
+// ANCHOR_END: UdevSource
+
+// ANCHOR: GpuDevice
+/// A GPU with a KMS (card) node.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GpuDevice {
+    /// Device number of the card node; identifies the GPU.
+    pub id: dev_t,
+    /// Kernel name of the card node, e.g. `card1`.
+    pub name: String,
+    pub node: PathBuf,
+    /// Render node, which GPUs without rendering (e.g. USB displays) lack.
+    pub render_node: Option<PathBuf>,
+    pub render_id: Option<dev_t>,
+    /// PCI slot or other bus name.
+    pub bus_id: Option<String>,
+    pub driver: Option<String>,
+    pub boot_vga: bool,
+    pub removable: bool,
+}
+
+impl GpuDevice {
+    pub fn can_render(&self) -> bool {
+        self.render_node.is_some()
+    }
+
+    /// Device clients should allocate on to have this GPU render their buffers.
+    pub fn feedback_device(&self) -> dev_t {
+        self.render_id.unwrap_or(self.id)
+    }
+
+    /// Whether `selector` from `[gpu] render_device` names this GPU: a node path, the card
+    /// name, the bus id or the driver.
+    pub fn matches(&self, selector: &str) -> bool {
+        let selector_path = Path::new(selector);
+        self.node == selector_path
+            || self.render_node.as_deref() == Some(selector_path)
+            || self.name == selector
+            || self.bus_id.as_deref() == Some(selector)
+            || self.driver.as_deref() == Some(selector)
+    }
+}
+
+fn is_node(sysname: &str, prefix: &str) -> bool {
+    sysname.strip_prefix(prefix).is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// The GPUs of `seat` in `records`, each with the render node of the same parent device,
+/// ordered by device number.
+pub fn gpus_from_udev(records: &[UdevRecord], seat: &str) -> Vec<GpuDevice> {
+    let on_seat = |record: &UdevRecord| record.seat.as_deref().unwrap_or(DEFAULT_SEAT) == seat;
+    let mut gpus: Vec<GpuDevice> = records
+        .iter()
+        .filter(|record| is_node(&record.sysname, "card") && on_seat(record))
+        .filter_map(|card| {
+            let render = records.iter().find(|record| {
+                is_node(&record.sysname, "renderD") && record.parent_syspath.is_some() && record.parent_syspath == card.parent_syspath
+            });
+            Some(GpuDevice {
+                id: card.devnum?,
+                name: card.sysname.clone(),
+                node: card.devnode.clone()?,
+                render_node: render.and_then(|r| r.devnode.clone()),
+                render_id: render.and_then(|r| r.devnum),
+                bus_id: card.parent_sysname.clone(),
+                driver: card.driver.clone(),
+                boot_vga: card.boot_vga,
+                removable: card.removable,
+            })
+        })
+        .collect();
+    gpus.sort_by_key(|gpu| gpu.id);
+    gpus
+}
+
+/// The GPU to composite on: the one `preferred` names if it can render, else the one the
+/// firmware booted with, preferring built-in GPUs over removable ones.
+pub fn select_primary<'a>(devices: &'a [GpuDevice], preferred: Option<&str>) -> Option<&'a GpuDevice> {
+    if let Some(selector) = preferred {
+        match devices.iter().find(|gpu| gpu.matches(selector)) {
+            Some(gpu) if gpu.can_render() => return Some(gpu),
+            Some(gpu) => tracing::warn!("GPU {} from [gpu] render_device cannot render; choosing another.", gpu.name),
+            None => tracing::warn!("No GPU matches [gpu] render_device = '{}'; choosing one.", selector),
+        }
+    }
+    devices
+        .iter()
+        .filter(|gpu| gpu.can_render())
+        .min_by_key(|gpu| (!gpu.boot_vga, gpu.removable, gpu.id))
+}
+// ANCHOR_END: GpuDevice
+
+/// How frames reach an output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CopyMode {
+    /// The output is on the primary GPU, which scans out what it composites.
+    Direct,
+    /// The output's GPU imports the composited frame as a DMA-BUF and copies it.
+    Dmabuf,
+    /// The composited frame is read back and uploaded to the output's GPU.
+    Cpu,
+}
+
+/// One preference tranche of DMA-BUF feedback.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeedbackTranche {
+    pub target_device: dev_t,
+    /// Buffers in these formats can be scanned out directly.
+    pub scanout: bool,
+    pub formats: HashSet<Format>,
+}
+
+/// DMA-BUF feedback for surfaces shown on one output: tranches in order of preference,
+/// followed by everything the primary GPU can composite.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeedbackPlan {
+    pub main_device: dev_t,
+    pub formats: HashSet<Format>,
+    pub preferred: Vec<FeedbackTranche>,
+}
+
+/// Effect of a rescan on the device set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GpuChange {
+    Added(dev_t),
+    Removed(dev_t),
+    PrimaryChanged { from: Option<dev_t>, to: Option<dev_t> },
+    /// Outputs of removed devices.
+    OutputsLost(Vec<String>),
+}
+
+#[derive(Debug, Clone)]
+struct OutputGpu {
+    device: dev_t,
+    /// Formats the output's primary plane scans out.
+    scanout_formats: HashSet<Format>,
+}
+
+// ANCHOR: GpuRegistry
+/// The GPUs of the seat, the primary one and which GPU drives each output.
+#[derive(Debug)]
+pub struct GpuRegistry {
+    seat: String,
+    preferred: Option<String>,
+    devices: BTreeMap<dev_t, GpuDevice>,
+    primary: Option<dev_t>,
+    /// Formats each opened GPU can import and render to.
+    render_formats: HashMap<dev_t, HashSet<Format>>,
+    outputs: HashMap<String, OutputGpu>,
+}
+
+impl GpuRegistry {
+    pub fn new(config: &GpuConfig, seat: &str) -> Self {
+        Self {
+            seat: seat.to_string(),
+            preferred: config.render_device.clone(),
+            devices: BTreeMap::new(),
+            primary: None,
+            render_formats: HashMap::new(),
+            outputs: HashMap::new(),
+        }
+    }
+
+    /// Applies a changed `[gpu]` section; takes effect on the next scan.
+    pub fn configure(&mut self, config: &GpuConfig) {
+        self.preferred = config.render_device.clone();
+    }
+
+    /// Brings the device set up to date with `source`, at startup and on udev hotplug events.
+    ///
+    /// The primary GPU only changes when it disappears or when the GPU `[gpu] render_device`
+    /// names appears; switching it means re-importing every client buffer.
+    pub fn scan(&mut self, source: &dyn UdevSource) -> io::Result<Vec<GpuChange>> {
+        let found = gpus_from_udev(&source.drm_devices()?, &self.seat);
+        let mut changes = Vec::new();
+
+        let removed: Vec<dev_t> = self.devices.keys().copied().filter(|id| !found.iter().any(|gpu| gpu.id == *id)).collect();
+        let mut lost = Vec::new();
+        for id in removed {
+            self.devices.remove(&id);
+            self.render_formats.remove(&id);
+            self.outputs.retain(|name, output| {
+                let keep = output.device != id;
+                if !keep {
+                    lost.push(name.clone());
+                }
+                keep
+            });
+            changes.push(GpuChange::Removed(id));
+        }
+        if !lost.is_empty() {
+            lost.sort();
+            changes.push(GpuChange::OutputsLost(lost));
+        }
+        for gpu in found {
+            if !self.devices.contains_key(&gpu.id) {
+                changes.push(GpuChange::Added(gpu.id));
+            }
+            self.devices.insert(gpu.id, gpu);
+        }
+
+        let devices: Vec<GpuDevice> = self.devices.values().cloned().collect();
+        let current = self.primary.filter(|id| self.devices.contains_key(id));
+        let wanted = self.preferred.as_deref().and_then(|selector| devices.iter().find(|gpu| gpu.matches(selector) && gpu.can_render()));
+        let primary = match (current, wanted) {
+            (Some(current), Some(wanted)) if wanted.id != current => Some(wanted.id),
+            (Some(current), _) => Some(current),
+            (None, _) => select_primary(&devices, self.preferred.as_deref()).map(|gpu| gpu.id),
+        };
+        if primary != self.primary {
+            changes.push(GpuChange::PrimaryChanged { from: self.primary, to: primary });
+            self.primary = primary;
+        }
+        Ok(changes)
+    }
+
+    pub fn primary(&self) -> Option<&GpuDevice> {
+        self.primary.and_then(|id| self.devices.get(&id))
+    }
+
+    pub fn device(&self, id: dev_t) -> Option<&GpuDevice> {
+        self.devices.get(&id)
+    }
+
+    pub fn devices(&self) -> impl Iterator<Item = &GpuDevice> {
+        self.devices.values()
+    }
+
+    /// Records the DMA-BUF formats the renderer of GPU `id` supports, once it is opened.
+    pub fn set_render_formats(&mut self, id: dev_t, formats: HashSet<Format>) {
+        self.render_formats.insert(id, formats);
+    }
+
+    /// Records that `output` is a connector of GPU `device` whose primary plane supports
+    /// `scanout_formats`.
+    pub fn add_output(&mut self, output: &str, device: dev_t, scanout_formats: HashSet<Format>) {
+        self.outputs.insert(output.to_string(), OutputGpu { device, scanout_formats });
+    }
+
+    pub fn remove_output(&mut self, output: &str) {
+        self.outputs.remove(output);
+    }
+
+    /// GPU driving `output`.
+    pub fn output_device(&self, output: &str) -> Option<&GpuDevice> {
+        self.outputs.get(output).and_then(|o| self.devices.get(&o.device))
+    }
+
+    fn formats(&self, id: dev_t) -> HashSet<Format> {
+        self.render_formats.get(&id).cloned().unwrap_or_default()
+    }
+
+    /// How composited frames get to `output`.
+    pub fn copy_mode(&self, output: &str) -> Option<CopyMode> {
+        let device = self.output_device(output)?;
+        let primary = self.primary()?;
+        if device.id == primary.id {
+            return Some(CopyMode::Direct);
+        }
+        // The output's GPU must render to import, and share a format with the primary.
+        let shared = self.render_formats.get(&primary.id).zip(self.render_formats.get(&device.id));
+        match shared {
+            Some((primary_formats, formats)) if device.can_render() && !primary_formats.is_disjoint(formats) => Some(CopyMode::Dmabuf),
+            _ => Some(CopyMode::Cpu),
+        }
+    }
+
+    /// DMA-BUF feedback for surfaces on `output`, or for surfaces not on any output.
+    ///
+    /// Clients always allocate on the primary GPU, which composites their buffers. For surfaces
+    /// on an output, buffers its plane can scan out directly are preferred, marked for the GPU
+    /// driving the output.
+    pub fn feedback(&self, output: Option<&str>) -> Option<FeedbackPlan> {
+        let primary = self.primary()?;
+        let formats = self.formats(primary.id);
+        let mut preferred = Vec::new();
+        if let Some(output) = output.and_then(|name| self.outputs.get(name)) {
+            let scanout: HashSet<Format> = output.scanout_formats.intersection(&formats).copied().collect();
+            if !scanout.is_empty() {
+                let target_device = self.devices.get(&output.device).map_or(output.device, GpuDevice::feedback_device);
+                preferred.push(FeedbackTranche { target_device, scanout: true, formats: scanout });
+            }
+        }
+        Some(FeedbackPlan { main_device: primary.feedback_device(), formats, preferred })
+    }
+}
+// ANCHOR_END: GpuRegistry
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use smithay::backend::allocator::{Fourcc, Modifier};
+    use std::cell::RefCell;
+
+    /// `udevadm`-like data, editable to simulate hotplug.
+    struct FakeUdev(RefCell<Vec<UdevRecord>>);
+
+    impl UdevSource for FakeUdev {
+        fn drm_devices(&self) -> io::Result<Vec<UdevRecord>> {
+            Ok(self.0.borrow().clone())
+        }
+    }
+
+    fn node(sysname: &str, devnum: dev_t, parent: &str, driver: &str) -> UdevRecord {
+        UdevRecord {
+            sysname: sysname.into(),
+            devnode: Some(PathBuf::from("/dev/dri").join(sysname)),
+            devnum: Some(devnum),
+            parent_syspath: Some(PathBuf::from("/sys/devices/pci0000:00").join(parent)),
+            parent_sysname: Some(parent.into()),
+            driver: Some(driver.into()),
+            ..UdevRecord::default()
+        }
+    }
+
+    /// Intel iGPU (boot VGA, eDP) and NVIDIA dGPU with the external ports.
+    fn hybrid_laptop() -> Vec<UdevRecord> {
+        let mut igpu = node("card0", 0xe200, "0000:00:02.0", "i915");
+        igpu.boot_vga = true;
+        let connector = UdevRecord { devnode: None, devnum: None, ..node("card0-eDP-1", 0, "0000:00:02.0", "i915") };
+        vec![
+            igpu,
+            connector,
+            node("renderD128", 0xe280, "0000:00:02.0", "i915"),
+            node("card1", 0xe201, "0000:01:00.0", "nvidia"),
+            node("renderD129", 0xe281, "0000:01:00.0", "nvidia"),
+        ]
+    }
+
+    fn egpu() -> Vec<UdevRecord> {
+        let mut card = node("card2", 0xe202, "0000:3c:00.0", "amdgpu");
+        card.removable = true;
+        vec![card, node("renderD130", 0xe282, "0000:3c:00.0", "amdgpu")]
+    }
+
+    fn formats(list: &[(Fourcc, Modifier)]) -> HashSet<Format> {
+        list.iter().map(|&(code, modifier)| Format { code, modifier }).collect()
+    }
+
+    #[test]
+    fn test_enumerate_pairs_render_nodes_and_filters_seat() {
+        let mut records = hybrid_laptop();
+        let mut other_seat = node("card5", 0xe205, "0000:05:00.0", "amdgpu");
+        other_seat.seat = Some("seat1".into());
+        records.push(other_seat);
+        let gpus = gpus_from_udev(&records, DEFAULT_SEAT);
+        let names: Vec<&str> = gpus.iter().map(|gpu| gpu.name.as_str()).collect();
+        assert_eq!(names, ["card0", "card1"]);
+        assert_eq!(gpus[1].render_node.as_deref(), Some(Path::new("/dev/dri/renderD129")));
+        assert_eq!((gpus[1].render_id, gpus[1].bus_id.as_deref()), (Some(0xe281), Some("0000:01:00.0")));
+        assert!(gpus[0].boot_vga);
+    }
+
+    #[test]
+    fn test_primary_selection() {
+        let gpus = gpus_from_udev(&hybrid_laptop(), DEFAULT_SEAT);
+        assert_eq!(select_primary(&gpus, None).unwrap().name, "card0");
+        assert_eq!(select_primary(&gpus, Some("nvidia")).unwrap().name, "card1");
+        assert_eq!(select_primary(&gpus, Some("/dev/dri/renderD129")).unwrap().name, "card1");
+        assert_eq!(select_primary(&gpus, Some("0000:09:00.0")).unwrap().name, "card0");
+
+        // A display-only device (no render node) is never primary.
+        let mut records = hybrid_laptop();
+        records.retain(|r| r.sysname != "renderD128");
+        let gpus = gpus_from_udev(&records, DEFAULT_SEAT);
+        assert_eq!(select_primary(&gpus, Some("card0")).unwrap().name, "card1");
+    }
+
+    #[test]
+    fn test_egpu_hotplug_and_copy_paths() {
+        let udev = FakeUdev(RefCell::new(hybrid_laptop()));
+        let mut registry = GpuRegistry::new(&GpuConfig::default(), DEFAULT_SEAT);
+        let changes = registry.scan(&udev).unwrap();
+        assert!(changes.contains(&GpuChange::PrimaryChanged { from: None, to: Some(0xe200) }));
+
+        let linear = formats(&[(Fourcc::Xrgb8888, Modifier::Linear), (Fourcc::Argb8888, Modifier::Linear)]);
+        let tiled = formats(&[(Fourcc::Xrgb8888, Modifier::I915_x_tiled)]);
+        registry.set_render_formats(0xe200, linear.union(&tiled).copied().collect());
+        registry.set_render_formats(0xe201, linear.clone());
+        registry.add_output("eDP-1", 0xe200, tiled.clone());
+        registry.add_output("DP-2", 0xe201, formats(&[(Fourcc::Xrgb8888, Modifier::Linear)]));
+        assert_eq!(registry.copy_mode("eDP-1"), Some(CopyMode::Direct));
+        assert_eq!(registry.copy_mode("DP-2"), Some(CopyMode::Dmabuf));
+
+        // Feedback steers surfaces on the dGPU output to linear buffers it can scan out.
+        let plan = registry.feedback(Some("DP-2")).unwrap();
+        assert_eq!(plan.main_device, 0xe280);
+        assert_eq!(plan.preferred, vec![FeedbackTranche {
+            target_device: 0xe281,
+            scanout: true,
+            formats: formats(&[(Fourcc::Xrgb8888, Modifier::Linear)]),
+        }]);
+        assert!(registry.feedback(None).unwrap().preferred.is_empty());
+
+        // Plugging in an eGPU keeps the primary; its outputs need a CPU copy until it is opened.
+        udev.0.borrow_mut().extend(egpu());
+        assert_eq!(registry.scan(&udev).unwrap(), vec![GpuChange::Added(0xe202)]);
+        registry.add_output("DP-5", 0xe202, linear.clone());
+        assert_eq!(registry.copy_mode("DP-5"), Some(CopyMode::Cpu));
+        registry.set_render_formats(0xe202, linear);
+        assert_eq!(registry.copy_mode("DP-5"), Some(CopyMode::Dmabuf));
+
+        // Unplugging it drops its outputs.
+        udev.0.borrow_mut().retain(|r| r.parent_sysname.as_deref() != Some("0000:3c:00.0"));
+        assert_eq!(registry.scan(&udev).unwrap(), vec![GpuChange::Removed(0xe202), GpuChange::OutputsLost(vec!["DP-5".into()])]);
+        assert!(registry.output_device("DP-5").is_none());
+    }
+
+    #[test]
+    fn test_preferred_egpu_becomes_primary_when_plugged_in() {
+        let udev = FakeUdev(RefCell::new(hybrid_laptop()));
+        let config = GpuConfig { render_device: Some("amdgpu".into()) };
+        let mut registry = GpuRegistry::new(&config, DEFAULT_SEAT);
+        registry.scan(&udev).unwrap();
+        assert_eq!(registry.primary().unwrap().name, "card0");
+
+        udev.0.borrow_mut().extend(egpu());
+        let changes = registry.scan(&udev).unwrap();
+        assert!(changes.contains(&GpuChange::PrimaryChanged { from: Some(0xe200), to: Some(0xe202) }));
+
+        // When it is unplugged again, the boot GPU takes over.
+        udev.0.borrow_mut().retain(|r| r.driver.as_deref() != Some("amdgpu"));
+        let changes = registry.scan(&udev).unwrap();
+        assert!(changes.contains(&GpuChange::PrimaryChanged { from: Some(0xe202), to: Some(0xe200) }));
+    }
+}
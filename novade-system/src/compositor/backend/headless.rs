@@ -0,0 +1,253 @@
+// novade-system/src/compositor/backend/headless.rs
+
+//! Headless fallback for when no GPU renderer can be created.
+//!
+//! Windows are composited on the CPU by the [`SoftwareRenderer`] into a framebuffer in system
+//! memory, for a virtual output. Nothing reaches a screen, but clients keep running and screen
+//! recording reads the frames back. Frames are repainted in full from the clients' SHM
+//! buffers; surfaces with DMA-BUFs are left out, and windows are drawn where they are, without
+//! animations or effects.
+
+use std::time::{Duration, Instant};
+
+use smithay::backend::renderer::utils::RendererSurfaceStateUserData;
+use smithay::output::{Mode, Output, PhysicalProperties, Subpixel};
+use smithay::reexports::calloop::EventLoop;
+use smithay::reexports::wayland_server::protocol::{wl_shm, wl_surface::WlSurface};
+use smithay::reexports::wayland_server::{Display, Resource};
+use smithay::utils::{Logical, Point, Transform};
+use smithay::wayland::compositor::{with_surface_tree_downward, TraversalAction};
+use smithay::wayland::shm::with_buffer_contents;
+use tracing::{debug, error, info, warn};
+
+use novade_core::types::geometry::Rect as NovaRect;
+
+use crate::compositor::errors::CompositorError;
+use crate::compositor::ipc::protocol::{IpcEvent, OutputChange};
+use crate::compositor::render::MainNovaRenderer;
+use crate::compositor::renderer_interface::abstraction::{
+    BufferContent, BufferFormat, ClientBuffer, FrameRenderer, RenderElement, RendererError, TextureRenderParams,
+};
+use crate::compositor::scene_graph::Transform as SceneGraphTransform;
+use crate::compositor::state::DesktopState;
+use crate::renderer::SoftwareRenderer;
+
+pub const HEADLESS_OUTPUT_NAME: &str = "HEADLESS-1";
+const HEADLESS_OUTPUT_SIZE: (i32, i32) = (1920, 1080);
+const HEADLESS_REFRESH_MHZ: i32 = 60_000;
+/// Longest the loop sleeps before dispatching Wayland clients again.
+const CLIENT_POLL_INTERVAL: Duration = Duration::from_millis(4);
+const CLEAR_COLOR: [f32; 4] = [0.1, 0.1, 0.3, 1.0];
+
+/// Creates the virtual output and the software renderer, then runs the compositor until it is
+/// asked to stop.
+pub fn run_headless(
+    event_loop: &mut EventLoop<'static, DesktopState>,
+    display: &mut Display<DesktopState>,
+    desktop_state: &mut DesktopState,
+) -> Result<(), CompositorError> {
+    let output = create_headless_output(display, desktop_state);
+    let size = output.current_mode().map_or(HEADLESS_OUTPUT_SIZE.into(), |mode| mode.size);
+    let mut renderer = SoftwareRenderer::new(size);
+    renderer.set_clear_color(CLEAR_COLOR);
+    desktop_state.main_renderer = Some(MainNovaRenderer::Software(Box::new(renderer)));
+
+    if let Err(e) = crate::compositor::xwayland::spawn_xwayland_if_enabled(desktop_state, &event_loop.handle(), &display.handle()) {
+        error!("Failed to spawn XWayland: {}", e);
+    }
+    info!("NovaDE Compositor running headless on the software renderer.");
+
+    let mut frame_seq: u64 = 0;
+    desktop_state.frame_scheduler(&output).request_redraw();
+    while *desktop_state.running.read().unwrap() {
+        let wake_in = {
+            let scheduler = desktop_state.frame_scheduler(&output);
+            let now = scheduler.now();
+            [scheduler.time_until_render(), scheduler.presentation_target().map(|target| target.saturating_sub(now))]
+                .into_iter()
+                .flatten()
+                .min()
+                .map_or(CLIENT_POLL_INTERVAL, |wake_in| wake_in.min(CLIENT_POLL_INTERVAL))
+        };
+        event_loop.dispatch(Some(wake_in), desktop_state)?;
+        if let Err(e) = display.dispatch_clients(desktop_state) {
+            warn!("Error dispatching Wayland client events: {}", e);
+        }
+
+        if desktop_state.advance_animations(Instant::now()) {
+            desktop_state.frame_scheduler(&output).request_redraw();
+        }
+        // There is no display to report a flip: the frame is presented at the vblank it was
+        // aimed at.
+        let scheduler = desktop_state.frame_scheduler(&output);
+        if scheduler.presentation_target().is_some_and(|target| target <= scheduler.now()) {
+            desktop_state.frame_presented(&output, None, false);
+        }
+        if desktop_state.frame_scheduler(&output).should_render() {
+            let render_started = desktop_state.frame_scheduler(&output).now();
+            match render_headless_frame(desktop_state, &output) {
+                Ok(rendered) => {
+                    frame_seq += 1;
+                    desktop_state.frame_submitted(&output, render_started, frame_seq, rendered);
+                }
+                Err(e) => {
+                    error!("Software rendering failed: {}", e);
+                    desktop_state.frame_scheduler(&output).frame_skipped();
+                }
+            }
+        }
+
+        if let Err(e) = display.flush_clients() {
+            warn!("Error flushing Wayland clients: {}", e);
+        }
+    }
+    info!("NovaDE Compositor headless loop finished.");
+    Ok(())
+}
+
+fn create_headless_output(display: &Display<DesktopState>, desktop_state: &mut DesktopState) -> Output {
+    let physical_properties = PhysicalProperties {
+        size: (0, 0).into(),
+        subpixel: Subpixel::Unknown,
+        make: "NovaDE".into(),
+        model: "Headless".into(),
+    };
+    let output = Output::new(HEADLESS_OUTPUT_NAME.to_string(), physical_properties, desktop_state.clock.id(), display.handle());
+    let mode = Mode { size: HEADLESS_OUTPUT_SIZE.into(), refresh: HEADLESS_REFRESH_MHZ };
+    output.change_current_state(Some(mode), Some(Transform::Normal), Some(smithay::output::Scale::Integer(1)), Some((0, 0).into()));
+    output.set_preferred(mode);
+
+    desktop_state.output_manager_state.add_output(&output);
+    desktop_state.space.lock().unwrap().map_output(&output, (0, 0).into(), mode);
+    desktop_state.ensure_workspaces_for_output(&output.name());
+    desktop_state.emit_ipc_event(IpcEvent::Output { change: OutputChange::Added, name: output.name() });
+    output
+}
+
+/// Renders the windows shown on `output` and returns their surfaces, which get frame callbacks
+/// once the frame is presented.
+fn render_headless_frame(desktop_state: &mut DesktopState, output: &Output) -> Result<Vec<WlSurface>, RendererError> {
+    let recording_region = desktop_state.recording_region(output);
+    let Some(MainNovaRenderer::Software(renderer)) = desktop_state.main_renderer.as_mut() else {
+        return Err(RendererError::Generic("the software renderer is not active".to_string()));
+    };
+    let space = desktop_state.space.lock().unwrap();
+    let output_scale = output.current_scale().fractional_scale();
+    let output_geometry = space.output_geometry(output).unwrap_or_default().to_physical_precise_round(output_scale);
+
+    let mut elements = Vec::new();
+    let mut rendered = Vec::new();
+    // The renderer paints in order, so bottom to top.
+    let windows = crate::compositor::spatial_index::visible_windows(&space, output, |_| false, |_| 0.0);
+    for window in windows.into_iter().rev() {
+        let (Some(surface), Some(location)) = (window.wl_surface_ref(), space.element_location(window)) else {
+            continue;
+        };
+        surface_tree_elements(renderer, surface, location - window.geometry().loc, &mut elements);
+        rendered.push(surface.clone());
+    }
+    drop(space);
+
+    renderer.set_damage_region(None);
+    renderer.render_frame(elements, output_geometry, output_scale)?;
+    renderer.submit_and_present_frame()?;
+
+    let recording_frame = recording_region.map(|region| renderer.read_pixels(region).map(|pixels| (region, pixels)));
+    match recording_frame {
+        Some(Ok((region, pixels))) => desktop_state.submit_recording_frame(output, region, pixels, false),
+        Some(Err(e)) => desktop_state.abort_recording(format!("cannot read back frames: {}", e)),
+        None => {}
+    }
+    Ok(rendered)
+}
+
+/// Adds texture elements for `surface` and its subsurfaces, bottom to top, with the surface at
+/// `location` in global logical coordinates. Textures are uploaded from the surfaces' current
+/// SHM buffers, keyed by the `wl_surface`.
+fn surface_tree_elements(renderer: &mut SoftwareRenderer, surface: &WlSurface, location: Point<i32, Logical>, elements: &mut Vec<RenderElement<'static>>) {
+    with_surface_tree_downward(
+        surface,
+        location,
+        |_, states, location| {
+            let data = states.data_map.get::<RendererSurfaceStateUserData>();
+            match data.and_then(|data| data.lock().unwrap().view()) {
+                Some(view) => TraversalAction::DoChildren(*location + view.offset),
+                None => TraversalAction::SkipChildren,
+            }
+        },
+        |surface, states, location| {
+            let Some(data) = states.data_map.get::<RendererSurfaceStateUserData>() else {
+                return;
+            };
+            let data = data.lock().unwrap();
+            let (Some(view), Some(buffer), Some(surface_size)) = (data.view(), data.buffer(), data.surface_size()) else {
+                return;
+            };
+            let location = *location + view.offset;
+            let uploaded = with_buffer_contents(buffer, |ptr, len, attributes| {
+                let format = match attributes.format {
+                    wl_shm::Format::Argb8888 => BufferFormat::Argb8888,
+                    wl_shm::Format::Xrgb8888 => BufferFormat::Xrgb8888,
+                    other => return Err(RendererError::InvalidBufferType(format!("SHM format {:?}", other))),
+                };
+                // SAFETY: smithay keeps the pool mapped and `len` bytes valid for the closure.
+                let bytes = unsafe { std::slice::from_raw_parts(ptr, len) };
+                let data = bytes
+                    .get(attributes.offset.max(0) as usize..)
+                    .ok_or_else(|| RendererError::TextureUploadFailed("SHM buffer offset out of range".to_string()))?;
+                let content = BufferContent::Shm {
+                    id: buffer.id().protocol_id() as u64,
+                    data,
+                    width: attributes.width as u32,
+                    height: attributes.height as u32,
+                    stride: attributes.stride as u32,
+                    format,
+                };
+                renderer.upload_surface_texture(surface.id(), &ClientBuffer { content })
+            });
+            let texture = match uploaded {
+                Ok(Ok(texture)) => texture,
+                Ok(Err(e)) => {
+                    debug!("Skipping surface {:?} in the software renderer: {}", surface.id(), e);
+                    return;
+                }
+                Err(_) => {
+                    debug!("Skipping surface {:?} in the software renderer: not an SHM buffer", surface.id());
+                    return;
+                }
+            };
+            if surface_size.w <= 0 || surface_size.h <= 0 || view.src.size.w <= 0.0 || view.src.size.h <= 0.0 {
+                return;
+            }
+
+            // The viewport's source, normalised to the surface, is drawn stretched to its
+            // destination size.
+            let source = NovaRect::from_coords(
+                (view.src.loc.x / surface_size.w as f64) as f32,
+                (view.src.loc.y / surface_size.h as f64) as f32,
+                (view.src.size.w / surface_size.w as f64) as f32,
+                (view.src.size.h / surface_size.h as f64) as f32,
+            );
+            let texels = (source.size.width * texture.width_px() as f32, source.size.height * texture.height_px() as f32);
+            let transform = SceneGraphTransform {
+                matrix: [
+                    [view.dst.w as f32 / texels.0, 0.0, location.x as f32],
+                    [0.0, view.dst.h as f32 / texels.1, location.y as f32],
+                ],
+            };
+            let clip_rect = NovaRect::from_coords(location.x as f32, location.y as f32, view.dst.w as f32, view.dst.h as f32);
+            elements.push(RenderElement::TextureNode(TextureRenderParams {
+                texture,
+                transform,
+                alpha: 1.0,
+                clip_rect,
+                source_rect: source,
+                visible_region: Vec::new(),
+                corner_radius: 0.0,
+                color_transform: None,
+            }));
+        },
+        |_, _, _| true,
+    );
+}
+
@@ -9,13 +9,17 @@ use crate::compositor::state::DesktopState; // Assuming DesktopState is here
 // Forward declare winit_backend and drm_backend modules
 pub mod winit_backend;
 pub mod drm_backend;
+pub mod headless;
+pub mod planes;
+pub mod gpu;
 
 /// Enum to select the active backend for the compositor.
 #[derive(Debug, Clone, Copy)]
 pub enum BackendType {
     Winit,
     Drm,
-    // Headless, // Potentially for testing
+    /// Software rendering without a screen, used when no GPU renderer can be created.
+    Headless,
 }
 
 /// Trait defining the capabilities of a compositor backend.
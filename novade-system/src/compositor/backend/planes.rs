@@ -0,0 +1,618 @@
+// novade-system/src/compositor/backend/planes.rs
+
+//! Direct scanout and hardware plane assignment.
+//!
+//! Before composing a frame, the visible elements are offered to the [`PlaneAssigner`]. A
+//! fullscreen client's DMA-BUF can replace the composited frame on the primary plane, video
+//! surfaces can go on overlay planes and the cursor on the cursor plane. Every assignment is
+//! checked with a test-only commit through [`KmsDevice`], and any element that cannot be
+//! scanned out is composited as usual. The reason is kept per element so it can be inspected
+//! when scanout unexpectedly stops working.
+//!
+//! Nothing assigns planes yet: the DRM backend does not drive CRTCs, so it has no
+//! [`KmsDevice`] and never calls
+//! [`DrmBackend::assign_planes`](super::drm_backend::DrmBackend::assign_planes). Only the fake
+//! device in the tests exercises this module.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::hash::Hash;
+
+use smithay::backend::allocator::Format;
+use smithay::utils::{Buffer, Physical, Rectangle, Size, Transform};
+
+use crate::compositor::config::PerformanceConfig;
+
+/// Kind of a KMS plane.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PlaneType {
+    Primary,
+    Overlay,
+    Cursor,
+}
+
+/// Capabilities of one KMS plane on the CRTC driving an output.
+#[derive(Debug, Clone)]
+pub struct PlaneInfo {
+    pub id: u32,
+    pub plane_type: PlaneType,
+    /// Supported format/modifier pairs (`IN_FORMATS`).
+    pub formats: HashSet<Format>,
+    /// Stacking position; higher is on top.
+    pub zpos: u32,
+    /// Whether the plane can scale between source and destination size.
+    pub supports_scaling: bool,
+    /// Largest buffer the plane accepts, typically only set for cursor planes.
+    pub max_size: Option<Size<i32, Physical>>,
+}
+
+/// A plane configuration to validate: the buffer of `format` and `src_size` shown at `dst`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlaneState {
+    pub plane_id: u32,
+    pub format: Format,
+    pub src_size: Size<i32, Buffer>,
+    pub dst: Rectangle<i32, Physical>,
+}
+
+/// The parts of a KMS device the plane assignment needs, meant to be implemented with atomic
+/// test-only commits once the DRM backend drives CRTCs. Only the tests' fake device implements
+/// it so far.
+pub trait KmsDevice {
+    /// Planes usable on the output's CRTC.
+    fn planes(&self) -> &[PlaneInfo];
+
+    /// Checks whether the hardware accepts `planes` on top of the primary plane, which shows
+    /// either `primary` or, when `None`, the composited frame. Nothing is shown on screen.
+    fn test_commit(&mut self, primary: Option<&PlaneState>, planes: &[PlaneState]) -> Result<(), String>;
+}
+
+/// What an element is, which decides the plane it may use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ElementRole {
+    /// A fullscreen client window; candidate for the primary plane.
+    Fullscreen,
+    /// A video surface; candidate for an overlay plane.
+    Video,
+    /// The pointer; candidate for the cursor plane.
+    Cursor,
+    /// Anything else, always composited.
+    Other,
+}
+
+/// One visible element of a frame as seen by the plane assignment.
+#[derive(Debug, Clone)]
+pub struct ScanoutCandidate<K> {
+    pub key: K,
+    pub role: ElementRole,
+    /// Format of the client's DMA-BUF, `None` for SHM and other buffers that cannot be scanned out.
+    pub dmabuf_format: Option<Format>,
+    pub src_size: Size<i32, Buffer>,
+    /// Where the element is shown, in output coordinates.
+    pub dst: Rectangle<i32, Physical>,
+    pub transform: Transform,
+    /// Whether the buffer has no translucent pixels.
+    pub opaque: bool,
+}
+
+/// Why an element was composited instead of being scanned out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScanoutRejection {
+    /// Direct scanout or overlay planes are turned off in the configuration.
+    Disabled,
+    /// The element does not use a DMA-BUF.
+    NotDmabuf,
+    /// No suitable plane supports the buffer's format and modifier.
+    UnsupportedFormat(Format),
+    /// The buffer would have to be rotated or flipped.
+    Transformed(Transform),
+    /// A fullscreen buffer with translucent pixels would show nothing behind it.
+    NotOpaque,
+    /// The buffer does not exactly cover the output.
+    GeometryMismatch,
+    /// The buffer needs scaling and the plane cannot scale.
+    ScalingUnsupported,
+    /// The buffer is larger than the plane allows.
+    TooLarge,
+    /// A composited element above overlaps it, so a plane below the composition cannot be used.
+    CoveredByComposited,
+    /// All suitable planes are already in use.
+    NoFreePlane,
+    /// The driver rejected the configuration in a test commit.
+    TestCommitFailed(String),
+}
+
+impl fmt::Display for ScanoutRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScanoutRejection::Disabled => write!(f, "disabled in configuration"),
+            ScanoutRejection::NotDmabuf => write!(f, "buffer is not a DMA-BUF"),
+            ScanoutRejection::UnsupportedFormat(format) => {
+                write!(f, "format {:?} with modifier {:?} not supported by plane", format.code, format.modifier)
+            }
+            ScanoutRejection::Transformed(transform) => write!(f, "buffer transform {:?} required", transform),
+            ScanoutRejection::NotOpaque => write!(f, "buffer is not opaque"),
+            ScanoutRejection::GeometryMismatch => write!(f, "buffer does not match output geometry"),
+            ScanoutRejection::ScalingUnsupported => write!(f, "plane cannot scale the buffer"),
+            ScanoutRejection::TooLarge => write!(f, "buffer too large for plane"),
+            ScanoutRejection::CoveredByComposited => write!(f, "covered by a composited element"),
+            ScanoutRejection::NoFreePlane => write!(f, "no free plane"),
+            ScanoutRejection::TestCommitFailed(reason) => write!(f, "test commit failed: {}", reason),
+        }
+    }
+}
+
+/// Result of assigning one frame's elements to planes.
+#[derive(Debug, Clone)]
+pub struct PlaneAssignment<K> {
+    /// Element shown directly on the primary plane. When set, nothing is composited.
+    pub primary: Option<(K, PlaneState)>,
+    /// Elements on overlay and cursor planes.
+    pub planes: Vec<(K, PlaneState)>,
+    /// Elements left to the renderer, bottom to top.
+    pub composited: Vec<K>,
+    /// Candidates for a plane that were composited instead, with the reason.
+    pub rejected: Vec<(K, ScanoutRejection)>,
+}
+
+impl<K> PlaneAssignment<K> {
+    /// Whether the renderer has to produce a frame for the primary plane.
+    pub fn needs_composition(&self) -> bool {
+        self.primary.is_none()
+    }
+}
+
+// ANCHOR: PlaneAssigner
+/// Decides per frame which elements are scanned out on which plane.
+#[derive(Debug)]
+pub struct PlaneAssigner<K> {
+    direct_scanout: bool,
+    overlay_planes: bool,
+    /// Reason the last frame composited an element that asked for a plane.
+    rejections: HashMap<K, ScanoutRejection>,
+}
+
+impl<K: Clone + Eq + Hash + fmt::Debug> PlaneAssigner<K> {
+    pub fn new(config: &PerformanceConfig) -> Self {
+        Self { direct_scanout: config.direct_scanout, overlay_planes: config.overlay_planes, rejections: HashMap::new() }
+    }
+
+    /// Applies a changed configuration; takes effect on the next frame.
+    pub fn configure(&mut self, config: &PerformanceConfig) {
+        self.direct_scanout = config.direct_scanout;
+        self.overlay_planes = config.overlay_planes;
+    }
+
+    /// Why `key` was composited in the last frame, if it wanted a plane.
+    pub fn rejection(&self, key: &K) -> Option<&ScanoutRejection> {
+        self.rejections.get(key)
+    }
+
+    /// Assigns `candidates`, the visible elements of `output` ordered bottom to top.
+    ///
+    /// Elements are visited top-down so that an element only gets a plane when nothing
+    /// composited is stacked over it. Every accepted plane is validated with a test commit
+    /// together with the planes accepted before it.
+    pub fn assign(
+        &mut self,
+        device: &mut dyn KmsDevice,
+        output: Rectangle<i32, Physical>,
+        candidates: &[ScanoutCandidate<K>],
+    ) -> PlaneAssignment<K> {
+        let mut free: Vec<PlaneInfo> = device.planes().to_vec();
+        free.sort_by(|a, b| b.zpos.cmp(&a.zpos));
+
+        let mut primary = None;
+        let mut planes: Vec<(K, PlaneState)> = Vec::new();
+        let mut composited: Vec<K> = Vec::new();
+        let mut composited_above: Vec<Rectangle<i32, Physical>> = Vec::new();
+        let mut rejected: Vec<(K, ScanoutRejection)> = Vec::new();
+        // Overlays must keep the stacking order of their elements.
+        let mut zpos_limit = u32::MAX;
+
+        for candidate in candidates.iter().rev() {
+            let result = match candidate.role {
+                ElementRole::Other => None,
+                ElementRole::Fullscreen => {
+                    Some(self.try_primary(device, output, candidate, &free, &composited_above, &planes).map(|state| {
+                        primary = Some((candidate.key.clone(), state));
+                    }))
+                }
+                ElementRole::Video | ElementRole::Cursor => {
+                    let plane_type = if candidate.role == ElementRole::Cursor { PlaneType::Cursor } else { PlaneType::Overlay };
+                    Some(
+                        self.try_plane(device, plane_type, candidate, &free, zpos_limit, &composited_above, &planes)
+                            .map(|(index, state)| {
+                                let plane = free.remove(index);
+                                if plane.plane_type == PlaneType::Overlay {
+                                    zpos_limit = plane.zpos;
+                                }
+                                planes.push((candidate.key.clone(), state));
+                            }),
+                    )
+                }
+            };
+            match result {
+                Some(Ok(())) => {
+                    if primary.is_some() {
+                        // An opaque fullscreen buffer hides everything below it.
+                        break;
+                    }
+                }
+                Some(Err(reason)) => {
+                    rejected.push((candidate.key.clone(), reason));
+                    composited.push(candidate.key.clone());
+                    composited_above.push(candidate.dst);
+                }
+                None => {
+                    composited.push(candidate.key.clone());
+                    composited_above.push(candidate.dst);
+                }
+            }
+        }
+        composited.reverse();
+        rejected.reverse();
+
+        self.record_rejections(&rejected);
+        PlaneAssignment { primary, planes, composited, rejected }
+    }
+
+    fn try_primary(
+        &self,
+        device: &mut dyn KmsDevice,
+        output: Rectangle<i32, Physical>,
+        candidate: &ScanoutCandidate<K>,
+        free: &[PlaneInfo],
+        composited_above: &[Rectangle<i32, Physical>],
+        planes: &[(K, PlaneState)],
+    ) -> Result<PlaneState, ScanoutRejection> {
+        if !self.direct_scanout {
+            return Err(ScanoutRejection::Disabled);
+        }
+        let format = candidate.dmabuf_format.ok_or(ScanoutRejection::NotDmabuf)?;
+        if !composited_above.is_empty() {
+            return Err(ScanoutRejection::CoveredByComposited);
+        }
+        if candidate.transform != Transform::Normal {
+            return Err(ScanoutRejection::Transformed(candidate.transform));
+        }
+        if !candidate.opaque {
+            return Err(ScanoutRejection::NotOpaque);
+        }
+        if candidate.dst != output || !same_size(candidate.src_size, candidate.dst.size) {
+            return Err(ScanoutRejection::GeometryMismatch);
+        }
+        let plane = free
+            .iter()
+            .find(|p| p.plane_type == PlaneType::Primary)
+            .ok_or(ScanoutRejection::NoFreePlane)?;
+        if !plane.formats.contains(&format) {
+            return Err(ScanoutRejection::UnsupportedFormat(format));
+        }
+        let state = PlaneState { plane_id: plane.id, format, src_size: candidate.src_size, dst: candidate.dst };
+        let others: Vec<PlaneState> = planes.iter().map(|(_, s)| s.clone()).collect();
+        device.test_commit(Some(&state), &others).map_err(ScanoutRejection::TestCommitFailed)?;
+        Ok(state)
+    }
+
+    /// Finds a free plane of `plane_type` for `candidate` and returns its index in `free`.
+    #[allow(clippy::too_many_arguments)]
+    fn try_plane(
+        &self,
+        device: &mut dyn KmsDevice,
+        plane_type: PlaneType,
+        candidate: &ScanoutCandidate<K>,
+        free: &[PlaneInfo],
+        zpos_limit: u32,
+        composited_above: &[Rectangle<i32, Physical>],
+        planes: &[(K, PlaneState)],
+    ) -> Result<(usize, PlaneState), ScanoutRejection> {
+        if !self.overlay_planes {
+            return Err(ScanoutRejection::Disabled);
+        }
+        let format = candidate.dmabuf_format.ok_or(ScanoutRejection::NotDmabuf)?;
+        if composited_above.iter().any(|above| above.overlaps(candidate.dst)) {
+            return Err(ScanoutRejection::CoveredByComposited);
+        }
+        if candidate.transform != Transform::Normal {
+            return Err(ScanoutRejection::Transformed(candidate.transform));
+        }
+
+        let mut others: Vec<PlaneState> = planes.iter().map(|(_, s)| s.clone()).collect();
+        // Most specific reason across the planes that were considered.
+        let mut reason = ScanoutRejection::NoFreePlane;
+        for (index, plane) in free.iter().enumerate() {
+            if plane.plane_type != plane_type || (plane_type == PlaneType::Overlay && plane.zpos >= zpos_limit) {
+                continue;
+            }
+            if !plane.formats.contains(&format) {
+                reason = ScanoutRejection::UnsupportedFormat(format);
+                continue;
+            }
+            if let Some(max) = plane.max_size {
+                if candidate.src_size.w > max.w || candidate.src_size.h > max.h {
+                    reason = ScanoutRejection::TooLarge;
+                    continue;
+                }
+            }
+            if !plane.supports_scaling && !same_size(candidate.src_size, candidate.dst.size) {
+                reason = ScanoutRejection::ScalingUnsupported;
+                continue;
+            }
+            let state = PlaneState { plane_id: plane.id, format, src_size: candidate.src_size, dst: candidate.dst };
+            others.push(state.clone());
+            match device.test_commit(None, &others) {
+                Ok(()) => return Ok((index, state)),
+                Err(e) => {
+                    others.pop();
+                    reason = ScanoutRejection::TestCommitFailed(e);
+                }
+            }
+        }
+        Err(reason)
+    }
+
+    fn record_rejections(&mut self, rejected: &[(K, ScanoutRejection)]) {
+        let mut current = HashMap::with_capacity(rejected.len());
+        for (key, reason) in rejected {
+            // Only log changes, the same reason usually repeats every frame.
+            if self.rejections.get(key) != Some(reason) {
+                tracing::debug!("Scanout of {:?} rejected: {}", key, reason);
+            }
+            current.insert(key.clone(), reason.clone());
+        }
+        self.rejections = current;
+    }
+}
+// ANCHOR_END: PlaneAssigner
+
+fn same_size(src: Size<i32, Buffer>, dst: Size<i32, Physical>) -> bool {
+    src.w == dst.w && src.h == dst.h
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use smithay::backend::allocator::{Fourcc, Modifier};
+    use smithay::utils::Point;
+
+    const PRIMARY: u32 = 31;
+    const OVERLAY_HIGH: u32 = 41;
+    const OVERLAY_LOW: u32 = 40;
+    const CURSOR: u32 = 50;
+
+    fn xrgb_linear() -> Format {
+        Format { code: Fourcc::Xrgb8888, modifier: Modifier::Linear }
+    }
+
+    fn nv12_linear() -> Format {
+        Format { code: Fourcc::Nv12, modifier: Modifier::Linear }
+    }
+
+    fn argb_linear() -> Format {
+        Format { code: Fourcc::Argb8888, modifier: Modifier::Linear }
+    }
+
+    fn rect(x: i32, y: i32, w: i32, h: i32) -> Rectangle<i32, Physical> {
+        Rectangle::from_loc_and_size(Point::from((x, y)), Size::from((w, h)))
+    }
+
+    fn output() -> Rectangle<i32, Physical> {
+        rect(0, 0, 1920, 1080)
+    }
+
+    /// Fake KMS device with one primary, two overlay and one cursor plane. Test commits fail
+    /// for the planes in `rejected_planes` and when more than `max_active_planes` are used.
+    struct FakeKms {
+        planes: Vec<PlaneInfo>,
+        rejected_planes: HashSet<u32>,
+        max_active_planes: usize,
+        test_commits: usize,
+    }
+
+    impl FakeKms {
+        fn new() -> Self {
+            let plane = |id, plane_type, formats: &[Format], zpos, supports_scaling, max_size| PlaneInfo {
+                id,
+                plane_type,
+                formats: formats.iter().copied().collect(),
+                zpos,
+                supports_scaling,
+                max_size,
+            };
+            Self {
+                planes: vec![
+                    plane(PRIMARY, PlaneType::Primary, &[xrgb_linear(), argb_linear()], 0, false, None),
+                    plane(OVERLAY_LOW, PlaneType::Overlay, &[nv12_linear(), xrgb_linear()], 1, true, None),
+                    plane(OVERLAY_HIGH, PlaneType::Overlay, &[nv12_linear()], 2, true, None),
+                    plane(CURSOR, PlaneType::Cursor, &[argb_linear()], 3, false, Some(Size::from((64, 64)))),
+                ],
+                rejected_planes: HashSet::new(),
+                max_active_planes: 4,
+                test_commits: 0,
+            }
+        }
+    }
+
+    impl KmsDevice for FakeKms {
+        fn planes(&self) -> &[PlaneInfo] {
+            &self.planes
+        }
+
+        fn test_commit(&mut self, primary: Option<&PlaneState>, planes: &[PlaneState]) -> Result<(), String> {
+            self.test_commits += 1;
+            let all: Vec<&PlaneState> = primary.into_iter().chain(planes).collect();
+            if all.len() + usize::from(primary.is_none()) > self.max_active_planes {
+                return Err("bandwidth exceeded".into());
+            }
+            match all.iter().find(|s| self.rejected_planes.contains(&s.plane_id)) {
+                Some(state) => Err(format!("plane {} rejected", state.plane_id)),
+                None => Ok(()),
+            }
+        }
+    }
+
+    fn candidate(key: u32, role: ElementRole, format: Option<Format>, dst: Rectangle<i32, Physical>) -> ScanoutCandidate<u32> {
+        ScanoutCandidate {
+            key,
+            role,
+            dmabuf_format: format,
+            src_size: Size::from((dst.size.w, dst.size.h)),
+            dst,
+            transform: Transform::Normal,
+            opaque: true,
+        }
+    }
+
+    fn assigner() -> PlaneAssigner<u32> {
+        PlaneAssigner::new(&PerformanceConfig::default())
+    }
+
+    #[test]
+    fn test_fullscreen_dmabuf_is_scanned_out() {
+        let mut kms = FakeKms::new();
+        let mut assigner = assigner();
+        let candidates = [
+            candidate(1, ElementRole::Other, None, rect(100, 100, 400, 300)),
+            candidate(2, ElementRole::Fullscreen, Some(xrgb_linear()), output()),
+        ];
+        let assignment = assigner.assign(&mut kms, output(), &candidates);
+        let (key, state) = assignment.primary.clone().expect("direct scanout");
+        assert_eq!(key, 2);
+        assert_eq!(state.plane_id, PRIMARY);
+        assert!(!assignment.needs_composition());
+        // The window below is hidden and not composited.
+        assert!(assignment.composited.is_empty());
+        assert!(assigner.rejection(&2).is_none());
+    }
+
+    #[test]
+    fn test_fullscreen_rejections_fall_back_to_composition() {
+        let cases = [
+            (candidate(1, ElementRole::Fullscreen, None, output()), ScanoutRejection::NotDmabuf),
+            (
+                candidate(1, ElementRole::Fullscreen, Some(nv12_linear()), output()),
+                ScanoutRejection::UnsupportedFormat(nv12_linear()),
+            ),
+            (candidate(1, ElementRole::Fullscreen, Some(xrgb_linear()), rect(0, 0, 1280, 720)), ScanoutRejection::GeometryMismatch),
+            (
+                ScanoutCandidate { transform: Transform::_90, ..candidate(1, ElementRole::Fullscreen, Some(xrgb_linear()), output()) },
+                ScanoutRejection::Transformed(Transform::_90),
+            ),
+            (
+                ScanoutCandidate { opaque: false, ..candidate(1, ElementRole::Fullscreen, Some(argb_linear()), output()) },
+                ScanoutRejection::NotOpaque,
+            ),
+        ];
+        for (fullscreen, reason) in cases {
+            let mut kms = FakeKms::new();
+            let mut assigner = assigner();
+            let assignment = assigner.assign(&mut kms, output(), &[fullscreen]);
+            assert!(assignment.needs_composition());
+            assert_eq!(assignment.composited, vec![1]);
+            assert_eq!(assigner.rejection(&1), Some(&reason));
+        }
+    }
+
+    #[test]
+    fn test_fullscreen_below_composited_popup_is_composited() {
+        let mut kms = FakeKms::new();
+        let mut assigner = assigner();
+        let candidates = [
+            candidate(1, ElementRole::Fullscreen, Some(xrgb_linear()), output()),
+            candidate(2, ElementRole::Other, None, rect(800, 400, 300, 200)),
+        ];
+        let assignment = assigner.assign(&mut kms, output(), &candidates);
+        assert!(assignment.primary.is_none());
+        assert_eq!(assignment.composited, vec![1, 2]);
+        assert_eq!(assigner.rejection(&1), Some(&ScanoutRejection::CoveredByComposited));
+    }
+
+    #[test]
+    fn test_video_and_cursor_use_planes() {
+        let mut kms = FakeKms::new();
+        let mut assigner = assigner();
+        let candidates = [
+            candidate(1, ElementRole::Other, None, output()),
+            ScanoutCandidate { src_size: Size::from((1280, 720)), ..candidate(2, ElementRole::Video, Some(nv12_linear()), rect(100, 100, 960, 540)) },
+            candidate(3, ElementRole::Other, None, rect(1200, 800, 300, 200)),
+            candidate(4, ElementRole::Cursor, Some(argb_linear()), rect(500, 500, 64, 64)),
+        ];
+        let assignment = assigner.assign(&mut kms, output(), &candidates);
+        assert!(assignment.needs_composition());
+        assert_eq!(assignment.composited, vec![1, 3]);
+        let planes: Vec<(u32, u32)> = assignment.planes.iter().map(|(k, s)| (*k, s.plane_id)).collect();
+        assert_eq!(planes, vec![(4, CURSOR), (2, OVERLAY_HIGH)]);
+        assert!(assignment.rejected.is_empty());
+    }
+
+    #[test]
+    fn test_video_covered_by_composited_element() {
+        let mut kms = FakeKms::new();
+        let mut assigner = assigner();
+        let candidates = [
+            candidate(1, ElementRole::Video, Some(nv12_linear()), rect(100, 100, 960, 540)),
+            candidate(2, ElementRole::Other, None, rect(900, 500, 300, 200)),
+        ];
+        let assignment = assigner.assign(&mut kms, output(), &candidates);
+        assert!(assignment.planes.is_empty());
+        assert_eq!(assigner.rejection(&1), Some(&ScanoutRejection::CoveredByComposited));
+    }
+
+    #[test]
+    fn test_overlays_keep_stacking_order() {
+        let mut kms = FakeKms::new();
+        let mut assigner = assigner();
+        let candidates = [
+            candidate(1, ElementRole::Video, Some(nv12_linear()), rect(0, 0, 640, 360)),
+            candidate(2, ElementRole::Video, Some(nv12_linear()), rect(700, 0, 640, 360)),
+            candidate(3, ElementRole::Video, Some(nv12_linear()), rect(0, 400, 640, 360)),
+        ];
+        let assignment = assigner.assign(&mut kms, output(), &candidates);
+        let planes: Vec<(u32, u32)> = assignment.planes.iter().map(|(k, s)| (*k, s.plane_id)).collect();
+        assert_eq!(planes, vec![(3, OVERLAY_HIGH), (2, OVERLAY_LOW)]);
+        assert_eq!(assignment.composited, vec![1]);
+        assert_eq!(assigner.rejection(&1), Some(&ScanoutRejection::NoFreePlane));
+    }
+
+    #[test]
+    fn test_cursor_too_large_and_test_commit_failure() {
+        let mut kms = FakeKms::new();
+        kms.rejected_planes.insert(OVERLAY_HIGH);
+        kms.rejected_planes.insert(OVERLAY_LOW);
+        let mut assigner = assigner();
+        let candidates = [
+            candidate(1, ElementRole::Video, Some(nv12_linear()), rect(100, 100, 960, 540)),
+            candidate(2, ElementRole::Cursor, Some(argb_linear()), rect(1500, 900, 128, 128)),
+        ];
+        let assignment = assigner.assign(&mut kms, output(), &candidates);
+        assert!(assignment.planes.is_empty());
+        assert_eq!(assignment.composited, vec![1, 2]);
+        assert_eq!(assigner.rejection(&2), Some(&ScanoutRejection::TooLarge));
+        assert!(matches!(assigner.rejection(&1), Some(ScanoutRejection::TestCommitFailed(_))));
+        assert_eq!(kms.test_commits, 2);
+
+        // Once the element scans out again the old reason is forgotten.
+        kms.rejected_planes.clear();
+        assigner.assign(&mut kms, output(), &candidates[..1]);
+        assert!(assigner.rejection(&1).is_none());
+    }
+
+    #[test]
+    fn test_disabled_by_config() {
+        let mut kms = FakeKms::new();
+        let config = PerformanceConfig { direct_scanout: false, overlay_planes: false, ..Default::default() };
+        let mut assigner = PlaneAssigner::new(&config);
+        let candidates = [
+            candidate(1, ElementRole::Fullscreen, Some(xrgb_linear()), output()),
+            candidate(2, ElementRole::Video, Some(nv12_linear()), rect(100, 100, 640, 360)),
+        ];
+        let assignment = assigner.assign(&mut kms, output(), &candidates);
+        assert!(assignment.primary.is_none() && assignment.planes.is_empty());
+        assert_eq!(assigner.rejection(&1), Some(&ScanoutRejection::Disabled));
+        assert_eq!(kms.test_commits, 0);
+
+        assigner.configure(&PerformanceConfig::default());
+        assert!(assigner.assign(&mut kms, output(), &candidates[..1]).primary.is_some());
+    }
+}
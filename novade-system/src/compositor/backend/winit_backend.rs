@@ -1,7 +1,6 @@
 // This is novade-system/src/compositor/backend/winit_backend.rs
 // Implementation for running the compositor within a Winit window for testing and development.
 
-use std::time::Duration;
 use smithay::{
     backend::{
         input::{self as backend_input, Axis, AxisSource as BackendAxisSource, InputEvent as BackendInputEvent},
@@ -230,9 +229,6 @@ pub fn handle_winit_event(
             //     }
             // }
         }
-        smithay::reexports::winit::event::Event::MainEventsCleared => {
-             winit_data.window.request_redraw(); // Keep requesting redraw for continuous rendering/updates
-        }
         smithay::reexports::winit::event::Event::LoopDestroyed => {
             info!("Winit event loop destroyed.");
             *desktop_state.running.write().unwrap() = false;
@@ -240,9 +236,8 @@ pub fn handle_winit_event(
         _ => {}
     }
 
+    // Redraws and wake-ups otherwise follow the output's frame scheduler, see `run_compositor`.
     if !*desktop_state.running.read().unwrap() {
         *control_flow = ControlFlow::Exit;
-    } else {
-        *control_flow = ControlFlow::WaitUntil(std::time::Instant::now() + Duration::from_millis(16));
     }
 }
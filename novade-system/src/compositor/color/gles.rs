@@ -0,0 +1,306 @@
+// novade-system/src/compositor/color/gles.rs
+
+//! Colour conversion in the GLES renderer of the winit backend.
+//!
+//! GLES 2 has no 3D textures, so LUTs are uploaded as 2D half-float textures with the blue
+//! slices side by side ([`Lut3d::to_tiled_rgba_f16`]) and sampled by `gles_color_lut.frag`.
+//! Surfaces with an image description are drawn through their LUT; that shader also cuts
+//! rounded corners, as an element is drawn with a single texture program. Outputs with an ICC
+//! profile are composited into a texture, which is then drawn through the output LUT.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Weak};
+
+use smithay::backend::allocator::Fourcc;
+use smithay::backend::renderer::damage::OutputDamageTracker;
+use smithay::backend::renderer::element::texture::TextureRenderElement;
+use smithay::backend::renderer::element::{Element, Id, Kind, RenderElement, UnderlyingStorage};
+use smithay::backend::renderer::gles2::{ffi, Gles2Error, Gles2Frame, Gles2Renderer, Gles2Texture, GlesTexProgram, Uniform, UniformName, UniformType};
+use smithay::backend::renderer::utils::{CommitCounter, DamageSet, OpaqueRegions};
+use smithay::backend::renderer::{Bind, ImportMem, Offscreen, Texture};
+use smithay::output::Output;
+use smithay::utils::{Buffer, Physical, Point, Rectangle, Scale, Size, Transform};
+use tracing::{error, warn};
+
+use super::Lut3d;
+use crate::compositor::effects::gles::RoundedCorners;
+
+const COLOR_LUT_SHADER: &str = include_str!("../../../assets/shaders/gles_color_lut.frag");
+
+/// Texture unit the LUT is bound to while an element is drawn; smithay samples from unit 0.
+const LUT_TEXTURE_UNIT: u32 = 1;
+
+/// Offscreen composition of an output with an output LUT.
+struct OutputComposite {
+    scene: Gles2Texture,
+    /// Output scale the scene was composited at.
+    scale: f64,
+    /// Damage of the scene texture, which keeps its content from frame to frame.
+    scene_tracker: OutputDamageTracker,
+    /// Damage of the window's buffers, which get the whole converted scene.
+    present_tracker: OutputDamageTracker,
+    /// Changed whenever the scene or the LUT changed, so the present tracker repaints it.
+    present_id: Id,
+    /// Address of the LUT the scene was last presented through.
+    lut: usize,
+}
+
+// ANCHOR: GlesColorPipeline
+/// The LUT shader, uploaded LUTs and the offscreen scenes of outputs with an output LUT.
+#[derive(Default)]
+pub struct GlesColorPipeline {
+    program: Option<GlesTexProgram>,
+    /// Set once compiling failed, so it is neither retried nor reported every frame.
+    compile_failed: bool,
+    /// Uploaded LUTs by address, dropped once the LUT is.
+    luts: HashMap<usize, (Weak<Lut3d>, Gles2Texture)>,
+    outputs: HashMap<String, OutputComposite>,
+}
+
+impl GlesColorPipeline {
+    fn program(&mut self, renderer: &mut Gles2Renderer) -> Option<GlesTexProgram> {
+        if self.program.is_none() && !self.compile_failed {
+            let uniforms = [
+                UniformName::new("lut", UniformType::_1i),
+                UniformName::new("lut_size", UniformType::_1f),
+                UniformName::new("element_offset", UniformType::_2f),
+                UniformName::new("element_size", UniformType::_2f),
+                UniformName::new("geo_size", UniformType::_2f),
+                UniformName::new("corner_radius", UniformType::_1f),
+            ];
+            match renderer.compile_custom_texture_shader(COLOR_LUT_SHADER, &uniforms) {
+                Ok(program) => self.program = Some(program),
+                Err(e) => {
+                    error!("Failed to compile the colour LUT shader, drawing without colour conversion: {}", e);
+                    self.compile_failed = true;
+                }
+            }
+        }
+        self.program.clone()
+    }
+
+    /// How to draw through `lut`, uploading it on first use. `None` if that is not possible;
+    /// content is then drawn unconverted.
+    pub fn conversion(&mut self, renderer: &mut Gles2Renderer, lut: &Arc<Lut3d>) -> Option<LutConversion> {
+        let program = self.program(renderer)?;
+        self.luts.retain(|_, (lut, _)| lut.strong_count() > 0);
+        let key = Arc::as_ptr(lut) as usize;
+        let texture = match self.luts.get(&key) {
+            Some((_, texture)) => texture.clone(),
+            None => {
+                let size = lut.size() as i32;
+                let texels = lut.to_tiled_rgba_f16();
+                let bytes: Vec<u8> = texels.iter().flat_map(|texel| texel.to_ne_bytes()).collect();
+                match renderer.import_memory(&bytes, Fourcc::Abgr16161616f, Size::from((size * size, size)), false) {
+                    Ok(texture) => {
+                        self.luts.insert(key, (Arc::downgrade(lut), texture.clone()));
+                        texture
+                    }
+                    Err(e) => {
+                        warn!("Failed to upload a colour LUT, drawing without colour conversion: {}", e);
+                        return None;
+                    }
+                }
+            }
+        };
+        Some(LutConversion { program, texture, size: lut.size() as f32 })
+    }
+
+    /// Composites `elements` (topmost first) for `output` into its scene texture and returns
+    /// the element drawing that scene through `lut`. `output_size` is the output's size in
+    /// physical pixels after its transform. `None` if the conversion is not possible; the
+    /// output is then drawn directly.
+    #[allow(clippy::too_many_arguments)]
+    pub fn composite_output<E: RenderElement<Gles2Renderer>>(
+        &mut self,
+        renderer: &mut Gles2Renderer,
+        output: &Output,
+        output_size: Size<i32, Physical>,
+        scale: f64,
+        elements: &[E],
+        clear_color: [f32; 4],
+        lut: &Arc<Lut3d>,
+    ) -> Result<Option<ColorConvertedElement<TextureRenderElement<Gles2Texture>>>, Gles2Error> {
+        let Some(conversion) = self.conversion(renderer, lut) else {
+            return Ok(None);
+        };
+        let scene_size = Size::<i32, Buffer>::from((output_size.w, output_size.h));
+        let reusable = self
+            .outputs
+            .get(&output.name())
+            .is_some_and(|composite| composite.scene.size() == scene_size && composite.scale == scale);
+        if !reusable {
+            let scene = Offscreen::<Gles2Texture>::create_buffer(renderer, Fourcc::Abgr8888, scene_size)?;
+            let composite = OutputComposite {
+                scene,
+                scale,
+                // The scene is drawn upright; the output transform is applied when presenting.
+                scene_tracker: OutputDamageTracker::new(output_size, scale, Transform::Normal),
+                present_tracker: OutputDamageTracker::from_output(output),
+                present_id: Id::new(),
+                lut: Arc::as_ptr(lut) as usize,
+            };
+            self.outputs.insert(output.name(), composite);
+        }
+        let composite = self.outputs.get_mut(&output.name()).expect("output composite was just created");
+
+        // A reused scene holds the previous frame.
+        let age = if reusable { 1 } else { 0 };
+        let mut scene = composite.scene.clone();
+        let scene_changed = {
+            let mut target = renderer.bind(&mut scene)?;
+            composite.scene_tracker.render_output(renderer, &mut target, age, elements, clear_color)?.damage.is_some()
+        };
+        if scene_changed || composite.lut != Arc::as_ptr(lut) as usize {
+            composite.present_id = Id::new();
+            composite.lut = Arc::as_ptr(lut) as usize;
+        }
+
+        let logical_size = output_size.to_f64().to_logical(scale).to_i32_round();
+        let element = TextureRenderElement::from_static_texture(
+            composite.present_id.clone(),
+            renderer.context_id(),
+            (0.0, 0.0),
+            composite.scene.clone(),
+            1,
+            Transform::Normal,
+            None,
+            None,
+            Some(logical_size),
+            None,
+            Kind::Unspecified,
+        );
+        Ok(Some(conversion.apply(element, None)))
+    }
+
+    /// Damage tracker for presenting the converted scene of `output`, which must have been
+    /// composited this frame.
+    pub fn present_tracker(&mut self, output: &Output) -> Option<&mut OutputDamageTracker> {
+        self.outputs.get_mut(&output.name()).map(|composite| &mut composite.present_tracker)
+    }
+
+    /// Drops the offscreen scene of `output_name`, e.g. once it has no output LUT anymore.
+    /// Returns whether there was one.
+    pub fn forget_output(&mut self, output_name: &str) -> bool {
+        self.outputs.remove(output_name).is_some()
+    }
+}
+// ANCHOR_END: GlesColorPipeline
+
+// ANCHOR: ColorConvertedElement
+/// An uploaded LUT and the shader sampling it.
+#[derive(Debug, Clone)]
+pub struct LutConversion {
+    program: GlesTexProgram,
+    texture: Gles2Texture,
+    size: f32,
+}
+
+impl LutConversion {
+    /// Draws `element` through the LUT, cut to `corners` if given.
+    pub fn apply<E: Element>(&self, element: E, corners: Option<RoundedCorners>) -> ColorConvertedElement<E> {
+        ColorConvertedElement { inner: element, conversion: self.clone(), corners }
+    }
+}
+
+/// An element whose colours are converted through a LUT while it is drawn. Converted
+/// elements are never scanned out directly; with rounded corners they report no opaque
+/// regions.
+#[derive(Debug)]
+pub struct ColorConvertedElement<E> {
+    inner: E,
+    conversion: LutConversion,
+    corners: Option<RoundedCorners>,
+}
+
+impl<E: Element> Element for ColorConvertedElement<E> {
+    fn id(&self) -> &Id {
+        self.inner.id()
+    }
+
+    fn current_commit(&self) -> CommitCounter {
+        self.inner.current_commit()
+    }
+
+    fn location(&self, scale: Scale<f64>) -> Point<i32, Physical> {
+        self.inner.location(scale)
+    }
+
+    fn src(&self) -> Rectangle<f64, Buffer> {
+        self.inner.src()
+    }
+
+    fn transform(&self) -> Transform {
+        self.inner.transform()
+    }
+
+    fn geometry(&self, scale: Scale<f64>) -> Rectangle<i32, Physical> {
+        self.inner.geometry(scale)
+    }
+
+    fn damage_since(&self, scale: Scale<f64>, commit: Option<CommitCounter>) -> DamageSet<i32, Physical> {
+        self.inner.damage_since(scale, commit)
+    }
+
+    fn opaque_regions(&self, scale: Scale<f64>) -> OpaqueRegions<i32, Physical> {
+        match self.corners {
+            Some(_) => OpaqueRegions::default(),
+            None => self.inner.opaque_regions(scale),
+        }
+    }
+
+    fn alpha(&self) -> f32 {
+        self.inner.alpha()
+    }
+
+    fn kind(&self) -> Kind {
+        self.inner.kind()
+    }
+}
+
+impl<E: RenderElement<Gles2Renderer>> RenderElement<Gles2Renderer> for ColorConvertedElement<E> {
+    fn draw(
+        &self,
+        frame: &mut Gles2Frame<'_, '_>,
+        src: Rectangle<f64, Buffer>,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        opaque_regions: &[Rectangle<i32, Physical>],
+    ) -> Result<(), Gles2Error> {
+        let mut uniforms = match &self.corners {
+            Some(corners) => corners.uniforms(&self.inner),
+            None => vec![
+                Uniform::new("element_offset", (0.0f32, 0.0f32)),
+                Uniform::new("element_size", (0.0f32, 0.0f32)),
+                Uniform::new("geo_size", (0.0f32, 0.0f32)),
+                Uniform::new("corner_radius", 0.0f32),
+            ],
+        };
+        uniforms.push(Uniform::new("lut", LUT_TEXTURE_UNIT as i32));
+        uniforms.push(Uniform::new("lut_size", self.conversion.size));
+
+        let lut = self.conversion.texture.tex_id();
+        frame.with_context(|gl| unsafe {
+            gl.ActiveTexture(ffi::TEXTURE0 + LUT_TEXTURE_UNIT);
+            gl.BindTexture(ffi::TEXTURE_2D, lut);
+            // Entries are read at texel centres and interpolated by the shader.
+            gl.TexParameteri(ffi::TEXTURE_2D, ffi::TEXTURE_MIN_FILTER, ffi::NEAREST as i32);
+            gl.TexParameteri(ffi::TEXTURE_2D, ffi::TEXTURE_MAG_FILTER, ffi::NEAREST as i32);
+            gl.ActiveTexture(ffi::TEXTURE0);
+        })?;
+        frame.override_default_tex_program(self.conversion.program.clone(), uniforms);
+        let result = self.inner.draw(frame, src, dst, damage, opaque_regions);
+        frame.clear_tex_program_override();
+        frame.with_context(|gl| unsafe {
+            gl.ActiveTexture(ffi::TEXTURE0 + LUT_TEXTURE_UNIT);
+            gl.BindTexture(ffi::TEXTURE_2D, 0);
+            gl.ActiveTexture(ffi::TEXTURE0);
+        })?;
+        result
+    }
+
+    fn underlying_storage(&self, _renderer: &mut Gles2Renderer) -> Option<UnderlyingStorage<'_>> {
+        None
+    }
+}
+// ANCHOR_END: ColorConvertedElement
@@ -0,0 +1,305 @@
+// novade-system/src/compositor/color/icc.rs
+
+//! Minimal ICC v2/v4 reader for matrix/TRC display profiles.
+//!
+//! Calibration tools write monitor profiles as three colorant tags (`rXYZ`, `gXYZ`, `bXYZ`,
+//! already adapted to the D50 connection space) plus one tone curve per channel. That is all a
+//! display transform needs, so LUT-based (`A2B0`) profiles are rejected rather than
+//! approximated.
+
+use std::sync::Arc;
+
+use super::math::{Mat3, TransferFunction, Vec3};
+use super::{ColorError, ColorSpace, Luminance};
+
+/// Largest profile we accept, as in the wp-color-management protocol.
+pub const MAX_ICC_SIZE: usize = 32 * 1024 * 1024;
+
+const HEADER_SIZE: usize = 128;
+
+fn invalid(message: impl Into<String>) -> ColorError {
+    ColorError::InvalidIcc(message.into())
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&self, offset: usize, len: usize) -> Result<&'a [u8], ColorError> {
+        offset
+            .checked_add(len)
+            .and_then(|end| self.data.get(offset..end))
+            .ok_or_else(|| invalid(format!("read of {} bytes at offset {} is out of bounds", len, offset)))
+    }
+
+    fn u16(&self, offset: usize) -> Result<u16, ColorError> {
+        let b = self.bytes(offset, 2)?;
+        Ok(u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    fn u32(&self, offset: usize) -> Result<u32, ColorError> {
+        let b = self.bytes(offset, 4)?;
+        Ok(u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    /// `s15Fixed16Number`.
+    fn s15f16(&self, offset: usize) -> Result<f64, ColorError> {
+        Ok(self.u32(offset)? as i32 as f64 / 65536.0)
+    }
+
+    fn signature(&self, offset: usize) -> Result<[u8; 4], ColorError> {
+        let b = self.bytes(offset, 4)?;
+        Ok([b[0], b[1], b[2], b[3]])
+    }
+}
+
+// ANCHOR: IccProfile
+/// The parts of a display profile the colour pipeline uses.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IccProfile {
+    pub version: (u8, u8),
+    /// Linear device RGB to D50 XYZ, from the colorant tags.
+    pub to_pcs: Mat3,
+    pub transfer: [TransferFunction; 3],
+    /// Display white luminance in cd/m² from the `lumi` tag, if present.
+    pub luminance: Option<f64>,
+    pub description: Option<String>,
+}
+
+impl IccProfile {
+    pub fn parse(data: &[u8]) -> Result<Self, ColorError> {
+        if data.len() > MAX_ICC_SIZE {
+            return Err(invalid(format!("profile of {} bytes exceeds the {} byte limit", data.len(), MAX_ICC_SIZE)));
+        }
+        let reader = Reader { data };
+        if data.len() < HEADER_SIZE + 4 {
+            return Err(invalid("file is shorter than the ICC header"));
+        }
+        let declared = reader.u32(0)? as usize;
+        if declared > data.len() {
+            return Err(invalid(format!("header declares {} bytes but only {} are present", declared, data.len())));
+        }
+        if &reader.signature(36)? != b"acsp" {
+            return Err(invalid("missing 'acsp' signature"));
+        }
+        let version = (data[8], data[9] >> 4);
+        if !(2..=4).contains(&version.0) {
+            return Err(ColorError::UnsupportedIcc(format!("ICC version {}.{}", version.0, version.1)));
+        }
+        if &reader.signature(16)? != b"RGB " {
+            return Err(ColorError::UnsupportedIcc("only RGB profiles can describe a display".into()));
+        }
+        if &reader.signature(20)? != b"XYZ " {
+            return Err(ColorError::UnsupportedIcc("only XYZ connection spaces are supported".into()));
+        }
+
+        let tag_count = reader.u32(HEADER_SIZE)? as usize;
+        let mut tags = Vec::with_capacity(tag_count.min(256));
+        for i in 0..tag_count {
+            let entry = HEADER_SIZE + 4 + i * 12;
+            tags.push((reader.signature(entry)?, reader.u32(entry + 4)? as usize, reader.u32(entry + 8)? as usize));
+        }
+        let tag = |sig: &[u8; 4]| -> Option<Result<&[u8], ColorError>> {
+            tags.iter().find(|(s, _, _)| s == sig).map(|(_, offset, size)| reader.bytes(*offset, *size))
+        };
+        let required = |sig: &[u8; 4]| -> Result<&[u8], ColorError> {
+            tag(sig).unwrap_or_else(|| {
+                Err(ColorError::UnsupportedIcc(format!(
+                    "missing '{}' tag; only matrix/TRC display profiles are supported",
+                    String::from_utf8_lossy(sig)
+                )))
+            })
+        };
+
+        let red = parse_xyz(required(b"rXYZ")?)?;
+        let green = parse_xyz(required(b"gXYZ")?)?;
+        let blue = parse_xyz(required(b"bXYZ")?)?;
+        let to_pcs = Mat3::from_columns(red, green, blue);
+        if to_pcs.inverse().is_none() {
+            return Err(invalid("colorant matrix is singular"));
+        }
+        let transfer = [parse_curve(required(b"rTRC")?)?, parse_curve(required(b"gTRC")?)?, parse_curve(required(b"bTRC")?)?];
+        let luminance = match tag(b"lumi") {
+            Some(bytes) => Some(parse_xyz(bytes?)?[1]),
+            None => None,
+        };
+        let description = tag(b"desc").and_then(|bytes| bytes.ok()).and_then(parse_text);
+        Ok(Self { version, to_pcs, transfer, luminance, description })
+    }
+
+    pub fn load(path: &std::path::Path) -> Result<Self, ColorError> {
+        let data = std::fs::read(path).map_err(|source| ColorError::Io { path: path.to_path_buf(), source })?;
+        Self::parse(&data)
+    }
+
+    /// The colour space of the calibrated display, treated as SDR with `white_nits` white.
+    pub fn color_space(&self, white_nits: f64) -> ColorSpace {
+        let white = self.luminance.unwrap_or(white_nits);
+        ColorSpace { to_pcs: self.to_pcs, transfer: self.transfer.clone(), luminance: Luminance { min: 0.2, max: white, reference: white } }
+    }
+}
+// ANCHOR_END: IccProfile
+
+fn parse_xyz(bytes: &[u8]) -> Result<Vec3, ColorError> {
+    let reader = Reader { data: bytes };
+    if &reader.signature(0)? != b"XYZ " {
+        return Err(invalid("expected an XYZ tag"));
+    }
+    Ok([reader.s15f16(8)?, reader.s15f16(12)?, reader.s15f16(16)?])
+}
+
+fn parse_curve(bytes: &[u8]) -> Result<TransferFunction, ColorError> {
+    let reader = Reader { data: bytes };
+    match &reader.signature(0)? {
+        b"curv" => {
+            let count = reader.u32(8)? as usize;
+            match count {
+                0 => Ok(TransferFunction::Linear),
+                // u8Fixed8Number gamma.
+                1 => Ok(TransferFunction::Power(reader.u16(12)? as f64 / 256.0)),
+                _ => {
+                    let table: Result<Arc<[f64]>, _> = (0..count).map(|i| reader.u16(12 + i * 2).map(|v| v as f64 / 65535.0)).collect();
+                    Ok(TransferFunction::Sampled(table?))
+                }
+            }
+        }
+        b"para" => {
+            let function = reader.u16(8)?;
+            let param_count = match function {
+                0 => 1,
+                1 => 3,
+                2 => 4,
+                3 => 5,
+                4 => 7,
+                other => return Err(invalid(format!("unknown parametric curve type {}", other))),
+            };
+            let p: Vec<f64> = (0..param_count).map(|i| reader.s15f16(12 + i * 4)).collect::<Result<_, _>>()?;
+            // Normalise every variant to the general `(a·x + b)^g + e` / `c·x + f` form.
+            let curve = match function {
+                0 => TransferFunction::Power(p[0]),
+                1 => TransferFunction::Parametric { g: p[0], a: p[1], b: p[2], c: 0.0, d: -p[2] / p[1], e: 0.0, f: 0.0 },
+                2 => TransferFunction::Parametric { g: p[0], a: p[1], b: p[2], c: 0.0, d: -p[2] / p[1], e: p[3], f: p[3] },
+                3 => TransferFunction::Parametric { g: p[0], a: p[1], b: p[2], c: p[3], d: p[4], e: 0.0, f: 0.0 },
+                _ => TransferFunction::Parametric { g: p[0], a: p[1], b: p[2], c: p[3], d: p[4], e: p[5], f: p[6] },
+            };
+            Ok(curve)
+        }
+        other => Err(invalid(format!("unsupported curve type '{}'", String::from_utf8_lossy(other)))),
+    }
+}
+
+/// Reads a `desc` (v2 textDescriptionType) or `mluc` (v4) tag.
+fn parse_text(bytes: &[u8]) -> Option<String> {
+    let reader = Reader { data: bytes };
+    match &reader.signature(0).ok()? {
+        b"desc" => {
+            let len = reader.u32(8).ok()? as usize;
+            let text = reader.bytes(12, len).ok()?;
+            Some(String::from_utf8_lossy(text).trim_end_matches('\0').to_string())
+        }
+        b"mluc" => {
+            let (len, offset) = (reader.u32(20).ok()? as usize, reader.u32(24).ok()? as usize);
+            let units: Vec<u16> = reader.bytes(offset, len).ok()?.chunks_exact(2).map(|c| u16::from_be_bytes([c[0], c[1]])).collect();
+            Some(String::from_utf16_lossy(&units))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::compositor::color::lut::ColorTransform;
+    use crate::compositor::color::RenderIntent;
+    use crate::compositor::color::math::Primaries;
+
+    fn s15f16(v: f64) -> [u8; 4] {
+        ((v * 65536.0).round() as i32).to_be_bytes()
+    }
+
+    fn xyz_tag(v: Vec3) -> Vec<u8> {
+        let mut tag = b"XYZ \0\0\0\0".to_vec();
+        v.iter().for_each(|c| tag.extend(s15f16(*c)));
+        tag
+    }
+
+    /// Builds a v4 matrix/TRC profile with the given colorants and sRGB parametric curves.
+    pub(crate) fn build_profile(colorants: Mat3) -> Vec<u8> {
+        let m = colorants.0;
+        let mut trc = b"para\0\0\0\0\0\x03\0\0".to_vec();
+        for p in [2.4, 1.0 / 1.055, 0.055 / 1.055, 1.0 / 12.92, 0.04045] {
+            trc.extend(s15f16(p));
+        }
+        let desc: Vec<u8> = [b"desc\0\0\0\0".as_slice(), 8u32.to_be_bytes().as_slice(), b"Test ICC".as_slice()].concat();
+        let tags: Vec<(&[u8; 4], Vec<u8>)> = vec![
+            (b"rXYZ", xyz_tag([m[0][0], m[1][0], m[2][0]])),
+            (b"gXYZ", xyz_tag([m[0][1], m[1][1], m[2][1]])),
+            (b"bXYZ", xyz_tag([m[0][2], m[1][2], m[2][2]])),
+            (b"rTRC", trc.clone()),
+            (b"gTRC", trc.clone()),
+            (b"bTRC", trc),
+            (b"desc", desc),
+        ];
+        let mut header = vec![0u8; HEADER_SIZE];
+        header[8] = 4;
+        header[12..16].copy_from_slice(b"mntr");
+        header[16..20].copy_from_slice(b"RGB ");
+        header[20..24].copy_from_slice(b"XYZ ");
+        header[36..40].copy_from_slice(b"acsp");
+        let mut table = (tags.len() as u32).to_be_bytes().to_vec();
+        let mut body = Vec::new();
+        let mut offset = HEADER_SIZE + 4 + tags.len() * 12;
+        for (sig, data) in &tags {
+            table.extend_from_slice(&sig[..]);
+            table.extend((offset as u32).to_be_bytes());
+            table.extend((data.len() as u32).to_be_bytes());
+            body.extend(data);
+            while body.len() % 4 != 0 {
+                body.push(0);
+            }
+            offset = HEADER_SIZE + 4 + tags.len() * 12 + body.len();
+        }
+        let mut profile = [header, table, body].concat();
+        let len = (profile.len() as u32).to_be_bytes();
+        profile[0..4].copy_from_slice(&len);
+        profile
+    }
+
+    #[test]
+    fn test_parse_matrix_trc_profile() {
+        let data = build_profile(Primaries::SRGB.rgb_to_pcs());
+        let profile = IccProfile::parse(&data).unwrap();
+        assert_eq!(profile.version.0, 4);
+        assert_eq!(profile.description.as_deref(), Some("Test ICC"));
+        assert!(matches!(profile.transfer[0], TransferFunction::Parametric { .. }));
+
+        // A profile describing an sRGB display makes the output transform an identity, up to
+        // the s15Fixed16 quantisation of the tags.
+        let transform = ColorTransform::new(&ColorSpace::srgb(), &profile.color_space(80.0), RenderIntent::Relative).unwrap();
+        for v in [0.0f32, 0.1, 0.5, 0.9, 1.0] {
+            let out = transform.apply([v, 1.0 - v, v * 0.5]);
+            assert!((out[0] - v).abs() < 1e-3 && (out[1] - (1.0 - v)).abs() < 1e-3 && (out[2] - v * 0.5).abs() < 1e-3, "{:?}", out);
+        }
+    }
+
+    #[test]
+    fn test_rejects_invalid_profiles() {
+        assert!(matches!(IccProfile::parse(b"short"), Err(ColorError::InvalidIcc(_))));
+
+        let mut data = build_profile(Primaries::SRGB.rgb_to_pcs());
+        data[36..40].copy_from_slice(b"nope");
+        assert!(matches!(IccProfile::parse(&data), Err(ColorError::InvalidIcc(_))));
+
+        // A profile without colorant tags (e.g. LUT-based) is unsupported, not invalid.
+        let mut data = build_profile(Primaries::SRGB.rgb_to_pcs());
+        let first_tag = HEADER_SIZE + 4;
+        data[first_tag..first_tag + 4].copy_from_slice(b"A2B0");
+        assert!(matches!(IccProfile::parse(&data), Err(ColorError::UnsupportedIcc(_))));
+
+        // Tag offsets pointing past the end are caught.
+        let mut data = build_profile(Primaries::SRGB.rgb_to_pcs());
+        data[first_tag + 4..first_tag + 8].copy_from_slice(&u32::MAX.to_be_bytes());
+        assert!(matches!(IccProfile::parse(&data), Err(ColorError::InvalidIcc(_))));
+    }
+}
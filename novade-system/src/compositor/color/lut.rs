@@ -0,0 +1,282 @@
+// novade-system/src/compositor/color/lut.rs
+
+//! Colour transforms between [`ColorSpace`]s and their baked 3D LUT form.
+//!
+//! [`ColorTransform`] is the exact per-pixel pipeline: decode, scale to reference white,
+//! convert primaries through the D50 connection space, clip, tone map, encode. Renderers don't
+//! run it per pixel; they sample a [`Lut3d`] baked from it, which is what the tests compare.
+
+use super::math::{tone_map, Mat3, TransferFunction};
+use super::{ColorSpace, RenderIntent};
+
+// ANCHOR: ColorTransform
+/// Conversion of encoded RGB from one colour space to another.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorTransform {
+    matrix: Mat3,
+    source_transfer: [TransferFunction; 3],
+    target_transfer: [TransferFunction; 3],
+    /// Linear source value → multiples of reference white.
+    source_scale: f64,
+    /// Multiples of reference white → linear target value.
+    target_scale: f64,
+    source_headroom: f64,
+    target_headroom: f64,
+    tone_map: bool,
+}
+
+impl ColorTransform {
+    /// Returns `None` if the target primaries are degenerate.
+    pub fn new(source: &ColorSpace, target: &ColorSpace, intent: RenderIntent) -> Option<Self> {
+        let from_pcs = target.to_pcs.inverse()?;
+        Some(Self {
+            matrix: from_pcs.mul(&source.to_pcs),
+            source_transfer: source.transfer.clone(),
+            target_transfer: target.transfer.clone(),
+            source_scale: source.unit_nits() / source.luminance.reference,
+            target_scale: target.luminance.reference / target.unit_nits(),
+            source_headroom: source.headroom(),
+            target_headroom: target.headroom(),
+            tone_map: intent == RenderIntent::Perceptual,
+        })
+    }
+
+    /// Whether the transform leaves every value unchanged, so the pass can be skipped.
+    pub fn is_identity(&self) -> bool {
+        let matrix_is_identity = self.matrix.0.iter().flatten().zip(Mat3::IDENTITY.0.iter().flatten()).all(|(a, b)| (a - b).abs() < 1e-6);
+        matrix_is_identity
+            && self.source_transfer == self.target_transfer
+            && (self.source_scale * self.target_scale - 1.0).abs() < 1e-6
+            && (!self.tone_map || self.source_headroom <= self.target_headroom)
+    }
+
+    /// Converts one encoded colour; inputs and outputs are in `0..=1`.
+    pub fn apply(&self, rgb: [f32; 3]) -> [f32; 3] {
+        let linear = [0, 1, 2].map(|i| self.source_transfer[i].to_linear(rgb[i] as f64) * self.source_scale);
+        // Out-of-gamut colours are clipped to the target gamut.
+        let converted = self.matrix.apply(linear).map(|v| v.max(0.0));
+        let mapped = if self.tone_map { tone_map(converted, self.source_headroom, self.target_headroom) } else { converted };
+        [0, 1, 2].map(|i| self.target_transfer[i].from_linear(mapped[i] * self.target_scale) as f32)
+    }
+
+    /// Bakes the transform into a LUT with `size` points per axis.
+    pub fn bake(&self, size: usize) -> Lut3d {
+        Lut3d::bake(size, |rgb| self.apply(rgb))
+    }
+}
+// ANCHOR_END: ColorTransform
+
+// ANCHOR: Lut3d
+/// A 3D lookup table over encoded RGB in `0..=1`, sampled with trilinear interpolation.
+///
+/// Entries are stored red-fastest, i.e. at `(b * size + g) * size + r`, the texel order of a
+/// 3D texture with red along x, so [`Lut3d::to_rgba_f32`] can be uploaded as-is.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lut3d {
+    size: usize,
+    data: Vec<[f32; 3]>,
+}
+
+impl Lut3d {
+    /// Default number of points per axis.
+    pub const DEFAULT_SIZE: usize = 33;
+
+    pub fn bake(size: usize, f: impl Fn([f32; 3]) -> [f32; 3]) -> Self {
+        let size = size.max(2);
+        let step = 1.0 / (size - 1) as f32;
+        let mut data = Vec::with_capacity(size * size * size);
+        for b in 0..size {
+            for g in 0..size {
+                for r in 0..size {
+                    data.push(f([r as f32 * step, g as f32 * step, b as f32 * step]));
+                }
+            }
+        }
+        Self { size, data }
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn data(&self) -> &[[f32; 3]] {
+        &self.data
+    }
+
+    fn entry(&self, r: usize, g: usize, b: usize) -> [f32; 3] {
+        self.data[(b * self.size + g) * self.size + r]
+    }
+
+    pub fn sample(&self, rgb: [f32; 3]) -> [f32; 3] {
+        let max = (self.size - 1) as f32;
+        let mut index = [0usize; 3];
+        let mut frac = [0f32; 3];
+        for i in 0..3 {
+            let x = rgb[i].clamp(0.0, 1.0) * max;
+            let base = (x.floor() as usize).min(self.size - 2);
+            index[i] = base;
+            frac[i] = x - base as f32;
+        }
+        let mut out = [0.0f32; 3];
+        for corner in 0..8 {
+            let (dr, dg, db) = (corner & 1, (corner >> 1) & 1, (corner >> 2) & 1);
+            let weight = [dr, dg, db].iter().zip(frac).map(|(d, f)| if *d == 1 { f } else { 1.0 - f }).product::<f32>();
+            if weight == 0.0 {
+                continue;
+            }
+            let value = self.entry(index[0] + dr, index[1] + dg, index[2] + db);
+            for c in 0..3 {
+                out[c] += weight * value[c];
+            }
+        }
+        out
+    }
+
+    /// RGBA texel data with alpha 1, for uploading as an `Rgba32Float` 3D texture.
+    pub fn to_rgba_f32(&self) -> Vec<f32> {
+        self.data.iter().flat_map(|[r, g, b]| [*r, *g, *b, 1.0]).collect()
+    }
+
+    /// Half-float RGBA texel data with alpha 1 for renderers without 3D textures: a 2D texture
+    /// `size * size` texels wide and `size` high, with the entry for `(r, g, b)` at
+    /// `(b * size + r, g)`.
+    pub fn to_tiled_rgba_f16(&self) -> Vec<u16> {
+        let size = self.size;
+        let mut out = Vec::with_capacity(self.data.len() * 4);
+        for g in 0..size {
+            for b in 0..size {
+                for r in 0..size {
+                    let [red, green, blue] = self.entry(r, g, b);
+                    out.extend([f16_bits(red), f16_bits(green), f16_bits(blue), f16_bits(1.0)]);
+                }
+            }
+        }
+        out
+    }
+}
+// ANCHOR_END: Lut3d
+
+/// Bits of the IEEE half-precision float nearest to `value`.
+fn f16_bits(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    if value.is_nan() {
+        return sign | 0x7e00;
+    }
+    let exponent = ((bits >> 23) & 0xff) as i32 - 127 + 15;
+    let mantissa = bits & 0x7f_ffff;
+    if exponent >= 0x1f {
+        return sign | 0x7c00;
+    }
+    if exponent <= 0 {
+        if exponent < -10 {
+            return sign;
+        }
+        // Subnormal: shift the mantissa with its implicit bit in, rounding to nearest.
+        let shift = (14 - exponent) as u32;
+        let mantissa = mantissa | 0x80_0000;
+        return sign | ((mantissa + (1 << (shift - 1))) >> shift) as u16;
+    }
+    // Rounding may carry into the exponent, which still yields the nearest value.
+    let half = ((exponent as u32) << 10) | (mantissa >> 13);
+    sign | (half + ((mantissa >> 12) & 1)) as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compositor::color::math::Primaries;
+    use crate::compositor::color::Luminance;
+
+    /// Deterministic sample points that avoid the LUT grid.
+    fn sample_points() -> impl Iterator<Item = [f32; 3]> {
+        (0..17 * 17 * 17).map(|i| {
+            let (r, g, b) = (i % 17, (i / 17) % 17, i / 289);
+            [(r as f32 + 0.37) / 17.0, (g as f32 + 0.61) / 17.0, (b as f32 + 0.13) / 17.0]
+        })
+    }
+
+    fn max_error(lut: &Lut3d, transform: &ColorTransform) -> f32 {
+        sample_points()
+            .map(|p| {
+                let (a, b) = (lut.sample(p), transform.apply(p));
+                (0..3).map(|c| (a[c] - b[c]).abs()).fold(0.0, f32::max)
+            })
+            .fold(0.0, f32::max)
+    }
+
+    #[test]
+    fn test_identity_transform() {
+        let srgb = ColorSpace::srgb();
+        let transform = ColorTransform::new(&srgb, &srgb, RenderIntent::Perceptual).unwrap();
+        assert!(transform.is_identity());
+        for p in sample_points().take(200) {
+            let out = transform.apply(p);
+            assert!((0..3).all(|c| (out[c] - p[c]).abs() < 1e-5), "{:?} -> {:?}", p, out);
+        }
+        let lut = transform.bake(17);
+        assert!(max_error(&lut, &transform) < 1e-3);
+    }
+
+    #[test]
+    fn test_srgb_to_display_p3_lut_accuracy() {
+        let p3 = ColorSpace::from_primaries(&Primaries::DISPLAY_P3, TransferFunction::Srgb, Luminance::SDR);
+        let transform = ColorTransform::new(&ColorSpace::srgb(), &p3, RenderIntent::Relative).unwrap();
+        assert!(!transform.is_identity());
+
+        // Pure sRGB red is inside P3; its linear value matches the reference matrix.
+        let red = transform.apply([1.0, 0.0, 0.0]);
+        assert!((TransferFunction::Srgb.to_linear(red[0] as f64) - 0.8225).abs() < 1e-3);
+        assert!((TransferFunction::Srgb.to_linear(red[1] as f64) - 0.0332).abs() < 1e-3);
+        // White stays white.
+        assert!(transform.apply([1.0; 3]).iter().all(|c| (c - 1.0).abs() < 1e-4));
+
+        // A 33-point LUT stays within half an 8-bit step of the exact pipeline.
+        let lut = transform.bake(Lut3d::DEFAULT_SIZE);
+        assert_eq!(lut.data().len(), 33 * 33 * 33);
+        assert!(max_error(&lut, &transform) < 0.5 / 255.0);
+        assert_eq!(lut.to_rgba_f32().len(), 33 * 33 * 33 * 4);
+    }
+
+    #[test]
+    fn test_tiled_f16_layout() {
+        assert_eq!([1.0, 0.5, 0.0, -2.0, 1.0 / 3.0, 65504.0, 1e6].map(f16_bits), [0x3c00, 0x3800, 0, 0xc000, 0x3555, 0x7bff, 0x7c00]);
+        assert_eq!(f16_bits(2f32.powi(-24)), 1);
+
+        let lut = Lut3d::bake(3, |[r, g, b]| [r, g * 0.5, b * 0.25]);
+        let tiled = lut.to_tiled_rgba_f16();
+        assert_eq!(tiled.len(), 3 * 3 * 3 * 4);
+        // Entry (r, g, b) = (1, 2, 1) sits at x = b * 3 + r = 4 in row g = 2.
+        let texel = &tiled[(2 * 9 + 4) * 4..][..4];
+        assert_eq!(texel, [f16_bits(0.5), f16_bits(0.5), f16_bits(0.125), 0x3c00]);
+    }
+
+    #[test]
+    fn test_pq_content_is_tone_mapped_for_sdr_output() {
+        let pq = ColorSpace::bt2100_pq();
+        let transform = ColorTransform::new(&pq, &ColorSpace::srgb(), RenderIntent::Perceptual).unwrap();
+        // PQ reference white (203 cd/m²) lands close to SDR white, its peak exactly on it.
+        let reference = TransferFunction::Pq.from_linear(203.0 / 10_000.0) as f32;
+        let white = transform.apply([reference; 3]);
+        assert!(white[0] > 0.9 && white[0] <= 1.0, "{:?}", white);
+        assert!((transform.apply([1.0; 3])[0] - 1.0).abs() < 1e-4);
+        // Brighter input never gets darker.
+        let mut previous = 0.0;
+        for i in 0..=64 {
+            let v = transform.apply([i as f32 / 64.0; 3])[1];
+            assert!(v + 1e-6 >= previous);
+            previous = v;
+        }
+        // The relative intent clips highlights instead: reference white is already at the top.
+        let clipped = ColorTransform::new(&pq, &ColorSpace::srgb(), RenderIntent::Relative).unwrap();
+        assert!((clipped.apply([reference; 3])[0] - 1.0).abs() < 1e-4);
+        // SDR content on an HDR output keeps its reference white at the configured level.
+        let hdr_output = ColorSpace::from_primaries(
+            &Primaries::BT2020,
+            TransferFunction::Pq,
+            Luminance { min: 0.005, max: 1000.0, reference: 203.0 },
+        );
+        let sdr_white = ColorTransform::new(&ColorSpace::srgb(), &hdr_output, RenderIntent::Perceptual).unwrap().apply([1.0; 3]);
+        assert!((sdr_white[0] - reference).abs() < 1e-4);
+    }
+}
@@ -0,0 +1,399 @@
+// novade-system/src/compositor/color/math.rs
+
+//! Colorimetry primitives: 3x3 matrices, chromaticities, named primaries, transfer functions
+//! and tone mapping. Everything is `f64`; only baked LUTs are stored as `f32`.
+
+use std::sync::Arc;
+
+pub type Vec3 = [f64; 3];
+
+// ANCHOR: Mat3
+/// Row-major 3x3 matrix.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mat3(pub [[f64; 3]; 3]);
+
+impl Mat3 {
+    pub const IDENTITY: Mat3 = Mat3([[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]]);
+
+    pub fn from_columns(a: Vec3, b: Vec3, c: Vec3) -> Self {
+        Mat3([[a[0], b[0], c[0]], [a[1], b[1], c[1]], [a[2], b[2], c[2]]])
+    }
+
+    pub fn diagonal(d: Vec3) -> Self {
+        Mat3([[d[0], 0.0, 0.0], [0.0, d[1], 0.0], [0.0, 0.0, d[2]]])
+    }
+
+    /// `self * other`, i.e. `other` is applied first.
+    pub fn mul(&self, other: &Mat3) -> Mat3 {
+        let (a, b) = (&self.0, &other.0);
+        let mut out = [[0.0; 3]; 3];
+        for (i, row) in out.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = a[i][0] * b[0][j] + a[i][1] * b[1][j] + a[i][2] * b[2][j];
+            }
+        }
+        Mat3(out)
+    }
+
+    pub fn apply(&self, v: Vec3) -> Vec3 {
+        let m = &self.0;
+        [
+            m[0][0] * v[0] + m[0][1] * v[1] + m[0][2] * v[2],
+            m[1][0] * v[0] + m[1][1] * v[1] + m[1][2] * v[2],
+            m[2][0] * v[0] + m[2][1] * v[1] + m[2][2] * v[2],
+        ]
+    }
+
+    pub fn inverse(&self) -> Option<Mat3> {
+        let m = &self.0;
+        let cofactor = |r0: usize, r1: usize, c0: usize, c1: usize| m[r0][c0] * m[r1][c1] - m[r0][c1] * m[r1][c0];
+        let det = m[0][0] * cofactor(1, 2, 1, 2) - m[0][1] * cofactor(1, 2, 0, 2) + m[0][2] * cofactor(1, 2, 0, 1);
+        if det.abs() < 1e-12 {
+            return None;
+        }
+        let inv = 1.0 / det;
+        Some(Mat3([
+            [cofactor(1, 2, 1, 2) * inv, -cofactor(0, 2, 1, 2) * inv, cofactor(0, 1, 1, 2) * inv],
+            [-cofactor(1, 2, 0, 2) * inv, cofactor(0, 2, 0, 2) * inv, -cofactor(0, 1, 0, 2) * inv],
+            [cofactor(1, 2, 0, 1) * inv, -cofactor(0, 2, 0, 1) * inv, cofactor(0, 1, 0, 1) * inv],
+        ]))
+    }
+}
+// ANCHOR_END: Mat3
+
+/// CIE 1931 xy chromaticity.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Chromaticity {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl Chromaticity {
+    pub const fn new(x: f64, y: f64) -> Self {
+        Self { x, y }
+    }
+
+    /// XYZ with luminance `Y = 1`.
+    pub fn to_xyz(self) -> Vec3 {
+        [self.x / self.y, 1.0, (1.0 - self.x - self.y) / self.y]
+    }
+
+    /// Chromaticity of an XYZ colour.
+    pub fn from_xyz(xyz: Vec3) -> Self {
+        let sum = xyz[0] + xyz[1] + xyz[2];
+        Self { x: xyz[0] / sum, y: xyz[1] / sum }
+    }
+}
+
+pub const D65: Chromaticity = Chromaticity::new(0.3127, 0.3290);
+/// White point of the ICC profile connection space.
+pub const D50: Chromaticity = Chromaticity::new(0.3457, 0.3585);
+pub const DCI_WHITE: Chromaticity = Chromaticity::new(0.314, 0.351);
+pub const ILLUMINANT_C: Chromaticity = Chromaticity::new(0.310, 0.316);
+pub const ILLUMINANT_E: Chromaticity = Chromaticity::new(1.0 / 3.0, 1.0 / 3.0);
+
+// ANCHOR: Primaries
+/// RGB primaries and white point of a colour space.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Primaries {
+    pub red: Chromaticity,
+    pub green: Chromaticity,
+    pub blue: Chromaticity,
+    pub white: Chromaticity,
+}
+
+const fn primaries(r: (f64, f64), g: (f64, f64), b: (f64, f64), white: Chromaticity) -> Primaries {
+    Primaries { red: Chromaticity::new(r.0, r.1), green: Chromaticity::new(g.0, g.1), blue: Chromaticity::new(b.0, b.1), white }
+}
+
+impl Primaries {
+    /// sRGB / BT.709.
+    pub const SRGB: Primaries = primaries((0.64, 0.33), (0.30, 0.60), (0.15, 0.06), D65);
+    pub const PAL_M: Primaries = primaries((0.67, 0.33), (0.21, 0.71), (0.14, 0.08), ILLUMINANT_C);
+    pub const PAL: Primaries = primaries((0.64, 0.33), (0.29, 0.60), (0.15, 0.06), D65);
+    pub const NTSC: Primaries = primaries((0.630, 0.340), (0.310, 0.595), (0.155, 0.070), D65);
+    pub const GENERIC_FILM: Primaries = primaries((0.681, 0.319), (0.243, 0.692), (0.145, 0.049), ILLUMINANT_C);
+    pub const BT2020: Primaries = primaries((0.708, 0.292), (0.170, 0.797), (0.131, 0.046), D65);
+    pub const CIE1931_XYZ: Primaries = primaries((1.0, 0.0), (0.0, 1.0), (0.0, 0.0), ILLUMINANT_E);
+    pub const DCI_P3: Primaries = primaries((0.680, 0.320), (0.265, 0.690), (0.150, 0.060), DCI_WHITE);
+    pub const DISPLAY_P3: Primaries = primaries((0.680, 0.320), (0.265, 0.690), (0.150, 0.060), D65);
+    pub const ADOBE_RGB: Primaries = primaries((0.64, 0.33), (0.21, 0.71), (0.15, 0.06), D65);
+
+    /// Matrix from linear RGB to XYZ relative to this space's own white point.
+    pub fn rgb_to_xyz(&self) -> Mat3 {
+        let m = Mat3::from_columns(self.red.to_xyz(), self.green.to_xyz(), self.blue.to_xyz());
+        let scale = m.inverse().map(|inv| inv.apply(self.white.to_xyz())).unwrap_or([1.0; 3]);
+        m.mul(&Mat3::diagonal(scale))
+    }
+
+    /// Matrix from linear RGB to D50 XYZ (the ICC connection space), adapting the white point.
+    pub fn rgb_to_pcs(&self) -> Mat3 {
+        bradford_adaptation(self.white, D50).mul(&self.rgb_to_xyz())
+    }
+}
+// ANCHOR_END: Primaries
+
+/// Chromatic adaptation matrix from `src` to `dst` white using the Bradford transform.
+pub fn bradford_adaptation(src: Chromaticity, dst: Chromaticity) -> Mat3 {
+    const BRADFORD: Mat3 = Mat3([[0.8951, 0.2664, -0.1614], [-0.7502, 1.7135, 0.0367], [0.0389, -0.0685, 1.0296]]);
+    let inverse = BRADFORD.inverse().expect("Bradford matrix is invertible");
+    let (s, d) = (BRADFORD.apply(src.to_xyz()), BRADFORD.apply(dst.to_xyz()));
+    inverse.mul(&Mat3::diagonal([d[0] / s[0], d[1] / s[1], d[2] / s[2]])).mul(&BRADFORD)
+}
+
+// ANCHOR: TransferFunction
+/// Electro-optical transfer function of a colour channel.
+///
+/// [`TransferFunction::to_linear`] returns light relative to the encoding's nominal peak: 1.0
+/// is SDR white for SDR curves, 10 000 cd/m² for PQ and 1 000 cd/m² for HLG.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TransferFunction {
+    Srgb,
+    Gamma22,
+    Gamma28,
+    /// BT.1886 with a zero black level, i.e. a pure 2.4 power.
+    Bt1886,
+    /// SMPTE ST 240.
+    St240,
+    Linear,
+    /// SMPTE ST 2084 perceptual quantiser.
+    Pq,
+    /// ARIB STD-B67 hybrid log-gamma, including the OOTF of a 1 000 cd/m² display.
+    Hlg,
+    Power(f64),
+    /// ICC parametric curve: `(a·x + b)^g + e` for `x >= d`, `c·x + f` below.
+    Parametric { g: f64, a: f64, b: f64, c: f64, d: f64, e: f64, f: f64 },
+    /// Uniformly sampled curve, e.g. an ICC `curv` table.
+    Sampled(Arc<[f64]>),
+}
+
+const PQ_M1: f64 = 2610.0 / 16384.0;
+const PQ_M2: f64 = 2523.0 / 4096.0 * 128.0;
+const PQ_C1: f64 = 3424.0 / 4096.0;
+const PQ_C2: f64 = 2413.0 / 4096.0 * 32.0;
+const PQ_C3: f64 = 2392.0 / 4096.0 * 32.0;
+const HLG_A: f64 = 0.178_832_77;
+const HLG_B: f64 = 0.284_668_92;
+const HLG_C: f64 = 0.559_910_73;
+/// HLG system gamma for a 1 000 cd/m² display.
+const HLG_GAMMA: f64 = 1.2;
+
+impl TransferFunction {
+    /// Luminance in cd/m² that linear 1.0 stands for, if the curve is absolute.
+    pub fn absolute_peak_nits(&self) -> Option<f64> {
+        match self {
+            TransferFunction::Pq => Some(10_000.0),
+            TransferFunction::Hlg => Some(1_000.0),
+            _ => None,
+        }
+    }
+
+    /// Decodes an encoded value in `0..=1`.
+    pub fn to_linear(&self, e: f64) -> f64 {
+        let e = e.clamp(0.0, 1.0);
+        match self {
+            TransferFunction::Srgb => {
+                if e <= 0.04045 {
+                    e / 12.92
+                } else {
+                    ((e + 0.055) / 1.055).powf(2.4)
+                }
+            }
+            TransferFunction::Gamma22 => e.powf(2.2),
+            TransferFunction::Gamma28 => e.powf(2.8),
+            TransferFunction::Bt1886 => e.powf(2.4),
+            TransferFunction::St240 => {
+                if e < 0.0913 {
+                    e / 4.0
+                } else {
+                    ((e + 0.1115) / 1.1115).powf(1.0 / 0.45)
+                }
+            }
+            TransferFunction::Linear => e,
+            TransferFunction::Pq => {
+                let p = e.powf(1.0 / PQ_M2);
+                ((p - PQ_C1).max(0.0) / (PQ_C2 - PQ_C3 * p)).powf(1.0 / PQ_M1)
+            }
+            TransferFunction::Hlg => {
+                // Inverse OETF to scene light; the OOTF is applied per channel as an approximation.
+                let scene = if e <= 0.5 { e * e / 3.0 } else { ((e - HLG_C) / HLG_A).exp() / 12.0 + HLG_B / 12.0 };
+                scene.powf(HLG_GAMMA)
+            }
+            TransferFunction::Power(g) => e.powf(*g),
+            TransferFunction::Parametric { g, a, b, c, d, e: offset, f } => {
+                if e >= *d {
+                    (a * e + b).max(0.0).powf(*g) + offset
+                } else {
+                    c * e + f
+                }
+            }
+            TransferFunction::Sampled(table) => sample_table(table, e),
+        }
+    }
+
+    /// Encodes linear light, clamped to `0..=1`.
+    pub fn from_linear(&self, l: f64) -> f64 {
+        let l = l.clamp(0.0, 1.0);
+        match self {
+            TransferFunction::Srgb => {
+                if l <= 0.003_130_8 {
+                    l * 12.92
+                } else {
+                    1.055 * l.powf(1.0 / 2.4) - 0.055
+                }
+            }
+            TransferFunction::Gamma22 => l.powf(1.0 / 2.2),
+            TransferFunction::Gamma28 => l.powf(1.0 / 2.8),
+            TransferFunction::Bt1886 => l.powf(1.0 / 2.4),
+            TransferFunction::St240 => {
+                if l < 0.0228 {
+                    l * 4.0
+                } else {
+                    1.1115 * l.powf(0.45) - 0.1115
+                }
+            }
+            TransferFunction::Linear => l,
+            TransferFunction::Pq => {
+                let p = l.powf(PQ_M1);
+                ((PQ_C1 + PQ_C2 * p) / (1.0 + PQ_C3 * p)).powf(PQ_M2)
+            }
+            TransferFunction::Hlg => {
+                let scene = l.powf(1.0 / HLG_GAMMA);
+                if scene <= 1.0 / 12.0 {
+                    (3.0 * scene).sqrt()
+                } else {
+                    HLG_A * (12.0 * scene - HLG_B).ln() + HLG_C
+                }
+            }
+            TransferFunction::Power(g) => l.powf(1.0 / g),
+            // No closed-form inverse in general; the curves are monotonic, so bisect.
+            TransferFunction::Parametric { .. } | TransferFunction::Sampled(_) => invert_monotonic(|e| self.to_linear(e), l),
+        }
+    }
+}
+// ANCHOR_END: TransferFunction
+
+fn sample_table(table: &[f64], e: f64) -> f64 {
+    match table.len() {
+        0 => e,
+        1 => table[0],
+        n => {
+            let x = e * (n - 1) as f64;
+            let i = (x.floor() as usize).min(n - 2);
+            let t = x - i as f64;
+            table[i] + (table[i + 1] - table[i]) * t
+        }
+    }
+}
+
+fn invert_monotonic(f: impl Fn(f64) -> f64, target: f64) -> f64 {
+    let increasing = f(1.0) >= f(0.0);
+    let (mut lo, mut hi) = (0.0, 1.0);
+    for _ in 0..40 {
+        let mid = 0.5 * (lo + hi);
+        if (f(mid) < target) == increasing {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    0.5 * (lo + hi)
+}
+
+// ANCHOR: ToneMapping
+/// Compresses `rgb`, in units of reference white, from a source headroom to a smaller target
+/// headroom (peak / reference white). Values below a knee at 75 % of the target headroom are
+/// left alone; above it an extended Reinhard curve maps the source peak exactly onto the
+/// target peak. Scaling all channels by the factor of the largest one preserves hue.
+pub fn tone_map(rgb: Vec3, source_headroom: f64, target_headroom: f64) -> Vec3 {
+    let peak = rgb[0].max(rgb[1]).max(rgb[2]);
+    if source_headroom <= target_headroom || peak <= 0.0 {
+        return rgb;
+    }
+    let mapped = tone_map_value(peak, source_headroom, target_headroom);
+    let scale = mapped / peak;
+    [rgb[0] * scale, rgb[1] * scale, rgb[2] * scale]
+}
+
+fn tone_map_value(x: f64, source_headroom: f64, target_headroom: f64) -> f64 {
+    let knee = 0.75 * target_headroom;
+    if x <= knee {
+        return x;
+    }
+    let range = target_headroom - knee;
+    let t = (x - knee) / range;
+    let t_max = (source_headroom - knee) / range;
+    knee + range * (t * (1.0 + t / (t_max * t_max)) / (1.0 + t)).min(1.0)
+}
+// ANCHOR_END: ToneMapping
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: f64, b: f64, eps: f64) {
+        assert!((a - b).abs() <= eps, "{} != {} (eps {})", a, b, eps);
+    }
+
+    #[test]
+    fn test_srgb_matrix_matches_reference() {
+        let m = Primaries::SRGB.rgb_to_xyz();
+        let expected = [[0.4124, 0.3576, 0.1805], [0.2126, 0.7152, 0.0722], [0.0193, 0.1192, 0.9505]];
+        for (row, expected_row) in m.0.iter().zip(expected) {
+            for (v, e) in row.iter().zip(expected_row) {
+                assert_close(*v, e, 1e-3);
+            }
+        }
+        let round_trip = m.inverse().unwrap().mul(&m);
+        for i in 0..3 {
+            for j in 0..3 {
+                assert_close(round_trip.0[i][j], Mat3::IDENTITY.0[i][j], 1e-12);
+            }
+        }
+        // The white point is preserved through adaptation to the PCS.
+        let white = Primaries::SRGB.rgb_to_pcs().apply([1.0; 3]);
+        assert_close(white[0], 0.9642, 1e-3);
+        assert_close(white[2], 0.8249, 1e-3);
+    }
+
+    #[test]
+    fn test_transfer_functions_round_trip() {
+        let curves = [
+            TransferFunction::Srgb,
+            TransferFunction::Gamma22,
+            TransferFunction::Bt1886,
+            TransferFunction::St240,
+            TransferFunction::Pq,
+            TransferFunction::Hlg,
+            TransferFunction::Parametric { g: 2.4, a: 1.0 / 1.055, b: 0.055 / 1.055, c: 1.0 / 12.92, d: 0.04045, e: 0.0, f: 0.0 },
+            TransferFunction::Sampled((0..=255).map(|i| (i as f64 / 255.0).powf(2.2)).collect()),
+        ];
+        for curve in &curves {
+            for i in 0..=20 {
+                let e = i as f64 / 20.0;
+                assert_close(curve.from_linear(curve.to_linear(e)), e, 1e-6);
+            }
+        }
+        // PQ: 100 cd/m² encodes to about 0.508.
+        assert_close(TransferFunction::Pq.from_linear(100.0 / 10_000.0), 0.508, 1e-3);
+        // The ICC parametric form of sRGB equals the named curve.
+        assert_close(curves[6].to_linear(0.5), TransferFunction::Srgb.to_linear(0.5), 1e-12);
+    }
+
+    #[test]
+    fn test_tone_map_curve() {
+        // Identity below the knee and without excess headroom.
+        assert_eq!(tone_map([0.5, 0.2, 0.1], 4.9, 1.0), [0.5, 0.2, 0.1]);
+        assert_eq!(tone_map([3.0, 1.0, 0.0], 2.0, 4.0), [3.0, 1.0, 0.0]);
+        // The source peak lands on the target peak, monotonically, keeping channel ratios.
+        assert_close(tone_map([4.9, 0.0, 0.0], 4.9, 1.0)[0], 1.0, 1e-9);
+        let mut previous = 0.0;
+        for i in 0..=100 {
+            let v = tone_map([i as f64 * 0.049, 0.0, 0.0], 4.9, 1.0)[0];
+            assert!(v >= previous && v <= 1.0);
+            previous = v;
+        }
+        let mapped = tone_map([2.0, 1.0, 0.5], 4.9, 1.0);
+        assert_close(mapped[1] / mapped[0], 0.5, 1e-12);
+    }
+}
@@ -0,0 +1,321 @@
+// novade-system/src/compositor/color/mod.rs
+//! Colour management: per-output colour pipelines and client colour spaces.
+//!
+//! Every output composites in a *composition space*: sRGB for SDR outputs, BT.2020 with the
+//! PQ curve for outputs in HDR mode. Surfaces that describe their content through
+//! wp-color-management are converted into that space while they are sampled, which includes
+//! tone mapping HDR content for SDR outputs. The composited frame then goes through the output
+//! transform, built from the ICC profile configured for the output under `[color.outputs]`.
+//! Both conversions are baked into [`Lut3d`]s that renderers sample on the GPU or the CPU; the
+//! winit backend's GLES renderer does so through [`gles`].
+
+pub mod gles;
+pub mod icc;
+pub mod lut;
+pub mod math;
+pub mod protocol;
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use smithay::backend::renderer::element::Id;
+use smithay::reexports::wayland_server::protocol::wl_surface::WlSurface;
+use smithay::reexports::wayland_server::DisplayHandle;
+use smithay::wayland::compositor::{get_parent, with_states, with_surface_tree_downward, TraversalAction};
+use thiserror::Error;
+use tracing::{info, warn};
+
+pub use icc::IccProfile;
+pub use lut::{ColorTransform, Lut3d};
+pub use math::{Mat3, Primaries, TransferFunction};
+pub use protocol::{ColorProtocolState, ImageDescription, SurfaceColorDescription};
+
+use super::config::OutputColorConfig;
+use super::state::DesktopState;
+
+#[derive(Debug, Error)]
+pub enum ColorError {
+    #[error("Invalid ICC profile: {0}")]
+    InvalidIcc(String),
+    #[error("Unsupported ICC profile: {0}")]
+    UnsupportedIcc(String),
+    #[error("Failed to read ICC profile '{path}': {source}")]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+}
+
+/// How colours outside the target's range are handled.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RenderIntent {
+    /// Tone map highlights the target cannot show; clip out-of-gamut colours.
+    #[default]
+    Perceptual,
+    /// Keep in-range colours exact and clip everything else, highlights included.
+    Relative,
+}
+
+// ANCHOR: ColorSpace
+/// Luminance levels of a colour space in cd/m².
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Luminance {
+    pub min: f64,
+    pub max: f64,
+    /// Level of SDR/diffuse white.
+    pub reference: f64,
+}
+
+impl Luminance {
+    /// Defaults of the wp-color-management protocol per transfer function.
+    pub const SDR: Luminance = Luminance { min: 0.2, max: 80.0, reference: 80.0 };
+    pub const BT1886: Luminance = Luminance { min: 0.01, max: 100.0, reference: 100.0 };
+    pub const PQ: Luminance = Luminance { min: 0.005, max: 10_000.0, reference: 203.0 };
+    pub const HLG: Luminance = Luminance { min: 0.005, max: 1_000.0, reference: 203.0 };
+
+    pub fn default_for(transfer: &TransferFunction) -> Luminance {
+        match transfer {
+            TransferFunction::Pq => Luminance::PQ,
+            TransferFunction::Hlg => Luminance::HLG,
+            TransferFunction::Bt1886 => Luminance::BT1886,
+            _ => Luminance::SDR,
+        }
+    }
+}
+
+/// An RGB colour space: primaries (as a matrix to the D50 connection space), per-channel
+/// transfer functions and luminance levels.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorSpace {
+    /// Linear RGB to D50 XYZ.
+    pub to_pcs: Mat3,
+    pub transfer: [TransferFunction; 3],
+    pub luminance: Luminance,
+}
+
+impl ColorSpace {
+    pub fn from_primaries(primaries: &Primaries, transfer: TransferFunction, luminance: Luminance) -> Self {
+        Self { to_pcs: primaries.rgb_to_pcs(), transfer: [transfer.clone(), transfer.clone(), transfer], luminance }
+    }
+
+    pub fn srgb() -> Self {
+        Self::from_primaries(&Primaries::SRGB, TransferFunction::Srgb, Luminance::SDR)
+    }
+
+    /// BT.2100 with the PQ curve.
+    pub fn bt2100_pq() -> Self {
+        Self::from_primaries(&Primaries::BT2020, TransferFunction::Pq, Luminance::PQ)
+    }
+
+    /// Luminance that linear 1.0 stands for.
+    pub fn unit_nits(&self) -> f64 {
+        self.transfer[0].absolute_peak_nits().unwrap_or(self.luminance.max)
+    }
+
+    /// Peak luminance as a multiple of reference white.
+    pub fn headroom(&self) -> f64 {
+        self.luminance.max / self.luminance.reference
+    }
+
+    pub fn is_hdr(&self) -> bool {
+        self.headroom() > 1.0
+    }
+}
+// ANCHOR_END: ColorSpace
+
+// ANCHOR: OutputColorState
+/// Colour pipeline of one output.
+#[derive(Debug, Clone)]
+pub struct OutputColorState {
+    pub hdr: bool,
+    /// Space the output's content is composited in and that clients are told to prefer.
+    pub composition_space: ColorSpace,
+    /// Composition space to display conversion, `None` when it is an identity.
+    pub output_lut: Option<Arc<Lut3d>>,
+    /// Description of the loaded ICC profile, if any.
+    pub profile: Option<String>,
+    /// Identity of the composition space as announced to clients.
+    pub identity: u32,
+}
+
+impl OutputColorState {
+    pub fn new(output_name: &str, config: &OutputColorConfig, lut_size: usize, identity: u32) -> Self {
+        if config.hdr {
+            if config.icc_profile.is_some() {
+                warn!("Output {}: ICC profiles describe SDR displays and are ignored in HDR mode.", output_name);
+            }
+            let luminance = Luminance { min: 0.005, max: config.max_luminance_nits as f64, reference: config.sdr_white_nits as f64 };
+            let composition_space = ColorSpace::from_primaries(&Primaries::BT2020, TransferFunction::Pq, luminance);
+            return Self { hdr: true, composition_space, output_lut: None, profile: None, identity };
+        }
+
+        let composition_space = ColorSpace::srgb();
+        let profile = config.icc_profile.as_ref().and_then(|path| match IccProfile::load(&expand_home(path)) {
+            Ok(profile) => Some(profile),
+            Err(e) => {
+                warn!("Output {}: not using ICC profile: {}", output_name, e);
+                None
+            }
+        });
+        let output_lut = profile.as_ref().and_then(|profile| {
+            let display = profile.color_space(config.sdr_white_nits as f64);
+            let transform = ColorTransform::new(&composition_space, &display, RenderIntent::Relative)?;
+            (!transform.is_identity()).then(|| Arc::new(transform.bake(lut_size)))
+        });
+        let profile = profile.map(|p| p.description.unwrap_or_else(|| "unnamed profile".to_string()));
+        if let Some(name) = &profile {
+            info!("Output {}: using ICC profile '{}'.", output_name, name);
+        }
+        Self { hdr: false, composition_space, output_lut, profile, identity }
+    }
+}
+// ANCHOR_END: OutputColorState
+
+/// Resolves a leading `~/` against `$HOME`.
+pub(crate) fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), std::env::var_os("HOME")) {
+        (Ok(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+// ANCHOR: ColorManagerState
+pub struct ColorManagerState {
+    outputs: HashMap<String, (OutputColorConfig, OutputColorState)>,
+    /// Baked surface → composition space LUTs, by description identity, intent and output.
+    surface_luts: HashMap<(u32, RenderIntent, String), Arc<Lut3d>>,
+    /// LUT size the baked tables were built with.
+    lut_size: usize,
+    next_identity: u32,
+    pub protocol: ColorProtocolState,
+}
+
+impl ColorManagerState {
+    /// Creates the state and advertises the wp-color-management global.
+    pub fn new(display: &DisplayHandle, lut_size: usize) -> Self {
+        Self {
+            outputs: HashMap::new(),
+            surface_luts: HashMap::new(),
+            lut_size,
+            next_identity: 1,
+            protocol: ColorProtocolState::new(display),
+        }
+    }
+
+    /// A fresh identity for an image description.
+    pub fn next_identity(&mut self) -> u32 {
+        let identity = self.next_identity;
+        self.next_identity = self.next_identity.wrapping_add(1).max(1);
+        identity
+    }
+
+    pub fn output(&self, output_name: &str) -> Option<&OutputColorState> {
+        self.outputs.get(output_name).map(|(_, state)| state)
+    }
+}
+// ANCHOR_END: ColorManagerState
+
+// ANCHOR: DesktopStateColorIntegration
+impl DesktopState {
+    /// Colour pipeline of `output_name`, built from the configuration on first use.
+    pub fn output_color(&mut self, output_name: &str) -> &OutputColorState {
+        if !self.color_manager.outputs.contains_key(output_name) {
+            self.configure_output_color(output_name, false);
+        }
+        self.color_manager.output(output_name).expect("output colour state was just configured")
+    }
+
+    /// (Re)builds the pipeline of `output_name` if its settings changed or `force` is set, telling
+    /// clients about a new composition space.
+    pub fn configure_output_color(&mut self, output_name: &str, force: bool) {
+        let config = self.config.color.outputs.get(output_name).cloned().unwrap_or_default();
+        if !force && self.color_manager.outputs.get(output_name).is_some_and(|(current, _)| *current == config) {
+            return;
+        }
+        let mut state = OutputColorState::new(output_name, &config, self.color_manager.lut_size, 0);
+        // Keep the identity if only the display side (the ICC profile) changed.
+        let space_changed = match self.color_manager.output(output_name) {
+            Some(previous) if previous.composition_space == state.composition_space => {
+                state.identity = previous.identity;
+                false
+            }
+            previous => {
+                state.identity = self.color_manager.next_identity();
+                previous.is_some()
+            }
+        };
+        self.color_manager.surface_luts.retain(|(_, _, output), _| output != output_name);
+        self.color_manager.outputs.insert(output_name.to_string(), (config, state));
+        if space_changed {
+            self.notify_output_color_changed(output_name);
+        }
+        self.schedule_redraw_all();
+    }
+
+    /// Applies changed `[color]` settings to every known output.
+    pub fn reconfigure_color(&mut self) {
+        let lut_size = self.config.color.lut_size as usize;
+        let rebake = lut_size != self.color_manager.lut_size;
+        self.color_manager.lut_size = lut_size;
+        let mut names: Vec<String> = self.color_manager.outputs.keys().cloned().collect();
+        names.extend(self.output_workspaces.keys().filter(|n| !self.color_manager.outputs.contains_key(*n)).cloned());
+        for name in names {
+            self.configure_output_color(&name, rebake);
+        }
+    }
+
+    /// Output a surface is mainly shown on: its window's output, else the primary output.
+    pub fn surface_output_name(&self, surface: &WlSurface) -> Option<String> {
+        let mut root = surface.clone();
+        while let Some(parent) = get_parent(&root) {
+            root = parent;
+        }
+        self.windows
+            .values()
+            .find(|window| window.wl_surface_ref().is_some_and(|s| *s == root))
+            .and_then(|window| window.output_name.read().unwrap().clone())
+            .or_else(|| self.primary_output_name.read().unwrap().clone())
+    }
+
+    /// LUT converting `surface`'s content into the composition space of `output_name`, or
+    /// `None` if the surface has no image description or needs no conversion.
+    pub fn surface_color_transform(&mut self, surface: &WlSurface, output_name: &str) -> Option<Arc<Lut3d>> {
+        let current = with_states(surface, |states| states.cached_state.get::<SurfaceColorDescription>().current().clone());
+        let description = current.description?;
+        let key = (description.identity, current.intent, output_name.to_string());
+        if let Some(lut) = self.color_manager.surface_luts.get(&key) {
+            return Some(lut.clone());
+        }
+        let lut_size = self.color_manager.lut_size;
+        let target = self.output_color(output_name).composition_space.clone();
+        let transform = ColorTransform::new(&description.space, &target, current.intent)?;
+        if transform.is_identity() {
+            return None;
+        }
+        let lut = Arc::new(transform.bake(lut_size));
+        self.color_manager.surface_luts.insert(key, lut.clone());
+        Some(lut)
+    }
+
+    /// LUTs of the window surfaces, subsurfaces included, that need converting for
+    /// `output_name`, keyed by the ids of the surfaces' render elements.
+    pub fn window_color_transforms(&mut self, output_name: &str) -> HashMap<Id, Arc<Lut3d>> {
+        let mut surfaces = Vec::new();
+        for root in self.windows.values().filter_map(|window| window.wl_surface_ref()) {
+            with_surface_tree_downward(
+                root,
+                (),
+                |_, _, _| TraversalAction::DoChildren(()),
+                |surface, _, _| surfaces.push(surface.clone()),
+                |_, _, _| true,
+            );
+        }
+        surfaces
+            .into_iter()
+            .filter_map(|surface| Some((Id::from_wayland_resource(&surface), self.surface_color_transform(&surface, output_name)?)))
+            .collect()
+    }
+}
+// ANCHOR_END: DesktopStateColorIntegration
@@ -0,0 +1,600 @@
+// novade-system/src/compositor/color/protocol.rs
+
+//! Server side of `wp-color-management-v1`.
+//!
+//! Clients build image descriptions from named or custom primaries and transfer functions, or
+//! from an ICC file, and attach them to surfaces. The attachment is double-buffered surface
+//! state ([`SurfaceColorDescription`]) applied on commit. Outputs and surface feedback objects
+//! hand out descriptions of the output's composition space, the only descriptions that support
+//! `get_information`.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::os::unix::fs::FileExt;
+use std::sync::{Arc, Mutex};
+
+use smithay::output::Output;
+use smithay::reexports::wayland_protocols::wp::color_management::v1::server::{
+    wp_color_management_output_v1::{self, WpColorManagementOutputV1},
+    wp_color_management_surface_feedback_v1::{self, WpColorManagementSurfaceFeedbackV1},
+    wp_color_management_surface_v1::{self, WpColorManagementSurfaceV1},
+    wp_color_manager_v1::{self, WpColorManagerV1},
+    wp_image_description_creator_icc_v1::{self, WpImageDescriptionCreatorIccV1},
+    wp_image_description_creator_params_v1::{self, WpImageDescriptionCreatorParamsV1},
+    wp_image_description_info_v1::WpImageDescriptionInfoV1,
+    wp_image_description_v1::{self, WpImageDescriptionV1},
+};
+use smithay::reexports::wayland_server::backend::{ClientId, GlobalId, ObjectId};
+use smithay::reexports::wayland_server::protocol::wl_surface::WlSurface;
+use smithay::reexports::wayland_server::{Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource, WEnum, Weak};
+use smithay::wayland::compositor::{with_states, Cacheable};
+use tracing::{debug, info, warn};
+
+use super::icc::{IccProfile, MAX_ICC_SIZE};
+use super::math::{self, Chromaticity, TransferFunction};
+use super::{ColorSpace, Luminance, RenderIntent};
+use crate::compositor::state::DesktopState;
+
+use wp_color_manager_v1::{Feature, Primaries as NamedPrimaries, RenderIntent as WpRenderIntent, TransferFunction as NamedTransfer};
+
+const VERSION: u32 = 1;
+
+/// Transfer functions clients may name, and their pipeline equivalents.
+const SUPPORTED_TRANSFER: [NamedTransfer; 8] = [
+    NamedTransfer::Srgb,
+    NamedTransfer::Gamma22,
+    NamedTransfer::Gamma28,
+    NamedTransfer::Bt1886,
+    NamedTransfer::St240,
+    NamedTransfer::ExtLinear,
+    NamedTransfer::St2084Pq,
+    NamedTransfer::Hlg,
+];
+
+const SUPPORTED_PRIMARIES: [NamedPrimaries; 10] = [
+    NamedPrimaries::Srgb,
+    NamedPrimaries::PalM,
+    NamedPrimaries::Pal,
+    NamedPrimaries::Ntsc,
+    NamedPrimaries::GenericFilm,
+    NamedPrimaries::Bt2020,
+    NamedPrimaries::Cie1931Xyz,
+    NamedPrimaries::DciP3,
+    NamedPrimaries::DisplayP3,
+    NamedPrimaries::AdobeRgb,
+];
+
+fn transfer_from_named(tf: NamedTransfer) -> Option<TransferFunction> {
+    Some(match tf {
+        NamedTransfer::Srgb => TransferFunction::Srgb,
+        NamedTransfer::Gamma22 => TransferFunction::Gamma22,
+        NamedTransfer::Gamma28 => TransferFunction::Gamma28,
+        NamedTransfer::Bt1886 => TransferFunction::Bt1886,
+        NamedTransfer::St240 => TransferFunction::St240,
+        NamedTransfer::ExtLinear => TransferFunction::Linear,
+        NamedTransfer::St2084Pq => TransferFunction::Pq,
+        NamedTransfer::Hlg => TransferFunction::Hlg,
+        _ => return None,
+    })
+}
+
+fn primaries_from_named(primaries: NamedPrimaries) -> Option<math::Primaries> {
+    Some(match primaries {
+        NamedPrimaries::Srgb => math::Primaries::SRGB,
+        NamedPrimaries::PalM => math::Primaries::PAL_M,
+        NamedPrimaries::Pal => math::Primaries::PAL,
+        NamedPrimaries::Ntsc => math::Primaries::NTSC,
+        NamedPrimaries::GenericFilm => math::Primaries::GENERIC_FILM,
+        NamedPrimaries::Bt2020 => math::Primaries::BT2020,
+        NamedPrimaries::Cie1931Xyz => math::Primaries::CIE1931_XYZ,
+        NamedPrimaries::DciP3 => math::Primaries::DCI_P3,
+        NamedPrimaries::DisplayP3 => math::Primaries::DISPLAY_P3,
+        NamedPrimaries::AdobeRgb => math::Primaries::ADOBE_RGB,
+        _ => return None,
+    })
+}
+
+/// Chromaticity from the protocol's fixed-point encoding (x·1 000 000).
+fn chromaticity(x: i32, y: i32) -> Chromaticity {
+    Chromaticity::new(x as f64 / 1_000_000.0, y as f64 / 1_000_000.0)
+}
+
+fn encode_chromaticity(c: Chromaticity) -> (i32, i32) {
+    ((c.x * 1_000_000.0).round() as i32, (c.y * 1_000_000.0).round() as i32)
+}
+
+// ANCHOR: ImageDescription
+/// Named parameters of a description that can be reported through `get_information`.
+#[derive(Debug, Clone, PartialEq)]
+struct DescriptionInfo {
+    primaries: math::Primaries,
+    primaries_named: Option<NamedPrimaries>,
+    transfer: NamedTransfer,
+    luminance: Luminance,
+}
+
+/// A ready `wp_image_description_v1`.
+#[derive(Debug)]
+pub struct ImageDescription {
+    pub identity: u32,
+    pub space: ColorSpace,
+    info: Option<DescriptionInfo>,
+}
+
+/// User data of `wp_image_description_v1`; `None` for descriptions that failed.
+pub struct ImageDescriptionData(Option<Arc<ImageDescription>>);
+
+/// Double-buffered image description of a surface.
+#[derive(Debug, Clone, Default)]
+pub struct SurfaceColorDescription {
+    pub description: Option<Arc<ImageDescription>>,
+    pub intent: RenderIntent,
+}
+
+impl Cacheable for SurfaceColorDescription {
+    fn commit(&mut self, _dh: &DisplayHandle) -> Self {
+        self.clone()
+    }
+
+    fn merge_into(self, into: &mut Self, _dh: &DisplayHandle) {
+        *into = self;
+    }
+}
+// ANCHOR_END: ImageDescription
+
+// ANCHOR: ColorProtocolState
+/// Protocol objects that receive events when an output's colour pipeline changes.
+pub struct ColorProtocolState {
+    _global: GlobalId,
+    outputs: Vec<(WpColorManagementOutputV1, String)>,
+    feedbacks: Vec<(WpColorManagementSurfaceFeedbackV1, Weak<WlSurface>)>,
+    /// Colour management surface objects by `wl_surface`; a surface may have only one.
+    surfaces: HashMap<ObjectId, WpColorManagementSurfaceV1>,
+}
+
+impl ColorProtocolState {
+    pub fn new(display: &DisplayHandle) -> Self {
+        let global = display.create_global::<DesktopState, WpColorManagerV1, ()>(VERSION, ());
+        Self { _global: global, outputs: Vec::new(), feedbacks: Vec::new(), surfaces: HashMap::new() }
+    }
+}
+// ANCHOR_END: ColorProtocolState
+
+/// Parameters collected by a `wp_image_description_creator_params_v1`.
+#[derive(Debug, Default)]
+pub struct ParametricBuilder {
+    transfer: Option<(TransferFunction, Option<NamedTransfer>)>,
+    primaries: Option<(math::Primaries, Option<NamedPrimaries>)>,
+    luminance: Option<Luminance>,
+    mastering_primaries: Option<math::Primaries>,
+    mastering_luminance: Option<(f64, f64)>,
+    max_cll: Option<u32>,
+    max_fall: Option<u32>,
+}
+
+impl ParametricBuilder {
+    /// The colour space, or `None` if the transfer function or primaries are missing.
+    fn build(&self) -> Option<ColorSpace> {
+        let (transfer, _) = self.transfer.clone()?;
+        let (primaries, _) = self.primaries?;
+        let mut luminance = self.luminance.unwrap_or_else(|| Luminance::default_for(&transfer));
+        // The mastering peak (or the content light level within it) bounds what tone mapping
+        // has to compress.
+        if let Some((_, mastering_max)) = self.mastering_luminance {
+            luminance.max = luminance.max.min(mastering_max);
+        }
+        if let Some(max_cll) = self.max_cll.filter(|cll| *cll > 0) {
+            luminance.max = luminance.max.min(max_cll as f64);
+        }
+        // Content never peaks below its own reference white.
+        luminance.max = luminance.max.max(luminance.reference);
+        Some(ColorSpace::from_primaries(&primaries, transfer, luminance))
+    }
+}
+
+impl DesktopState {
+    /// The composition space of `output_name` as a ready image description with information.
+    fn output_image_description(&mut self, output_name: &str) -> Arc<ImageDescription> {
+        let state = self.output_color(output_name);
+        let (primaries, primaries_named, transfer) = if state.hdr {
+            (math::Primaries::BT2020, NamedPrimaries::Bt2020, NamedTransfer::St2084Pq)
+        } else {
+            (math::Primaries::SRGB, NamedPrimaries::Srgb, NamedTransfer::Srgb)
+        };
+        let info = DescriptionInfo { primaries, primaries_named: Some(primaries_named), transfer, luminance: state.composition_space.luminance };
+        Arc::new(ImageDescription { identity: state.identity, space: state.composition_space.clone(), info: Some(info) })
+    }
+
+    /// Tells clients that `output_name` switched composition space.
+    pub fn notify_output_color_changed(&mut self, output_name: &str) {
+        let protocol = &mut self.color_manager.protocol;
+        protocol.outputs.retain(|(resource, _)| resource.is_alive());
+        protocol.feedbacks.retain(|(resource, surface)| resource.is_alive() && surface.upgrade().is_ok());
+        for (resource, _) in protocol.outputs.iter().filter(|(_, name)| name == output_name) {
+            resource.image_description_changed();
+        }
+        let feedbacks: Vec<(WpColorManagementSurfaceFeedbackV1, WlSurface)> =
+            protocol.feedbacks.iter().filter_map(|(resource, surface)| surface.upgrade().ok().map(|s| (resource.clone(), s))).collect();
+        let identity = self.output_color(output_name).identity;
+        for (resource, surface) in feedbacks {
+            if self.surface_output_name(&surface).as_deref() == Some(output_name) {
+                resource.preferred_changed(identity);
+            }
+        }
+    }
+
+    fn preferred_image_description(&mut self, surface: &WlSurface) -> Arc<ImageDescription> {
+        let output_name = self.surface_output_name(surface).unwrap_or_default();
+        self.output_image_description(&output_name)
+    }
+}
+
+fn ready(data_init: &mut DataInit<'_, DesktopState>, id: New<WpImageDescriptionV1>, description: Arc<ImageDescription>) {
+    let identity = description.identity;
+    let resource = data_init.init(id, ImageDescriptionData(Some(description)));
+    resource.ready(identity);
+}
+
+fn failed(data_init: &mut DataInit<'_, DesktopState>, id: New<WpImageDescriptionV1>, cause: wp_image_description_v1::Cause, message: String) {
+    warn!("Image description failed: {}", message);
+    let resource = data_init.init(id, ImageDescriptionData(None));
+    resource.failed(cause, message);
+}
+
+// ANCHOR: ColorManagerDispatch
+impl GlobalDispatch<WpColorManagerV1, ()> for DesktopState {
+    fn bind(_state: &mut Self, _dh: &DisplayHandle, _client: &Client, resource: New<WpColorManagerV1>, _data: &(), data_init: &mut DataInit<'_, Self>) {
+        let manager = data_init.init(resource, ());
+        manager.supported_intent(WpRenderIntent::Perceptual);
+        manager.supported_intent(WpRenderIntent::Relative);
+        for feature in [Feature::IccV2V4, Feature::Parametric, Feature::SetPrimaries, Feature::SetTfPower, Feature::SetLuminances, Feature::SetMasteringDisplayPrimaries] {
+            manager.supported_feature(feature);
+        }
+        for tf in SUPPORTED_TRANSFER {
+            manager.supported_tf_named(tf);
+        }
+        for primaries in SUPPORTED_PRIMARIES {
+            manager.supported_primaries_named(primaries);
+        }
+        manager.done();
+    }
+}
+
+impl Dispatch<WpColorManagerV1, ()> for DesktopState {
+    fn request(state: &mut Self, _client: &Client, manager: &WpColorManagerV1, request: wp_color_manager_v1::Request, _data: &(), _dh: &DisplayHandle, data_init: &mut DataInit<'_, Self>) {
+        match request {
+            wp_color_manager_v1::Request::GetOutput { id, output } => {
+                let output_name = Output::from_resource(&output).map(|o| o.name()).unwrap_or_default();
+                let resource = data_init.init(id, output_name.clone());
+                state.color_manager.protocol.outputs.push((resource, output_name));
+            }
+            wp_color_manager_v1::Request::GetSurface { id, surface } => {
+                let resource = data_init.init(id, surface.downgrade());
+                let surfaces = &mut state.color_manager.protocol.surfaces;
+                if surfaces.get(&surface.id()).is_some_and(|existing| existing.is_alive()) {
+                    manager.post_error(wp_color_manager_v1::Error::SurfaceExists, "wl_surface already has a color management surface");
+                    return;
+                }
+                surfaces.insert(surface.id(), resource);
+            }
+            wp_color_manager_v1::Request::GetSurfaceFeedback { id, surface } => {
+                let resource = data_init.init(id, surface.downgrade());
+                state.color_manager.protocol.feedbacks.push((resource, surface.downgrade()));
+            }
+            wp_color_manager_v1::Request::CreateIccCreator { obj } => {
+                data_init.init(obj, Mutex::new(None::<Vec<u8>>));
+            }
+            wp_color_manager_v1::Request::CreateParametricCreator { obj } => {
+                data_init.init(obj, Mutex::new(ParametricBuilder::default()));
+            }
+            wp_color_manager_v1::Request::CreateWindowsScrgb { image_description } => {
+                data_init.init(image_description, ImageDescriptionData(None));
+                manager.post_error(wp_color_manager_v1::Error::UnsupportedFeature, "windows_scrgb is not supported");
+            }
+            wp_color_manager_v1::Request::Destroy => {}
+            _ => {}
+        }
+    }
+}
+// ANCHOR_END: ColorManagerDispatch
+
+impl Dispatch<WpColorManagementOutputV1, String> for DesktopState {
+    fn request(state: &mut Self, _client: &Client, _resource: &WpColorManagementOutputV1, request: wp_color_management_output_v1::Request, output_name: &String, _dh: &DisplayHandle, data_init: &mut DataInit<'_, Self>) {
+        match request {
+            wp_color_management_output_v1::Request::GetImageDescription { image_description } => {
+                if output_name.is_empty() {
+                    failed(data_init, image_description, wp_image_description_v1::Cause::NoOutput, "the output is gone".into());
+                    return;
+                }
+                let description = state.output_image_description(output_name);
+                ready(data_init, image_description, description);
+            }
+            wp_color_management_output_v1::Request::Destroy => {}
+            _ => {}
+        }
+    }
+
+    fn destroyed(state: &mut Self, _client: ClientId, resource: &WpColorManagementOutputV1, _data: &String) {
+        state.color_manager.protocol.outputs.retain(|(r, _)| r != resource);
+    }
+}
+
+// ANCHOR: ColorSurfaceDispatch
+impl Dispatch<WpColorManagementSurfaceV1, Weak<WlSurface>> for DesktopState {
+    fn request(_state: &mut Self, _client: &Client, resource: &WpColorManagementSurfaceV1, request: wp_color_management_surface_v1::Request, surface: &Weak<WlSurface>, _dh: &DisplayHandle, _data_init: &mut DataInit<'_, Self>) {
+        let Ok(surface) = surface.upgrade() else {
+            if !matches!(request, wp_color_management_surface_v1::Request::Destroy) {
+                resource.post_error(wp_color_management_surface_v1::Error::Inert, "the wl_surface was destroyed");
+            }
+            return;
+        };
+        let pending = match request {
+            wp_color_management_surface_v1::Request::SetImageDescription { image_description, render_intent } => {
+                let intent = match render_intent {
+                    WEnum::Value(WpRenderIntent::Perceptual) => RenderIntent::Perceptual,
+                    WEnum::Value(WpRenderIntent::Relative) => RenderIntent::Relative,
+                    other => {
+                        resource.post_error(wp_color_management_surface_v1::Error::RenderIntent, format!("unsupported render intent {:?}", other));
+                        return;
+                    }
+                };
+                let Some(description) = image_description.data::<ImageDescriptionData>().and_then(|data| data.0.clone()) else {
+                    resource.post_error(wp_color_management_surface_v1::Error::ImageDescription, "the image description is not ready");
+                    return;
+                };
+                debug!(surface = ?surface.id(), identity = description.identity, "Surface image description set");
+                SurfaceColorDescription { description: Some(description), intent }
+            }
+            wp_color_management_surface_v1::Request::UnsetImageDescription => SurfaceColorDescription::default(),
+            _ => return,
+        };
+        with_states(&surface, |states| *states.cached_state.get::<SurfaceColorDescription>().pending() = pending);
+    }
+
+    fn destroyed(state: &mut Self, _client: ClientId, resource: &WpColorManagementSurfaceV1, surface: &Weak<WlSurface>) {
+        // Destroying the object unsets the description on the next commit.
+        if let Ok(surface) = surface.upgrade() {
+            with_states(&surface, |states| *states.cached_state.get::<SurfaceColorDescription>().pending() = SurfaceColorDescription::default());
+        }
+        state.color_manager.protocol.surfaces.retain(|_, r| r != resource);
+    }
+}
+// ANCHOR_END: ColorSurfaceDispatch
+
+impl Dispatch<WpColorManagementSurfaceFeedbackV1, Weak<WlSurface>> for DesktopState {
+    fn request(state: &mut Self, _client: &Client, resource: &WpColorManagementSurfaceFeedbackV1, request: wp_color_management_surface_feedback_v1::Request, surface: &Weak<WlSurface>, _dh: &DisplayHandle, data_init: &mut DataInit<'_, Self>) {
+        let image_description = match request {
+            wp_color_management_surface_feedback_v1::Request::GetPreferred { image_description } => image_description,
+            wp_color_management_surface_feedback_v1::Request::GetPreferredParametric { image_description } => image_description,
+            _ => return,
+        };
+        match surface.upgrade() {
+            Ok(surface) => {
+                let description = state.preferred_image_description(&surface);
+                ready(data_init, image_description, description);
+            }
+            Err(_) => {
+                data_init.init(image_description, ImageDescriptionData(None));
+                resource.post_error(wp_color_management_surface_feedback_v1::Error::Inert, "the wl_surface was destroyed");
+            }
+        }
+    }
+
+    fn destroyed(state: &mut Self, _client: ClientId, resource: &WpColorManagementSurfaceFeedbackV1, _data: &Weak<WlSurface>) {
+        state.color_manager.protocol.feedbacks.retain(|(r, _)| r != resource);
+    }
+}
+
+// ANCHOR: ImageDescriptionCreators
+impl Dispatch<WpImageDescriptionCreatorIccV1, Mutex<Option<Vec<u8>>>> for DesktopState {
+    fn request(state: &mut Self, _client: &Client, resource: &WpImageDescriptionCreatorIccV1, request: wp_image_description_creator_icc_v1::Request, data: &Mutex<Option<Vec<u8>>>, _dh: &DisplayHandle, data_init: &mut DataInit<'_, Self>) {
+        use wp_image_description_creator_icc_v1::Error;
+        match request {
+            wp_image_description_creator_icc_v1::Request::SetIccFile { icc_profile, offset, length } => {
+                let mut icc = data.lock().unwrap();
+                if icc.is_some() {
+                    resource.post_error(Error::AlreadySet, "the ICC file was already set");
+                    return;
+                }
+                if length == 0 || length as usize > MAX_ICC_SIZE {
+                    resource.post_error(Error::BadSize, format!("ICC file length {} is out of range", length));
+                    return;
+                }
+                let file = File::from(icc_profile);
+                let mut bytes = vec![0u8; length as usize];
+                match file.read_exact_at(&mut bytes, offset as u64) {
+                    Ok(()) => *icc = Some(bytes),
+                    Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                        resource.post_error(Error::OutOfFile, "offset and length exceed the file");
+                    }
+                    Err(e) => resource.post_error(Error::BadFd, format!("cannot read the ICC file: {}", e)),
+                }
+            }
+            wp_image_description_creator_icc_v1::Request::Create { image_description } => {
+                let Some(bytes) = data.lock().unwrap().take() else {
+                    data_init.init(image_description, ImageDescriptionData(None));
+                    resource.post_error(Error::IncompleteSet, "no ICC file was set");
+                    return;
+                };
+                match IccProfile::parse(&bytes) {
+                    Ok(profile) => {
+                        let identity = state.color_manager.next_identity();
+                        let space = profile.color_space(Luminance::SDR.reference);
+                        ready(data_init, image_description, Arc::new(ImageDescription { identity, space, info: None }));
+                    }
+                    Err(e) => failed(data_init, image_description, wp_image_description_v1::Cause::Unsupported, e.to_string()),
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<WpImageDescriptionCreatorParamsV1, Mutex<ParametricBuilder>> for DesktopState {
+    fn request(state: &mut Self, _client: &Client, resource: &WpImageDescriptionCreatorParamsV1, request: wp_image_description_creator_params_v1::Request, data: &Mutex<ParametricBuilder>, _dh: &DisplayHandle, data_init: &mut DataInit<'_, Self>) {
+        use wp_image_description_creator_params_v1::{Error, Request};
+        let mut builder = data.lock().unwrap();
+        let already_set = |set: bool, what: &str| {
+            if set {
+                resource.post_error(Error::AlreadySet, format!("{} was already set", what));
+            }
+            set
+        };
+        match request {
+            Request::SetTfNamed { tf } => {
+                if already_set(builder.transfer.is_some(), "the transfer function") {
+                    return;
+                }
+                match tf.into_result().ok().and_then(|named| transfer_from_named(named).map(|tf| (tf, Some(named)))) {
+                    Some(transfer) => builder.transfer = Some(transfer),
+                    None => resource.post_error(Error::InvalidTf, "unsupported transfer function"),
+                }
+            }
+            Request::SetTfPower { eexp } => {
+                if already_set(builder.transfer.is_some(), "the transfer function") {
+                    return;
+                }
+                if !(10_000..=100_000).contains(&eexp) {
+                    resource.post_error(Error::InvalidTf, format!("power exponent {} is outside 1.0..=10.0", eexp as f64 / 10_000.0));
+                    return;
+                }
+                builder.transfer = Some((TransferFunction::Power(eexp as f64 / 10_000.0), None));
+            }
+            Request::SetPrimariesNamed { primaries } => {
+                if already_set(builder.primaries.is_some(), "the primaries") {
+                    return;
+                }
+                match primaries.into_result().ok().and_then(|named| primaries_from_named(named).map(|p| (p, Some(named)))) {
+                    Some(primaries) => builder.primaries = Some(primaries),
+                    None => resource.post_error(Error::InvalidPrimariesNamed, "unsupported primaries"),
+                }
+            }
+            Request::SetPrimaries { r_x, r_y, g_x, g_y, b_x, b_y, w_x, w_y } => {
+                if already_set(builder.primaries.is_some(), "the primaries") {
+                    return;
+                }
+                let primaries = math::Primaries { red: chromaticity(r_x, r_y), green: chromaticity(g_x, g_y), blue: chromaticity(b_x, b_y), white: chromaticity(w_x, w_y) };
+                builder.primaries = Some((primaries, None));
+            }
+            Request::SetLuminances { min_lum, max_lum, reference_lum } => {
+                if already_set(builder.luminance.is_some(), "the luminances") {
+                    return;
+                }
+                let min = min_lum as f64 / 10_000.0;
+                if max_lum as f64 <= min || reference_lum as f64 <= min {
+                    resource.post_error(Error::InvalidLuminance, "max and reference luminance must exceed the minimum");
+                    return;
+                }
+                builder.luminance = Some(Luminance { min, max: max_lum as f64, reference: reference_lum as f64 });
+            }
+            Request::SetMasteringDisplayPrimaries { r_x, r_y, g_x, g_y, b_x, b_y, w_x, w_y } => {
+                if already_set(builder.mastering_primaries.is_some(), "the mastering display primaries") {
+                    return;
+                }
+                builder.mastering_primaries = Some(math::Primaries { red: chromaticity(r_x, r_y), green: chromaticity(g_x, g_y), blue: chromaticity(b_x, b_y), white: chromaticity(w_x, w_y) });
+            }
+            Request::SetMasteringLuminance { min_lum, max_lum } => {
+                if already_set(builder.mastering_luminance.is_some(), "the mastering luminance") {
+                    return;
+                }
+                let min = min_lum as f64 / 10_000.0;
+                if max_lum as f64 <= min {
+                    resource.post_error(Error::InvalidLuminance, "mastering max luminance must exceed the minimum");
+                    return;
+                }
+                builder.mastering_luminance = Some((min, max_lum as f64));
+            }
+            Request::SetMaxCll { max_cll } => {
+                if !already_set(builder.max_cll.is_some(), "max_cll") {
+                    builder.max_cll = Some(max_cll);
+                }
+            }
+            Request::SetMaxFall { max_fall } => {
+                if !already_set(builder.max_fall.is_some(), "max_fall") {
+                    builder.max_fall = Some(max_fall);
+                }
+            }
+            Request::Create { image_description } => match builder.build() {
+                Some(space) => {
+                    let identity = state.color_manager.next_identity();
+                    info!(identity, "Parametric image description created");
+                    ready(data_init, image_description, Arc::new(ImageDescription { identity, space, info: None }));
+                }
+                None => {
+                    data_init.init(image_description, ImageDescriptionData(None));
+                    resource.post_error(Error::IncompleteSet, "primaries and transfer function are required");
+                }
+            },
+            _ => {}
+        }
+    }
+}
+// ANCHOR_END: ImageDescriptionCreators
+
+impl Dispatch<WpImageDescriptionV1, ImageDescriptionData> for DesktopState {
+    fn request(_state: &mut Self, _client: &Client, resource: &WpImageDescriptionV1, request: wp_image_description_v1::Request, data: &ImageDescriptionData, _dh: &DisplayHandle, data_init: &mut DataInit<'_, Self>) {
+        let wp_image_description_v1::Request::GetInformation { information } = request else {
+            return;
+        };
+        let info = data.0.as_ref().map(|d| d.info.clone());
+        let info_resource = data_init.init(information, ());
+        let info = match info {
+            None => return resource.post_error(wp_image_description_v1::Error::NotReady, "the image description failed"),
+            Some(None) => return resource.post_error(wp_image_description_v1::Error::NoInformation, "the image description has no information"),
+            Some(Some(info)) => info,
+        };
+        let p = info.primaries;
+        let [(r_x, r_y), (g_x, g_y), (b_x, b_y), (w_x, w_y)] = [p.red, p.green, p.blue, p.white].map(encode_chromaticity);
+        info_resource.primaries(r_x, r_y, g_x, g_y, b_x, b_y, w_x, w_y);
+        if let Some(named) = info.primaries_named {
+            info_resource.primaries_named(named);
+        }
+        info_resource.tf_named(info.transfer);
+        let l = info.luminance;
+        let min_lum = (l.min * 10_000.0).round() as u32;
+        info_resource.luminances(min_lum, l.max.round() as u32, l.reference.round() as u32);
+        info_resource.target_primaries(r_x, r_y, g_x, g_y, b_x, b_y, w_x, w_y);
+        info_resource.target_luminance(min_lum, l.max.round() as u32);
+        info_resource.done();
+    }
+}
+
+impl Dispatch<WpImageDescriptionInfoV1, ()> for DesktopState {
+    fn request(_state: &mut Self, _client: &Client, _resource: &WpImageDescriptionInfoV1, _request: <WpImageDescriptionInfoV1 as Resource>::Request, _data: &(), _dh: &DisplayHandle, _data_init: &mut DataInit<'_, Self>) {
+        // The info object has no requests; it is destroyed by its `done` event.
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_named_values_map_to_pipeline() {
+        for tf in SUPPORTED_TRANSFER {
+            assert!(transfer_from_named(tf).is_some(), "{:?}", tf);
+        }
+        for primaries in SUPPORTED_PRIMARIES {
+            assert!(primaries_from_named(primaries).is_some(), "{:?}", primaries);
+        }
+        assert_eq!(transfer_from_named(NamedTransfer::Log100), None);
+        let (x, y) = encode_chromaticity(chromaticity(312_700, 329_000));
+        assert_eq!((x, y), (312_700, 329_000));
+    }
+
+    #[test]
+    fn test_parametric_builder() {
+        let mut builder = ParametricBuilder::default();
+        assert!(builder.build().is_none());
+        builder.transfer = Some((TransferFunction::Pq, Some(NamedTransfer::St2084Pq)));
+        builder.primaries = Some((math::Primaries::BT2020, Some(NamedPrimaries::Bt2020)));
+        // Defaults follow the transfer function.
+        assert_eq!(builder.build().unwrap().luminance, Luminance::PQ);
+        // Mastering metadata narrows the peak tone mapping has to handle.
+        builder.mastering_luminance = Some((0.001, 4000.0));
+        builder.max_cll = Some(1500);
+        let space = builder.build().unwrap();
+        assert_eq!(space.luminance.max, 1500.0);
+        assert!((space.headroom() - 1500.0 / 203.0).abs() < 1e-9);
+    }
+}
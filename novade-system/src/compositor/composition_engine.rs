@@ -4,15 +4,23 @@
 use crate::renderer_interface::{
     RendererInterface, ClientBuffer, BufferFormat as RendererBufferFormat, RenderableTexture,
     RenderElement, TextureRenderParams, BufferContent, DmabufDescriptor, DmabufPlaneFormat, // Added DMABUF related types
+    BlurRenderParams, ShadowRenderParams,
 };
 use novade_compositor_core::surface::SurfaceId;
 use std::collections::HashMap;
 use super::scene_graph::{
-    SceneGraph, SurfaceAttributes, BufferFormat as SceneGraphBufferFormat,
+    SceneGraph, SceneGraphNode, SurfaceAttributes, BufferFormat as SceneGraphBufferFormat,
     Transform as SceneGraphTransform, BufferSourceType, // Added BufferSourceType
 };
 use novade_core::types::geometry::{Point2D, Size2D, Rect as NovaRect, Rectangle};
 use std::sync::Arc;
+use std::time::Instant;
+use tracing::{error, warn};
+use super::damage::{DamageFlash, DamageRegion, FrameElement, OutputDamageTracker};
+use super::color::Lut3d;
+use super::effects::blur::expand_damage_for_blur;
+use super::effects::shape::shadow_bounds;
+use super::effects::SurfaceEffects;
 
 pub struct CompositionEngine<R: RendererInterface> {
     renderer: R,
@@ -27,6 +35,22 @@ pub struct CompositionEngine<R: RendererInterface> {
     // 4. Re-uploading would occur if a surface becomes visible again after its texture was evicted.
     // ANCHOR [TextureManagementOutlines]
     surface_textures: HashMap<SurfaceId, Box<dyn RenderableTexture>>,
+    // ANCHOR [DamageTrackingState]
+    /// Damage history per output, keyed by output name.
+    damage_trackers: HashMap<String, OutputDamageTracker<SurfaceId>>,
+    /// Buffer-local damage reported by clients and not yet composited, per output.
+    pending_damage: HashMap<String, HashMap<SurfaceId, Vec<Rectangle>>>,
+    /// Debug overlay flashing repainted regions, when enabled.
+    damage_flash: Option<DamageFlash>,
+    // ANCHOR [ColorManagementState]
+    /// Composition space to display conversion per output, from the output's ICC profile.
+    output_color_luts: HashMap<String, Arc<Lut3d>>,
+    /// Conversion of a surface's content into the composition space, for surfaces with an
+    /// image description that differs from it.
+    surface_color_transforms: HashMap<SurfaceId, Arc<Lut3d>>,
+    // ANCHOR [WindowEffectsState]
+    /// Corners, shadow and backdrop blur of surfaces that have any.
+    surface_effects: HashMap<SurfaceId, SurfaceEffects>,
 }
 
 impl<R: RendererInterface> CompositionEngine<R> {
@@ -36,9 +60,78 @@ impl<R: RendererInterface> CompositionEngine<R> {
             scene_graph: SceneGraph::new(),
             active_surfaces: HashMap::new(),
             surface_textures: HashMap::new(), // Initialize new field
+            damage_trackers: HashMap::new(),
+            pending_damage: HashMap::new(),
+            damage_flash: None,
+            output_color_luts: HashMap::new(),
+            surface_color_transforms: HashMap::new(),
+            surface_effects: HashMap::new(),
         }
     }
 
+    /// Sets or clears the colour conversion applied to everything composited on `output_name`.
+    pub fn set_output_color_lut(&mut self, output_name: &str, lut: Option<Arc<Lut3d>>) {
+        match lut {
+            Some(lut) => self.output_color_luts.insert(output_name.to_string(), lut),
+            None => self.output_color_luts.remove(output_name),
+        };
+        if let Some(tracker) = self.damage_trackers.get_mut(output_name) {
+            tracker.damage_all();
+        }
+    }
+
+    /// Sets or clears the conversion of `surface_id`'s content into the composition space.
+    pub fn set_surface_color_transform(&mut self, surface_id: SurfaceId, lut: Option<Arc<Lut3d>>) {
+        match lut {
+            Some(lut) => self.surface_color_transforms.insert(surface_id, lut),
+            None => self.surface_color_transforms.remove(&surface_id),
+        };
+        self.damage_all_outputs();
+    }
+
+    /// Sets the effects drawn with `surface_id`, as resolved by `DesktopState::surface_effects`.
+    pub fn set_surface_effects(&mut self, surface_id: SurfaceId, effects: SurfaceEffects) {
+        let previous = if effects.is_empty() { self.surface_effects.remove(&surface_id) } else { self.surface_effects.insert(surface_id, effects.clone()) };
+        if previous.unwrap_or_default() != effects {
+            self.damage_all_outputs();
+        }
+    }
+
+    /// Records damage a client reported with `wl_surface.damage_buffer`, in buffer coordinates.
+    /// Buffer scale and buffer transform are assumed to be 1 and normal.
+    pub fn damage_surface_buffer(&mut self, surface_id: SurfaceId, damage: &[Rectangle]) {
+        for output_name in self.damage_trackers.keys() {
+            self.pending_damage
+                .entry(output_name.clone())
+                .or_default()
+                .entry(surface_id)
+                .or_default()
+                .extend_from_slice(damage);
+        }
+    }
+
+    /// Forces a full repaint of every output on the next frame.
+    pub fn damage_all_outputs(&mut self) {
+        for tracker in self.damage_trackers.values_mut() {
+            tracker.damage_all();
+        }
+    }
+
+    /// Enables or disables the debug overlay that flashes damaged regions.
+    pub fn set_debug_damage_flash(&mut self, enabled: bool) {
+        if enabled == self.damage_flash.is_some() {
+            return;
+        }
+        self.damage_flash = if enabled { Some(DamageFlash::default()) } else { None };
+        // Erase a fading overlay or start from a clean frame.
+        self.damage_all_outputs();
+    }
+
+    /// Returns `true` while the damage flash overlay still needs frames to fade out.
+    pub fn has_pending_overlay_frames(&self) -> bool {
+        self.damage_flash.as_ref().map_or(false, DamageFlash::is_active)
+    }
+
     pub fn composite_frame(&mut self /*, outputs: &OutputManager */) {
         // In a real system, `active_surfaces` would be populated/updated by Wayland event handlers
         // reacting to wl_surface.commit, xdg_surface.configure, etc.
@@ -46,14 +139,24 @@ impl<R: RendererInterface> CompositionEngine<R> {
 
         // Define example output geometry
         let output_geometry = Rectangle::from_coords(0.0, 0.0, 1920.0, 1080.0); // Example output
+        self.composite_output("default", output_geometry);
+    }
 
+    /// Composites one output, repainting only what changed since the buffer being drawn into
+    /// was last presented. Nothing is rendered or presented when no region is damaged.
+    pub fn composite_output(&mut self, output_name: &str, output_geometry: Rectangle) {
         // 1. Update scene graph using the stored attributes and output geometry
         // ANCHOR [SceneGraphIntegration]
         self.scene_graph.update(&self.active_surfaces, &output_geometry);
 
         let renderable_nodes = self.scene_graph.get_renderable_nodes();
+        let tracker = self
+            .damage_trackers
+            .entry(output_name.to_string())
+            .or_insert_with(|| OutputDamageTracker::new(output_geometry));
+        tracker.set_output_geometry(output_geometry);
 
-        if renderable_nodes.is_empty() {
+        if renderable_nodes.is_empty() && !tracker.has_elements() {
             // Potentially clear the screen or do nothing
             // self.renderer.clear_screen(); // Example
             // self.renderer.present();
@@ -61,6 +164,58 @@ impl<R: RendererInterface> CompositionEngine<R> {
             return;
         }
 
+        // ANCHOR [DamageTracking]
+        // Client damage is moved from buffer space into output space with the node's transform.
+        // Occluded nodes are left out: when they become visible they count as new and are repainted.
+        // A node's shadow belongs to it, so moving a window repaints where its shadow was.
+        let client_damage = self.pending_damage.remove(output_name).unwrap_or_default();
+        let frame_elements: Vec<FrameElement<SurfaceId>> = renderable_nodes
+            .iter()
+            .filter_map(|node| {
+                let shadow = shadow_rect(node, self.surface_effects.get(&node.surface_id));
+                if node.is_occluded && shadow.is_none() {
+                    return None;
+                }
+                let mut damage = DamageRegion::new();
+                for rect in client_damage.get(&node.surface_id).into_iter().flatten() {
+                    damage.add(node.final_transform.transform_rect_bounding_box(*rect));
+                }
+                let geometry = shadow
+                    .and_then(|shadow| shadow.union(&node.clipped_rect).intersection(&output_geometry))
+                    .unwrap_or(node.clipped_rect);
+                Some(FrameElement { key: node.surface_id, geometry, damage })
+            })
+            .collect();
+
+        let now = Instant::now();
+        if let Some(flash) = self.damage_flash.as_mut() {
+            tracker.add_overlay_damage(&flash.take_repaint(now));
+        }
+        let frame_damage = tracker.record_frame(frame_elements);
+        if let Some(flash) = self.damage_flash.as_mut() {
+            flash.push(now, &frame_damage);
+        }
+        let mut repaint = tracker.repaint_region(self.renderer.buffer_age());
+        // ANCHOR [BlurDamageExpansion]
+        // Blurred pixels depend on their surroundings, see `expand_damage_for_blur`.
+        let blur_areas: Vec<(NovaRect<f32>, f32)> = renderable_nodes
+            .iter()
+            .filter(|node| !node.is_occluded)
+            .filter_map(|node| {
+                let blur = self.surface_effects.get(&node.surface_id)?.blur.as_ref()?;
+                Some((node.clipped_rect, blur.style.reach()))
+            })
+            .collect();
+        if !repaint.is_empty() {
+            expand_damage_for_blur(&mut repaint, &blur_areas, &output_geometry);
+        }
+        if repaint.is_empty() {
+            // Idle output (or only off-screen changes): keep the last frame on screen.
+            tracing::trace!("Composition: Output {} has no damage, skipping repaint.", output_name);
+            return;
+        }
+        let full_repaint = repaint.rects() == [output_geometry];
+
         // ANCHOR [TextureUploadPipeline]
         // TODO [TextureStreaming] Implement more sophisticated texture streaming.
         // TODO [LRUEviction] Implement LRU eviction for textures when GPU memory is constrained.
@@ -68,15 +223,36 @@ impl<R: RendererInterface> CompositionEngine<R> {
         // TODO [EfficientBufferUpdateDetection] Only upload textures if buffer_id has changed or texture doesn't exist.
 
         let mut visible_nodes_for_render_list: Vec<Arc<SceneGraphNode>> = Vec::new();
+        let mut visible_rects: HashMap<SurfaceId, Vec<NovaRect<f32>>> = HashMap::new();
 
         for node in renderable_nodes.iter() {
+            // A shadow can reach into damage its surface does not touch, even from under other
+            // windows; such nodes are drawn without their texture.
+            let shadow_damaged = shadow_rect(node, self.surface_effects.get(&node.surface_id)).map_or(false, |shadow| repaint.intersects(&shadow));
             if node.is_occluded {
                 // If node is occluded, and we previously had a texture for it,
                 // this might be a place to consider releasing it (if not done by LRU).
                 // For now, we just skip it.
                 // self.surface_textures.remove(&node.surface_id); // Example of immediate release
+                if shadow_damaged {
+                    visible_nodes_for_render_list.push(node.clone());
+                }
+                continue;
+            }
+            // Only the unoccluded parts inside the damaged region are drawn; the rest of the
+            // buffer is either covered or still valid.
+            let rects: Vec<NovaRect<f32>> = repaint
+                .rects()
+                .iter()
+                .flat_map(|damage| node.visible_region.intersect_rect(damage).rects().to_vec())
+                .collect();
+            if rects.is_empty() {
+                if shadow_damaged {
+                    visible_nodes_for_render_list.push(node.clone());
+                }
                 continue;
             }
+            visible_rects.insert(node.surface_id, rects);
 
             visible_nodes_for_render_list.push(node.clone()); // Keep a list of nodes that will actually be rendered
 
@@ -187,7 +363,7 @@ impl<R: RendererInterface> CompositionEngine<R> {
                             self.surface_textures.insert(node.surface_id, texture_handle);
                         }
                         Err(e) => {
-                            eprintln!("Failed to upload texture for surface {:?}, buffer_id: {}: {:?}", node.surface_id, buffer_id, e);
+                            warn!("Failed to upload texture for surface {:?}, buffer_id: {}: {:?}", node.surface_id, buffer_id, e);
                         }
                     }
                 } else {
@@ -211,6 +387,22 @@ impl<R: RendererInterface> CompositionEngine<R> {
         for node_arc in visible_nodes_for_render_list.iter() { // Iterate over the collected visible nodes
             let node = &**node_arc; // Dereference Arc<SceneGraphNode> to &SceneGraphNode
 
+            // ANCHOR [WindowEffects]
+            // Blur first, so it does not pick up the surface's own shadow.
+            let effects = self.surface_effects.get(&node.surface_id).cloned().unwrap_or_default();
+            let geometry = surface_geometry(node);
+            let visible_region = visible_rects.remove(&node.surface_id);
+            if let (Some(blur), Some(_)) = (&effects.blur, &visible_region) {
+                let region = blur.region.iter().flatten().map(|rect| node.final_transform.transform_rect_bounding_box(*rect)).collect();
+                render_elements_list.push(RenderElement::BackdropBlur(BlurRenderParams { geometry, corner_radius: effects.corner_radius, region, style: blur.style }));
+            }
+            if let Some(style) = effects.shadow {
+                render_elements_list.push(RenderElement::Shadow(ShadowRenderParams { geometry, corner_radius: effects.corner_radius, style }));
+            }
+            let Some(visible_region) = visible_region else {
+                continue; // Only the shadow is damaged.
+            };
+
             if let Some(texture) = self.surface_textures.remove(&node.surface_id) { // Take ownership
                 let params = TextureRenderParams {
                     texture, // Pass the owned Box<dyn RenderableTexture>
@@ -222,15 +414,35 @@ impl<R: RendererInterface> CompositionEngine<R> {
                     // source_rect: NovaRect::new(0.0, 0.0, node.attributes.size.width, node.attributes.size.height),
                     // But for normalized, it's:
                     source_rect: NovaRect::new(0.0, 0.0, 1.0, 1.0),
+                    visible_region,
+                    // Buffer scale is 1, so the radius is in texels too.
+                    corner_radius: effects.corner_radius,
+                    color_transform: self.surface_color_transforms.get(&node.surface_id).cloned(),
                 };
                 render_elements_list.push(RenderElement::TextureNode(params));
             } else {
                 // This case should ideally not happen if texture upload was successful for all visible nodes.
                 // It might happen if a node became visible but its texture upload failed or was skipped.
-                eprintln!("Texture not found for visible node {:?}, skipping render.", node.surface_id);
+                warn!("Texture not found for visible node {:?}, skipping render.", node.surface_id);
             }
         }
 
+        // ANCHOR [DamageFlashOverlay]
+        if let Some(flash) = self.damage_flash.as_ref() {
+            for (rect, color) in flash.overlay(now) {
+                render_elements_list.push(RenderElement::SolidColor {
+                    color,
+                    geometry: smithay::utils::Rectangle::from_loc_and_size(
+                        (rect.origin.x.floor() as i32, rect.origin.y.floor() as i32),
+                        (rect.size.width.ceil() as i32, rect.size.height.ceil() as i32),
+                    ),
+                });
+            }
+        }
+
+        // Everything outside the scissor keeps the contents of the reused buffer.
+        self.renderer.set_damage_region(if full_repaint { None } else { Some(repaint.rects()) });
+
         if !render_elements_list.is_empty() {
             println!("Composition: Preparing to render {} elements.", render_elements_list.len());
             // The renderer's render_frame might handle begin_frame/end_frame internally.
@@ -241,13 +453,17 @@ impl<R: RendererInterface> CompositionEngine<R> {
                     // self.renderer.present_frame(); // This will be handled in a later stage/subtask
                 }
                 Err(e) => {
-                    eprintln!("Error during renderer.render_frame: {:?}", e);
+                    error!("Error during renderer.render_frame: {:?}", e);
                 }
             }
         } else if final_nodes_to_render_count > 0 {
              println!("Composition: {} visible nodes, but 0 render elements prepared (likely texture issues).", final_nodes_to_render_count);
+        } else {
+            // The last surfaces went away: render the empty scene so the damaged area is cleared.
+            if let Err(e) = self.renderer.render_frame(render_elements_list, &output_geometry, 1.0) {
+                error!("Error during renderer.render_frame: {:?}", e);
+            }
         }
-        // else: No visible nodes, nothing to render, already printed earlier.
 
         // ANCHOR [PostProcessingPipeline]
         // TODO [PostProcessingConfig]: Make post-processing steps and their parameters configurable.
@@ -256,7 +472,7 @@ impl<R: RendererInterface> CompositionEngine<R> {
         // TODO [GammaValueConfig]: Get gamma from display/user settings.
         const DEFAULT_GAMMA: f32 = 2.2;
         if let Err(e) = self.renderer.apply_gamma_correction(DEFAULT_GAMMA) {
-            eprintln!("Failed to apply gamma correction: {:?}", e);
+            warn!("Failed to apply gamma correction: {:?}", e);
         }
 
         // 2. HDR to SDR Tone Mapping (example call, might be conditional)
@@ -265,16 +481,22 @@ impl<R: RendererInterface> CompositionEngine<R> {
         const DEFAULT_MAX_LUMINANCE: f32 = 1000.0; // Example nits
         const DEFAULT_EXPOSURE: f32 = 1.0;
         if let Err(e) = self.renderer.apply_hdr_to_sdr_tone_mapping(DEFAULT_MAX_LUMINANCE, DEFAULT_EXPOSURE) {
-            eprintln!("Failed to apply tone mapping: {:?}", e);
+            warn!("Failed to apply tone mapping: {:?}", e);
+        }
+
+        // 3. Output colour conversion (ICC profile of the display)
+        if let Some(lut) = self.output_color_luts.get(output_name) {
+            if let Err(e) = self.renderer.apply_color_space_conversion(lut) {
+                warn!("Failed to apply color space conversion: {:?}", e);
+            }
         }
 
-        // TODO [ColorSpaceConversion]: Implement color space conversion step.
         // TODO [AntiAliasing]: Implement anti-aliasing step.
         // TODO [CustomEffects]: Implement custom effects application.
 
         // ANCHOR [FramePresentation]
         if let Err(e) = self.renderer.submit_and_present_frame() {
-            eprintln!("Failed to submit and present frame: {:?}", e);
+            error!("Failed to submit and present frame: {:?}", e);
             // TODO [ErrorHandlingPresentation]: More robust error handling here,
             // potentially re-initialize renderer or mark output as problematic.
         }
@@ -291,10 +513,29 @@ impl<R: RendererInterface> CompositionEngine<R> {
 
     pub fn remove_surface(&mut self, surface_id: SurfaceId) {
         self.active_surfaces.remove(&surface_id);
+        self.surface_color_transforms.remove(&surface_id);
+        self.surface_effects.remove(&surface_id);
+        for pending in self.pending_damage.values_mut() {
+            pending.remove(&surface_id);
+        }
         println!("Surface {:?} removed from composition engine.", surface_id);
     }
 }
 
+/// Unclipped geometry of `node` on the output.
+fn surface_geometry(node: &SceneGraphNode) -> NovaRect<f32> {
+    let size = &node.attributes.size;
+    node.final_transform.transform_rect_bounding_box(Rectangle::from_coords(0.0, 0.0, size.width, size.height))
+}
+
+/// Area on the output the shadow of `node` may paint.
+fn shadow_rect(node: &SceneGraphNode, effects: Option<&SurfaceEffects>) -> Option<NovaRect<f32>> {
+    let effects = effects?;
+    let g = surface_geometry(node);
+    let [x, y, w, h] = shadow_bounds([g.origin.x, g.origin.y, g.size.width, g.size.height], effects.corner_radius, effects.shadow.as_ref()?)?;
+    Some(Rectangle::from_coords(x, y, w, h))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -359,7 +600,10 @@ mod tests {
 
     #[derive(Debug, Clone)]
     enum RenderElementInfo {
-        TextureNode { texture_id: Uuid, transform_matrix: [[f32;3];2] },
+        TextureNode { texture_id: Uuid, transform_matrix: [[f32;3];2], corner_radius: f32 },
+        SolidColor { color: [f32; 4] },
+        Shadow { geometry: Rectangle },
+        BackdropBlur { geometry: Rectangle, region: Vec<Rectangle> },
         // Add other variants if needed by tests
     }
 
@@ -370,9 +614,12 @@ mod tests {
         rendered_elements: Vec<RenderElementInfo>, // Simplified for now
         gamma_correction_calls: Vec<f32>,
         tone_mapping_calls: Vec<(f32, f32)>,
+        color_conversion_calls: Vec<usize>,
         submit_and_present_frame_called_count: usize,
         // Track calls to other methods if necessary for specific tests
         render_frame_count: usize,
+        buffer_age: usize,
+        damage_regions: Vec<Option<Vec<Rectangle>>>,
     }
 
     impl MockRendererInternalState {
@@ -382,8 +629,11 @@ mod tests {
                 rendered_elements: Vec::new(),
                 gamma_correction_calls: Vec::new(),
                 tone_mapping_calls: Vec::new(),
+                color_conversion_calls: Vec::new(),
                 submit_and_present_frame_called_count: 0,
                 render_frame_count: 0,
+                buffer_age: 0,
+                damage_regions: Vec::new(),
             }
         }
     }
@@ -406,6 +656,7 @@ mod tests {
         fn submit_and_present_frame_called_count(&self) -> usize { self.state.borrow().submit_and_present_frame_called_count }
         fn gamma_calls_count(&self) -> usize { self.state.borrow().gamma_correction_calls.len() }
         fn tone_mapping_calls_count(&self) -> usize { self.state.borrow().tone_mapping_calls.len() }
+        fn color_conversion_lut_sizes(&self) -> Vec<usize> { self.state.borrow().color_conversion_calls.clone() }
         fn render_frame_count(&self) -> usize { self.state.borrow().render_frame_count }
     }
 
@@ -425,12 +676,25 @@ mod tests {
                      state.rendered_elements.push(RenderElementInfo::TextureNode {
                         texture_id: params.texture.id(),
                         transform_matrix: params.transform.matrix,
+                        corner_radius: params.corner_radius,
                     });
+                } else if let RendererRenderElement::SolidColor { color, .. } = element {
+                    state.rendered_elements.push(RenderElementInfo::SolidColor { color });
+                } else if let RendererRenderElement::Shadow(params) = element {
+                    state.rendered_elements.push(RenderElementInfo::Shadow { geometry: params.geometry });
+                } else if let RendererRenderElement::BackdropBlur(params) = element {
+                    state.rendered_elements.push(RenderElementInfo::BackdropBlur { geometry: params.geometry, region: params.region });
                 }
             }
             Ok(())
         }
 
+        fn buffer_age(&self) -> usize { self.state.borrow().buffer_age }
+
+        fn set_damage_region(&mut self, damage: Option<&[Rectangle]>) {
+            self.state.borrow_mut().damage_regions.push(damage.map(|rects| rects.to_vec()));
+        }
+
         fn submit_and_present_frame(&mut self) -> Result<(), RendererError> {
             self.state.borrow_mut().submit_and_present_frame_called_count += 1;
             Ok(())
@@ -494,6 +758,11 @@ mod tests {
             Ok(())
         }
 
+        fn apply_color_space_conversion(&mut self, lut: &Lut3d) -> Result<(), RendererError> {
+            self.state.borrow_mut().color_conversion_calls.push(lut.size());
+            Ok(())
+        }
+
         // Stubs for other FrameRenderer methods
         fn screen_size(&self) -> NovaSize<i32, smithay::utils::Physical> { NovaSize::new(1920, 1080) }
         fn create_texture_from_shm(&mut self, _buffer: &smithay::reexports::wayland_server::protocol::wl_buffer::WlBuffer) -> Result<Box<dyn RenderableTexture>, RendererError> {
@@ -815,4 +1084,174 @@ mod tests {
         assert_eq!(engine.renderer.tone_mapping_calls_count(), 1);
         assert_eq!(engine.renderer.submit_and_present_frame_called_count(), 1);
     }
+
+    fn shm_attributes(x: f32, y: f32, width: f32, height: f32, z_order: i32, buffer_id: u64) -> SurfaceAttributes {
+        SurfaceAttributes {
+            position: Point2D::new(x, y),
+            size: Size2D::new(width, height),
+            transform: SceneGraphTransform::identity(),
+            is_visible: true,
+            z_order,
+            opaque_region: None,
+            parent: None,
+            current_buffer_id: Some(buffer_id),
+            buffer_format: Some(SceneGraphBufferFormatExt::Argb8888),
+            buffer_stride: width as u32 * 4,
+            buffer_type: Some(SceneGraphBufferSourceType::Shm),
+        }
+    }
+
+    #[test]
+    fn test_idle_output_skips_repaint() {
+        let mock_renderer = MockRenderer::new();
+        mock_renderer.state.borrow_mut().buffer_age = 1;
+        let mut engine = CompositionEngine::new(mock_renderer);
+        engine.add_surface(SurfaceId::new(1), shm_attributes(0.0, 0.0, 400.0, 300.0, 0, 1));
+
+        engine.composite_frame();
+        engine.composite_frame();
+
+        assert_eq!(engine.renderer.render_frame_count(), 1, "an unchanged scene must not be rendered again");
+        assert_eq!(engine.renderer.submit_and_present_frame_called_count(), 1);
+        assert_eq!(engine.renderer.state.borrow().damage_regions, vec![None]);
+    }
+
+    #[test]
+    fn test_surface_damage_is_transformed_and_used_as_scissor() {
+        let mock_renderer = MockRenderer::new();
+        mock_renderer.state.borrow_mut().buffer_age = 1;
+        let mut engine = CompositionEngine::new(mock_renderer);
+        let terminal = SurfaceId::new(1);
+        engine.add_surface(terminal, shm_attributes(100.0, 50.0, 400.0, 300.0, 0, 1));
+        engine.add_surface(SurfaceId::new(2), shm_attributes(1000.0, 500.0, 200.0, 200.0, 1, 2));
+        engine.composite_frame();
+        let uploads_after_first_frame = engine.renderer.uploaded_textures_count();
+
+        // A blinking cursor: a 2x16 pixel damage inside the terminal.
+        engine.damage_surface_buffer(terminal, &[Rectangle::from_coords(10.0, 20.0, 2.0, 16.0)]);
+        engine.composite_frame();
+
+        let state = engine.renderer.state.borrow();
+        assert_eq!(state.damage_regions.last().unwrap(), &Some(vec![Rectangle::from_coords(110.0, 70.0, 2.0, 16.0)]));
+        assert_eq!(state.uploaded_textures.len(), uploads_after_first_frame + 1, "only the damaged surface is redrawn");
+        assert_eq!(state.uploaded_textures.last().unwrap().surface_id, terminal);
+    }
+
+    #[test]
+    fn test_old_buffers_repaint_accumulated_damage() {
+        let mock_renderer = MockRenderer::new();
+        mock_renderer.state.borrow_mut().buffer_age = 2;
+        let mut engine = CompositionEngine::new(mock_renderer);
+        let surface = SurfaceId::new(1);
+        engine.add_surface(surface, shm_attributes(0.0, 0.0, 800.0, 600.0, 0, 1));
+        engine.composite_frame();
+
+        let first = Rectangle::from_coords(10.0, 10.0, 5.0, 5.0);
+        let second = Rectangle::from_coords(300.0, 300.0, 5.0, 5.0);
+        engine.damage_surface_buffer(surface, &[first]);
+        engine.composite_frame();
+        engine.damage_surface_buffer(surface, &[second]);
+        engine.composite_frame();
+
+        let state = engine.renderer.state.borrow();
+        assert_eq!(state.damage_regions.last().unwrap(), &Some(vec![second, first]));
+    }
+
+    #[test]
+    fn test_removed_surface_damages_its_area() {
+        let mock_renderer = MockRenderer::new();
+        mock_renderer.state.borrow_mut().buffer_age = 1;
+        let mut engine = CompositionEngine::new(mock_renderer);
+        engine.add_surface(SurfaceId::new(1), shm_attributes(0.0, 0.0, 1920.0, 1080.0, 0, 1));
+        engine.add_surface(SurfaceId::new(2), shm_attributes(200.0, 200.0, 100.0, 100.0, 1, 2));
+        engine.composite_frame();
+
+        engine.remove_surface(SurfaceId::new(2));
+        engine.composite_frame();
+
+        let state = engine.renderer.state.borrow();
+        assert_eq!(state.render_frame_count, 2);
+        assert_eq!(state.damage_regions.last().unwrap(), &Some(vec![Rectangle::from_coords(200.0, 200.0, 100.0, 100.0)]));
+    }
+
+    #[test]
+    fn test_damage_flash_draws_overlay() {
+        let mock_renderer = MockRenderer::new();
+        mock_renderer.state.borrow_mut().buffer_age = 1;
+        let mut engine = CompositionEngine::new(mock_renderer);
+        let surface = SurfaceId::new(1);
+        engine.add_surface(surface, shm_attributes(0.0, 0.0, 400.0, 300.0, 0, 1));
+        engine.set_debug_damage_flash(true);
+        engine.composite_frame();
+
+        assert!(engine.has_pending_overlay_frames());
+        let solid_colors = engine
+            .renderer
+            .state
+            .borrow()
+            .rendered_elements
+            .iter()
+            .filter(|e| matches!(e, RenderElementInfo::SolidColor { .. }))
+            .count();
+        assert_eq!(solid_colors, 1, "the damaged area is flashed");
+    }
+
+    #[test]
+    fn test_output_color_lut_is_applied_after_composition() {
+        let mut engine = CompositionEngine::new(MockRenderer::new());
+        engine.add_surface(SurfaceId::new(1), shm_attributes(0.0, 0.0, 400.0, 300.0, 0, 1));
+        engine.composite_frame();
+        assert!(engine.renderer.color_conversion_lut_sizes().is_empty());
+
+        engine.set_output_color_lut("default", Some(StdArc::new(Lut3d::bake(17, |rgb| rgb))));
+        engine.composite_frame();
+        assert_eq!(engine.renderer.color_conversion_lut_sizes(), vec![17]);
+
+        engine.set_output_color_lut("other", Some(StdArc::new(Lut3d::bake(9, |rgb| rgb))));
+        engine.set_output_color_lut("default", None);
+        engine.composite_frame();
+        assert_eq!(engine.renderer.color_conversion_lut_sizes(), vec![17], "only the composited output's LUT is used");
+    }
+
+    #[test]
+    fn test_window_effects_are_drawn_and_widen_damage() {
+        use crate::compositor::effects::{BlurStyle, ShadowStyle, SurfaceBlur};
+
+        let mock_renderer = MockRenderer::new();
+        mock_renderer.state.borrow_mut().buffer_age = 1;
+        let mut engine = CompositionEngine::new(mock_renderer);
+        let wallpaper = SurfaceId::new(1);
+        let panel = SurfaceId::new(2);
+        let window = SurfaceId::new(3);
+        engine.add_surface(wallpaper, shm_attributes(0.0, 0.0, 1920.0, 1080.0, 0, 1));
+        engine.add_surface(panel, shm_attributes(0.0, 0.0, 1920.0, 40.0, 2, 2));
+        engine.add_surface(window, shm_attributes(500.0, 300.0, 400.0, 300.0, 1, 3));
+        let blur = BlurStyle { passes: 1, offset: 1.0 };
+        engine.set_surface_effects(panel, SurfaceEffects { blur: Some(SurfaceBlur { style: blur, region: Some(vec![Rectangle::from_coords(0.0, 0.0, 200.0, 40.0)]) }), ..SurfaceEffects::default() });
+        let shadow = ShadowStyle { offset: (0.0, 4.0), blur_radius: 8.0, spread: 0.0, color: [0.0, 0.0, 0.0, 0.5] };
+        engine.set_surface_effects(window, SurfaceEffects { corner_radius: 8.0, shadow: Some(shadow), blur: None });
+        engine.composite_frame();
+        {
+            let state = engine.renderer.state.borrow();
+            let elements = &state.rendered_elements;
+            assert_eq!(elements.len(), 5, "{:?}", elements);
+            assert!(matches!(&elements[1], RenderElementInfo::Shadow { geometry } if *geometry == Rectangle::from_coords(500.0, 300.0, 400.0, 300.0)));
+            assert!(matches!(&elements[2], RenderElementInfo::TextureNode { corner_radius, .. } if *corner_radius == 8.0));
+            assert!(matches!(&elements[3], RenderElementInfo::BackdropBlur { region, .. } if region == &vec![Rectangle::from_coords(0.0, 0.0, 200.0, 40.0)]));
+        }
+
+        // Damage within the blur's reach of the panel repaints the panel and its surroundings.
+        engine.damage_surface_buffer(wallpaper, &[Rectangle::from_coords(100.0, 45.0, 10.0, 10.0)]);
+        engine.composite_frame();
+        let reach = blur.reach();
+        let damage = engine.renderer.state.borrow().damage_regions.last().unwrap().clone().unwrap();
+        assert!(damage.contains(&Rectangle::from_coords(0.0, 0.0, 1920.0, 40.0 + reach)), "{:?}", damage);
+
+        // Moving the window repaints where its shadow was.
+        engine.add_surface(window, shm_attributes(600.0, 300.0, 400.0, 300.0, 1, 3));
+        engine.composite_frame();
+        let damage = engine.renderer.state.borrow().damage_regions.last().unwrap().clone().unwrap();
+        let old_shadow = shadow_bounds([500.0, 300.0, 400.0, 300.0], 8.0, &shadow).unwrap();
+        assert!(damage.iter().any(|r| r.origin.x <= old_shadow[0] && r.origin.y + r.size.height >= old_shadow[1] + old_shadow[3]), "{:?}", damage);
+    }
 }
@@ -0,0 +1,69 @@
+// novade-system/src/compositor/config/devices.rs
+//! Applies [`DeviceSettings`] to libinput devices.
+
+use smithay::reexports::input::{self as libinput, DeviceConfigError};
+use tracing::{debug, warn};
+
+use super::{AccelProfile, DeviceSettings, InputConfig, ScrollMethod};
+
+/// Returns `true` if libinput reports tap support, which is how touchpads are told apart
+/// from other pointer devices.
+pub fn is_touchpad(device: &libinput::Device) -> bool {
+    device.config_tap_finger_count() > 0
+}
+
+/// Resolves the settings for `device` from `config` and applies them.
+pub fn configure_libinput_device(device: &mut libinput::Device, config: &InputConfig) {
+    let name = device.name().to_string();
+    let settings = config.settings_for_device(&name, is_touchpad(device));
+    apply_device_settings(device, &name, &settings);
+}
+
+/// Applies every set field of `settings` to `device`. Unsupported options are logged and skipped.
+pub fn apply_device_settings(device: &mut libinput::Device, name: &str, settings: &DeviceSettings) {
+    let report = |option: &str, result: Result<(), DeviceConfigError>| match result {
+        Ok(()) => debug!("Input device '{}': applied {}", name, option),
+        Err(DeviceConfigError::Unsupported) => debug!("Input device '{}' does not support {}", name, option),
+        Err(e) => warn!("Input device '{}': failed to apply {}: {:?}", name, option, e),
+    };
+
+    if let Some(enabled) = settings.enabled {
+        let mode = if enabled {
+            libinput::SendEventsMode::ENABLED
+        } else {
+            libinput::SendEventsMode::DISABLED
+        };
+        report("enabled", device.config_send_events_set_mode(mode));
+    }
+    if let Some(profile) = settings.accel_profile {
+        let profile = match profile {
+            AccelProfile::Flat => libinput::AccelProfile::Flat,
+            AccelProfile::Adaptive => libinput::AccelProfile::Adaptive,
+        };
+        report("accel_profile", device.config_accel_set_profile(profile));
+    }
+    if let Some(speed) = settings.accel_speed {
+        report("accel_speed", device.config_accel_set_speed(speed));
+    }
+    if let Some(natural) = settings.natural_scroll {
+        report("natural_scroll", device.config_scroll_set_natural_scroll_enabled(natural));
+    }
+    if let Some(left_handed) = settings.left_handed {
+        report("left_handed", device.config_left_handed_set(left_handed));
+    }
+    if let Some(tap) = settings.tap_to_click {
+        report("tap_to_click", device.config_tap_set_enabled(tap));
+    }
+    if let Some(dwt) = settings.disable_while_typing {
+        report("disable_while_typing", device.config_dwt_set_enabled(dwt));
+    }
+    if let Some(method) = settings.scroll_method {
+        let method = match method {
+            ScrollMethod::NoScroll => libinput::ScrollMethod::NoScroll,
+            ScrollMethod::TwoFinger => libinput::ScrollMethod::TwoFinger,
+            ScrollMethod::Edge => libinput::ScrollMethod::Edge,
+            ScrollMethod::OnButtonDown => libinput::ScrollMethod::OnButtonDown,
+        };
+        report("scroll_method", device.config_scroll_set_method(method));
+    }
+}
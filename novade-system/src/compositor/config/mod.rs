@@ -1,66 +1,1018 @@
 // novade-system/src/compositor/config/mod.rs
+//! Typed compositor configuration.
+//!
+//! The configuration is read from `$XDG_CONFIG_HOME/novade/compositor.toml` (falling back to
+//! `~/.config/novade/compositor.toml`). Every section and field is optional; missing values
+//! take their defaults. Syntax errors and semantic validation errors are reported as
+//! [`ConfigError`]s that carry the line and column of the offending key.
+//!
+//! ```toml
+//! [layout]
+//! default_layout = "master_stack"
+//! master_factor = 0.6
+//!
+//! [visual.gaps]
+//! inner = 8
+//! outer = 12
+//!
+//! [input]
+//! focus_follows_mouse = true
+//!
+//! [[input.device]]
+//! name = "Logitech MX Master 3"
+//! accel_profile = "flat"
+//!
+//! [visual.effects]
+//! blur_passes = 4
+//!
+//! [[window_rule]]
+//! namespace = "novade-panel"
+//! blur = true
+//!
+//! [color.outputs."DP-1"]
+//! icc_profile = "~/.local/share/icc/dell-u2720q.icc"
+//!
+//! [color.outputs."HDMI-A-1"]
+//! hdr = true
+//! max_luminance_nits = 600
+//!
+//! [night_light]
+//! enabled = true
+//! latitude = 52.52
+//! longitude = 13.4
+//! temperature = 3500
+//!
+//! [recorder]
+//! directory = "~/Videos/Screencasts"
+//! fps = 60
+//!
+//! [gpu]
+//! render_device = "/dev/dri/card1"
+//!
+//! [debug]
+//! damage_flash = true
+//! ```
+
+pub mod devices;
+pub mod reload;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use novade_domain::WorkspaceOutputPolicy;
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::compositor::animations::AnimationSettings;
+use crate::compositor::keybindings::{KeyCombo, KeybindingConfig};
+use crate::compositor::workspaces::TilingLayout;
+
+/// File name of the compositor configuration inside the NovaDE config directory.
+pub const CONFIG_FILE_NAME: &str = "compositor.toml";
+
+// ANCHOR[id=config_errors]
+/// A single semantic problem found while validating a configuration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigIssue {
+    /// Dotted path of the offending key, e.g. `visual.gaps.inner` or `input.device[1].accel_speed`.
+    pub path: String,
+    /// 1-based line of the key in the source file, if it could be located.
+    pub line: Option<usize>,
+    /// 1-based column of the key in the source file, if it could be located.
+    pub column: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => write!(f, "line {}, column {}: {}: {}", line, column, self.path, self.message),
+            (Some(line), None) => write!(f, "line {}: {}: {}", line, self.path, self.message),
+            _ => write!(f, "{}: {}", self.path, self.message),
+        }
+    }
+}
+
+/// Errors that can occur while loading, validating or saving the configuration.
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("Failed to access config file '{path}': {source}")]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Syntax error at line {line}, column {column}: {message}")]
+    Parse { line: usize, column: usize, message: String },
+    #[error("Invalid configuration:\n{}", format_issues(.0))]
+    Validation(Vec<ConfigIssue>),
+    #[error("Failed to serialize configuration: {0}")]
+    Serialize(#[from] toml::ser::Error),
+}
+
+fn format_issues(issues: &[ConfigIssue]) -> String {
+    issues.iter().map(|i| format!("  {}", i)).collect::<Vec<_>>().join("\n")
+}
+// ANCHOR_END[id=config_errors]
 
 // ANCHOR[id=main_config_struct]
-#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Config {
+    #[serde(default)]
+    pub layout: LayoutConfig,
     #[serde(default)]
     pub performance: PerformanceConfig,
     #[serde(default)]
     pub input: InputConfig,
     #[serde(default)]
     pub visual: VisualConfig,
+    /// Bindings added to (or replacing) the built-in ones, written as `[[keybinding]]` tables.
+    #[serde(default, rename = "keybinding", skip_serializing_if = "Vec::is_empty")]
+    pub keybindings: Vec<KeybindingConfig>,
+    #[serde(default)]
+    pub color: ColorConfig,
+    #[serde(default)]
+    pub night_light: NightLightConfig,
+    #[serde(default)]
+    pub recorder: RecorderConfig,
+    #[serde(default)]
+    pub gpu: GpuConfig,
+    #[serde(default)]
+    pub debug: DebugConfig,
+    #[serde(default, rename = "window_rule", skip_serializing_if = "Vec::is_empty")]
+    pub window_rules: Vec<WindowRule>,
+}
+
+// ANCHOR[id=layout_config_struct]
+/// Tiling layout mode as written in the config file.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LayoutMode {
+    #[default]
+    Floating,
+    MasterStack,
+}
+
+impl From<LayoutMode> for TilingLayout {
+    fn from(mode: LayoutMode) -> Self {
+        match mode {
+            LayoutMode::Floating => TilingLayout::None,
+            LayoutMode::MasterStack => TilingLayout::MasterStack,
+        }
+    }
+}
+
+/// Per-output overrides of the default layout.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OutputLayoutConfig {
+    pub default_layout: Option<LayoutMode>,
+    pub master_factor: Option<f32>,
+    pub workspaces: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LayoutConfig {
+    /// Layout new workspaces start with.
+    pub default_layout: LayoutMode,
+    /// Share of the output width given to the master window, in `0.1..=0.9`.
+    pub master_factor: f32,
+    /// Number of workspaces created per output.
+    pub workspaces_per_output: u32,
+    /// Output new workspaces are created on: the focused one or the one under the pointer.
+    pub new_workspace_output: WorkspaceOutputPolicy,
+    /// Overrides keyed by output name (e.g. `"DP-1"`).
+    pub outputs: HashMap<String, OutputLayoutConfig>,
+}
+
+impl Default for LayoutConfig {
+    fn default() -> Self {
+        Self {
+            default_layout: LayoutMode::Floating,
+            master_factor: 0.6,
+            workspaces_per_output: 4,
+            new_workspace_output: WorkspaceOutputPolicy::FollowFocus,
+            outputs: HashMap::new(),
+        }
+    }
+}
+
+impl LayoutConfig {
+    /// Default layout for workspaces on `output_name`.
+    pub fn layout_for_output(&self, output_name: &str) -> TilingLayout {
+        self.outputs
+            .get(output_name)
+            .and_then(|o| o.default_layout)
+            .unwrap_or(self.default_layout)
+            .into()
+    }
+
+    /// Master factor for `output_name`.
+    pub fn master_factor_for_output(&self, output_name: &str) -> f32 {
+        self.outputs.get(output_name).and_then(|o| o.master_factor).unwrap_or(self.master_factor)
+    }
+
+    /// Number of workspaces to create on `output_name`.
+    pub fn workspaces_for_output(&self, output_name: &str) -> u32 {
+        self.outputs.get(output_name).and_then(|o| o.workspaces).unwrap_or(self.workspaces_per_output)
+    }
 }
 
 // ANCHOR[id=performance_config_struct]
-#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+/// Variable refresh rate policy.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VrrPolicy {
+    /// Never enable VRR.
+    Off,
+    /// Enable VRR only while a fullscreen surface is shown.
+    #[default]
+    Fullscreen,
+    /// Keep VRR enabled whenever the output supports it.
+    Always,
+}
+
+/// Policy for tearing (asynchronous) page flips.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TearingPolicy {
+    /// Never tear.
+    Never,
+    /// Tear only for fullscreen clients that opt in via the tearing-control protocol.
+    #[default]
+    FullscreenOptIn,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct PerformanceConfig {
-    pub gpu_performance_settings: Option<String>, // Placeholder, to be detailed
-    pub rendering_quality_presets: Option<String>, // Placeholder, to be detailed
-    pub memory_usage_limits: Option<String>, // Placeholder, to be detailed
-    pub power_management_settings: Option<String>, // Placeholder, to be detailed
-    pub adaptive_performance_tuning: Option<bool>, // Placeholder, to be detailed
+    pub vrr: VrrPolicy,
+    pub tearing: TearingPolicy,
+    /// Fixed render budget in milliseconds before vblank. `None` lets the scheduler predict it.
+    pub max_render_time_ms: Option<u32>,
+    /// Lower animation quality and effects under sustained load.
+    pub adaptive_performance_tuning: bool,
+    /// Scan out a fullscreen client's buffer directly on the primary plane when possible.
+    pub direct_scanout: bool,
+    /// Put video and cursor surfaces on overlay and cursor planes when possible.
+    pub overlay_planes: bool,
+}
+
+impl Default for PerformanceConfig {
+    fn default() -> Self {
+        Self {
+            vrr: VrrPolicy::default(),
+            tearing: TearingPolicy::default(),
+            max_render_time_ms: None,
+            adaptive_performance_tuning: false,
+            direct_scanout: true,
+            overlay_planes: true,
+        }
+    }
 }
 
 // ANCHOR[id=input_config_struct]
-#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AccelProfile {
+    Flat,
+    #[default]
+    Adaptive,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScrollMethod {
+    NoScroll,
+    #[default]
+    TwoFinger,
+    Edge,
+    OnButtonDown,
+}
+
+/// XKB keyboard settings.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct KeyboardConfig {
+    pub layout: String,
+    pub variant: String,
+    pub model: String,
+    pub options: Option<String>,
+    /// Key repeat rate in characters per second.
+    pub repeat_rate: i32,
+    /// Delay before key repeat starts, in milliseconds.
+    pub repeat_delay: i32,
+}
+
+impl Default for KeyboardConfig {
+    fn default() -> Self {
+        Self {
+            layout: "us".to_string(),
+            variant: String::new(),
+            model: String::new(),
+            options: None,
+            repeat_rate: 25,
+            repeat_delay: 200,
+        }
+    }
+}
+
+/// libinput settings of a pointer device. Unset fields keep the libinput default.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DeviceSettings {
+    pub enabled: Option<bool>,
+    pub accel_profile: Option<AccelProfile>,
+    /// Pointer speed in `-1.0..=1.0`.
+    pub accel_speed: Option<f64>,
+    pub natural_scroll: Option<bool>,
+    pub left_handed: Option<bool>,
+    pub tap_to_click: Option<bool>,
+    pub disable_while_typing: Option<bool>,
+    pub scroll_method: Option<ScrollMethod>,
+}
+
+impl DeviceSettings {
+    /// Returns `self` with every unset field taken from `fallback`.
+    pub fn merged_over(&self, fallback: &DeviceSettings) -> DeviceSettings {
+        DeviceSettings {
+            enabled: self.enabled.or(fallback.enabled),
+            accel_profile: self.accel_profile.or(fallback.accel_profile),
+            accel_speed: self.accel_speed.or(fallback.accel_speed),
+            natural_scroll: self.natural_scroll.or(fallback.natural_scroll),
+            left_handed: self.left_handed.or(fallback.left_handed),
+            tap_to_click: self.tap_to_click.or(fallback.tap_to_click),
+            disable_while_typing: self.disable_while_typing.or(fallback.disable_while_typing),
+            scroll_method: self.scroll_method.or(fallback.scroll_method),
+        }
+    }
+}
+
+/// Settings for one specific input device, matched by its libinput name.
+// `deny_unknown_fields` is not supported together with `flatten`.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeviceConfig {
+    pub name: String,
+    #[serde(flatten)]
+    pub settings: DeviceSettings,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct InputConfig {
-    pub keyboard_layout: Option<String>, // Placeholder, to be detailed (e.g., XKB integration)
-    pub pointer_acceleration_settings: Option<String>, // Placeholder, to be detailed
-    pub touch_gesture_configuration: Option<String>, // Placeholder, to be detailed
-    pub multi_device_configuration: Option<String>, // Placeholder, to be detailed
-    pub input_device_profiles: Option<String>, // Placeholder, to be detailed
+    /// Focus the window under the pointer without clicking.
+    pub focus_follows_mouse: bool,
+    pub keyboard: KeyboardConfig,
+    /// Defaults for all pointer devices (mice, trackballs).
+    pub pointer: DeviceSettings,
+    /// Defaults for all touchpads; falls back to `pointer` for unset fields.
+    pub touchpad: DeviceSettings,
+    /// Per-device overrides, written as `[[input.device]]` tables.
+    #[serde(rename = "device")]
+    pub devices: Vec<DeviceConfig>,
+}
+
+impl InputConfig {
+    /// Resolves the effective settings of a device: per-device override, then the touchpad
+    /// or pointer defaults.
+    pub fn settings_for_device(&self, name: &str, is_touchpad: bool) -> DeviceSettings {
+        let defaults = if is_touchpad {
+            self.touchpad.merged_over(&self.pointer)
+        } else {
+            self.pointer.clone()
+        };
+        match self.devices.iter().find(|d| d.name == name) {
+            Some(device) => device.settings.merged_over(&defaults),
+            None => defaults,
+        }
+    }
 }
 
 // ANCHOR[id=visual_config_struct]
-#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+/// An RGBA color written as `"#rrggbb"` or `"#rrggbbaa"`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Color(pub [u8; 4]);
+
+impl Color {
+    /// Returns the color as normalized `[r, g, b, a]` floats for the renderer.
+    pub fn to_f32_array(self) -> [f32; 4] {
+        self.0.map(|c| c as f32 / 255.0)
+    }
+}
+
+impl TryFrom<String> for Color {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let hex = value
+            .strip_prefix('#')
+            .ok_or_else(|| format!("color '{}' must start with '#'", value))?;
+        if !(hex.len() == 6 || hex.len() == 8) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!("color '{}' must be #rrggbb or #rrggbbaa", value));
+        }
+        let byte = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap_or(0);
+        let alpha = if hex.len() == 8 { byte(6) } else { 0xff };
+        Ok(Color([byte(0), byte(2), byte(4), alpha]))
+    }
+}
+
+impl From<Color> for String {
+    fn from(color: Color) -> Self {
+        let [r, g, b, a] = color.0;
+        if a == 0xff {
+            format!("#{:02x}{:02x}{:02x}", r, g, b)
+        } else {
+            format!("#{:02x}{:02x}{:02x}{:02x}", r, g, b, a)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GapsConfig {
+    /// Gap between tiled windows, in logical pixels.
+    pub inner: i32,
+    /// Gap between tiled windows and the output edge, in logical pixels.
+    pub outer: i32,
+}
+
+impl Default for GapsConfig {
+    fn default() -> Self {
+        Self { inner: 0, outer: 0 }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BorderConfig {
+    /// Border width in logical pixels; `0` disables borders.
+    pub width: i32,
+    pub active_color: Color,
+    pub inactive_color: Color,
+    pub corner_radius: f32,
+}
+
+impl Default for BorderConfig {
+    fn default() -> Self {
+        Self {
+            width: 2,
+            active_color: Color([0x5e, 0x81, 0xac, 0xff]),
+            inactive_color: Color([0x3b, 0x42, 0x52, 0xff]),
+            corner_radius: 0.0,
+        }
+    }
+}
+
+/// Window shadows and background blur; geometry and colours can also come from the theme.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EffectsConfig {
+    /// Draw drop shadows under windows.
+    pub shadows: bool,
+    /// Shadow colour when the theme does not set one.
+    pub shadow_color: Color,
+    /// Blur behind surfaces that request it through ext-background-effect or a window rule.
+    pub blur: bool,
+    /// Downsampling passes of the blur, `1..=6`; each doubles its radius.
+    pub blur_passes: u32,
+    /// Sample distance of each pass in texels.
+    pub blur_offset: f32,
+}
+
+impl Default for EffectsConfig {
+    fn default() -> Self {
+        Self { shadows: true, shadow_color: Color([0, 0, 0, 0x80]), blur: true, blur_passes: 3, blur_offset: 2.0 }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColorScheme {
+    #[default]
+    System,
+    Light,
+    Dark,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct VisualConfig {
-    pub theme_configuration: Option<String>, // Placeholder, to be detailed
-    pub color_scheme_settings: Option<String>, // Placeholder, e.g., "dark", "light"
-    pub font_configuration: Option<String>, // Placeholder, to be detailed
-    pub animation_settings: Option<String>, // Placeholder, to be detailed
-    pub custom_shader_loading: Option<String>, // Placeholder, to be detailed
+    pub theme: Option<String>,
+    pub color_scheme: ColorScheme,
+    pub gaps: GapsConfig,
+    pub border: BorderConfig,
+    #[serde(rename = "animations")]
+    pub animation_settings: AnimationSettings,
+    pub effects: EffectsConfig,
+}
+
+// ANCHOR[id=window_rule_struct]
+/// Effect overrides for matching surfaces, written as `[[window_rule]]` tables.
+///
+/// A rule matches windows by exact `app_id` or layer surfaces by exact `namespace`; when
+/// several rules match, later ones win field by field.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WindowRule {
+    pub app_id: Option<String>,
+    pub namespace: Option<String>,
+    /// Blur behind the whole surface, or never blur it.
+    pub blur: Option<bool>,
+    pub corner_radius: Option<f32>,
+    pub shadow: Option<bool>,
+}
+
+// ANCHOR[id=color_config_struct]
+/// Colour pipeline settings of one output.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OutputColorConfig {
+    /// ICC profile describing the display; `~/` is expanded.
+    pub icc_profile: Option<PathBuf>,
+    /// Composite in BT.2020/PQ and drive the output in HDR mode.
+    pub hdr: bool,
+    /// Luminance SDR white is shown at in HDR mode, in cd/m².
+    pub sdr_white_nits: f32,
+    /// Peak luminance of the display in HDR mode, in cd/m².
+    pub max_luminance_nits: f32,
+}
+
+impl Default for OutputColorConfig {
+    fn default() -> Self {
+        Self { icc_profile: None, hdr: false, sdr_white_nits: 203.0, max_luminance_nits: 1000.0 }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ColorConfig {
+    /// Points per axis of baked 3D LUTs, in `2..=65`.
+    pub lut_size: u32,
+    /// Settings keyed by output name (e.g. `"DP-1"`).
+    pub outputs: HashMap<String, OutputColorConfig>,
+}
+
+impl Default for ColorConfig {
+    fn default() -> Self {
+        Self { lut_size: 33, outputs: HashMap::new() }
+    }
+}
+
+// ANCHOR[id=night_light_config_struct]
+/// A local wall-clock time written as `"HH:MM"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct TimeOfDay {
+    pub hour: u8,
+    pub minute: u8,
+}
+
+impl TimeOfDay {
+    /// Minutes past midnight.
+    pub fn minutes(self) -> u32 {
+        self.hour as u32 * 60 + self.minute as u32
+    }
+}
+
+impl TryFrom<String> for TimeOfDay {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let parsed = value.split_once(':').and_then(|(hour, minute)| {
+            let hour: u8 = hour.parse().ok().filter(|h| *h < 24)?;
+            let minute: u8 = minute.parse().ok().filter(|m| *m < 60 && minute.len() == 2)?;
+            Some(TimeOfDay { hour, minute })
+        });
+        parsed.ok_or_else(|| format!("time '{}' must be HH:MM", value))
+    }
+}
+
+impl From<TimeOfDay> for String {
+    fn from(time: TimeOfDay) -> Self {
+        format!("{:02}:{:02}", time.hour, time.minute)
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NightLightSchedule {
+    /// From sunset to sunrise at `latitude`/`longitude`.
+    #[default]
+    SunsetToSunrise,
+    /// From `start` to `end` local time.
+    Manual,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NightLightConfig {
+    /// Follow the schedule. The quick settings toggle works either way.
+    pub enabled: bool,
+    pub schedule: NightLightSchedule,
+    /// Position for `sunset_to_sunrise`, in degrees north.
+    pub latitude: Option<f64>,
+    /// Position for `sunset_to_sunrise`, in degrees east.
+    pub longitude: Option<f64>,
+    /// Start of the night for `manual`.
+    pub start: TimeOfDay,
+    /// End of the night for `manual`.
+    pub end: TimeOfDay,
+    /// Colour temperature at night in kelvin, in `1000..=6500`.
+    pub temperature: u32,
+    /// Length of the fade at either end of the night.
+    pub transition_minutes: u32,
+}
+
+impl Default for NightLightConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            schedule: NightLightSchedule::SunsetToSunrise,
+            latitude: None,
+            longitude: None,
+            start: TimeOfDay { hour: 20, minute: 0 },
+            end: TimeOfDay { hour: 7, minute: 0 },
+            temperature: 4000,
+            transition_minutes: 30,
+        }
+    }
+}
+
+// ANCHOR[id=gpu_config_struct]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GpuConfig {
+    /// GPU to composite on, by node path (`/dev/dri/card1`), card name, PCI slot or driver.
+    /// Unset picks the GPU the firmware booted with. Read when the DRM backend starts and
+    /// when GPUs are plugged in.
+    pub render_device: Option<String>,
+}
+
+// ANCHOR[id=debug_config_struct]
+/// Diagnostics for compositor developers.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DebugConfig {
+    /// Flash the regions clients damage with each commit.
+    pub damage_flash: bool,
+}
+
+// ANCHOR[id=recorder_config_struct]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RecorderConfig {
+    /// Where recordings are saved; `$XDG_VIDEOS_DIR` or `~/Videos` if unset.
+    pub directory: Option<PathBuf>,
+    /// Frame rate of recordings, in `1..=120`.
+    pub fps: u32,
+    /// Draw the pointer into recordings.
+    pub cursor: bool,
+    /// Name of the encoder; `y4m` is built in.
+    pub encoder: String,
+}
+
+impl Default for RecorderConfig {
+    fn default() -> Self {
+        Self { directory: None, fps: 30, cursor: true, encoder: "y4m".to_string() }
+    }
 }
 
 // ANCHOR[id=config_impl]
 impl Config {
-    // TODO[issue=config_loading]: Implement loading from file
-    pub fn load() -> Result<Self, Box<dyn std::error::Error>> {
-        // Placeholder: Load default configuration
-        Ok(Config::default())
+    /// Default location of the configuration file.
+    pub fn default_path() -> PathBuf {
+        let base = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .filter(|p| p.is_absolute())
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+            .unwrap_or_else(|| PathBuf::from("/etc/xdg"));
+        base.join("novade").join(CONFIG_FILE_NAME)
+    }
+
+    /// Loads the configuration from [`Config::default_path`].
+    /// A missing file yields the default configuration.
+    pub fn load() -> Result<Self, ConfigError> {
+        Self::load_from_path(&Self::default_path())
     }
 
-    // TODO[issue=config_saving]: Implement saving to file
-    pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
-        // Placeholder: Save current configuration
-        Ok(())
+    /// Loads and validates the configuration from `path`.
+    /// A missing file yields the default configuration.
+    pub fn load_from_path(path: &Path) -> Result<Self, ConfigError> {
+        match std::fs::read_to_string(path) {
+            Ok(source) => Self::from_toml_str(&source),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                tracing::info!("No compositor config at {}, using defaults.", path.display());
+                Ok(Config::default())
+            }
+            Err(source) => Err(ConfigError::Io { path: path.to_path_buf(), source }),
+        }
     }
 
-    // TODO[issue=config_validation]: Implement validation logic
-    pub fn validate(&self) -> Result<(), Vec<String>> {
-        // Placeholder: Validate configuration
-        Ok(())
+    /// Parses and validates a configuration from TOML source.
+    pub fn from_toml_str(source: &str) -> Result<Self, ConfigError> {
+        let config: Config = toml::from_str(source).map_err(|e| {
+            let (line, column) = e.span().map_or((1, 1), |span| line_col(source, span.start));
+            ConfigError::Parse { line, column, message: e.message().to_string() }
+        })?;
+
+        config.validate().map_err(|mut issues| {
+            for issue in &mut issues {
+                if let Some((line, column)) = locate_key(source, &issue.path) {
+                    issue.line = Some(line);
+                    issue.column = Some(column);
+                }
+            }
+            ConfigError::Validation(issues)
+        })?;
+        Ok(config)
+    }
+
+    /// Saves the configuration to [`Config::default_path`].
+    pub fn save(&self) -> Result<(), ConfigError> {
+        self.save_to_path(&Self::default_path())
+    }
+
+    /// Saves the configuration to `path`, creating parent directories as needed.
+    pub fn save_to_path(&self, path: &Path) -> Result<(), ConfigError> {
+        let text = toml::to_string_pretty(self)?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|source| ConfigError::Io { path: parent.to_path_buf(), source })?;
+        }
+        std::fs::write(path, text).map_err(|source| ConfigError::Io { path: path.to_path_buf(), source })
+    }
+
+    /// Checks value ranges and cross-field constraints. Issues carry the key path but no
+    /// location; [`Config::from_toml_str`] fills in line numbers.
+    pub fn validate(&self) -> Result<(), Vec<ConfigIssue>> {
+        let mut issues = Vec::new();
+        let mut check = |ok: bool, path: String, message: String| {
+            if !ok {
+                issues.push(ConfigIssue { path, line: None, column: None, message });
+            }
+        };
+
+        let layout = &self.layout;
+        check(
+            (0.1..=0.9).contains(&layout.master_factor),
+            "layout.master_factor".into(),
+            format!("must be between 0.1 and 0.9, got {}", layout.master_factor),
+        );
+        check(
+            (1..=32).contains(&layout.workspaces_per_output),
+            "layout.workspaces_per_output".into(),
+            format!("must be between 1 and 32, got {}", layout.workspaces_per_output),
+        );
+        let mut output_names: Vec<&String> = layout.outputs.keys().collect();
+        output_names.sort();
+        for name in output_names {
+            let output = &layout.outputs[name];
+            if let Some(factor) = output.master_factor {
+                check(
+                    (0.1..=0.9).contains(&factor),
+                    format!("layout.outputs.{}.master_factor", name),
+                    format!("must be between 0.1 and 0.9, got {}", factor),
+                );
+            }
+            if let Some(count) = output.workspaces {
+                check(
+                    (1..=32).contains(&count),
+                    format!("layout.outputs.{}.workspaces", name),
+                    format!("must be between 1 and 32, got {}", count),
+                );
+            }
+        }
+
+        if let Some(ms) = self.performance.max_render_time_ms {
+            check((1..=100).contains(&ms), "performance.max_render_time_ms".into(), format!("must be between 1 and 100, got {}", ms));
+        }
+
+        let keyboard = &self.input.keyboard;
+        check(!keyboard.layout.trim().is_empty(), "input.keyboard.layout".into(), "must not be empty".into());
+        check(
+            (1..=200).contains(&keyboard.repeat_rate),
+            "input.keyboard.repeat_rate".into(),
+            format!("must be between 1 and 200, got {}", keyboard.repeat_rate),
+        );
+        check(
+            (100..=2000).contains(&keyboard.repeat_delay),
+            "input.keyboard.repeat_delay".into(),
+            format!("must be between 100 and 2000, got {}", keyboard.repeat_delay),
+        );
+
+        let mut check_device = |settings: &DeviceSettings, prefix: String| {
+            if let Some(speed) = settings.accel_speed {
+                check(
+                    (-1.0..=1.0).contains(&speed),
+                    format!("{}.accel_speed", prefix),
+                    format!("must be between -1.0 and 1.0, got {}", speed),
+                );
+            }
+        };
+        check_device(&self.input.pointer, "input.pointer".into());
+        check_device(&self.input.touchpad, "input.touchpad".into());
+        for (i, device) in self.input.devices.iter().enumerate() {
+            check_device(&device.settings, format!("input.device[{}]", i));
+        }
+        for (i, device) in self.input.devices.iter().enumerate() {
+            check(!device.name.trim().is_empty(), format!("input.device[{}].name", i), "must not be empty".into());
+            if self.input.devices[..i].iter().any(|d| d.name == device.name) {
+                check(false, format!("input.device[{}].name", i), format!("duplicate device '{}'", device.name));
+            }
+        }
+
+        let visual = &self.visual;
+        check((0..=200).contains(&visual.gaps.inner), "visual.gaps.inner".into(), format!("must be between 0 and 200, got {}", visual.gaps.inner));
+        check((0..=200).contains(&visual.gaps.outer), "visual.gaps.outer".into(), format!("must be between 0 and 200, got {}", visual.gaps.outer));
+        check((0..=50).contains(&visual.border.width), "visual.border.width".into(), format!("must be between 0 and 50, got {}", visual.border.width));
+        check(
+            (0.0..=100.0).contains(&visual.border.corner_radius),
+            "visual.border.corner_radius".into(),
+            format!("must be between 0 and 100, got {}", visual.border.corner_radius),
+        );
+        check(
+            (0.0..=10.0).contains(&visual.animation_settings.duration_scale),
+            "visual.animations.duration_scale".into(),
+            format!("must be between 0.0 and 10.0, got {}", visual.animation_settings.duration_scale),
+        );
+        let effects = &visual.effects;
+        check((1..=6).contains(&effects.blur_passes), "visual.effects.blur_passes".into(), format!("must be between 1 and 6, got {}", effects.blur_passes));
+        check(
+            (0.5..=10.0).contains(&effects.blur_offset),
+            "visual.effects.blur_offset".into(),
+            format!("must be between 0.5 and 10.0, got {}", effects.blur_offset),
+        );
+
+        for (i, binding) in self.keybindings.iter().enumerate() {
+            if let Err(e) = binding.keys.parse::<KeyCombo>() {
+                check(false, format!("keybinding[{}].keys", i), e);
+            }
+        }
+
+        for (i, rule) in self.window_rules.iter().enumerate() {
+            check(rule.app_id.is_some() || rule.namespace.is_some(), format!("window_rule[{}]", i), "needs app_id or namespace".into());
+            if let Some(radius) = rule.corner_radius {
+                check((0.0..=100.0).contains(&radius), format!("window_rule[{}].corner_radius", i), format!("must be between 0 and 100, got {}", radius));
+            }
+        }
+
+        let color = &self.color;
+        check((2..=65).contains(&color.lut_size), "color.lut_size".into(), format!("must be between 2 and 65, got {}", color.lut_size));
+        let mut output_names: Vec<&String> = color.outputs.keys().collect();
+        output_names.sort();
+        for name in output_names {
+            let output = &color.outputs[name];
+            check(
+                (80.0..=500.0).contains(&output.sdr_white_nits),
+                format!("color.outputs.{}.sdr_white_nits", name),
+                format!("must be between 80 and 500, got {}", output.sdr_white_nits),
+            );
+            check(
+                output.max_luminance_nits >= output.sdr_white_nits && output.max_luminance_nits <= 10_000.0,
+                format!("color.outputs.{}.max_luminance_nits", name),
+                format!("must be between sdr_white_nits and 10000, got {}", output.max_luminance_nits),
+            );
+        }
+
+        let night_light = &self.night_light;
+        check(
+            (1000..=6500).contains(&night_light.temperature),
+            "night_light.temperature".into(),
+            format!("must be between 1000 and 6500, got {}", night_light.temperature),
+        );
+        check(
+            night_light.transition_minutes <= 180,
+            "night_light.transition_minutes".into(),
+            format!("must be at most 180, got {}", night_light.transition_minutes),
+        );
+        if let Some(latitude) = night_light.latitude {
+            check((-90.0..=90.0).contains(&latitude), "night_light.latitude".into(), format!("must be between -90 and 90, got {}", latitude));
+        }
+        if let Some(longitude) = night_light.longitude {
+            check((-180.0..=180.0).contains(&longitude), "night_light.longitude".into(), format!("must be between -180 and 180, got {}", longitude));
+        }
+        check(
+            !(night_light.enabled
+                && night_light.schedule == NightLightSchedule::SunsetToSunrise
+                && (night_light.latitude.is_none() || night_light.longitude.is_none())),
+            "night_light.schedule".into(),
+            "sunset_to_sunrise needs latitude and longitude".into(),
+        );
+
+        check((1..=120).contains(&self.recorder.fps), "recorder.fps".into(), format!("must be between 1 and 120, got {}", self.recorder.fps));
+        check(!self.recorder.encoder.is_empty(), "recorder.encoder".into(), "must not be empty".into());
+
+        if issues.is_empty() {
+            Ok(())
+        } else {
+            Err(issues)
+        }
+    }
+}
+
+// ANCHOR[id=config_source_locations]
+/// Converts a byte offset into a 1-based `(line, column)` pair.
+fn line_col(source: &str, offset: usize) -> (usize, usize) {
+    let offset = offset.min(source.len());
+    let before = &source[..offset];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().map_or(0, |l| l.chars().count()) + 1;
+    (line, column)
+}
+
+/// Finds the line and column of a dotted key path such as `visual.gaps.inner` or
+/// `input.device[1].accel_speed` in TOML source.
+///
+/// Understands standard tables (`[a.b]`), arrays of tables (`[[a.b]]`) and dotted keys
+/// (`b.c = 1`) within a table. Inline tables are not searched; the issue is then reported
+/// without a location.
+fn locate_key(source: &str, path: &str) -> Option<(usize, usize)> {
+    // Split "input.device[1].accel_speed" into (["input", "device"], Some(1), ["accel_speed"]).
+    let (table_part, index, key_part) = match path.find('[') {
+        Some(open) => {
+            let close = path[open..].find(']')? + open;
+            let index: usize = path[open + 1..close].parse().ok()?;
+            let rest = path[close + 1..].trim_start_matches('.');
+            (&path[..open], Some(index), rest)
+        }
+        None => (path, None, ""),
+    };
+    let segments: Vec<&str> = table_part.split('.').collect();
+    let key_segments: Vec<&str> = if key_part.is_empty() { Vec::new() } else { key_part.split('.').collect() };
+
+    let mut current_table: Vec<String> = Vec::new();
+    let mut array_counts: HashMap<Vec<String>, usize> = HashMap::new();
+    let mut current_index: Option<usize> = None;
+
+    for (line_no, raw_line) in source.lines().enumerate() {
+        let trimmed = raw_line.trim_start();
+        let indent = raw_line.len() - trimmed.len();
+        let line = strip_comment(trimmed).trim_end();
+        if line.is_empty() {
+            continue;
+        }
+
+        if let Some(header) = line.strip_prefix("[[").and_then(|l| l.strip_suffix("]]")) {
+            current_table = split_key(header);
+            let count = array_counts.entry(current_table.clone()).or_insert(0);
+            current_index = Some(*count);
+            *count += 1;
+            if index.is_some() && key_segments.is_empty() && current_table == segments && current_index == index {
+                return Some((line_no + 1, indent + 1));
+            }
+            continue;
+        }
+        if let Some(header) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            current_table = split_key(header);
+            current_index = None;
+            if index.is_none() && current_table == segments {
+                return Some((line_no + 1, indent + 1));
+            }
+            continue;
+        }
+
+        let key = match line.split_once('=') {
+            Some((key, _)) => split_key(key),
+            None => continue,
+        };
+        let mut full: Vec<String> = current_table.clone();
+        full.extend(key);
+
+        let matches = match index {
+            Some(_) => {
+                current_index == index
+                    && full.len() == segments.len() + key_segments.len()
+                    && full[..segments.len()] == segments[..]
+                    && full[segments.len()..] == key_segments[..]
+            }
+            None => full == segments,
+        };
+        if matches {
+            return Some((line_no + 1, indent + 1));
+        }
+    }
+    None
+}
+
+fn split_key(key: &str) -> Vec<String> {
+    key.split('.')
+        .map(|part| part.trim().trim_matches('"').trim_matches('\'').to_string())
+        .collect()
+}
+
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' | '\'' => in_string = !in_string,
+            '#' if !in_string => return &line[..i],
+            _ => {}
+        }
     }
+    line
 }
 
 // ANCHOR[id=tests_module]
@@ -72,17 +1024,287 @@ mod tests {
     #[test]
     fn test_default_config() {
         let config = Config::default();
-        assert_eq!(config.performance.adaptive_performance_tuning, Some(false)); // Example assertion
-        // TODO[issue=tests_detail]: Add more detailed assertions for default values
+        assert!(!config.performance.adaptive_performance_tuning);
+        assert_eq!(config.performance.vrr, VrrPolicy::Fullscreen);
+        assert_eq!(config.performance.tearing, TearingPolicy::FullscreenOptIn);
+        assert_eq!(config.layout.default_layout, LayoutMode::Floating);
+        assert_eq!(config.input.keyboard.layout, "us");
+        assert!(!config.input.focus_follows_mouse);
+        assert!(config.validate().is_ok());
     }
 
     // ANCHOR[id=test_load_save_config]
     #[test]
     fn test_load_save_config() {
-        // TODO[issue=config_load_save_tests]: Implement tests for loading and saving configuration
-        // This will require actual file operations and a sample config file.
-        // For now, this is a placeholder.
-        let config = Config::load().unwrap();
-        config.save().unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("novade").join(CONFIG_FILE_NAME);
+
+        // A missing file yields defaults.
+        assert_eq!(Config::load_from_path(&path).unwrap(), Config::default());
+
+        let mut config = Config::default();
+        config.visual.gaps = GapsConfig { inner: 8, outer: 12 };
+        config.input.devices.push(DeviceConfig {
+            name: "Test Mouse".into(),
+            settings: DeviceSettings { accel_profile: Some(AccelProfile::Flat), ..Default::default() },
+        });
+        config.save_to_path(&path).unwrap();
+        assert_eq!(Config::load_from_path(&path).unwrap(), config);
+    }
+
+    #[test]
+    fn test_parse_full_example() {
+        let source = r##"
+[layout]
+default_layout = "master_stack"
+master_factor = 0.55
+new_workspace_output = "follow-mouse"
+
+[layout.outputs."HDMI-A-1"]
+default_layout = "floating"
+
+[performance]
+vrr = "always"
+tearing = "never"
+overlay_planes = false
+
+[input]
+focus_follows_mouse = true
+
+[input.touchpad]
+tap_to_click = true
+natural_scroll = true
+
+[[input.device]]
+name = "Logitech MX Master 3"
+accel_profile = "flat"
+accel_speed = -0.25
+
+[visual.gaps]
+inner = 8
+outer = 12
+
+[visual.border]
+active_color = "#ff8800"
+inactive_color = "#11223380"
+
+[visual.animations]
+reduce_motion = true
+
+[visual.effects]
+shadow_color = "#00000060"
+blur_passes = 4
+
+[[window_rule]]
+namespace = "novade-panel"
+blur = true
+
+[[window_rule]]
+app_id = "org.gnome.Calculator"
+corner_radius = 0.0
+shadow = false
+
+[color]
+lut_size = 17
+
+[color.outputs."HDMI-A-1"]
+hdr = true
+max_luminance_nits = 600
+
+[night_light]
+enabled = true
+schedule = "manual"
+start = "21:30"
+end = "06:45"
+temperature = 3500
+
+[recorder]
+directory = "~/Videos/Screencasts"
+fps = 60
+cursor = false
+
+[gpu]
+render_device = "0000:01:00.0"
+
+[debug]
+damage_flash = true
+"##;
+        let config = Config::from_toml_str(source).unwrap();
+        assert_eq!(config.layout.layout_for_output("DP-1"), TilingLayout::MasterStack);
+        assert_eq!(config.layout.layout_for_output("HDMI-A-1"), TilingLayout::None);
+        assert_eq!(config.layout.new_workspace_output, WorkspaceOutputPolicy::FollowMouse);
+        assert_eq!(config.performance.vrr, VrrPolicy::Always);
+        assert!(config.performance.direct_scanout);
+        assert!(!config.performance.overlay_planes);
+        assert!(config.input.focus_follows_mouse);
+        assert_eq!(config.visual.border.active_color, Color([0xff, 0x88, 0x00, 0xff]));
+        assert_eq!(config.visual.border.inactive_color, Color([0x11, 0x22, 0x33, 0x80]));
+        assert!(config.visual.animation_settings.reduce_motion);
+        assert_eq!(config.visual.effects.shadow_color, Color([0, 0, 0, 0x60]));
+        assert_eq!(config.visual.effects.blur_passes, 4);
+        assert!(config.visual.effects.blur);
+        assert_eq!(config.window_rules.len(), 2);
+        assert_eq!(config.window_rules[0].blur, Some(true));
+        assert_eq!(config.window_rules[1].corner_radius, Some(0.0));
+        assert_eq!(config.color.lut_size, 17);
+        let hdmi = &config.color.outputs["HDMI-A-1"];
+        assert!(hdmi.hdr);
+        assert_eq!((hdmi.sdr_white_nits, hdmi.max_luminance_nits), (203.0, 600.0));
+        assert_eq!(config.night_light.schedule, NightLightSchedule::Manual);
+        assert_eq!((config.night_light.start.minutes(), config.night_light.end.minutes()), (21 * 60 + 30, 6 * 60 + 45));
+        assert_eq!(config.night_light.temperature, 3500);
+        assert_eq!(config.night_light.transition_minutes, 30);
+        assert_eq!(config.recorder.directory.as_deref(), Some(Path::new("~/Videos/Screencasts")));
+        assert_eq!((config.recorder.fps, config.recorder.cursor), (60, false));
+        assert_eq!(config.recorder.encoder, "y4m");
+        assert_eq!(config.gpu.render_device.as_deref(), Some("0000:01:00.0"));
+        assert!(config.debug.damage_flash);
+
+        let mouse = config.input.settings_for_device("Logitech MX Master 3", false);
+        assert_eq!(mouse.accel_profile, Some(AccelProfile::Flat));
+        assert_eq!(mouse.accel_speed, Some(-0.25));
+        let touchpad = config.input.settings_for_device("SynPS/2 Touchpad", true);
+        assert_eq!(touchpad.tap_to_click, Some(true));
+        assert_eq!(touchpad.accel_profile, None);
+    }
+
+    #[test]
+    fn test_syntax_error_reports_line() {
+        let source = "[visual.gaps]\ninner = 8\nouter = \n";
+        match Config::from_toml_str(source) {
+            Err(ConfigError::Parse { line, .. }) => assert_eq!(line, 3),
+            other => panic!("expected parse error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_type_and_unknown_key_errors_report_line() {
+        match Config::from_toml_str("[input]\nfocus_follows_mouse = \"yes\"\n") {
+            Err(ConfigError::Parse { line, .. }) => assert_eq!(line, 2),
+            other => panic!("expected parse error, got {:?}", other),
+        }
+        match Config::from_toml_str("[visual]\n\ngapz = 3\n") {
+            Err(ConfigError::Parse { line, message, .. }) => {
+                assert_eq!(line, 3);
+                assert!(message.contains("gapz"), "{}", message);
+            }
+            other => panic!("expected parse error, got {:?}", other),
+        }
+        match Config::from_toml_str("[visual.border]\nactive_color = \"orange\"\n") {
+            Err(ConfigError::Parse { line, message, .. }) => {
+                assert_eq!(line, 2);
+                assert!(message.contains("must start with '#'"), "{}", message);
+            }
+            other => panic!("expected parse error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_validation_errors_include_line_numbers() {
+        let source = r#"
+[layout]
+master_factor = 1.5
+
+[[input.device]]
+name = "A"
+
+[[input.device]]
+name = "B"
+  accel_speed = 3.0 # too fast
+
+[visual]
+gaps.inner = -4
+"#;
+        let issues = match Config::from_toml_str(source) {
+            Err(ConfigError::Validation(issues)) => issues,
+            other => panic!("expected validation error, got {:?}", other),
+        };
+        let find = |path: &str| issues.iter().find(|i| i.path == path).unwrap_or_else(|| panic!("no issue for {}", path));
+
+        assert_eq!(find("layout.master_factor").line, Some(3));
+        let speed = find("input.device[1].accel_speed");
+        assert_eq!((speed.line, speed.column), (Some(10), Some(3)));
+        assert_eq!(find("visual.gaps.inner").line, Some(13));
+
+        let rendered = ConfigError::Validation(issues.clone()).to_string();
+        assert!(rendered.contains("line 3, column 1: layout.master_factor"), "{}", rendered);
+    }
+
+    #[test]
+    fn test_keybindings_are_parsed_and_validated() {
+        let source = "[[keybinding]]\nkeys = \"Super+Return\"\naction = \"spawn\"\ncommand = \"foot\"\n\n[[keybinding]]\nkeys = \"Super+Nope\"\naction = \"quit\"\n";
+        match Config::from_toml_str(source) {
+            Err(ConfigError::Validation(issues)) => {
+                assert_eq!(issues.len(), 1);
+                assert_eq!(issues[0].path, "keybinding[1].keys");
+                assert_eq!(issues[0].line, Some(7));
+            }
+            other => panic!("expected validation error, got {:?}", other),
+        }
+        let config = Config::from_toml_str("[[keybinding]]\nkeys = \"Super+Return\"\naction = \"spawn\"\ncommand = \"foot\"\n").unwrap();
+        assert_eq!(
+            config.keybindings[0].action,
+            crate::compositor::keybindings::KeybindingAction::Spawn { command: "foot".into() }
+        );
+    }
+
+    #[test]
+    fn test_effects_and_window_rule_validation() {
+        let source = "[visual.effects]\nblur_passes = 9\n\n[[window_rule]]\nblur = true\n\n[[window_rule]]\napp_id = \"foot\"\ncorner_radius = 400.0\n";
+        let issues = match Config::from_toml_str(source) {
+            Err(ConfigError::Validation(issues)) => issues,
+            other => panic!("expected validation error, got {:?}", other),
+        };
+        let paths: Vec<&str> = issues.iter().map(|i| i.path.as_str()).collect();
+        assert_eq!(paths, ["visual.effects.blur_passes", "window_rule[0]", "window_rule[1].corner_radius"]);
+        assert_eq!(issues[1].line, Some(4));
+        assert_eq!(issues[2].line, Some(9));
+    }
+
+    #[test]
+    fn test_night_light_validation() {
+        let source = "[night_light]\nenabled = true\nlatitude = 95.0\ntemperature = 8000\n";
+        let issues = match Config::from_toml_str(source) {
+            Err(ConfigError::Validation(issues)) => issues,
+            other => panic!("expected validation error, got {:?}", other),
+        };
+        let paths: Vec<&str> = issues.iter().map(|i| i.path.as_str()).collect();
+        assert_eq!(paths, ["night_light.temperature", "night_light.latitude", "night_light.schedule"]);
+        assert_eq!(issues[1].line, Some(3));
+
+        match Config::from_toml_str("[night_light]\nstart = \"25:00\"\n") {
+            Err(ConfigError::Parse { line, message, .. }) => {
+                assert_eq!(line, 2);
+                assert!(message.contains("must be HH:MM"), "{}", message);
+            }
+            other => panic!("expected parse error, got {:?}", other),
+        }
+        // Without a schedule to follow, missing coordinates are fine.
+        assert!(Config::from_toml_str("[night_light]\ntemperature = 3000\n").is_ok());
+    }
+
+    #[test]
+    fn test_recorder_validation() {
+        let issues = match Config::from_toml_str("[recorder]\nfps = 0\nencoder = \"\"\n") {
+            Err(ConfigError::Validation(issues)) => issues,
+            other => panic!("expected validation error, got {:?}", other),
+        };
+        let paths: Vec<&str> = issues.iter().map(|i| i.path.as_str()).collect();
+        assert_eq!(paths, ["recorder.fps", "recorder.encoder"]);
+        assert_eq!(issues[0].line, Some(2));
+        assert!(Config::from_toml_str("[recorder]\nfps = 120\n").is_ok());
+    }
+
+    #[test]
+    fn test_duplicate_device_is_rejected() {
+        let source = "[[input.device]]\nname = \"A\"\n[[input.device]]\nname = \"A\"\n";
+        match Config::from_toml_str(source) {
+            Err(ConfigError::Validation(issues)) => {
+                assert_eq!(issues.len(), 1);
+                assert_eq!(issues[0].path, "input.device[1].name");
+                assert_eq!(issues[0].line, Some(4));
+            }
+            other => panic!("expected validation error, got {:?}", other),
+        }
     }
 }
@@ -0,0 +1,174 @@
+// novade-system/src/compositor/config/reload.rs
+//! Runtime reloading of the compositor configuration.
+//!
+//! Reload requests arrive from other threads (the D-Bus service, the IPC socket) through a
+//! calloop channel, so the configuration is always re-read and applied on the compositor thread.
+
+use std::path::{Path, PathBuf};
+
+use smithay::input::keyboard::XkbConfig;
+use smithay::reexports::calloop::{
+    channel::{self, Channel, Event as ChannelEvent, Sender},
+    LoopHandle,
+};
+use tokio::sync::oneshot;
+use tracing::{info, warn};
+
+use super::{Config, ConfigError};
+use crate::compositor::errors::CompositorError;
+use crate::compositor::keybindings;
+use crate::compositor::state::DesktopState;
+use crate::compositor::tiling;
+
+/// Outcome of a reload as reported back to the requester. On failure the previous
+/// configuration stays active and the error text (including line numbers) is returned.
+pub type ConfigReloadResult = Result<(), String>;
+
+/// A request to re-read the configuration file and apply it.
+#[derive(Debug)]
+pub struct ConfigReloadRequest {
+    /// Receives the result once the reload has been applied. `None` for fire-and-forget requests.
+    pub reply: Option<oneshot::Sender<ConfigReloadResult>>,
+}
+
+/// Cloneable, thread-safe handle used to trigger config reloads on the compositor thread.
+#[derive(Debug, Clone)]
+pub struct ConfigReloadHandle {
+    sender: Sender<ConfigReloadRequest>,
+    config_path: PathBuf,
+}
+
+impl ConfigReloadHandle {
+    /// Path of the configuration file reloads read.
+    pub fn config_path(&self) -> &Path {
+        &self.config_path
+    }
+
+    /// Requests a reload and waits for its result.
+    pub async fn reload(&self) -> ConfigReloadResult {
+        let (tx, rx) = oneshot::channel();
+        self.sender
+            .send(ConfigReloadRequest { reply: Some(tx) })
+            .map_err(|_| "compositor event loop is not running".to_string())?;
+        rx.await.map_err(|_| "compositor dropped the reload request".to_string())?
+    }
+
+    /// Requests a reload without waiting for the result.
+    pub fn request_reload(&self) -> bool {
+        self.sender.send(ConfigReloadRequest { reply: None }).is_ok()
+    }
+}
+
+/// Creates the reload channel and registers its receiving end on the compositor event loop.
+/// `config_path` must be the file [`DesktopState::reload_config`] reads.
+pub fn register_reload_source(
+    loop_handle: &LoopHandle<'static, DesktopState>,
+    config_path: PathBuf,
+) -> Result<ConfigReloadHandle, CompositorError> {
+    let (sender, channel): (Sender<ConfigReloadRequest>, Channel<ConfigReloadRequest>) = channel::channel();
+    loop_handle
+        .insert_source(channel, |event, _, state: &mut DesktopState| {
+            if let ChannelEvent::Msg(request) = event {
+                let result = state.reload_config().map_err(|e| e.to_string());
+                if let Some(reply) = request.reply {
+                    let _ = reply.send(result);
+                }
+            }
+        })
+        .map_err(|e| CompositorError::ConfigError(format!("Failed to register config reload source: {}", e)))?;
+    Ok(ConfigReloadHandle { sender, config_path })
+}
+
+// ANCHOR: DesktopStateConfigIntegration
+impl DesktopState {
+    /// Re-reads the configuration from `config_path` and applies it. On error the current
+    /// configuration is kept.
+    pub fn reload_config(&mut self) -> Result<(), ConfigError> {
+        let path: PathBuf = self.config_path.clone();
+        match Config::load_from_path(&path) {
+            Ok(config) => {
+                info!("Reloaded compositor configuration from {}.", path.display());
+                self.apply_config(config);
+                Ok(())
+            }
+            Err(e) => {
+                warn!("Keeping previous compositor configuration, reload failed: {}", e);
+                Err(e)
+            }
+        }
+    }
+
+    /// Makes `config` the active configuration and pushes every changed setting into the
+    /// running compositor.
+    pub fn apply_config(&mut self, config: Config) {
+        let previous = std::mem::replace(&mut self.config, config);
+
+        self.apply_animation_settings(self.config.visual.animation_settings.clone());
+        self.keybindings = keybindings::resolve_keybindings(&self.config.keybindings);
+
+        if let Some(keyboard) = self.primary_seat.get_keyboard() {
+            let kb = self.config.input.keyboard.clone();
+            if kb.repeat_rate != previous.input.keyboard.repeat_rate || kb.repeat_delay != previous.input.keyboard.repeat_delay {
+                keyboard.change_repeat_info(kb.repeat_rate, kb.repeat_delay);
+            }
+            if kb.layout != previous.input.keyboard.layout
+                || kb.variant != previous.input.keyboard.variant
+                || kb.model != previous.input.keyboard.model
+                || kb.options != previous.input.keyboard.options
+            {
+                let xkb_config = XkbConfig {
+                    rules: "",
+                    model: &kb.model,
+                    layout: &kb.layout,
+                    variant: &kb.variant,
+                    options: kb.options.clone(),
+                };
+                if let Err(e) = keyboard.set_xkb_config(self, xkb_config) {
+                    warn!("Failed to apply keyboard layout '{}': {:?}", kb.layout, e);
+                }
+            }
+        }
+
+        if self.config.input != previous.input {
+            let input_config = self.config.input.clone();
+            for device in &mut self.libinput_devices {
+                super::devices::configure_libinput_device(device, &input_config);
+            }
+        }
+
+        if self.config.performance != previous.performance {
+            let performance = self.config.performance.clone();
+            for scheduler in self.frame_schedulers.values_mut() {
+                scheduler.configure(&performance);
+            }
+            self.schedule_redraw_all();
+        }
+
+        if self.config.color != previous.color {
+            self.reconfigure_color();
+        }
+
+        if self.config.night_light != previous.night_light {
+            self.start_night_light();
+        }
+
+        if self.config.debug != previous.debug {
+            self.apply_debug_config();
+        }
+
+        if self.config.visual.effects != previous.visual.effects
+            || self.config.visual.border.corner_radius != previous.visual.border.corner_radius
+            || self.config.window_rules != previous.window_rules
+        {
+            self.reconfigure_effects();
+        }
+
+        if self.config.layout != previous.layout || self.config.visual.gaps != previous.visual.gaps {
+            let outputs: Vec<String> = self.output_workspaces.keys().cloned().collect();
+            for output_name in outputs {
+                tiling::apply_layout_for_output(self, &output_name);
+            }
+        }
+    }
+}
+// ANCHOR_END: DesktopStateConfigIntegration
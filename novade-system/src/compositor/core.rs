@@ -2,10 +2,12 @@
 // Main Wayland compositor logic, event loop, and global state management.
 
 use std::{
+    collections::HashMap,
     process::Command,
     sync::{Arc, Mutex as StdMutex},
-    time::Duration,
+    time::{Duration, Instant},
     env,
+    path::PathBuf,
 };
 use tracing::{info, warn, error, debug};
 
@@ -73,6 +75,12 @@ use smithay::{
 
 use crate::compositor::{
     state::DesktopState,
+    animations::AnimationType,
+    color::{gles::{ColorConvertedElement, GlesColorPipeline}, Lut3d},
+    damage::DamageRect,
+    effects::{gles::{BackdropBlur, GlesEffects, RoundedElement}, SurfaceEffects},
+    overview::OverviewDrawOp,
+    shell::xdg_shell::types::{DomainWindowIdentifier, ManagedWindow},
     // render::MainRenderer, // Will be used for initializing renderer
     // input::initialize_input_system, // Will be used for input setup
     // xwayland::initialize_xwayland, // Will be used for XWayland setup
@@ -81,6 +89,27 @@ use crate::compositor::{
 
 const SOCKET_NAME: &str = "novade-wayland-0";
 
+/// Background of the winit window where nothing is drawn.
+const WINIT_CLEAR_COLOR: [f32; 4] = [0.1, 0.1, 0.3, 1.0];
+/// Longest the winit loop sleeps before dispatching Wayland clients again.
+const CLIENT_POLL_INTERVAL: Duration = Duration::from_millis(4);
+
+smithay::backend::renderer::element::render_elements! {
+    /// Elements of a winit frame: client surfaces where they are, possibly with rounded
+    /// corners or colour converted, their shadows and backdrop blur, and while the overview is
+    /// shown its scaled windows and solid rectangles.
+    WinitRenderElement<=Gles2Renderer>;
+    Surface=smithay::backend::renderer::element::surface::WaylandSurfaceRenderElement<Gles2Renderer>,
+    Scaled=smithay::backend::renderer::element::utils::RescaleRenderElement<smithay::backend::renderer::element::surface::WaylandSurfaceRenderElement<Gles2Renderer>>,
+    Rounded=RoundedElement<smithay::backend::renderer::element::surface::WaylandSurfaceRenderElement<Gles2Renderer>>,
+    ScaledRounded=smithay::backend::renderer::element::utils::RescaleRenderElement<RoundedElement<smithay::backend::renderer::element::surface::WaylandSurfaceRenderElement<Gles2Renderer>>>,
+    Converted=ColorConvertedElement<smithay::backend::renderer::element::surface::WaylandSurfaceRenderElement<Gles2Renderer>>,
+    ScaledConverted=smithay::backend::renderer::element::utils::RescaleRenderElement<ColorConvertedElement<smithay::backend::renderer::element::surface::WaylandSurfaceRenderElement<Gles2Renderer>>>,
+    Shadow=smithay::backend::renderer::gles2::element::PixelShaderElement,
+    Blur=smithay::backend::renderer::element::texture::TextureRenderElement<smithay::backend::renderer::gles2::Gles2Texture>,
+    Solid=smithay::backend::renderer::element::solid::SolidColorRenderElement,
+}
+
 pub fn run_compositor() -> Result<(), CompositorError> {
     info!("Starting NovaDE Wayland Compositor core...");
 
@@ -108,6 +137,46 @@ pub fn run_compositor() -> Result<(), CompositorError> {
         }
     }).map_err(|e| CompositorError::EventLoopError(e.into()))?;
 
+    // Allow the configuration to be reloaded at runtime (D-Bus, IPC).
+    let config_reload_handle = crate::compositor::config::reload::register_reload_source(&event_loop.handle(), desktop_state.config_path.clone())?;
+    desktop_state.config_reload_handle = Some(config_reload_handle.clone());
+    std::thread::Builder::new()
+        .name("novade-config-dbus".into())
+        .spawn(move || {
+            let runtime = match tokio::runtime::Builder::new_current_thread().enable_all().build() {
+                Ok(rt) => rt,
+                Err(e) => {
+                    error!("Failed to create runtime for config D-Bus service: {}", e);
+                    return;
+                }
+            };
+            runtime.block_on(async move {
+                match crate::dbus_integration::DbusServiceManager::new_session().await {
+                    Ok(manager) => {
+                        if let Err(e) = manager.serve_compositor_config_service(config_reload_handle).await {
+                            warn!("Compositor config D-Bus service unavailable: {}", e);
+                            return;
+                        }
+                        std::future::pending::<()>().await;
+                    }
+                    Err(e) => warn!("No session bus, compositor config D-Bus service disabled: {}", e),
+                }
+            });
+        })
+        .map_err(|e| CompositorError::Internal(format!("Failed to spawn config D-Bus thread: {}", e)))?;
+
+    // JSON IPC socket for novadectl and scripts, next to the Wayland socket.
+    let runtime_dir = env::var_os("XDG_RUNTIME_DIR").map(PathBuf::from).unwrap_or_else(env::temp_dir);
+    let ipc_socket_path =
+        crate::compositor::ipc::protocol::socket_path_for_display(&runtime_dir, &socket_name.to_string_lossy());
+    match crate::compositor::ipc::start_ipc_server(&event_loop.handle(), ipc_socket_path, None) {
+        Ok(ipc) => {
+            env::set_var(crate::compositor::ipc::protocol::IPC_SOCKET_ENV, &ipc.socket_path);
+            desktop_state.ipc = Some(ipc);
+        }
+        Err(e) => warn!("Compositor IPC disabled: {}", e),
+    }
+
     info!("Listening on Wayland socket: {:?}", socket_name);
     env::set_var("WAYLAND_DISPLAY", socket_name.to_string_lossy().as_ref());
 
@@ -115,7 +184,13 @@ pub fn run_compositor() -> Result<(), CompositorError> {
     // --- Backend Initialization (Winit for now) ---
     info!("Attempting Winit backend initialization...");
     let (winit_event_loop, mut winit_data, winit_gles_renderer) =
-        crate::compositor::backend::winit_backend::init_winit_backend(display.handle(), desktop_state.clock.id())?;
+        match crate::compositor::backend::winit_backend::init_winit_backend(display.handle(), desktop_state.clock.id()) {
+            Ok(backend) => backend,
+            Err(e) => {
+                warn!("Winit GLES backend unavailable, running headless on the software renderer: {}", e);
+                return crate::compositor::backend::headless::run_headless(&mut event_loop, &mut display, &mut desktop_state);
+            }
+        };
 
     // Store the GLES renderer from Winit into DesktopState's MainNovaRenderer
     let gles_nova_renderer = GlesNovaRenderer::new(winit_gles_renderer);
@@ -126,6 +201,11 @@ pub fn run_compositor() -> Result<(), CompositorError> {
     desktop_state.output_manager_state.add_output(&winit_data.smithay_output);
     desktop_state.space.lock().unwrap().map_output(&winit_data.smithay_output, (0,0).into(), winit_data.smithay_output.current_mode().unwrap());
     info!("Winit output '{}' added to compositor state and space.", winit_data.smithay_output.name());
+    desktop_state.ensure_workspaces_for_output(&winit_data.smithay_output.name());
+    desktop_state.emit_ipc_event(crate::compositor::ipc::protocol::IpcEvent::Output {
+        change: crate::compositor::ipc::protocol::OutputChange::Added,
+        name: winit_data.smithay_output.name(),
+    });
 
     // Store Winit event loop proxy for requesting redraws from other parts of the system
     desktop_state.winit_event_loop_proxy = Some(winit_data.event_loop_proxy.clone());
@@ -169,6 +249,9 @@ pub fn run_compositor() -> Result<(), CompositorError> {
 
     info!("NovaDE Compositor starting Winit event loop...");
 
+    // Sequence number of presented frames, for wp_presentation feedback.
+    let mut frame_seq: u64 = 0;
+
     winit_event_loop.run(move |event, _, control_flow| {
         // Dispatch Calloop events first, non-blockingly
         let mut calloop_dispatcher = Dispatcher::new(&mut desktop_state, |_, _, _| PostAction::Continue);
@@ -182,6 +265,8 @@ pub fn run_compositor() -> Result<(), CompositorError> {
             return;
         }
 
+        // Input and window changes may change what is shown.
+        let window_event = matches!(event, smithay::reexports::winit::event::Event::WindowEvent { .. });
         crate::compositor::backend::winit_backend::handle_winit_event(
             event,
             &mut desktop_state,
@@ -198,6 +283,18 @@ pub fn run_compositor() -> Result<(), CompositorError> {
             warn!("Error flushing Wayland clients: {}", e);
         }
 
+        // Advance window animations and the overview transition; running ones need another frame.
+        if desktop_state.advance_animations(std::time::Instant::now()) || window_event {
+            desktop_state.frame_scheduler(&winit_data.smithay_output).request_redraw();
+        }
+
+        // The winit window has no page-flip events, so a submitted frame counts as presented
+        // once the vblank it was aimed at has passed.
+        let scheduler = desktop_state.frame_scheduler(&winit_data.smithay_output);
+        if scheduler.presentation_target().is_some_and(|target| target <= scheduler.now()) {
+            desktop_state.frame_presented(&winit_data.smithay_output, None, false);
+        }
+
         // Perform rendering if needed (e.g., if damage occurred or redraw requested)
         // This is a simplified render call. A real compositor would track damage.
         if control_flow != &ControlFlow::Exit && desktop_state.running.read().unwrap().clone() { // Check running again
@@ -217,56 +314,187 @@ pub fn run_compositor() -> Result<(), CompositorError> {
 
             // Actual rendering logic for Winit backend
             if let smithay::reexports::winit::event::Event::RedrawRequested(_) = event {
+                // The winit window has no VRR.
+                desktop_state.update_sync_mode(&winit_data.smithay_output, false);
+                let render_started = desktop_state.frame_scheduler(&winit_data.smithay_output).now();
+                // The cursor is not part of the winit frame, the recorder draws it.
+                let recording_region = desktop_state.recording_region(&winit_data.smithay_output);
+                let overview_ops = desktop_state
+                    .overview
+                    .is_active()
+                    .then(|| desktop_state.overview.draw_ops(&winit_data.smithay_output.name(), Instant::now()));
+                let damage_flash = desktop_state.damage_flash_overlay(&winit_data.smithay_output.name(), Instant::now());
+                let closing_windows = desktop_state.closing_windows_on(&winit_data.smithay_output.name());
+                let window_effects: HashMap<DomainWindowIdentifier, SurfaceEffects> = desktop_state
+                    .windows
+                    .values()
+                    .filter_map(|window| Some((window.domain_id, desktop_state.surface_effects(window.wl_surface_ref()?))))
+                    .collect();
+                let output_lut = desktop_state.output_color(&winit_data.smithay_output.name()).output_lut.clone();
+                let surface_luts = desktop_state.window_color_transforms(&winit_data.smithay_output.name());
                 if let Some(main_renderer) = desktop_state.main_renderer.as_mut() {
                     if let MainNovaRenderer::Gles(gles_renderer_wrapper) = main_renderer {
                         let output = &winit_data.smithay_output;
                         let renderer_node = &winit_data.renderer_node; // This is the Winit window's node
 
                         let mut space_lock = desktop_state.space.lock().unwrap();
-                        let mut damage_tracker = smithay::backend::renderer::damage::OutputDamageTracker::new_for_output(output); // Recreate for now, should be stored
 
-                        // Gather render elements
-                        let mut render_elements: Vec<smithay::backend::renderer::element::surface::WaylandSurfaceRenderElement<Gles2Renderer>> = Vec::new();
+                        // Gather render elements, topmost first.
+                        let mut render_elements: Vec<WinitRenderElement> = damage_flash_elements(&damage_flash);
                         let mut surfaces_for_callback: Vec<wl_surface::WlSurface> = Vec::new();
 
-                        // Iterate over windows in space, filter for current output
-                        for window_element in space_lock.elements_for_output(output).unwrap_or_default() {
-                            if !window_element.is_mapped() { continue; } // Skip unmapped
-
-                            if let Some(surface) = window_element.wl_surface() {
-                                if let Some(surface_attributes) = surface.data::<smithay::wayland::compositor::SurfaceAttributes>() {
-                                    if let Some(buffer) = surface_attributes.buffer.as_ref() {
-                                        // Texture caching logic would go here. For now, always import.
-                                        match gles_renderer_wrapper.import_shm_buffer(buffer, Some(surface_attributes), &[]) {
-                                            Ok(texture) => {
-                                                // Attempt to create the render element
-                                                match smithay::backend::renderer::element::surface::WaylandSurfaceRenderElement::from_space_view(
-                                                    &mut gles_renderer_wrapper.inner,
-                                                    window_element,
-                                                    surface_attributes,
-                                                    &texture,
-                                                    output,
-                                                    &space_lock,
-                                                ) {
-                                                    Ok(element) => {
-                                                        render_elements.push(element);
-                                                        surfaces_for_callback.push(surface.clone()); // Clone WlSurface for callback
-                                                    },
-                                                    Err(e) => {
-                                                        warn!("Failed to create render element for surface {:?}: {}", surface.id(), e);
-                                                    }
-                                                }
-                                            }
-                                            Err(e) => {
-                                                warn!("Failed to import SHM buffer for surface {:?}: {}", surface.id(), e);
-                                            }
-                                        }
-                                    }
-                                }
+                        // The overview draws the workspace's windows itself, scaled into its grid.
+                        let space_windows = match &overview_ops {
+                            Some(ops) => {
+                                render_elements.extend(overview_render_elements(
+                                    &mut gles_renderer_wrapper.inner,
+                                    ops,
+                                    &desktop_state.windows,
+                                    output.current_scale().fractional_scale(),
+                                    &mut surfaces_for_callback,
+                                ));
+                                Vec::new()
+                            }
+                            // Topmost first, without windows hidden behind opaque ones.
+                            None => crate::compositor::spatial_index::visible_windows(
+                                &space_lock,
+                                output,
+                                |window| desktop_state.animation_manager.has_active_animations(Some(window.domain_id)),
+                                |window| {
+                                    let fullscreen = window.state.read().unwrap().fullscreen;
+                                    let radius = window_effects.get(&window.domain_id).map_or(0.0, |effects| effects.corner_radius);
+                                    if fullscreen { 0.0 } else { radius }
+                                },
+                            ),
+                        };
+
+                        // Windows are drawn where their animations currently have them. Closing
+                        // windows are no longer in the space and fade out above the others. Each
+                        // window's elements, topmost first, are kept apart with the blur to draw
+                        // behind them until everything below is known.
+                        let mut window_layers: Vec<(Vec<WinitRenderElement>, Option<(DomainWindowIdentifier, BackdropBlur)>)> = Vec::new();
+                        let output_geometry = space_lock.output_geometry(output).unwrap_or_default();
+                        let output_scale = output.current_scale().fractional_scale();
+                        let closing = closing_windows
+                            .iter()
+                            .filter(|_| overview_ops.is_none())
+                            .map(|(window, geometry)| (&**window, Some(*geometry)));
+                        let shown = space_windows.into_iter().map(|window| (window, None));
+                        for (window_element, closing_geometry) in closing.chain(shown) {
+                            let Some(surface) = window_element.wl_surface() else { continue };
+                            let location = match closing_geometry {
+                                Some(geometry) => geometry.loc,
+                                None => match space_lock.element_location(window_element) {
+                                    Some(location) => location,
+                                    None => continue,
+                                },
+                            };
+                            let animations = &desktop_state.animation_manager;
+                            let real = Rectangle::from_loc_and_size(location - output_geometry.loc, window_element.geometry().size);
+                            let drawn = animations.render_geometry(window_element.domain_id, real);
+                            let alpha = animations.value_or_resting(window_element.domain_id, AnimationType::Opacity);
+                            let (activated, fullscreen) = {
+                                let state = window_element.state.read().unwrap();
+                                (state.activated, state.fullscreen)
+                            };
+                            // Fullscreen windows are drawn square and without a shadow.
+                            let effects = window_effects.get(&window_element.domain_id).cloned().unwrap_or_default();
+                            let corner_radius = if fullscreen { 0.0 } else { effects.corner_radius };
+                            let mut elements = window_render_elements(
+                                &mut gles_renderer_wrapper.inner,
+                                &mut desktop_state.gles_effects,
+                                &mut desktop_state.gles_color,
+                                &surface_luts,
+                                &surface,
+                                window_element.geometry().loc,
+                                real.size,
+                                drawn,
+                                output_scale,
+                                alpha,
+                                corner_radius,
+                            );
+                            let border = &desktop_state.config.visual.border;
+                            if border.width > 0 && !fullscreen {
+                                let color = if activated { border.active_color } else { border.inactive_color };
+                                let buffers = desktop_state.border_buffers.entry(window_element.domain_id).or_default();
+                                elements.extend(border_elements(buffers, drawn, border.width, color.to_f32_array(), output_scale, alpha));
+                            }
+                            if let Some(shadow) = effects.shadow.as_ref().filter(|_| !fullscreen) {
+                                let shadow = desktop_state.gles_effects.shadow_element(
+                                    &mut gles_renderer_wrapper.inner,
+                                    window_element.domain_id,
+                                    drawn,
+                                    corner_radius,
+                                    shadow,
+                                    output_scale,
+                                    alpha,
+                                );
+                                elements.extend(shadow.map(WinitRenderElement::Shadow));
+                            }
+                            let blur = effects.blur.map(|blur| {
+                                let rects = match blur.region {
+                                    Some(region) => region
+                                        .iter()
+                                        .map(|rect| surface_rect_to_drawn(rect, window_element.geometry().loc, real.size, drawn))
+                                        .collect(),
+                                    None => vec![drawn],
+                                };
+                                (window_element.domain_id, BackdropBlur { rects, geometry: drawn, corner_radius, style: blur.style, alpha })
+                            });
+                            window_layers.push((elements, blur));
+                            if closing_geometry.is_none() {
+                                surfaces_for_callback.push(surface.clone());
                             }
                         }
                         drop(space_lock); // Release lock before rendering
 
+                        // Blur behind windows bottom-most first, so blurs show the blurs below them.
+                        let transform = output.current_transform();
+                        let output_size = transform.transform_size(output.current_mode().unwrap().size);
+                        for index in (0..window_layers.len()).rev() {
+                            let (layers, below) = window_layers.split_at_mut(index + 1);
+                            let (elements, blur) = &mut layers[index];
+                            let Some((window_id, blur)) = blur else { continue };
+                            let below: Vec<&WinitRenderElement> = below.iter().flat_map(|(elements, _)| elements.iter()).collect();
+                            match desktop_state.gles_effects.backdrop_blur(
+                                &mut gles_renderer_wrapper.inner,
+                                *window_id,
+                                blur,
+                                &below,
+                                output_size,
+                                output_scale,
+                                WINIT_CLEAR_COLOR,
+                            ) {
+                                Ok(blurred) => elements.extend(blurred.into_iter().map(WinitRenderElement::Blur)),
+                                Err(e) => warn!("Failed to blur behind window {:?}: {}", window_id, e),
+                            }
+                        }
+                        render_elements.extend(window_layers.into_iter().flat_map(|(elements, _)| elements));
+
+                        let windows = &desktop_state.windows;
+                        desktop_state.border_buffers.retain(|window_id, _| windows.contains_key(window_id));
+                        desktop_state.gles_effects.retain_windows(|window_id| windows.contains_key(window_id));
+
+                        // Outputs with an ICC profile are composited offscreen, then converted
+                        // whole while drawing to the window.
+                        let converted_scene = output_lut.as_ref().and_then(|lut| {
+                            desktop_state
+                                .gles_color
+                                .composite_output(
+                                    &mut gles_renderer_wrapper.inner,
+                                    output,
+                                    output_size,
+                                    output_scale,
+                                    &render_elements,
+                                    WINIT_CLEAR_COLOR,
+                                    lut,
+                                )
+                                .unwrap_or_else(|e| {
+                                    warn!("Failed to composite output {} for colour conversion: {}", output.name(), e);
+                                    None
+                                })
+                        });
+
                         // Bind the graphics backend for rendering
                         if let Err(e) = winit_graphics_backend.bind() {
                             error!("Failed to bind Winit graphics backend: {}", e);
@@ -274,29 +502,65 @@ pub fn run_compositor() -> Result<(), CompositorError> {
                             return;
                         }
 
-                        let render_result = damage_tracker.render_output(
-                            &mut gles_renderer_wrapper.inner,
-                            renderer_node,
-                            0, // age
-                            output.current_mode().unwrap().size,
-                            output.current_scale(),
-                            output.current_transform(),
-                            &render_elements[..], // Pass as slice
-                            [0.1, 0.1, 0.3, 1.0], // Clear color: dark blue
-                        );
+                        // Only what changed since the back buffer was last drawn is repainted.
+                        // Switching between direct and converted drawing starts over with a
+                        // fresh tracker, as the other one's history no longer matches the buffers.
+                        let buffer_age = winit_graphics_backend.buffer_age().unwrap_or(0);
+                        let render_result = match &converted_scene {
+                            Some(scene) => {
+                                desktop_state.output_damage_trackers.remove(&output.name());
+                                let damage_tracker =
+                                    desktop_state.gles_color.present_tracker(output).expect("the output was composited this frame");
+                                damage_tracker.render_output(
+                                    &mut gles_renderer_wrapper.inner,
+                                    renderer_node,
+                                    buffer_age,
+                                    output.current_mode().unwrap().size,
+                                    output.current_scale(),
+                                    output.current_transform(),
+                                    std::slice::from_ref(scene),
+                                    WINIT_CLEAR_COLOR,
+                                )
+                            }
+                            None => {
+                                desktop_state.gles_color.forget_output(&output.name());
+                                let damage_tracker = desktop_state
+                                    .output_damage_trackers
+                                    .entry(output.name())
+                                    .or_insert_with(|| smithay::backend::renderer::damage::OutputDamageTracker::from_output(output));
+                                damage_tracker.render_output(
+                                    &mut gles_renderer_wrapper.inner,
+                                    renderer_node,
+                                    buffer_age,
+                                    output.current_mode().unwrap().size,
+                                    output.current_scale(),
+                                    output.current_transform(),
+                                    &render_elements[..], // Pass as slice
+                                    WINIT_CLEAR_COLOR,
+                                )
+                            }
+                        };
 
                         match render_result {
                             Ok(render_damage) => {
+                                // Read back before the buffers are swapped.
+                                let recording_frame = recording_region
+                                    .map(|region| read_back_gles(&mut gles_renderer_wrapper.inner, region).map(|pixels| (region, pixels)));
+                                match recording_frame {
+                                    Some(Ok((region, pixels))) => desktop_state.submit_recording_frame(output, region, pixels, false),
+                                    Some(Err(e)) => desktop_state.abort_recording(format!("cannot read back frames: {}", e)),
+                                    None => {}
+                                }
                                 if let Err(e) = winit_graphics_backend.submit(render_damage.as_ref().map(|v| &v[..])) {
                                     error!("Winit graphics backend submit failed: {}", e);
                                 } else {
                                     debug!("Winit frame submitted with damage: {:?}", render_damage);
-                                    // Send frame callbacks
-                                    let time = desktop_state.clock.now();
-                                    for elem in render_elements {
-                                        if let Some(surface) = elem.wl_surface() {
-                                            surface.send_frame_done(time);
-                                        }
+                                    frame_seq += 1;
+                                    // Presentation feedback and frame callbacks follow at the vblank.
+                                    desktop_state.frame_submitted(output, render_started, frame_seq, surfaces_for_callback);
+                                    // Keep drawing until the damage flashes have faded.
+                                    if !damage_flash.is_empty() {
+                                        desktop_state.frame_scheduler(output).request_redraw();
                                     }
                                 }
                             }
@@ -312,6 +576,21 @@ pub fn run_compositor() -> Result<(), CompositorError> {
 
         if !*desktop_state.running.read().unwrap() {
             *control_flow = ControlFlow::Exit;
+        } else {
+            // Render when the frame scheduler says so, and wake up for the next frame or the
+            // presentation of the pending one. Wayland clients are only dispatched when the
+            // loop wakes, so it never sleeps longer than CLIENT_POLL_INTERVAL.
+            let scheduler = desktop_state.frame_scheduler(&winit_data.smithay_output);
+            if scheduler.should_render() {
+                winit_graphics_backend.window().request_redraw();
+            }
+            let now = scheduler.now();
+            let wake_in = [scheduler.time_until_render(), scheduler.presentation_target().map(|target| target.saturating_sub(now))]
+                .into_iter()
+                .flatten()
+                .min()
+                .map_or(CLIENT_POLL_INTERVAL, |wake_in| wake_in.min(CLIENT_POLL_INTERVAL));
+            *control_flow = ControlFlow::WaitUntil(Instant::now() + wake_in);
         }
     }); // winit_event_loop.run consumes the loop and blocks until exit.
 
@@ -320,6 +599,205 @@ pub fn run_compositor() -> Result<(), CompositorError> {
     Ok(())
 }
 
+/// The `[debug] damage_flash` overlay as solid rectangles. They get new ids every frame, so the
+/// damage tracker repaints them as they fade and once more when they are gone.
+fn damage_flash_elements(flash: &[(DamageRect, [f32; 4])]) -> Vec<WinitRenderElement> {
+    use smithay::backend::renderer::element::{solid::SolidColorRenderElement, Id, Kind};
+    use smithay::backend::renderer::utils::CommitCounter;
+
+    flash
+        .iter()
+        .rev()
+        .map(|(rect, color)| {
+            let geometry = Rectangle::<i32, smithay::utils::Physical>::from_loc_and_size(
+                (rect.left().floor() as i32, rect.top().floor() as i32),
+                (rect.size.width.ceil() as i32, rect.size.height.ceil() as i32),
+            );
+            WinitRenderElement::Solid(SolidColorRenderElement::new(Id::new(), geometry, CommitCounter::default(), *color, Kind::Unspecified))
+        })
+        .collect()
+}
+
+/// Elements of a window's surface tree, topmost first, drawn with its window geometry at
+/// `drawn` (output-local) instead of its real size, cut to `corner_radius` and converted
+/// through the LUTs in `surface_luts`. `geometry_offset` is where the window geometry starts
+/// within the root surface, e.g. behind client-side shadows.
+#[allow(clippy::too_many_arguments)]
+fn window_render_elements(
+    renderer: &mut Gles2Renderer,
+    effects: &mut GlesEffects,
+    color: &mut GlesColorPipeline,
+    surface_luts: &HashMap<smithay::backend::renderer::element::Id, Arc<Lut3d>>,
+    surface: &wl_surface::WlSurface,
+    geometry_offset: Point<i32, Logical>,
+    real_size: Size<i32, Logical>,
+    drawn: Rectangle<i32, Logical>,
+    scale: f64,
+    alpha: f32,
+    corner_radius: f32,
+) -> Vec<WinitRenderElement> {
+    use smithay::backend::renderer::element::{
+        surface::{render_elements_from_surface_tree, WaylandSurfaceRenderElement},
+        utils::RescaleRenderElement,
+        Element, Kind,
+    };
+
+    let location = (drawn.loc - geometry_offset).to_physical_precise_round(scale);
+    let elements: Vec<WaylandSurfaceRenderElement<Gles2Renderer>> =
+        render_elements_from_surface_tree(renderer, surface, location, scale, alpha, Kind::Unspecified);
+    // Corners are cut before rescaling, from the window geometry at its real size.
+    let corners = effects.rounded_corners(renderer, Rectangle::from_loc_and_size(drawn.loc, real_size), corner_radius, scale);
+    let rescale = (drawn.size != real_size && real_size.w > 0 && real_size.h > 0).then(|| {
+        let factor = smithay::utils::Scale::from((
+            drawn.size.w as f64 / real_size.w as f64,
+            drawn.size.h as f64 / real_size.h as f64,
+        ));
+        (drawn.loc.to_physical_precise_round(scale), factor)
+    });
+    elements
+        .into_iter()
+        .map(|element| {
+            let conversion = surface_luts.get(element.id()).and_then(|lut| color.conversion(renderer, lut));
+            match (conversion, &corners, rescale) {
+                (Some(conversion), corners, None) => WinitRenderElement::Converted(conversion.apply(element, corners.clone())),
+                (Some(conversion), corners, Some((origin, factor))) => WinitRenderElement::ScaledConverted(
+                    RescaleRenderElement::from_element(conversion.apply(element, corners.clone()), origin, factor),
+                ),
+                (None, Some(corners), None) => WinitRenderElement::Rounded(corners.apply(element)),
+                (None, Some(corners), Some((origin, factor))) => {
+                    WinitRenderElement::ScaledRounded(RescaleRenderElement::from_element(corners.apply(element), origin, factor))
+                }
+                (None, None, None) => WinitRenderElement::Surface(element),
+                (None, None, Some((origin, factor))) => {
+                    WinitRenderElement::Scaled(RescaleRenderElement::from_element(element, origin, factor))
+                }
+            }
+        })
+        .collect()
+}
+
+/// A surface-local rectangle of a window, e.g. from its blur region, where it is drawn when
+/// the window geometry is drawn at `drawn` instead of its real size.
+fn surface_rect_to_drawn(
+    rect: &novade_core::types::geometry::Rect<f32>,
+    geometry_offset: Point<i32, Logical>,
+    real_size: Size<i32, Logical>,
+    drawn: Rectangle<i32, Logical>,
+) -> Rectangle<i32, Logical> {
+    let (sx, sy) = if real_size.w > 0 && real_size.h > 0 {
+        (drawn.size.w as f32 / real_size.w as f32, drawn.size.h as f32 / real_size.h as f32)
+    } else {
+        (1.0, 1.0)
+    };
+    let left = drawn.loc.x as f32 + (rect.left() - geometry_offset.x as f32) * sx;
+    let top = drawn.loc.y as f32 + (rect.top() - geometry_offset.y as f32) * sy;
+    let right = drawn.loc.x as f32 + (rect.right() - geometry_offset.x as f32) * sx;
+    let bottom = drawn.loc.y as f32 + (rect.bottom() - geometry_offset.y as f32) * sy;
+    Rectangle::from_extremities((left.floor() as i32, top.floor() as i32), (right.ceil() as i32, bottom.ceil() as i32))
+}
+
+/// A window border of `width` logical pixels around `drawn`, drawn from the window's four
+/// edge buffers (top, bottom, left, right) so unchanged borders cause no damage.
+fn border_elements(
+    buffers: &mut [smithay::backend::renderer::element::solid::SolidColorBuffer; 4],
+    drawn: Rectangle<i32, Logical>,
+    width: i32,
+    color: [f32; 4],
+    scale: f64,
+    alpha: f32,
+) -> Vec<WinitRenderElement> {
+    use smithay::backend::renderer::element::{solid::SolidColorRenderElement, Kind};
+
+    let (x, y, w, h) = (drawn.loc.x, drawn.loc.y, drawn.size.w, drawn.size.h);
+    let edges: [Rectangle<i32, Logical>; 4] = [
+        Rectangle::from_loc_and_size((x - width, y - width), (w + 2 * width, width)),
+        Rectangle::from_loc_and_size((x - width, y + h), (w + 2 * width, width)),
+        Rectangle::from_loc_and_size((x - width, y), (width, h)),
+        Rectangle::from_loc_and_size((x + w, y), (width, h)),
+    ];
+    buffers
+        .iter_mut()
+        .zip(edges)
+        .map(|(buffer, edge)| {
+            buffer.update(edge.size, color);
+            WinitRenderElement::Solid(SolidColorRenderElement::from_buffer(
+                buffer,
+                edge.loc.to_physical_precise_round(scale),
+                scale,
+                alpha,
+                Kind::Unspecified,
+            ))
+        })
+        .collect()
+}
+
+/// Turns the overview's draw ops for an output into render elements, topmost first as the
+/// damage tracker expects. Windows' surfaces are added to `surfaces_for_callback` so they keep
+/// updating in the grid.
+fn overview_render_elements(
+    renderer: &mut Gles2Renderer,
+    ops: &[OverviewDrawOp],
+    windows: &HashMap<DomainWindowIdentifier, Arc<ManagedWindow>>,
+    scale: f64,
+    surfaces_for_callback: &mut Vec<wl_surface::WlSurface>,
+) -> Vec<WinitRenderElement> {
+    use smithay::backend::renderer::element::{
+        solid::SolidColorRenderElement, surface::render_elements_from_surface_tree, utils::RescaleRenderElement, Id, Kind,
+    };
+    use smithay::backend::renderer::utils::CommitCounter;
+
+    let mut elements = Vec::new();
+    for op in ops.iter().rev() {
+        match op {
+            OverviewDrawOp::Rect { geometry, color } => elements.push(WinitRenderElement::Solid(SolidColorRenderElement::new(
+                Id::new(),
+                geometry.to_physical_precise_round(scale),
+                CommitCounter::default(),
+                *color,
+                Kind::Unspecified,
+            ))),
+            OverviewDrawOp::Window { window_id, geometry } => {
+                let Some(window) = windows.get(window_id) else { continue };
+                let Some(surface) = window.wl_surface_ref() else { continue };
+                let real = *window.current_geometry.read().unwrap();
+                if real.size.w <= 0 {
+                    continue;
+                }
+                let location = geometry.loc.to_physical_precise_round(scale);
+                let factor = geometry.size.w as f64 / real.size.w as f64;
+                let surface_elements: Vec<smithay::backend::renderer::element::surface::WaylandSurfaceRenderElement<Gles2Renderer>> =
+                    render_elements_from_surface_tree(renderer, surface, location, scale, 1.0, Kind::Unspecified);
+                elements.extend(
+                    surface_elements
+                        .into_iter()
+                        .map(|element| WinitRenderElement::Scaled(RescaleRenderElement::from_element(element, location, factor))),
+                );
+                surfaces_for_callback.push(surface.clone());
+            }
+        }
+    }
+    elements
+}
+
+/// Reads `region` of the bound framebuffer as premultiplied `0xAARRGGBB` pixels for the
+/// recorder.
+fn read_back_gles(renderer: &mut Gles2Renderer, region: Rectangle<i32, smithay::utils::Physical>) -> Result<Vec<u32>, String> {
+    use smithay::backend::renderer::{ExportMem, Texture};
+    use smithay::backend::renderer::utils::Fourcc;
+
+    let buffer_region = Rectangle::<i32, smithay::utils::Buffer>::from_loc_and_size((region.loc.x, region.loc.y), (region.size.w, region.size.h));
+    let mapping = renderer.copy_framebuffer(buffer_region, Fourcc::Argb8888).map_err(|e| e.to_string())?;
+    let flipped = mapping.flipped();
+    let width = mapping.width() as usize;
+    let bytes = renderer.map_texture(&mapping).map_err(|e| e.to_string())?;
+    let mut rows: Vec<&[u8]> = bytes.chunks_exact(width * 4).collect();
+    // GL reads bottom-up.
+    if flipped {
+        rows.reverse();
+    }
+    Ok(rows.into_iter().flat_map(|row| row.chunks_exact(4).map(|p| u32::from_le_bytes([p[0], p[1], p[2], p[3]]))).collect())
+}
+
 /// Initializes all required Wayland globals.
 fn initialize_globals(display_handle: &mut DisplayHandle, state: &mut DesktopState) -> Result<(), CompositorError> {
     info!("Initializing Wayland globals...");
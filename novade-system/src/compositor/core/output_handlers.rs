@@ -38,10 +38,17 @@ impl OutputHandler for DesktopState {
         // If an Output is passed to new_output, its global should have been handled.
         // We just need to store it and map it to our space.
         
+        let output_name = output.name();
         self.outputs.push(output);
         // Trigger a refresh of all outputs in the space due to layout change.
         self.space.damage_all_outputs(); 
-        tracing::info!("Output {} mapped to space at {:?} and added to state.", output.name(), position);
+        tracing::info!("Output {} mapped to space at {:?} and added to state.", output_name, position);
+
+        self.ensure_workspaces_for_output(&output_name);
+        self.emit_ipc_event(crate::compositor::ipc::protocol::IpcEvent::Output {
+            change: crate::compositor::ipc::protocol::OutputChange::Added,
+            name: output_name,
+        });
     }
 
     fn output_mode_updated(&mut self, output: &Output, new_mode: Mode) {
@@ -69,6 +76,8 @@ impl OutputHandler for DesktopState {
 
     fn output_destroyed(&mut self, destroyed_output: &Output) {
         tracing::info!("Output destroyed: {}", destroyed_output.name());
+        // Move the workspaces away while the output's geometry is still known.
+        self.migrate_workspaces_from_output(&destroyed_output.name());
         self.space.unmap_output(destroyed_output);
         self.outputs.retain(|o| o.name() != destroyed_output.name());
         // The global for the output is automatically cleaned up by Smithay when the Output is dropped,
@@ -77,6 +86,10 @@ impl OutputHandler for DesktopState {
         // Trigger a refresh of all outputs due to layout change.
         self.space.damage_all_outputs();
         tracing::info!("Output {} unmapped from space and removed from state.", destroyed_output.name());
+        self.emit_ipc_event(crate::compositor::ipc::protocol::IpcEvent::Output {
+            change: crate::compositor::ipc::protocol::OutputChange::Removed,
+            name: destroyed_output.name(),
+        });
     }
 }
 
@@ -215,4 +228,47 @@ mod tests {
         assert_eq!(state.space.outputs().count(), 1, "Destroyed output should be unmapped from space");
         assert_eq!(state.space.outputs().next().unwrap().name(), "test-output-keep", "Kept output should remain in space");
     }
+
+    #[test]
+    fn test_unplugged_output_workspaces_migrate_and_return() {
+        let mut state = create_test_desktop_state();
+        let output_mode = get_default_mode();
+        let laptop = create_dummy_output("eDP-1", output_mode);
+        let external = create_dummy_output("DP-1", output_mode);
+
+        state.new_output(laptop.clone());
+        state.new_output(external.clone());
+        let per_output = state.config.layout.workspaces_per_output as usize;
+        assert_eq!(state.output_workspaces["DP-1"].len(), per_output);
+        let second_on_external = state.output_workspaces["DP-1"][1].read().unwrap().id;
+        assert!(state.switch_workspace_on_output("DP-1", second_on_external));
+
+        state.output_destroyed(&external);
+        assert!(!state.output_workspaces.contains_key("DP-1"));
+        assert_eq!(state.output_workspaces["eDP-1"].len(), per_output * 2);
+        assert_ne!(state.active_workspaces.read().unwrap().get("eDP-1"), Some(&second_on_external));
+
+        state.new_output(external.clone());
+        assert_eq!(state.output_workspaces["eDP-1"].len(), per_output);
+        assert_eq!(state.output_workspaces["DP-1"].len(), per_output);
+        assert_eq!(state.active_workspaces.read().unwrap().get("DP-1"), Some(&second_on_external));
+    }
+
+    #[test]
+    fn test_move_workspace_to_output_keeps_source_populated() {
+        let mut state = create_test_desktop_state();
+        let output_mode = get_default_mode();
+        state.new_output(create_dummy_output("eDP-1", output_mode));
+        state.new_output(create_dummy_output("DP-1", output_mode));
+
+        let moved = state.output_workspaces["eDP-1"][0].read().unwrap().id;
+        state.move_workspace_to_output(moved, "DP-1").unwrap();
+
+        assert_eq!(state.active_workspaces.read().unwrap().get("DP-1"), Some(&moved));
+        let source_active = *state.active_workspaces.read().unwrap().get("eDP-1").unwrap();
+        assert!(state.output_workspaces["eDP-1"].iter().any(|ws| ws.read().unwrap().id == source_active));
+        let moved_ws = state.output_workspaces["DP-1"].iter().find(|ws| ws.read().unwrap().id == moved).unwrap().clone();
+        assert_eq!(moved_ws.read().unwrap().preferred_output, "DP-1");
+        assert!(state.move_workspace_to_output(moved, "HDMI-A-1").is_err());
+    }
 }
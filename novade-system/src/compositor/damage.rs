@@ -0,0 +1,600 @@
+// novade-system/src/compositor/damage.rs
+//! Buffer-age aware damage tracking for the composition engine.
+//!
+//! Surfaces report damage in buffer coordinates (`wl_surface.damage_buffer`). The engine moves
+//! it into output space and feeds it, together with the geometry of every visible element, to
+//! the output's [`OutputDamageTracker`]. The tracker remembers the damage of the last few
+//! frames: a swapchain buffer that is `age` frames old missed the damage of the `age - 1`
+//! frames drawn since, so those are repainted together with the current frame's damage.
+//! An unknown age (0) or an age older than the history means the whole output is repainted.
+//!
+//! The winit backend renders through smithay's damage tracker instead and only uses
+//! [`surface_damage_in_output`] and [`DamageFlash`] here, for the `[debug] damage_flash`
+//! overlay.
+
+use std::collections::{HashMap, VecDeque};
+use std::hash::Hash;
+use std::time::{Duration, Instant};
+
+use novade_core::types::geometry::Rect;
+use smithay::backend::renderer::utils::RendererSurfaceStateUserData;
+use smithay::reexports::wayland_server::protocol::wl_surface::WlSurface;
+use smithay::utils::{Buffer, Logical, Point, Rectangle, Size, Transform};
+use smithay::wayland::compositor::{get_parent, with_states, Damage, SubsurfaceCachedState, SurfaceAttributes};
+use smithay::wayland::viewporter::ViewportCachedState;
+
+use crate::compositor::state::DesktopState;
+
+/// Rectangle in output coordinates.
+pub type DamageRect = Rect<f32>;
+
+/// Number of past frames remembered per output. Covers triple buffering with a spare buffer.
+pub const MAX_TRACKED_BUFFER_AGE: usize = 4;
+
+/// Above this many rectangles a region collapses into its bounding box: one slightly larger
+/// scissor is cheaper than many tiny draws.
+pub const MAX_DAMAGE_RECTS: usize = 16;
+
+fn is_empty(rect: &DamageRect) -> bool {
+    rect.size.width <= 0.0 || rect.size.height <= 0.0
+}
+
+fn contains(outer: &DamageRect, inner: &DamageRect) -> bool {
+    inner.left() >= outer.left() && inner.top() >= outer.top() && inner.right() <= outer.right() && inner.bottom() <= outer.bottom()
+}
+
+// ANCHOR: DamageRegion
+/// A set of damaged rectangles. Rectangles may overlap; ones fully covered by another are
+/// dropped when added.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DamageRegion {
+    rects: Vec<DamageRect>,
+}
+
+impl DamageRegion {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A region covering `rect` only.
+    pub fn from_rect(rect: DamageRect) -> Self {
+        let mut region = Self::new();
+        region.add(rect);
+        region
+    }
+
+    pub fn rects(&self) -> &[DamageRect] {
+        &self.rects
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rects.is_empty()
+    }
+
+    /// Adds `rect`, ignoring empty rectangles and ones already covered by the region.
+    pub fn add(&mut self, rect: DamageRect) {
+        if is_empty(&rect) || self.rects.iter().any(|r| contains(r, &rect)) {
+            return;
+        }
+        self.rects.retain(|r| !contains(&rect, r));
+        self.rects.push(rect);
+        if self.rects.len() > MAX_DAMAGE_RECTS {
+            let bounds = self.bounding_box().expect("region is not empty");
+            self.rects = vec![bounds];
+        }
+    }
+
+    pub fn add_region(&mut self, other: &DamageRegion) {
+        for rect in &other.rects {
+            self.add(*rect);
+        }
+    }
+
+    /// Restricts the region to `clip`.
+    pub fn clip(&mut self, clip: &DamageRect) {
+        self.rects = self.rects.iter().filter_map(|r| r.intersection(clip)).collect();
+    }
+
+    /// Returns `true` if any rectangle of the region overlaps `rect`.
+    pub fn intersects(&self, rect: &DamageRect) -> bool {
+        self.rects.iter().any(|r| r.intersection(rect).is_some())
+    }
+
+    pub fn bounding_box(&self) -> Option<DamageRect> {
+        let (first, rest) = self.rects.split_first()?;
+        Some(rest.iter().fold(*first, |bounds, r| bounds.union(r)))
+    }
+
+    /// Total area, counting overlapping parts more than once.
+    pub fn area(&self) -> f32 {
+        self.rects.iter().map(|r| r.size.width * r.size.height).sum()
+    }
+}
+// ANCHOR_END: DamageRegion
+
+/// A visible element of a frame as seen by the damage tracker.
+#[derive(Debug, Clone)]
+pub struct FrameElement<K> {
+    pub key: K,
+    /// Visible geometry on the output.
+    pub geometry: DamageRect,
+    /// Damage reported by the client since the last frame, in output coordinates.
+    pub damage: DamageRegion,
+}
+
+// ANCHOR: OutputDamageTracker
+/// Damage history of one output.
+#[derive(Debug)]
+pub struct OutputDamageTracker<K> {
+    output_geometry: DamageRect,
+    /// Damage of past frames, newest first.
+    history: VecDeque<DamageRegion>,
+    /// Geometry of every element in the previous frame.
+    elements: HashMap<K, DamageRect>,
+    /// Damage added outside of elements (mode changes, full redraws) for the next frame.
+    pending: DamageRegion,
+    /// Damage of compositor overlays for the next frame, repainted but not reported.
+    pending_overlay: DamageRegion,
+}
+
+impl<K: Eq + Hash + Clone> OutputDamageTracker<K> {
+    /// Creates a tracker whose first frame repaints the whole output.
+    pub fn new(output_geometry: DamageRect) -> Self {
+        Self {
+            output_geometry,
+            history: VecDeque::with_capacity(MAX_TRACKED_BUFFER_AGE),
+            elements: HashMap::new(),
+            pending: DamageRegion::from_rect(output_geometry),
+            pending_overlay: DamageRegion::new(),
+        }
+    }
+
+    pub fn output_geometry(&self) -> DamageRect {
+        self.output_geometry
+    }
+
+    /// Updates the output geometry. A change invalidates the history and damages everything.
+    pub fn set_output_geometry(&mut self, output_geometry: DamageRect) {
+        if output_geometry != self.output_geometry {
+            self.output_geometry = output_geometry;
+            self.history.clear();
+            self.damage_all();
+        }
+    }
+
+    /// Marks the whole output as damaged for the next frame.
+    pub fn damage_all(&mut self) {
+        self.pending = DamageRegion::from_rect(self.output_geometry);
+    }
+
+    /// Adds damage not tied to an element to the next frame.
+    pub fn add_damage(&mut self, region: &DamageRegion) {
+        self.pending.add_region(region);
+    }
+
+    /// Adds damage caused by compositor overlays (such as the [`DamageFlash`]) to the next
+    /// frame. It is repainted but not part of the damage returned by [`Self::record_frame`],
+    /// so an overlay that visualizes damage does not feed on itself.
+    pub fn add_overlay_damage(&mut self, region: &DamageRegion) {
+        self.pending_overlay.add_region(region);
+    }
+
+    /// Returns `true` if the previous frame had any elements.
+    pub fn has_elements(&self) -> bool {
+        !self.elements.is_empty()
+    }
+
+    /// Computes the damage of a new frame from its elements and records it in the history.
+    ///
+    /// Elements that appeared, disappeared, moved or were resized damage both their old and new
+    /// geometry; unchanged elements contribute the damage their client reported.
+    pub fn record_frame(&mut self, frame: impl IntoIterator<Item = FrameElement<K>>) -> DamageRegion {
+        let mut damage = std::mem::take(&mut self.pending);
+        let mut previous = std::mem::take(&mut self.elements);
+
+        for element in frame {
+            match previous.remove(&element.key) {
+                Some(old) if old == element.geometry => {
+                    let mut element_damage = element.damage;
+                    element_damage.clip(&element.geometry);
+                    damage.add_region(&element_damage);
+                }
+                Some(old) => {
+                    damage.add(old);
+                    damage.add(element.geometry);
+                }
+                None => damage.add(element.geometry),
+            }
+            self.elements.insert(element.key, element.geometry);
+        }
+        for (_, gone) in previous {
+            damage.add(gone);
+        }
+
+        damage.clip(&self.output_geometry);
+        let mut repainted = damage.clone();
+        repainted.add_region(&std::mem::take(&mut self.pending_overlay));
+        repainted.clip(&self.output_geometry);
+        self.history.push_front(repainted);
+        self.history.truncate(MAX_TRACKED_BUFFER_AGE);
+        damage
+    }
+
+    /// Region to repaint into a buffer that is `buffer_age` frames old, including the frame
+    /// just recorded. Age 0 (unknown) or an age beyond the history repaints everything.
+    pub fn repaint_region(&self, buffer_age: usize) -> DamageRegion {
+        if buffer_age == 0 || buffer_age > self.history.len() {
+            return DamageRegion::from_rect(self.output_geometry);
+        }
+        let mut region = DamageRegion::new();
+        for frame in self.history.iter().take(buffer_age) {
+            region.add_region(frame);
+        }
+        region
+    }
+}
+// ANCHOR_END: OutputDamageTracker
+
+// ANCHOR: DamageFlash
+/// Debug overlay that flashes damaged regions and fades them out over `duration`.
+#[derive(Debug)]
+pub struct DamageFlash {
+    duration: Duration,
+    color: [f32; 3],
+    entries: VecDeque<(Instant, DamageRegion)>,
+}
+
+impl Default for DamageFlash {
+    fn default() -> Self {
+        Self::new(Duration::from_millis(300))
+    }
+}
+
+impl DamageFlash {
+    pub fn new(duration: Duration) -> Self {
+        Self { duration, color: [1.0, 0.0, 1.0], entries: VecDeque::new() }
+    }
+
+    /// Region the overlay needs repainted at `now`: every flash still fading plus the ones that
+    /// just ended and must be erased. Ended flashes are forgotten.
+    pub fn take_repaint(&mut self, now: Instant) -> DamageRegion {
+        let mut repaint = DamageRegion::new();
+        for (_, region) in &self.entries {
+            repaint.add_region(region);
+        }
+        let duration = self.duration;
+        self.entries.retain(|(start, _)| now.saturating_duration_since(*start) < duration);
+        repaint
+    }
+
+    /// Starts flashing `damage` at `now`.
+    pub fn push(&mut self, now: Instant, damage: &DamageRegion) {
+        if !damage.is_empty() {
+            self.entries.push_back((now, damage.clone()));
+        }
+    }
+
+    /// Overlay rectangles with their RGBA color at `now`, oldest first.
+    pub fn overlay(&self, now: Instant) -> Vec<(DamageRect, [f32; 4])> {
+        let [r, g, b] = self.color;
+        self.entries
+            .iter()
+            .flat_map(|(start, region)| {
+                let progress = now.saturating_duration_since(*start).as_secs_f32() / self.duration.as_secs_f32();
+                let alpha = 0.4 * (1.0 - progress.clamp(0.0, 1.0));
+                region.rects().iter().map(move |rect| (*rect, [r, g, b, alpha]))
+            })
+            .filter(|(_, color)| color[3] > 0.0)
+            .collect()
+    }
+
+    /// Returns `true` while a flash is still fading and more frames are needed.
+    pub fn is_active(&self) -> bool {
+        !self.entries.is_empty()
+    }
+}
+// ANCHOR_END: DamageFlash
+
+// ANCHOR: SurfaceDamage
+/// How a surface shows its buffer, as set by its last commit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SurfaceBufferMapping {
+    /// Size of the attached buffer in pixels.
+    pub buffer_size: Size<i32, Buffer>,
+    pub buffer_scale: i32,
+    pub buffer_transform: Transform,
+    /// `wp_viewport` source rectangle, in surface coordinates before the viewport is applied.
+    pub viewport_src: Option<Rectangle<f64, Logical>>,
+    /// `wp_viewport` destination size.
+    pub viewport_dst: Option<Size<i32, Logical>>,
+}
+
+impl SurfaceBufferMapping {
+    /// Moves committed damage into surface-local coordinates. Buffer damage is undone through
+    /// the buffer transform, the buffer scale and the viewport, in that order.
+    pub fn surface_damage(&self, damage: &Damage) -> Rectangle<f64, Logical> {
+        let rect = match damage {
+            Damage::Surface(rect) => return rect.to_f64(),
+            Damage::Buffer(rect) => rect.to_logical(self.buffer_scale, self.buffer_transform, &self.buffer_size).to_f64(),
+        };
+        if self.viewport_src.is_none() && self.viewport_dst.is_none() {
+            return rect;
+        }
+        let unviewported = self.buffer_size.to_logical(self.buffer_scale, self.buffer_transform).to_f64();
+        let src = self.viewport_src.unwrap_or_else(|| Rectangle::from_loc_and_size((0.0, 0.0), unviewported));
+        let dst = self.viewport_dst.map_or(src.size, |dst| dst.to_f64());
+        if src.size.w <= 0.0 || src.size.h <= 0.0 {
+            return Rectangle::default();
+        }
+        let (scale_x, scale_y) = (dst.w / src.size.w, dst.h / src.size.h);
+        Rectangle::from_loc_and_size(
+            ((rect.loc.x - src.loc.x) * scale_x, (rect.loc.y - src.loc.y) * scale_y),
+            (rect.size.w * scale_x, rect.size.h * scale_y),
+        )
+    }
+}
+
+/// Committed damage of a surface shown at `surface_location` (logical, relative to the output)
+/// as a region of the output in physical pixels.
+pub fn surface_damage_in_output(
+    damage: &[Damage],
+    mapping: &SurfaceBufferMapping,
+    surface_location: Point<f64, Logical>,
+    output_scale: f64,
+) -> DamageRegion {
+    let mut region = DamageRegion::new();
+    for damage in damage {
+        let rect = mapping.surface_damage(damage);
+        let loc = (rect.loc + surface_location).to_physical(output_scale);
+        let size = rect.size.to_physical(output_scale);
+        region.add(Rect::from_coords(loc.x as f32, loc.y as f32, size.w as f32, size.h as f32));
+    }
+    region
+}
+// ANCHOR_END: SurfaceDamage
+
+// ANCHOR: DesktopStateDamageFlash
+impl DesktopState {
+    /// Applies the `[debug]` config section.
+    pub fn apply_debug_config(&mut self) {
+        if !self.config.debug.damage_flash {
+            self.damage_flashes.clear();
+        }
+    }
+
+    /// Flashes the damage `surface` just committed on every output showing it, if
+    /// `[debug] damage_flash` is on. Needs the renderer surface state, so it must run after
+    /// smithay's commit buffer handler.
+    pub fn record_surface_damage(&mut self, surface: &WlSurface) {
+        if !self.config.debug.damage_flash {
+            return;
+        }
+        let committed = with_states(surface, |states| {
+            let attributes = states.cached_state.get::<SurfaceAttributes>();
+            let attributes = attributes.current();
+            if attributes.damage.is_empty() {
+                return None;
+            }
+            let renderer_state = states.data_map.get::<RendererSurfaceStateUserData>()?.lock().unwrap();
+            let buffer_scale = renderer_state.buffer_scale();
+            let buffer_transform = renderer_state.buffer_transform();
+            let buffer_size = renderer_state.buffer_size()?.to_buffer(buffer_scale, buffer_transform);
+            let viewport = states.cached_state.get::<ViewportCachedState>();
+            let viewport = viewport.current();
+            let mapping = SurfaceBufferMapping {
+                buffer_size,
+                buffer_scale,
+                buffer_transform,
+                viewport_src: viewport.src,
+                viewport_dst: viewport.size,
+            };
+            Some((attributes.damage.clone(), mapping))
+        });
+        let Some((damage, mapping)) = committed else { return };
+
+        // Subsurfaces are placed relative to their parent.
+        let mut root = surface.clone();
+        let mut offset = Point::<i32, Logical>::default();
+        while let Some(parent) = get_parent(&root) {
+            offset += with_states(&root, |states| states.cached_state.get::<SubsurfaceCachedState>().current().location);
+            root = parent;
+        }
+
+        let now = Instant::now();
+        let space = self.space.lock().unwrap();
+        let Some(window) = space.window_for_surface(&root).cloned() else { return };
+        let Some(window_location) = space.element_location(&window) else { return };
+        let surface_location = window_location - window.geometry().loc + offset;
+        for output in space.outputs_for_element(&window) {
+            let Some(output_geometry) = space.output_geometry(&output) else { continue };
+            let region = surface_damage_in_output(
+                &damage,
+                &mapping,
+                (surface_location - output_geometry.loc).to_f64(),
+                output.current_scale().fractional_scale(),
+            );
+            self.damage_flashes.entry(output.name()).or_default().push(now, &region);
+        }
+    }
+
+    /// Damage flash rectangles to draw on `output_name` at `now`, in physical output
+    /// coordinates. Flashes that faded out are dropped.
+    pub fn damage_flash_overlay(&mut self, output_name: &str, now: Instant) -> Vec<(DamageRect, [f32; 4])> {
+        let Some(flash) = self.damage_flashes.get_mut(output_name) else { return Vec::new() };
+        flash.take_repaint(now);
+        let overlay = flash.overlay(now);
+        if !flash.is_active() {
+            self.damage_flashes.remove(output_name);
+        }
+        overlay
+    }
+}
+// ANCHOR_END: DesktopStateDamageFlash
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(x: f32, y: f32, w: f32, h: f32) -> DamageRect {
+        Rect::from_coords(x, y, w, h)
+    }
+
+    fn element(key: u32, geometry: DamageRect, damage: &[DamageRect]) -> FrameElement<u32> {
+        let mut region = DamageRegion::new();
+        for r in damage {
+            region.add(*r);
+        }
+        FrameElement { key, geometry, damage: region }
+    }
+
+    const OUTPUT: DamageRect = Rect::from_coords(0.0, 0.0, 1920.0, 1080.0);
+
+    #[test]
+    fn test_region_drops_covered_rects_and_collapses() {
+        let mut region = DamageRegion::new();
+        region.add(rect(10.0, 10.0, 10.0, 10.0));
+        region.add(rect(12.0, 12.0, 2.0, 2.0));
+        assert_eq!(region.rects().len(), 1);
+        region.add(rect(0.0, 0.0, 100.0, 100.0));
+        assert_eq!(region.rects(), &[rect(0.0, 0.0, 100.0, 100.0)]);
+        region.add(rect(0.0, 0.0, 0.0, 50.0));
+        assert_eq!(region.rects().len(), 1);
+
+        let mut many = DamageRegion::new();
+        for i in 0..=MAX_DAMAGE_RECTS {
+            many.add(rect(i as f32 * 20.0, 0.0, 10.0, 10.0));
+        }
+        assert_eq!(many.rects(), &[rect(0.0, 0.0, MAX_DAMAGE_RECTS as f32 * 20.0 + 10.0, 10.0)]);
+    }
+
+    #[test]
+    fn test_first_frame_is_full_then_only_client_damage() {
+        let mut tracker = OutputDamageTracker::new(OUTPUT);
+        let window = rect(100.0, 100.0, 400.0, 300.0);
+
+        let first = tracker.record_frame(vec![element(1, window, &[])]);
+        assert_eq!(first.rects(), &[OUTPUT]);
+
+        // A blinking cursor inside the window damages a few pixels only.
+        let caret = rect(150.0, 120.0, 2.0, 16.0);
+        let second = tracker.record_frame(vec![element(1, window, &[caret])]);
+        assert_eq!(second.rects(), &[caret]);
+
+        let idle = tracker.record_frame(vec![element(1, window, &[])]);
+        assert!(idle.is_empty());
+    }
+
+    #[test]
+    fn test_moved_and_removed_elements_damage_old_geometry() {
+        let mut tracker = OutputDamageTracker::new(OUTPUT);
+        let a = rect(0.0, 0.0, 100.0, 100.0);
+        let b = rect(500.0, 500.0, 50.0, 50.0);
+        tracker.record_frame(vec![element(1, a, &[]), element(2, b, &[])]);
+
+        let moved = rect(10.0, 0.0, 100.0, 100.0);
+        let damage = tracker.record_frame(vec![element(1, moved, &[])]);
+        assert!(damage.intersects(&rect(0.0, 0.0, 5.0, 5.0)), "old position must be repainted");
+        assert!(damage.intersects(&rect(105.0, 0.0, 5.0, 5.0)), "new position must be repainted");
+        assert!(damage.rects().contains(&b), "removed element must be erased");
+    }
+
+    #[test]
+    fn test_repaint_region_follows_buffer_age() {
+        let mut tracker = OutputDamageTracker::new(OUTPUT);
+        let window = rect(0.0, 0.0, 800.0, 600.0);
+        tracker.record_frame(vec![element(1, window, &[])]);
+        let first = rect(10.0, 10.0, 5.0, 5.0);
+        let second = rect(400.0, 300.0, 5.0, 5.0);
+        tracker.record_frame(vec![element(1, window, &[first])]);
+        tracker.record_frame(vec![element(1, window, &[second])]);
+
+        assert_eq!(tracker.repaint_region(1).rects(), &[second]);
+        assert_eq!(tracker.repaint_region(2).rects(), &[second, first]);
+        assert_eq!(tracker.repaint_region(0).rects(), &[OUTPUT]);
+        assert_eq!(tracker.repaint_region(MAX_TRACKED_BUFFER_AGE + 1).rects(), &[OUTPUT]);
+    }
+
+    #[test]
+    fn test_overlay_damage_is_repainted_but_not_reported() {
+        let mut tracker = OutputDamageTracker::new(OUTPUT);
+        let window = rect(0.0, 0.0, 800.0, 600.0);
+        tracker.record_frame(vec![element(1, window, &[])]);
+
+        let flash = DamageRegion::from_rect(rect(20.0, 20.0, 10.0, 10.0));
+        tracker.add_overlay_damage(&flash);
+        assert!(tracker.record_frame(vec![element(1, window, &[])]).is_empty());
+        assert_eq!(tracker.repaint_region(1), flash);
+    }
+
+    #[test]
+    fn test_output_resize_damages_everything() {
+        let mut tracker: OutputDamageTracker<u32> = OutputDamageTracker::new(OUTPUT);
+        tracker.record_frame(Vec::new());
+        assert!(tracker.record_frame(Vec::new()).is_empty());
+        let larger = rect(0.0, 0.0, 2560.0, 1440.0);
+        tracker.set_output_geometry(larger);
+        assert_eq!(tracker.record_frame(Vec::new()).rects(), &[larger]);
+        assert_eq!(tracker.repaint_region(2).rects(), &[larger]);
+    }
+
+    #[test]
+    fn test_damage_flash_fades_and_erases() {
+        let start = Instant::now();
+        let mut flash = DamageFlash::new(Duration::from_millis(100));
+        let caret = DamageRegion::from_rect(rect(0.0, 0.0, 2.0, 16.0));
+
+        assert!(flash.take_repaint(start).is_empty());
+        flash.push(start, &caret);
+        let overlay = flash.overlay(start + Duration::from_millis(50));
+        assert_eq!(overlay.len(), 1);
+        assert!(overlay[0].1[3] > 0.0 && overlay[0].1[3] < 0.4);
+
+        // The expired flash is repainted once more so it disappears, then forgotten.
+        assert_eq!(flash.take_repaint(start + Duration::from_millis(150)), caret);
+        assert!(!flash.is_active());
+    }
+
+    fn mapping(buffer_size: (i32, i32), buffer_scale: i32, buffer_transform: Transform) -> SurfaceBufferMapping {
+        SurfaceBufferMapping {
+            buffer_size: buffer_size.into(),
+            buffer_scale,
+            buffer_transform,
+            viewport_src: None,
+            viewport_dst: None,
+        }
+    }
+
+    #[test]
+    fn test_buffer_damage_honours_buffer_scale_and_output_scale() {
+        // A 400x200 buffer at scale 2 is a 200x100 surface.
+        let hidpi = mapping((400, 200), 2, Transform::Normal);
+        let damage = [
+            Damage::Buffer(Rectangle::from_loc_and_size((20, 40), (80, 60))),
+            Damage::Surface(Rectangle::from_loc_and_size((0, 0), (10, 5))),
+        ];
+        let region = surface_damage_in_output(&damage, &hidpi, (100.0, 50.0).into(), 1.0);
+        assert_eq!(region.rects(), &[rect(110.0, 70.0, 40.0, 30.0), rect(100.0, 50.0, 10.0, 5.0)]);
+
+        // On a scale 2 output the same damage covers twice the pixels.
+        let region = surface_damage_in_output(&damage[..1], &hidpi, (100.0, 50.0).into(), 2.0);
+        assert_eq!(region.rects(), &[rect(220.0, 140.0, 80.0, 60.0)]);
+    }
+
+    #[test]
+    fn test_buffer_damage_honours_buffer_transform_and_viewport() {
+        let rotated = mapping((200, 100), 1, Transform::_180);
+        let corner = [Damage::Buffer(Rectangle::from_loc_and_size((0, 0), (20, 10)))];
+        let region = surface_damage_in_output(&corner, &rotated, (0.0, 0.0).into(), 1.0);
+        assert_eq!(region.rects(), &[rect(180.0, 90.0, 20.0, 10.0)]);
+
+        // The right half of the buffer stretched over a 400x400 surface.
+        let viewported = SurfaceBufferMapping {
+            viewport_src: Some(Rectangle::from_loc_and_size((100.0, 0.0), (100.0, 100.0))),
+            viewport_dst: Some((400, 400).into()),
+            ..mapping((200, 100), 1, Transform::Normal)
+        };
+        let damage = [Damage::Buffer(Rectangle::from_loc_and_size((110, 10), (10, 10)))];
+        let region = surface_damage_in_output(&damage, &viewported, (0.0, 0.0).into(), 1.0);
+        assert_eq!(region.rects(), &[rect(40.0, 40.0, 40.0, 40.0)]);
+    }
+}
@@ -97,8 +97,11 @@ pub fn run_compositor_event_loop() -> Result<(), Box<dyn std::error::Error>> {
                 info!("NovaWgpuRenderer initialized and would be set as active_renderer in DesktopState.");
             }
             Err(e) => {
-                error!("Failed to initialize NovaWgpuRenderer: {}. Proceeding without WGPU renderer.", e);
-                // For MVP, this might be critical, but allow continuing for core protocol testing.
+                error!("Failed to initialize NovaWgpuRenderer: {}. Falling back to the software renderer.", e);
+                let size = smithay::utils::Size::from((initial_window_size_physical.width as i32, initial_window_size_physical.height as i32));
+                desktop_state.main_renderer = Some(crate::compositor::render::MainNovaRenderer::Software(Box::new(
+                    crate::renderer::SoftwareRenderer::new(size),
+                )));
             }
         };
     }
@@ -0,0 +1,141 @@
+// novade-system/src/compositor/effects/blur.rs
+
+//! Dual-Kawase blur parameters and damage expansion.
+//!
+//! The blur halves the image `passes` times and scales it back up, each pass taking a few
+//! bilinear samples `offset` texels apart (Marius Bjørge, "Bandwidth-Efficient Rendering",
+//! SIGGRAPH 2015). All renderers use the same kernels:
+//!
+//! * down: the texel centre with weight 4 and the four diagonals at `±offset` with weight 1;
+//! * up: the four axis neighbours at `±2 * offset` with weight 1 and the four diagonals at
+//!   `±offset` with weight 2.
+//!
+//! Offsets are in texels of the level being sampled. Level sizes are halved with floor
+//! division, samples are taken with clamp-to-edge addressing.
+
+use crate::compositor::damage::{DamageRect, DamageRegion};
+
+/// Most passes a blur may use; more are indistinguishable from a flat colour on most panels.
+pub const MAX_BLUR_PASSES: u32 = 6;
+
+// ANCHOR: BlurStyle
+/// Strength of a dual-Kawase blur.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlurStyle {
+    /// Downsampling passes, in `1..=MAX_BLUR_PASSES`.
+    pub passes: u32,
+    /// Sample distance in texels of each level.
+    pub offset: f32,
+}
+
+impl Default for BlurStyle {
+    fn default() -> Self {
+        Self { passes: 3, offset: 2.0 }
+    }
+}
+
+impl BlurStyle {
+    /// Passes clamped to the supported range.
+    pub fn clamped_passes(&self) -> u32 {
+        self.passes.clamp(1, MAX_BLUR_PASSES)
+    }
+
+    /// Upper bound, in pixels of the full-size image, of how far content can influence a
+    /// blurred pixel.
+    ///
+    /// A downsample from level `s` reads up to `offset + 1` texels of `2^s` pixels away, an
+    /// upsample into level `s` up to `2 * offset + 1` texels of `2^(s+1)` pixels; summed over
+    /// all levels and padded by half a texel of the smallest level.
+    pub fn reach(&self) -> f32 {
+        let levels = (1u32 << self.clamped_passes()) as f32;
+        let offset = self.offset.max(0.0);
+        (levels - 1.0) * (5.0 * offset + 3.0) + levels / 2.0
+    }
+}
+// ANCHOR_END: BlurStyle
+
+/// Sizes of the downsampled levels of a `width` x `height` image, level 1 first.
+pub fn level_sizes(width: u32, height: u32, passes: u32) -> Vec<(u32, u32)> {
+    let mut size = (width.max(1), height.max(1));
+    (0..passes)
+        .map(|_| {
+            size = ((size.0 / 2).max(1), (size.1 / 2).max(1));
+            size
+        })
+        .collect()
+}
+
+fn grow(rect: &DamageRect, by: f32) -> DamageRect {
+    DamageRect::from_coords(rect.origin.x - by, rect.origin.y - by, rect.size.width + 2.0 * by, rect.size.height + 2.0 * by)
+}
+
+// ANCHOR: ExpandDamageForBlur
+/// Widens `damage` for blurred `areas` in output coordinates, each with its blur's reach.
+///
+/// The blur samples the frame as it is being drawn, so a blurred pixel is only right if
+/// everything within reach of it was repainted this frame. When damage comes within reach of
+/// an area, the area and its surroundings up to the reach are repainted whole. Added damage
+/// can bring further areas into reach, so this repeats until nothing changes.
+pub fn expand_damage_for_blur(damage: &mut DamageRegion, areas: &[(DamageRect, f32)], output: &DamageRect) {
+    let mut expanded = vec![false; areas.len()];
+    loop {
+        let mut changed = false;
+        for (done, (area, reach)) in expanded.iter_mut().zip(areas) {
+            let padded = grow(area, *reach);
+            if *done || !damage.intersects(&padded) {
+                continue;
+            }
+            if let Some(padded) = padded.intersection(output) {
+                damage.add(padded);
+            }
+            *done = true;
+            changed = true;
+        }
+        if !changed {
+            break;
+        }
+    }
+}
+// ANCHOR_END: ExpandDamageForBlur
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OUTPUT: DamageRect = DamageRect::from_coords(0.0, 0.0, 1920.0, 1080.0);
+
+    #[test]
+    fn test_reach_and_levels() {
+        assert_eq!(BlurStyle { passes: 1, offset: 1.0 }.reach(), 9.0);
+        assert_eq!(BlurStyle::default().reach(), 95.0);
+        assert_eq!(BlurStyle { passes: 40, offset: 1.0 }.clamped_passes(), MAX_BLUR_PASSES);
+        assert_eq!(level_sizes(101, 6, 3), vec![(50, 3), (25, 1), (12, 1)]);
+    }
+
+    #[test]
+    fn test_damage_near_blur_repaints_whole_area() {
+        let panel = DamageRect::from_coords(0.0, 0.0, 1920.0, 40.0);
+        let window = DamageRect::from_coords(500.0, 300.0, 400.0, 300.0);
+        let areas = [(panel, 10.0), (window, 20.0)];
+
+        // Far away from both: unchanged.
+        let mut damage = DamageRegion::from_rect(DamageRect::from_coords(1500.0, 900.0, 10.0, 10.0));
+        expand_damage_for_blur(&mut damage, &areas, &OUTPUT);
+        assert_eq!(damage.rects().len(), 1);
+
+        // Just below the panel, within its reach.
+        let mut damage = DamageRegion::from_rect(DamageRect::from_coords(100.0, 45.0, 10.0, 10.0));
+        expand_damage_for_blur(&mut damage, &areas, &OUTPUT);
+        assert!(damage.rects().contains(&DamageRect::from_coords(0.0, 0.0, 1920.0, 50.0)));
+        assert!(!damage.intersects(&window));
+
+        // A window just below the panel: damage near the window repaints its surroundings,
+        // which reach into the panel's blur.
+        let window = DamageRect::from_coords(500.0, 60.0, 400.0, 300.0);
+        let areas = [(panel, 10.0), (window, 20.0)];
+        let mut damage = DamageRegion::from_rect(DamageRect::from_coords(600.0, 200.0, 10.0, 10.0));
+        expand_damage_for_blur(&mut damage, &areas, &OUTPUT);
+        assert!(damage.rects().contains(&DamageRect::from_coords(480.0, 40.0, 440.0, 340.0)));
+        assert!(damage.rects().contains(&DamageRect::from_coords(0.0, 0.0, 1920.0, 50.0)));
+    }
+}
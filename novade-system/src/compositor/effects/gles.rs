@@ -0,0 +1,481 @@
+// novade-system/src/compositor/effects/gles.rs
+
+//! Window effects for the GLES renderer of the winit backend.
+//!
+//! * Shadows are [`PixelShaderElement`]s running `gles_shadow.frag`, a port of [`shape`](super::shape).
+//! * Rounded corners wrap a window's surface elements in a [`RoundedElement`], which draws them
+//!   with `gles_rounded.frag` to cut away everything outside the window's rounded geometry.
+//! * Backdrop blur renders the part of the frame below a window into a texture, runs the
+//!   dual-Kawase passes of [`blur`](super::blur) over it with `gles_blur.frag` and draws the
+//!   result behind the window, rounded like it.
+//!
+//! Lengths handed to the shaders are in physical pixels.
+
+use std::collections::HashMap;
+
+use smithay::backend::allocator::Fourcc;
+use smithay::backend::renderer::element::texture::TextureRenderElement;
+use smithay::backend::renderer::element::{Element, Id, Kind, RenderElement, UnderlyingStorage};
+use smithay::backend::renderer::gles2::element::PixelShaderElement;
+use smithay::backend::renderer::gles2::{
+    Gles2Error, Gles2Frame, Gles2Renderer, Gles2Texture, GlesPixelProgram, GlesTexProgram, Uniform, UniformName, UniformType,
+};
+use smithay::backend::renderer::utils::{draw_render_elements, CommitCounter, DamageSet, OpaqueRegions};
+use smithay::backend::renderer::{Bind, Frame, Offscreen, Renderer, Texture};
+use smithay::utils::{Buffer, Logical, Physical, Point, Rectangle, Scale, Size, Transform};
+use tracing::error;
+
+use super::blur::{level_sizes, BlurStyle};
+use super::shape::{shadow_bounds, shadow_shape};
+use super::ShadowStyle;
+use crate::compositor::shell::xdg_shell::types::DomainWindowIdentifier;
+
+const SHADOW_SHADER: &str = include_str!("../../../assets/shaders/gles_shadow.frag");
+const ROUNDED_SHADER: &str = include_str!("../../../assets/shaders/gles_rounded.frag");
+const BLUR_SHADER: &str = include_str!("../../../assets/shaders/gles_blur.frag");
+
+/// Compiled effect shaders. Programs are reference counted, cloning is cheap.
+#[derive(Debug, Clone)]
+struct EffectPrograms {
+    shadow: GlesPixelProgram,
+    rounded: GlesTexProgram,
+    blur: GlesTexProgram,
+}
+
+impl EffectPrograms {
+    fn compile(renderer: &mut Gles2Renderer) -> Result<Self, Gles2Error> {
+        let shadow = renderer.compile_custom_pixel_shader(
+            SHADOW_SHADER,
+            &[
+                UniformName::new("rect", UniformType::_4f),
+                UniformName::new("radius", UniformType::_1f),
+                UniformName::new("shadow_shape", UniformType::_4f),
+                UniformName::new("shadow_radius", UniformType::_1f),
+                UniformName::new("sigma", UniformType::_1f),
+                UniformName::new("color", UniformType::_4f),
+            ],
+        )?;
+        let rounded = renderer.compile_custom_texture_shader(
+            ROUNDED_SHADER,
+            &[
+                UniformName::new("element_offset", UniformType::_2f),
+                UniformName::new("element_size", UniformType::_2f),
+                UniformName::new("geo_size", UniformType::_2f),
+                UniformName::new("corner_radius", UniformType::_1f),
+            ],
+        )?;
+        let blur = renderer.compile_custom_texture_shader(
+            BLUR_SHADER,
+            &[
+                UniformName::new("mode", UniformType::_1f),
+                UniformName::new("texel", UniformType::_2f),
+                UniformName::new("offset", UniformType::_1f),
+                UniformName::new("rect", UniformType::_4f),
+                UniformName::new("radius", UniformType::_1f),
+                UniformName::new("target_size", UniformType::_2f),
+            ],
+        )?;
+        Ok(Self { shadow, rounded, blur })
+    }
+}
+
+/// Backdrop blur of one window: where to blur and how.
+#[derive(Debug, Clone)]
+pub struct BackdropBlur {
+    /// Output-local logical rectangles to blur behind.
+    pub rects: Vec<Rectangle<i32, Logical>>,
+    /// Window geometry as drawn, output-local; the blur is cut to it with rounded corners.
+    pub geometry: Rectangle<i32, Logical>,
+    pub corner_radius: f32,
+    pub style: BlurStyle,
+    pub alpha: f32,
+}
+
+// ANCHOR: GlesEffects
+/// Effect shaders and the resources kept between frames.
+#[derive(Default)]
+pub struct GlesEffects {
+    programs: Option<EffectPrograms>,
+    /// Set once compiling failed, so it is neither retried nor reported every frame.
+    compile_failed: bool,
+    /// Each window's shadow with the parameters it was built from. A shadow is only replaced
+    /// when they change, so an unchanged shadow causes no damage.
+    shadows: HashMap<DomainWindowIdentifier, ((Rectangle<i32, Logical>, [f32; 16]), PixelShaderElement)>,
+    /// The frame below a blurred window, then its downsampled levels; reused by every blur.
+    blur_scratch: Vec<Gles2Texture>,
+    /// Finished blur of each window, drawn later in the frame.
+    blur_results: HashMap<DomainWindowIdentifier, Gles2Texture>,
+}
+
+impl GlesEffects {
+    /// The effect shaders, compiled on first use. `None` if they do not compile; windows are
+    /// then drawn without effects.
+    fn programs(&mut self, renderer: &mut Gles2Renderer) -> Option<EffectPrograms> {
+        if self.programs.is_none() && !self.compile_failed {
+            match EffectPrograms::compile(renderer) {
+                Ok(programs) => self.programs = Some(programs),
+                Err(e) => {
+                    error!("Failed to compile the window effect shaders, drawing windows without effects: {}", e);
+                    self.compile_failed = true;
+                }
+            }
+        }
+        self.programs.clone()
+    }
+
+    /// Drops the resources of windows for which `keep` returns false.
+    pub fn retain_windows(&mut self, keep: impl Fn(&DomainWindowIdentifier) -> bool) {
+        self.shadows.retain(|window_id, _| keep(window_id));
+        self.blur_results.retain(|window_id, _| keep(window_id));
+    }
+
+    /// Shadow of a window drawn at `geometry` (output-local, logical) with `corner_radius`.
+    pub fn shadow_element(
+        &mut self,
+        renderer: &mut Gles2Renderer,
+        window_id: DomainWindowIdentifier,
+        geometry: Rectangle<i32, Logical>,
+        corner_radius: f32,
+        style: &ShadowStyle,
+        scale: f64,
+        alpha: f32,
+    ) -> Option<PixelShaderElement> {
+        let programs = self.programs(renderer)?;
+        let factor = scale as f32;
+        let rect = [
+            geometry.loc.x as f32 * factor,
+            geometry.loc.y as f32 * factor,
+            geometry.size.w as f32 * factor,
+            geometry.size.h as f32 * factor,
+        ];
+        let radius = corner_radius * factor;
+        let style = style.scaled(factor);
+        let (shape, shape_radius) = shadow_shape(rect, radius, &style)?;
+        let [bx, by, bw, bh] = shadow_bounds(rect, radius, &style)?;
+
+        // The element covers whole logical pixels; the shader measures from its physical origin.
+        let area = Rectangle::<i32, Logical>::from_extremities(
+            ((bx / factor).floor() as i32, (by / factor).floor() as i32),
+            (((bx + bw) / factor).ceil() as i32, ((by + bh) / factor).ceil() as i32),
+        );
+        let origin = area.loc.to_physical_precise_round::<f64, i32>(scale);
+        let (ox, oy) = (origin.x as f32, origin.y as f32);
+        let [r, g, b, a] = style.color;
+        let params = [
+            rect[0] - ox, rect[1] - oy, rect[2], rect[3], radius,
+            shape[0] - ox, shape[1] - oy, shape[2], shape[3], shape_radius,
+            style.sigma(), r * a, g * a, b * a, a, alpha,
+        ];
+
+        if let Some((current, element)) = self.shadows.get(&window_id) {
+            if *current == (area, params) {
+                return Some(element.clone());
+            }
+        }
+        let element = PixelShaderElement::new(
+            programs.shadow,
+            area,
+            None,
+            alpha,
+            vec![
+                Uniform::new("rect", (params[0], params[1], params[2], params[3])),
+                Uniform::new("radius", params[4]),
+                Uniform::new("shadow_shape", (params[5], params[6], params[7], params[8])),
+                Uniform::new("shadow_radius", params[9]),
+                Uniform::new("sigma", params[10]),
+                Uniform::new("color", (params[11], params[12], params[13], params[14])),
+            ],
+            Kind::Unspecified,
+        );
+        self.shadows.insert(window_id, ((area, params), element.clone()));
+        Some(element)
+    }
+
+    /// How to cut the elements of a window drawn at `geometry` to its rounded corners. `None`
+    /// if the corners are square or the shaders are unavailable.
+    pub fn rounded_corners(
+        &mut self,
+        renderer: &mut Gles2Renderer,
+        geometry: Rectangle<i32, Logical>,
+        corner_radius: f32,
+        scale: f64,
+    ) -> Option<RoundedCorners> {
+        if corner_radius <= 0.0 {
+            return None;
+        }
+        let programs = self.programs(renderer)?;
+        Some(RoundedCorners {
+            program: programs.rounded,
+            geometry: geometry.to_f64().to_physical(scale),
+            corner_radius: corner_radius * scale as f32,
+            scale: Scale::from(scale),
+        })
+    }
+
+    /// Blurs what `below` draws behind `blur` and returns the elements showing it, topmost
+    /// first. `below` holds the frame's elements under the window, topmost first, drawn over
+    /// `clear_color` on an output of `output_size` physical pixels.
+    ///
+    /// What is behind the window may change every frame, so the elements get new ids each time
+    /// and the blurred area is always repainted.
+    pub fn backdrop_blur<E: RenderElement<Gles2Renderer>>(
+        &mut self,
+        renderer: &mut Gles2Renderer,
+        window_id: DomainWindowIdentifier,
+        blur: &BackdropBlur,
+        below: &[E],
+        output_size: Size<i32, Physical>,
+        scale: f64,
+        clear_color: [f32; 4],
+    ) -> Result<Vec<TextureRenderElement<Gles2Texture>>, Gles2Error> {
+        let Some(programs) = self.programs(renderer) else {
+            return Ok(Vec::new());
+        };
+        let output = Rectangle::<i32, Physical>::from_loc_and_size((0, 0), output_size);
+        let rects: Vec<Rectangle<i32, Logical>> = blur
+            .rects
+            .iter()
+            .filter_map(|rect| rect.intersection(blur.geometry))
+            .filter(|rect| rect.to_physical_precise_round(scale).intersection(output).is_some())
+            .collect();
+        let Some(bounds) = rects.iter().copied().reduce(|a, b| a.merge(b)) else {
+            return Ok(Vec::new());
+        };
+
+        // Only the area the blur can reach needs the frame below drawn.
+        let reach = blur.style.reach().ceil() as i32;
+        let Some(area) = bounds.to_physical_precise_round::<f64, i32>(scale).intersection(output).and_then(|area| {
+            Rectangle::from_loc_and_size((area.loc.x - reach, area.loc.y - reach), (area.size.w + 2 * reach, area.size.h + 2 * reach))
+                .intersection(output)
+        }) else {
+            return Ok(Vec::new());
+        };
+        let full_size = Size::<i32, Buffer>::from((output_size.w, output_size.h));
+        let mut scene = self.scratch_texture(renderer, 0, full_size)?;
+        {
+            let mut target = renderer.bind(&mut scene)?;
+            let mut frame = renderer.render(&mut target, output_size, Transform::Normal)?;
+            frame.clear(clear_color.into(), &[output])?;
+            draw_render_elements(&mut frame, scale, below, &[area])?;
+            frame.finish()?;
+        }
+
+        let passes = blur.style.clamped_passes();
+        let levels = level_sizes(output_size.w as u32, output_size.h as u32, passes);
+        let mut level_textures = Vec::with_capacity(levels.len());
+        for (index, (w, h)) in levels.iter().enumerate() {
+            level_textures.push(self.scratch_texture(renderer, index + 1, Size::from((*w as i32, *h as i32)))?);
+        }
+        let physical_geometry = blur.geometry.to_f64().to_physical(scale);
+        let window_rect = (
+            physical_geometry.loc.x as f32,
+            physical_geometry.loc.y as f32,
+            physical_geometry.size.w as f32,
+            physical_geometry.size.h as f32,
+        );
+        let pass = |renderer: &mut Gles2Renderer, source: &Gles2Texture, target: &mut Gles2Texture, mode: f32| {
+            blur_pass(renderer, &programs.blur, source, target, mode, blur.style.offset, window_rect, blur.corner_radius * scale as f32)
+        };
+
+        let mut source = scene;
+        for target in level_textures.iter_mut() {
+            pass(renderer, &source, target, 0.0)?;
+            source = target.clone();
+        }
+        for target in level_textures.iter_mut().rev().skip(1) {
+            pass(renderer, &source, target, 1.0)?;
+            source = target.clone();
+        }
+        let mut result = match self.blur_results.get(&window_id) {
+            Some(texture) if texture.size() == full_size => texture.clone(),
+            _ => Offscreen::<Gles2Texture>::create_buffer(renderer, Fourcc::Abgr8888, full_size)?,
+        };
+        pass(renderer, &source, &mut result, 2.0)?;
+        self.blur_results.insert(window_id, result.clone());
+
+        Ok(rects
+            .iter()
+            .filter_map(|rect| {
+                let physical = rect.to_physical_precise_round::<f64, i32>(scale).intersection(output)?;
+                Some(TextureRenderElement::from_static_texture(
+                    Id::new(),
+                    renderer.context_id(),
+                    physical.loc.to_f64(),
+                    result.clone(),
+                    1,
+                    Transform::Normal,
+                    Some(blur.alpha),
+                    Some(Rectangle::from_loc_and_size(
+                        (physical.loc.x as f64, physical.loc.y as f64),
+                        (physical.size.w as f64, physical.size.h as f64),
+                    )),
+                    Some(rect.size),
+                    None,
+                    Kind::Unspecified,
+                ))
+            })
+            .collect())
+    }
+
+    /// Scratch texture `index` of the blur, (re)created at `size`.
+    fn scratch_texture(&mut self, renderer: &mut Gles2Renderer, index: usize, size: Size<i32, Buffer>) -> Result<Gles2Texture, Gles2Error> {
+        if self.blur_scratch.get(index).map_or(true, |texture| texture.size() != size) {
+            let texture = Offscreen::<Gles2Texture>::create_buffer(renderer, Fourcc::Abgr8888, size)?;
+            if index < self.blur_scratch.len() {
+                self.blur_scratch[index] = texture;
+            } else {
+                self.blur_scratch.push(texture);
+            }
+        }
+        Ok(self.blur_scratch[index].clone())
+    }
+}
+// ANCHOR_END: GlesEffects
+
+/// Draws all of `source` into all of `target` with one blur pass. `mode` is the shader's: 0
+/// downsamples, 1 upsamples, 2 upsamples and cuts out `rect` with `radius`.
+#[allow(clippy::too_many_arguments)]
+fn blur_pass(
+    renderer: &mut Gles2Renderer,
+    program: &GlesTexProgram,
+    source: &Gles2Texture,
+    target: &mut Gles2Texture,
+    mode: f32,
+    offset: f32,
+    rect: (f32, f32, f32, f32),
+    radius: f32,
+) -> Result<(), Gles2Error> {
+    let source_size = source.size();
+    let target_size = target.size();
+    let dst = Rectangle::<i32, Physical>::from_loc_and_size((0, 0), (target_size.w, target_size.h));
+    let src = Rectangle::<f64, Buffer>::from_loc_and_size((0.0, 0.0), (source_size.w as f64, source_size.h as f64));
+    let mut framebuffer = renderer.bind(target)?;
+    let mut frame = renderer.render(&mut framebuffer, dst.size, Transform::Normal)?;
+    frame.clear([0.0, 0.0, 0.0, 0.0].into(), &[dst])?;
+    frame.render_texture_from_to(
+        source,
+        src,
+        dst,
+        &[dst],
+        &[],
+        Transform::Normal,
+        1.0,
+        Some(program),
+        &[
+            Uniform::new("mode", mode),
+            Uniform::new("texel", (1.0 / source_size.w as f32, 1.0 / source_size.h as f32)),
+            Uniform::new("offset", offset),
+            Uniform::new("rect", rect),
+            Uniform::new("radius", radius),
+            Uniform::new("target_size", (target_size.w as f32, target_size.h as f32)),
+        ],
+    )?;
+    frame.finish()?;
+    Ok(())
+}
+
+// ANCHOR: RoundedElement
+/// Rounded corners of one window, to be applied to each of its elements.
+#[derive(Debug, Clone)]
+pub struct RoundedCorners {
+    program: GlesTexProgram,
+    /// Window geometry in physical pixels at `scale`.
+    geometry: Rectangle<f64, Physical>,
+    corner_radius: f32,
+    scale: Scale<f64>,
+}
+
+impl RoundedCorners {
+    pub fn apply<E: Element>(&self, element: E) -> RoundedElement<E> {
+        RoundedElement { inner: element, corners: self.clone() }
+    }
+
+    /// Uniforms for drawing `element` with `gles_rounded.frag` or `gles_color_lut.frag`.
+    pub(crate) fn uniforms<E: Element>(&self, element: &E) -> Vec<Uniform<'static>> {
+        let element = element.geometry(self.scale).to_f64();
+        vec![
+            Uniform::new(
+                "element_offset",
+                ((element.loc.x - self.geometry.loc.x) as f32, (element.loc.y - self.geometry.loc.y) as f32),
+            ),
+            Uniform::new("element_size", (element.size.w as f32, element.size.h as f32)),
+            Uniform::new("geo_size", (self.geometry.size.w as f32, self.geometry.size.h as f32)),
+            Uniform::new("corner_radius", self.corner_radius),
+        ]
+    }
+}
+
+/// An element cut to the rounded geometry of the window it belongs to.
+///
+/// The mask is computed in the element's own, unscaled pixels, so a window shrunk by an
+/// animation keeps proportionally shrunk corners. Buffers cropped by a viewport or drawn with
+/// a buffer transform are masked as if they were neither. Rounded elements report no opaque
+/// regions and are never scanned out directly.
+#[derive(Debug)]
+pub struct RoundedElement<E> {
+    inner: E,
+    corners: RoundedCorners,
+}
+
+impl<E: Element> Element for RoundedElement<E> {
+    fn id(&self) -> &Id {
+        self.inner.id()
+    }
+
+    fn current_commit(&self) -> CommitCounter {
+        self.inner.current_commit()
+    }
+
+    fn location(&self, scale: Scale<f64>) -> Point<i32, Physical> {
+        self.inner.location(scale)
+    }
+
+    fn src(&self) -> Rectangle<f64, Buffer> {
+        self.inner.src()
+    }
+
+    fn transform(&self) -> Transform {
+        self.inner.transform()
+    }
+
+    fn geometry(&self, scale: Scale<f64>) -> Rectangle<i32, Physical> {
+        self.inner.geometry(scale)
+    }
+
+    fn damage_since(&self, scale: Scale<f64>, commit: Option<CommitCounter>) -> DamageSet<i32, Physical> {
+        self.inner.damage_since(scale, commit)
+    }
+
+    fn opaque_regions(&self, _scale: Scale<f64>) -> OpaqueRegions<i32, Physical> {
+        OpaqueRegions::default()
+    }
+
+    fn alpha(&self) -> f32 {
+        self.inner.alpha()
+    }
+
+    fn kind(&self) -> Kind {
+        self.inner.kind()
+    }
+}
+
+impl<E: RenderElement<Gles2Renderer>> RenderElement<Gles2Renderer> for RoundedElement<E> {
+    fn draw(
+        &self,
+        frame: &mut Gles2Frame<'_, '_>,
+        src: Rectangle<f64, Buffer>,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        opaque_regions: &[Rectangle<i32, Physical>],
+    ) -> Result<(), Gles2Error> {
+        frame.override_default_tex_program(self.corners.program.clone(), self.corners.uniforms(&self.inner));
+        let result = self.inner.draw(frame, src, dst, damage, opaque_regions);
+        frame.clear_tex_program_override();
+        result
+    }
+
+    fn underlying_storage(&self, _renderer: &mut Gles2Renderer) -> Option<UnderlyingStorage<'_>> {
+        None
+    }
+}
+// ANCHOR_END: RoundedElement
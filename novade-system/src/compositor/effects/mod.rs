@@ -0,0 +1,242 @@
+// novade-system/src/compositor/effects/mod.rs
+//! Window effects: rounded corners, drop shadows and background blur.
+//!
+//! Every surface gets a [`SurfaceEffects`] from three sources, later ones winning:
+//!
+//! 1. the [`EffectTheme`], built from the session's theme tokens and `[visual]`, which gives
+//!    toplevel windows their corner radius and shadow;
+//! 2. the surface's own blur region from ext-background-effect (see [`protocol`]);
+//! 3. `[[window_rule]]` tables matching the window's app id or the layer surface's namespace.
+//!
+//! The winit backend draws the effects of windows with the GLES shaders in [`gles`]: rounded
+//! surface elements, a shadow element and a blurred copy of the frame below the window. Its
+//! blur elements are repainted with every frame. The wgpu renderer can draw the same effects
+//! from `Shadow` and `BackdropBlur` render elements plus the corner radius of a surface's
+//! texture, though no backend hands them to it yet; as its blur reads what was drawn behind the
+//! surface this frame, damage near a blurred area grows to cover it
+//! ([`blur::expand_damage_for_blur`]).
+
+pub mod blur;
+pub mod gles;
+pub mod protocol;
+pub mod shape;
+pub mod theme;
+
+use std::collections::BTreeMap;
+
+use novade_core::types::geometry::Rect;
+use novade_domain::AppliedThemeState;
+use smithay::desktop::{layer_map_for_output, WindowSurfaceType};
+use smithay::reexports::wayland_server::protocol::wl_surface::WlSurface;
+use smithay::reexports::wayland_server::DisplayHandle;
+use smithay::wayland::compositor::{with_states, RectangleKind, RegionAttributes};
+use tracing::debug;
+
+pub use blur::BlurStyle;
+pub use protocol::{BackgroundEffectState, BackgroundEffectSurfaceState};
+pub use theme::{EffectTheme, ShadowStyle};
+
+use super::config::WindowRule;
+use super::region::Region;
+use super::state::DesktopState;
+
+/// Blur behind a surface.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SurfaceBlur {
+    pub style: BlurStyle,
+    /// Surface-local rectangles to blur behind; `None` for the whole surface.
+    pub region: Option<Vec<Rect<f32>>>,
+}
+
+// ANCHOR: SurfaceEffects
+/// Effects drawn with one surface, in logical pixels.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SurfaceEffects {
+    pub corner_radius: f32,
+    pub shadow: Option<ShadowStyle>,
+    pub blur: Option<SurfaceBlur>,
+}
+
+impl SurfaceEffects {
+    pub fn is_empty(&self) -> bool {
+        self.corner_radius <= 0.0 && self.shadow.is_none() && self.blur.is_none()
+    }
+}
+// ANCHOR_END: SurfaceEffects
+
+/// What a surface is, as far as effects and window rules care.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EffectTarget<'a> {
+    Window { app_id: Option<&'a str> },
+    Layer { namespace: &'a str },
+    /// Popups, subsurfaces and surfaces without a role.
+    Other,
+}
+
+impl EffectTarget<'_> {
+    fn matches(&self, rule: &WindowRule) -> bool {
+        match *self {
+            EffectTarget::Window { app_id } => rule.app_id.is_some() && rule.app_id.as_deref() == app_id,
+            EffectTarget::Layer { namespace } => rule.namespace.as_deref() == Some(namespace),
+            EffectTarget::Other => false,
+        }
+    }
+}
+
+// ANCHOR: ResolveEffects
+/// Effects of a surface from the theme, its requested blur region and the window rules.
+///
+/// Only windows get the theme's corners and shadow by default; rules can add them to layer
+/// surfaces. A rule's `blur` overrides the client's request: `true` blurs behind the whole
+/// surface, `false` never blurs. Nothing is blurred while blur is disabled.
+pub fn resolve_effects(theme: &EffectTheme, rules: &[WindowRule], target: EffectTarget<'_>, requested_blur: Option<Vec<Rect<f32>>>) -> SurfaceEffects {
+    let is_window = matches!(target, EffectTarget::Window { .. });
+    let mut corner_radius = if is_window { theme.corner_radius } else { 0.0 };
+    let mut shadow = is_window;
+    let mut blur = requested_blur.map(Some);
+    for rule in rules.iter().filter(|rule| target.matches(rule)) {
+        corner_radius = rule.corner_radius.unwrap_or(corner_radius);
+        shadow = rule.shadow.unwrap_or(shadow);
+        match rule.blur {
+            Some(true) => blur = Some(None),
+            Some(false) => blur = None,
+            None => {}
+        }
+    }
+    SurfaceEffects {
+        corner_radius,
+        shadow: theme.shadow.filter(|_| shadow),
+        blur: theme.blur.zip(blur).map(|(style, region)| SurfaceBlur { style, region }),
+    }
+}
+// ANCHOR_END: ResolveEffects
+
+/// Resolves a `wl_region` into disjoint rectangles; `None` if it covers nothing.
+fn region_rects(attributes: &RegionAttributes) -> Option<Vec<Rect<f32>>> {
+    let mut region = Region::new();
+    for (kind, rect) in &attributes.rects {
+        let rect = Rect::from_coords(rect.loc.x as f32, rect.loc.y as f32, rect.size.w as f32, rect.size.h as f32);
+        match kind {
+            RectangleKind::Add => region.union_rect(&rect),
+            RectangleKind::Subtract => region.subtract_rect(&rect),
+        }
+    }
+    (!region.is_empty()).then(|| region.rects().to_vec())
+}
+
+// ANCHOR: EffectsState
+/// Effect defaults and the ext-background-effect global.
+pub struct EffectsState {
+    pub protocol: BackgroundEffectState,
+    theme: EffectTheme,
+    /// Last tokens pushed by the theming engine; kept to rebuild the theme on config reloads.
+    tokens: BTreeMap<String, String>,
+}
+
+impl EffectsState {
+    pub fn new(display: &DisplayHandle, visual: &super::config::VisualConfig) -> Self {
+        Self { protocol: BackgroundEffectState::new(display), theme: EffectTheme::from_config(visual), tokens: BTreeMap::new() }
+    }
+
+    pub fn theme(&self) -> &EffectTheme {
+        &self.theme
+    }
+}
+// ANCHOR_END: EffectsState
+
+// ANCHOR: DesktopStateEffectsIntegration
+impl DesktopState {
+    /// Rebuilds the effect theme after `[visual]` or `[[window_rule]]` changed.
+    pub fn reconfigure_effects(&mut self) {
+        let theme = EffectTheme::from_tokens(&self.effects.tokens, &self.config.visual);
+        let blur_changed = theme.blur.is_some() != self.effects.theme.blur.is_some();
+        self.effects.theme = theme;
+        if blur_changed {
+            self.effects.protocol.send_capabilities(self.effects.theme.blur.is_some());
+        }
+        self.schedule_redraw_all();
+    }
+
+    /// Takes the resolved tokens of the active theme, e.g. after a theme or accent switch.
+    pub fn apply_theme_tokens(&mut self, tokens: BTreeMap<String, String>) {
+        debug!("Applying {} theme tokens to window effects", tokens.len());
+        self.effects.tokens = tokens;
+        self.reconfigure_effects();
+    }
+
+    pub fn apply_theme_state(&mut self, theme: &AppliedThemeState) {
+        let tokens = theme.resolved_tokens.iter().map(|(id, value)| (id.as_str().to_string(), value.clone())).collect();
+        self.apply_theme_tokens(tokens);
+    }
+
+    /// Effects to draw with `surface`.
+    pub fn surface_effects(&self, surface: &WlSurface) -> SurfaceEffects {
+        let requested_blur = with_states(surface, |states| {
+            states.cached_state.get::<BackgroundEffectSurfaceState>().current().blur_region.clone()
+        });
+        let requested_blur = requested_blur.and_then(|region| region_rects(&region));
+        let namespace = self.space.lock().unwrap().outputs().find_map(|output| {
+            layer_map_for_output(output).layer_for_surface(surface, WindowSurfaceType::TOPLEVEL).map(|layer| layer.namespace().to_string())
+        });
+        let window = self.windows.values().find(|window| window.wl_surface_ref() == Some(surface));
+        let target = match (&namespace, window) {
+            (Some(namespace), _) => EffectTarget::Layer { namespace },
+            (None, Some(window)) => EffectTarget::Window { app_id: window.app_id.as_deref() },
+            _ => EffectTarget::Other,
+        };
+        resolve_effects(&self.effects.theme, &self.config.window_rules, target, requested_blur)
+    }
+}
+// ANCHOR_END: DesktopStateEffectsIntegration
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn theme() -> EffectTheme {
+        EffectTheme { corner_radius: 8.0, shadow: Some(ShadowStyle::default()), blur: Some(BlurStyle::default()) }
+    }
+
+    fn rule(app_id: Option<&str>, namespace: Option<&str>) -> WindowRule {
+        WindowRule { app_id: app_id.map(String::from), namespace: namespace.map(String::from), ..WindowRule::default() }
+    }
+
+    #[test]
+    fn test_defaults_apply_to_windows_only() {
+        let window = resolve_effects(&theme(), &[], EffectTarget::Window { app_id: Some("foot") }, None);
+        assert_eq!(window.corner_radius, 8.0);
+        assert_eq!(window.shadow, Some(ShadowStyle::default()));
+        assert!(window.blur.is_none());
+
+        let panel = resolve_effects(&theme(), &[], EffectTarget::Layer { namespace: "novade-panel" }, None);
+        assert!(panel.is_empty());
+    }
+
+    #[test]
+    fn test_window_rules_override_in_order() {
+        let region = vec![Rect::from_coords(0.0, 0.0, 100.0, 30.0)];
+        let mut blur_panel = rule(None, Some("novade-panel"));
+        blur_panel.blur = Some(true);
+        blur_panel.corner_radius = Some(12.0);
+        let mut square_foot = rule(Some("foot"), None);
+        square_foot.corner_radius = Some(0.0);
+        square_foot.shadow = Some(false);
+        square_foot.blur = Some(false);
+        let mut round_foot = rule(Some("foot"), None);
+        round_foot.corner_radius = Some(4.0);
+        let rules = [blur_panel, square_foot, round_foot];
+
+        let panel = resolve_effects(&theme(), &rules, EffectTarget::Layer { namespace: "novade-panel" }, Some(region.clone()));
+        assert_eq!(panel.corner_radius, 12.0);
+        assert_eq!(panel.blur, Some(SurfaceBlur { style: BlurStyle::default(), region: None }));
+
+        let foot = resolve_effects(&theme(), &rules, EffectTarget::Window { app_id: Some("foot") }, Some(region.clone()));
+        assert_eq!((foot.corner_radius, foot.shadow, foot.blur), (4.0, None, None));
+
+        // A client's own request is honoured when no rule says otherwise, unless blur is off.
+        let other = resolve_effects(&theme(), &rules, EffectTarget::Window { app_id: None }, Some(region.clone()));
+        assert_eq!(other.blur.unwrap().region, Some(region.clone()));
+        let no_blur = EffectTheme { blur: None, ..theme() };
+        assert!(resolve_effects(&no_blur, &rules, EffectTarget::Layer { namespace: "novade-panel" }, Some(region)).blur.is_none());
+    }
+}
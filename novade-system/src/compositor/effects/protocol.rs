@@ -0,0 +1,129 @@
+// novade-system/src/compositor/effects/protocol.rs
+
+//! Server side of `ext-background-effect-v1`.
+//!
+//! A surface asks for the content behind it to be blurred within a region. The region is
+//! double-buffered surface state ([`BackgroundEffectSurfaceState`]) applied on commit. The
+//! manager advertises blur only while `[visual.effects] blur` is on and tells bound clients
+//! when that changes.
+
+use std::collections::HashMap;
+
+use smithay::reexports::wayland_protocols::ext::background_effect::v1::server::{
+    ext_background_effect_manager_v1::{self, Capability, ExtBackgroundEffectManagerV1},
+    ext_background_effect_surface_v1::{self, ExtBackgroundEffectSurfaceV1},
+};
+use smithay::reexports::wayland_server::backend::{ClientId, GlobalId, ObjectId};
+use smithay::reexports::wayland_server::protocol::wl_surface::WlSurface;
+use smithay::reexports::wayland_server::{Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource, Weak};
+use smithay::wayland::compositor::{get_region_attributes, with_states, Cacheable, RegionAttributes};
+use tracing::debug;
+
+use crate::compositor::state::DesktopState;
+
+const VERSION: u32 = 1;
+
+// ANCHOR: BackgroundEffectSurfaceState
+/// Double-buffered background effect of a surface.
+#[derive(Debug, Clone, Default)]
+pub struct BackgroundEffectSurfaceState {
+    /// Region to blur behind, in surface-local coordinates; `None` for no blur.
+    pub blur_region: Option<RegionAttributes>,
+}
+
+impl Cacheable for BackgroundEffectSurfaceState {
+    fn commit(&mut self, _dh: &DisplayHandle) -> Self {
+        self.clone()
+    }
+
+    fn merge_into(self, into: &mut Self, _dh: &DisplayHandle) {
+        *into = self;
+    }
+}
+// ANCHOR_END: BackgroundEffectSurfaceState
+
+// ANCHOR: BackgroundEffectState
+/// Background effect protocol objects.
+pub struct BackgroundEffectState {
+    _global: GlobalId,
+    managers: Vec<ExtBackgroundEffectManagerV1>,
+    /// Background effect objects by `wl_surface`; a surface may have only one.
+    surfaces: HashMap<ObjectId, ExtBackgroundEffectSurfaceV1>,
+}
+
+impl BackgroundEffectState {
+    pub fn new(display: &DisplayHandle) -> Self {
+        let global = display.create_global::<DesktopState, ExtBackgroundEffectManagerV1, ()>(VERSION, ());
+        Self { _global: global, managers: Vec::new(), surfaces: HashMap::new() }
+    }
+
+    /// Sends the current capabilities to every bound manager.
+    pub fn send_capabilities(&mut self, blur: bool) {
+        self.managers.retain(|manager| manager.is_alive());
+        for manager in &self.managers {
+            manager.capabilities(capabilities(blur));
+        }
+    }
+}
+
+fn capabilities(blur: bool) -> Capability {
+    if blur {
+        Capability::Blur
+    } else {
+        Capability::empty()
+    }
+}
+// ANCHOR_END: BackgroundEffectState
+
+impl GlobalDispatch<ExtBackgroundEffectManagerV1, ()> for DesktopState {
+    fn bind(state: &mut Self, _dh: &DisplayHandle, _client: &Client, resource: New<ExtBackgroundEffectManagerV1>, _data: &(), data_init: &mut DataInit<'_, Self>) {
+        let manager = data_init.init(resource, ());
+        manager.capabilities(capabilities(state.effects.theme.blur.is_some()));
+        state.effects.protocol.managers.push(manager);
+    }
+}
+
+impl Dispatch<ExtBackgroundEffectManagerV1, ()> for DesktopState {
+    fn request(state: &mut Self, _client: &Client, manager: &ExtBackgroundEffectManagerV1, request: ext_background_effect_manager_v1::Request, _data: &(), _dh: &DisplayHandle, data_init: &mut DataInit<'_, Self>) {
+        match request {
+            ext_background_effect_manager_v1::Request::GetBackgroundEffect { id, surface } => {
+                let resource = data_init.init(id, surface.downgrade());
+                let surfaces = &mut state.effects.protocol.surfaces;
+                if surfaces.get(&surface.id()).is_some_and(|existing| existing.is_alive()) {
+                    manager.post_error(ext_background_effect_manager_v1::Error::BackgroundEffectExists, "wl_surface already has a background effect object");
+                    return;
+                }
+                surfaces.insert(surface.id(), resource);
+            }
+            ext_background_effect_manager_v1::Request::Destroy => {}
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<ExtBackgroundEffectSurfaceV1, Weak<WlSurface>> for DesktopState {
+    fn request(_state: &mut Self, _client: &Client, resource: &ExtBackgroundEffectSurfaceV1, request: ext_background_effect_surface_v1::Request, surface: &Weak<WlSurface>, _dh: &DisplayHandle, _data_init: &mut DataInit<'_, Self>) {
+        let Ok(surface) = surface.upgrade() else {
+            if !matches!(request, ext_background_effect_surface_v1::Request::Destroy) {
+                resource.post_error(ext_background_effect_surface_v1::Error::SurfaceDestroyed, "the wl_surface was destroyed");
+            }
+            return;
+        };
+        let pending = match request {
+            ext_background_effect_surface_v1::Request::SetBlurRegion { region } => {
+                debug!(surface = ?surface.id(), blur = region.is_some(), "Background blur region set");
+                BackgroundEffectSurfaceState { blur_region: region.as_ref().map(get_region_attributes) }
+            }
+            _ => return,
+        };
+        with_states(&surface, |states| *states.cached_state.get::<BackgroundEffectSurfaceState>().pending() = pending);
+    }
+
+    fn destroyed(state: &mut Self, _client: ClientId, resource: &ExtBackgroundEffectSurfaceV1, surface: &Weak<WlSurface>) {
+        // Destroying the object removes the blur on the next commit.
+        if let Ok(surface) = surface.upgrade() {
+            with_states(&surface, |states| *states.cached_state.get::<BackgroundEffectSurfaceState>().pending() = BackgroundEffectSurfaceState::default());
+        }
+        state.effects.protocol.surfaces.retain(|_, r| r != resource);
+    }
+}
@@ -0,0 +1,120 @@
+// novade-system/src/compositor/effects/shape.rs
+
+//! Coverage functions for rounded rectangles and their shadows.
+//!
+//! Renderers evaluate these per pixel centre: the software renderer calls them directly,
+//! `assets/shaders/effects.wgsl` and `gles_shadow.frag` mirror them line by line, so all draw
+//! the same shapes.
+//! Rectangles are `[x, y, width, height]` in target pixels.
+
+use super::ShadowStyle;
+
+/// Signed distance from `(x, y)` to the outline of a rectangle with rounded corners, negative
+/// inside. The radius is limited to half the shorter side.
+pub fn rounded_rect_distance(x: f32, y: f32, rect: [f32; 4], radius: f32) -> f32 {
+    let [left, top, w, h] = rect;
+    let (hw, hh) = (w / 2.0, h / 2.0);
+    let radius = radius.clamp(0.0, hw.min(hh).max(0.0));
+    let qx = (x - left - hw).abs() - hw + radius;
+    let qy = (y - top - hh).abs() - hh + radius;
+    let outside = (qx.max(0.0) * qx.max(0.0) + qy.max(0.0) * qy.max(0.0)).sqrt();
+    outside + qx.max(qy).min(0.0) - radius
+}
+
+/// Coverage of the pixel centred at `(x, y)` by a rounded rectangle, anti-aliased over one pixel.
+pub fn rounded_rect_coverage(x: f32, y: f32, rect: [f32; 4], radius: f32) -> f32 {
+    (0.5 - rounded_rect_distance(x, y, rect, radius)).clamp(0.0, 1.0)
+}
+
+/// Error function after Abramowitz and Stegun 7.1.26; absolute error below 1.5e-7.
+pub fn erf(x: f32) -> f32 {
+    let t = 1.0 / (1.0 + 0.327_591_1 * x.abs());
+    let poly = t * (0.254_829_6 + t * (-0.284_496_74 + t * (1.421_413_7 + t * (-1.453_152 + t * 1.061_405_4))));
+    (1.0 - poly * (-x * x).exp()).copysign(x)
+}
+
+/// Rectangle and corner radius of the shadow cast by `rect`: moved by the offset and grown by
+/// the spread. `None` if a negative spread swallows it.
+pub fn shadow_shape(rect: [f32; 4], radius: f32, style: &ShadowStyle) -> Option<([f32; 4], f32)> {
+    let [x, y, w, h] = rect;
+    let s = style.spread;
+    let shape = [x + style.offset.0 - s, y + style.offset.1 - s, w + 2.0 * s, h + 2.0 * s];
+    (shape[2] > 0.0 && shape[3] > 0.0).then(|| (shape, (radius + s).max(0.0)))
+}
+
+/// Pixel bounds of the shadow cast by `rect`; three standard deviations cover all but 0.2% of
+/// the Gaussian.
+pub fn shadow_bounds(rect: [f32; 4], radius: f32, style: &ShadowStyle) -> Option<[f32; 4]> {
+    let ([x, y, w, h], _) = shadow_shape(rect, radius, style)?;
+    let pad = (3.0 * style.sigma()).ceil() + 1.0;
+    Some([(x - pad).floor(), (y - pad).floor(), (w + 2.0 * pad).ceil(), (h + 2.0 * pad).ceil()])
+}
+
+/// Opacity at `(x, y)` of the shadow cast by `rect`, to be multiplied with the shadow colour.
+///
+/// The shadow is the shape's coverage convolved with a Gaussian along the distance field, which
+/// is exact along straight edges and close enough at the corners. The casting rectangle itself
+/// is cut out, so translucent windows do not show their own shadow through.
+pub fn shadow_coverage(x: f32, y: f32, rect: [f32; 4], radius: f32, style: &ShadowStyle) -> f32 {
+    let Some((shape, shape_radius)) = shadow_shape(rect, radius, style) else {
+        return 0.0;
+    };
+    let distance = rounded_rect_distance(x, y, shape, shape_radius);
+    let sigma = style.sigma();
+    let shadow = if sigma > 0.0 {
+        0.5 - 0.5 * erf(distance / (sigma * std::f32::consts::SQRT_2))
+    } else {
+        (0.5 - distance).clamp(0.0, 1.0)
+    };
+    shadow * (1.0 - rounded_rect_coverage(x, y, rect, radius))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WINDOW: [f32; 4] = [10.0, 10.0, 40.0, 20.0];
+
+    fn style(offset: (f32, f32), blur_radius: f32, spread: f32) -> ShadowStyle {
+        ShadowStyle { offset, blur_radius, spread, color: [0.0, 0.0, 0.0, 1.0] }
+    }
+
+    #[test]
+    fn test_rounded_rect_distance() {
+        assert_eq!(rounded_rect_distance(30.0, 20.0, WINDOW, 0.0), -10.0);
+        assert_eq!(rounded_rect_distance(55.0, 20.0, WINDOW, 6.0), 5.0);
+        // Corner: distance to the arc centred at (16, 16).
+        let d = rounded_rect_distance(10.0, 10.0, WINDOW, 6.0);
+        assert!((d - (72.0f32.sqrt() - 6.0)).abs() < 1e-5, "{}", d);
+        // The radius is limited to half the height.
+        assert_eq!(rounded_rect_distance(30.0, 10.0, WINDOW, 50.0), 0.0);
+        assert_eq!(rounded_rect_coverage(10.5, 20.0, WINDOW, 6.0), 1.0);
+        assert_eq!(rounded_rect_coverage(10.5, 10.5, WINDOW, 6.0), 0.0);
+    }
+
+    #[test]
+    fn test_erf() {
+        assert!(erf(0.0).abs() < 1e-6);
+        assert!((erf(1.0) - 0.842_700_8).abs() < 1e-6);
+        assert!((erf(-0.5) + 0.520_499_9).abs() < 1e-6);
+        assert!((erf(3.0) - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_shadow_coverage() {
+        let soft = style((0.0, 4.0), 8.0, 0.0);
+        // Half intensity on the shadow's edge, fading outwards; nothing under the window.
+        assert!((shadow_coverage(30.0, 34.0, WINDOW, 0.0, &soft) - 0.5).abs() < 1e-4);
+        assert!(shadow_coverage(30.0, 42.0, WINDOW, 0.0, &soft) < 0.05);
+        assert_eq!(shadow_coverage(30.0, 20.0, WINDOW, 0.0, &soft), 0.0);
+        // A hard shadow is the moved shape.
+        let hard = style((5.0, 5.0), 0.0, 0.0);
+        assert_eq!(shadow_coverage(52.5, 32.5, WINDOW, 0.0, &hard), 1.0);
+        assert_eq!(shadow_coverage(55.5, 32.5, WINDOW, 0.0, &hard), 0.0);
+
+        let bounds = shadow_bounds(WINDOW, 0.0, &soft).unwrap();
+        assert_eq!(bounds, [-3.0, 1.0, 66.0, 46.0]);
+        assert!(shadow_coverage(bounds[0] + 0.5, 30.0, WINDOW, 0.0, &soft) < 0.002);
+        assert!(shadow_bounds(WINDOW, 0.0, &style((0.0, 0.0), 0.0, -10.0)).is_none());
+    }
+}
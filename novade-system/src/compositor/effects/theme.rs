@@ -0,0 +1,239 @@
+// novade-system/src/compositor/effects/theme.rs
+
+//! Effect defaults from theme tokens.
+//!
+//! The session's theming engine resolves tokens and pushes them over IPC
+//! (`set_theme_tokens`). Until then, and for tokens a theme does not define, the `[visual]`
+//! section of `compositor.toml` provides the values.
+
+use std::collections::BTreeMap;
+
+use super::blur::BlurStyle;
+use crate::compositor::config::VisualConfig;
+
+/// Corner radius of windows, e.g. `"8px"`.
+pub const TOKEN_WINDOW_CORNER_RADIUS: &str = "property-window-corner-radius";
+/// General corner radius, used for windows when the theme has no window-specific one.
+pub const TOKEN_BORDER_RADIUS: &str = "property-border-radius";
+/// CSS `box-shadow` of windows, e.g. `"0 4px 16px 0 rgba(0, 0, 0, 0.5)"`.
+pub const TOKEN_WINDOW_SHADOW: &str = "shadow-window";
+/// Shadow colour, used with the configured geometry when the theme has no window shadow.
+pub const TOKEN_SHADOW_COLOR: &str = "color-shadow-default";
+
+// ANCHOR: ShadowStyle
+/// A drop shadow as in CSS `box-shadow`, in logical pixels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShadowStyle {
+    pub offset: (f32, f32),
+    /// CSS blur radius: twice the standard deviation of the Gaussian.
+    pub blur_radius: f32,
+    /// Growth of the shadow's shape on every side; negative values shrink it.
+    pub spread: f32,
+    /// Straight (not premultiplied) RGBA.
+    pub color: [f32; 4],
+}
+
+impl ShadowStyle {
+    /// Standard deviation of the Gaussian.
+    pub fn sigma(&self) -> f32 {
+        self.blur_radius.max(0.0) / 2.0
+    }
+
+    /// The style with all lengths multiplied by `factor`, e.g. from logical to physical pixels.
+    pub fn scaled(&self, factor: f32) -> Self {
+        Self {
+            offset: (self.offset.0 * factor, self.offset.1 * factor),
+            blur_radius: self.blur_radius * factor,
+            spread: self.spread * factor,
+            color: self.color,
+        }
+    }
+
+    /// Parses a single CSS shadow: two to four lengths and a colour, in any order of the two
+    /// groups. `inset` shadows and lists of shadows are not supported.
+    pub fn parse_css(value: &str) -> Option<Self> {
+        let mut lengths = Vec::new();
+        let mut color = None;
+        // Lengths must be contiguous: none may follow a colour that followed lengths.
+        let mut lengths_closed = false;
+        for part in split_css(value) {
+            match parse_length(&part) {
+                Some(length) if !lengths_closed => lengths.push(length),
+                Some(_) => return None,
+                None => {
+                    if color.is_some() {
+                        return None;
+                    }
+                    lengths_closed = !lengths.is_empty();
+                    color = Some(parse_css_color(&part)?);
+                }
+            }
+        }
+        if !(2..=4).contains(&lengths.len()) {
+            return None;
+        }
+        Some(Self {
+            offset: (lengths[0], lengths[1]),
+            blur_radius: lengths.get(2).copied().unwrap_or(0.0).max(0.0),
+            spread: lengths.get(3).copied().unwrap_or(0.0),
+            color: color.unwrap_or([0.0, 0.0, 0.0, 1.0]),
+        })
+    }
+}
+
+impl Default for ShadowStyle {
+    fn default() -> Self {
+        Self { offset: (0.0, 4.0), blur_radius: 16.0, spread: 0.0, color: [0.0, 0.0, 0.0, 0.5] }
+    }
+}
+// ANCHOR_END: ShadowStyle
+
+/// Splits on whitespace outside of parentheses.
+fn split_css(value: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut depth = 0u32;
+    for c in value.trim().chars() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            c if c.is_whitespace() && depth == 0 => {
+                if !current.is_empty() {
+                    parts.push(std::mem::take(&mut current));
+                }
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+    if !current.is_empty() {
+        parts.push(current);
+    }
+    parts
+}
+
+/// A length in `px`; a bare number is accepted as pixels.
+fn parse_length(value: &str) -> Option<f32> {
+    let number = value.strip_suffix("px").unwrap_or(value);
+    number.parse::<f32>().ok().filter(|n| n.is_finite())
+}
+
+/// `#rgb`, `#rrggbb`, `#rrggbbaa`, `rgb(r, g, b)` and `rgba(r, g, b, a)`, as straight RGBA.
+fn parse_css_color(value: &str) -> Option<[f32; 4]> {
+    if let Some(hex) = value.strip_prefix('#') {
+        if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        let digit = |i: usize| u8::from_str_radix(&hex[i..i + 1], 16).ok().map(|d| (d * 17) as f32 / 255.0);
+        let byte = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok().map(|b| b as f32 / 255.0);
+        return match hex.len() {
+            3 => Some([digit(0)?, digit(1)?, digit(2)?, 1.0]),
+            6 => Some([byte(0)?, byte(2)?, byte(4)?, 1.0]),
+            8 => Some([byte(0)?, byte(2)?, byte(4)?, byte(6)?]),
+            _ => None,
+        };
+    }
+    let (name, args) = value.strip_suffix(')')?.split_once('(')?;
+    let args: Vec<&str> = args.split(',').map(str::trim).collect();
+    let channel = |s: &str| s.parse::<f32>().ok().map(|v| (v / 255.0).clamp(0.0, 1.0));
+    match (name.trim(), args.as_slice()) {
+        ("rgb", [r, g, b]) => Some([channel(r)?, channel(g)?, channel(b)?, 1.0]),
+        ("rgba", [r, g, b, a]) => Some([channel(r)?, channel(g)?, channel(b)?, a.parse::<f32>().ok()?.clamp(0.0, 1.0)]),
+        _ => None,
+    }
+}
+
+// ANCHOR: EffectTheme
+/// Effect defaults for windows.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EffectTheme {
+    /// Corner radius of windows in logical pixels.
+    pub corner_radius: f32,
+    /// Shadow of windows; `None` when shadows are disabled.
+    pub shadow: Option<ShadowStyle>,
+    /// Blur behind surfaces that ask for it; `None` when blur is disabled.
+    pub blur: Option<BlurStyle>,
+}
+
+impl EffectTheme {
+    /// Defaults from `[visual]` alone.
+    pub fn from_config(visual: &VisualConfig) -> Self {
+        Self::from_tokens(&BTreeMap::new(), visual)
+    }
+
+    /// Defaults from resolved theme tokens, falling back to `[visual]` for missing or
+    /// unparsable tokens. `[visual.effects]` switches still decide whether shadows and blur
+    /// are drawn at all.
+    pub fn from_tokens(tokens: &BTreeMap<String, String>, visual: &VisualConfig) -> Self {
+        let effects = &visual.effects;
+        let corner_radius = [TOKEN_WINDOW_CORNER_RADIUS, TOKEN_BORDER_RADIUS]
+            .iter()
+            .find_map(|id| tokens.get(*id).and_then(|v| parse_length(v.trim())))
+            .map(|radius| radius.max(0.0))
+            .unwrap_or(visual.border.corner_radius);
+        let shadow = tokens.get(TOKEN_WINDOW_SHADOW).and_then(|v| ShadowStyle::parse_css(v)).unwrap_or_else(|| {
+            let color = tokens.get(TOKEN_SHADOW_COLOR).and_then(|v| parse_css_color(v.trim()));
+            ShadowStyle { color: color.unwrap_or(effects.shadow_color.to_f32_array()), ..ShadowStyle::default() }
+        });
+        Self {
+            corner_radius,
+            shadow: effects.shadows.then_some(shadow),
+            blur: effects.blur.then_some(BlurStyle { passes: effects.blur_passes, offset: effects.blur_offset }),
+        }
+    }
+}
+// ANCHOR_END: EffectTheme
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_css_shadow() {
+        let shadow = ShadowStyle::parse_css("0 4px 12px 2px rgba(0, 0, 0, 0.25)").unwrap();
+        assert_eq!(shadow, ShadowStyle { offset: (0.0, 4.0), blur_radius: 12.0, spread: 2.0, color: [0.0, 0.0, 0.0, 0.25] });
+        assert_eq!(shadow.sigma(), 6.0);
+        let shadow = ShadowStyle::parse_css("#00000080 1px -2px").unwrap();
+        assert_eq!(shadow.offset, (1.0, -2.0));
+        assert_eq!(shadow.blur_radius, 0.0);
+        assert!((shadow.color[3] - 0.502).abs() < 1e-3);
+        assert_eq!(ShadowStyle::parse_css("2px 2px #fff").unwrap().color, [1.0, 1.0, 1.0, 1.0]);
+
+        assert!(ShadowStyle::parse_css("4px").is_none());
+        assert!(ShadowStyle::parse_css("1px #000 2px").is_none());
+        assert!(ShadowStyle::parse_css("1px 2px red").is_none());
+    }
+
+    #[test]
+    fn test_theme_from_tokens() {
+        let mut visual = VisualConfig::default();
+        visual.border.corner_radius = 6.0;
+        let theme = EffectTheme::from_config(&visual);
+        assert_eq!(theme.corner_radius, 6.0);
+        let shadow = theme.shadow.unwrap();
+        assert_eq!((shadow.offset, shadow.blur_radius), ((0.0, 4.0), 16.0));
+        assert!((shadow.color[3] - 0.5).abs() < 0.01);
+        assert_eq!(theme.blur, Some(BlurStyle::default()));
+
+        // The shipped themes only define the generic radius and a shadow colour.
+        let mut tokens = BTreeMap::new();
+        tokens.insert(TOKEN_BORDER_RADIUS.to_string(), "4px".to_string());
+        tokens.insert(TOKEN_SHADOW_COLOR.to_string(), "#00000040".to_string());
+        let theme = EffectTheme::from_tokens(&tokens, &visual);
+        assert_eq!(theme.corner_radius, 4.0);
+        assert_eq!(theme.shadow.unwrap().offset, ShadowStyle::default().offset);
+        assert!((theme.shadow.unwrap().color[3] - 0.25).abs() < 0.01);
+
+        tokens.insert(TOKEN_WINDOW_CORNER_RADIUS.to_string(), "12px".to_string());
+        tokens.insert(TOKEN_WINDOW_SHADOW.to_string(), "0 8px 24px rgba(0, 0, 0, 0.4)".to_string());
+        let theme = EffectTheme::from_tokens(&tokens, &visual);
+        assert_eq!(theme.corner_radius, 12.0);
+        assert_eq!(theme.shadow.unwrap().blur_radius, 24.0);
+
+        visual.effects.shadows = false;
+        visual.effects.blur = false;
+        let theme = EffectTheme::from_tokens(&tokens, &visual);
+        assert!(theme.shadow.is_none() && theme.blur.is_none());
+    }
+}
@@ -0,0 +1,747 @@
+// novade-system/src/compositor/frame_scheduler.rs
+//! Per-output frame scheduling.
+//!
+//! A [`FrameScheduler`] decides when an output renders its next frame. With vsync it starts
+//! rendering as late as possible before the vblank the frame is aimed at, using the predicted
+//! render time; with variable refresh rate it renders as soon as content changes, limited to the
+//! mode's maximum rate; with tearing it renders and flips immediately. Presentation results feed
+//! `wp_presentation` feedback, and [`FrameCallbackThrottle`] keeps surfaces that are not shown
+//! anywhere from being redrawn at full rate.
+//!
+//! All time comes from a [`FrameClock`], so tests drive the scheduler with a [`SimulatedClock`].
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use smithay::desktop::utils::{take_presentation_feedback_surface_tree, OutputPresentationFeedback};
+use smithay::output::Output;
+use smithay::reexports::wayland_protocols::wp::presentation_time::server::wp_presentation_feedback;
+use smithay::reexports::wayland_protocols::wp::tearing_control::v1::server::wp_tearing_control_v1;
+use smithay::reexports::wayland_server::protocol::wl_surface::WlSurface;
+use smithay::reexports::wayland_server::Resource;
+use smithay::utils::{Clock, Monotonic, Time};
+use smithay::wayland::compositor::with_states;
+use smithay::wayland::presentation::Refresh;
+use smithay::wayland::tearing_control::TearingControlSurfaceCachedState;
+
+use super::config::{PerformanceConfig, TearingPolicy, VrrPolicy};
+use super::state::DesktopState;
+
+/// Render time assumed before any frame has been measured.
+const DEFAULT_RENDER_TIME: Duration = Duration::from_millis(4);
+/// Extra time added to the prediction to absorb scheduling jitter.
+const RENDER_SAFETY_MARGIN: Duration = Duration::from_millis(1);
+/// Number of recent render times the prediction looks at.
+const RENDER_TIME_SAMPLES: usize = 32;
+/// Refresh interval used when an output reports no mode.
+const FALLBACK_REFRESH: Duration = Duration::from_nanos(16_666_667);
+/// How often surfaces that are not shown anywhere still get a frame callback.
+pub const HIDDEN_FRAME_CALLBACK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Source of monotonic time for the scheduler.
+pub trait FrameClock: fmt::Debug + Send + Sync {
+    /// Time since an arbitrary fixed point.
+    fn now(&self) -> Duration;
+}
+
+impl FrameClock for Clock<Monotonic> {
+    fn now(&self) -> Duration {
+        Duration::from(Clock::now(self))
+    }
+}
+
+/// Manually advanced clock for tests and replays.
+#[derive(Debug, Clone, Default)]
+pub struct SimulatedClock {
+    now: Arc<Mutex<Duration>>,
+}
+
+impl SimulatedClock {
+    pub fn new(start: Duration) -> Self {
+        Self { now: Arc::new(Mutex::new(start)) }
+    }
+
+    pub fn advance(&self, by: Duration) {
+        *self.now.lock().unwrap() += by;
+    }
+
+    pub fn set(&self, to: Duration) {
+        *self.now.lock().unwrap() = to;
+    }
+}
+
+impl FrameClock for SimulatedClock {
+    fn now(&self) -> Duration {
+        *self.now.lock().unwrap()
+    }
+}
+
+// ANCHOR: RenderTimePredictor
+/// Predicts how long the next frame takes to render from the recent ones.
+///
+/// Uses the slowest recent frame, so a single spike delays frames for a short while instead of
+/// making the output miss vblanks until the average catches up.
+#[derive(Debug, Clone, Default)]
+pub struct RenderTimePredictor {
+    samples: VecDeque<Duration>,
+    /// Fixed budget from the configuration, overriding the measurement.
+    fixed: Option<Duration>,
+}
+
+impl RenderTimePredictor {
+    pub fn record(&mut self, render_time: Duration) {
+        if self.samples.len() == RENDER_TIME_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(render_time);
+    }
+
+    pub fn set_fixed(&mut self, fixed: Option<Duration>) {
+        self.fixed = fixed;
+    }
+
+    /// Time to reserve for rendering, including the safety margin.
+    pub fn predict(&self) -> Duration {
+        if let Some(fixed) = self.fixed {
+            return fixed;
+        }
+        self.samples.iter().copied().max().unwrap_or(DEFAULT_RENDER_TIME) + RENDER_SAFETY_MARGIN
+    }
+}
+// ANCHOR_END: RenderTimePredictor
+
+/// How frames of an output are synchronised with the display.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncMode {
+    /// Fixed refresh; frames are aimed at vblanks.
+    Vsync,
+    /// Variable refresh; the display waits for the next frame.
+    Vrr,
+    /// Asynchronous page flips for a fullscreen client that asked for them.
+    Tearing,
+}
+
+/// Picks the sync mode of an output for the current frame.
+///
+/// `fullscreen` is set when a fullscreen client is shown on the output and `tearing_requested`
+/// when that client set the async presentation hint through tearing-control.
+pub fn select_sync_mode(config: &PerformanceConfig, vrr_capable: bool, fullscreen: bool, tearing_requested: bool) -> SyncMode {
+    if fullscreen && tearing_requested && config.tearing == TearingPolicy::FullscreenOptIn {
+        return SyncMode::Tearing;
+    }
+    let vrr = match config.vrr {
+        VrrPolicy::Off => false,
+        VrrPolicy::Fullscreen => fullscreen,
+        VrrPolicy::Always => true,
+    };
+    if vrr && vrr_capable {
+        SyncMode::Vrr
+    } else {
+        SyncMode::Vsync
+    }
+}
+
+/// Flags of a `wp_presentation_feedback.presented` event.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PresentationFlags {
+    /// The frame was shown at a vblank.
+    pub vsync: bool,
+    /// The timestamp comes from the display hardware.
+    pub hw_clock: bool,
+    /// The hardware signalled when the frame was shown.
+    pub hw_completion: bool,
+    /// The client buffer was scanned out without copying.
+    pub zero_copy: bool,
+}
+
+impl PresentationFlags {
+    pub fn to_kind(self) -> wp_presentation_feedback::Kind {
+        let mut kind = wp_presentation_feedback::Kind::empty();
+        if self.vsync {
+            kind |= wp_presentation_feedback::Kind::Vsync;
+        }
+        if self.hw_clock {
+            kind |= wp_presentation_feedback::Kind::HwClock;
+        }
+        if self.hw_completion {
+            kind |= wp_presentation_feedback::Kind::HwCompletion;
+        }
+        if self.zero_copy {
+            kind |= wp_presentation_feedback::Kind::ZeroCopy;
+        }
+        kind
+    }
+}
+
+/// What to report to clients for a presented frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PresentationFeedbackInfo {
+    pub time: Duration,
+    /// Nominal refresh interval of the output.
+    pub refresh: Duration,
+    /// Set under VRR, when `refresh` is only the shortest possible interval.
+    pub variable_refresh: bool,
+    pub seq: u64,
+    pub flags: PresentationFlags,
+}
+
+/// A submitted frame whose presentation has not been reported yet.
+pub struct PendingPresentation {
+    feedback: OutputPresentationFeedback,
+    seq: u64,
+    /// Surfaces shown in the frame, which get frame callbacks once it is presented.
+    rendered: Vec<WlSurface>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FrameState {
+    Idle,
+    /// Rendering is due at `render_at`, aiming for presentation at `target`.
+    Scheduled { render_at: Duration, target: Duration },
+    /// A frame was submitted and the presentation event has not arrived yet.
+    Pending { target: Duration },
+}
+
+// ANCHOR: FrameScheduler
+/// Frame clock of a single output.
+#[derive(Debug)]
+pub struct FrameScheduler {
+    clock: Arc<dyn FrameClock>,
+    refresh: Duration,
+    sync_mode: SyncMode,
+    predictor: RenderTimePredictor,
+    state: FrameState,
+    /// A redraw was requested while a frame was pending.
+    redraw_queued: bool,
+    last_presentation: Option<Duration>,
+    missed_deadlines: u64,
+}
+
+impl FrameScheduler {
+    /// Creates a scheduler for an output refreshing every `refresh`.
+    pub fn new(clock: Arc<dyn FrameClock>, refresh: Duration) -> Self {
+        Self {
+            clock,
+            refresh: if refresh.is_zero() { FALLBACK_REFRESH } else { refresh },
+            sync_mode: SyncMode::Vsync,
+            predictor: RenderTimePredictor::default(),
+            state: FrameState::Idle,
+            redraw_queued: false,
+            last_presentation: None,
+            missed_deadlines: 0,
+        }
+    }
+
+    /// Creates a scheduler for `output` using its current mode.
+    pub fn for_output(clock: Arc<dyn FrameClock>, output: &Output) -> Self {
+        Self::new(clock, output.current_mode().map(|mode| refresh_interval(mode.refresh)).unwrap_or(FALLBACK_REFRESH))
+    }
+
+    pub fn now(&self) -> Duration {
+        self.clock.now()
+    }
+
+    pub fn refresh(&self) -> Duration {
+        self.refresh
+    }
+
+    /// Updates the refresh interval after a mode change.
+    pub fn set_refresh(&mut self, refresh: Duration) {
+        if !refresh.is_zero() {
+            self.refresh = refresh;
+        }
+    }
+
+    pub fn sync_mode(&self) -> SyncMode {
+        self.sync_mode
+    }
+
+    /// Changes the sync mode; a scheduled frame is rescheduled under the new mode.
+    pub fn set_sync_mode(&mut self, mode: SyncMode) {
+        if self.sync_mode != mode {
+            self.sync_mode = mode;
+            if let FrameState::Scheduled { .. } = self.state {
+                self.state = FrameState::Idle;
+                self.schedule();
+            }
+        }
+    }
+
+    /// Applies the render budget of the configuration.
+    pub fn configure(&mut self, config: &PerformanceConfig) {
+        self.predictor.set_fixed(config.max_render_time_ms.map(|ms| Duration::from_millis(ms.into())));
+    }
+
+    /// Number of frames presented later than the vblank they were aimed at.
+    pub fn missed_deadlines(&self) -> u64 {
+        self.missed_deadlines
+    }
+
+    /// Asks for a new frame, e.g. after a surface commit or a running animation.
+    pub fn request_redraw(&mut self) {
+        match self.state {
+            FrameState::Idle => self.schedule(),
+            FrameState::Scheduled { .. } => {}
+            FrameState::Pending { .. } => self.redraw_queued = true,
+        }
+    }
+
+    /// When rendering of the next frame should start, if one is due.
+    pub fn next_render_time(&self) -> Option<Duration> {
+        match self.state {
+            FrameState::Scheduled { render_at, .. } => Some(render_at),
+            _ => None,
+        }
+    }
+
+    /// Time until rendering should start, for arming a timer. `Some(ZERO)` means render now.
+    pub fn time_until_render(&self) -> Option<Duration> {
+        self.next_render_time().map(|at| at.saturating_sub(self.now()))
+    }
+
+    /// Whether the output should render now.
+    pub fn should_render(&self) -> bool {
+        self.next_render_time().is_some_and(|at| at <= self.now())
+    }
+
+    /// The vblank the submitted frame is aimed at, while its presentation is pending. Backends
+    /// without page-flip events report the frame as presented once this has passed.
+    pub fn presentation_target(&self) -> Option<Duration> {
+        match self.state {
+            FrameState::Pending { target } => Some(target),
+            _ => None,
+        }
+    }
+
+    /// Records that a frame rendering since `render_started` was submitted to the display.
+    pub fn frame_submitted(&mut self, render_started: Duration) {
+        let now = self.now();
+        self.predictor.record(now.saturating_sub(render_started));
+        let target = match self.state {
+            FrameState::Scheduled { target, .. } => target,
+            // Rendered outside the schedule (first frame, forced repaint): aim for the next vblank.
+            _ => self.next_vblank_at_or_after(now),
+        };
+        self.state = FrameState::Pending { target };
+    }
+
+    /// Records that the scheduled frame had nothing to draw, so no presentation will follow.
+    pub fn frame_skipped(&mut self) {
+        if let FrameState::Scheduled { .. } = self.state {
+            self.state = FrameState::Idle;
+        }
+    }
+
+    /// Records that the submitted frame reached the screen and returns the feedback for clients.
+    ///
+    /// `hw_time` is the timestamp of the page-flip event when the backend has one; otherwise
+    /// the current time is used. A redraw requested meanwhile is scheduled.
+    pub fn frame_presented(&mut self, hw_time: Option<Duration>, seq: u64, zero_copy: bool) -> PresentationFeedbackInfo {
+        let time = hw_time.unwrap_or_else(|| self.now());
+        if let FrameState::Pending { target } = self.state {
+            // Half a refresh of tolerance for timestamp jitter.
+            if self.sync_mode == SyncMode::Vsync && time > target + self.refresh / 2 {
+                self.missed_deadlines += 1;
+            }
+        }
+        self.last_presentation = Some(time);
+        self.state = FrameState::Idle;
+        if std::mem::take(&mut self.redraw_queued) {
+            self.schedule();
+        }
+        PresentationFeedbackInfo {
+            time,
+            refresh: self.refresh,
+            variable_refresh: self.sync_mode == SyncMode::Vrr,
+            seq,
+            flags: PresentationFlags {
+                vsync: self.sync_mode != SyncMode::Tearing,
+                hw_clock: hw_time.is_some(),
+                hw_completion: hw_time.is_some(),
+                zero_copy,
+            },
+        }
+    }
+
+    fn schedule(&mut self) {
+        let now = self.now();
+        let budget = self.predictor.predict();
+        let (render_at, target) = match self.sync_mode {
+            SyncMode::Tearing => (now, now + budget),
+            SyncMode::Vrr => {
+                // The display waits for us, but not longer than its maximum refresh rate allows.
+                let earliest = self.last_presentation.map_or(now, |last| last + self.refresh);
+                let target = earliest.max(now + budget);
+                (target - budget, target)
+            }
+            SyncMode::Vsync => {
+                let target = self.next_vblank_at_or_after(now + budget);
+                (target - budget, target)
+            }
+        };
+        self.state = FrameState::Scheduled { render_at, target };
+    }
+
+    /// First vblank at or after `t`, extrapolated from the last presentation. Vblanks that
+    /// already got a frame are skipped.
+    fn next_vblank_at_or_after(&self, t: Duration) -> Duration {
+        let Some(last) = self.last_presentation else {
+            return t;
+        };
+        let first = last + self.refresh;
+        if t <= first {
+            return first;
+        }
+        let periods = (t - first).as_nanos().div_ceil(self.refresh.as_nanos());
+        first + self.refresh * periods as u32
+    }
+}
+// ANCHOR_END: FrameScheduler
+
+/// Refresh interval of a mode refreshing at `refresh_mhz` millihertz.
+pub fn refresh_interval(refresh_mhz: i32) -> Duration {
+    if refresh_mhz <= 0 {
+        return FALLBACK_REFRESH;
+    }
+    Duration::from_nanos(1_000_000_000_000 / refresh_mhz as u64)
+}
+
+// ANCHOR: FrameCallbackThrottle
+/// Limits frame callbacks of surfaces that are not shown on any output.
+///
+/// Visible surfaces get a callback for every frame they are part of. Hidden ones (minimized, on
+/// another workspace, fully occluded) only get one every `hidden_interval`, so clients keep
+/// making progress without rendering frames nobody sees.
+#[derive(Debug, Clone)]
+pub struct FrameCallbackThrottle<K> {
+    hidden_interval: Duration,
+    last_sent: HashMap<K, Duration>,
+}
+
+impl<K: Clone + Eq + Hash> FrameCallbackThrottle<K> {
+    pub fn new(hidden_interval: Duration) -> Self {
+        Self { hidden_interval, last_sent: HashMap::new() }
+    }
+
+    /// Whether `key` should receive a frame callback at `now`; records it if so.
+    pub fn should_send(&mut self, key: &K, visible: bool, now: Duration) -> bool {
+        let due = visible || self.last_sent.get(key).map_or(true, |last| now.saturating_sub(*last) >= self.hidden_interval);
+        if due {
+            self.last_sent.insert(key.clone(), now);
+        }
+        due
+    }
+
+    /// Forgets surfaces for which `keep` returns `false`.
+    pub fn retain(&mut self, mut keep: impl FnMut(&K) -> bool) {
+        self.last_sent.retain(|key, _| keep(key));
+    }
+}
+
+impl<K: Clone + Eq + Hash> Default for FrameCallbackThrottle<K> {
+    fn default() -> Self {
+        Self::new(HIDDEN_FRAME_CALLBACK_INTERVAL)
+    }
+}
+// ANCHOR_END: FrameCallbackThrottle
+
+/// Whether the client asked for asynchronous (tearing) presentation of `surface`.
+pub fn surface_requests_tearing(surface: &WlSurface) -> bool {
+    with_states(surface, |states| {
+        let mut cached = states.cached_state.get::<TearingControlSurfaceCachedState>();
+        matches!(cached.current().presentation_hint(), wp_tearing_control_v1::PresentationHint::Async)
+    })
+}
+
+// ANCHOR: DesktopStateFrameScheduling
+impl DesktopState {
+    /// The frame scheduler of `output`, created from its current mode on first use.
+    pub fn frame_scheduler(&mut self, output: &Output) -> &mut FrameScheduler {
+        let performance = self.config.performance.clone();
+        self.frame_schedulers.entry(output.name()).or_insert_with(|| {
+            let mut scheduler = FrameScheduler::for_output(Arc::new(Clock::<Monotonic>::new()), output);
+            scheduler.configure(&performance);
+            scheduler
+        })
+    }
+
+    /// Requests a new frame on every output, e.g. after a configuration change.
+    pub fn schedule_redraw_all(&mut self) {
+        for scheduler in self.frame_schedulers.values_mut() {
+            scheduler.request_redraw();
+        }
+    }
+
+    /// Re-evaluates VRR and tearing for `output` from its fullscreen window, if any.
+    pub fn update_sync_mode(&mut self, output: &Output, vrr_capable: bool) -> SyncMode {
+        let output_name = output.name();
+        let fullscreen = self.windows.values().find(|window| {
+            window.output_name.read().unwrap().as_deref() == Some(output_name.as_str()) && window.state.read().unwrap().fullscreen
+        });
+        let tearing_requested = fullscreen.and_then(|window| window.wl_surface_ref()).is_some_and(surface_requests_tearing);
+        let mode = select_sync_mode(&self.config.performance, vrr_capable, fullscreen.is_some(), tearing_requested);
+        self.frame_scheduler(output).set_sync_mode(mode);
+        mode
+    }
+
+    /// Records that a frame of `output` showing `rendered` and rendering since
+    /// `render_started` was submitted. The surfaces' presentation feedback is taken now, so
+    /// commits arriving before the frame is shown are not reported with it.
+    pub fn frame_submitted(&mut self, output: &Output, render_started: Duration, seq: u64, rendered: Vec<WlSurface>) {
+        // A frame submitted before the previous one was reported replaced it on screen.
+        if self.pending_presentations.contains_key(&output.name()) {
+            self.frame_presented(output, None, false);
+        }
+        self.frame_scheduler(output).frame_submitted(render_started);
+        let mut feedback = OutputPresentationFeedback::new(output);
+        for surface in &rendered {
+            take_presentation_feedback_surface_tree(surface, &mut feedback, |_, _| Some(output.clone()), |_, _| wp_presentation_feedback::Kind::empty());
+        }
+        self.pending_presentations.insert(output.name(), PendingPresentation { feedback, seq, rendered });
+    }
+
+    /// Records that the frame submitted for `output` reached the screen at `hw_time`, the
+    /// page-flip timestamp if the backend has one, and sends its `wp_presentation` feedback and
+    /// frame callbacks.
+    pub fn frame_presented(&mut self, output: &Output, hw_time: Option<Duration>, zero_copy: bool) {
+        let Some(mut pending) = self.pending_presentations.remove(&output.name()) else {
+            return;
+        };
+        let info = self.frame_scheduler(output).frame_presented(hw_time, pending.seq, zero_copy);
+        let refresh = if info.variable_refresh { Refresh::Variable(info.refresh) } else { Refresh::Fixed(info.refresh) };
+        pending.feedback.presented(Time::<Monotonic>::from(info.time), refresh, info.seq, info.flags.to_kind());
+        self.send_frame_callbacks(output, &pending.rendered);
+    }
+
+    /// Sends frame callbacks after a frame of `output` was presented: to every surface in
+    /// `rendered`, and at a reduced rate to mapped windows that are not shown anywhere.
+    pub fn send_frame_callbacks(&mut self, output: &Output, rendered: &[WlSurface]) {
+        let time = self.clock.now();
+        let now = Duration::from(time);
+        for surface in rendered {
+            self.frame_callback_throttle.should_send(&surface.id(), true, now);
+            surface.send_frame_done(time);
+        }
+
+        let space = self.space.lock().unwrap();
+        let shown: Vec<WlSurface> = space.outputs().flat_map(|o| space.elements_for_output(o)).filter_map(|w| w.wl_surface_ref().cloned()).collect();
+        drop(space);
+        for window in self.windows.values() {
+            let Some(surface) = window.wl_surface_ref() else { continue };
+            if rendered.contains(surface) || shown.contains(surface) {
+                continue;
+            }
+            if self.frame_callback_throttle.should_send(&surface.id(), false, now) {
+                surface.send_frame_done(time);
+            }
+        }
+        let live: Vec<_> = self.windows.values().filter_map(|w| w.wl_surface_ref().map(|s| s.id())).collect();
+        self.frame_callback_throttle.retain(|id| live.contains(id));
+        tracing::trace!("Sent frame callbacks for output {}.", output.name());
+    }
+}
+// ANCHOR_END: DesktopStateFrameScheduling
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REFRESH: Duration = Duration::from_nanos(16_666_667);
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    fn scheduler(start: Duration) -> (SimulatedClock, FrameScheduler) {
+        let clock = SimulatedClock::new(start);
+        let scheduler = FrameScheduler::new(Arc::new(clock.clone()), REFRESH);
+        (clock, scheduler)
+    }
+
+    /// Renders a frame taking `render_time` once it is due and presents it at `present_at`, or
+    /// right after submission when there is no hardware timestamp.
+    fn run_frame(clock: &SimulatedClock, scheduler: &mut FrameScheduler, render_time: Duration, present_at: Option<Duration>) -> PresentationFeedbackInfo {
+        let render_at = scheduler.next_render_time().expect("frame scheduled");
+        clock.set(render_at.max(clock.now()));
+        assert!(scheduler.should_render());
+        let started = clock.now();
+        clock.advance(render_time);
+        scheduler.frame_submitted(started);
+        if let Some(at) = present_at {
+            clock.set(at);
+        }
+        scheduler.frame_presented(present_at, 1, false)
+    }
+
+    #[test]
+    fn test_vsync_renders_as_late_as_possible() {
+        let (clock, mut scheduler) = scheduler(ms(100));
+        scheduler.request_redraw();
+        // No vblank known yet: render immediately.
+        assert_eq!(scheduler.next_render_time(), Some(ms(100)));
+        let vblank = ms(110);
+        run_frame(&clock, &mut scheduler, ms(3), Some(vblank));
+
+        clock.set(vblank + ms(1));
+        scheduler.request_redraw();
+        // Slowest frame so far took 3ms, plus the 1ms margin.
+        let budget = ms(4);
+        assert_eq!(scheduler.next_render_time(), Some(vblank + REFRESH - budget));
+        assert!(!scheduler.should_render());
+        assert_eq!(scheduler.time_until_render(), Some(REFRESH - budget - ms(1)));
+    }
+
+    #[test]
+    fn test_vsync_skips_vblank_when_too_late() {
+        let (clock, mut scheduler) = scheduler(ms(0));
+        scheduler.request_redraw();
+        run_frame(&clock, &mut scheduler, ms(2), Some(ms(10)));
+
+        // Only 1ms left before the next vblank: aim for the one after.
+        clock.set(ms(10) + REFRESH - ms(1));
+        scheduler.request_redraw();
+        assert_eq!(scheduler.next_render_time(), Some(ms(10) + REFRESH * 2 - ms(3)));
+    }
+
+    #[test]
+    fn test_presentation_target_is_the_aimed_vblank() {
+        let (clock, mut scheduler) = scheduler(ms(0));
+        scheduler.request_redraw();
+        run_frame(&clock, &mut scheduler, ms(2), Some(ms(10)));
+        assert_eq!(scheduler.presentation_target(), None);
+
+        clock.set(ms(12));
+        scheduler.request_redraw();
+        assert_eq!(scheduler.presentation_target(), None);
+        clock.set(scheduler.next_render_time().unwrap());
+        scheduler.frame_submitted(clock.now());
+        assert_eq!(scheduler.presentation_target(), Some(ms(10) + REFRESH));
+
+        // Without page-flip events the frame is presented once that vblank passed.
+        clock.set(ms(10) + REFRESH);
+        let feedback = scheduler.frame_presented(None, 2, false);
+        assert_eq!(feedback.time, ms(10) + REFRESH);
+        assert!(!feedback.flags.hw_clock);
+        assert_eq!(scheduler.presentation_target(), None);
+        assert_eq!(scheduler.missed_deadlines(), 0);
+    }
+
+    #[test]
+    fn test_redraw_while_pending_waits_for_presentation() {
+        let (clock, mut scheduler) = scheduler(ms(0));
+        scheduler.request_redraw();
+        let render_at = scheduler.next_render_time().unwrap();
+        clock.set(render_at);
+        scheduler.frame_submitted(render_at);
+        scheduler.request_redraw();
+        assert_eq!(scheduler.next_render_time(), None);
+
+        let feedback = scheduler.frame_presented(Some(ms(16)), 7, true);
+        assert_eq!(feedback.seq, 7);
+        assert_eq!(feedback.flags, PresentationFlags { vsync: true, hw_clock: true, hw_completion: true, zero_copy: true });
+        assert!(!feedback.variable_refresh);
+        assert!(scheduler.next_render_time().is_some());
+    }
+
+    #[test]
+    fn test_prediction_tracks_slow_frames_and_config() {
+        let mut predictor = RenderTimePredictor::default();
+        assert_eq!(predictor.predict(), DEFAULT_RENDER_TIME + RENDER_SAFETY_MARGIN);
+        predictor.record(ms(2));
+        predictor.record(ms(9));
+        predictor.record(ms(3));
+        assert_eq!(predictor.predict(), ms(10));
+        for _ in 0..RENDER_TIME_SAMPLES {
+            predictor.record(ms(2));
+        }
+        assert_eq!(predictor.predict(), ms(3));
+        predictor.set_fixed(Some(ms(6)));
+        assert_eq!(predictor.predict(), ms(6));
+    }
+
+    #[test]
+    fn test_missed_deadline_is_counted() {
+        let (clock, mut scheduler) = scheduler(ms(0));
+        scheduler.request_redraw();
+        run_frame(&clock, &mut scheduler, ms(2), Some(ms(10)));
+        scheduler.request_redraw();
+        let target = ms(10) + REFRESH;
+        run_frame(&clock, &mut scheduler, ms(2), Some(target));
+        assert_eq!(scheduler.missed_deadlines(), 0);
+        scheduler.request_redraw();
+        run_frame(&clock, &mut scheduler, ms(30), Some(target + REFRESH * 3));
+        assert_eq!(scheduler.missed_deadlines(), 1);
+    }
+
+    #[test]
+    fn test_vrr_renders_immediately_within_max_rate() {
+        let (clock, mut scheduler) = scheduler(ms(0));
+        scheduler.set_sync_mode(SyncMode::Vrr);
+        scheduler.request_redraw();
+        let feedback = run_frame(&clock, &mut scheduler, ms(2), Some(ms(10)));
+        assert!(feedback.variable_refresh);
+
+        // Content changes well after the minimum interval: no waiting for a vblank grid.
+        clock.set(ms(40));
+        scheduler.request_redraw();
+        assert!(scheduler.should_render());
+
+        // Too soon after the last frame: held back to the maximum refresh rate.
+        run_frame(&clock, &mut scheduler, ms(2), Some(ms(43)));
+        clock.set(ms(44));
+        scheduler.request_redraw();
+        assert_eq!(scheduler.next_render_time(), Some(ms(43) + REFRESH - ms(3)));
+    }
+
+    #[test]
+    fn test_tearing_flips_immediately_without_vsync_flag() {
+        let (clock, mut scheduler) = scheduler(ms(0));
+        scheduler.request_redraw();
+        run_frame(&clock, &mut scheduler, ms(2), Some(ms(10)));
+        scheduler.set_sync_mode(SyncMode::Tearing);
+        clock.set(ms(11));
+        scheduler.request_redraw();
+        assert_eq!(scheduler.next_render_time(), Some(ms(11)));
+        let feedback = run_frame(&clock, &mut scheduler, ms(2), None);
+        assert!(!feedback.flags.vsync);
+        assert!(!feedback.flags.hw_clock);
+        assert_eq!(feedback.time, ms(13));
+    }
+
+    #[test]
+    fn test_select_sync_mode() {
+        let config = PerformanceConfig::default();
+        assert_eq!(select_sync_mode(&config, true, false, false), SyncMode::Vsync);
+        assert_eq!(select_sync_mode(&config, true, true, false), SyncMode::Vrr);
+        assert_eq!(select_sync_mode(&config, false, true, false), SyncMode::Vsync);
+        assert_eq!(select_sync_mode(&config, true, true, true), SyncMode::Tearing);
+        assert_eq!(select_sync_mode(&config, true, false, true), SyncMode::Vsync);
+
+        let config = PerformanceConfig { vrr: VrrPolicy::Always, tearing: TearingPolicy::Never, ..Default::default() };
+        assert_eq!(select_sync_mode(&config, true, false, false), SyncMode::Vrr);
+        assert_eq!(select_sync_mode(&config, true, true, true), SyncMode::Vrr);
+    }
+
+    #[test]
+    fn test_hidden_surfaces_are_throttled() {
+        let mut throttle = FrameCallbackThrottle::new(ms(1000));
+        assert!(throttle.should_send(&1u32, false, ms(0)));
+        assert!(!throttle.should_send(&1, false, ms(500)));
+        assert!(throttle.should_send(&1, true, ms(516)));
+        assert!(!throttle.should_send(&1, false, ms(1000)));
+        assert!(throttle.should_send(&1, false, ms(1516)));
+
+        throttle.retain(|_| false);
+        assert!(throttle.should_send(&1, false, ms(1600)));
+    }
+
+    #[test]
+    fn test_refresh_interval() {
+        assert_eq!(refresh_interval(60_000), Duration::from_nanos(16_666_666));
+        assert_eq!(refresh_interval(144_000), Duration::from_nanos(6_944_444));
+        assert_eq!(refresh_interval(0), FALLBACK_REFRESH);
+    }
+}
@@ -17,6 +17,7 @@ use smithay::{
     delegate_wlr_layer_shell,
     delegate_idle_notifier,
     delegate_single_pixel_buffer_manager,
+    delegate_tearing_control,
 
     reexports::{
         wayland_server::{
@@ -208,6 +209,7 @@ delegate_xdg_activation!(DesktopState);
 // --- Other Protocol Handlers (delegated or with custom logic) ---
 delegate_presentation_time!(DesktopState);
 delegate_single_pixel_buffer_manager!(DesktopState);
+delegate_tearing_control!(DesktopState);
 delegate_relative_pointer_manager!(DesktopState);
 delegate_pointer_constraints!(DesktopState); // Will use PointerConstraintsHandler on DesktopState
 delegate_input_method_manager!(DesktopState); // Will use InputMethod* traits on DesktopState
@@ -489,6 +491,11 @@ impl CompositorHandler for DesktopState {
     }
     fn commit(&mut self, surface: &wl_surface::WlSurface) {
         smithay::wayland::compositor::handlers::commit_handler::<DesktopState>(surface); // Smithay's main commit processing
+        // Tracks buffer size, scale, transform and damage for the surface's render elements.
+        smithay::backend::renderer::utils::on_commit_buffer_handler::<DesktopState>(surface);
+        self.record_surface_damage(surface);
+        // Outputs only repaint what the commit damaged.
+        self.schedule_redraw_all();
 
         // Custom post-commit logic for different roles
         if let Some(window) = self.space.lock().unwrap().window_for_surface(surface).cloned() {
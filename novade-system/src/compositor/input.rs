@@ -45,6 +45,8 @@ use xkbcommon::xkb; // For keysym definitions
 
 use crate::compositor::state::{DesktopState, NovaSeatState}; // Assuming NovaSeatState wraps SmithaySeatState
 use crate::compositor::errors::CompositorError;
+use crate::compositor::keybindings::KeybindingAction;
+use crate::compositor::overview;
 
 
 // --- Input Event Processing ---
@@ -54,7 +56,9 @@ pub fn process_input_event<B: backend_input::InputBackend>(
     state: &mut DesktopState,
     event: BackendInputEvent<B>,
     // output_name: &str, // Name of the output where the event originated, for coordinate transformation
-) {
+) where
+    B::Device: 'static,
+{
     let serial = SERIAL_COUNTER.next_serial();
     let time = state.clock.now().as_millis() as u32; // Use DesktopState's clock
 
@@ -63,17 +67,31 @@ pub fn process_input_event<B: backend_input::InputBackend>(
     match event {
         BackendInputEvent::Keyboard { event, .. } => {
             if let Some(keyboard) = seat.get_keyboard() {
+                let key_state = event.state();
                 keyboard.input(
                     state, // &mut DesktopState which implements SeatHandler
                     event.key_code(),
-                    event.state(),
+                    key_state,
                     serial,
                     time,
                     |d_state, modifiers, handle| {
                         // This is the key filter callback.
                         // `handle` is a KeyboardHandle. `modifiers` is the current ModifiersState.
+                        // While the overview accepts input, it consumes all keys.
+                        if d_state.overview.accepts_input() {
+                            if key_state == backend_input::KeyState::Pressed {
+                                d_state.handle_overview_key(*modifiers, handle.modified_sym());
+                            }
+                            return XkbFilterResult::HandledByCompositor;
+                        }
                         // Check for compositor keybindings first.
-                        if d_state.handle_compositor_keybinding(modifiers, handle.modified_sym()) {
+                        // Shifted bindings like Super+Shift+1 are matched against the unshifted keysym too.
+                        if key_state == backend_input::KeyState::Pressed
+                            && (d_state.handle_compositor_keybinding(*modifiers, handle.modified_sym())
+                                || handle.raw_latin_sym_or_raw_current_sym().map_or(false, |raw| {
+                                    raw != handle.modified_sym() && d_state.handle_compositor_keybinding(*modifiers, raw)
+                                }))
+                        {
                             return XkbFilterResult::HandledByCompositor;
                         }
                         // If not handled, let it pass to the client.
@@ -98,7 +116,14 @@ pub fn process_input_event<B: backend_input::InputBackend>(
                 // let scale = output_under_pointer.map_or(Scale::from(1.0), |o| o.current_scale());
                 // state.pointer_location += delta.to_f64().to_logical(scale);
 
+                if state.overview.accepts_input() {
+                    let action = state.overview.pointer_motion(state.pointer_location);
+                    state.apply_overview_action(action);
+                    return;
+                }
+
                 pointer.motion(state, state.pointer_location, serial, time);
+                state.focus_window_under_pointer();
             }
         }
         BackendInputEvent::PointerMotionAbsolute { event, .. } => {
@@ -127,10 +152,24 @@ pub fn process_input_event<B: backend_input::InputBackend>(
                     });
 
                 state.pointer_location = new_logical_pos;
+                if state.overview.accepts_input() {
+                    let action = state.overview.pointer_motion(state.pointer_location);
+                    state.apply_overview_action(action);
+                    return;
+                }
                 pointer.motion(state, state.pointer_location, serial, time);
+                state.focus_window_under_pointer();
             }
         }
         BackendInputEvent::PointerButton { event, .. } => {
+            if state.overview.accepts_input() {
+                let action = match event.state() {
+                    backend_input::ButtonState::Pressed => state.overview.pointer_pressed(state.pointer_location),
+                    backend_input::ButtonState::Released => state.overview.pointer_released(state.pointer_location),
+                };
+                state.apply_overview_action(action);
+                return;
+            }
             if let Some(pointer) = seat.get_pointer() {
                 pointer.button(state, event.button_code(), event.state(), serial, time);
             }
@@ -194,11 +233,19 @@ pub fn process_input_event<B: backend_input::InputBackend>(
         }
         BackendInputEvent::DeviceAdded { device } => {
             info!("Input device added: {} (Backend notified)", device.name());
+            let mut device = device;
+            if let Some(libinput_device) = (&mut device as &mut dyn std::any::Any).downcast_mut::<smithay::reexports::input::Device>() {
+                crate::compositor::config::devices::configure_libinput_device(libinput_device, &state.config.input);
+                state.libinput_devices.push(libinput_device.clone());
+            }
             // Backend (e.g. UdevBackend) usually handles adding device to Seat.
             // If manual association is needed: state.primary_seat.add_device(&device);
         }
         BackendInputEvent::DeviceRemoved { device } => {
             info!("Input device removed: {} (Backend notified)", device.name());
+            if let Some(libinput_device) = (&device as &dyn std::any::Any).downcast_ref::<smithay::reexports::input::Device>() {
+                state.libinput_devices.retain(|d| d != libinput_device);
+            }
             // Backend usually handles removing device from Seat.
             // If manual: state.primary_seat.remove_device(&device);
         }
@@ -210,44 +257,55 @@ pub fn process_input_event<B: backend_input::InputBackend>(
 }
 
 impl DesktopState {
+    /// Focuses the window under the pointer when `input.focus_follows_mouse` is enabled.
+    fn focus_window_under_pointer(&mut self) {
+        if !self.config.input.focus_follows_mouse || self.active_move_grab.is_some() || self.active_resize_grab.is_some() {
+            return;
+        }
+        let under = self
+            .space
+            .lock()
+            .unwrap()
+            .element_under(self.pointer_location)
+            .map(|(window, _)| window.domain_id);
+        if let Some(window_id) = under {
+            if self.focused_domain_window_id() != Some(window_id) {
+                self.focus_domain_window(window_id);
+            }
+        }
+    }
+
     /// Handles compositor-level keybindings.
     /// Returns `true` if the keybinding was handled, `false` otherwise.
     fn handle_compositor_keybinding(&mut self, modifiers: ModifiersState, keysym: Keysym) -> bool {
-        // Example: Alt + F4 to close focused window
-        if modifiers.alt && keysym == xkb::KEY_F4 {
-            info!("Alt+F4 pressed. Attempting to close focused window.");
-            // Find focused window (XDG Toplevel or XWayland) and send close request.
-            // This requires access to the focused window, e.g., via seat.keyboard_focus_element().
-            if let Some(keyboard) = self.primary_seat.get_keyboard() {
-                if let Some(focused_surface) = keyboard.focused_surface() {
-                     if let Some(window) = self.space.lock().unwrap().window_for_surface(&focused_surface).cloned() {
-                        match window.toplevel() {
-                            Some(WindowSurfaceType::Xdg(xdg_toplevel)) => {
-                                xdg_toplevel.send_close();
-                                return true;
-                            }
-                            Some(WindowSurfaceType::X11(x11_surface)) => {
-                                x11_surface.close().ok(); // Attempt to close X11 window
-                                return true;
-                            }
-                            _ => {}
-                        }
-                    }
-                }
-            }
+        let action = match self.keybinding_action_for(&modifiers, keysym) {
+            Some(action) => action,
+            None => return false, // Not handled by compositor
+        };
+        if let Err(e) = self.run_action(action) {
+            debug!("Keybinding action not applied: {}", e);
         }
-        // Add more keybindings here
-        // Example: Ctrl + Alt + T to launch terminal
-        // if modifiers.ctrl && modifiers.alt && keysym == xkb::KEY_T {
-        //     info!("Ctrl+Alt+T pressed. Launching terminal (TODO).");
-        //     // Command::new("foot").spawn().ok(); // Example, needs error handling
-        //     return true;
-        // }
-        false // Not handled by compositor
+        true
     }
 }
 
 
+impl DesktopState {
+    /// Forwards a key press to the overview while it accepts input.
+    fn handle_overview_key(&mut self, modifiers: ModifiersState, keysym: Keysym) {
+        // Whatever key opens the overview also closes it.
+        let toggles = self.keybinding_action_for(&modifiers, keysym) == Some(KeybindingAction::ToggleOverview);
+        if toggles || overview::is_overview_toggle(modifiers, keysym) {
+            self.toggle_overview();
+            return;
+        }
+        if let Some(key) = overview::overview_key_from_keysym(keysym, keysym.key_char()) {
+            let action = self.overview.handle_key(key);
+            self.apply_overview_action(action);
+        }
+    }
+}
+
 // Helper to map backend AxisSource to Smithay's AxisSource
 fn map_backend_axis_source(backend_source: BackendAxisSource) -> smithay::input::pointer::AxisSource {
     match backend_source {
@@ -0,0 +1,465 @@
+// novade-system/src/compositor/ipc/mod.rs
+//! JSON-over-Unix-socket IPC for scripting the compositor (see [`protocol`] for the wire format).
+//!
+//! The socket is served by a tokio runtime on its own thread. Requests that need compositor
+//! state are forwarded to the compositor thread through a calloop channel and answered there
+//! by [`DesktopState::handle_ipc_request`]; events are published on a broadcast channel that
+//! every subscribed connection reads from.
+
+pub mod protocol;
+pub mod server;
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use smithay::reexports::calloop::{
+    channel::{self, Event as ChannelEvent, Sender},
+    LoopHandle,
+};
+use tokio::sync::{broadcast, oneshot};
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use novade_domain::WorkspaceManagerService;
+
+use self::protocol::{
+    CompositorMode, IpcEvent, IpcRequest, IpcResponse, IpcWindow, OutputInfo, VersionInfo, WindowChange,
+    WorkspaceChange, WorkspaceSummary,
+};
+use self::server::IpcRequestHandler;
+use crate::compositor::errors::CompositorError;
+use crate::compositor::shell::xdg_shell::types::{DomainWindowIdentifier, ManagedWindow};
+use crate::compositor::state::DesktopState;
+use crate::compositor::workspaces::TilingLayout;
+use crate::window_mechanics::data_types::{WindowId, WindowInfo, WindowRect, WindowState};
+
+/// Capacity of the event broadcast channel; slower subscribers skip older events.
+const IPC_EVENT_BUFFER: usize = 256;
+
+/// A request forwarded to the compositor thread.
+#[derive(Debug)]
+pub struct IpcCommand {
+    pub request: IpcRequest,
+    pub reply: oneshot::Sender<IpcResponse>,
+}
+
+/// Forwards requests to the compositor thread and enriches workspace replies with the domain
+/// workspace metadata (names, icons, accent colors) when a [`WorkspaceManagerService`] is
+/// available.
+pub struct CompositorIpcHandler {
+    commands: Sender<IpcCommand>,
+    workspace_service: Option<Arc<dyn WorkspaceManagerService>>,
+}
+
+impl CompositorIpcHandler {
+    async fn forward(&self, request: IpcRequest) -> IpcResponse {
+        let (reply, response) = oneshot::channel();
+        if self.commands.send(IpcCommand { request, reply }).is_err() {
+            return IpcResponse::error("compositor is shutting down");
+        }
+        response.await.unwrap_or_else(|_| IpcResponse::error("compositor dropped the request"))
+    }
+}
+
+#[async_trait]
+impl IpcRequestHandler for CompositorIpcHandler {
+    async fn handle(&self, request: IpcRequest) -> IpcResponse {
+        let is_workspace_query = request == IpcRequest::GetWorkspaces;
+        let response = self.forward(request).await;
+        match (&self.workspace_service, is_workspace_query) {
+            (Some(service), true) => match response.into_data::<Vec<WorkspaceSummary>>() {
+                Ok(mut summaries) => {
+                    merge_domain_workspaces(&mut summaries, service.as_ref());
+                    IpcResponse::with_data(&summaries)
+                }
+                Err(e) => IpcResponse::error(e),
+            },
+            _ => response,
+        }
+    }
+}
+
+/// Copies domain metadata onto matching compositor workspaces and appends domain workspaces
+/// that are not placed on any output.
+fn merge_domain_workspaces(summaries: &mut Vec<WorkspaceSummary>, service: &dyn WorkspaceManagerService) {
+    let active_domain = service.active_workspace_id();
+    for workspace in service.all_workspaces_ordered() {
+        let id = workspace.id();
+        match summaries.iter_mut().find(|s| s.id == id) {
+            Some(summary) => {
+                summary.name = workspace.name().to_string();
+                summary.persistent_id = workspace.persistent_id().map(str::to_string);
+                summary.icon_name = workspace.icon_name().map(str::to_string);
+                summary.accent_color_hex = workspace.accent_color_hex().map(str::to_string);
+            }
+            None => summaries.push(WorkspaceSummary {
+                id,
+                name: workspace.name().to_string(),
+                output: None,
+                index: None,
+                active: active_domain == Some(id),
+                focused: false,
+                layout: format!("{:?}", workspace.layout_type()).to_lowercase(),
+                windows: Vec::new(),
+                persistent_id: workspace.persistent_id().map(str::to_string),
+                icon_name: workspace.icon_name().map(str::to_string),
+                accent_color_hex: workspace.accent_color_hex().map(str::to_string),
+            }),
+        }
+    }
+}
+
+/// The compositor's handle on the running IPC server.
+#[derive(Debug)]
+pub struct IpcState {
+    pub socket_path: PathBuf,
+    events: broadcast::Sender<IpcEvent>,
+}
+
+impl IpcState {
+    /// Publishes an event to all subscribers. Dropped silently if nobody is subscribed.
+    pub fn emit(&self, event: IpcEvent) {
+        let _ = self.events.send(event);
+    }
+}
+
+impl Drop for IpcState {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.socket_path);
+    }
+}
+
+/// Binds the IPC socket at `socket_path` and starts serving it.
+///
+/// Domain workspace events from `workspace_service` are forwarded to subscribers as
+/// `workspace` events with `change: "domain"`.
+pub fn start_ipc_server(
+    loop_handle: &LoopHandle<'static, DesktopState>,
+    socket_path: PathBuf,
+    workspace_service: Option<Arc<dyn WorkspaceManagerService>>,
+) -> Result<IpcState, CompositorError> {
+    let listener = server::bind_socket(&socket_path)
+        .map_err(|e| CompositorError::Internal(format!("Failed to bind IPC socket {}: {}", socket_path.display(), e)))?;
+
+    let (commands, channel) = channel::channel::<IpcCommand>();
+    loop_handle
+        .insert_source(channel, |event, _, state: &mut DesktopState| {
+            if let ChannelEvent::Msg(command) = event {
+                let response = state.handle_ipc_request(command.request);
+                let _ = command.reply.send(response);
+            }
+        })
+        .map_err(|e| CompositorError::Internal(format!("Failed to register IPC command source: {}", e)))?;
+
+    let (events, _) = broadcast::channel(IPC_EVENT_BUFFER);
+    let server_events = events.clone();
+    let handler = Arc::new(CompositorIpcHandler { commands, workspace_service: workspace_service.clone() });
+
+    std::thread::Builder::new()
+        .name("novade-ipc".into())
+        .spawn(move || {
+            let runtime = match tokio::runtime::Builder::new_current_thread().enable_all().build() {
+                Ok(rt) => rt,
+                Err(e) => {
+                    error!("Failed to create IPC runtime: {}", e);
+                    return;
+                }
+            };
+            runtime.block_on(async move {
+                if let Some(service) = workspace_service {
+                    let domain_events = server_events.clone();
+                    let mut receiver = service.subscribe_to_workspace_events();
+                    tokio::spawn(async move {
+                        loop {
+                            match receiver.recv().await {
+                                Ok(domain_event) => {
+                                    let _ = domain_events.send(IpcEvent::Workspace {
+                                        change: WorkspaceChange::Domain,
+                                        id: None,
+                                        output: None,
+                                        domain_event: Some(domain_event),
+                                    });
+                                }
+                                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                                Err(broadcast::error::RecvError::Closed) => break,
+                            }
+                        }
+                    });
+                }
+                match tokio::net::UnixListener::from_std(listener) {
+                    Ok(listener) => server::serve(listener, handler, server_events).await,
+                    Err(e) => error!("Failed to register IPC socket with tokio: {}", e),
+                }
+            });
+        })
+        .map_err(|e| CompositorError::Internal(format!("Failed to spawn IPC thread: {}", e)))?;
+
+    info!("Compositor IPC listening on {}", socket_path.display());
+    Ok(IpcState { socket_path, events })
+}
+
+// ANCHOR: DesktopStateIpcIntegration
+impl DesktopState {
+    /// Answers an IPC request on the compositor thread.
+    pub fn handle_ipc_request(&mut self, request: IpcRequest) -> IpcResponse {
+        match request {
+            IpcRequest::GetVersion => IpcResponse::with_data(&VersionInfo::current()),
+            IpcRequest::GetOutputs => IpcResponse::with_data(&self.ipc_outputs()),
+            IpcRequest::GetWorkspaces => IpcResponse::with_data(&self.ipc_workspaces()),
+            IpcRequest::GetWindows => IpcResponse::with_data(&self.ipc_windows()),
+            IpcRequest::GetNightLight => IpcResponse::with_data(&self.night_light_status()),
+            IpcRequest::GetRecording => IpcResponse::with_data(&self.recording_status()),
+            IpcRequest::RunAction { action } => match self.run_action(action) {
+                Ok(()) => IpcResponse::ok(),
+                Err(e) => IpcResponse::error(e),
+            },
+            IpcRequest::ReloadConfig => match self.reload_config() {
+                Ok(()) => IpcResponse::ok(),
+                Err(e) => IpcResponse::error(e.to_string()),
+            },
+            IpcRequest::SetThemeTokens { tokens } => {
+                self.apply_theme_tokens(tokens);
+                IpcResponse::ok()
+            }
+            // Handled by the socket server; only reaches here if sent through another path.
+            IpcRequest::Subscribe { .. } => IpcResponse::error("subscribe is only valid on the IPC socket"),
+        }
+    }
+
+    /// Publishes an IPC event if the IPC server is running.
+    pub fn emit_ipc_event(&self, event: IpcEvent) {
+        if let Some(ipc) = &self.ipc {
+            ipc.emit(event);
+        }
+    }
+
+    /// Publishes a window event carrying the window's current state.
+    pub fn emit_window_event(&self, change: WindowChange, window_id: DomainWindowIdentifier) {
+        if self.ipc.is_none() {
+            return;
+        }
+        let window = if change == WindowChange::Close { None } else { self.ipc_window(window_id) };
+        self.emit_ipc_event(IpcEvent::Window { change, id: WindowId::from_uuid(window_id.as_uuid()), window });
+    }
+
+    /// Publishes a workspace focus event for `output_name`.
+    pub fn emit_workspace_focus_event(&self, output_name: &str, workspace_id: Uuid) {
+        self.emit_ipc_event(IpcEvent::Workspace {
+            change: WorkspaceChange::Focus,
+            id: Some(workspace_id),
+            output: Some(output_name.to_string()),
+            domain_event: None,
+        });
+    }
+
+    /// Publishes the current interaction mode.
+    pub fn emit_mode_event(&self) {
+        let mode = if self.overview.accepts_input() { CompositorMode::Overview } else { CompositorMode::Normal };
+        self.emit_ipc_event(IpcEvent::Mode { mode });
+    }
+
+    pub fn ipc_outputs(&self) -> Vec<OutputInfo> {
+        let primary = self.primary_output_name.read().unwrap().clone();
+        let focused = self.current_output_name();
+        let active = self.active_workspaces.read().unwrap().clone();
+        let space = self.space.lock().unwrap();
+        space
+            .outputs()
+            .map(|output| {
+                let name = output.name();
+                let geometry = space.output_geometry(output).unwrap_or_default();
+                let properties = output.physical_properties();
+                OutputInfo {
+                    description: output.description(),
+                    make: properties.make,
+                    model: properties.model,
+                    x: geometry.loc.x,
+                    y: geometry.loc.y,
+                    width: geometry.size.w,
+                    height: geometry.size.h,
+                    refresh_mhz: output.current_mode().map_or(0, |mode| mode.refresh),
+                    scale: output.current_scale().fractional_scale(),
+                    primary: primary.as_deref() == Some(name.as_str()),
+                    focused: focused.as_deref() == Some(name.as_str()),
+                    active_workspace: active.get(&name).copied(),
+                    name,
+                }
+            })
+            .collect()
+    }
+
+    pub fn ipc_workspaces(&self) -> Vec<WorkspaceSummary> {
+        let focused_output = self.current_output_name();
+        let active = self.active_workspaces.read().unwrap().clone();
+        let mut output_names: Vec<&String> = self.output_workspaces.keys().collect();
+        output_names.sort();
+
+        let mut summaries = Vec::new();
+        for output_name in output_names {
+            for (position, workspace) in self.output_workspaces[output_name].iter().enumerate() {
+                let workspace = workspace.read().unwrap();
+                let is_active = active.get(output_name) == Some(&workspace.id);
+                let layout = match *workspace.tiling_layout.read().unwrap() {
+                    TilingLayout::None => "floating",
+                    TilingLayout::MasterStack => "master_stack",
+                };
+                summaries.push(WorkspaceSummary {
+                    id: workspace.id,
+                    name: workspace.name.clone(),
+                    output: Some(output_name.clone()),
+                    index: Some(position + 1),
+                    active: is_active,
+                    focused: is_active && focused_output.as_deref() == Some(output_name.as_str()),
+                    layout: layout.to_string(),
+                    windows: workspace.window_ids().into_iter().map(|id| WindowId::from_uuid(id.as_uuid())).collect(),
+                    persistent_id: None,
+                    icon_name: None,
+                    accent_color_hex: None,
+                });
+            }
+        }
+        summaries
+    }
+
+    pub fn ipc_windows(&self) -> Vec<IpcWindow> {
+        let focused = self.focused_domain_window_id();
+        let mut windows: Vec<IpcWindow> = self
+            .windows
+            .values()
+            .filter(|w| matches!(w.xdg_surface, smithay::wayland::shell::xdg::WindowSurface::Toplevel(_)))
+            .map(|w| self.describe_window(w, focused))
+            .collect();
+        windows.sort_by(|a, b| a.info.id.cmp(&b.info.id));
+        windows
+    }
+
+    pub fn ipc_window(&self, window_id: DomainWindowIdentifier) -> Option<IpcWindow> {
+        let focused = self.focused_domain_window_id();
+        self.windows.get(&window_id).map(|w| self.describe_window(w, focused))
+    }
+
+    fn describe_window(&self, window: &ManagedWindow, focused: Option<DomainWindowIdentifier>) -> IpcWindow {
+        let geometry = *window.current_geometry.read().unwrap();
+        let workspace_id = *window.workspace_id.read().unwrap();
+        let output = window.output_name.read().unwrap().clone();
+        let state = window.state.read().unwrap();
+        let window_state = if state.fullscreen {
+            WindowState::Fullscreen
+        } else if state.maximized {
+            WindowState::Maximized
+        } else if state.minimized {
+            WindowState::Minimized
+        } else if self.workspace_layout(workspace_id) == Some(TilingLayout::MasterStack) {
+            WindowState::Tiled
+        } else {
+            WindowState::Floating
+        };
+        drop(state);
+
+        IpcWindow {
+            info: WindowInfo::new(
+                WindowId::from_uuid(window.domain_id.as_uuid()),
+                window.title.clone().unwrap_or_default(),
+                WindowRect::new(geometry.loc.x as f64, geometry.loc.y as f64, geometry.size.w as f64, geometry.size.h as f64),
+                window_state,
+            ),
+            app_id: window.app_id.clone(),
+            workspace_id,
+            output,
+            focused: focused == Some(window.domain_id),
+            mapped: window.is_mapped,
+        }
+    }
+
+    fn workspace_layout(&self, workspace_id: Option<Uuid>) -> Option<TilingLayout> {
+        let workspace_id = workspace_id?;
+        self.output_workspaces.values().flatten().find_map(|ws| {
+            let ws = ws.read().unwrap();
+            (ws.id == workspace_id).then(|| *ws.tiling_layout.read().unwrap())
+        })
+    }
+}
+// ANCHOR_END: DesktopStateIpcIntegration
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use novade_domain::{
+        CoreWorkspaceLayoutType as WorkspaceLayoutType, WindowIdentifier, Workspace, WorkspaceEvent, WorkspaceManagerError,
+        WorkspaceOutputPolicy,
+    };
+
+    /// In-memory stand-in for the domain workspace manager.
+    struct FakeWorkspaces {
+        workspaces: Vec<Workspace>,
+        events: broadcast::Sender<WorkspaceEvent>,
+    }
+
+    #[async_trait]
+    impl WorkspaceManagerService for FakeWorkspaces {
+        async fn load_or_initialize_workspaces(&self) -> Result<(), WorkspaceManagerError> { Ok(()) }
+        async fn create_workspace(&self, _: Option<String>, _: Option<String>, _: Option<String>, _: Option<String>) -> Result<Uuid, WorkspaceManagerError> { unimplemented!() }
+        async fn delete_workspace(&self, _: Uuid, _: Option<Uuid>) -> Result<(), WorkspaceManagerError> { unimplemented!() }
+        fn get_workspace(&self, id: Uuid) -> Option<Workspace> { self.workspaces.iter().find(|w| w.id() == id).cloned() }
+        fn all_workspaces_ordered(&self) -> Vec<Workspace> { self.workspaces.clone() }
+        fn active_workspace_id(&self) -> Option<Uuid> { self.workspaces.first().map(|w| w.id()) }
+        async fn set_active_workspace(&self, _: Uuid) -> Result<(), WorkspaceManagerError> { unimplemented!() }
+        async fn assign_window_to_active_workspace(&self, _: &WindowIdentifier) -> Result<(), WorkspaceManagerError> { unimplemented!() }
+        async fn assign_window_to_specific_workspace(&self, _: Uuid, _: &WindowIdentifier) -> Result<(), WorkspaceManagerError> { unimplemented!() }
+        async fn remove_window_from_its_workspace(&self, _: &WindowIdentifier) -> Result<Option<Uuid>, WorkspaceManagerError> { unimplemented!() }
+        async fn move_window_to_specific_workspace(&self, _: Uuid, _: &WindowIdentifier) -> Result<(), WorkspaceManagerError> { unimplemented!() }
+        async fn rename_workspace(&self, _: Uuid, _: String) -> Result<(), WorkspaceManagerError> { unimplemented!() }
+        async fn set_workspace_layout(&self, _: Uuid, _: WorkspaceLayoutType) -> Result<(), WorkspaceManagerError> { unimplemented!() }
+        async fn set_workspace_icon(&self, _: Uuid, _: Option<String>) -> Result<(), WorkspaceManagerError> { unimplemented!() }
+        async fn set_workspace_accent_color(&self, _: Uuid, _: Option<String>) -> Result<(), WorkspaceManagerError> { unimplemented!() }
+        async fn save_configuration(&self) -> Result<(), WorkspaceManagerError> { Ok(()) }
+        fn subscribe_to_workspace_events(&self) -> broadcast::Receiver<WorkspaceEvent> { self.events.subscribe() }
+        async fn reorder_workspace(&self, _: Uuid, _: usize) -> Result<(), WorkspaceManagerError> { unimplemented!() }
+        async fn output_connected(&self, _: &str) -> Result<(), WorkspaceManagerError> { unimplemented!() }
+        async fn output_disconnected(&self, _: &str) -> Result<(), WorkspaceManagerError> { unimplemented!() }
+        async fn move_workspace_to_output(&self, _: Uuid, _: &str) -> Result<(), WorkspaceManagerError> { unimplemented!() }
+        async fn set_focused_output(&self, _: &str) -> Result<(), WorkspaceManagerError> { unimplemented!() }
+        async fn set_pointer_output(&self, _: Option<String>) {}
+        async fn set_workspace_output_policy(&self, _: WorkspaceOutputPolicy) {}
+        async fn connected_outputs(&self) -> Vec<String> { Vec::new() }
+        async fn focused_output(&self) -> Option<String> { None }
+        async fn workspaces_on_output(&self, _: &str) -> Vec<Workspace> { Vec::new() }
+        async fn active_workspace_on_output(&self, _: &str) -> Option<Uuid> { None }
+    }
+
+    fn summary(id: Uuid, name: &str) -> WorkspaceSummary {
+        WorkspaceSummary {
+            id,
+            name: name.into(),
+            output: Some("DP-1".into()),
+            index: Some(1),
+            active: true,
+            focused: true,
+            layout: "floating".into(),
+            windows: vec![],
+            persistent_id: None,
+            icon_name: None,
+            accent_color_hex: None,
+        }
+    }
+
+    #[test]
+    fn test_merge_domain_workspaces() {
+        let web = Workspace::new("Web".into(), Some("web".into()), Some("globe".into()), Some("#336699".into())).unwrap();
+        let chat = Workspace::new("Chat".into(), None, None, None).unwrap();
+        let service = FakeWorkspaces { workspaces: vec![web.clone(), chat.clone()], events: broadcast::channel(4).0 };
+
+        let mut summaries = vec![summary(web.id(), "1"), summary(Uuid::new_v4(), "2")];
+        merge_domain_workspaces(&mut summaries, &service);
+
+        assert_eq!(summaries.len(), 3);
+        assert_eq!(summaries[0].name, "Web");
+        assert_eq!(summaries[0].icon_name.as_deref(), Some("globe"));
+        assert_eq!(summaries[0].output.as_deref(), Some("DP-1"));
+        assert_eq!(summaries[1].name, "2");
+        assert_eq!(summaries[2].id, chat.id());
+        assert_eq!(summaries[2].output, None);
+        assert!(!summaries[2].active);
+    }
+}
@@ -0,0 +1,365 @@
+// novade-system/src/compositor/ipc/protocol.rs
+//! Wire format of the compositor IPC socket.
+//!
+//! The protocol is newline-delimited JSON over a Unix stream socket. Every line a client
+//! writes is an [`IpcRequest`]; the compositor answers each with exactly one [`IpcResponse`]
+//! line. After a successful `subscribe` request the connection switches to a stream of
+//! [`IpcEvent`] lines and further requests on it are ignored.
+//!
+//! ```text
+//! -> {"type":"get_windows"}
+//! <- {"success":true,"data":[{"id":"…","title":"foot","rect":{…},"state":"tiled",…}]}
+//! -> {"type":"run_action","action":{"action":"switch_workspace","index":2}}
+//! <- {"success":true}
+//! -> {"type":"subscribe","events":["window","workspace"]}
+//! <- {"success":true}
+//! <- {"event":"window","change":"focus","id":"…","window":{…}}
+//! ```
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use novade_domain::WorkspaceEvent;
+
+use crate::compositor::keybindings::KeybindingAction;
+use crate::compositor::recorder::RecordingStatus;
+use crate::window_mechanics::data_types::{WindowId, WindowInfo};
+
+/// Version of the IPC protocol, bumped on incompatible changes.
+pub const IPC_PROTOCOL_VERSION: u32 = 1;
+
+/// Environment variable the compositor exports with the socket path, for child processes.
+pub const IPC_SOCKET_ENV: &str = "NOVADE_SOCK";
+
+/// Socket path for a given runtime directory and Wayland display name.
+pub fn socket_path_for_display(runtime_dir: &Path, wayland_display: &str) -> PathBuf {
+    runtime_dir.join(format!("novade-ipc.{}.sock", wayland_display))
+}
+
+/// Socket path a client should connect to: `$NOVADE_SOCK`, else derived from
+/// `$XDG_RUNTIME_DIR` and `$WAYLAND_DISPLAY`.
+pub fn default_socket_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os(IPC_SOCKET_ENV) {
+        return Some(PathBuf::from(path));
+    }
+    let runtime_dir = std::env::var_os("XDG_RUNTIME_DIR")?;
+    let display = std::env::var("WAYLAND_DISPLAY").unwrap_or_else(|_| "wayland-0".to_string());
+    Some(socket_path_for_display(Path::new(&runtime_dir), &display))
+}
+
+// ANCHOR: IpcRequestDefinition
+/// A request sent by a client.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum IpcRequest {
+    /// Returns [`VersionInfo`].
+    GetVersion,
+    /// Returns a list of [`OutputInfo`].
+    GetOutputs,
+    /// Returns a list of [`WorkspaceSummary`].
+    GetWorkspaces,
+    /// Returns a list of [`IpcWindow`].
+    GetWindows,
+    /// Returns the [`NightLightStatus`](crate::compositor::night_light::NightLightStatus).
+    GetNightLight,
+    /// Returns the [`RecordingStatus`].
+    GetRecording,
+    /// Runs any action that can be bound to a key.
+    RunAction { action: KeybindingAction },
+    /// Re-reads `compositor.toml`; the error text carries line numbers on failure.
+    ReloadConfig,
+    /// Hands the compositor the resolved tokens of the active theme, keyed by token id, for
+    /// window effects. Replaces the previous set.
+    SetThemeTokens { tokens: BTreeMap<String, String> },
+    /// Switches the connection to an event stream of the given kinds (all kinds if empty).
+    Subscribe {
+        #[serde(default)]
+        events: Vec<IpcEventKind>,
+    },
+}
+
+/// The reply to a request.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IpcResponse {
+    pub success: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl IpcResponse {
+    /// A successful reply without payload.
+    pub fn ok() -> Self {
+        Self { success: true, data: None, error: None }
+    }
+
+    /// A successful reply carrying `data`.
+    pub fn with_data<T: Serialize>(data: &T) -> Self {
+        match serde_json::to_value(data) {
+            Ok(value) => Self { success: true, data: Some(value), error: None },
+            Err(e) => Self::error(format!("failed to serialize reply: {}", e)),
+        }
+    }
+
+    /// A failed reply.
+    pub fn error(message: impl Into<String>) -> Self {
+        Self { success: false, data: None, error: Some(message.into()) }
+    }
+
+    /// Deserializes the payload of a successful reply.
+    pub fn into_data<T: for<'de> Deserialize<'de>>(self) -> Result<T, String> {
+        if !self.success {
+            return Err(self.error.unwrap_or_else(|| "request failed".to_string()));
+        }
+        serde_json::from_value(self.data.unwrap_or(serde_json::Value::Null)).map_err(|e| e.to_string())
+    }
+}
+// ANCHOR_END: IpcRequestDefinition
+
+// ANCHOR: IpcReplyTypes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VersionInfo {
+    pub name: String,
+    pub version: String,
+    pub protocol_version: u32,
+}
+
+impl VersionInfo {
+    pub fn current() -> Self {
+        Self {
+            name: "novade-compositor".to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            protocol_version: IPC_PROTOCOL_VERSION,
+        }
+    }
+}
+
+/// A connected output.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OutputInfo {
+    pub name: String,
+    pub description: String,
+    pub make: String,
+    pub model: String,
+    /// Position in the global logical space.
+    pub x: i32,
+    pub y: i32,
+    /// Logical size.
+    pub width: i32,
+    pub height: i32,
+    /// Refresh rate of the current mode in mHz.
+    pub refresh_mhz: i32,
+    pub scale: f64,
+    pub primary: bool,
+    /// The output commands without an explicit output apply to.
+    pub focused: bool,
+    pub active_workspace: Option<Uuid>,
+}
+
+/// A workspace, combining compositor runtime state with the domain workspace metadata.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorkspaceSummary {
+    pub id: Uuid,
+    pub name: String,
+    /// Output the workspace lives on; `None` for domain workspaces not placed on any output.
+    pub output: Option<String>,
+    /// 1-based position on its output, as used by `switch_workspace`.
+    pub index: Option<usize>,
+    /// Shown on its output.
+    pub active: bool,
+    /// Shown on the focused output.
+    pub focused: bool,
+    pub layout: String,
+    pub windows: Vec<WindowId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub persistent_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accent_color_hex: Option<String>,
+}
+
+/// A toplevel window: the shared [`WindowInfo`] model plus compositor placement.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IpcWindow {
+    #[serde(flatten)]
+    pub info: WindowInfo,
+    pub app_id: Option<String>,
+    pub workspace_id: Option<Uuid>,
+    pub output: Option<String>,
+    pub focused: bool,
+    pub mapped: bool,
+}
+// ANCHOR_END: IpcReplyTypes
+
+// ANCHOR: IpcEventDefinition
+/// Event categories a client can subscribe to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IpcEventKind {
+    Window,
+    Workspace,
+    Output,
+    Mode,
+    Recording,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WindowChange {
+    New,
+    Close,
+    Focus,
+    Title,
+    /// Moved to another workspace or output.
+    Move,
+    /// Maximized, fullscreen or minimized state changed.
+    State,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WorkspaceChange {
+    /// A workspace became active on its output.
+    Focus,
+    Created,
+    Removed,
+    /// A workspace moved to another output.
+    Move,
+    /// Forwarded from the domain `WorkspaceManagerService`; see `domain_event`.
+    Domain,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputChange {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// Compositor-wide interaction mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CompositorMode {
+    Normal,
+    Overview,
+}
+
+/// An event streamed to subscribed clients.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum IpcEvent {
+    Window {
+        change: WindowChange,
+        id: WindowId,
+        /// Current window state; absent for `close`.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        window: Option<IpcWindow>,
+    },
+    Workspace {
+        change: WorkspaceChange,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        id: Option<Uuid>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        output: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        domain_event: Option<WorkspaceEvent>,
+    },
+    Output {
+        change: OutputChange,
+        name: String,
+    },
+    Mode {
+        mode: CompositorMode,
+    },
+    /// A recording started, stopped, failed or finished saving.
+    Recording {
+        status: RecordingStatus,
+    },
+}
+
+impl IpcEvent {
+    pub fn kind(&self) -> IpcEventKind {
+        match self {
+            IpcEvent::Window { .. } => IpcEventKind::Window,
+            IpcEvent::Workspace { .. } => IpcEventKind::Workspace,
+            IpcEvent::Output { .. } => IpcEventKind::Output,
+            IpcEvent::Mode { .. } => IpcEventKind::Mode,
+            IpcEvent::Recording { .. } => IpcEventKind::Recording,
+        }
+    }
+}
+// ANCHOR_END: IpcEventDefinition
+
+/// Serializes `value` as one protocol line, including the trailing newline.
+pub fn encode_line<T: Serialize>(value: &T) -> serde_json::Result<String> {
+    let mut line = serde_json::to_string(value)?;
+    line.push('\n');
+    Ok(line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::window_mechanics::data_types::{WindowRect, WindowState};
+
+    #[test]
+    fn test_request_wire_format() {
+        let request: IpcRequest = serde_json::from_str(r#"{"type":"run_action","action":{"action":"switch_workspace","index":3}}"#).unwrap();
+        assert_eq!(
+            request,
+            IpcRequest::RunAction { action: KeybindingAction::SwitchWorkspace { index: 3, output: None } }
+        );
+        let subscribe: IpcRequest = serde_json::from_str(r#"{"type":"subscribe"}"#).unwrap();
+        assert_eq!(subscribe, IpcRequest::Subscribe { events: vec![] });
+        assert_eq!(encode_line(&IpcRequest::GetWindows).unwrap(), "{\"type\":\"get_windows\"}\n");
+        let tokens: IpcRequest = serde_json::from_str(r#"{"type":"set_theme_tokens","tokens":{"property-border-radius":"6px"}}"#).unwrap();
+        assert_eq!(tokens, IpcRequest::SetThemeTokens { tokens: BTreeMap::from([("property-border-radius".to_string(), "6px".to_string())]) });
+    }
+
+    #[test]
+    fn test_window_event_flattens_window_info() {
+        let id = WindowId::new_v4();
+        let window = IpcWindow {
+            info: WindowInfo::new(id, "foot".into(), WindowRect::new(0.0, 0.0, 640.0, 480.0), WindowState::Floating),
+            app_id: Some("foot".into()),
+            workspace_id: None,
+            output: Some("DP-1".into()),
+            focused: true,
+            mapped: true,
+        };
+        let event = IpcEvent::Window { change: WindowChange::Focus, id, window: Some(window.clone()) };
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["event"], "window");
+        assert_eq!(json["change"], "focus");
+        assert_eq!(json["window"]["title"], "foot");
+        assert_eq!(json["window"]["state"], "floating");
+        assert_eq!(json["window"]["output"], "DP-1");
+        assert_eq!(serde_json::from_value::<IpcEvent>(json).unwrap(), event);
+        assert_eq!(event.kind(), IpcEventKind::Window);
+    }
+
+    #[test]
+    fn test_response_helpers() {
+        let reply = IpcResponse::with_data(&VersionInfo::current());
+        let version: VersionInfo = reply.into_data().unwrap();
+        assert_eq!(version.protocol_version, IPC_PROTOCOL_VERSION);
+
+        let failed = IpcResponse::error("no focused window");
+        assert_eq!(serde_json::to_string(&failed).unwrap(), r#"{"success":false,"error":"no focused window"}"#);
+        assert_eq!(failed.into_data::<()>().unwrap_err(), "no focused window");
+    }
+
+    #[test]
+    fn test_socket_path_for_display() {
+        assert_eq!(
+            socket_path_for_display(Path::new("/run/user/1000"), "wayland-1"),
+            PathBuf::from("/run/user/1000/novade-ipc.wayland-1.sock")
+        );
+    }
+}
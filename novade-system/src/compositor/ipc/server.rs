@@ -0,0 +1,301 @@
+// novade-system/src/compositor/ipc/server.rs
+//! Socket handling for the compositor IPC: an async server that answers requests through an
+//! [`IpcRequestHandler`] and streams events to subscribers, plus a small blocking client used
+//! by `novadectl`.
+
+use std::collections::HashSet;
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream as StdUnixStream;
+use std::path::Path;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use nix::sys::stat::{umask, Mode};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader as AsyncBufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::broadcast;
+use tracing::{debug, info, warn};
+
+use super::protocol::{encode_line, IpcEvent, IpcEventKind, IpcRequest, IpcResponse};
+
+/// Answers IPC requests. `Subscribe` requests are handled by the server itself and never
+/// reach the handler.
+#[async_trait]
+pub trait IpcRequestHandler: Send + Sync + 'static {
+    async fn handle(&self, request: IpcRequest) -> IpcResponse;
+}
+
+/// Binds the IPC socket at `path`, replacing a stale socket left behind by a crashed
+/// compositor. Fails if another compositor is still listening on it.
+pub fn bind_socket(path: &Path) -> io::Result<std::os::unix::net::UnixListener> {
+    if path.exists() {
+        if StdUnixStream::connect(path).is_ok() {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                format!("another compositor is listening on {}", path.display()),
+            ));
+        }
+        std::fs::remove_file(path)?;
+    }
+    // Only the session user may drive the compositor. The socket is created 0600 rather than
+    // chmod'ed after bind, which would leave a window where others could connect.
+    let previous_umask = umask(Mode::from_bits_truncate(0o177));
+    let listener = std::os::unix::net::UnixListener::bind(path);
+    umask(previous_umask);
+    let listener = listener?;
+    listener.set_nonblocking(true)?;
+    Ok(listener)
+}
+
+/// Accepts connections until the listener fails, serving each one on its own task.
+pub async fn serve(listener: UnixListener, handler: Arc<dyn IpcRequestHandler>, events: broadcast::Sender<IpcEvent>) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                let handler = handler.clone();
+                let events = events.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = handle_connection(stream, handler, events).await {
+                        debug!("IPC connection closed with error: {}", e);
+                    }
+                });
+            }
+            Err(e) => {
+                warn!("IPC socket accept failed, stopping IPC server: {}", e);
+                return;
+            }
+        }
+    }
+}
+
+async fn handle_connection(
+    stream: UnixStream,
+    handler: Arc<dyn IpcRequestHandler>,
+    events: broadcast::Sender<IpcEvent>,
+) -> io::Result<()> {
+    let (read_half, mut write_half) = stream.into_split();
+    let mut lines = AsyncBufReader::new(read_half).lines();
+
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let request = match serde_json::from_str::<IpcRequest>(&line) {
+            Ok(request) => request,
+            Err(e) => {
+                write_json(&mut write_half, &IpcResponse::error(format!("invalid request: {}", e))).await?;
+                continue;
+            }
+        };
+
+        if let IpcRequest::Subscribe { events: kinds } = request {
+            // Subscribe before acknowledging so no event after the reply is missed.
+            let receiver = events.subscribe();
+            write_json(&mut write_half, &IpcResponse::ok()).await?;
+            let kinds: HashSet<IpcEventKind> = kinds.into_iter().collect();
+            return stream_events(receiver, kinds, lines, write_half).await;
+        }
+
+        let response = handler.handle(request).await;
+        write_json(&mut write_half, &response).await?;
+    }
+    Ok(())
+}
+
+async fn stream_events(
+    mut receiver: broadcast::Receiver<IpcEvent>,
+    kinds: HashSet<IpcEventKind>,
+    mut lines: tokio::io::Lines<AsyncBufReader<tokio::net::unix::OwnedReadHalf>>,
+    mut write_half: tokio::net::unix::OwnedWriteHalf,
+) -> io::Result<()> {
+    loop {
+        tokio::select! {
+            event = receiver.recv() => match event {
+                Ok(event) => {
+                    if kinds.is_empty() || kinds.contains(&event.kind()) {
+                        write_json(&mut write_half, &event).await?;
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!("IPC subscriber lagged behind, {} events dropped.", skipped);
+                }
+                Err(broadcast::error::RecvError::Closed) => return Ok(()),
+            },
+            // Requests are ignored on a subscribed connection; EOF means the client left.
+            line = lines.next_line() => {
+                if line?.is_none() {
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+async fn write_json<T: serde::Serialize>(writer: &mut tokio::net::unix::OwnedWriteHalf, value: &T) -> io::Result<()> {
+    let line = encode_line(value).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    writer.write_all(line.as_bytes()).await
+}
+
+// ANCHOR: IpcClient
+/// Blocking IPC client.
+pub struct IpcClient {
+    reader: BufReader<StdUnixStream>,
+    writer: StdUnixStream,
+}
+
+impl IpcClient {
+    pub fn connect(path: &Path) -> io::Result<Self> {
+        let stream = StdUnixStream::connect(path)?;
+        let writer = stream.try_clone()?;
+        info!("Connected to compositor IPC at {}", path.display());
+        Ok(Self { reader: BufReader::new(stream), writer })
+    }
+
+    /// Sends one request and waits for its reply.
+    pub fn request(&mut self, request: &IpcRequest) -> io::Result<IpcResponse> {
+        let line = encode_line(request).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        self.writer.write_all(line.as_bytes())?;
+        self.read_json()
+    }
+
+    /// Subscribes to `kinds` (all if empty) and returns an iterator over incoming events.
+    pub fn subscribe(mut self, kinds: Vec<IpcEventKind>) -> io::Result<impl Iterator<Item = io::Result<IpcEvent>>> {
+        let response = self.request(&IpcRequest::Subscribe { events: kinds })?;
+        if !response.success {
+            return Err(io::Error::new(io::ErrorKind::Other, response.error.unwrap_or_default()));
+        }
+        Ok(std::iter::from_fn(move || match self.read_json::<IpcEvent>() {
+            Ok(event) => Some(Ok(event)),
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => None,
+            Err(e) => Some(Err(e)),
+        }))
+    }
+
+    fn read_json<T: for<'de> serde::Deserialize<'de>>(&mut self) -> io::Result<T> {
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "compositor closed the IPC connection"));
+        }
+        serde_json::from_str(&line).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+// ANCHOR_END: IpcClient
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compositor::ipc::protocol::{CompositorMode, VersionInfo};
+    use crate::compositor::keybindings::KeybindingAction;
+    use std::sync::Mutex;
+
+    /// Records actions and answers `get_version`; everything else fails.
+    #[derive(Default)]
+    struct FakeHandler {
+        actions: Mutex<Vec<KeybindingAction>>,
+    }
+
+    #[async_trait]
+    impl IpcRequestHandler for FakeHandler {
+        async fn handle(&self, request: IpcRequest) -> IpcResponse {
+            match request {
+                IpcRequest::GetVersion => IpcResponse::with_data(&VersionInfo::current()),
+                IpcRequest::RunAction { action } => {
+                    self.actions.lock().unwrap().push(action);
+                    IpcResponse::ok()
+                }
+                _ => IpcResponse::error("unsupported"),
+            }
+        }
+    }
+
+    fn start_server() -> (tempfile::TempDir, std::path::PathBuf, Arc<FakeHandler>, broadcast::Sender<IpcEvent>) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ipc.sock");
+        let listener = UnixListener::from_std(bind_socket(&path).unwrap()).unwrap();
+        let handler = Arc::new(FakeHandler::default());
+        let (events, _) = broadcast::channel(16);
+        tokio::spawn(serve(listener, handler.clone(), events.clone()));
+        (dir, path, handler, events)
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_request_reply_roundtrip() {
+        let (_dir, path, handler, _events) = start_server();
+
+        let replies = tokio::task::spawn_blocking(move || {
+            let mut client = IpcClient::connect(&path).unwrap();
+            let version = client.request(&IpcRequest::GetVersion).unwrap();
+            let action = client
+                .request(&IpcRequest::RunAction { action: KeybindingAction::ToggleOverview })
+                .unwrap();
+            let unsupported = client.request(&IpcRequest::GetWindows).unwrap();
+            (version, action, unsupported)
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(replies.0.into_data::<VersionInfo>().unwrap(), VersionInfo::current());
+        assert!(replies.1.success);
+        assert_eq!(replies.2.error.as_deref(), Some("unsupported"));
+        assert_eq!(*handler.actions.lock().unwrap(), vec![KeybindingAction::ToggleOverview]);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_invalid_request_gets_error_reply() {
+        let (_dir, path, _handler, _events) = start_server();
+        let reply = tokio::task::spawn_blocking(move || {
+            let mut stream = StdUnixStream::connect(&path).unwrap();
+            stream.write_all(b"{\"type\":\"launch_rockets\"}\n").unwrap();
+            let mut line = String::new();
+            BufReader::new(stream).read_line(&mut line).unwrap();
+            serde_json::from_str::<IpcResponse>(&line).unwrap()
+        })
+        .await
+        .unwrap();
+        assert!(!reply.success);
+        assert!(reply.error.unwrap().starts_with("invalid request"));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_subscribe_filters_event_kinds() {
+        let (_dir, path, _handler, events) = start_server();
+
+        let (subscribed_tx, subscribed_rx) = std::sync::mpsc::channel();
+        let reader = tokio::task::spawn_blocking(move || {
+            let client = IpcClient::connect(&path).unwrap();
+            let mut stream = client.subscribe(vec![IpcEventKind::Mode]).unwrap();
+            subscribed_tx.send(()).unwrap();
+            stream.next().unwrap().unwrap()
+        });
+
+        tokio::task::spawn_blocking(move || subscribed_rx.recv().unwrap()).await.unwrap();
+        events
+            .send(IpcEvent::Output { change: crate::compositor::ipc::protocol::OutputChange::Added, name: "DP-1".into() })
+            .unwrap();
+        events.send(IpcEvent::Mode { mode: CompositorMode::Overview }).unwrap();
+
+        assert_eq!(reader.await.unwrap(), IpcEvent::Mode { mode: CompositorMode::Overview });
+    }
+
+    #[test]
+    fn test_bind_socket_replaces_stale_socket() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ipc.sock");
+        drop(bind_socket(&path).unwrap());
+        // The file is left behind but nobody listens: binding again must succeed.
+        let listener = bind_socket(&path).unwrap();
+        // While it is listening, a second compositor must not steal the socket.
+        assert_eq!(bind_socket(&path).unwrap_err().kind(), io::ErrorKind::AddrInUse);
+        drop(listener);
+    }
+
+    #[test]
+    fn test_bind_socket_is_private_to_the_user() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ipc.sock");
+        let _listener = bind_socket(&path).unwrap();
+        assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+    }
+}
@@ -0,0 +1,424 @@
+// ANCHOR: KeybindingsModule
+//! Compositor keybindings and the actions they trigger.
+//!
+//! [`KeybindingAction`] is the single vocabulary of compositor commands: key presses, the IPC
+//! socket (`novadectl action ...`) and the config file all use it. Bindings are written as
+//! `"Super+Shift+1"`; modifier names are case-insensitive and the key is an XKB keysym name.
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use smithay::input::keyboard::{Keysym, ModifiersState};
+use smithay::wayland::shell::xdg::{WindowSurface, XdgShellHandler};
+use tracing::{info, warn};
+use uuid::Uuid;
+use xkbcommon::xkb;
+
+use crate::compositor::config::LayoutMode;
+use crate::compositor::night_light::NightLightMode;
+use crate::compositor::recorder::RecordingTarget;
+use crate::compositor::shell::xdg_shell::types::DomainWindowIdentifier;
+use crate::compositor::state::DesktopState;
+use crate::compositor::tiling;
+
+/// A command the compositor can execute, from a key press or over IPC.
+///
+/// Serialized with an `action` tag, e.g. `{"action": "switch_workspace", "index": 2}`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum KeybindingAction {
+    /// Asks the focused window to close.
+    CloseWindow,
+    /// Focuses (and raises) a window by its id.
+    FocusWindow { id: Uuid },
+    ToggleMaximize,
+    ToggleFullscreen,
+    Minimize,
+    ToggleOverview,
+    /// Switches to the workspace at 1-based `index` on `output`, or on the current output.
+    SwitchWorkspace {
+        index: usize,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        output: Option<String>,
+    },
+    /// Moves the focused window to the workspace at 1-based `index` on its output.
+    MoveToWorkspace { index: usize },
+    /// Moves the current workspace to `output`: an output name, `next` or `previous`.
+    MoveWorkspaceToOutput { output: String },
+    /// Focuses `output`: an output name, `next` or `previous`.
+    FocusOutput { output: String },
+    /// Creates a workspace on the output chosen by `layout.new_workspace_output`.
+    NewWorkspace,
+    /// Sets the layout of the current workspace.
+    SetLayout { layout: LayoutMode },
+    ReloadConfig,
+    /// Switches night light on or off, overriding its schedule, or back to the schedule.
+    SetNightLight { mode: NightLightMode },
+    /// Starts recording `target`, by default the current output.
+    StartRecording {
+        #[serde(default)]
+        target: RecordingTarget,
+    },
+    StopRecording,
+    /// Stops the recording if one runs, else starts recording `target`.
+    ToggleRecording {
+        #[serde(default)]
+        target: RecordingTarget,
+    },
+    /// Runs `command` through `sh -c`.
+    Spawn { command: String },
+    /// Stops the compositor.
+    Quit,
+}
+
+// ANCHOR: KeyComboDefinition
+/// A modifier set plus a keysym, parsed from strings like `"Super+Shift+Return"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyCombo {
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+    pub logo: bool,
+    /// Raw keysym, lowercased for Latin letters.
+    pub keysym: u32,
+}
+
+/// Maps Latin capital letters to lowercase so `Super+Q` and `Super+q` are the same binding.
+fn normalize_keysym(raw: u32) -> u32 {
+    if (xkb::KEY_A..=xkb::KEY_Z).contains(&raw) {
+        raw + (xkb::KEY_a - xkb::KEY_A)
+    } else {
+        raw
+    }
+}
+
+impl KeyCombo {
+    /// Returns `true` if the pressed `keysym` with `modifiers` triggers this combo.
+    pub fn matches(&self, modifiers: &ModifiersState, keysym: Keysym) -> bool {
+        self.ctrl == modifiers.ctrl
+            && self.alt == modifiers.alt
+            && self.shift == modifiers.shift
+            && self.logo == modifiers.logo
+            && self.keysym == normalize_keysym(keysym.raw())
+    }
+}
+
+impl FromStr for KeyCombo {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut combo = KeyCombo { ctrl: false, alt: false, shift: false, logo: false, keysym: xkb::KEY_NoSymbol };
+        let parts: Vec<&str> = s.split('+').map(str::trim).collect();
+        let (key, modifiers) = parts.split_last().ok_or_else(|| "empty key combination".to_string())?;
+        for modifier in modifiers {
+            match modifier.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => combo.ctrl = true,
+                "alt" | "mod1" => combo.alt = true,
+                "shift" => combo.shift = true,
+                "super" | "logo" | "mod4" => combo.logo = true,
+                "" => return Err(format!("empty modifier in '{}'", s)),
+                other => return Err(format!("unknown modifier '{}' in '{}'", other, s)),
+            }
+        }
+        if key.is_empty() {
+            return Err(format!("missing key in '{}'", s));
+        }
+        let keysym = xkb::keysym_from_name(key, xkb::KEYSYM_CASE_INSENSITIVE).raw();
+        if keysym == xkb::KEY_NoSymbol {
+            return Err(format!("unknown key '{}' in '{}'", key, s));
+        }
+        combo.keysym = normalize_keysym(keysym);
+        Ok(combo)
+    }
+}
+
+impl fmt::Display for KeyCombo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (set, name) in [(self.logo, "Super"), (self.ctrl, "Ctrl"), (self.alt, "Alt"), (self.shift, "Shift")] {
+            if set {
+                write!(f, "{}+", name)?;
+            }
+        }
+        write!(f, "{}", xkb::keysym_get_name(Keysym::new(self.keysym)))
+    }
+}
+// ANCHOR_END: KeyComboDefinition
+
+/// A key combination bound to an action.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keybinding {
+    pub combo: KeyCombo,
+    pub action: KeybindingAction,
+}
+
+/// A binding as written in `compositor.toml`:
+///
+/// ```toml
+/// [[keybinding]]
+/// keys = "Super+Return"
+/// action = "spawn"
+/// command = "foot"
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KeybindingConfig {
+    pub keys: String,
+    #[serde(flatten)]
+    pub action: KeybindingAction,
+}
+
+/// The built-in bindings.
+pub fn default_keybindings() -> Vec<Keybinding> {
+    let mut bindings: Vec<(String, KeybindingAction)> = vec![
+        ("Super+Tab".into(), KeybindingAction::ToggleOverview),
+        ("Alt+F4".into(), KeybindingAction::CloseWindow),
+        ("Super+Q".into(), KeybindingAction::CloseWindow),
+        ("Super+F".into(), KeybindingAction::ToggleFullscreen),
+        ("Super+M".into(), KeybindingAction::ToggleMaximize),
+        ("Super+H".into(), KeybindingAction::Minimize),
+        ("Super+Shift+R".into(), KeybindingAction::ReloadConfig),
+        ("Super+N".into(), KeybindingAction::NewWorkspace),
+        ("Super+period".into(), KeybindingAction::FocusOutput { output: "next".into() }),
+        ("Super+comma".into(), KeybindingAction::FocusOutput { output: "previous".into() }),
+        ("Super+Shift+period".into(), KeybindingAction::MoveWorkspaceToOutput { output: "next".into() }),
+        ("Super+Shift+comma".into(), KeybindingAction::MoveWorkspaceToOutput { output: "previous".into() }),
+        ("Super+Print".into(), KeybindingAction::ToggleRecording { target: RecordingTarget::default() }),
+        ("Super+Shift+Print".into(), KeybindingAction::ToggleRecording { target: RecordingTarget::Window { id: None } }),
+    ];
+    for index in 1..=9usize {
+        bindings.push((format!("Super+{}", index), KeybindingAction::SwitchWorkspace { index, output: None }));
+        bindings.push((format!("Super+Shift+{}", index), KeybindingAction::MoveToWorkspace { index }));
+    }
+    bindings
+        .into_iter()
+        .map(|(keys, action)| Keybinding { combo: keys.parse().expect("built-in keybinding must parse"), action })
+        .collect()
+}
+
+/// Builds the active binding table: the defaults, with configured bindings replacing defaults
+/// on the same key combination. Invalid entries are skipped (config validation reports them).
+pub fn resolve_keybindings(configured: &[KeybindingConfig]) -> Vec<Keybinding> {
+    let mut bindings = default_keybindings();
+    for entry in configured {
+        let combo = match entry.keys.parse::<KeyCombo>() {
+            Ok(combo) => combo,
+            Err(e) => {
+                warn!("Ignoring keybinding '{}': {}", entry.keys, e);
+                continue;
+            }
+        };
+        bindings.retain(|b| b.combo != combo);
+        bindings.push(Keybinding { combo, action: entry.action.clone() });
+    }
+    bindings
+}
+
+// ANCHOR: DesktopStateKeybindingActions
+impl DesktopState {
+    /// Looks up the action bound to `keysym` with `modifiers`.
+    pub fn keybinding_action_for(&self, modifiers: &ModifiersState, keysym: Keysym) -> Option<KeybindingAction> {
+        self.keybindings
+            .iter()
+            .find(|binding| binding.combo.matches(modifiers, keysym))
+            .map(|binding| binding.action.clone())
+    }
+
+    /// Executes a compositor action. Errors describe why the action could not run.
+    pub fn run_action(&mut self, action: KeybindingAction) -> Result<(), String> {
+        info!("Running compositor action {:?}", action);
+        self.schedule_redraw_all();
+        match action {
+            KeybindingAction::CloseWindow => {
+                let window = self.focused_managed_window().ok_or("no focused window")?;
+                match &window.xdg_surface {
+                    WindowSurface::Toplevel(toplevel) => toplevel.send_close(),
+                    _ => return Err("focused surface is not a toplevel".into()),
+                }
+            }
+            KeybindingAction::FocusWindow { id } => {
+                let window_id = DomainWindowIdentifier::from_uuid(id);
+                if !self.windows.contains_key(&window_id) {
+                    return Err(format!("no window with id {}", id));
+                }
+                self.focus_domain_window(window_id);
+            }
+            KeybindingAction::ToggleMaximize => {
+                let window = self.focused_managed_window().ok_or("no focused window")?;
+                let toplevel = toplevel_of(&window.xdg_surface)?;
+                if window.state.read().unwrap().maximized {
+                    XdgShellHandler::toplevel_request_unset_maximized(self, &toplevel);
+                } else {
+                    XdgShellHandler::toplevel_request_set_maximized(self, &toplevel);
+                }
+            }
+            KeybindingAction::ToggleFullscreen => {
+                let window = self.focused_managed_window().ok_or("no focused window")?;
+                let toplevel = toplevel_of(&window.xdg_surface)?;
+                if window.state.read().unwrap().fullscreen {
+                    XdgShellHandler::toplevel_request_unset_fullscreen(self, &toplevel);
+                } else {
+                    XdgShellHandler::toplevel_request_set_fullscreen(self, &toplevel, None);
+                }
+            }
+            KeybindingAction::Minimize => {
+                let window = self.focused_managed_window().ok_or("no focused window")?;
+                let toplevel = toplevel_of(&window.xdg_surface)?;
+                XdgShellHandler::toplevel_request_set_minimized(self, &toplevel);
+            }
+            KeybindingAction::ToggleOverview => self.toggle_overview(),
+            KeybindingAction::SwitchWorkspace { index, output } => {
+                let output_name = output.or_else(|| self.current_output_name()).ok_or("no output")?;
+                let workspace_id = self
+                    .workspace_id_at(&output_name, index)
+                    .ok_or_else(|| format!("output {} has no workspace {}", output_name, index))?;
+                self.switch_workspace_on_output(&output_name, workspace_id);
+            }
+            KeybindingAction::MoveToWorkspace { index } => {
+                let window_id = self.focused_domain_window_id().ok_or("no focused window")?;
+                let output_name = self.windows[&window_id]
+                    .output_name
+                    .read()
+                    .unwrap()
+                    .clone()
+                    .or_else(|| self.current_output_name())
+                    .ok_or("no output")?;
+                let workspace_id = self
+                    .workspace_id_at(&output_name, index)
+                    .ok_or_else(|| format!("output {} has no workspace {}", output_name, index))?;
+                if !self.move_window_to_workspace(window_id, workspace_id) {
+                    return Err("failed to move window".into());
+                }
+            }
+            KeybindingAction::MoveWorkspaceToOutput { output } => {
+                let source = self.current_output_name().ok_or("no output")?;
+                let target = self.resolve_output_name(&output).ok_or_else(|| format!("no output '{}'", output))?;
+                let workspace_id = *self.active_workspaces.read().unwrap().get(&source).ok_or("output has no active workspace")?;
+                self.move_workspace_to_output(workspace_id, &target)?;
+            }
+            KeybindingAction::FocusOutput { output } => {
+                let target = self.resolve_output_name(&output).ok_or_else(|| format!("no output '{}'", output))?;
+                self.focus_output(&target)?;
+            }
+            KeybindingAction::NewWorkspace => {
+                self.create_workspace().ok_or("no output")?;
+            }
+            KeybindingAction::SetLayout { layout } => {
+                let output_name = self.current_output_name().ok_or("no output")?;
+                let active_id = *self.active_workspaces.read().unwrap().get(&output_name).ok_or("output has no active workspace")?;
+                let workspace = self
+                    .output_workspaces
+                    .get(&output_name)
+                    .and_then(|list| list.iter().find(|ws| ws.read().unwrap().id == active_id).cloned())
+                    .ok_or("active workspace not found")?;
+                *workspace.read().unwrap().tiling_layout.write().unwrap() = layout.into();
+                tiling::apply_layout_for_output(self, &output_name);
+            }
+            KeybindingAction::ReloadConfig => self.reload_config().map_err(|e| e.to_string())?,
+            KeybindingAction::SetNightLight { mode } => self.set_night_light(mode),
+            KeybindingAction::StartRecording { target } => {
+                self.start_recording(target)?;
+            }
+            KeybindingAction::StopRecording => {
+                self.stop_recording()?;
+            }
+            KeybindingAction::ToggleRecording { target } => {
+                self.toggle_recording(target)?;
+            }
+            KeybindingAction::Spawn { command } => {
+                std::process::Command::new("/bin/sh")
+                    .arg("-c")
+                    .arg(&command)
+                    .spawn()
+                    .map_err(|e| format!("failed to spawn '{}': {}", command, e))?;
+            }
+            KeybindingAction::Quit => {
+                info!("Quit requested.");
+                *self.running.write().unwrap() = false;
+            }
+        }
+        Ok(())
+    }
+
+    fn focused_managed_window(&self) -> Option<std::sync::Arc<crate::compositor::shell::xdg_shell::types::ManagedWindow>> {
+        self.focused_domain_window_id().and_then(|id| self.windows.get(&id).cloned())
+    }
+
+    /// Id of the workspace at 1-based `index` on `output_name`.
+    fn workspace_id_at(&self, output_name: &str, index: usize) -> Option<Uuid> {
+        let list = self.output_workspaces.get(output_name)?;
+        list.get(index.checked_sub(1)?).map(|ws| ws.read().unwrap().id)
+    }
+}
+// ANCHOR_END: DesktopStateKeybindingActions
+
+fn toplevel_of(surface: &WindowSurface) -> Result<smithay::wayland::shell::xdg::ToplevelSurface, String> {
+    match surface {
+        WindowSurface::Toplevel(toplevel) => Ok(toplevel.clone()),
+        _ => Err("focused surface is not a toplevel".into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mods(ctrl: bool, alt: bool, shift: bool, logo: bool) -> ModifiersState {
+        ModifiersState { ctrl, alt, shift, logo, ..Default::default() }
+    }
+
+    #[test]
+    fn test_parse_key_combo() {
+        let combo: KeyCombo = "Super+Shift+Return".parse().unwrap();
+        assert!(combo.logo && combo.shift && !combo.ctrl && !combo.alt);
+        assert_eq!(combo.keysym, xkb::KEY_Return);
+
+        let upper: KeyCombo = "super+Q".parse().unwrap();
+        let lower: KeyCombo = "Mod4+q".parse().unwrap();
+        assert_eq!(upper, lower);
+        assert_eq!(upper.to_string(), "Super+q");
+
+        assert!("Hyper+Q".parse::<KeyCombo>().unwrap_err().contains("unknown modifier"));
+        assert!("Super+NotAKey".parse::<KeyCombo>().unwrap_err().contains("unknown key"));
+        assert!("Super+".parse::<KeyCombo>().is_err());
+    }
+
+    #[test]
+    fn test_combo_matches_exact_modifiers() {
+        let combo: KeyCombo = "Super+1".parse().unwrap();
+        let one = Keysym::new(xkb::KEY_1);
+        assert!(combo.matches(&mods(false, false, false, true), one));
+        assert!(!combo.matches(&mods(false, false, true, true), one));
+        assert!(!combo.matches(&mods(false, false, false, false), one));
+    }
+
+    #[test]
+    fn test_configured_bindings_override_defaults() {
+        let configured = vec![
+            KeybindingConfig { keys: "Super+Q".into(), action: KeybindingAction::Spawn { command: "foot".into() } },
+            KeybindingConfig { keys: "Bogus+X".into(), action: KeybindingAction::Quit },
+        ];
+        let bindings = resolve_keybindings(&configured);
+        let super_q: KeyCombo = "Super+q".parse().unwrap();
+        let matching: Vec<_> = bindings.iter().filter(|b| b.combo == super_q).collect();
+        assert_eq!(matching.len(), 1);
+        assert_eq!(matching[0].action, KeybindingAction::Spawn { command: "foot".into() });
+        assert!(!bindings.iter().any(|b| b.action == KeybindingAction::Quit));
+        assert_eq!(bindings.len(), default_keybindings().len());
+    }
+
+    #[test]
+    fn test_action_json_shape() {
+        let action = KeybindingAction::SwitchWorkspace { index: 2, output: None };
+        assert_eq!(serde_json::to_value(&action).unwrap(), serde_json::json!({"action": "switch_workspace", "index": 2}));
+        let parsed: KeybindingAction = serde_json::from_str(r#"{"action":"set_layout","layout":"master_stack"}"#).unwrap();
+        assert_eq!(parsed, KeybindingAction::SetLayout { layout: LayoutMode::MasterStack });
+        let parsed: KeybindingAction = serde_json::from_str(r#"{"action":"move_workspace_to_output","output":"next"}"#).unwrap();
+        assert_eq!(parsed, KeybindingAction::MoveWorkspaceToOutput { output: "next".into() });
+        let parsed: KeybindingAction = serde_json::from_str(r#"{"action":"set_night_light","mode":"toggle"}"#).unwrap();
+        assert_eq!(parsed, KeybindingAction::SetNightLight { mode: NightLightMode::Toggle });
+        let parsed: KeybindingAction = serde_json::from_str(r#"{"action":"toggle_recording","target":{"type":"window"}}"#).unwrap();
+        assert_eq!(parsed, KeybindingAction::ToggleRecording { target: RecordingTarget::Window { id: None } });
+        let parsed: KeybindingAction = serde_json::from_str(r#"{"action":"start_recording"}"#).unwrap();
+        assert_eq!(parsed, KeybindingAction::StartRecording { target: RecordingTarget::default() });
+    }
+}
@@ -39,8 +39,19 @@ pub mod shell;            // Generic shell module, now split into xdg_shell.rs,
 // pub mod scene_graph;
 pub mod display_management; // Domain level display management, may differ from output_manager.rs
 pub mod animations;
+pub mod color;
+pub mod damage;
+pub mod effects;
+pub mod region;
+pub mod spatial_index;
+pub mod frame_scheduler;
 pub mod workspaces;
 pub mod tiling;
+pub mod overview;
+pub mod keybindings;
+pub mod ipc;
+pub mod night_light;
+pub mod recorder;
 
 // Remove if outputs module is fully replaced by output_manager
 // pub mod outputs;
@@ -0,0 +1,113 @@
+// novade-system/src/compositor/night_light/gamma.rs
+
+//! Gamma ramps for the CRTC gamma LUT.
+
+/// Colour temperature of the display's native white point, i.e. no adjustment.
+pub const NEUTRAL_KELVIN: u32 = 6500;
+/// Lowest temperature a ramp is built for.
+pub const MIN_KELVIN: u32 = 1000;
+
+/// Hardware gamma LUT of an output. Meant to be implemented by the DRM backend for each CRTC;
+/// there is no implementation yet.
+pub trait GammaDevice {
+    /// Entries per channel of the LUT, 0 if the CRTC has none.
+    fn gamma_size(&self) -> usize;
+
+    /// Programs the LUT; `ramp` has [`GammaDevice::gamma_size`] entries per channel.
+    fn set_gamma(&mut self, ramp: &GammaRamp) -> Result<(), String>;
+}
+
+/// One gamma LUT: per-channel tables mapping the encoded framebuffer value to the value sent
+/// to the display, in `0..=u16::MAX`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GammaRamp {
+    pub red: Vec<u16>,
+    pub green: Vec<u16>,
+    pub blue: Vec<u16>,
+}
+
+impl GammaRamp {
+    /// Entries per channel.
+    pub fn size(&self) -> usize {
+        self.red.len()
+    }
+
+    /// Parses the wlr-gamma-control layout: `size` native-endian `u16`s per channel, red
+    /// first. Returns `None` if `bytes` has the wrong length.
+    pub fn from_ne_bytes(bytes: &[u8], size: usize) -> Option<Self> {
+        if size == 0 || bytes.len() != size * 3 * 2 {
+            return None;
+        }
+        let values: Vec<u16> = bytes.chunks_exact(2).map(|b| u16::from_ne_bytes([b[0], b[1]])).collect();
+        Some(Self { red: values[..size].to_vec(), green: values[size..2 * size].to_vec(), blue: values[2 * size..].to_vec() })
+    }
+}
+
+/// Relative RGB gains of a black body at `kelvin` against 6500 K, after Tanner Helland's fit
+/// of the CIE 1964 colour matching functions.
+pub fn whitepoint(kelvin: u32) -> [f64; 3] {
+    let rgb = |kelvin: u32| {
+        let t = kelvin.clamp(MIN_KELVIN, NEUTRAL_KELVIN) as f64 / 100.0;
+        // Only the branch for t <= 66 applies below 6600 K, where red is saturated.
+        let green = 99.470_802_586_1 * t.ln() - 161.119_568_166_1;
+        let blue = if t <= 19.0 { 0.0 } else { 138.517_731_223_1 * (t - 10.0).ln() - 305.044_792_730_7 };
+        [255.0, green.clamp(0.0, 255.0), blue.clamp(0.0, 255.0)]
+    };
+    let neutral = rgb(NEUTRAL_KELVIN);
+    let target = rgb(kelvin);
+    [0, 1, 2].map(|c| (target[c] / neutral[c]).clamp(0.0, 1.0))
+}
+
+/// A ramp of `size` entries per channel scaling white to the colour of `kelvin`.
+pub fn gamma_ramp(size: usize, kelvin: u32) -> GammaRamp {
+    let gains = whitepoint(kelvin);
+    let channel = |gain: f64| -> Vec<u16> {
+        (0..size)
+            .map(|i| {
+                let x = if size > 1 { i as f64 / (size - 1) as f64 } else { 1.0 };
+                (x * gain * u16::MAX as f64).round() as u16
+            })
+            .collect()
+    };
+    GammaRamp { red: channel(gains[0]), green: channel(gains[1]), blue: channel(gains[2]) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_neutral_ramp_is_identity() {
+        let ramp = gamma_ramp(256, NEUTRAL_KELVIN);
+        assert_eq!(ramp.size(), 256);
+        for channel in [&ramp.red, &ramp.green, &ramp.blue] {
+            assert_eq!(channel[0], 0);
+            assert_eq!(channel[255], u16::MAX);
+            assert_eq!(channel[51], 13_107);
+        }
+    }
+
+    #[test]
+    fn test_warm_ramp_attenuates_blue_most() {
+        let [r, g, b] = whitepoint(4000);
+        assert_eq!(r, 1.0);
+        assert!((g - 0.81).abs() < 0.01, "green gain {}", g);
+        assert!((b - 0.66).abs() < 0.01, "blue gain {}", b);
+        assert_eq!(whitepoint(1500)[2], 0.0);
+
+        let ramp = gamma_ramp(1024, 3000);
+        assert_eq!(ramp.red[1023], u16::MAX);
+        assert!(ramp.blue[1023] < ramp.green[1023]);
+        assert!(ramp.blue.windows(2).all(|w| w[0] <= w[1]));
+    }
+
+    #[test]
+    fn test_ramp_from_client_bytes() {
+        let values: [u16; 6] = [0, 65535, 0, 32768, 0, 100];
+        let bytes: Vec<u8> = values.iter().flat_map(|v| v.to_ne_bytes()).collect();
+        let ramp = GammaRamp::from_ne_bytes(&bytes, 2).unwrap();
+        assert_eq!(ramp.green, vec![0, 32768]);
+        assert_eq!(ramp.blue, vec![0, 100]);
+        assert!(GammaRamp::from_ne_bytes(&bytes, 3).is_none());
+    }
+}
@@ -0,0 +1,380 @@
+// novade-system/src/compositor/night_light/mod.rs
+//! Night light: a warmer colour temperature at night through the outputs' gamma LUTs.
+//!
+//! The `[night_light]` schedule runs from sunset to sunrise at the configured position, or
+//! between two fixed local times, and fades over `transition_minutes` at either end. The quick
+//! settings toggle and the `set_night_light` action override the schedule until its next
+//! boundary, so turning night light off in the evening lasts until the next morning.
+//!
+//! Clients such as gammastep may take over an output's gamma LUT through wlr-gamma-control
+//! (see [`protocol`]). Night light leaves outputs controlled by a client alone and restores its
+//! own ramp when the control goes away. Switching night light on explicitly revokes client
+//! controls: the user's latest choice wins.
+//!
+//! Nothing programs a gamma LUT yet. Ramps reach the hardware through
+//! [`DrmBackend::apply_gamma`](crate::compositor::backend::drm_backend::DrmBackend::apply_gamma),
+//! but the DRM backend does not set up CRTCs, and the winit backend has no LUT. No output
+//! reports a LUT size, so the schedule runs and its status is reported over IPC, while the
+//! screen keeps its colours and every gamma control fails.
+
+pub mod gamma;
+pub mod protocol;
+pub mod solar;
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use smithay::reexports::calloop::timer::{TimeoutAction, Timer};
+use smithay::reexports::calloop::RegistrationToken;
+use smithay::reexports::wayland_server::DisplayHandle;
+use tracing::{info, warn};
+
+pub use gamma::{gamma_ramp, GammaDevice, GammaRamp, NEUTRAL_KELVIN};
+pub use protocol::GammaControlState;
+
+use self::solar::SolarDay;
+use super::config::{NightLightConfig, NightLightSchedule};
+use super::state::DesktopState;
+
+/// Fastest temperature change for toggles and configuration changes, in kelvin per second.
+const MAX_RATE_KELVIN_PER_SEC: f64 = 2500.0;
+/// Tick interval while the temperature is moving towards its target.
+const ANIMATION_INTERVAL: Duration = Duration::from_millis(50);
+/// Tick interval otherwise; scheduled transitions take minutes.
+const IDLE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Requested night light state, from the quick settings toggle or a key binding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NightLightMode {
+    On,
+    Off,
+    Toggle,
+    /// Drops any override and follows the schedule.
+    Auto,
+}
+
+/// Snapshot reported over IPC.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NightLightStatus {
+    /// Whether night light is on, by schedule or override.
+    pub active: bool,
+    /// Temperature currently applied, in kelvin.
+    pub temperature: u32,
+    /// Temperature being faded to, in kelvin.
+    pub target_temperature: u32,
+    /// The explicit on/off choice overriding the schedule, if any.
+    pub override_active: Option<bool>,
+    /// Outputs whose gamma LUT a client controls.
+    pub gamma_controlled_outputs: Vec<String>,
+}
+
+/// Strength of night light at `minute` of the local day for a night from `start` to `end`
+/// (minutes past midnight; the night may span midnight): 0 during the day, 1 at night, ramping
+/// linearly over `transition` minutes after `start` and before `end`.
+pub fn window_factor(minute: f64, start: f64, end: f64, transition: f64) -> f64 {
+    let length = (end - start).rem_euclid(1440.0);
+    let since_start = (minute - start).rem_euclid(1440.0);
+    if length == 0.0 || since_start >= length {
+        return 0.0;
+    }
+    let transition = transition.min(length / 2.0);
+    if transition <= 0.0 {
+        return 1.0;
+    }
+    (since_start / transition).min((length - since_start) / transition).min(1.0)
+}
+
+/// Strength of night light per the schedule at `now` (Unix seconds) in a time zone
+/// `utc_offset` seconds east of UTC.
+pub fn scheduled_factor(config: &NightLightConfig, now: i64, utc_offset: i64) -> f64 {
+    let local = now + utc_offset;
+    let minute_of = |timestamp: i64| (timestamp + utc_offset).rem_euclid(86_400) as f64 / 60.0;
+    let (start, end) = match (config.schedule, config.latitude, config.longitude) {
+        (NightLightSchedule::SunsetToSunrise, Some(latitude), Some(longitude)) => {
+            match solar::solar_day(local.div_euclid(86_400), latitude, longitude) {
+                SolarDay::Normal { sunrise, sunset } => (minute_of(sunset), minute_of(sunrise)),
+                SolarDay::PolarDay => return 0.0,
+                SolarDay::PolarNight => return 1.0,
+            }
+        }
+        _ => (config.start.minutes() as f64, config.end.minutes() as f64),
+    };
+    window_factor(local.rem_euclid(86_400) as f64 / 60.0, start, end, config.transition_minutes as f64)
+}
+
+/// Temperature for night light strength `factor`, interpolated in mireds so that equal steps
+/// look alike.
+pub fn kelvin_for_factor(factor: f64, night_kelvin: u32) -> u32 {
+    let day = 1e6 / NEUTRAL_KELVIN as f64;
+    let night = 1e6 / night_kelvin.clamp(gamma::MIN_KELVIN, NEUTRAL_KELVIN) as f64;
+    (1e6 / (day + (night - day) * factor.clamp(0.0, 1.0))).round() as u32
+}
+
+/// Offset of the local time zone from UTC at `timestamp`, in seconds.
+fn local_utc_offset(timestamp: i64) -> i64 {
+    let time = timestamp as libc::time_t;
+    // SAFETY: localtime_r only writes to the zeroed `tm` it is given.
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    if unsafe { libc::localtime_r(&time, &mut tm) }.is_null() {
+        return 0;
+    }
+    tm.tm_gmtoff as i64
+}
+
+// ANCHOR: NightLightState
+pub struct NightLightState {
+    /// Explicit on/off choice and whether the schedule said "night" when it was made; the
+    /// override ends once the schedule changes its mind.
+    override_state: Option<(bool, bool)>,
+    /// Whether night light is on, by schedule or override.
+    active: bool,
+    /// Applied temperature; fractional so slow fades do not stall on rounding.
+    current_kelvin: f64,
+    target_kelvin: u32,
+    last_tick: Option<Instant>,
+    /// Gamma LUT sizes reported by the backend, by output name.
+    gamma_sizes: HashMap<String, usize>,
+    /// Ramps the backend has yet to program, by output name.
+    pending: HashMap<String, GammaRamp>,
+    timer: Option<RegistrationToken>,
+    pub protocol: GammaControlState,
+}
+
+impl NightLightState {
+    /// Creates the state and advertises the wlr-gamma-control global.
+    pub fn new(display: &DisplayHandle) -> Self {
+        Self {
+            override_state: None,
+            active: false,
+            current_kelvin: NEUTRAL_KELVIN as f64,
+            target_kelvin: NEUTRAL_KELVIN,
+            last_tick: None,
+            gamma_sizes: HashMap::new(),
+            pending: HashMap::new(),
+            timer: None,
+            protocol: GammaControlState::new(display),
+        }
+    }
+
+    /// Whether night light is on given what the schedule says.
+    fn is_on(&self, scheduled_night: bool) -> bool {
+        self.override_state.map_or(scheduled_night, |(on, _)| on)
+    }
+
+    /// Moves the applied temperature towards the target by at most `elapsed` worth of change.
+    /// Returns `true` if the rounded temperature changed.
+    fn advance(&mut self, elapsed: Duration) -> bool {
+        let before = self.current_kelvin.round();
+        let target = self.target_kelvin as f64;
+        let step = MAX_RATE_KELVIN_PER_SEC * elapsed.as_secs_f64();
+        self.current_kelvin = if (target - self.current_kelvin).abs() <= step {
+            target
+        } else {
+            self.current_kelvin + step.copysign(target - self.current_kelvin)
+        };
+        self.current_kelvin.round() != before
+    }
+
+    pub fn temperature(&self) -> u32 {
+        self.current_kelvin.round() as u32
+    }
+
+    pub fn gamma_size(&self, output_name: &str) -> Option<usize> {
+        self.gamma_sizes.get(output_name).copied().filter(|size| *size > 0)
+    }
+
+    /// Takes the ramp waiting to be programmed for `output_name`, if any.
+    pub fn take_gamma_update(&mut self, output_name: &str) -> Option<GammaRamp> {
+        self.pending.remove(output_name)
+    }
+}
+// ANCHOR_END: NightLightState
+
+// ANCHOR: DesktopStateNightLightIntegration
+impl DesktopState {
+    /// Registers the night light timer. The first tick runs right away.
+    pub fn start_night_light(&mut self) {
+        if let Some(token) = self.night_light.timer.take() {
+            self.event_loop_handle.remove(token);
+        }
+        let token = self.event_loop_handle.insert_source(Timer::immediate(), |_, _, state: &mut DesktopState| {
+            TimeoutAction::ToDuration(state.tick_night_light())
+        });
+        match token {
+            Ok(token) => self.night_light.timer = Some(token),
+            Err(e) => warn!("Night light disabled, failed to register its timer: {}", e),
+        }
+    }
+
+    /// Re-evaluates the schedule, moves the temperature on and queues changed ramps. Returns
+    /// the delay until the next tick.
+    fn tick_night_light(&mut self) -> Duration {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() as i64);
+        let config = &self.config.night_light;
+        let scheduled = if config.enabled { scheduled_factor(config, now, local_utc_offset(now)) } else { 0.0 };
+        let night_light = &mut self.night_light;
+        if let Some((_, night_when_set)) = night_light.override_state {
+            if (scheduled > 0.0) != night_when_set {
+                info!("Night light schedule reached its next boundary; dropping the override.");
+                night_light.override_state = None;
+            }
+        }
+        let factor = match night_light.override_state {
+            Some((true, _)) => 1.0,
+            Some((false, _)) => 0.0,
+            None => scheduled,
+        };
+        night_light.active = factor > 0.0;
+        night_light.target_kelvin = kelvin_for_factor(factor, config.temperature);
+
+        let tick = Instant::now();
+        let elapsed = night_light.last_tick.map_or(Duration::ZERO, |last| tick - last);
+        night_light.last_tick = Some(tick);
+        if night_light.advance(elapsed) {
+            self.queue_night_light_ramps();
+        }
+        if self.night_light.temperature() == self.night_light.target_kelvin {
+            IDLE_INTERVAL
+        } else {
+            ANIMATION_INTERVAL
+        }
+    }
+
+    /// Applies an explicit on/off choice; `Auto` returns to the schedule.
+    pub fn set_night_light(&mut self, mode: NightLightMode) {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() as i64);
+        let config = &self.config.night_light;
+        let scheduled_night = config.enabled && scheduled_factor(config, now, local_utc_offset(now)) > 0.0;
+        let on = match mode {
+            NightLightMode::On => true,
+            NightLightMode::Off => false,
+            NightLightMode::Toggle => !self.night_light.is_on(scheduled_night),
+            NightLightMode::Auto => {
+                self.night_light.override_state = None;
+                self.start_night_light();
+                return;
+            }
+        };
+        info!("Night light switched {}.", if on { "on" } else { "off" });
+        self.night_light.override_state = Some((on, scheduled_night));
+        self.night_light.active = on;
+        if on {
+            self.revoke_gamma_controls();
+        }
+        // Restart the timer so the fade begins now instead of at the next idle tick.
+        self.night_light.last_tick = None;
+        self.start_night_light();
+    }
+
+    /// Tells the backend about the gamma LUT size of `output_name` (0 if it has none).
+    pub fn set_gamma_size(&mut self, output_name: &str, size: usize) {
+        if self.night_light.gamma_sizes.insert(output_name.to_string(), size) != Some(size) {
+            self.queue_gamma_ramp(output_name);
+        }
+    }
+
+    fn queue_night_light_ramps(&mut self) {
+        let names: Vec<String> = self.night_light.gamma_sizes.keys().cloned().collect();
+        for name in names.iter().filter(|name| !self.night_light.protocol.is_controlled(name)) {
+            self.queue_gamma_ramp(name);
+        }
+    }
+
+    /// Queues the ramp `output_name` should show: the controlling client's, else night light's.
+    pub(crate) fn queue_gamma_ramp(&mut self, output_name: &str) {
+        let Some(size) = self.night_light.gamma_size(output_name) else {
+            return;
+        };
+        let ramp = match self.night_light.protocol.client_ramp(output_name) {
+            Some(ramp) if ramp.size() == size => ramp.clone(),
+            _ => gamma_ramp(size, self.night_light.temperature()),
+        };
+        self.night_light.pending.insert(output_name.to_string(), ramp);
+        self.schedule_redraw_all();
+    }
+
+    pub fn night_light_status(&self) -> NightLightStatus {
+        NightLightStatus {
+            active: self.night_light.active,
+            temperature: self.night_light.temperature(),
+            target_temperature: self.night_light.target_kelvin,
+            override_active: self.night_light.override_state.map(|(on, _)| on),
+            gamma_controlled_outputs: self.night_light.protocol.controlled_outputs(),
+        }
+    }
+}
+// ANCHOR_END: DesktopStateNightLightIntegration
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compositor::config::TimeOfDay;
+
+    fn manual(start: (u8, u8), end: (u8, u8), transition_minutes: u32) -> NightLightConfig {
+        NightLightConfig {
+            enabled: true,
+            schedule: NightLightSchedule::Manual,
+            start: TimeOfDay { hour: start.0, minute: start.1 },
+            end: TimeOfDay { hour: end.0, minute: end.1 },
+            transition_minutes,
+            ..NightLightConfig::default()
+        }
+    }
+
+    /// Unix time of `hour:minute` UTC on 2024-06-21.
+    fn at(hour: i64, minute: i64) -> i64 {
+        19_895 * 86_400 + hour * 3600 + minute * 60
+    }
+
+    #[test]
+    fn test_window_spanning_midnight() {
+        // 21:00 to 07:00 with half-hour fades.
+        let (start, end) = (21.0 * 60.0, 7.0 * 60.0);
+        assert_eq!(window_factor(12.0 * 60.0, start, end, 30.0), 0.0);
+        assert_eq!(window_factor(21.0 * 60.0 + 15.0, start, end, 30.0), 0.5);
+        assert_eq!(window_factor(2.0 * 60.0, start, end, 30.0), 1.0);
+        assert_eq!(window_factor(6.0 * 60.0 + 45.0, start, end, 30.0), 0.5);
+        assert_eq!(window_factor(7.0 * 60.0, start, end, 30.0), 0.0);
+        // Transitions never exceed half the night.
+        assert_eq!(window_factor(60.0, 0.0, 120.0, 90.0), 1.0);
+        assert_eq!(window_factor(60.0, 60.0, 60.0, 30.0), 0.0);
+    }
+
+    #[test]
+    fn test_manual_schedule_uses_local_time() {
+        let config = manual((22, 0), (6, 0), 0);
+        assert_eq!(scheduled_factor(&config, at(21, 30), 0), 0.0);
+        // 21:30 UTC is 23:30 at UTC+2.
+        assert_eq!(scheduled_factor(&config, at(21, 30), 2 * 3600), 1.0);
+    }
+
+    #[test]
+    fn test_sun_schedule() {
+        let config = NightLightConfig {
+            schedule: NightLightSchedule::SunsetToSunrise,
+            latitude: Some(52.52),
+            longitude: Some(13.405),
+            transition_minutes: 30,
+            ..manual((0, 0), (0, 0), 30)
+        };
+        let cest = 2 * 3600;
+        // Berlin sunset is at 19:33 UTC on the solstice.
+        assert_eq!(scheduled_factor(&config, at(12, 0), cest), 0.0);
+        assert_eq!(scheduled_factor(&config, at(19, 30), cest), 0.0);
+        assert!((scheduled_factor(&config, at(19, 48), cest) - 0.5).abs() < 0.05);
+        assert_eq!(scheduled_factor(&config, at(23, 0), cest), 1.0);
+
+        let tromso = NightLightConfig { latitude: Some(69.65), longitude: Some(18.96), ..config };
+        assert_eq!(scheduled_factor(&tromso, at(23, 0), cest), 0.0);
+    }
+
+    #[test]
+    fn test_kelvin_interpolates_in_mireds() {
+        assert_eq!(kelvin_for_factor(0.0, 3000), NEUTRAL_KELVIN);
+        assert_eq!(kelvin_for_factor(1.0, 3000), 3000);
+        // Halfway between 153.8 and 333.3 mireds.
+        assert_eq!(kelvin_for_factor(0.5, 3000), 4105);
+    }
+}
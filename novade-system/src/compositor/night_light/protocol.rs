@@ -0,0 +1,140 @@
+// novade-system/src/compositor/night_light/protocol.rs
+
+//! Server side of `wlr-gamma-control-unstable-v1`.
+//!
+//! A client gets exclusive control of one output's gamma LUT: a second control for the same
+//! output fails right away, as does a control for an output without a hardware LUT, which is
+//! every output until the DRM backend drives CRTCs (see [`super`]). The client's ramp replaces
+//! night light's on that output until the control is destroyed or revoked (see
+//! [`DesktopState::revoke_gamma_controls`]).
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::os::unix::fs::FileExt;
+
+use smithay::output::Output;
+use smithay::reexports::wayland_protocols_wlr::gamma_control::v1::server::{
+    zwlr_gamma_control_manager_v1::{self, ZwlrGammaControlManagerV1},
+    zwlr_gamma_control_v1::{self, ZwlrGammaControlV1},
+};
+use smithay::reexports::wayland_server::backend::{ClientId, GlobalId};
+use smithay::reexports::wayland_server::{Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource};
+use tracing::{debug, info};
+
+use super::GammaRamp;
+use crate::compositor::state::DesktopState;
+
+const VERSION: u32 = 1;
+
+// ANCHOR: GammaControlState
+/// Client gamma controls, by output name.
+pub struct GammaControlState {
+    _global: GlobalId,
+    controls: HashMap<String, GammaControl>,
+}
+
+struct GammaControl {
+    resource: ZwlrGammaControlV1,
+    /// The last ramp the client set; `None` until its first `set_gamma`.
+    ramp: Option<GammaRamp>,
+}
+
+impl GammaControlState {
+    pub fn new(display: &DisplayHandle) -> Self {
+        let global = display.create_global::<DesktopState, ZwlrGammaControlManagerV1, ()>(VERSION, ());
+        Self { _global: global, controls: HashMap::new() }
+    }
+
+    /// Whether a client has taken over `output_name`'s gamma LUT.
+    pub fn is_controlled(&self, output_name: &str) -> bool {
+        self.controls.contains_key(output_name)
+    }
+
+    /// The ramp the controlling client set for `output_name`.
+    pub fn client_ramp(&self, output_name: &str) -> Option<&GammaRamp> {
+        self.controls.get(output_name).and_then(|control| control.ramp.as_ref())
+    }
+
+    pub fn controlled_outputs(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.controls.keys().cloned().collect();
+        names.sort();
+        names
+    }
+}
+// ANCHOR_END: GammaControlState
+
+impl DesktopState {
+    /// Ends every client gamma control with `failed` and puts night light back in charge.
+    pub fn revoke_gamma_controls(&mut self) {
+        let controls: Vec<(String, GammaControl)> = self.night_light.protocol.controls.drain().collect();
+        for (output_name, control) in controls {
+            info!("Revoking gamma control of {} held by a client.", output_name);
+            control.resource.failed();
+            self.queue_gamma_ramp(&output_name);
+        }
+    }
+}
+
+impl GlobalDispatch<ZwlrGammaControlManagerV1, ()> for DesktopState {
+    fn bind(_state: &mut Self, _dh: &DisplayHandle, _client: &Client, resource: New<ZwlrGammaControlManagerV1>, _data: &(), data_init: &mut DataInit<'_, Self>) {
+        data_init.init(resource, ());
+    }
+}
+
+impl Dispatch<ZwlrGammaControlManagerV1, ()> for DesktopState {
+    fn request(state: &mut Self, _client: &Client, _manager: &ZwlrGammaControlManagerV1, request: zwlr_gamma_control_manager_v1::Request, _data: &(), _dh: &DisplayHandle, data_init: &mut DataInit<'_, Self>) {
+        match request {
+            zwlr_gamma_control_manager_v1::Request::GetGammaControl { id, output } => {
+                let output_name = Output::from_resource(&output).map(|o| o.name()).unwrap_or_default();
+                let size = state.night_light.gamma_size(&output_name);
+                let taken = state.night_light.protocol.is_controlled(&output_name);
+                let Some(size) = size.filter(|_| !taken) else {
+                    debug!("Refusing gamma control of '{}': {}", output_name, if taken { "already controlled" } else { "no gamma LUT" });
+                    // An empty name marks a control that never took effect.
+                    data_init.init(id, String::new()).failed();
+                    return;
+                };
+                let resource = data_init.init(id, output_name.clone());
+                resource.gamma_size(size as u32);
+                state.night_light.protocol.controls.insert(output_name, GammaControl { resource, ramp: None });
+            }
+            zwlr_gamma_control_manager_v1::Request::Destroy => {}
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<ZwlrGammaControlV1, String> for DesktopState {
+    fn request(state: &mut Self, _client: &Client, resource: &ZwlrGammaControlV1, request: zwlr_gamma_control_v1::Request, output_name: &String, _dh: &DisplayHandle, _data_init: &mut DataInit<'_, Self>) {
+        match request {
+            zwlr_gamma_control_v1::Request::SetGamma { fd } => {
+                // Failed or revoked controls are inert.
+                let is_active = state.night_light.protocol.controls.get(output_name).is_some_and(|control| control.resource == *resource);
+                let Some(size) = state.night_light.gamma_size(output_name).filter(|_| is_active) else {
+                    return;
+                };
+                let mut bytes = vec![0u8; size * 3 * 2];
+                let ramp = File::from(fd).read_exact_at(&mut bytes, 0).ok().and_then(|_| GammaRamp::from_ne_bytes(&bytes, size));
+                let Some(ramp) = ramp else {
+                    resource.post_error(zwlr_gamma_control_v1::Error::InvalidGamma, "gamma table has the wrong size");
+                    return;
+                };
+                if let Some(control) = state.night_light.protocol.controls.get_mut(output_name) {
+                    control.ramp = Some(ramp);
+                }
+                state.queue_gamma_ramp(output_name);
+            }
+            zwlr_gamma_control_v1::Request::Destroy => {}
+            _ => {}
+        }
+    }
+
+    fn destroyed(state: &mut Self, _client: ClientId, resource: &ZwlrGammaControlV1, output_name: &String) {
+        let controls = &mut state.night_light.protocol.controls;
+        if controls.get(output_name).is_some_and(|control| control.resource == *resource) {
+            controls.remove(output_name);
+            // Restores night light's ramp.
+            state.queue_gamma_ramp(output_name);
+        }
+    }
+}
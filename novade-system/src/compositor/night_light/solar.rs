@@ -0,0 +1,93 @@
+// novade-system/src/compositor/night_light/solar.rs
+
+//! Sunrise and sunset times from the NOAA sunrise equation.
+//!
+//! Accurate to a minute or two between the polar circles, which is plenty for scheduling a
+//! half-hour colour transition.
+
+/// Julian date of the Unix epoch.
+const UNIX_EPOCH_JD: f64 = 2_440_587.5;
+/// Julian date of J2000.0.
+const J2000: f64 = 2_451_545.0;
+/// Solar altitude at sunrise and sunset, accounting for refraction and the solar disc.
+const HORIZON_ALTITUDE_DEG: f64 = -0.833;
+/// Obliquity of the ecliptic.
+const OBLIQUITY_DEG: f64 = 23.4397;
+
+/// Sun times of one day.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SolarDay {
+    /// Sunrise and sunset as Unix timestamps. Sunset may fall on the next UTC day.
+    Normal { sunrise: i64, sunset: i64 },
+    /// The sun does not set.
+    PolarDay,
+    /// The sun does not rise.
+    PolarNight,
+}
+
+/// Sun times of the day `day` (days since the Unix epoch) at the given position in degrees,
+/// north and east positive.
+pub fn solar_day(day: i64, latitude: f64, longitude: f64) -> SolarDay {
+    // Days since J2000 at noon of `day`, shifted to mean solar noon at `longitude`.
+    let mean_noon = (day as f64 + UNIX_EPOCH_JD + 0.5 - J2000) - longitude / 360.0;
+    let anomaly = (357.5291 + 0.985_600_28 * mean_noon).rem_euclid(360.0).to_radians();
+    let center = 1.9148 * anomaly.sin() + 0.02 * (2.0 * anomaly).sin() + 0.0003 * (3.0 * anomaly).sin();
+    let ecliptic_longitude = (anomaly.to_degrees() + center + 180.0 + 102.9372).rem_euclid(360.0).to_radians();
+    let transit = J2000 + mean_noon + 0.0053 * anomaly.sin() - 0.0069 * (2.0 * ecliptic_longitude).sin();
+
+    let declination = (ecliptic_longitude.sin() * OBLIQUITY_DEG.to_radians().sin()).asin();
+    let latitude = latitude.to_radians();
+    let cos_hour_angle = (HORIZON_ALTITUDE_DEG.to_radians().sin() - latitude.sin() * declination.sin())
+        / (latitude.cos() * declination.cos());
+    if cos_hour_angle < -1.0 {
+        return SolarDay::PolarDay;
+    }
+    if cos_hour_angle > 1.0 {
+        return SolarDay::PolarNight;
+    }
+    let half_day = cos_hour_angle.acos().to_degrees() / 360.0;
+    let to_unix = |jd: f64| ((jd - UNIX_EPOCH_JD) * 86_400.0).round() as i64;
+    SolarDay::Normal { sunrise: to_unix(transit - half_day), sunset: to_unix(transit + half_day) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Days since the Unix epoch of 2024-06-21 and 2024-12-21.
+    const SUMMER_SOLSTICE: i64 = 19_895;
+    const WINTER_SOLSTICE: i64 = 20_078;
+
+    fn utc_hm(timestamp: i64) -> (i64, i64) {
+        let seconds = timestamp.rem_euclid(86_400);
+        (seconds / 3600, seconds % 3600 / 60)
+    }
+
+    #[test]
+    fn test_berlin_midsummer() {
+        let SolarDay::Normal { sunrise, sunset } = solar_day(SUMMER_SOLSTICE, 52.52, 13.405) else {
+            panic!("expected sunrise and sunset");
+        };
+        // 04:43 and 21:33 local summer time.
+        assert_eq!(utc_hm(sunrise), (2, 43));
+        assert_eq!(utc_hm(sunset), (19, 33));
+    }
+
+    #[test]
+    fn test_sunset_after_utc_midnight_west_of_greenwich() {
+        // San Francisco at the March equinox: 07:12 and 19:21 PDT.
+        let SolarDay::Normal { sunrise, sunset } = solar_day(19_802, 37.77, -122.42) else {
+            panic!("expected sunrise and sunset");
+        };
+        assert_eq!(utc_hm(sunrise), (14, 12));
+        assert_eq!(utc_hm(sunset), (2, 21));
+        assert!(sunset > sunrise);
+    }
+
+    #[test]
+    fn test_polar_day_and_night() {
+        // Tromsø.
+        assert_eq!(solar_day(SUMMER_SOLSTICE, 69.65, 18.96), SolarDay::PolarDay);
+        assert_eq!(solar_day(WINTER_SOLSTICE, 69.65, 18.96), SolarDay::PolarNight);
+    }
+}
@@ -0,0 +1,1154 @@
+// ANCHOR: OverviewModule
+//! Compositor-rendered overview ("exposé") mode.
+//!
+//! While the overview is shown, every window of the active workspace on each output is
+//! scaled into a non-overlapping grid and a strip of workspace thumbnails is drawn along the
+//! top edge of the output. Typing filters the grid by window title, the arrow keys move the
+//! selection, and windows can be dragged onto a workspace thumbnail to move them there.
+//!
+//! The layout and interaction logic in this module is independent of the renderer: the render
+//! path asks [`OverviewState::draw_ops`] what to draw on an output at a given point in time,
+//! built from the geometry [`OverviewState::render_items`] computes for each element, and draws
+//! it instead of the workspace's windows. Entering and leaving interpolate between a window's
+//! real geometry and its grid slot.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use smithay::input::keyboard::{Keysym, ModifiersState};
+use smithay::utils::{Logical, Point, Rectangle, Size};
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+use xkbcommon::xkb;
+
+use crate::compositor::shell::xdg_shell::types::DomainWindowIdentifier;
+use crate::compositor::state::DesktopState;
+
+/// Default duration of the enter/leave transition.
+pub const DEFAULT_OVERVIEW_TRANSITION_MS: u64 = 250;
+/// Spacing in logical pixels between grid cells and around the grid.
+pub const OVERVIEW_GRID_SPACING: i32 = 32;
+/// Fraction of the output height used by the workspace strip.
+pub const OVERVIEW_STRIP_HEIGHT_FACTOR: f32 = 0.15;
+/// Width in logical pixels of the outline around the selected window.
+pub const OVERVIEW_SELECTION_OUTLINE: i32 = 4;
+
+const BACKDROP_COLOR: [f32; 3] = [0.0, 0.0, 0.0];
+const THUMBNAIL_COLOR: [f32; 4] = [0.25, 0.25, 0.3, 0.9];
+const ACTIVE_THUMBNAIL_COLOR: [f32; 4] = [0.35, 0.45, 0.7, 0.9];
+const DROP_TARGET_COLOR: [f32; 4] = [0.45, 0.65, 1.0, 0.9];
+const SELECTION_COLOR: [f32; 4] = [0.45, 0.65, 1.0, 1.0];
+
+// ANCHOR: OverviewInputTypes
+/// A window as seen by the overview when it is entered.
+#[derive(Debug, Clone)]
+pub struct OverviewWindowEntry {
+    pub id: DomainWindowIdentifier,
+    pub title: String,
+    pub app_id: Option<String>,
+    /// The window's real geometry in global logical coordinates.
+    pub geometry: Rectangle<i32, Logical>,
+    pub workspace_id: Uuid,
+}
+
+/// A workspace as seen by the overview when it is entered.
+#[derive(Debug, Clone)]
+pub struct OverviewWorkspaceEntry {
+    pub id: Uuid,
+    pub name: String,
+    pub active: bool,
+}
+
+/// Everything the overview needs to know about one output.
+#[derive(Debug, Clone)]
+pub struct OverviewOutputInput {
+    pub output_name: String,
+    /// Output geometry in global logical coordinates.
+    pub area: Rectangle<i32, Logical>,
+    pub workspaces: Vec<OverviewWorkspaceEntry>,
+    /// Windows of the output's active workspace.
+    pub windows: Vec<OverviewWindowEntry>,
+}
+// ANCHOR_END: OverviewInputTypes
+
+// ANCHOR: OverviewLayoutTypes
+/// The place a window occupies in the overview grid.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OverviewSlot {
+    pub window_id: DomainWindowIdentifier,
+    /// Real window geometry (start of the enter transition).
+    pub source: Rectangle<i32, Logical>,
+    /// Scaled geometry inside the grid (end of the enter transition).
+    pub target: Rectangle<i32, Logical>,
+    pub scale: f64,
+}
+
+/// A workspace thumbnail in the strip at the top of an output.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorkspaceStripSlot {
+    pub workspace_id: Uuid,
+    pub name: String,
+    pub active: bool,
+    pub geometry: Rectangle<i32, Logical>,
+}
+
+/// Computed overview layout for one output.
+#[derive(Debug, Clone)]
+pub struct OverviewOutputLayout {
+    pub output_name: String,
+    pub area: Rectangle<i32, Logical>,
+    pub workspace_strip: Vec<WorkspaceStripSlot>,
+    pub window_slots: Vec<OverviewSlot>,
+    /// All windows of the output, including those hidden by the current filter.
+    windows: Vec<OverviewWindowEntry>,
+}
+// ANCHOR_END: OverviewLayoutTypes
+
+// ANCHOR: OverviewLayoutAlgorithms
+/// Calculates a non-overlapping grid for `windows` inside `area`.
+///
+/// Windows keep their aspect ratio and are never scaled up. The column count is chosen so
+/// that the resulting scale factor is as large as possible; windows are ordered by their
+/// original position (top-to-bottom, left-to-right) so the grid roughly mirrors the desktop.
+pub fn calculate_overview_grid(
+    windows: &[OverviewWindowEntry],
+    area: Rectangle<i32, Logical>,
+    spacing: i32,
+) -> Vec<OverviewSlot> {
+    let count = windows.len();
+    if count == 0 || area.size.w <= 0 || area.size.h <= 0 {
+        return Vec::new();
+    }
+
+    let mut ordered: Vec<&OverviewWindowEntry> = windows.iter().collect();
+    ordered.sort_by_key(|w| (w.geometry.loc.y, w.geometry.loc.x));
+
+    // Pick the column count that maximizes the smallest per-window scale.
+    let mut best_cols = 1;
+    let mut best_scale = f64::MIN;
+    for cols in 1..=count {
+        let rows = (count + cols - 1) / cols;
+        let (cell_w, cell_h) = cell_size(area.size, cols, rows, spacing);
+        if cell_w <= 0 || cell_h <= 0 {
+            continue;
+        }
+        let min_scale = ordered
+            .iter()
+            .map(|w| fit_scale(w.geometry.size, cell_w, cell_h))
+            .fold(f64::MAX, f64::min);
+        if min_scale > best_scale {
+            best_scale = min_scale;
+            best_cols = cols;
+        }
+    }
+
+    let cols = best_cols;
+    let rows = (count + cols - 1) / cols;
+    let (cell_w, cell_h) = cell_size(area.size, cols, rows, spacing);
+    let cell_w = cell_w.max(1);
+    let cell_h = cell_h.max(1);
+
+    ordered
+        .iter()
+        .enumerate()
+        .map(|(index, window)| {
+            let row = index / cols;
+            let col = index % cols;
+            // Center an incomplete last row horizontally.
+            let items_in_row = if row == rows - 1 { count - row * cols } else { cols };
+            let row_offset = ((cols - items_in_row) as i32 * (cell_w + spacing)) / 2;
+
+            let cell_x = area.loc.x + spacing + row_offset + col as i32 * (cell_w + spacing);
+            let cell_y = area.loc.y + spacing + row as i32 * (cell_h + spacing);
+
+            let scale = fit_scale(window.geometry.size, cell_w, cell_h);
+            let w = ((window.geometry.size.w.max(1) as f64) * scale).round().max(1.0) as i32;
+            let h = ((window.geometry.size.h.max(1) as f64) * scale).round().max(1.0) as i32;
+            let loc = Point::from((cell_x + (cell_w - w) / 2, cell_y + (cell_h - h) / 2));
+
+            OverviewSlot {
+                window_id: window.id,
+                source: window.geometry,
+                target: Rectangle::from_loc_and_size(loc, (w, h)),
+                scale,
+            }
+        })
+        .collect()
+}
+
+/// Lays out workspace thumbnails side by side, centered in `strip_area`.
+///
+/// Each thumbnail keeps the aspect ratio of `output_size`.
+pub fn calculate_workspace_strip(
+    workspaces: &[OverviewWorkspaceEntry],
+    strip_area: Rectangle<i32, Logical>,
+    output_size: Size<i32, Logical>,
+    spacing: i32,
+) -> Vec<WorkspaceStripSlot> {
+    let count = workspaces.len() as i32;
+    if count == 0 || strip_area.size.h <= spacing * 2 || output_size.h <= 0 {
+        return Vec::new();
+    }
+
+    let thumb_h = strip_area.size.h - spacing * 2;
+    let aspect = output_size.w as f64 / output_size.h as f64;
+    let mut thumb_w = (thumb_h as f64 * aspect).round() as i32;
+    let max_w = (strip_area.size.w - spacing * (count + 1)) / count;
+    let thumb_h = if thumb_w > max_w && max_w > 0 {
+        thumb_w = max_w;
+        (thumb_w as f64 / aspect).round() as i32
+    } else {
+        thumb_h
+    };
+
+    let total_w = count * thumb_w + (count - 1) * spacing;
+    let start_x = strip_area.loc.x + (strip_area.size.w - total_w) / 2;
+    let y = strip_area.loc.y + (strip_area.size.h - thumb_h) / 2;
+
+    workspaces
+        .iter()
+        .enumerate()
+        .map(|(i, ws)| WorkspaceStripSlot {
+            workspace_id: ws.id,
+            name: ws.name.clone(),
+            active: ws.active,
+            geometry: Rectangle::from_loc_and_size(
+                (start_x + i as i32 * (thumb_w + spacing), y),
+                (thumb_w, thumb_h),
+            ),
+        })
+        .collect()
+}
+
+fn cell_size(area: Size<i32, Logical>, cols: usize, rows: usize, spacing: i32) -> (i32, i32) {
+    let cols = cols as i32;
+    let rows = rows as i32;
+    (
+        (area.w - spacing * (cols + 1)) / cols,
+        (area.h - spacing * (rows + 1)) / rows,
+    )
+}
+
+fn fit_scale(size: Size<i32, Logical>, cell_w: i32, cell_h: i32) -> f64 {
+    let w = size.w.max(1) as f64;
+    let h = size.h.max(1) as f64;
+    (cell_w as f64 / w).min(cell_h as f64 / h).min(1.0)
+}
+
+fn matches_filter(window: &OverviewWindowEntry, filter: &str) -> bool {
+    if filter.is_empty() {
+        return true;
+    }
+    let needle = filter.to_lowercase();
+    window.title.to_lowercase().contains(&needle)
+        || window.app_id.as_deref().map_or(false, |a| a.to_lowercase().contains(&needle))
+}
+
+fn lerp_rect(from: Rectangle<i32, Logical>, to: Rectangle<i32, Logical>, t: f64) -> Rectangle<i32, Logical> {
+    let lerp = |a: i32, b: i32| (a as f64 + (b - a) as f64 * t).round() as i32;
+    Rectangle::from_loc_and_size(
+        (lerp(from.loc.x, to.loc.x), lerp(from.loc.y, to.loc.y)),
+        (lerp(from.size.w, to.size.w), lerp(from.size.h, to.size.h)),
+    )
+}
+
+fn rect_center(rect: &Rectangle<i32, Logical>) -> (f64, f64) {
+    (
+        rect.loc.x as f64 + rect.size.w as f64 / 2.0,
+        rect.loc.y as f64 + rect.size.h as f64 / 2.0,
+    )
+}
+
+fn rect_contains(rect: &Rectangle<i32, Logical>, pos: Point<f64, Logical>) -> bool {
+    pos.x >= rect.loc.x as f64
+        && pos.y >= rect.loc.y as f64
+        && pos.x < (rect.loc.x + rect.size.w) as f64
+        && pos.y < (rect.loc.y + rect.size.h) as f64
+}
+
+fn ease_out_cubic(t: f64) -> f64 {
+    1.0 - (1.0 - t).powi(3)
+}
+// ANCHOR_END: OverviewLayoutAlgorithms
+
+// ANCHOR: OverviewStateTypes
+/// Lifecycle of the overview, including the animated transitions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverviewPhase {
+    Hidden,
+    Entering { start: Instant },
+    Shown,
+    Leaving { start: Instant },
+}
+
+/// Keys the overview reacts to, decoupled from xkb keysyms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverviewKey {
+    Escape,
+    Enter,
+    Left,
+    Right,
+    Up,
+    Down,
+    Backspace,
+    Char(char),
+}
+
+/// Result of feeding input into the overview. Applied to `DesktopState` by the caller.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OverviewAction {
+    /// Nothing to do.
+    None,
+    /// Internal state changed, a redraw is needed.
+    Redraw,
+    /// Start leaving the overview without changing focus.
+    Leave,
+    /// Leave the overview and focus this window.
+    ActivateWindow(DomainWindowIdentifier),
+    /// Leave the overview and make this workspace active on the output.
+    SwitchWorkspace { output_name: String, workspace_id: Uuid },
+    /// Move a window to another workspace; the overview stays open.
+    MoveWindowToWorkspace { window_id: DomainWindowIdentifier, workspace_id: Uuid },
+}
+
+/// A window being dragged in the overview.
+#[derive(Debug, Clone, Copy)]
+pub struct OverviewDrag {
+    pub window_id: DomainWindowIdentifier,
+    /// Pointer offset from the dragged slot's origin.
+    pub grab_offset: Point<f64, Logical>,
+    pub pointer: Point<f64, Logical>,
+}
+
+/// What the render path should draw for the overview at a given time.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OverviewRenderItem {
+    /// Dimmed backdrop behind the overview.
+    Backdrop { output_name: String, alpha: f32 },
+    /// A workspace thumbnail in the strip.
+    WorkspaceThumbnail { output_name: String, workspace_id: Uuid, geometry: Rectangle<i32, Logical>, active: bool, drop_target: bool },
+    /// A window surface drawn at `geometry` (global logical coordinates).
+    Window { window_id: DomainWindowIdentifier, geometry: Rectangle<i32, Logical>, selected: bool },
+}
+
+/// One drawing step of the overview on an output, in output-local logical coordinates.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OverviewDrawOp {
+    /// A solid rectangle: the backdrop, a workspace thumbnail or a selection outline.
+    Rect { geometry: Rectangle<i32, Logical>, color: [f32; 4] },
+    /// The window's surface tree scaled into `geometry`.
+    Window { window_id: DomainWindowIdentifier, geometry: Rectangle<i32, Logical> },
+}
+// ANCHOR_END: OverviewStateTypes
+
+// ANCHOR: OverviewState
+/// Runtime state of the overview mode.
+#[derive(Debug)]
+pub struct OverviewState {
+    phase: OverviewPhase,
+    transition: Duration,
+    filter: String,
+    outputs: HashMap<String, OverviewOutputLayout>,
+    selected: Option<DomainWindowIdentifier>,
+    drag: Option<OverviewDrag>,
+}
+
+impl Default for OverviewState {
+    fn default() -> Self {
+        Self::new(Duration::from_millis(DEFAULT_OVERVIEW_TRANSITION_MS))
+    }
+}
+
+impl OverviewState {
+    pub fn new(transition: Duration) -> Self {
+        Self {
+            phase: OverviewPhase::Hidden,
+            transition,
+            filter: String::new(),
+            outputs: HashMap::new(),
+            selected: None,
+            drag: None,
+        }
+    }
+
+    /// Sets the enter/leave transition duration. A zero duration disables the animation.
+    pub fn set_transition_duration(&mut self, transition: Duration) {
+        self.transition = transition;
+    }
+
+    pub fn phase(&self) -> OverviewPhase {
+        self.phase
+    }
+
+    /// Returns `true` while the overview is visible, including the transitions.
+    pub fn is_active(&self) -> bool {
+        self.phase != OverviewPhase::Hidden
+    }
+
+    /// Returns `true` if input should go to the overview (i.e., it is not leaving).
+    pub fn accepts_input(&self) -> bool {
+        matches!(self.phase, OverviewPhase::Entering { .. } | OverviewPhase::Shown)
+    }
+
+    pub fn filter(&self) -> &str {
+        &self.filter
+    }
+
+    pub fn selected(&self) -> Option<DomainWindowIdentifier> {
+        self.selected
+    }
+
+    pub fn output_layout(&self, output_name: &str) -> Option<&OverviewOutputLayout> {
+        self.outputs.get(output_name)
+    }
+
+    /// Enters the overview with a fresh snapshot of outputs, workspaces and windows.
+    pub fn enter(&mut self, now: Instant, outputs: Vec<OverviewOutputInput>, focused: Option<DomainWindowIdentifier>) {
+        self.filter.clear();
+        self.drag = None;
+        self.outputs = outputs
+            .into_iter()
+            .map(|input| {
+                let name = input.output_name.clone();
+                (name, Self::layout_output(input))
+            })
+            .collect();
+        self.relayout();
+        self.selected = focused
+            .filter(|id| self.visible_slots().any(|s| s.window_id == *id))
+            .or_else(|| self.first_visible_window());
+
+        // Re-entering during the leave transition continues from the current position.
+        let progress = self.progress(now);
+        self.phase = OverviewPhase::Entering { start: now - self.transition.mul_f64(progress) };
+        info!("Overview entered with {} output(s).", self.outputs.len());
+    }
+
+    /// Starts the leave transition.
+    pub fn leave(&mut self, now: Instant) {
+        if !self.is_active() {
+            return;
+        }
+        self.drag = None;
+        let progress = self.progress(now);
+        self.phase = OverviewPhase::Leaving { start: now - self.transition.mul_f64(1.0 - progress) };
+        debug!("Overview leaving.");
+    }
+
+    /// Updates a window's workspace after it was moved so it leaves the grid.
+    pub fn window_moved(&mut self, window_id: DomainWindowIdentifier, workspace_id: Uuid) {
+        for layout in self.outputs.values_mut() {
+            let active_ws = layout.workspace_strip.iter().find(|ws| ws.active).map(|ws| ws.workspace_id);
+            for window in layout.windows.iter_mut().filter(|w| w.id == window_id) {
+                window.workspace_id = workspace_id;
+            }
+            layout.windows.retain(|w| Some(w.workspace_id) == active_ws);
+        }
+        self.relayout();
+        if self.selected == Some(window_id) {
+            self.selected = self.first_visible_window();
+        }
+    }
+
+    /// Eased transition progress: `0.0` is the normal desktop, `1.0` the full overview.
+    pub fn progress(&self, now: Instant) -> f64 {
+        let raw = |start: Instant| {
+            if self.transition.is_zero() {
+                1.0
+            } else {
+                (now.saturating_duration_since(start).as_secs_f64() / self.transition.as_secs_f64()).min(1.0)
+            }
+        };
+        match self.phase {
+            OverviewPhase::Hidden => 0.0,
+            OverviewPhase::Shown => 1.0,
+            OverviewPhase::Entering { start } => ease_out_cubic(raw(start)),
+            OverviewPhase::Leaving { start } => 1.0 - ease_out_cubic(raw(start)),
+        }
+    }
+
+    /// Advances transitions. Returns `true` if another frame is needed.
+    pub fn tick(&mut self, now: Instant) -> bool {
+        match self.phase {
+            OverviewPhase::Entering { start } if now.saturating_duration_since(start) >= self.transition => {
+                self.phase = OverviewPhase::Shown;
+                true
+            }
+            OverviewPhase::Leaving { start } if now.saturating_duration_since(start) >= self.transition => {
+                self.phase = OverviewPhase::Hidden;
+                self.outputs.clear();
+                self.selected = None;
+                true
+            }
+            OverviewPhase::Entering { .. } | OverviewPhase::Leaving { .. } => true,
+            OverviewPhase::Hidden | OverviewPhase::Shown => false,
+        }
+    }
+
+    // ANCHOR: OverviewKeyboardHandling
+    /// Handles a key press while the overview accepts input.
+    pub fn handle_key(&mut self, key: OverviewKey) -> OverviewAction {
+        if !self.accepts_input() {
+            return OverviewAction::None;
+        }
+        match key {
+            OverviewKey::Escape => {
+                if self.filter.is_empty() {
+                    OverviewAction::Leave
+                } else {
+                    self.filter.clear();
+                    self.refilter();
+                    OverviewAction::Redraw
+                }
+            }
+            OverviewKey::Enter => match self.selected {
+                Some(id) => OverviewAction::ActivateWindow(id),
+                None => OverviewAction::Leave,
+            },
+            OverviewKey::Backspace => {
+                if self.filter.pop().is_some() {
+                    self.refilter();
+                    OverviewAction::Redraw
+                } else {
+                    OverviewAction::None
+                }
+            }
+            OverviewKey::Char(c) if !c.is_control() => {
+                self.filter.push(c);
+                self.refilter();
+                OverviewAction::Redraw
+            }
+            OverviewKey::Char(_) => OverviewAction::None,
+            OverviewKey::Left => self.navigate(-1.0, 0.0),
+            OverviewKey::Right => self.navigate(1.0, 0.0),
+            OverviewKey::Up => self.navigate(0.0, -1.0),
+            OverviewKey::Down => self.navigate(0.0, 1.0),
+        }
+    }
+
+    /// Moves the selection to the nearest slot in the given direction, across outputs.
+    fn navigate(&mut self, dx: f64, dy: f64) -> OverviewAction {
+        let slots: Vec<OverviewSlot> = self.visible_slots().copied().collect();
+        let current = match self.selected.and_then(|id| slots.iter().find(|s| s.window_id == id)) {
+            Some(slot) => *slot,
+            None => {
+                self.selected = slots.first().map(|s| s.window_id);
+                return OverviewAction::Redraw;
+            }
+        };
+        let (cx, cy) = rect_center(&current.target);
+
+        let best = slots
+            .iter()
+            .filter(|s| s.window_id != current.window_id)
+            .filter_map(|s| {
+                let (x, y) = rect_center(&s.target);
+                let along = (x - cx) * dx + (y - cy) * dy;
+                if along <= 0.0 {
+                    return None;
+                }
+                let across = ((x - cx) * dy).abs() + ((y - cy) * dx).abs();
+                // Penalize sideways distance so the closest slot in line wins.
+                Some((along + across * 2.0, s.window_id))
+            })
+            .min_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+
+        match best {
+            Some((_, id)) => {
+                self.selected = Some(id);
+                OverviewAction::Redraw
+            }
+            None => OverviewAction::None,
+        }
+    }
+    // ANCHOR_END: OverviewKeyboardHandling
+
+    // ANCHOR: OverviewPointerHandling
+    /// Handles a pointer button press. Starts a drag if a window slot was hit.
+    pub fn pointer_pressed(&mut self, pos: Point<f64, Logical>) -> OverviewAction {
+        if !self.accepts_input() {
+            return OverviewAction::None;
+        }
+        let pressed = self.visible_slots().find(|s| rect_contains(&s.target, pos)).copied();
+        if let Some(slot) = pressed {
+            self.selected = Some(slot.window_id);
+            self.drag = Some(OverviewDrag {
+                window_id: slot.window_id,
+                grab_offset: pos - slot.target.loc.to_f64(),
+                pointer: pos,
+            });
+            return OverviewAction::Redraw;
+        }
+        OverviewAction::None
+    }
+
+    /// Handles pointer motion, updating a drag in progress.
+    pub fn pointer_motion(&mut self, pos: Point<f64, Logical>) -> OverviewAction {
+        match self.drag.as_mut() {
+            Some(drag) => {
+                drag.pointer = pos;
+                OverviewAction::Redraw
+            }
+            None => OverviewAction::None,
+        }
+    }
+
+    /// Handles a pointer button release.
+    ///
+    /// Dropping a dragged window on a workspace thumbnail moves it there. A click without
+    /// movement activates the window or switches to the clicked workspace.
+    pub fn pointer_released(&mut self, pos: Point<f64, Logical>) -> OverviewAction {
+        if !self.accepts_input() {
+            return OverviewAction::None;
+        }
+        let strip_hit = self.outputs.values().find_map(|layout| {
+            layout
+                .workspace_strip
+                .iter()
+                .find(|ws| rect_contains(&ws.geometry, pos))
+                .map(|ws| (layout.output_name.clone(), ws.workspace_id, ws.active))
+        });
+
+        match self.drag.take() {
+            Some(drag) => match strip_hit {
+                Some((_, workspace_id, false)) => {
+                    OverviewAction::MoveWindowToWorkspace { window_id: drag.window_id, workspace_id }
+                }
+                Some(_) => OverviewAction::Redraw,
+                None => {
+                    let clicked = self
+                        .visible_slots()
+                        .any(|s| s.window_id == drag.window_id && rect_contains(&s.target, pos));
+                    if clicked {
+                        OverviewAction::ActivateWindow(drag.window_id)
+                    } else {
+                        OverviewAction::Redraw
+                    }
+                }
+            },
+            None => match strip_hit {
+                Some((output_name, workspace_id, _)) => OverviewAction::SwitchWorkspace { output_name, workspace_id },
+                None => OverviewAction::Leave,
+            },
+        }
+    }
+    // ANCHOR_END: OverviewPointerHandling
+
+    // ANCHOR: OverviewRenderItems
+    /// Returns the elements to draw at `now`, back to front.
+    pub fn render_items(&self, now: Instant) -> Vec<OverviewRenderItem> {
+        if !self.is_active() {
+            return Vec::new();
+        }
+        let progress = self.progress(now);
+        let drop_target = self.drag.and_then(|drag| {
+            self.outputs
+                .values()
+                .flat_map(|l| l.workspace_strip.iter())
+                .find(|ws| rect_contains(&ws.geometry, drag.pointer))
+                .map(|ws| ws.workspace_id)
+        });
+
+        let mut items = Vec::new();
+        let mut outputs: Vec<&OverviewOutputLayout> = self.outputs.values().collect();
+        outputs.sort_by(|a, b| a.output_name.cmp(&b.output_name));
+
+        for layout in outputs {
+            items.push(OverviewRenderItem::Backdrop {
+                output_name: layout.output_name.clone(),
+                alpha: (0.6 * progress) as f32,
+            });
+
+            // The strip slides in from above the output.
+            let slide = ((1.0 - progress) * (layout.area.size.h as f64 * OVERVIEW_STRIP_HEIGHT_FACTOR as f64)).round() as i32;
+            for ws in &layout.workspace_strip {
+                let mut geometry = ws.geometry;
+                geometry.loc.y -= slide;
+                items.push(OverviewRenderItem::WorkspaceThumbnail {
+                    output_name: layout.output_name.clone(),
+                    workspace_id: ws.workspace_id,
+                    geometry,
+                    active: ws.active,
+                    drop_target: drop_target == Some(ws.workspace_id),
+                });
+            }
+
+            let mut dragged = None;
+            for slot in &layout.window_slots {
+                let mut geometry = lerp_rect(slot.source, slot.target, progress);
+                if let Some(drag) = self.drag.filter(|d| d.window_id == slot.window_id) {
+                    let loc = drag.pointer - drag.grab_offset;
+                    geometry.loc = (loc.x.round() as i32, loc.y.round() as i32).into();
+                    dragged = Some(OverviewRenderItem::Window { window_id: slot.window_id, geometry, selected: true });
+                    continue;
+                }
+                items.push(OverviewRenderItem::Window {
+                    window_id: slot.window_id,
+                    geometry,
+                    selected: self.selected == Some(slot.window_id),
+                });
+            }
+            // The dragged window is drawn on top of everything else.
+            items.extend(dragged);
+        }
+        items
+    }
+
+    /// Returns what to draw on `output_name` at `now`, back to front; empty while the overview is
+    /// hidden. Windows are drawn on every output they overlap, so a dragged window can cross
+    /// outputs.
+    pub fn draw_ops(&self, output_name: &str, now: Instant) -> Vec<OverviewDrawOp> {
+        let Some(area) = self.outputs.get(output_name).map(|layout| layout.area) else {
+            return Vec::new();
+        };
+        let local = |mut geometry: Rectangle<i32, Logical>| {
+            geometry.loc -= area.loc;
+            geometry
+        };
+
+        let mut ops = Vec::new();
+        for item in self.render_items(now) {
+            match item {
+                OverviewRenderItem::Backdrop { output_name: name, alpha } if name == output_name => {
+                    let [r, g, b] = BACKDROP_COLOR;
+                    ops.push(OverviewDrawOp::Rect { geometry: local(area), color: [r, g, b, alpha] });
+                }
+                OverviewRenderItem::WorkspaceThumbnail { output_name: name, geometry, active, drop_target, .. } if name == output_name => {
+                    let color = if drop_target {
+                        DROP_TARGET_COLOR
+                    } else if active {
+                        ACTIVE_THUMBNAIL_COLOR
+                    } else {
+                        THUMBNAIL_COLOR
+                    };
+                    ops.push(OverviewDrawOp::Rect { geometry: local(geometry), color });
+                }
+                OverviewRenderItem::Window { window_id, geometry, selected } if area.overlaps(geometry) => {
+                    if selected {
+                        let outline = Rectangle::from_loc_and_size(
+                            (geometry.loc.x - OVERVIEW_SELECTION_OUTLINE, geometry.loc.y - OVERVIEW_SELECTION_OUTLINE),
+                            (geometry.size.w + 2 * OVERVIEW_SELECTION_OUTLINE, geometry.size.h + 2 * OVERVIEW_SELECTION_OUTLINE),
+                        );
+                        ops.push(OverviewDrawOp::Rect { geometry: local(outline), color: SELECTION_COLOR });
+                    }
+                    ops.push(OverviewDrawOp::Window { window_id, geometry: local(geometry) });
+                }
+                _ => {}
+            }
+        }
+        ops
+    }
+    // ANCHOR_END: OverviewRenderItems
+
+    fn visible_slots(&self) -> impl Iterator<Item = &OverviewSlot> {
+        let mut layouts: Vec<&OverviewOutputLayout> = self.outputs.values().collect();
+        layouts.sort_by(|a, b| a.output_name.cmp(&b.output_name));
+        layouts.into_iter().flat_map(|l| l.window_slots.iter())
+    }
+
+    fn first_visible_window(&self) -> Option<DomainWindowIdentifier> {
+        self.visible_slots().next().map(|s| s.window_id)
+    }
+
+    fn layout_output(input: OverviewOutputInput) -> OverviewOutputLayout {
+        let strip_h = (input.area.size.h as f32 * OVERVIEW_STRIP_HEIGHT_FACTOR).round() as i32;
+        let strip_area = Rectangle::from_loc_and_size(input.area.loc, (input.area.size.w, strip_h));
+        OverviewOutputLayout {
+            workspace_strip: calculate_workspace_strip(&input.workspaces, strip_area, input.area.size, OVERVIEW_GRID_SPACING / 2),
+            output_name: input.output_name,
+            area: input.area,
+            window_slots: Vec::new(),
+            windows: input.windows,
+        }
+    }
+
+    fn relayout(&mut self) {
+        for layout in self.outputs.values_mut() {
+            let strip_h = (layout.area.size.h as f32 * OVERVIEW_STRIP_HEIGHT_FACTOR).round() as i32;
+            let grid_area = Rectangle::from_loc_and_size(
+                (layout.area.loc.x, layout.area.loc.y + strip_h),
+                (layout.area.size.w, layout.area.size.h - strip_h),
+            );
+            let visible: Vec<OverviewWindowEntry> = layout
+                .windows
+                .iter()
+                .filter(|w| matches_filter(w, &self.filter))
+                .cloned()
+                .collect();
+            layout.window_slots = calculate_overview_grid(&visible, grid_area, OVERVIEW_GRID_SPACING);
+        }
+    }
+
+    fn refilter(&mut self) {
+        self.relayout();
+        let still_visible = self.selected.map_or(false, |id| self.visible_slots().any(|s| s.window_id == id));
+        if !still_visible {
+            self.selected = self.first_visible_window();
+        }
+    }
+}
+// ANCHOR_END: OverviewState
+
+// ANCHOR: OverviewKeysymMapping
+/// Maps an xkb keysym (and its UTF-8 text) to an [`OverviewKey`].
+pub fn overview_key_from_keysym(keysym: Keysym, text: Option<char>) -> Option<OverviewKey> {
+    match keysym.raw() {
+        xkb::KEY_Escape => Some(OverviewKey::Escape),
+        xkb::KEY_Return | xkb::KEY_KP_Enter => Some(OverviewKey::Enter),
+        xkb::KEY_Left => Some(OverviewKey::Left),
+        xkb::KEY_Right => Some(OverviewKey::Right),
+        xkb::KEY_Up => Some(OverviewKey::Up),
+        xkb::KEY_Down => Some(OverviewKey::Down),
+        xkb::KEY_BackSpace => Some(OverviewKey::Backspace),
+        _ => text.filter(|c| !c.is_control()).map(OverviewKey::Char),
+    }
+}
+
+/// Returns `true` if the key combination toggles the overview (Super+Tab).
+pub fn is_overview_toggle(modifiers: ModifiersState, keysym: Keysym) -> bool {
+    modifiers.logo && keysym.raw() == xkb::KEY_Tab
+}
+// ANCHOR_END: OverviewKeysymMapping
+
+// ANCHOR: DesktopStateOverviewIntegration
+impl DesktopState {
+    /// Enters the overview if it is hidden, otherwise starts leaving it.
+    pub fn toggle_overview(&mut self) {
+        let now = Instant::now();
+        if self.overview.accepts_input() {
+            self.overview.leave(now);
+        } else {
+            let inputs = self.collect_overview_inputs();
+            let focused = self.focused_domain_window_id();
+            self.overview.enter(now, inputs, focused);
+        }
+        self.emit_mode_event();
+        self.space.lock().unwrap().damage_all_outputs();
+    }
+
+    /// Applies an [`OverviewAction`] produced by input handling.
+    pub fn apply_overview_action(&mut self, action: OverviewAction) {
+        let now = Instant::now();
+        let was_shown = self.overview.accepts_input();
+        match action {
+            OverviewAction::None => return,
+            OverviewAction::Redraw => {}
+            OverviewAction::Leave => self.overview.leave(now),
+            OverviewAction::ActivateWindow(window_id) => {
+                self.overview.leave(now);
+                self.focus_domain_window(window_id);
+            }
+            OverviewAction::SwitchWorkspace { output_name, workspace_id } => {
+                self.overview.leave(now);
+                self.switch_workspace_on_output(&output_name, workspace_id);
+            }
+            OverviewAction::MoveWindowToWorkspace { window_id, workspace_id } => {
+                if self.move_window_to_workspace(window_id, workspace_id) {
+                    self.overview.window_moved(window_id, workspace_id);
+                } else {
+                    warn!("Overview: failed to move window {:?} to workspace {}.", window_id, workspace_id);
+                }
+            }
+        }
+        if self.overview.accepts_input() != was_shown {
+            self.emit_mode_event();
+        }
+        self.space.lock().unwrap().damage_all_outputs();
+    }
+
+    /// Snapshots the current outputs, workspaces and windows for the overview.
+    fn collect_overview_inputs(&self) -> Vec<OverviewOutputInput> {
+        let space = self.space.lock().unwrap();
+        let active_workspaces = self.active_workspaces.read().unwrap();
+
+        space
+            .outputs()
+            .filter_map(|output| {
+                let output_name = output.name();
+                let area = space.output_geometry(output)?;
+                let active_ws = active_workspaces.get(&output_name).copied();
+
+                let workspaces = self
+                    .output_workspaces
+                    .get(&output_name)
+                    .map(|list| {
+                        list.iter()
+                            .map(|ws| {
+                                let ws = ws.read().unwrap();
+                                OverviewWorkspaceEntry { id: ws.id, name: ws.name.clone(), active: Some(ws.id) == active_ws }
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default();
+
+                let windows = self
+                    .windows
+                    .values()
+                    .filter(|mw| mw.is_mapped() && !mw.state.read().unwrap().minimized)
+                    .filter(|mw| mw.output_name.read().unwrap().as_deref() == Some(output_name.as_str()))
+                    .filter_map(|mw| {
+                        let workspace_id = (*mw.workspace_id.read().unwrap())?;
+                        if Some(workspace_id) != active_ws {
+                            return None;
+                        }
+                        Some(OverviewWindowEntry {
+                            id: mw.domain_id,
+                            title: mw.title.clone().unwrap_or_default(),
+                            app_id: mw.app_id.clone(),
+                            geometry: *mw.current_geometry.read().unwrap(),
+                            workspace_id,
+                        })
+                    })
+                    .collect();
+
+                Some(OverviewOutputInput { output_name, area, workspaces, windows })
+            })
+            .collect()
+    }
+}
+// ANCHOR_END: DesktopStateOverviewIntegration
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(x: i32, y: i32, w: i32, h: i32, title: &str, ws: Uuid) -> OverviewWindowEntry {
+        OverviewWindowEntry {
+            id: DomainWindowIdentifier::new_v4(),
+            title: title.to_string(),
+            app_id: None,
+            geometry: Rectangle::from_loc_and_size((x, y), (w, h)),
+            workspace_id: ws,
+        }
+    }
+
+    fn output_input(windows: Vec<OverviewWindowEntry>, workspaces: Vec<OverviewWorkspaceEntry>) -> OverviewOutputInput {
+        OverviewOutputInput {
+            output_name: "DP-1".to_string(),
+            area: Rectangle::from_loc_and_size((0, 0), (1920, 1080)),
+            workspaces,
+            windows,
+        }
+    }
+
+    fn overlaps(a: &Rectangle<i32, Logical>, b: &Rectangle<i32, Logical>) -> bool {
+        a.loc.x < b.loc.x + b.size.w && b.loc.x < a.loc.x + a.size.w && a.loc.y < b.loc.y + b.size.h && b.loc.y < a.loc.y + a.size.h
+    }
+
+    #[test]
+    fn test_grid_is_non_overlapping_and_inside_area() {
+        let ws = Uuid::new_v4();
+        let windows: Vec<_> = (0..7).map(|i| window(i * 10, i * 10, 1200, 800, "w", ws)).collect();
+        let area = Rectangle::from_loc_and_size((0, 100), (1920, 980));
+        let slots = calculate_overview_grid(&windows, area, OVERVIEW_GRID_SPACING);
+
+        assert_eq!(slots.len(), 7);
+        for (i, a) in slots.iter().enumerate() {
+            assert!(a.scale <= 1.0 && a.scale > 0.0);
+            assert!(a.target.loc.x >= area.loc.x && a.target.loc.y >= area.loc.y);
+            assert!(a.target.loc.x + a.target.size.w <= area.loc.x + area.size.w);
+            assert!(a.target.loc.y + a.target.size.h <= area.loc.y + area.size.h);
+            for b in &slots[i + 1..] {
+                assert!(!overlaps(&a.target, &b.target), "{:?} overlaps {:?}", a.target, b.target);
+            }
+        }
+    }
+
+    #[test]
+    fn test_grid_never_upscales_small_windows() {
+        let ws = Uuid::new_v4();
+        let windows = vec![window(0, 0, 200, 100, "small", ws)];
+        let slots = calculate_overview_grid(&windows, Rectangle::from_loc_and_size((0, 0), (1920, 1080)), 32);
+        assert_eq!(slots[0].scale, 1.0);
+        assert_eq!(slots[0].target.size, Size::from((200, 100)));
+    }
+
+    #[test]
+    fn test_workspace_strip_keeps_output_aspect() {
+        let workspaces: Vec<_> = (0..4)
+            .map(|i| OverviewWorkspaceEntry { id: Uuid::new_v4(), name: format!("{}", i + 1), active: i == 0 })
+            .collect();
+        let strip = calculate_workspace_strip(
+            &workspaces,
+            Rectangle::from_loc_and_size((0, 0), (1920, 162)),
+            (1920, 1080).into(),
+            16,
+        );
+        assert_eq!(strip.len(), 4);
+        assert!(strip[0].active);
+        let aspect = strip[0].geometry.size.w as f64 / strip[0].geometry.size.h as f64;
+        assert!((aspect - 16.0 / 9.0).abs() < 0.05);
+        assert!(strip.windows(2).all(|p| p[0].geometry.loc.x + p[0].geometry.size.w < p[1].geometry.loc.x));
+    }
+
+    #[test]
+    fn test_enter_and_leave_transitions() {
+        let mut overview = OverviewState::new(Duration::from_millis(100));
+        let ws = Uuid::new_v4();
+        let now = Instant::now();
+        overview.enter(now, vec![output_input(vec![window(0, 0, 800, 600, "a", ws)], vec![])], None);
+
+        assert!(overview.is_active());
+        assert_eq!(overview.progress(now), 0.0);
+        assert!(overview.tick(now + Duration::from_millis(50)));
+        assert!(overview.progress(now + Duration::from_millis(50)) > 0.5);
+        overview.tick(now + Duration::from_millis(100));
+        assert_eq!(overview.phase(), OverviewPhase::Shown);
+
+        let leave_at = now + Duration::from_millis(200);
+        overview.leave(leave_at);
+        assert!(!overview.accepts_input());
+        assert_eq!(overview.progress(leave_at), 1.0);
+        overview.tick(leave_at + Duration::from_millis(100));
+        assert_eq!(overview.phase(), OverviewPhase::Hidden);
+        assert!(overview.render_items(leave_at + Duration::from_millis(100)).is_empty());
+    }
+
+    #[test]
+    fn test_render_items_interpolate_from_source_to_target() {
+        let mut overview = OverviewState::new(Duration::from_millis(100));
+        let ws = Uuid::new_v4();
+        let win = window(100, 100, 1600, 900, "a", ws);
+        let id = win.id;
+        let now = Instant::now();
+        overview.enter(now, vec![output_input(vec![win], vec![])], None);
+
+        let geometry_at = |t: Instant| {
+            overview.render_items(t).into_iter().find_map(|item| match item {
+                OverviewRenderItem::Window { window_id, geometry, .. } if window_id == id => Some(geometry),
+                _ => None,
+            })
+        };
+        let slot = overview.output_layout("DP-1").unwrap().window_slots[0];
+        assert_eq!(geometry_at(now), Some(slot.source));
+        assert_eq!(geometry_at(now + Duration::from_millis(100)), Some(slot.target));
+    }
+
+    #[test]
+    fn test_draw_ops_are_output_local_and_per_output() {
+        let mut overview = OverviewState::new(Duration::from_millis(100));
+        let ws = Uuid::new_v4();
+        let left = window(0, 0, 800, 600, "left", ws);
+        let right = window(2000, 100, 800, 600, "right", ws);
+        let right_id = right.id;
+        let mut second = output_input(vec![right], vec![OverviewWorkspaceEntry { id: ws, name: "1".to_string(), active: true }]);
+        second.output_name = "HDMI-A-1".to_string();
+        second.area = Rectangle::from_loc_and_size((1920, 0), (1920, 1080));
+        let now = Instant::now();
+        overview.enter(now, vec![output_input(vec![left], vec![]), second], Some(right_id));
+        let shown = now + Duration::from_millis(100);
+        overview.tick(shown);
+
+        let ops = overview.draw_ops("HDMI-A-1", shown);
+        assert!(matches!(ops[0], OverviewDrawOp::Rect { geometry, color } if geometry == Rectangle::from_loc_and_size((0, 0), (1920, 1080)) && color[3] > 0.5));
+        assert!(matches!(ops[1], OverviewDrawOp::Rect { color, .. } if color == ACTIVE_THUMBNAIL_COLOR));
+        let windows: Vec<_> = ops
+            .iter()
+            .filter_map(|op| match op {
+                OverviewDrawOp::Window { window_id, geometry } => Some((*window_id, *geometry)),
+                _ => None,
+            })
+            .collect();
+        let mut target = overview.output_layout("HDMI-A-1").unwrap().window_slots[0].target;
+        target.loc.x -= 1920;
+        assert_eq!(windows, vec![(right_id, target)]);
+        // The selected window sits on its outline.
+        assert!(matches!(ops[ops.len() - 2], OverviewDrawOp::Rect { color, .. } if color == SELECTION_COLOR));
+
+        assert!(overview.draw_ops("eDP-1", shown).is_empty());
+        overview.leave(shown);
+        overview.tick(shown + Duration::from_millis(100));
+        assert!(overview.draw_ops("HDMI-A-1", shown + Duration::from_millis(100)).is_empty());
+    }
+
+    #[test]
+    fn test_typing_filters_by_title_and_keeps_selection_valid() {
+        let mut overview = OverviewState::new(Duration::ZERO);
+        let ws = Uuid::new_v4();
+        let terminal = window(0, 0, 800, 600, "Terminal", ws);
+        let browser = window(900, 0, 800, 600, "Web Browser", ws);
+        let browser_id = browser.id;
+        overview.enter(Instant::now(), vec![output_input(vec![terminal, browser], vec![])], None);
+        assert_eq!(overview.output_layout("DP-1").unwrap().window_slots.len(), 2);
+
+        for c in "brow".chars() {
+            assert_eq!(overview.handle_key(OverviewKey::Char(c)), OverviewAction::Redraw);
+        }
+        let slots = &overview.output_layout("DP-1").unwrap().window_slots;
+        assert_eq!(slots.len(), 1);
+        assert_eq!(overview.selected(), Some(browser_id));
+        assert_eq!(overview.handle_key(OverviewKey::Enter), OverviewAction::ActivateWindow(browser_id));
+
+        // Escape clears the filter first, then leaves.
+        assert_eq!(overview.handle_key(OverviewKey::Escape), OverviewAction::Redraw);
+        assert_eq!(overview.filter(), "");
+        assert_eq!(overview.handle_key(OverviewKey::Escape), OverviewAction::Leave);
+    }
+
+    #[test]
+    fn test_keyboard_navigation_moves_between_neighbours() {
+        let mut overview = OverviewState::new(Duration::ZERO);
+        let ws = Uuid::new_v4();
+        let left = window(0, 0, 800, 600, "left", ws);
+        let right = window(1000, 0, 800, 600, "right", ws);
+        let (left_id, right_id) = (left.id, right.id);
+        overview.enter(Instant::now(), vec![output_input(vec![left, right], vec![])], Some(left_id));
+
+        assert_eq!(overview.selected(), Some(left_id));
+        assert_eq!(overview.handle_key(OverviewKey::Right), OverviewAction::Redraw);
+        assert_eq!(overview.selected(), Some(right_id));
+        assert_eq!(overview.handle_key(OverviewKey::Right), OverviewAction::None);
+        overview.handle_key(OverviewKey::Left);
+        assert_eq!(overview.selected(), Some(left_id));
+    }
+
+    #[test]
+    fn test_drag_window_onto_workspace_thumbnail() {
+        let mut overview = OverviewState::new(Duration::ZERO);
+        let ws1 = Uuid::new_v4();
+        let ws2 = Uuid::new_v4();
+        let win = window(0, 0, 800, 600, "a", ws1);
+        let id = win.id;
+        let workspaces = vec![
+            OverviewWorkspaceEntry { id: ws1, name: "1".into(), active: true },
+            OverviewWorkspaceEntry { id: ws2, name: "2".into(), active: false },
+        ];
+        overview.enter(Instant::now(), vec![output_input(vec![win], workspaces)], None);
+
+        let layout = overview.output_layout("DP-1").unwrap();
+        let slot_center = layout.window_slots[0].target.loc.to_f64() + Point::from((10.0, 10.0));
+        let target = layout.workspace_strip[1].geometry;
+        let drop_point = target.loc.to_f64() + Point::from((5.0, 5.0));
+
+        assert_eq!(overview.pointer_pressed(slot_center), OverviewAction::Redraw);
+        assert_eq!(overview.pointer_motion(drop_point), OverviewAction::Redraw);
+        assert_eq!(
+            overview.pointer_released(drop_point),
+            OverviewAction::MoveWindowToWorkspace { window_id: id, workspace_id: ws2 }
+        );
+
+        overview.window_moved(id, ws2);
+        assert!(overview.output_layout("DP-1").unwrap().window_slots.is_empty());
+        assert_eq!(overview.selected(), None);
+    }
+
+    #[test]
+    fn test_click_on_thumbnail_switches_workspace() {
+        let mut overview = OverviewState::new(Duration::ZERO);
+        let ws1 = Uuid::new_v4();
+        let ws2 = Uuid::new_v4();
+        let workspaces = vec![
+            OverviewWorkspaceEntry { id: ws1, name: "1".into(), active: true },
+            OverviewWorkspaceEntry { id: ws2, name: "2".into(), active: false },
+        ];
+        overview.enter(Instant::now(), vec![output_input(vec![], workspaces)], None);
+        let target = overview.output_layout("DP-1").unwrap().workspace_strip[1].geometry;
+        let pos = target.loc.to_f64() + Point::from((1.0, 1.0));
+        assert_eq!(overview.pointer_pressed(pos), OverviewAction::None);
+        assert_eq!(
+            overview.pointer_released(pos),
+            OverviewAction::SwitchWorkspace { output_name: "DP-1".into(), workspace_id: ws2 }
+        );
+    }
+}
+// ANCHOR_END: OverviewModule
@@ -0,0 +1,198 @@
+// novade-system/src/compositor/recorder/encoder.rs
+
+//! Video encoders behind the [`FrameEncoder`] trait.
+//!
+//! An encoder turns composited frames into a container written to any [`Write`]. It runs on
+//! the recorder's worker thread, so it may take its time, but it must be [`Send`]. Encoders are
+//! registered with the recorder as an [`EncoderBackend`] under the name `[recorder] encoder`
+//! refers to; [`Y4M`] is built in and needs nothing beyond the standard library.
+
+use std::io::{self, Write};
+
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum EncoderError {
+    #[error("Failed to write the recording: {0}")]
+    Io(#[from] io::Error),
+    #[error("Frame is {got_width}x{got_height}, the stream is {width}x{height}")]
+    FrameSize { width: u32, height: u32, got_width: u32, got_height: u32 },
+    #[error("Encoder error: {0}")]
+    Other(String),
+}
+
+/// A composited frame: premultiplied `0xAARRGGBB` pixels, row by row without padding.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VideoFrame {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u32>,
+}
+
+impl VideoFrame {
+    /// An opaque black frame.
+    pub fn black(width: u32, height: u32) -> Self {
+        Self { width, height, pixels: vec![0xff00_0000; width as usize * height as usize] }
+    }
+}
+
+/// Properties of a stream, fixed when the recording starts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamInfo {
+    pub width: u32,
+    pub height: u32,
+    /// Frames per second; the stream has a constant frame rate.
+    pub fps: u32,
+}
+
+// ANCHOR: FrameEncoder
+/// Writes frames of one stream.
+pub trait FrameEncoder: Send {
+    /// Appends `frame`, which has the stream's size.
+    fn encode(&mut self, frame: &VideoFrame) -> Result<(), EncoderError>;
+
+    /// Completes the stream, e.g. writes an index, and flushes it. Called once, after the
+    /// last frame.
+    fn finish(&mut self) -> Result<(), EncoderError>;
+}
+
+/// Creates the encoder of a new stream writing to `sink`.
+pub type CreateEncoder = fn(sink: Box<dyn Write + Send>, info: StreamInfo) -> Result<Box<dyn FrameEncoder>, EncoderError>;
+
+/// An encoder the recorder can be configured to use.
+#[derive(Debug, Clone, Copy)]
+pub struct EncoderBackend {
+    /// Name used by `[recorder] encoder`.
+    pub name: &'static str,
+    /// File name extension of the container.
+    pub extension: &'static str,
+    pub create: CreateEncoder,
+}
+// ANCHOR_END: FrameEncoder
+
+/// Uncompressed YUV 4:2:0 in a YUV4MPEG2 container, which ffmpeg, mpv and most editors read.
+/// Files are large (about 3 MB per 1080p frame); transcode them for sharing.
+pub const Y4M: EncoderBackend = EncoderBackend { name: "y4m", extension: "y4m", create: Y4mEncoder::<Box<dyn Write + Send>>::create };
+
+// ANCHOR: Y4mEncoder
+/// YUV4MPEG2 writer using BT.709 coefficients in limited range.
+///
+/// Pixels are taken as composited over black, which is what the output shows. Chroma is the
+/// average of each 2x2 block (centre siting, `C420jpeg`); odd sizes round the chroma planes up.
+pub struct Y4mEncoder<W: Write> {
+    sink: W,
+    info: StreamInfo,
+    /// Y, Cb and Cr planes of one frame, reused between frames.
+    planes: Vec<u8>,
+}
+
+impl<W: Write + Send> Y4mEncoder<W> {
+    /// Writes the stream header.
+    pub fn new(mut sink: W, info: StreamInfo) -> Result<Self, EncoderError> {
+        writeln!(sink, "YUV4MPEG2 W{} H{} F{}:1 Ip A1:1 C420jpeg XCOLORRANGE=LIMITED", info.width, info.height, info.fps)?;
+        Ok(Self { sink, info, planes: Vec::new() })
+    }
+}
+
+impl Y4mEncoder<Box<dyn Write + Send>> {
+    fn create(sink: Box<dyn Write + Send>, info: StreamInfo) -> Result<Box<dyn FrameEncoder>, EncoderError> {
+        Ok(Box::new(Self::new(sink, info)?))
+    }
+}
+
+/// Limited-range BT.709 luma of 8-bit RGB.
+fn luma(r: f32, g: f32, b: f32) -> f32 {
+    16.0 + 219.0 / 255.0 * (0.2126 * r + 0.7152 * g + 0.0722 * b)
+}
+
+/// Limited-range BT.709 chroma (Cb, Cr) of 8-bit RGB.
+fn chroma(r: f32, g: f32, b: f32) -> (f32, f32) {
+    let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+    let scale = 224.0 / 255.0;
+    (128.0 + scale * (b - y) / 1.8556, 128.0 + scale * (r - y) / 1.5748)
+}
+
+fn rgb(pixel: u32) -> (f32, f32, f32) {
+    (((pixel >> 16) & 0xff) as f32, ((pixel >> 8) & 0xff) as f32, (pixel & 0xff) as f32)
+}
+
+fn to_byte(value: f32) -> u8 {
+    value.round().clamp(0.0, 255.0) as u8
+}
+
+impl<W: Write + Send> FrameEncoder for Y4mEncoder<W> {
+    fn encode(&mut self, frame: &VideoFrame) -> Result<(), EncoderError> {
+        let StreamInfo { width, height, .. } = self.info;
+        if (frame.width, frame.height) != (width, height) || frame.pixels.len() != (width * height) as usize {
+            return Err(EncoderError::FrameSize { width, height, got_width: frame.width, got_height: frame.height });
+        }
+        let (w, h) = (width as usize, height as usize);
+        let (cw, ch) = (w.div_ceil(2), h.div_ceil(2));
+        self.planes.clear();
+        self.planes.extend(frame.pixels.iter().map(|&p| {
+            let (r, g, b) = rgb(p);
+            to_byte(luma(r, g, b))
+        }));
+        let mut cr_plane = Vec::with_capacity(cw * ch);
+        for cy in 0..ch {
+            for cx in 0..cw {
+                let (mut sum, mut count) = ((0.0, 0.0, 0.0), 0.0);
+                for y in (2 * cy)..(2 * cy + 2).min(h) {
+                    for x in (2 * cx)..(2 * cx + 2).min(w) {
+                        let (r, g, b) = rgb(frame.pixels[y * w + x]);
+                        sum = (sum.0 + r, sum.1 + g, sum.2 + b);
+                        count += 1.0;
+                    }
+                }
+                let (cb, cr) = chroma(sum.0 / count, sum.1 / count, sum.2 / count);
+                self.planes.push(to_byte(cb));
+                cr_plane.push(to_byte(cr));
+            }
+        }
+        self.planes.extend(cr_plane);
+        self.sink.write_all(b"FRAME\n")?;
+        self.sink.write_all(&self.planes)?;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), EncoderError> {
+        self.sink.flush()?;
+        Ok(())
+    }
+}
+// ANCHOR_END: Y4mEncoder
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_y4m_stream_layout() {
+        let info = StreamInfo { width: 3, height: 2, fps: 30 };
+        let mut out = Vec::new();
+        let mut encoder = Y4mEncoder::new(&mut out, info).unwrap();
+        // White, black, red / white, black, red.
+        let row = [0xffff_ffff, 0xff00_0000, 0xffff_0000];
+        let frame = VideoFrame { width: 3, height: 2, pixels: [row, row].concat() };
+        encoder.encode(&frame).unwrap();
+        encoder.finish().unwrap();
+        drop(encoder);
+
+        let header = "YUV4MPEG2 W3 H2 F30:1 Ip A1:1 C420jpeg XCOLORRANGE=LIMITED\nFRAME\n";
+        assert_eq!(&out[..header.len()], header.as_bytes());
+        let planes = &out[header.len()..];
+        // 6 luma samples, then 2x1 for each chroma plane.
+        assert_eq!(planes.len(), 6 + 2 + 2);
+        assert_eq!(&planes[..6], &[235, 16, 63, 235, 16, 63]);
+        // Grey average of white and black is neutral; pure red is Cb 102, Cr 240.
+        assert_eq!(&planes[6..8], &[128, 102]);
+        assert_eq!(&planes[8..10], &[128, 240]);
+    }
+
+    #[test]
+    fn test_frame_size_must_match_stream() {
+        let mut encoder = Y4mEncoder::new(Vec::new(), StreamInfo { width: 4, height: 4, fps: 30 }).unwrap();
+        assert!(matches!(encoder.encode(&VideoFrame::black(2, 2)), Err(EncoderError::FrameSize { got_width: 2, .. })));
+        assert!(encoder.encode(&VideoFrame::black(4, 4)).is_ok());
+    }
+}
@@ -0,0 +1,147 @@
+// novade-system/src/compositor/recorder/frame.rs
+
+//! Preparing captured pixels for the encoder: fitting them to the stream size and drawing
+//! the cursor into frames that were composited without it.
+
+use crate::renderer::software::SoftwareImage;
+
+use super::encoder::VideoFrame;
+
+/// The compositor's own arrow, for themed (named) cursors. `#` is the outline, `o` the fill.
+const ARROW: [&str; 19] = [
+    "#           ",
+    "##          ",
+    "#o#         ",
+    "#oo#        ",
+    "#ooo#       ",
+    "#oooo#      ",
+    "#ooooo#     ",
+    "#oooooo#    ",
+    "#ooooooo#   ",
+    "#oooooooo#  ",
+    "#ooooooooo# ",
+    "#oooooo#####",
+    "#ooo#oo#    ",
+    "#oo# #oo#   ",
+    "#o#  #oo#   ",
+    "##    #oo#  ",
+    "#     #oo#  ",
+    "       #oo# ",
+    "        ##  ",
+];
+
+// ANCHOR: CursorSprite
+/// A cursor image in physical pixels with its hotspot.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CursorSprite {
+    pub image: SoftwareImage,
+    pub hotspot: (i32, i32),
+}
+
+impl CursorSprite {
+    /// The built-in arrow scaled by `scale` (at least 1), nearest neighbour.
+    pub fn arrow(scale: u32) -> Self {
+        let scale = scale.max(1);
+        let (width, height) = (ARROW[0].len() as u32, ARROW.len() as u32);
+        let mut pixels = Vec::with_capacity((width * height * scale * scale) as usize);
+        for row in ARROW {
+            let row: Vec<u32> = row
+                .bytes()
+                .map(|c| match c {
+                    b'#' => 0xff00_0000,
+                    b'o' => 0xffff_ffff,
+                    _ => 0,
+                })
+                .flat_map(|pixel| std::iter::repeat_n(pixel, scale as usize))
+                .collect();
+            for _ in 0..scale {
+                pixels.extend_from_slice(&row);
+            }
+        }
+        Self { image: SoftwareImage::from_pixels(width * scale, height * scale, pixels), hotspot: (0, 0) }
+    }
+
+    /// Draws the sprite over `frame` with its hotspot at `position`, in frame pixels.
+    pub fn draw(&self, frame: &mut VideoFrame, position: (i32, i32)) {
+        let left = position.0 - self.hotspot.0;
+        let top = position.1 - self.hotspot.1;
+        for sy in 0..self.image.height() as i32 {
+            let y = top + sy;
+            if y < 0 || y >= frame.height as i32 {
+                continue;
+            }
+            for sx in 0..self.image.width() as i32 {
+                let x = left + sx;
+                if x < 0 || x >= frame.width as i32 {
+                    continue;
+                }
+                let index = (y as u32 * frame.width + x as u32) as usize;
+                frame.pixels[index] = over(self.image.pixel(sx as u32, sy as u32), frame.pixels[index]);
+            }
+        }
+    }
+}
+// ANCHOR_END: CursorSprite
+
+/// Premultiplied `src` over `dst`, per channel in 8 bits.
+fn over(src: u32, dst: u32) -> u32 {
+    let inverse = 255 - (src >> 24);
+    let channel = |shift: u32| {
+        let s = (src >> shift) & 0xff;
+        let d = (dst >> shift) & 0xff;
+        (s + (d * inverse + 127) / 255).min(255) << shift
+    };
+    channel(24) | channel(16) | channel(8) | channel(0)
+}
+
+/// `frame` cropped or padded with black to `width` x `height`, anchored at the top left.
+///
+/// Streams keep the size they started with; a window that grows is cut off, one that shrinks
+/// or an output switching to a smaller mode leaves black bars on the right and bottom.
+pub fn fit_frame(frame: VideoFrame, width: u32, height: u32) -> VideoFrame {
+    if (frame.width, frame.height) == (width, height) {
+        return frame;
+    }
+    let mut fitted = VideoFrame::black(width, height);
+    let copy_width = frame.width.min(width) as usize;
+    for y in 0..frame.height.min(height) as usize {
+        let src = y * frame.width as usize;
+        let dst = y * width as usize;
+        fitted.pixels[dst..dst + copy_width].copy_from_slice(&frame.pixels[src..src + copy_width]);
+    }
+    fitted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fit_frame_crops_and_pads() {
+        let frame = VideoFrame { width: 3, height: 2, pixels: vec![1, 2, 3, 4, 5, 6] };
+        assert_eq!(fit_frame(frame.clone(), 3, 2), frame);
+        assert_eq!(fit_frame(frame.clone(), 2, 3).pixels, vec![1, 2, 4, 5, 0xff00_0000, 0xff00_0000]);
+        assert_eq!(fit_frame(frame, 4, 1).pixels, vec![1, 2, 3, 0xff00_0000]);
+    }
+
+    #[test]
+    fn test_cursor_is_drawn_at_hotspot_and_clipped() {
+        let sprite = CursorSprite::arrow(2);
+        assert_eq!((sprite.image.width(), sprite.image.height()), (24, 38));
+        let grey = 0xff80_8080;
+        let mut frame = VideoFrame { width: 16, height: 16, pixels: vec![grey; 256] };
+        sprite.draw(&mut frame, (4, 4));
+        // Outline at the tip, fill inside, transparent parts and the left untouched.
+        assert_eq!(frame.pixels[4 * 16 + 4], 0xff00_0000);
+        assert_eq!(frame.pixels[10 * 16 + 6], 0xffff_ffff);
+        assert_eq!(frame.pixels[4 * 16 + 10], grey);
+        assert_eq!(frame.pixels[10 * 16 + 3], grey);
+        // Sprites hanging off the frame are clipped.
+        sprite.draw(&mut frame, (14, 14));
+        assert_eq!(frame.pixels[14 * 16 + 14], 0xff00_0000);
+
+        // Half-transparent white over black.
+        assert_eq!(over(0x8080_8080, 0xff00_0000), 0xff80_8080);
+        assert_eq!(over(0, 0xff12_3456), 0xff12_3456);
+    }
+}
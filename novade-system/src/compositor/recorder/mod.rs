@@ -0,0 +1,695 @@
+// novade-system/src/compositor/recorder/mod.rs
+//! Built-in screen recorder.
+//!
+//! Records an output, a region of one or a window from the composited frames, so what ends up
+//! in the file is exactly what was on screen, including effects, night light excepted (gamma
+//! is applied by the display). Backends call [`DesktopState::capture_recording_frame`] after
+//! rendering an output; the recorder reads back only the frames it needs for its frame rate
+//! (see [`pacing`]) and draws the cursor into them when the backend shows it on a separate
+//! plane.
+//!
+//! Frames are encoded on a worker thread through a [`FrameEncoder`]. The built-in encoder
+//! writes raw Y4M; other encoders can be registered with [`RecorderState::register_encoder`]
+//! and picked with `[recorder] encoder`. When the worker falls behind, frames are dropped and
+//! the previous frame is repeated in their place, so the recording keeps its timing.
+//!
+//! Recordings are started and stopped with the `start_recording`, `stop_recording` and
+//! `toggle_recording` actions (by default `Super+Print` for the current output and
+//! `Super+Shift+Print` for the focused window); the panel shows an indicator while one runs.
+
+pub mod encoder;
+pub mod frame;
+pub mod pacing;
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use smithay::input::pointer::{CursorImageStatus, CursorImageSurfaceData};
+use smithay::output::Output;
+use smithay::reexports::calloop::timer::{TimeoutAction, Timer};
+use smithay::reexports::calloop::RegistrationToken;
+use smithay::reexports::wayland_server::protocol::{wl_shm, wl_surface::WlSurface};
+use smithay::utils::{Logical, Physical, Point, Rectangle, Size};
+use smithay::wayland::compositor::{with_states, BufferAssignment, SurfaceAttributes};
+use smithay::wayland::shm::with_buffer_contents;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+pub use encoder::{EncoderBackend, EncoderError, FrameEncoder, StreamInfo, VideoFrame, Y4M};
+
+use self::frame::{fit_frame, CursorSprite};
+use self::pacing::FramePacer;
+use super::color::expand_home;
+use super::ipc::protocol::IpcEvent;
+use super::renderer_interface::abstraction::FrameRenderer;
+use super::shell::xdg_shell::types::DomainWindowIdentifier;
+use super::state::DesktopState;
+use crate::renderer::software::SoftwareImage;
+
+/// Frames waiting for the encoder before further frames are dropped.
+const QUEUE_FRAMES: usize = 8;
+/// Tick interval while recordings are being written out after stopping.
+const FINISHING_INTERVAL: Duration = Duration::from_millis(100);
+
+/// What to record.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RecordingTarget {
+    /// A whole output; the current output if `None`.
+    Output {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        output: Option<String>,
+    },
+    /// A rectangle in global logical coordinates, clipped to the output its top-left corner
+    /// is on.
+    Region { x: i32, y: i32, width: i32, height: i32 },
+    /// A window, followed as it moves; the focused window if `None`. The recording keeps the
+    /// window's size at the start.
+    Window {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        id: Option<Uuid>,
+    },
+}
+
+impl Default for RecordingTarget {
+    fn default() -> Self {
+        RecordingTarget::Output { output: None }
+    }
+}
+
+/// Snapshot reported over IPC and in `recording` events.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RecordingStatus {
+    pub active: bool,
+    /// The running recording's target, with the output or window filled in.
+    pub target: Option<RecordingTarget>,
+    /// File of the running recording, else of the last one.
+    pub path: Option<PathBuf>,
+    pub width: u32,
+    pub height: u32,
+    pub fps: u32,
+    /// Length of the running recording.
+    pub duration_ms: u64,
+    /// Frames of the running recording replaced by repeats because the encoder fell behind.
+    pub dropped_frames: u64,
+    /// Whether stopped recordings are still being written out.
+    pub saving: bool,
+    /// Why the last recording failed, if it did.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Where a recording's frames come from.
+#[derive(Debug, Clone, PartialEq)]
+enum CaptureSource {
+    Output { output: String },
+    /// A fixed region of an output, in its physical pixels.
+    Region { output: String, region: Rectangle<i32, Physical> },
+    Window { id: DomainWindowIdentifier },
+}
+
+/// The physical region of an output at `output_loc` with `scale` and `mode_size` that shows
+/// global logical point `loc` at its top left and is `size` large, moved inside the output
+/// where it would stick out.
+pub fn region_on_output(
+    loc: Point<i32, Logical>,
+    output_loc: Point<i32, Logical>,
+    scale: f64,
+    size: (u32, u32),
+    mode_size: Size<i32, Physical>,
+) -> Rectangle<i32, Physical> {
+    let w = (size.0 as i32).min(mode_size.w);
+    let h = (size.1 as i32).min(mode_size.h);
+    let x = (((loc.x - output_loc.x) as f64 * scale).round() as i32).clamp(0, mode_size.w - w);
+    let y = (((loc.y - output_loc.y) as f64 * scale).round() as i32).clamp(0, mode_size.h - h);
+    Rectangle::from_loc_and_size((x, y), (w, h))
+}
+
+/// `size` logical pixels at `scale` as a stream size: whole, even numbers of physical pixels,
+/// as 4:2:0 encoders want.
+pub fn stream_size(size: Size<i32, Logical>, scale: f64) -> (u32, u32) {
+    let even = |v: i32| ((v as f64 * scale).round() as u32 & !1).max(2);
+    (even(size.w), even(size.h))
+}
+
+/// Local time as `YYYY-MM-DD_HH-MM-SS`, for file names.
+fn local_timestamp() -> String {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs()) as libc::time_t;
+    // SAFETY: localtime_r only writes to the zeroed `tm` it is given.
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    if unsafe { libc::localtime_r(&now, &mut tm) }.is_null() {
+        return now.to_string();
+    }
+    format!("{:04}-{:02}-{:02}_{:02}-{:02}-{:02}", tm.tm_year + 1900, tm.tm_mon + 1, tm.tm_mday, tm.tm_hour, tm.tm_min, tm.tm_sec)
+}
+
+/// Directory recordings go to when `[recorder] directory` is unset.
+fn default_directory() -> PathBuf {
+    std::env::var_os("XDG_VIDEOS_DIR")
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .unwrap_or_else(|| expand_home(Path::new("~/Videos")))
+}
+
+// ANCHOR: EncoderWorker
+enum WorkerMessage {
+    /// A new frame, after repeating the previous one `repeats_before` times.
+    Frame { frame: VideoFrame, repeats_before: u64 },
+    /// Repeats the previous frame.
+    Repeat(u64),
+}
+
+impl WorkerMessage {
+    /// Frame slots the message fills.
+    fn slots(&self) -> u64 {
+        match self {
+            WorkerMessage::Frame { repeats_before, .. } => repeats_before + 1,
+            WorkerMessage::Repeat(count) => *count,
+        }
+    }
+}
+
+fn run_worker(mut encoder: Box<dyn FrameEncoder>, messages: Receiver<WorkerMessage>) -> Result<(), EncoderError> {
+    let mut last: Option<VideoFrame> = None;
+    for message in messages {
+        match message {
+            WorkerMessage::Frame { frame, repeats_before } => {
+                // Slots before the first frame show the first frame.
+                let previous = last.as_ref().unwrap_or(&frame);
+                for _ in 0..repeats_before {
+                    encoder.encode(previous)?;
+                }
+                encoder.encode(&frame)?;
+                last = Some(frame);
+            }
+            WorkerMessage::Repeat(count) => {
+                if let Some(frame) = &last {
+                    for _ in 0..count {
+                        encoder.encode(frame)?;
+                    }
+                }
+            }
+        }
+    }
+    encoder.finish()
+}
+// ANCHOR_END: EncoderWorker
+
+// ANCHOR: ActiveRecording
+struct ActiveRecording {
+    /// The target as resolved at the start.
+    target: RecordingTarget,
+    source: CaptureSource,
+    path: PathBuf,
+    info: StreamInfo,
+    cursor: bool,
+    pacer: FramePacer,
+    sender: SyncSender<WorkerMessage>,
+    worker: JoinHandle<Result<(), EncoderError>>,
+    /// Slots of messages the queue had no room for, to repeat with the next message.
+    owed_repeats: u64,
+    dropped_frames: u64,
+    /// A frame was rendered after the current slot was filled; the last content may be
+    /// missing from the recording until the next frame.
+    stale: bool,
+}
+
+impl ActiveRecording {
+    /// Queues `frame`, or only repeats when `None`, without blocking.
+    fn send(&mut self, frame: Option<VideoFrame>, repeats: u64) {
+        let repeats = repeats + std::mem::take(&mut self.owed_repeats);
+        let message = match frame {
+            Some(frame) => WorkerMessage::Frame { frame, repeats_before: repeats },
+            None if repeats == 0 => return,
+            None => WorkerMessage::Repeat(repeats),
+        };
+        match self.sender.try_send(message) {
+            Ok(()) => {}
+            Err(TrySendError::Full(message)) => {
+                if matches!(message, WorkerMessage::Frame { .. }) {
+                    self.dropped_frames += 1;
+                }
+                self.owed_repeats += message.slots();
+            }
+            // The worker failed; the next tick reports why.
+            Err(TrySendError::Disconnected(_)) => {}
+        }
+    }
+
+    fn output_name(&self, state: &DesktopState) -> Option<String> {
+        match &self.source {
+            CaptureSource::Output { output } | CaptureSource::Region { output, .. } => Some(output.clone()),
+            CaptureSource::Window { id } => state.windows.get(id).and_then(|w| w.output_name.read().unwrap().clone()),
+        }
+    }
+}
+// ANCHOR_END: ActiveRecording
+
+// ANCHOR: RecorderState
+pub struct RecorderState {
+    encoders: Vec<EncoderBackend>,
+    active: Option<ActiveRecording>,
+    /// Stopped recordings whose worker is still writing, with their files.
+    finishing: Vec<(PathBuf, JoinHandle<Result<(), EncoderError>>)>,
+    last_path: Option<PathBuf>,
+    last_error: Option<String>,
+    timer: Option<RegistrationToken>,
+}
+
+impl RecorderState {
+    /// Creates the state with the built-in encoders.
+    pub fn new() -> Self {
+        Self { encoders: vec![Y4M], active: None, finishing: Vec::new(), last_path: None, last_error: None, timer: None }
+    }
+
+    /// Makes `backend` available to `[recorder] encoder`, replacing one of the same name.
+    pub fn register_encoder(&mut self, backend: EncoderBackend) {
+        self.encoders.retain(|e| e.name != backend.name);
+        self.encoders.push(backend);
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.active.is_some()
+    }
+}
+
+impl Default for RecorderState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+// ANCHOR_END: RecorderState
+
+// ANCHOR: DesktopStateRecorderIntegration
+impl DesktopState {
+    fn output_by_name(&self, name: &str) -> Option<Output> {
+        self.space.lock().unwrap().outputs().find(|o| o.name() == name).cloned()
+    }
+
+    /// Logical position, scale and physical mode size of `output`.
+    fn output_layout(&self, output: &Output) -> Option<(Point<i32, Logical>, f64, Size<i32, Physical>)> {
+        let loc = self.space.lock().unwrap().output_geometry(output)?.loc;
+        let mode = output.current_mode()?;
+        Some((loc, output.current_scale().fractional_scale(), mode.size))
+    }
+
+    /// Resolves a requested target into what to capture and the stream size.
+    fn resolve_recording_target(&self, target: RecordingTarget) -> Result<(RecordingTarget, CaptureSource, (u32, u32)), String> {
+        match target {
+            RecordingTarget::Output { output } => {
+                let name = output.or_else(|| self.current_output_name()).ok_or("no output to record")?;
+                let output = self.output_by_name(&name).ok_or_else(|| format!("output {} is not connected", name))?;
+                let (_, _, mode_size) = self.output_layout(&output).ok_or_else(|| format!("output {} has no mode", name))?;
+                let size = ((mode_size.w as u32 & !1).max(2), (mode_size.h as u32 & !1).max(2));
+                Ok((RecordingTarget::Output { output: Some(name.clone()) }, CaptureSource::Output { output: name }, size))
+            }
+            RecordingTarget::Region { x, y, width, height } => {
+                if width <= 0 || height <= 0 {
+                    return Err(format!("region {}x{} is empty", width, height));
+                }
+                let rect = Rectangle::<i32, Logical>::from_loc_and_size((x, y), (width, height));
+                let space = self.space.lock().unwrap();
+                let output = space.output_under(rect.loc.to_f64()).next().cloned().ok_or("region does not start on an output")?;
+                let output_geometry = space.output_geometry(&output).ok_or("output is not mapped")?;
+                drop(space);
+                let clipped = rect.intersection(output_geometry).ok_or("region does not overlap its output")?;
+                let (output_loc, scale, mode_size) = self.output_layout(&output).ok_or("output has no mode")?;
+                let size = stream_size(clipped.size, scale);
+                let region = region_on_output(clipped.loc, output_loc, scale, size, mode_size);
+                let size = (region.size.w as u32, region.size.h as u32);
+                let target = RecordingTarget::Region { x: clipped.loc.x, y: clipped.loc.y, width: clipped.size.w, height: clipped.size.h };
+                Ok((target, CaptureSource::Region { output: output.name(), region }, size))
+            }
+            RecordingTarget::Window { id } => {
+                let id = match id {
+                    Some(id) => DomainWindowIdentifier::from_uuid(id),
+                    None => self.focused_domain_window_id().ok_or("no focused window")?,
+                };
+                let window = self.windows.get(&id).ok_or_else(|| format!("no window with id {}", id.as_uuid()))?;
+                let geometry = *window.current_geometry.read().unwrap();
+                let output_name = window.output_name.read().unwrap().clone().ok_or("window is not on an output")?;
+                let output = self.output_by_name(&output_name).ok_or_else(|| format!("output {} is not connected", output_name))?;
+                let (_, scale, _) = self.output_layout(&output).ok_or("output has no mode")?;
+                let size = stream_size(geometry.size, scale);
+                Ok((RecordingTarget::Window { id: Some(id.as_uuid()) }, CaptureSource::Window { id }, size))
+            }
+        }
+    }
+
+    /// Starts recording `target` into a new file in `[recorder] directory` and returns its
+    /// path.
+    pub fn start_recording(&mut self, target: RecordingTarget) -> Result<PathBuf, String> {
+        if let Some(active) = &self.recorder.active {
+            return Err(format!("already recording to {}", active.path.display()));
+        }
+        let config = self.config.recorder.clone();
+        let (target, source, (width, height)) = self.resolve_recording_target(target)?;
+        let backend = *self
+            .recorder
+            .encoders
+            .iter()
+            .find(|e| e.name == config.encoder)
+            .ok_or_else(|| format!("unknown encoder '{}'", config.encoder))?;
+
+        let directory = config.directory.as_deref().map(expand_home).unwrap_or_else(default_directory);
+        std::fs::create_dir_all(&directory).map_err(|e| format!("cannot create {}: {}", directory.display(), e))?;
+        let path = directory.join(format!("novade-recording-{}.{}", local_timestamp(), backend.extension));
+        let file = File::create(&path).map_err(|e| format!("cannot create {}: {}", path.display(), e))?;
+        let info = StreamInfo { width, height, fps: config.fps };
+        let encoder = (backend.create)(Box::new(BufWriter::new(file)), info).map_err(|e| e.to_string())?;
+
+        let (sender, receiver) = mpsc::sync_channel(QUEUE_FRAMES);
+        let worker = std::thread::Builder::new()
+            .name("novade-recorder".into())
+            .spawn(move || run_worker(encoder, receiver))
+            .map_err(|e| format!("cannot start the encoder thread: {}", e))?;
+
+        info!("Recording {:?} at {}x{}, {} fps to {}.", target, width, height, info.fps, path.display());
+        let recording = ActiveRecording {
+            target,
+            source,
+            path: path.clone(),
+            info,
+            cursor: config.cursor,
+            pacer: FramePacer::new(Instant::now(), info.fps),
+            sender,
+            worker,
+            owed_repeats: 0,
+            dropped_frames: 0,
+            stale: false,
+        };
+        let output = recording.output_name(self).and_then(|name| self.output_by_name(&name));
+        self.recorder.active = Some(recording);
+        self.recorder.last_error = None;
+        // The first frame should not wait for the next change on screen.
+        if let Some(output) = output {
+            self.frame_scheduler(&output).request_redraw();
+        }
+        self.start_recorder_timer();
+        self.emit_recording_event();
+        Ok(path)
+    }
+
+    /// Stops the recording. Its file is written out in the background; the returned path is
+    /// complete once `recording_status().saving` is false.
+    pub fn stop_recording(&mut self) -> Result<PathBuf, String> {
+        let path = self.finish_recording(None).ok_or("not recording")?;
+        self.emit_recording_event();
+        Ok(path)
+    }
+
+    /// Stops the recording if one runs, else starts recording `target`.
+    pub fn toggle_recording(&mut self, target: RecordingTarget) -> Result<PathBuf, String> {
+        if self.recorder.is_recording() {
+            self.stop_recording()
+        } else {
+            self.start_recording(target)
+        }
+    }
+
+    /// Ends the running recording, noting `error` as the reason if it failed.
+    fn finish_recording(&mut self, error: Option<String>) -> Option<PathBuf> {
+        let mut recording = self.recorder.active.take()?;
+        let repeats = recording.pacer.fill_until(Instant::now()) + std::mem::take(&mut recording.owed_repeats);
+        if repeats > 0 {
+            // Blocks until the worker has room, so the file gets its full length.
+            let _ = recording.sender.send(WorkerMessage::Repeat(repeats));
+        }
+        drop(recording.sender);
+        match &error {
+            Some(error) => warn!("Recording to {} failed: {}", recording.path.display(), error),
+            None => info!("Stopped recording after {:?}; writing {}.", recording.pacer.duration(), recording.path.display()),
+        }
+        self.recorder.last_error = error;
+        self.recorder.last_path = Some(recording.path.clone());
+        self.recorder.finishing.push((recording.path.clone(), recording.worker));
+        Some(recording.path)
+    }
+
+    fn start_recorder_timer(&mut self) {
+        if self.recorder.timer.is_some() {
+            return;
+        }
+        let token = self.event_loop_handle.insert_source(Timer::immediate(), |_, _, state: &mut DesktopState| state.tick_recorder());
+        match token {
+            Ok(token) => self.recorder.timer = Some(token),
+            Err(e) => warn!("Failed to register the recorder timer: {}", e),
+        }
+    }
+
+    /// Fills frame slots nothing was rendered for, asks for frames that were skipped, ends
+    /// recordings whose window closed or whose encoder failed and reaps finished files.
+    fn tick_recorder(&mut self) -> TimeoutAction {
+        let mut finished = false;
+        let mut index = 0;
+        while index < self.recorder.finishing.len() {
+            if !self.recorder.finishing[index].1.is_finished() {
+                index += 1;
+                continue;
+            }
+            let (path, worker) = self.recorder.finishing.remove(index);
+            match worker.join() {
+                Ok(Ok(())) => info!("Recording saved to {}.", path.display()),
+                Ok(Err(e)) => {
+                    warn!("Failed to write recording {}: {}", path.display(), e);
+                    self.recorder.last_error = Some(e.to_string());
+                }
+                Err(_) => self.recorder.last_error = Some("the encoder thread panicked".to_string()),
+            }
+            finished = true;
+        }
+
+        let (failed, window_closed) = match &self.recorder.active {
+            Some(recording) => (
+                recording.worker.is_finished(),
+                matches!(&recording.source, CaptureSource::Window { id } if !self.windows.contains_key(id)),
+            ),
+            None => (false, false),
+        };
+        if failed {
+            // The worker's own error replaces this one when it is reaped on the next tick.
+            self.finish_recording(Some("the encoder stopped".to_string()));
+            finished = true;
+        } else if window_closed {
+            info!("Recorded window closed; stopping the recording.");
+            self.finish_recording(None);
+            finished = true;
+        }
+
+        let mut redraw = None;
+        if let Some(recording) = self.recorder.active.as_mut() {
+            let now = Instant::now();
+            if let Some(last_slot) = now.checked_sub(recording.pacer.interval()) {
+                let repeats = recording.pacer.fill_until(last_slot);
+                recording.send(None, repeats);
+            }
+            if std::mem::take(&mut recording.stale) {
+                redraw = self.recorder.active.as_ref().and_then(|r| r.output_name(self));
+            }
+        }
+        if let Some(output) = redraw.and_then(|name| self.output_by_name(&name)) {
+            self.frame_scheduler(&output).request_redraw();
+        }
+        if finished {
+            self.emit_recording_event();
+        }
+
+        match &self.recorder.active {
+            Some(recording) => TimeoutAction::ToDuration(recording.pacer.interval()),
+            None if !self.recorder.finishing.is_empty() => TimeoutAction::ToDuration(FINISHING_INTERVAL),
+            None => {
+                self.recorder.timer = None;
+                TimeoutAction::Drop
+            }
+        }
+    }
+
+    /// The part of `output` to read back from the frame just rendered, in its physical
+    /// pixels, if the recorder wants this frame.
+    pub fn recording_region(&mut self, output: &Output) -> Option<Rectangle<i32, Physical>> {
+        let recording = self.recorder.active.as_ref()?;
+        if recording.output_name(self).as_deref() != Some(output.name().as_str()) {
+            return None;
+        }
+        if !recording.pacer.is_due(Instant::now()) {
+            self.recorder.active.as_mut()?.stale = true;
+            return None;
+        }
+        let (width, height) = (recording.info.width, recording.info.height);
+        let (output_loc, scale, mode_size) = self.output_layout(output)?;
+        match &recording.source {
+            CaptureSource::Output { .. } => Some(Rectangle::from_loc_and_size((0, 0), mode_size)),
+            CaptureSource::Region { region, .. } => region.intersection(Rectangle::from_loc_and_size((0, 0), mode_size)),
+            CaptureSource::Window { id } => {
+                let geometry = *self.windows.get(id)?.current_geometry.read().unwrap();
+                Some(region_on_output(geometry.loc, output_loc, scale, (width, height), mode_size))
+            }
+        }
+    }
+
+    /// Hands the recorder the pixels of `region` from [`Self::recording_region`]: premultiplied
+    /// `0xAARRGGBB`, row by row. `with_cursor` tells whether the frame already shows the cursor.
+    pub fn submit_recording_frame(&mut self, output: &Output, region: Rectangle<i32, Physical>, pixels: Vec<u32>, with_cursor: bool) {
+        let Some(recording) = self.recorder.active.as_ref() else {
+            return;
+        };
+        let mut frame = VideoFrame { width: region.size.w.max(0) as u32, height: region.size.h.max(0) as u32, pixels };
+        if frame.pixels.len() != (frame.width * frame.height) as usize {
+            warn!("Recorder got {} pixels for a {}x{} region; skipping the frame.", frame.pixels.len(), frame.width, frame.height);
+            return;
+        }
+        if recording.cursor && !with_cursor {
+            if let Some((output_loc, scale, _)) = self.output_layout(output) {
+                if let Some(sprite) = self.recording_cursor_sprite(scale) {
+                    let x = ((self.pointer_location.x - output_loc.x as f64) * scale).round() as i32 - region.loc.x;
+                    let y = ((self.pointer_location.y - output_loc.y as f64) * scale).round() as i32 - region.loc.y;
+                    sprite.draw(&mut frame, (x, y));
+                }
+            }
+        }
+        let Some(recording) = self.recorder.active.as_mut() else {
+            return;
+        };
+        let frame = fit_frame(frame, recording.info.width, recording.info.height);
+        let repeats = recording.pacer.take_frame(Instant::now());
+        recording.stale = false;
+        recording.send(Some(frame), repeats);
+    }
+
+    /// Reads the recorder's part of the frame just rendered for `output` back from `renderer`,
+    /// if it wants this frame. Call after rendering and before presenting.
+    pub fn capture_recording_frame<R: FrameRenderer>(&mut self, output: &Output, renderer: &R, with_cursor: bool) {
+        let Some(region) = self.recording_region(output) else {
+            return;
+        };
+        match renderer.read_pixels(region) {
+            Ok(pixels) => self.submit_recording_frame(output, region, pixels, with_cursor),
+            Err(e) => self.abort_recording(format!("cannot read back frames: {}", e)),
+        }
+    }
+
+    /// Stops the recording because of `error`, e.g. when the backend cannot read back frames.
+    pub fn abort_recording(&mut self, error: String) {
+        if self.finish_recording(Some(error)).is_some() {
+            self.emit_recording_event();
+        }
+    }
+
+    /// The cursor as the user sees it, at `scale`.
+    fn recording_cursor_sprite(&self, scale: f64) -> Option<CursorSprite> {
+        match &*self.cursor_status.lock().unwrap() {
+            CursorImageStatus::Hidden => None,
+            CursorImageStatus::Surface(surface) => client_cursor_sprite(surface),
+            _ => Some(CursorSprite::arrow(scale.round() as u32)),
+        }
+    }
+
+    pub fn recording_status(&self) -> RecordingStatus {
+        let saving = !self.recorder.finishing.is_empty();
+        match &self.recorder.active {
+            Some(recording) => RecordingStatus {
+                active: true,
+                target: Some(recording.target.clone()),
+                path: Some(recording.path.clone()),
+                width: recording.info.width,
+                height: recording.info.height,
+                fps: recording.info.fps,
+                duration_ms: recording.pacer.duration().as_millis() as u64,
+                dropped_frames: recording.dropped_frames,
+                saving,
+                error: None,
+            },
+            None => RecordingStatus {
+                path: self.recorder.last_path.clone(),
+                saving,
+                error: self.recorder.last_error.clone(),
+                ..RecordingStatus::default()
+            },
+        }
+    }
+
+    fn emit_recording_event(&self) {
+        self.emit_ipc_event(IpcEvent::Recording { status: self.recording_status() });
+    }
+}
+// ANCHOR_END: DesktopStateRecorderIntegration
+
+/// The image of a cursor surface, taken from its current SHM buffer.
+fn client_cursor_sprite(surface: &WlSurface) -> Option<CursorSprite> {
+    let (buffer, hotspot) = with_states(surface, |states| {
+        let buffer = match &states.cached_state.get::<SurfaceAttributes>().current().buffer {
+            Some(BufferAssignment::NewBuffer(buffer)) => Some(buffer.clone()),
+            _ => None,
+        };
+        let hotspot = states.data_map.get::<CursorImageSurfaceData>().map_or((0, 0).into(), |data| data.lock().unwrap().hotspot);
+        (buffer, hotspot)
+    });
+    let image = with_buffer_contents(&buffer?, |ptr, len, data| {
+        // SAFETY: smithay keeps the pool mapped and `len` bytes valid for the closure.
+        let bytes = unsafe { std::slice::from_raw_parts(ptr, len) };
+        let pixels = bytes.get(data.offset.max(0) as usize..)?;
+        let opaque = match data.format {
+            wl_shm::Format::Argb8888 => false,
+            wl_shm::Format::Xrgb8888 => true,
+            _ => return None,
+        };
+        SoftwareImage::from_shm(pixels, data.width as u32, data.height as u32, data.stride as u32, opaque).ok()
+    })
+    .ok()
+    .flatten()?;
+    Some(CursorSprite { image, hotspot: (hotspot.x, hotspot.y) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_target_json_shape() {
+        let target: RecordingTarget = serde_json::from_str(r#"{"type":"output"}"#).unwrap();
+        assert_eq!(target, RecordingTarget::default());
+        let target = RecordingTarget::Region { x: 10, y: 20, width: 640, height: 480 };
+        assert_eq!(serde_json::to_value(&target).unwrap(), serde_json::json!({"type": "region", "x": 10, "y": 20, "width": 640, "height": 480}));
+    }
+
+    #[test]
+    fn test_regions_on_scaled_outputs() {
+        // A 1280x720 logical output at x=1920 with scale 1.5.
+        let mode = Size::from((1920, 1080));
+        let size = stream_size(Size::from((400, 300)), 1.5);
+        assert_eq!(size, (600, 450));
+        let region = region_on_output(Point::from((2020, 100)), Point::from((1920, 0)), 1.5, size, mode);
+        assert_eq!(region, Rectangle::from_loc_and_size((150, 150), (600, 450)));
+        // A window partly off the right edge is recorded up to the edge.
+        let region = region_on_output(Point::from((3000, -50)), Point::from((1920, 0)), 1.5, size, mode);
+        assert_eq!(region, Rectangle::from_loc_and_size((1320, 0), (600, 450)));
+    }
+
+    #[test]
+    fn test_worker_keeps_timing_with_repeats() {
+        struct Count(std::sync::Arc<std::sync::Mutex<Vec<u32>>>);
+        impl FrameEncoder for Count {
+            fn encode(&mut self, frame: &VideoFrame) -> Result<(), EncoderError> {
+                self.0.lock().unwrap().push(frame.pixels[0]);
+                Ok(())
+            }
+            fn finish(&mut self) -> Result<(), EncoderError> {
+                Ok(())
+            }
+        }
+        let written = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let (sender, receiver) = mpsc::sync_channel(4);
+        let frame = |pixel| VideoFrame { width: 1, height: 1, pixels: vec![pixel] };
+        sender.send(WorkerMessage::Frame { frame: frame(1), repeats_before: 2 }).unwrap();
+        sender.send(WorkerMessage::Repeat(1)).unwrap();
+        sender.send(WorkerMessage::Frame { frame: frame(2), repeats_before: 1 }).unwrap();
+        drop(sender);
+        run_worker(Box::new(Count(written.clone())), receiver).unwrap();
+        assert_eq!(*written.lock().unwrap(), vec![1, 1, 1, 1, 1, 2]);
+    }
+}
@@ -0,0 +1,91 @@
+// novade-system/src/compositor/recorder/pacing.rs
+
+//! Constant frame rate from damage-driven rendering.
+//!
+//! The compositor only renders when something changed, while recordings have a fixed frame
+//! rate. Time since the start of the recording is cut into slots of `1 / fps`; the first frame
+//! rendered in a slot fills it and slots without a frame repeat the previous one.
+
+use std::time::{Duration, Instant};
+
+// ANCHOR: FramePacer
+#[derive(Debug, Clone)]
+pub struct FramePacer {
+    started: Instant,
+    fps: u32,
+    /// Slots filled so far.
+    filled: u64,
+}
+
+impl FramePacer {
+    pub fn new(started: Instant, fps: u32) -> Self {
+        Self { started, fps: fps.max(1), filled: 0 }
+    }
+
+    /// Length of one slot.
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(1) / self.fps
+    }
+
+    /// Index of the slot `now` falls into.
+    fn slot(&self, now: Instant) -> u64 {
+        (now.saturating_duration_since(self.started).as_nanos() * self.fps as u128 / 1_000_000_000) as u64
+    }
+
+    /// Whether a frame rendered at `now` would fill a slot that is still empty.
+    pub fn is_due(&self, now: Instant) -> bool {
+        self.slot(now) >= self.filled
+    }
+
+    /// Takes a frame rendered at `now`, which must be due. Returns how many skipped slots
+    /// before it repeat the previous frame.
+    pub fn take_frame(&mut self, now: Instant) -> u64 {
+        let slot = self.slot(now);
+        let repeats = slot.saturating_sub(self.filled);
+        self.filled = self.filled.max(slot + 1);
+        repeats
+    }
+
+    /// Fills every slot up to and including the one of `now` with the previous frame, e.g. when
+    /// the recording stops. Returns how many slots that is.
+    pub fn fill_until(&mut self, now: Instant) -> u64 {
+        let end = self.slot(now) + 1;
+        let repeats = end.saturating_sub(self.filled);
+        self.filled = self.filled.max(end);
+        repeats
+    }
+
+    /// Start of the next empty slot.
+    pub fn next_slot_start(&self) -> Instant {
+        self.started + self.duration()
+    }
+
+    /// Length of the stream so far.
+    pub fn duration(&self) -> Duration {
+        Duration::from_nanos(self.filled.saturating_mul(1_000_000_000) / self.fps as u64)
+    }
+}
+// ANCHOR_END: FramePacer
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slots_are_filled_once_and_gaps_repeat() {
+        let start = Instant::now();
+        let ms = |ms: u64| start + Duration::from_millis(ms);
+        let mut pacer = FramePacer::new(start, 10);
+        assert!(pacer.is_due(start));
+        assert_eq!(pacer.take_frame(ms(5)), 0);
+        // A second frame within the first 100 ms slot is not needed.
+        assert!(!pacer.is_due(ms(60)));
+        assert!(pacer.is_due(ms(100)));
+        assert_eq!(pacer.next_slot_start(), ms(100));
+        // Nothing was rendered for slots 1 and 2.
+        assert_eq!(pacer.take_frame(ms(350)), 2);
+        assert_eq!(pacer.duration(), Duration::from_millis(400));
+        assert_eq!(pacer.fill_until(ms(620)), 3);
+        assert_eq!(pacer.fill_until(ms(650)), 0);
+    }
+}
@@ -0,0 +1,195 @@
+// novade-system/src/compositor/region.rs
+//! Rectangle-set arithmetic for occlusion culling and clipping.
+//!
+//! A [`Region`] is a set of pairwise disjoint, non-empty rectangles. Subtracting a rectangle
+//! splits every intersecting member into at most four bands (above, below, left, right), so
+//! the result stays disjoint and its area is exact.
+
+use novade_core::types::geometry::Rect;
+
+fn is_empty(rect: &Rect<f32>) -> bool {
+    rect.size.width <= 0.0 || rect.size.height <= 0.0
+}
+
+/// Pushes the parts of `rect` not covered by `cut` onto `out`.
+fn subtract_into(rect: &Rect<f32>, cut: &Rect<f32>, out: &mut Vec<Rect<f32>>) {
+    let inter = match rect.intersection(cut) {
+        Some(inter) => inter,
+        None => {
+            out.push(*rect);
+            return;
+        }
+    };
+    let bands = [
+        // Full-width band above and below the cut, then the left and right remainders.
+        Rect::from_coords(rect.left(), rect.top(), rect.size.width, inter.top() - rect.top()),
+        Rect::from_coords(rect.left(), inter.bottom(), rect.size.width, rect.bottom() - inter.bottom()),
+        Rect::from_coords(rect.left(), inter.top(), inter.left() - rect.left(), inter.size.height),
+        Rect::from_coords(inter.right(), inter.top(), rect.right() - inter.right(), inter.size.height),
+    ];
+    out.extend(bands.into_iter().filter(|band| !is_empty(band)));
+}
+
+// ANCHOR: RegionDefinition
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Region {
+    rects: Vec<Rect<f32>>,
+}
+
+impl Region {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_rect(rect: Rect<f32>) -> Self {
+        let mut region = Self::new();
+        region.union_rect(&rect);
+        region
+    }
+
+    /// The disjoint rectangles making up the region, in no particular order.
+    pub fn rects(&self) -> &[Rect<f32>] {
+        &self.rects
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rects.is_empty()
+    }
+
+    pub fn area(&self) -> f32 {
+        self.rects.iter().map(|r| r.size.width * r.size.height).sum()
+    }
+
+    pub fn bounding_box(&self) -> Option<Rect<f32>> {
+        let (first, rest) = self.rects.split_first()?;
+        Some(rest.iter().fold(*first, |bounds, r| bounds.union(r)))
+    }
+
+    /// Returns `true` if any part of the region overlaps `rect`.
+    pub fn intersects(&self, rect: &Rect<f32>) -> bool {
+        self.rects.iter().any(|r| r.intersection(rect).is_some())
+    }
+
+    /// Adds `rect`. Only the parts not already in the region are stored.
+    pub fn union_rect(&mut self, rect: &Rect<f32>) {
+        if is_empty(rect) {
+            return;
+        }
+        let mut pieces = vec![*rect];
+        for existing in &self.rects {
+            if pieces.is_empty() {
+                return;
+            }
+            let mut remaining = Vec::with_capacity(pieces.len());
+            for piece in &pieces {
+                subtract_into(piece, existing, &mut remaining);
+            }
+            pieces = remaining;
+        }
+        self.rects.extend(pieces);
+    }
+
+    pub fn union(&mut self, other: &Region) {
+        for rect in &other.rects {
+            self.union_rect(rect);
+        }
+    }
+
+    /// Removes `rect` from the region.
+    pub fn subtract_rect(&mut self, rect: &Rect<f32>) {
+        if is_empty(rect) || !self.intersects(rect) {
+            return;
+        }
+        let mut remaining = Vec::with_capacity(self.rects.len() + 3);
+        for existing in &self.rects {
+            subtract_into(existing, rect, &mut remaining);
+        }
+        self.rects = remaining;
+    }
+
+    pub fn subtract(&mut self, other: &Region) {
+        for rect in &other.rects {
+            if self.is_empty() {
+                return;
+            }
+            self.subtract_rect(rect);
+        }
+    }
+
+    /// The part of the region inside `clip`.
+    pub fn intersect_rect(&self, clip: &Rect<f32>) -> Region {
+        Region { rects: self.rects.iter().filter_map(|r| r.intersection(clip)).collect() }
+    }
+
+    /// The part of the region inside `other`.
+    pub fn intersect(&self, other: &Region) -> Region {
+        // Members of both regions are disjoint, so the pairwise intersections are too.
+        Region {
+            rects: self
+                .rects
+                .iter()
+                .flat_map(|a| other.rects.iter().filter_map(move |b| a.intersection(b)))
+                .collect(),
+        }
+    }
+}
+// ANCHOR_END: RegionDefinition
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(x: f32, y: f32, w: f32, h: f32) -> Rect<f32> {
+        Rect::from_coords(x, y, w, h)
+    }
+
+    fn assert_disjoint(region: &Region) {
+        for (i, a) in region.rects().iter().enumerate() {
+            for b in &region.rects()[i + 1..] {
+                assert!(a.intersection(b).is_none(), "{:?} overlaps {:?}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_subtract_center_leaves_frame() {
+        let mut region = Region::from_rect(rect(0.0, 0.0, 100.0, 100.0));
+        region.subtract_rect(&rect(25.0, 25.0, 50.0, 50.0));
+        assert_eq!(region.rects().len(), 4);
+        assert_eq!(region.area(), 100.0 * 100.0 - 50.0 * 50.0);
+        assert!(!region.intersects(&rect(30.0, 30.0, 10.0, 10.0)));
+        assert_disjoint(&region);
+    }
+
+    #[test]
+    fn test_union_stores_only_new_area() {
+        let mut region = Region::from_rect(rect(0.0, 0.0, 50.0, 50.0));
+        region.union_rect(&rect(25.0, 25.0, 50.0, 50.0));
+        assert_eq!(region.area(), 2.0 * 50.0 * 50.0 - 25.0 * 25.0);
+        assert_eq!(region.bounding_box(), Some(rect(0.0, 0.0, 75.0, 75.0)));
+        assert_disjoint(&region);
+
+        region.union_rect(&rect(10.0, 10.0, 5.0, 5.0));
+        assert_eq!(region.area(), 2.0 * 50.0 * 50.0 - 25.0 * 25.0);
+    }
+
+    #[test]
+    fn test_several_rects_cover_region() {
+        let mut region = Region::from_rect(rect(0.0, 0.0, 100.0, 100.0));
+        let mut occluders = Region::new();
+        occluders.union_rect(&rect(0.0, 0.0, 60.0, 100.0));
+        occluders.union_rect(&rect(50.0, 0.0, 50.0, 100.0));
+        region.subtract(&occluders);
+        assert!(region.is_empty());
+    }
+
+    #[test]
+    fn test_intersect() {
+        let mut region = Region::from_rect(rect(0.0, 0.0, 100.0, 100.0));
+        region.subtract_rect(&rect(40.0, 0.0, 20.0, 100.0));
+        let clipped = region.intersect_rect(&rect(30.0, 30.0, 40.0, 10.0));
+        assert_eq!(clipped.area(), 2.0 * 10.0 * 10.0);
+        let other = Region::from_rect(rect(50.0, 50.0, 100.0, 100.0));
+        assert_eq!(region.intersect(&other).area(), 40.0 * 50.0);
+    }
+}
@@ -25,6 +25,7 @@ use crate::compositor::{
     errors::CompositorError,
     state::DesktopState, // To access space, outputs, etc.
 };
+use crate::renderer::SoftwareRenderer;
 
 // If using ash directly for Vulkan:
 // use ash::vk;
@@ -432,6 +433,8 @@ impl NovaRendererBackend for VulkanNovaRenderer {
 pub enum MainNovaRenderer {
     Gles(Box<GlesNovaRenderer>),
     Vulkan(Box<VulkanNovaRenderer>),
+    /// CPU fallback when no GPU renderer could be created, see `backend::headless`.
+    Software(Box<SoftwareRenderer>),
     None, // For headless mode or if rendering fails to initialize
 }
 
@@ -452,6 +455,7 @@ impl MainNovaRenderer {
         match self {
             MainNovaRenderer::Gles(r) => r.id(),
             MainNovaRenderer::Vulkan(r) => r.id(),
+            MainNovaRenderer::Software(_) => "Software",
             MainNovaRenderer::None => "None",
         }
     }
@@ -15,9 +15,13 @@ use smithay::{
 // For now, Gles2RendererError is not #[from] to avoid cyclic dependency issues if it also uses RendererError,
 // or if Gles2RendererError is not yet fully defined. This can be refined.
 use crate::compositor::surface_management::SurfaceData; // Assuming this path
-use novade_compositor_core::surface::SurfaceId; // Added for SurfaceId in upload_surface_texture
 use novade_core::types::geometry::Rect as NovaRect; // For clip_rect and source_rect
 use crate::compositor::scene_graph::Transform as SceneGraphTransform; // For the transformation matrix
+use crate::compositor::color::Lut3d;
+use crate::compositor::effects::{BlurStyle, ShadowStyle};
+
+/// Identifies a surface's textures: the protocol object id of its `wl_surface`.
+pub type SurfaceId = smithay::reexports::wayland_server::backend::ObjectId;
 
 #[derive(Debug, Error)]
 pub enum RendererError {
@@ -157,6 +161,38 @@ pub struct TextureRenderParams {
     /// Coordinates can be normalized (0.0-1.0) or pixel-based, depending on renderer convention.
     /// For now, assume normalized: Rect::new(0.0, 0.0, 1.0, 1.0) for full texture.
     pub source_rect: NovaRect<f32>,
+    /// Disjoint parts of `clip_rect` that are actually visible and need drawing.
+    /// Empty means the whole `clip_rect`.
+    pub visible_region: Vec<NovaRect<f32>>,
+    /// Radius of rounded corners in texture-local units; 0.0 for square corners.
+    pub corner_radius: f32,
+    /// Conversion of the texture's colours into the output's composition space, applied to
+    /// unpremultiplied samples; `None` if the content is already in that space.
+    pub color_transform: Option<Arc<Lut3d>>,
+}
+
+/// Parameters of a drop shadow, used by `RenderElement::Shadow`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShadowRenderParams {
+    /// The rectangle casting the shadow, in world coordinates.
+    pub geometry: NovaRect<f32>,
+    /// Corner radius of the casting rectangle.
+    pub corner_radius: f32,
+    /// Shadow geometry in world units.
+    pub style: ShadowStyle,
+}
+
+/// Parameters of a blur of what was drawn so far, used by `RenderElement::BackdropBlur`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlurRenderParams {
+    /// The surface the blur is drawn behind, in world coordinates; its corners are rounded
+    /// with `corner_radius`.
+    pub geometry: NovaRect<f32>,
+    pub corner_radius: f32,
+    /// Disjoint parts of `geometry` to blur, in world coordinates; empty for all of it.
+    pub region: Vec<NovaRect<f32>>,
+    /// Offsets are in texels of each downsampled level, independent of the output scale.
+    pub style: BlurStyle,
 }
 
 #[derive(Debug)]
@@ -179,6 +215,11 @@ pub enum RenderElement<'a> {
     /// Represents a texture node from the composition scene graph, ready for rendering.
     /// This variant owns its texture data via `Box<dyn RenderableTexture>`.
     TextureNode(TextureRenderParams),
+    /// A drop shadow, drawn before the surface casting it.
+    Shadow(ShadowRenderParams),
+    /// Replaces the frame behind a surface with a blurred copy of itself. Must come after
+    /// everything the surface covers and before the surface.
+    BackdropBlur(BlurRenderParams),
 }
 
 pub trait FrameRenderer: 'static {
@@ -197,6 +238,16 @@ pub trait FrameRenderer: 'static {
     /// Submits all recorded commands for the current frame and schedules it for presentation.
     fn submit_and_present_frame(&mut self) -> Result<(), RendererError>;
 
+    /// Age of the buffer the next frame is rendered into, as reported by the swapchain
+    /// (`EGL_EXT_buffer_age`). 0 means the contents are undefined and everything is repainted.
+    fn buffer_age(&self) -> usize {
+        0
+    }
+
+    /// Restricts drawing of the next `render_frame` to `damage`, in output coordinates.
+    /// `None` repaints the whole output. Renderers without scissor support may ignore it.
+    fn set_damage_region(&mut self, _damage: Option<&[NovaRect<f32>]>) {}
+
     fn create_texture_from_shm(
         &mut self,
         buffer: &WlBuffer,
@@ -213,11 +264,17 @@ pub trait FrameRenderer: 'static {
 
     fn screen_size(&self) -> Size<i32, Physical>;
 
+    /// Reads back `region` of the frame just rendered as premultiplied `0xAARRGGBB` pixels, row
+    /// by row, e.g. for screen recording.
+    fn read_pixels(&self, _region: Rectangle<i32, Physical>) -> Result<Vec<u32>, RendererError> {
+        Err(RendererError::Unsupported("Reading back frames".to_string()))
+    }
+
     /// Uploads or updates a texture for a given surface using client-provided buffer data.
     /// Returns a renderable texture representation.
     fn upload_surface_texture(
         &mut self,
-        surface_id: SurfaceId, // Assuming SurfaceId from novade_compositor_core
+        surface_id: SurfaceId,
         buffer: &ClientBuffer<'_>,
     ) -> Result<Box<dyn RenderableTexture>, RendererError>;
 
@@ -232,8 +289,13 @@ pub trait FrameRenderer: 'static {
     /// `exposure` is an adjustment factor.
     fn apply_hdr_to_sdr_tone_mapping(&mut self, max_luminance: f32, exposure: f32) -> Result<(), RendererError>;
 
+    /// Maps every pixel of the current frame through `lut`, e.g. from the composition space to
+    /// the display's calibrated colour space.
+    fn apply_color_space_conversion(&mut self, _lut: &Lut3d) -> Result<(), RendererError> {
+        Err(RendererError::Unsupported("Color space conversion".to_string()))
+    }
+
     // Add more methods for other effects as needed in the future, e.g.:
-    // fn apply_color_space_conversion(&mut self, target_space: ColorSpace) -> Result<(), RendererError>;
     // fn apply_anti_aliasing(&mut self, method: AntiAliasingMethod) -> Result<(), RendererError>;
-    // fn apply_custom_effect(&mut self, effect_id: &str, params: &EffectParams) -> Result<(), RendererError>;
+    // Per-surface effects (blur, shadows) are render elements instead, see `RenderElement::Shadow`.
 }
@@ -3,8 +3,10 @@
 use novade_compositor_core::surface::{SurfaceId, SurfaceState}; // Assuming SurfaceState exists
 // Assuming Point2D = Point<f32>, Size2D = Size<f32>, Rectangle = Rect<f32> from novade_core
 use novade_core::types::geometry::{Point, Size, Rect};
-use std::collections::{HashMap, HashSet}; // Added HashSet
+use std::collections::HashMap;
 use std::sync::Arc;
+use super::region::Region;
+use super::spatial_index::{compute_visible_regions, OcclusionLayer};
 
 const GRID_CELL_SIZE: f32 = 256.0; // Example cell size
 // MAX_OUTPUT constants are for initial placeholder, actual geometry is used in rebuild.
@@ -12,91 +14,13 @@ const MAX_OUTPUT_WIDTH_FOR_GRID: f32 = 1920.0 * 2.0;
 const MAX_OUTPUT_HEIGHT_FOR_GRID: f32 = 1080.0 * 2.0;
 
 // ANCHOR [SpatialIndexingImplemented]
-#[derive(Debug)]
-pub struct SpatialIndex {
-    grid: HashMap<(i32, i32), Vec<SurfaceId>>,
-    grid_cols: i32,
-    grid_rows: i32,
-    cell_size: f32,
-    indexed_output_geometry: Rectangle,
-}
-
-impl SpatialIndex {
-    // ANCHOR [SpatialIndexingImplemented]
-    pub fn new(output_geometry: &Rectangle, cell_size: f32) -> Self {
-        // Use output_geometry.size.width and .size.height for cols/rows calculation
-        let grid_cols = (output_geometry.size.width / cell_size).ceil() as i32;
-        let grid_rows = (output_geometry.size.height / cell_size).ceil() as i32;
-        SpatialIndex {
-            grid: HashMap::new(),
-            grid_cols,
-            grid_rows,
-            cell_size,
-            indexed_output_geometry: *output_geometry,
-        }
-    }
+// The index itself lives in `spatial_index` so the occlusion pass can be used without a scene graph.
+pub use super::spatial_index::SpatialIndex;
 
-    // ANCHOR [SpatialIndexingImplemented]
+impl SpatialIndex<SurfaceId> {
     pub fn rebuild_corrected(&mut self, nodes: &[Arc<SceneGraphNode>], output_geometry: &Rectangle) {
-        self.grid.clear();
-        self.indexed_output_geometry = *output_geometry;
-        self.grid_cols = (output_geometry.size.width / self.cell_size).ceil() as i32;
-        self.grid_rows = (output_geometry.size.height / self.cell_size).ceil() as i32;
-
-        for node in nodes {
-            // Use node.clipped_rect for indexing, as this is its visible part on the output
-            if node.clipped_rect.size.width == 0.0 || node.clipped_rect.size.height == 0.0 {
-                continue;
-            }
-
-            // Calculate node's clipped_rect relative to the output_geometry's origin for cell indexing
-            let relative_x = node.clipped_rect.origin.x - output_geometry.origin.x;
-            let relative_y = node.clipped_rect.origin.y - output_geometry.origin.y;
-
-            let min_c = (relative_x / self.cell_size).floor() as i32;
-            let max_c = ((relative_x + node.clipped_rect.size.width) / self.cell_size).ceil() as i32;
-            let min_r = (relative_y / self.cell_size).floor() as i32;
-            let max_r = ((relative_y + node.clipped_rect.size.height) / self.cell_size).ceil() as i32;
-
-            for r_idx in min_r..max_r { // Iterate up to max_r (exclusive)
-                for c_idx in min_c..max_c { // Iterate up to max_c (exclusive)
-                    // Ensure cells are within the valid grid range [0, grid_cols-1] and [0, grid_rows-1]
-                    if r_idx >= 0 && r_idx < self.grid_rows && c_idx >= 0 && c_idx < self.grid_cols {
-                        self.grid.entry((c_idx, r_idx)).or_default().push(node.surface_id);
-                    }
-                }
-            }
-        }
-    }
-
-    // ANCHOR [SpatialIndexingImplemented]
-    pub fn query(&self, query_rect: &Rectangle) -> HashSet<SurfaceId> {
-        let mut potential_surfaces = HashSet::new();
-        if query_rect.size.width == 0.0 || query_rect.size.height == 0.0 {
-            return potential_surfaces;
-        }
-
-        // Calculate query_rect's position relative to the indexed_output_geometry's origin
-        let relative_x = query_rect.origin.x - self.indexed_output_geometry.origin.x;
-        let relative_y = query_rect.origin.y - self.indexed_output_geometry.origin.y;
-
-        let min_c = (relative_x / self.cell_size).floor() as i32;
-        let max_c = ((relative_x + query_rect.size.width) / self.cell_size).ceil() as i32;
-        let min_r = (relative_y / self.cell_size).floor() as i32;
-        let max_r = ((relative_y + query_rect.size.height) / self.cell_size).ceil() as i32;
-
-        for r_idx in min_r..max_r {
-            for c_idx in min_c..max_c {
-                 if r_idx >= 0 && r_idx < self.grid_rows && c_idx >= 0 && c_idx < self.grid_cols {
-                    if let Some(surfaces_in_cell) = self.grid.get(&(c_idx, r_idx)) {
-                        for surface_id in surfaces_in_cell {
-                            potential_surfaces.insert(*surface_id);
-                        }
-                    }
-                }
-            }
-        }
-        potential_surfaces
+        // Use node.clipped_rect for indexing, as this is its visible part on the output
+        self.rebuild(nodes.iter().map(|node| (node.surface_id, node.clipped_rect)), output_geometry);
     }
 }
 
@@ -177,6 +101,8 @@ pub struct SceneGraphNode {
     pub children: Vec<Arc<SceneGraphNode>>, // For subsurfaces, if managed directly here
     pub z_order: i32,
     pub is_occluded: bool, // New field
+    /// Part of `clipped_rect` not hidden by opaque nodes above. Empty when `is_occluded`.
+    pub visible_region: Region,
 }
 
 // Helper trait for extended Rectangle methods
@@ -243,7 +169,7 @@ pub struct SurfaceAttributes {
 
 pub struct SceneGraph {
     nodes: Vec<Arc<SceneGraphNode>>, // Flattened list of top-level nodes for rendering, sorted by Z
-    spatial_index: SpatialIndex, // New field
+    spatial_index: SpatialIndex<SurfaceId>, // New field
 }
 
 impl SceneGraph {
@@ -322,6 +248,7 @@ impl SceneGraph {
                         children: Vec::new(), // Still placeholder for subsurfaces
                         z_order: attributes.z_order,
                         is_occluded: false, // Initialize new field
+                        visible_region: Region::from_rect(clipped_rect),
                     });
                 }
             }
@@ -359,83 +286,50 @@ impl SceneGraph {
              // println!("Spatial index unchanged: no nodes and same output geometry.");
         } else { // This case implies nodes.is_empty() but output_geometry *has* changed.
              // So, we need to update the index to reflect the new empty geometry.
-            self.spatial_index.grid.clear(); // Clear out old data
-            self.spatial_index.indexed_output_geometry = *output_geometry;
-            self.spatial_index.grid_cols = (output_geometry.size.width / self.spatial_index.cell_size).ceil() as i32;
-            self.spatial_index.grid_rows = (output_geometry.size.height / self.spatial_index.cell_size).ceil() as i32;
+            self.spatial_index.reset(output_geometry);
             println!("Spatial index reset for new empty output geometry {:?}", output_geometry);
         }
     }
 
-    // Placeholder for occlusion culling
     // ANCHOR [OcclusionCullingImplemented]
-    // TODO [SpatialIndexForOcclusion] Current occlusion culling iterates all higher Z-nodes.
-    // This could be optimized by querying the spatial_index for potential occluders
-    // in the vicinity of the current_node_arc.clipped_rect.
+    // Each node is only compared against the higher nodes the spatial index reports near it, and
+    // their opaque areas are subtracted from its visible region. A node is occluded once nothing
+    // is left, whether one occluder or several cover it.
     fn perform_occlusion_culling(&mut self) {
         if self.nodes.is_empty() {
             return;
         }
 
-        let mut new_nodes_with_occlusion_info = Vec::with_capacity(self.nodes.len());
-
-        for i in 0..self.nodes.len() {
-            let current_node_arc = &self.nodes[i];
-            let mut current_node_is_occluded = false;
-
-            // Use the new RectExt trait method for checking if clipped_rect is empty
-            if current_node_arc.clipped_rect.is_empty_rect() {
-                current_node_is_occluded = true;
-            } else {
-                // Check against nodes with higher Z-order (rendered on top)
-                // These are nodes from index i+1 to self.nodes.len()-1
-                for j in (i + 1)..self.nodes.len() {
-                    let occluder_node_arc = &self.nodes[j];
-
-                    // Only consider occluders that are not already marked occluded themselves
-                    // (though this simple algorithm doesn't mark occluders as occluded based on others above them yet)
-                    // and have a valid, non-empty clipped rectangle.
-                    if occluder_node_arc.clipped_rect.is_empty_rect() { // Using RectExt
-                        continue;
-                    }
-
-                    // Use opaque_region if available, otherwise use clipped_rect as a proxy
-                    // The opaque_region is in local surface coordinates. It needs to be transformed.
-                    let occluder_opaque_rect_in_world = occluder_node_arc.attributes.opaque_region
-                        .as_ref()
-                        .map(|local_opaque_region| {
-                            // Transform local opaque region to world space using the occluder's final_transform
-                            let world_opaque_region = occluder_node_arc.final_transform.transform_rect_bounding_box(*local_opaque_region);
-                            // Then, this world_opaque_region must be clipped by the occluder's own clipped_rect,
-                            // as an occluder cannot make opaque an area where it isn't drawn.
-                            world_opaque_region.intersection(&occluder_node_arc.clipped_rect)
-                                               .unwrap_or_else(|| Rectangle::from_coords(0.0,0.0,0.0,0.0)) // if intersection is None
-                        })
-                        .filter(|r| !r.is_empty_rect()) // Use RectExt, ensure it's not empty after intersection
-                        .unwrap_or(occluder_node_arc.clipped_rect); // Fallback to full clipped_rect if no specific opaque_region or if it became empty
-
-
-                    if occluder_opaque_rect_in_world.is_empty_rect() { // Use RectExt
-                        continue;
-                    }
-
-                    // Check if current_node's clipped_rect is fully contained within this single occluder's effective opaque area.
-                    if current_node_arc.clipped_rect.is_fully_contained_by(&occluder_opaque_rect_in_world) {
-                        current_node_is_occluded = true;
-                        break;
-                    }
-                }
-            }
-
-            // Create a new node with updated occlusion status.
-            // This clones the Arc and then the Node, which is not ideal for performance.
-            // In a real-world scenario, `is_occluded` might use interior mutability (e.g., RefCell<bool>)
-            // or the list of nodes would be rebuilt more carefully.
-            let mut new_node_data = (**current_node_arc).clone(); // Deref Arc, then clone SceneGraphNode
-            new_node_data.is_occluded = current_node_is_occluded;
-            new_nodes_with_occlusion_info.push(Arc::new(new_node_data));
-        }
-        self.nodes = new_nodes_with_occlusion_info;
+        let layers: Vec<OcclusionLayer<SurfaceId>> = self
+            .nodes
+            .iter()
+            .map(|node| {
+                // The opaque_region is in local surface coordinates: transform it to world space and clip
+                // it to where the node is drawn. Nodes without an opaque region count as fully opaque.
+                let opaque = match node.attributes.opaque_region {
+                    Some(local) => node
+                        .final_transform
+                        .transform_rect_bounding_box(local)
+                        .intersection(&node.clipped_rect)
+                        .unwrap_or_else(|| Rectangle::from_coords(0.0, 0.0, 0.0, 0.0)),
+                    None => node.clipped_rect,
+                };
+                OcclusionLayer { key: node.surface_id, bounds: node.clipped_rect, opaque }
+            })
+            .collect();
+        let visible_regions = compute_visible_regions(&layers, &self.spatial_index);
+
+        self.nodes = self
+            .nodes
+            .iter()
+            .zip(visible_regions)
+            .map(|(node, visible_region)| {
+                let mut node = (**node).clone();
+                node.is_occluded = visible_region.is_empty();
+                node.visible_region = visible_region;
+                Arc::new(node)
+            })
+            .collect();
         println!("Occlusion culling performed. {} nodes processed.", self.nodes.len());
     }
 
@@ -473,6 +367,9 @@ mod tests {
                 matrix: [[1.0, 0.0, x], [0.0, 1.0, y]]
             },
             children: Vec::new(),
+            z_order: z,
+            is_occluded: false,
+            visible_region: Region::from_rect(Rectangle::from_coords(x, y, w, h)),
         })
     }
 
@@ -719,6 +616,32 @@ mod tests {
         sg.update(&surface_data_map, &output_geom);
         let node1 = sg.nodes.iter().find(|n| n.surface_id == surf1_id).unwrap();
         assert!(!node1.is_occluded, "Node 1 should not be marked as occluded due to partial overlap by this simple algorithm");
+        // Only the L-shaped part outside (30,30)-(80,80) is left to draw.
+        assert_eq!(node1.visible_region.area(), 50.0 * 50.0 - 30.0 * 30.0);
+        assert!(!node1.visible_region.intersects(&Rectangle::from_coords(30.0, 30.0, 30.0, 30.0)));
+    }
+
+    #[test]
+    fn test_occlusion_culling_by_several_partial_occluders() {
+        let output_geom = Rectangle::from_coords(0.0, 0.0, 800.0, 600.0);
+        let mut sg = SceneGraph::new();
+        let mut surface_data_map = HashMap::new();
+
+        let attributes = |x: f32, w: f32, z: i32, buffer_id: u64| SurfaceAttributes {
+            position: Point2D::new(x, 10.0), size: Size2D::new(w, 50.0),
+            transform: Transform::identity(), is_visible: true, z_order: z,
+            opaque_region: Some(Rectangle::from_coords(0.0, 0.0, w, 50.0)), parent: None,
+            current_buffer_id: Some(buffer_id), buffer_format: Some(BufferFormat::Argb8888), buffer_stride: (w as u32) * 4, buffer_type: Some(BufferSourceType::Shm),
+        };
+        let surf1_id = SurfaceId::new(1); // Covered by the union of 2 and 3, but by neither alone
+        surface_data_map.insert(surf1_id, attributes(10.0, 50.0, 1, 601));
+        surface_data_map.insert(SurfaceId::new(2), attributes(0.0, 40.0, 2, 602));
+        surface_data_map.insert(SurfaceId::new(3), attributes(30.0, 40.0, 3, 603));
+
+        sg.update(&surface_data_map, &output_geom);
+        let node1 = sg.nodes.iter().find(|n| n.surface_id == surf1_id).unwrap();
+        assert!(node1.is_occluded, "Node 1 should be occluded by the union of nodes 2 and 3");
+        assert!(node1.visible_region.is_empty());
     }
 
     #[test]
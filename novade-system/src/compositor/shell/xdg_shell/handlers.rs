@@ -25,6 +25,7 @@ use crate::compositor::{
         DomainWindowIdentifier, ManagedWindow, XdgSurfaceUserData, XdgSurfaceRole, XdgSurfaceState,
     },
     errors::XdgShellError,
+    ipc::protocol::WindowChange,
 };
 // uuid::Uuid is not directly used here anymore, DomainWindowIdentifier::new_v4() handles it.
 
@@ -274,6 +275,7 @@ impl XdgShellHandler for DesktopState {
             {
                 let mut managed_win_state_guard = window_arc.state.write().unwrap();
                 managed_win_state_guard.is_mapped = true;
+                self.animation_manager.animate_map(std::time::Instant::now(), window_arc.domain_id);
                 // ANCHOR: SetActivatedOnMap
                 // Set activated to true when mapped, assuming it will receive focus.
                 managed_win_state_guard.activated = true;
@@ -367,6 +369,7 @@ impl XdgShellHandler for DesktopState {
                 tracing::info!("XDG Toplevel {:?} marked as deactivated due to no keyboard on map.", window_arc.id);
             }
 
+            self.emit_window_event(WindowChange::New, window_arc.domain_id);
             self.space.damage_all_outputs();
         } else {
             tracing::warn!("Map request for an XDG Toplevel whose WlSurface ({:?}) is not associated with any ManagedWindow.",
@@ -421,6 +424,7 @@ impl XdgShellHandler for DesktopState {
             managed_win_state_guard.title = Some(title.clone());
             drop(managed_win_state_guard);
             tracing::info!("Window {:?} requested title change to: {}", window_arc.id, title);
+            self.emit_window_event(WindowChange::Title, window_arc.domain_id);
         }
     }
 
@@ -501,7 +505,9 @@ impl XdgShellHandler for DesktopState {
                 });
 
             // Update ManagedWindow's current geometry (overall window size including SSD)
+            let previous_geometry = window_arc.geometry();
             *window_arc.current_geometry.write().unwrap() = maximized_geometry;
+            self.animation_manager.animate_maximize(std::time::Instant::now(), window_arc.domain_id, previous_geometry, maximized_geometry);
             // Update WindowState's position and size fields to match overall window size
             win_state_guard.position = maximized_geometry.loc;
             win_state_guard.size = maximized_geometry.size;
@@ -534,6 +540,7 @@ impl XdgShellHandler for DesktopState {
                          window_arc.id, serial, configure_size, maximized_geometry);
             self.space.map_window(window_arc.clone(), maximized_geometry.loc, false);
             self.space.damage_all_outputs();
+            self.emit_window_event(WindowChange::State, window_arc.domain_id);
         }
     }
     // ANCHOR_END: HandleSetMaximized
@@ -556,7 +563,9 @@ impl XdgShellHandler for DesktopState {
                 });
 
             // Update ManagedWindow's current geometry (overall window size)
+            let previous_geometry = window_arc.geometry();
             *window_arc.current_geometry.write().unwrap() = restored_geometry;
+            self.animation_manager.animate_maximize(std::time::Instant::now(), window_arc.domain_id, previous_geometry, restored_geometry);
             // Update WindowState's position and size fields
             win_state_guard.position = restored_geometry.loc;
             win_state_guard.size = restored_geometry.size;
@@ -588,6 +597,7 @@ impl XdgShellHandler for DesktopState {
                           window_arc.id, serial, configure_size, restored_geometry);
             self.space.map_window(window_arc.clone(), restored_geometry.loc, false);
             self.space.damage_all_outputs();
+            self.emit_window_event(WindowChange::State, window_arc.domain_id);
         }
     }
     // ANCHOR_END: HandleUnsetMaximized
@@ -609,7 +619,9 @@ impl XdgShellHandler for DesktopState {
             if win_state_guard.is_mapped { // Check if it was mapped
                 win_state_guard.is_mapped = false;
                 win_state_guard.activated = false; // Deactivate on minimize
+                let geometry = *window_arc.current_geometry.read().unwrap();
                 self.space.unmap_window(&window_arc);
+                self.animate_window_close(window_arc.domain_id, geometry);
                 tracing::info!("Window {:?} unmapped due to minimization.", window_arc.id);
             }
             drop(win_state_guard); // Release lock
@@ -624,6 +636,7 @@ impl XdgShellHandler for DesktopState {
 
             tracing::debug!("Window {:?} minimized. Sent configure (serial: {:?}).", window_arc.id, serial);
             self.space.damage_all_outputs(); // Damage where it was, if applicable
+            self.emit_window_event(WindowChange::State, window_arc.domain_id);
         }
     }
     // ANCHOR_END: HandleSetMinimized
@@ -692,6 +705,7 @@ impl XdgShellHandler for DesktopState {
                          window_arc.id, serial, configure_size, fullscreen_geometry);
             self.space.map_window(window_arc.clone(), fullscreen_geometry.loc, true);
             self.space.damage_all_outputs();
+            self.emit_window_event(WindowChange::State, window_arc.domain_id);
         }
     }
     // ANCHOR_END: HandleSetFullscreen
@@ -744,6 +758,7 @@ impl XdgShellHandler for DesktopState {
                          window_arc.id, serial, configure_size, restored_geometry);
             self.space.map_window(window_arc.clone(), restored_geometry.loc, false);
             self.space.damage_all_outputs();
+            self.emit_window_event(WindowChange::State, window_arc.domain_id);
         }
     }
     // ANCHOR_END: HandleUnsetFullscreen
@@ -787,6 +802,7 @@ impl XdgShellHandler for DesktopState {
 
             self.space.unmap_window(&window_arc);
             self.windows.remove(&window_arc.domain_id());
+            self.animation_manager.remove_window(window_arc.domain_id);
             tracing::info!("ManagedWindow {:?} (domain: {:?}) removed due to toplevel destruction.", window_arc.id, window_arc.domain_id());
 
             // ANCHOR: ApplyTilingOnDestroyRefactored
@@ -805,6 +821,7 @@ impl XdgShellHandler for DesktopState {
                  }
             }
             // ANCHOR_END: ApplyTilingOnDestroy
+            self.emit_window_event(WindowChange::Close, window_arc.domain_id);
             self.space.damage_all_outputs();
         } else {
              tracing::warn!("Destroyed toplevel {:?} was not found in self.windows by its WlSurface.", wl_surface.id());
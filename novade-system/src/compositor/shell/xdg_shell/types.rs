@@ -106,6 +106,14 @@ impl DomainWindowIdentifier {
     pub fn new_v4() -> Self {
         Self(Uuid::new_v4())
     }
+
+    pub fn from_uuid(uuid: Uuid) -> Self {
+        Self(uuid)
+    }
+
+    pub fn as_uuid(&self) -> Uuid {
+        self.0
+    }
 }
 
 /// Window state
@@ -0,0 +1,297 @@
+// novade-system/src/compositor/spatial_index.rs
+//! Uniform-grid spatial index over output space and the occlusion pass built on it.
+//!
+//! The scene graph indexes every node by its clipped rectangle; occlusion culling then only
+//! compares a node against the nodes sharing a grid cell with it instead of every node above.
+//! The winit backend uses the same pass through [`visible_windows`] to skip windows that other
+//! windows hide completely.
+
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+
+use novade_core::types::geometry::Rect;
+use smithay::desktop::Space;
+use smithay::output::Output;
+use smithay::utils::Logical;
+use smithay::wayland::compositor::{with_states, RectangleKind, SurfaceAttributes};
+
+use super::region::Region;
+use super::shell::xdg_shell::types::ManagedWindow;
+
+type Rectangle = Rect<f32>;
+
+// ANCHOR [SpatialIndexingImplemented]
+#[derive(Debug)]
+pub struct SpatialIndex<K> {
+    pub(crate) grid: HashMap<(i32, i32), Vec<K>>,
+    pub(crate) grid_cols: i32,
+    pub(crate) grid_rows: i32,
+    pub(crate) cell_size: f32,
+    pub(crate) indexed_output_geometry: Rectangle,
+}
+
+impl<K: Copy + Eq + Hash> SpatialIndex<K> {
+    // ANCHOR [SpatialIndexingImplemented]
+    pub fn new(output_geometry: &Rectangle, cell_size: f32) -> Self {
+        // Use output_geometry.size.width and .size.height for cols/rows calculation
+        let grid_cols = (output_geometry.size.width / cell_size).ceil() as i32;
+        let grid_rows = (output_geometry.size.height / cell_size).ceil() as i32;
+        SpatialIndex {
+            grid: HashMap::new(),
+            grid_cols,
+            grid_rows,
+            cell_size,
+            indexed_output_geometry: *output_geometry,
+        }
+    }
+
+    /// Clears the index and resizes the grid to `output_geometry`.
+    pub fn reset(&mut self, output_geometry: &Rectangle) {
+        self.grid.clear();
+        self.indexed_output_geometry = *output_geometry;
+        self.grid_cols = (output_geometry.size.width / self.cell_size).ceil() as i32;
+        self.grid_rows = (output_geometry.size.height / self.cell_size).ceil() as i32;
+    }
+
+    /// Rebuilds the index for `output_geometry` from `(key, rect)` pairs.
+    pub fn rebuild(&mut self, items: impl IntoIterator<Item = (K, Rectangle)>, output_geometry: &Rectangle) {
+        self.reset(output_geometry);
+        for (key, rect) in items {
+            self.insert(key, &rect);
+        }
+    }
+
+    /// Adds `key` to every cell `rect` overlaps. Empty rectangles are not indexed.
+    pub fn insert(&mut self, key: K, rect: &Rectangle) {
+        if rect.size.width == 0.0 || rect.size.height == 0.0 {
+            return;
+        }
+        let (min_c, max_c, min_r, max_r) = self.cell_range(rect);
+        for r_idx in min_r..max_r { // Iterate up to max_r (exclusive)
+            for c_idx in min_c..max_c { // Iterate up to max_c (exclusive)
+                self.grid.entry((c_idx, r_idx)).or_default().push(key);
+            }
+        }
+    }
+
+    // ANCHOR [SpatialIndexingImplemented]
+    pub fn query(&self, query_rect: &Rectangle) -> HashSet<K> {
+        let mut potential = HashSet::new();
+        if query_rect.size.width == 0.0 || query_rect.size.height == 0.0 {
+            return potential;
+        }
+        let (min_c, max_c, min_r, max_r) = self.cell_range(query_rect);
+        for r_idx in min_r..max_r {
+            for c_idx in min_c..max_c {
+                if let Some(keys_in_cell) = self.grid.get(&(c_idx, r_idx)) {
+                    potential.extend(keys_in_cell.iter().copied());
+                }
+            }
+        }
+        potential
+    }
+
+    /// Cells covered by `rect`, as `(min_col, max_col, min_row, max_row)` with exclusive
+    /// maxima, limited to the grid.
+    fn cell_range(&self, rect: &Rectangle) -> (i32, i32, i32, i32) {
+        // Position relative to the indexed output's origin.
+        let relative_x = rect.origin.x - self.indexed_output_geometry.origin.x;
+        let relative_y = rect.origin.y - self.indexed_output_geometry.origin.y;
+
+        let min_c = ((relative_x / self.cell_size).floor() as i32).max(0);
+        let max_c = (((relative_x + rect.size.width) / self.cell_size).ceil() as i32).min(self.grid_cols);
+        let min_r = ((relative_y / self.cell_size).floor() as i32).max(0);
+        let max_r = (((relative_y + rect.size.height) / self.cell_size).ceil() as i32).min(self.grid_rows);
+        (min_c, max_c, min_r, max_r)
+    }
+}
+
+// ANCHOR: OcclusionCulling
+/// One element of the occlusion pass.
+#[derive(Debug, Clone)]
+pub struct OcclusionLayer<K> {
+    pub key: K,
+    /// Area the element draws to, already clipped to the output.
+    pub bounds: Rectangle,
+    /// Part of `bounds` that fully hides what is below. Empty for translucent elements.
+    pub opaque: Rectangle,
+}
+
+/// Computes the visible region of every layer. `layers` are ordered bottom to top and
+/// `index` must contain each layer's `bounds` under its key.
+///
+/// Each layer starts with its bounds and subtracts the opaque rectangles of the layers above
+/// it that the index reports near it, so a layer hidden by several partial occluders ends up
+/// with an empty region.
+pub fn compute_visible_regions<K: Copy + Eq + Hash>(layers: &[OcclusionLayer<K>], index: &SpatialIndex<K>) -> Vec<Region> {
+    let position: HashMap<K, usize> = layers.iter().enumerate().map(|(i, layer)| (layer.key, i)).collect();
+    let mut visible = Vec::with_capacity(layers.len());
+    let mut above: Vec<usize> = Vec::new();
+
+    for (i, layer) in layers.iter().enumerate() {
+        let mut region = Region::from_rect(layer.bounds);
+        above.clear();
+        above.extend(index.query(&layer.bounds).into_iter().filter_map(|key| position.get(&key).copied()).filter(|&j| j > i));
+        // Large occluders first so the region empties (and the loop stops) early.
+        above.sort_unstable_by(|a, b| {
+            let area = |j: usize| layers[j].opaque.size.width * layers[j].opaque.size.height;
+            area(*b).total_cmp(&area(*a))
+        });
+        for &j in &above {
+            region.subtract_rect(&layers[j].opaque);
+            if region.is_empty() {
+                break;
+            }
+        }
+        visible.push(region);
+    }
+    visible
+}
+// ANCHOR_END: OcclusionCulling
+
+// ANCHOR: WindowOcclusionCulling
+/// Grid cell size for culling windows, in logical pixels.
+const WINDOW_CULLING_CELL_SIZE: f32 = 256.0;
+
+fn to_rect(rect: smithay::utils::Rectangle<i32, Logical>) -> Rectangle {
+    Rect::from_coords(rect.loc.x as f32, rect.loc.y as f32, rect.size.w as f32, rect.size.h as f32)
+}
+
+/// Largest rectangle of `window`'s opaque region within `bounds`, in space coordinates. Only
+/// the opaque region of the window's root surface is considered, without the rows its
+/// `corner_radius` cuts into.
+fn window_opaque_rect(space: &Space<ManagedWindow>, window: &ManagedWindow, bounds: &Rectangle, corner_radius: f32) -> Rectangle {
+    let empty = Rect::from_coords(0.0, 0.0, 0.0, 0.0);
+    let (Some(surface), Some(location)) = (window.wl_surface(), space.element_location(window)) else { return empty };
+    let bounds = if corner_radius > 0.0 {
+        let size = window.geometry().size;
+        let square = Rect::from_coords(
+            location.x as f32,
+            location.y as f32 + corner_radius,
+            size.w as f32,
+            size.h as f32 - 2.0 * corner_radius,
+        );
+        match bounds.intersection(&square) {
+            Some(bounds) => bounds,
+            None => return empty,
+        }
+    } else {
+        *bounds
+    };
+    let origin = location - window.geometry().loc;
+    let mut opaque = Region::new();
+    with_states(&surface, |states| {
+        if let Some(region) = states.cached_state.get::<SurfaceAttributes>().current().opaque_region.as_ref() {
+            for (kind, rect) in &region.rects {
+                let mut rect = to_rect(*rect);
+                rect.origin.x += origin.x as f32;
+                rect.origin.y += origin.y as f32;
+                match kind {
+                    RectangleKind::Add => opaque.union_rect(&rect),
+                    RectangleKind::Subtract => opaque.subtract_rect(&rect),
+                }
+            }
+        }
+    });
+    opaque
+        .intersect_rect(&bounds)
+        .rects()
+        .iter()
+        .copied()
+        .max_by(|a, b| (a.size.width * a.size.height).total_cmp(&(b.size.width * b.size.height)))
+        .unwrap_or(empty)
+}
+
+/// Windows shown on `output`, topmost first, without the ones the opaque regions of windows
+/// above hide completely. Animating windows are drawn away from their real geometry and
+/// possibly translucent, so they are always kept and never occlude. `corner_radius` gives the
+/// rounding windows are drawn with, whose corners hide nothing.
+pub fn visible_windows<'a>(
+    space: &'a Space<ManagedWindow>,
+    output: &Output,
+    is_animating: impl Fn(&ManagedWindow) -> bool,
+    corner_radius: impl Fn(&ManagedWindow) -> f32,
+) -> Vec<&'a ManagedWindow> {
+    let Some(output_geometry) = space.output_geometry(output).map(to_rect) else { return Vec::new() };
+    // Bottom to top, as the occlusion pass expects.
+    let windows: Vec<&ManagedWindow> = space.elements_for_output(output).collect();
+    let layers: Vec<OcclusionLayer<usize>> = windows
+        .iter()
+        .enumerate()
+        .filter_map(|(key, window)| {
+            let bounds = to_rect(space.element_bbox(window)?).intersection(&output_geometry)?;
+            let opaque = if is_animating(window) {
+                Rect::from_coords(0.0, 0.0, 0.0, 0.0)
+            } else {
+                window_opaque_rect(space, window, &bounds, corner_radius(window))
+            };
+            Some(OcclusionLayer { key, bounds, opaque })
+        })
+        .collect();
+    let mut index = SpatialIndex::new(&output_geometry, WINDOW_CULLING_CELL_SIZE);
+    index.rebuild(layers.iter().map(|layer| (layer.key, layer.bounds)), &output_geometry);
+    let visible = compute_visible_regions(&layers, &index);
+    layers
+        .iter()
+        .zip(visible)
+        .rev()
+        .filter(|(layer, region)| !region.is_empty() || is_animating(windows[layer.key]))
+        .map(|(layer, _)| windows[layer.key])
+        .collect()
+}
+// ANCHOR_END: WindowOcclusionCulling
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(x: f32, y: f32, w: f32, h: f32) -> Rectangle {
+        Rect::from_coords(x, y, w, h)
+    }
+
+    fn layer(key: u32, bounds: Rectangle, opaque: bool) -> OcclusionLayer<u32> {
+        OcclusionLayer { key, bounds, opaque: if opaque { bounds } else { rect(0.0, 0.0, 0.0, 0.0) } }
+    }
+
+    fn cull(layers: &[OcclusionLayer<u32>]) -> Vec<Region> {
+        let output = rect(0.0, 0.0, 1920.0, 1080.0);
+        let mut index = SpatialIndex::new(&output, 256.0);
+        index.rebuild(layers.iter().map(|l| (l.key, l.bounds)), &output);
+        compute_visible_regions(layers, &index)
+    }
+
+    #[test]
+    fn test_query_ignores_cells_outside_grid() {
+        let output = rect(0.0, 0.0, 512.0, 512.0);
+        let mut index = SpatialIndex::new(&output, 256.0);
+        index.insert(1u32, &rect(-100.0, -100.0, 150.0, 150.0));
+        index.insert(2u32, &rect(600.0, 600.0, 10.0, 10.0));
+        assert_eq!(index.query(&rect(0.0, 0.0, 10.0, 10.0)), HashSet::from([1]));
+        assert!(index.grid.keys().all(|(c, r)| *c >= 0 && *r >= 0 && *c < 2 && *r < 2));
+    }
+
+    #[test]
+    fn test_partially_covered_layer_is_clipped() {
+        let visible = cull(&[layer(1, rect(0.0, 0.0, 100.0, 100.0), true), layer(2, rect(50.0, 0.0, 100.0, 100.0), true)]);
+        assert_eq!(visible[0].area(), 50.0 * 100.0);
+        assert_eq!(visible[0].bounding_box(), Some(rect(0.0, 0.0, 50.0, 100.0)));
+        assert_eq!(visible[1].area(), 100.0 * 100.0);
+    }
+
+    #[test]
+    fn test_layer_hidden_by_several_occluders() {
+        let visible = cull(&[
+            layer(1, rect(100.0, 100.0, 400.0, 300.0), true),
+            layer(2, rect(0.0, 0.0, 300.0, 1080.0), true),
+            layer(3, rect(300.0, 0.0, 600.0, 1080.0), true),
+        ]);
+        assert!(visible[0].is_empty());
+        assert!(!visible[1].is_empty() && !visible[2].is_empty());
+    }
+
+    #[test]
+    fn test_translucent_layer_does_not_occlude() {
+        let visible = cull(&[layer(1, rect(0.0, 0.0, 100.0, 100.0), true), layer(2, rect(0.0, 0.0, 200.0, 200.0), false)]);
+        assert_eq!(visible[0].area(), 100.0 * 100.0);
+    }
+}
@@ -83,6 +83,47 @@ pub fn calculate_master_stack_layout(
     layouts
 }
 
+/// Shrinks `rect` by `amount` logical pixels on every side, never below 1x1.
+pub fn inset_rect(rect: Rectangle<i32, Logical>, amount: i32) -> Rectangle<i32, Logical> {
+    if amount <= 0 {
+        return rect;
+    }
+    let w = (rect.size.w - 2 * amount).max(1);
+    let h = (rect.size.h - 2 * amount).max(1);
+    Rectangle::from_loc_and_size((rect.loc.x + amount, rect.loc.y + amount), (w, h))
+}
+
+/// Separates adjacent tiles by `inner` pixels: each tile gives up half the gap on every side,
+/// so two neighbours end up exactly `inner` apart. Edges on the boundary of the tiled area are
+/// left alone; the outer gap is applied to the area beforehand.
+pub fn apply_inner_gaps(layouts: &mut HashMap<DomainWindowIdentifier, Rectangle<i32, Logical>>, inner: i32) {
+    if inner <= 0 || layouts.len() < 2 {
+        return;
+    }
+    let area = layouts.values().fold(None::<Rectangle<i32, Logical>>, |acc, r| {
+        Some(acc.map_or(*r, |a| a.merge(*r)))
+    });
+    let area = match area {
+        Some(a) => a,
+        None => return,
+    };
+    let half = inner / 2;
+    let other_half = inner - half;
+    for rect in layouts.values_mut() {
+        if rect.size.w <= 0 || rect.size.h <= 0 {
+            continue;
+        }
+        let left = if rect.loc.x > area.loc.x { other_half } else { 0 };
+        let top = if rect.loc.y > area.loc.y { other_half } else { 0 };
+        let right = if rect.loc.x + rect.size.w < area.loc.x + area.size.w { half } else { 0 };
+        let bottom = if rect.loc.y + rect.size.h < area.loc.y + area.size.h { half } else { 0 };
+        *rect = Rectangle::from_loc_and_size(
+            (rect.loc.x + left, rect.loc.y + top),
+            ((rect.size.w - left - right).max(1), (rect.size.h - top - bottom).max(1)),
+        );
+    }
+}
+
 // ANCHOR: ApplyLayoutForOutputSignature
 /// Applies the active tiling layout to windows on the specified output's active workspace.
 pub fn apply_layout_for_output(
@@ -174,14 +215,20 @@ pub fn apply_layout_for_output(
 
     // For tiling, calculations are done relative to the output's origin (0,0)
     // then translated to global coordinates.
-    let tiling_area_for_calc = Rectangle::from_loc_and_size(Point::default(), output_geometry_in_global_space.size);
+    let gaps = desktop_state.config.visual.gaps;
+    let tiling_area_for_calc = inset_rect(
+        Rectangle::from_loc_and_size(Point::default(), output_geometry_in_global_space.size),
+        gaps.outer,
+    );
+    let master_factor = desktop_state.config.layout.master_factor_for_output(output_name);
 
-    let new_geometries_relative_to_output = match layout_mode {
+    let mut new_geometries_relative_to_output = match layout_mode {
         TilingLayout::MasterStack => {
-            calculate_master_stack_layout(&windows_to_layout, tiling_area_for_calc, 0.6)
+            calculate_master_stack_layout(&windows_to_layout, tiling_area_for_calc, master_factor)
         }
         TilingLayout::None => unreachable!(),
     };
+    apply_inner_gaps(&mut new_geometries_relative_to_output, gaps.inner);
 
     for window_arc in &windows_to_layout {
         if let Some(new_geom_relative) = new_geometries_relative_to_output.get(&window_arc.domain_id) {
@@ -199,7 +246,11 @@ pub fn apply_layout_for_output(
                 tracing::info!("Tiling: Applying geometry {:?} to window {:?} (Domain ID: {:?}) on workspace {}",
                     new_geom, window_arc.id, window_arc.domain_id, active_workspace_id);
 
+                let previous_geom = *window_arc.current_geometry.read().unwrap();
                 *window_arc.current_geometry.write().unwrap() = *new_geom;
+                if window_arc.is_mapped() && previous_geom.size.w > 0 && previous_geom.size.h > 0 {
+                    desktop_state.animation_manager.animate_layout(std::time::Instant::now(), window_arc.domain_id, previous_geom, *new_geom);
+                }
                 let mut win_state = window_arc.state.write().unwrap();
                 win_state.position = new_geom.loc;
                 win_state.size = new_geom.size;
@@ -2,8 +2,12 @@
 //! Defines the compositor-specific workspace structures.
 
 use std::sync::{Arc, RwLock};
+use novade_domain::WorkspaceOutputPolicy;
+use smithay::utils::{Logical, Point, SERIAL_COUNTER};
 use uuid::Uuid;
 use crate::compositor::shell::xdg_shell::types::DomainWindowIdentifier; // Adjusted path
+use crate::compositor::ipc::protocol::{IpcEvent, WindowChange, WorkspaceChange};
+use crate::compositor::state::DesktopState;
 
 /// Represents a single workspace within the compositor.
 /// This is a runtime structure for managing live windows on a workspace.
@@ -14,6 +18,9 @@ pub struct CompositorWorkspace {
     // ANCHOR: AddOutputNameToCompositorWorkspace
     pub output_name: String, // Name of the output this workspace belongs to
     // ANCHOR_END: AddOutputNameToCompositorWorkspace
+    /// Output the workspace returns to when that output is connected again. Set on creation
+    /// and when the user moves the workspace, not when it is migrated off an unplugged output.
+    pub preferred_output: String,
     /// List of window identifiers belonging to this workspace.
     /// `DomainWindowIdentifier` is used to refer to `ManagedWindow`s stored in `DesktopState`.
     pub windows: RwLock<Vec<DomainWindowIdentifier>>,
@@ -40,6 +47,7 @@ impl CompositorWorkspace {
         Self {
             id: Uuid::new_v4(),
             name,
+            preferred_output: output_name.clone(),
             output_name,
             windows: RwLock::new(Vec::new()),
             tiling_layout: Arc::new(RwLock::new(TilingLayout::None)), // Default to floating
@@ -72,6 +80,469 @@ impl CompositorWorkspace {
 }
 // ANCHOR_END: CompositorWorkspaceDefinition
 
+// ANCHOR: DesktopStateWorkspaceOperations
+impl DesktopState {
+    /// Returns the domain ID of the window that currently has keyboard focus.
+    pub fn focused_domain_window_id(&self) -> Option<DomainWindowIdentifier> {
+        let keyboard = self.primary_seat.get_keyboard()?;
+        let surface = keyboard.current_focus()?;
+        self.windows
+            .values()
+            .find(|mw| mw.wl_surface_ref() == Some(&surface))
+            .map(|mw| mw.domain_id)
+    }
+
+    /// The output commands without an explicit output apply to: the output of the focused
+    /// window, else the output under the pointer, else the primary output.
+    pub fn current_output_name(&self) -> Option<String> {
+        if let Some(name) = self
+            .focused_domain_window_id()
+            .and_then(|id| self.windows.get(&id))
+            .and_then(|w| w.output_name.read().unwrap().clone())
+        {
+            return Some(name);
+        }
+        let space = self.space.lock().unwrap();
+        if let Some(output) = space.output_under(self.pointer_location).next() {
+            return Some(output.name());
+        }
+        drop(space);
+        self.primary_output_name.read().unwrap().clone()
+    }
+
+    /// Gives keyboard focus to a window and raises it, switching to its workspace if needed.
+    pub fn focus_domain_window(&mut self, window_id: DomainWindowIdentifier) {
+        let window_arc = match self.windows.get(&window_id) {
+            Some(w) => w.clone(),
+            None => {
+                tracing::warn!("focus_domain_window: unknown window {:?}.", window_id);
+                return;
+            }
+        };
+
+        let output_name = window_arc.output_name.read().unwrap().clone();
+        let workspace_id = *window_arc.workspace_id.read().unwrap();
+        if let (Some(output_name), Some(workspace_id)) = (output_name, workspace_id) {
+            let is_active = self.active_workspaces.read().unwrap().get(&output_name) == Some(&workspace_id);
+            if !is_active {
+                self.switch_workspace_on_output(&output_name, workspace_id);
+            }
+        }
+
+        self.space.lock().unwrap().raise_element(&window_arc, true);
+        if let (Some(keyboard), Some(surface)) = (self.primary_seat.get_keyboard(), window_arc.wl_surface_ref().cloned()) {
+            keyboard.set_focus(self, Some(surface), SERIAL_COUNTER.next_serial());
+        }
+        self.emit_window_event(WindowChange::Focus, window_id);
+    }
+
+    /// Makes `workspace_id` the active workspace of `output_name`.
+    ///
+    /// Windows of the previously active workspace are unmapped from the space, windows of the
+    /// new one are mapped and the tiling layout is re-applied. Returns `false` if the workspace
+    /// does not belong to the output.
+    pub fn switch_workspace_on_output(&mut self, output_name: &str, workspace_id: Uuid) -> bool {
+        let belongs_to_output = self
+            .output_workspaces
+            .get(output_name)
+            .map_or(false, |list| list.iter().any(|ws| ws.read().unwrap().id == workspace_id));
+        if !belongs_to_output {
+            tracing::warn!("switch_workspace_on_output: workspace {} is not on output {}.", workspace_id, output_name);
+            return false;
+        }
+
+        let previous = self.active_workspaces.write().unwrap().insert(output_name.to_string(), workspace_id);
+        if previous == Some(workspace_id) {
+            return true;
+        }
+
+        // Slide in from the side the new workspace sits on.
+        let direction = self.output_workspaces.get(output_name).map_or(1, |list| {
+            let index_of = |id: Option<Uuid>| list.iter().position(|ws| Some(ws.read().unwrap().id) == id);
+            match (index_of(previous), index_of(Some(workspace_id))) {
+                (Some(old), Some(new)) if new < old => -1,
+                _ => 1,
+            }
+        });
+        let now = std::time::Instant::now();
+
+        {
+            let mut space = self.space.lock().unwrap();
+            let output_width = space
+                .outputs()
+                .find(|o| o.name() == output_name)
+                .and_then(|o| space.output_geometry(o))
+                .map_or(0, |geo| geo.size.w);
+            for window_arc in self.windows.values() {
+                if window_arc.output_name.read().unwrap().as_deref() != Some(output_name) {
+                    continue;
+                }
+                let window_ws = *window_arc.workspace_id.read().unwrap();
+                if window_ws == previous && previous.is_some() {
+                    space.unmap_elem(window_arc);
+                } else if window_ws == Some(workspace_id) && !window_arc.state.read().unwrap().minimized {
+                    let loc = window_arc.current_geometry.read().unwrap().loc;
+                    space.map_element(window_arc.clone(), loc, false);
+                    self.animation_manager.animate_workspace_enter(now, window_arc.domain_id, output_width, direction);
+                }
+            }
+        }
+
+        tracing::info!("Output {} switched to workspace {} (was {:?}).", output_name, workspace_id, previous);
+        crate::compositor::tiling::apply_layout_for_output(self, output_name);
+        self.emit_workspace_focus_event(output_name, workspace_id);
+        true
+    }
+
+    /// Moves a window to another workspace, possibly on another output.
+    ///
+    /// The window is mapped on the target output if the target workspace is active there, and
+    /// unmapped otherwise.
+    /// Layouts of both the source and the target output are re-applied.
+    pub fn move_window_to_workspace(&mut self, window_id: DomainWindowIdentifier, workspace_id: Uuid) -> bool {
+        let window_arc = match self.windows.get(&window_id) {
+            Some(w) => w.clone(),
+            None => return false,
+        };
+
+        let target = self.output_workspaces.iter().find_map(|(output_name, list)| {
+            list.iter()
+                .find(|ws| ws.read().unwrap().id == workspace_id)
+                .map(|ws| (output_name.clone(), ws.clone()))
+        });
+        let (target_output, target_ws) = match target {
+            Some(t) => t,
+            None => {
+                tracing::warn!("move_window_to_workspace: workspace {} not found.", workspace_id);
+                return false;
+            }
+        };
+
+        let source_ws_id = *window_arc.workspace_id.read().unwrap();
+        let source_output = window_arc.output_name.read().unwrap().clone();
+        if source_ws_id == Some(workspace_id) {
+            return true;
+        }
+        if let Some(source_ws_id) = source_ws_id {
+            for list in self.output_workspaces.values() {
+                if let Some(ws) = list.iter().find(|ws| ws.read().unwrap().id == source_ws_id) {
+                    ws.read().unwrap().remove_window(&window_id);
+                }
+            }
+        }
+
+        target_ws.read().unwrap().add_window(window_id);
+        *window_arc.workspace_id.write().unwrap() = Some(workspace_id);
+        *window_arc.output_name.write().unwrap() = Some(target_output.clone());
+
+        let target_is_active = self.active_workspaces.read().unwrap().get(&target_output) == Some(&workspace_id);
+        {
+            let mut space = self.space.lock().unwrap();
+            // Keep the window's position relative to its output when it changes outputs.
+            if let Some(source_output) = source_output.as_deref().filter(|o| *o != target_output) {
+                let location_of = |name: &str| {
+                    space.outputs().find(|o| o.name() == name).and_then(|o| space.output_geometry(o)).map(|geo| geo.loc)
+                };
+                if let (Some(from_loc), Some(to_loc)) = (location_of(source_output), location_of(&target_output)) {
+                    window_arc.current_geometry.write().unwrap().loc += to_loc - from_loc;
+                }
+            }
+            if target_is_active && !window_arc.state.read().unwrap().minimized {
+                let loc = window_arc.current_geometry.read().unwrap().loc;
+                space.map_element(window_arc.clone(), loc, false);
+            } else {
+                space.unmap_elem(&window_arc);
+            }
+        }
+
+        tracing::info!("Moved window {:?} from workspace {:?} to {} on output {}.", window_id, source_ws_id, workspace_id, target_output);
+        if let Some(source_output) = source_output.filter(|o| *o != target_output) {
+            crate::compositor::tiling::apply_layout_for_output(self, &source_output);
+        }
+        crate::compositor::tiling::apply_layout_for_output(self, &target_output);
+        self.emit_window_event(WindowChange::Move, window_id);
+        true
+    }
+}
+// ANCHOR_END: DesktopStateWorkspaceOperations
+
+// ANCHOR: DesktopStateOutputWorkspaces
+impl DesktopState {
+    /// Names of the outputs mapped in the space, ordered left to right, then top to bottom.
+    pub fn output_names(&self) -> Vec<String> {
+        let space = self.space.lock().unwrap();
+        let mut outputs: Vec<(Point<i32, Logical>, String)> = space
+            .outputs()
+            .map(|o| (space.output_geometry(o).map_or_else(Point::default, |geo| geo.loc), o.name()))
+            .collect();
+        outputs.sort_by_key(|(loc, name)| (loc.x, loc.y, name.clone()));
+        outputs.into_iter().map(|(_, name)| name).collect()
+    }
+
+    /// Resolves an output argument: an output name, or `"next"` / `"previous"` relative to the
+    /// current output (wrapping around).
+    pub fn resolve_output_name(&self, output: &str) -> Option<String> {
+        let outputs = self.output_names();
+        let step: isize = match output {
+            "next" => 1,
+            "previous" | "prev" => -1,
+            name => return outputs.iter().find(|o| o.as_str() == name).cloned(),
+        };
+        let current = self.current_output_name()?;
+        let index = outputs.iter().position(|o| *o == current)? as isize;
+        let len = outputs.len() as isize;
+        outputs.get((index + step).rem_euclid(len) as usize).cloned()
+    }
+
+    /// Output a new workspace is created on, per `layout.new_workspace_output`.
+    pub fn output_for_new_workspace(&self) -> Option<String> {
+        if self.config.layout.new_workspace_output == WorkspaceOutputPolicy::FollowMouse {
+            let space = self.space.lock().unwrap();
+            if let Some(output) = space.output_under(self.pointer_location).next() {
+                return Some(output.name());
+            }
+        }
+        self.current_output_name()
+    }
+
+    /// Appends a workspace to `output_name` using the output's configured default layout.
+    /// The workspace is not activated.
+    pub fn create_workspace_on_output(&mut self, name: String, output_name: &str) -> Uuid {
+        let workspace = CompositorWorkspace::new(name, output_name.to_string());
+        *workspace.tiling_layout.write().unwrap() = self.config.layout.layout_for_output(output_name);
+        let id = workspace.id;
+        self.output_workspaces.entry(output_name.to_string()).or_default().push(Arc::new(RwLock::new(workspace)));
+        self.emit_ipc_event(IpcEvent::Workspace {
+            change: WorkspaceChange::Created,
+            id: Some(id),
+            output: Some(output_name.to_string()),
+            domain_event: None,
+        });
+        id
+    }
+
+    /// Creates a workspace on the output chosen by the new-workspace policy and switches to it.
+    pub fn create_workspace(&mut self) -> Option<Uuid> {
+        let output_name = self.output_for_new_workspace()?;
+        let name = (self.output_workspaces.get(&output_name).map_or(0, Vec::len) + 1).to_string();
+        let id = self.create_workspace_on_output(name, &output_name);
+        self.switch_workspace_on_output(&output_name, id);
+        Some(id)
+    }
+
+    /// Sets up the workspaces of a newly connected output.
+    ///
+    /// Workspaces that prefer this output come back to it, workspaces left without an output
+    /// (after the last one was unplugged) are adopted, and if the output still has none the
+    /// configured number of workspaces is created. The workspace that was active when the
+    /// output was unplugged becomes active again.
+    pub fn ensure_workspaces_for_output(&mut self, output_name: &str) {
+        let connected = self.output_names();
+        let returning: Vec<(String, Uuid)> = self
+            .output_workspaces
+            .iter()
+            .filter(|(current, _)| current.as_str() != output_name)
+            .flat_map(|(current, list)| {
+                let orphaned = !connected.contains(current);
+                list.iter()
+                    .map(|ws| ws.read().unwrap())
+                    .filter(|ws| orphaned || ws.preferred_output == output_name)
+                    .map(|ws| (current.clone(), ws.id))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        let mut sources = Vec::new();
+        for (source, workspace_id) in returning {
+            self.relocate_workspace(workspace_id, &source, output_name);
+            if !sources.contains(&source) {
+                sources.push(source);
+            }
+        }
+        for source in sources {
+            if connected.contains(&source) {
+                self.ensure_active_workspace(&source);
+            } else if self.output_workspaces.get(&source).map_or(false, Vec::is_empty) {
+                self.output_workspaces.remove(&source);
+            }
+        }
+
+        if self.output_workspaces.get(output_name).map_or(true, Vec::is_empty) {
+            let count = self.config.layout.workspaces_for_output(output_name).max(1);
+            for index in 1..=count {
+                self.create_workspace_on_output(index.to_string(), output_name);
+            }
+        }
+
+        if self.primary_output_name.read().unwrap().is_none() {
+            *self.primary_output_name.write().unwrap() = Some(output_name.to_string());
+        }
+
+        // Drop a stale entry so the switch below maps the windows of the restored workspace.
+        self.active_workspaces.write().unwrap().remove(output_name);
+        let remembered = self.parked_active_workspaces.remove(output_name);
+        let list = &self.output_workspaces[output_name];
+        let target = remembered
+            .filter(|id| list.iter().any(|ws| ws.read().unwrap().id == *id))
+            .unwrap_or_else(|| list[0].read().unwrap().id);
+        self.switch_workspace_on_output(output_name, target);
+    }
+
+    /// Moves all workspaces of an output that is about to be unplugged to another output.
+    ///
+    /// The workspaces keep their preferred output so they return when it is plugged in again;
+    /// until then they are inactive on the target. Without another output they stay parked
+    /// under the old name and are adopted by the next output that connects.
+    pub fn migrate_workspaces_from_output(&mut self, output_name: &str) {
+        if let Some(active) = self.active_workspaces.write().unwrap().remove(output_name) {
+            self.parked_active_workspaces.insert(output_name.to_string(), active);
+        }
+        let remaining: Vec<String> = self.output_names().into_iter().filter(|o| o != output_name).collect();
+        let primary = self.primary_output_name.read().unwrap().clone();
+        let target = primary.filter(|p| remaining.contains(p)).or_else(|| remaining.first().cloned());
+        *self.primary_output_name.write().unwrap() = target.clone();
+
+        let workspace_ids: Vec<Uuid> = self
+            .output_workspaces
+            .get(output_name)
+            .map(|list| list.iter().map(|ws| ws.read().unwrap().id).collect())
+            .unwrap_or_default();
+        let target = match target {
+            Some(target) => target,
+            None => {
+                let mut space = self.space.lock().unwrap();
+                for window_arc in self.windows.values() {
+                    if window_arc.output_name.read().unwrap().as_deref() == Some(output_name) {
+                        space.unmap_elem(window_arc);
+                    }
+                }
+                tracing::info!("Last output {} removed, {} workspaces parked.", output_name, workspace_ids.len());
+                return;
+            }
+        };
+
+        for workspace_id in &workspace_ids {
+            self.relocate_workspace(*workspace_id, output_name, &target);
+        }
+        self.output_workspaces.remove(output_name);
+        tracing::info!("Migrated {} workspaces from {} to {}.", workspace_ids.len(), output_name, target);
+        crate::compositor::tiling::apply_layout_for_output(self, &target);
+    }
+
+    /// Moves a workspace, with its windows, to another output and shows it there.
+    ///
+    /// The workspace becomes its new preferred output. The source output switches to another
+    /// of its workspaces, or gets a new empty one if the moved workspace was its last.
+    pub fn move_workspace_to_output(&mut self, workspace_id: Uuid, target_output: &str) -> Result<(), String> {
+        if !self.output_names().iter().any(|o| o == target_output) {
+            return Err(format!("output {} is not connected", target_output));
+        }
+        let source_output = self
+            .output_workspaces
+            .iter()
+            .find(|(_, list)| list.iter().any(|ws| ws.read().unwrap().id == workspace_id))
+            .map(|(name, _)| name.clone())
+            .ok_or_else(|| format!("no workspace with id {}", workspace_id))?;
+
+        let workspace = self.relocate_workspace(workspace_id, &source_output, target_output).expect("workspace was found above");
+        workspace.write().unwrap().preferred_output = target_output.to_string();
+        if source_output == target_output {
+            return Ok(());
+        }
+
+        self.ensure_active_workspace(&source_output);
+        self.switch_workspace_on_output(target_output, workspace_id);
+        tracing::info!("Moved workspace {} from output {} to {}.", workspace_id, source_output, target_output);
+        self.emit_ipc_event(IpcEvent::Workspace {
+            change: WorkspaceChange::Move,
+            id: Some(workspace_id),
+            output: Some(target_output.to_string()),
+            domain_event: None,
+        });
+        Ok(())
+    }
+
+    /// Gives `output_name` keyboard focus: focuses the topmost window of its active workspace
+    /// and warps the pointer to the output's center.
+    pub fn focus_output(&mut self, output_name: &str) -> Result<(), String> {
+        let center = {
+            let space = self.space.lock().unwrap();
+            let output = space.outputs().find(|o| o.name() == output_name).ok_or_else(|| format!("output {} is not connected", output_name))?;
+            let geo = space.output_geometry(output).ok_or_else(|| format!("output {} is not mapped", output_name))?;
+            geo.loc.to_f64() + geo.size.to_f64().downscale(2.0).to_point()
+        };
+        self.pointer_location = center;
+
+        let active = self.active_workspaces.read().unwrap().get(output_name).copied();
+        let top_window = {
+            let space = self.space.lock().unwrap();
+            space
+                .elements()
+                .rev()
+                .find(|w| w.output_name.read().unwrap().as_deref() == Some(output_name) && *w.workspace_id.read().unwrap() == active)
+                .map(|w| w.domain_id)
+        };
+        match top_window {
+            Some(window_id) => self.focus_domain_window(window_id),
+            None => {
+                if let Some(keyboard) = self.primary_seat.get_keyboard() {
+                    keyboard.set_focus(self, None, SERIAL_COUNTER.next_serial());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Makes sure `output_name` shows one of its own workspaces, creating an empty one if it
+    /// has none left.
+    fn ensure_active_workspace(&mut self, output_name: &str) {
+        let list = self.output_workspaces.get(output_name).cloned().unwrap_or_default();
+        let active = self.active_workspaces.read().unwrap().get(output_name).copied();
+        if active.map_or(false, |id| list.iter().any(|ws| ws.read().unwrap().id == id)) {
+            return;
+        }
+        self.active_workspaces.write().unwrap().remove(output_name);
+        let next = match list.first() {
+            Some(ws) => ws.read().unwrap().id,
+            None => self.create_workspace_on_output("1".to_string(), output_name),
+        };
+        self.switch_workspace_on_output(output_name, next);
+    }
+
+    /// Moves a workspace between the per-output lists without activating it. Its windows are
+    /// unmapped, re-assigned to `to` and shifted by the offset between the two outputs.
+    fn relocate_workspace(&mut self, workspace_id: Uuid, from: &str, to: &str) -> Option<Arc<RwLock<CompositorWorkspace>>> {
+        let list = self.output_workspaces.get_mut(from)?;
+        let index = list.iter().position(|ws| ws.read().unwrap().id == workspace_id)?;
+        if from == to {
+            return Some(list[index].clone());
+        }
+        let workspace = list.remove(index);
+        workspace.write().unwrap().output_name = to.to_string();
+
+        {
+            let mut space = self.space.lock().unwrap();
+            let location_of = |name: &str| {
+                space.outputs().find(|o| o.name() == name).and_then(|o| space.output_geometry(o)).map(|geo| geo.loc)
+            };
+            let offset = match (location_of(from), location_of(to)) {
+                (Some(from_loc), Some(to_loc)) => to_loc - from_loc,
+                _ => Point::default(),
+            };
+            for window_id in workspace.read().unwrap().window_ids() {
+                if let Some(window_arc) = self.windows.get(&window_id) {
+                    *window_arc.output_name.write().unwrap() = Some(to.to_string());
+                    window_arc.current_geometry.write().unwrap().loc += offset;
+                    space.unmap_elem(window_arc);
+                }
+            }
+        }
+
+        self.output_workspaces.entry(to.to_string()).or_default().push(workspace.clone());
+        Some(workspace)
+    }
+}
+// ANCHOR_END: DesktopStateOutputWorkspaces
+
 // ANCHOR: ModRsWorkspacesModule
 // This file (workspaces.rs) should be part of a module.
 // If novade-system/src/compositor/mod.rs exists, add `pub mod workspaces;` there.
@@ -11,8 +11,19 @@ use zbus::{object_server::{Guard as ObjectServerGuard, ObjectServer}, Connection
 use zbus::names::WellKnownName;
 use std::sync::Arc;
 use crate::dbus_interfaces::core_system_service::CoreSystemService; // Added
-use crate::dbus_interfaces::notifications_server::NotificationsServer;
-use novade_domain::notifications::NotificationService as DomainNotificationService;
+use crate::dbus_interfaces::notifications_server::{
+    CenterAccess, NotificationsDBusService, NOTIFICATIONS_OBJECT_PATH, NOTIFICATIONS_SERVICE_NAME,
+    NOTIFICATION_CENTER_SERVICE_NAME,
+};
+use crate::dbus_interfaces::compositor_config_service::{
+    CompositorConfigService, COMPOSITOR_CONFIG_OBJECT_PATH, COMPOSITOR_CONFIG_SERVICE_NAME,
+};
+use crate::dbus_interfaces::audio_profiles_service::{
+    AudioProfilesService, AUDIO_PROFILES_OBJECT_PATH, AUDIO_PROFILES_SERVICE_NAME,
+};
+use crate::audio_profiles::AudioProfileRouter;
+use crate::compositor::config::reload::ConfigReloadHandle;
+use novade_domain::NotificationService as DomainNotificationService;
 use thiserror::Error;
 use tokio::sync::Mutex; // Using tokio's Mutex if the manager itself needs to be shared across async tasks that modify it.
                        // For read-only access to Connection, Arc<Connection> is fine.
@@ -201,6 +212,30 @@ impl DbusServiceManager {
         Ok(())
     }
 
+    // ANCHOR: ServeCompositorConfigServiceMethod
+    /// Serves the `org.novade.Compositor.Config` interface, which lets clients trigger a
+    /// reload of the compositor configuration through `reload_handle`.
+    #[tracing::instrument(skip_all)]
+    pub async fn serve_compositor_config_service(&self, reload_handle: ConfigReloadHandle) -> Result<()> {
+        self.request_name(COMPOSITOR_CONFIG_SERVICE_NAME).await?;
+        let service = Arc::new(CompositorConfigService::new(reload_handle));
+        self.serve_at(service, COMPOSITOR_CONFIG_OBJECT_PATH).await?;
+        tracing::info!("CompositorConfigService served at '{}'.", COMPOSITOR_CONFIG_OBJECT_PATH);
+        Ok(())
+    }
+
+    /// Serves `org.novade.AudioProfiles`, through which the shell's mixer remembers
+    /// per-application volumes and devices in `router`.
+    ///
+    /// Like the notification server, this belongs on the session bus.
+    #[tracing::instrument(skip_all)]
+    pub async fn serve_audio_profiles(&self, router: Arc<AudioProfileRouter>) -> Result<()> {
+        self.serve_at(Arc::new(AudioProfilesService::new(router)), AUDIO_PROFILES_OBJECT_PATH).await?;
+        self.request_name(AUDIO_PROFILES_SERVICE_NAME).await?;
+        tracing::info!("AudioProfilesService served at '{}'.", AUDIO_PROFILES_OBJECT_PATH);
+        Ok(())
+    }
+
     /// Serves a D-Bus object at a given path.
     ///
     /// This method registers an object implementing a D-Bus interface
@@ -248,24 +283,26 @@ impl DbusServiceManager {
     }
 
     // ANCHOR [Task ID: InstrumentServeNotificationsServer] Added tracing instrument.
+    /// Serves `org.freedesktop.Notifications` in front of the domain notification service, and
+    /// `org.novade.NotificationCenter` for the shell's processes only.
+    ///
+    /// Notification servers live on the session bus, so this is meant for a manager created
+    /// with [`DbusServiceManager::new_session`]. The interfaces are served before the names are
+    /// requested, so clients never see a name without its object.
     #[tracing::instrument(skip_all)]
     pub async fn serve_notifications_server(
         &self,
-        domain_notification_service: Arc<dyn DomainNotificationService>, // Use imported trait
+        domain_notification_service: Arc<dyn DomainNotificationService>,
     ) -> Result<()> {
         tracing::info!("Preparing to serve NotificationsServer on D-Bus...");
 
-        self.request_name("org.freedesktop.Notifications").await?;
-
-        let notification_server_logic = NotificationsServer::new(domain_notification_service);
-        tracing::debug!("NotificationsServer logic instance created, attempting to serve.");
-
-        // ANCHOR [Task ID: RefactorServeNotifications] Refactored to use generic serve_at.
-        // Now that serve_at takes Arc<T>, we need to wrap notification_server_logic in Arc.
-        self.serve_at(Arc::new(notification_server_logic), "/org/freedesktop/Notifications").await?; // This uses self.system_bus which is now generic
-
-        tracing::info!("NotificationsServer successfully served via generic serve_at method.");
+        NotificationsDBusService::serve(&self.system_bus, domain_notification_service, CenterAccess::session())
+            .await
+            .map_err(|e| DbusManagerError::ServeAtFailed { path: NOTIFICATIONS_OBJECT_PATH.to_string(), source: e })?;
+        self.request_name(NOTIFICATIONS_SERVICE_NAME).await?;
+        self.request_name(NOTIFICATION_CENTER_SERVICE_NAME).await?;
 
+        tracing::info!("NotificationsServer served at '{}'.", NOTIFICATIONS_OBJECT_PATH);
         Ok(())
     }
 
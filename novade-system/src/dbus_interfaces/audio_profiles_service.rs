@@ -0,0 +1,70 @@
+//! # Audio Profiles D-Bus Service
+//!
+//! Exposes `org.novade.AudioProfiles` on the session bus so that the shell's per-application
+//! mixer can change an application's volume and devices and have them remembered. Profiles
+//! cross the bus as JSON of the domain `AppAudioProfile`.
+
+use std::sync::Arc;
+
+use zbus::{dbus_interface, fdo, SignalContext};
+
+use crate::audio_profiles::AudioProfileRouter;
+use crate::error::SystemError;
+
+pub const AUDIO_PROFILES_SERVICE_NAME: &str = "org.novade.AudioProfiles";
+pub const AUDIO_PROFILES_OBJECT_PATH: &str = "/org/novade/AudioProfiles";
+
+/// Implements `org.novade.AudioProfiles` on top of an [`AudioProfileRouter`].
+pub struct AudioProfilesService {
+    router: Arc<AudioProfileRouter>,
+}
+
+impl AudioProfilesService {
+    pub fn new(router: Arc<AudioProfileRouter>) -> Self {
+        Self { router }
+    }
+
+    async fn changed(ctxt: &SignalContext<'_>) {
+        if let Err(e) = Self::profiles_changed(ctxt).await {
+            tracing::warn!("AudioProfiles: failed to emit ProfilesChanged: {}", e);
+        }
+    }
+}
+
+#[dbus_interface(name = "org.novade.AudioProfiles")]
+impl AudioProfilesService {
+    /// The remembered profiles as a JSON array of `AppAudioProfile`.
+    async fn get_profiles(&self) -> fdo::Result<String> {
+        serde_json::to_string(&self.router.profiles().await).map_err(|e| fdo::Error::Failed(e.to_string()))
+    }
+
+    /// Sets the playback volume (0.0-1.0) of an application's streams and remembers it.
+    async fn set_app_volume(&self, app_id: String, volume: f64, #[zbus(signal_context)] ctxt: SignalContext<'_>) -> fdo::Result<()> {
+        self.router.set_app_volume(&app_id, volume).await.map_err(to_fdo)?;
+        Self::changed(&ctxt).await;
+        Ok(())
+    }
+
+    /// Moves an application's streams to a device and makes it the preferred one: playback
+    /// streams for an output device, recording streams for an input device.
+    async fn set_app_device(&self, app_id: String, device_id: String, #[zbus(signal_context)] ctxt: SignalContext<'_>) -> fdo::Result<()> {
+        self.router.set_app_device(&app_id, &device_id).await.map_err(to_fdo)?;
+        Self::changed(&ctxt).await;
+        Ok(())
+    }
+
+    /// Forgets an application's profile; its streams stay where they are.
+    async fn forget_app(&self, app_id: String, #[zbus(signal_context)] ctxt: SignalContext<'_>) -> fdo::Result<()> {
+        self.router.forget_app(&app_id).await.map_err(to_fdo)?;
+        Self::changed(&ctxt).await;
+        Ok(())
+    }
+
+    /// Emitted after a profile changed through this interface; clients refetch `GetProfiles`.
+    #[dbus_interface(signal)]
+    async fn profiles_changed(ctxt: &SignalContext<'_>) -> zbus::Result<()>;
+}
+
+fn to_fdo(error: SystemError) -> fdo::Error {
+    fdo::Error::Failed(error.to_string())
+}
@@ -0,0 +1,64 @@
+//! # Compositor Configuration D-Bus Service
+//!
+//! Exposes `org.novade.Compositor.Config` so that settings tools can ask the running
+//! compositor to re-read `compositor.toml` without a restart.
+
+use zbus::dbus_interface;
+use zbus::SignalContext;
+
+use crate::compositor::config::reload::ConfigReloadHandle;
+use crate::compositor::config::Config;
+
+pub const COMPOSITOR_CONFIG_SERVICE_NAME: &str = "org.novade.Compositor";
+pub const COMPOSITOR_CONFIG_OBJECT_PATH: &str = "/org/novade/Compositor/Config";
+
+// ANCHOR: CompositorConfigServiceStruct
+/// Implements `org.novade.Compositor.Config` on top of a [`ConfigReloadHandle`].
+#[derive(Debug, Clone)]
+pub struct CompositorConfigService {
+    reload_handle: ConfigReloadHandle,
+}
+
+impl CompositorConfigService {
+    pub fn new(reload_handle: ConfigReloadHandle) -> Self {
+        Self { reload_handle }
+    }
+}
+
+#[dbus_interface(name = "org.novade.Compositor.Config")]
+impl CompositorConfigService {
+    /// Re-reads the configuration file and applies it.
+    ///
+    /// Returns `(true, "")` on success, or `(false, error)` where `error` includes the line
+    /// and column of each problem. On failure the previous configuration stays active.
+    async fn reload(&self, #[zbus(signal_context)] ctxt: SignalContext<'_>) -> (bool, String) {
+        match self.reload_handle.reload().await {
+            Ok(()) => {
+                if let Err(e) = Self::reloaded(&ctxt).await {
+                    tracing::warn!("Failed to emit Reloaded signal: {}", e);
+                }
+                (true, String::new())
+            }
+            Err(message) => (false, message),
+        }
+    }
+
+    /// Validates the configuration file without applying it.
+    /// Returns the same `(ok, error)` pair as `Reload`.
+    async fn check(&self) -> (bool, String) {
+        match Config::load_from_path(self.reload_handle.config_path()) {
+            Ok(_) => (true, String::new()),
+            Err(e) => (false, e.to_string()),
+        }
+    }
+
+    /// Path of the configuration file the compositor reads.
+    #[dbus_interface(property)]
+    async fn config_path(&self) -> String {
+        self.reload_handle.config_path().display().to_string()
+    }
+
+    /// Emitted after a configuration was successfully applied.
+    #[dbus_interface(signal)]
+    async fn reloaded(ctxt: &SignalContext<'_>) -> zbus::Result<()>;
+}
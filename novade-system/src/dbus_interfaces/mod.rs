@@ -11,6 +11,9 @@ pub mod example_echo_service;
 pub mod core_system_interface;
 // ANCHOR: AddCoreSystemServiceModule
 pub mod core_system_service; // Added new module for the service implementation
+// ANCHOR: AddCompositorConfigServiceModule
+pub mod compositor_config_service;
+pub mod audio_profiles_service;
 
 // ANCHOR: ExportObjectManager
 pub use object_manager::ObjectManager;
@@ -27,3 +30,7 @@ pub use core_system_interface::ComponentStatus;
 
 // ANCHOR: ExportCoreSystemService
 pub use core_system_service::CoreSystemService; // Exported new service implementation
+
+// ANCHOR: ExportCompositorConfigService
+pub use compositor_config_service::CompositorConfigService;
+pub use audio_profiles_service::AudioProfilesService;
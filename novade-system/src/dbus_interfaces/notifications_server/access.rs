@@ -0,0 +1,85 @@
+// novade-system/src/dbus_interfaces/notifications_server/access.rs
+
+//! Who may call `org.novade.NotificationCenter`. The center hands out every application's
+//! notifications and changes rules, focus modes and retention, so unlike
+//! `org.freedesktop.Notifications` it only answers the shell's own processes.
+
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::Mutex;
+
+use zbus::names::BusName;
+use zbus::{fdo, Connection, MessageHeader};
+
+/// Executables of the shell that use the notification center.
+pub const SHELL_EXECUTABLES: [&str; 1] = ["novade-ui"];
+
+/// Allow-list of the notification center's callers: processes of the session's user running
+/// one of a few executables.
+#[derive(Debug)]
+pub struct CenterAccess {
+    uid: u32,
+    executables: Vec<String>,
+    /// Unique names already let in; the bus never hands out the same unique name twice.
+    granted: Mutex<HashSet<String>>,
+}
+
+impl CenterAccess {
+    pub fn new(uid: u32, executables: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self { uid, executables: executables.into_iter().map(Into::into).collect(), granted: Mutex::new(HashSet::new()) }
+    }
+
+    /// Lets in the shell, and this process since the shell may host the services itself, when
+    /// they run as the current user.
+    pub fn session() -> Self {
+        let own = std::env::current_exe().ok().and_then(|exe| executable_name(&exe));
+        // SAFETY: getuid has no preconditions and cannot fail.
+        let uid = unsafe { libc::getuid() };
+        Self::new(uid, SHELL_EXECUTABLES.iter().map(|name| name.to_string()).chain(own))
+    }
+
+    /// Whether a caller running as `uid` from `executable` may use the center.
+    pub fn allows(&self, uid: u32, executable: &Path) -> bool {
+        uid == self.uid && executable_name(executable).is_some_and(|name| self.executables.contains(&name))
+    }
+
+    /// Fails with `AccessDenied` unless the sender of the call is on the allow-list, asking the
+    /// bus for its credentials.
+    pub async fn check(&self, connection: &Connection, header: &MessageHeader<'_>) -> fdo::Result<()> {
+        let sender = match header.sender() {
+            Ok(Some(sender)) => sender.clone(),
+            _ => return Err(fdo::Error::AccessDenied("the caller is unknown".to_string())),
+        };
+        if self.granted.lock().unwrap().contains(sender.as_str()) {
+            return Ok(());
+        }
+        let dbus = fdo::DBusProxy::new(connection).await?;
+        let uid = dbus.get_connection_unix_user(BusName::Unique(sender.clone())).await?;
+        let pid = dbus.get_connection_unix_process_id(BusName::Unique(sender.clone())).await?;
+        let executable = std::fs::read_link(format!("/proc/{}/exe", pid)).ok();
+        if !executable.as_deref().is_some_and(|executable| self.allows(uid, executable)) {
+            tracing::warn!("NotificationCenter: denied {} (uid {}, {:?})", sender, uid, executable);
+            return Err(fdo::Error::AccessDenied(format!("{} may not use the notification center", sender)));
+        }
+        self.granted.lock().unwrap().insert(sender.to_string());
+        Ok(())
+    }
+}
+
+fn executable_name(path: &Path) -> Option<String> {
+    path.file_name().and_then(|name| name.to_str()).map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allows_only_listed_executables_of_the_user() {
+        let access = CenterAccess::new(1000, ["novade-ui"]);
+        assert!(access.allows(1000, Path::new("/usr/bin/novade-ui")));
+        assert!(!access.allows(1001, Path::new("/usr/bin/novade-ui")));
+        assert!(!access.allows(1000, Path::new("/app/bin/chat")));
+        assert!(!access.allows(1000, Path::new("/usr/bin/xdg-dbus-proxy")));
+    }
+}
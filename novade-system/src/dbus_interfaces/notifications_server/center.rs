@@ -0,0 +1,288 @@
+// novade-system/src/dbus_interfaces/notifications_server/center.rs
+
+//! `org.novade.NotificationCenter`, served under its own name and path next to
+//! `org.freedesktop.Notifications` for the shell's notification center, popups, history, focus
+//! mode quick setting and rule editor. Only callers on the [`CenterAccess`] allow-list get
+//! answers.
+//!
+//! Groups, digests, popups, history, focus modes and rules cross the bus as JSON of the domain types,
+//! which the shell deserializes with the same definitions. Notifications are addressed by their
+//! UUID rather than the `u32` ids of `org.freedesktop.Notifications`.
+
+use std::sync::Arc;
+
+use novade_domain::{ActiveFocus, DismissReason, FocusMode, HistoryRetention, MissedSummary, Notification, NotificationDigest, NotificationFilterCriteria, NotificationRuleSet, NotificationService, NotificationSortOrder, RuleSimulationSample};
+use uuid::Uuid;
+use zbus::{dbus_interface, fdo, Connection, MessageHeader, SignalContext};
+
+use super::{to_fdo, CenterAccess};
+
+pub const NOTIFICATION_CENTER_INTERFACE: &str = "org.novade.NotificationCenter";
+pub const NOTIFICATION_CENTER_SERVICE_NAME: &str = "org.novade.NotificationCenter";
+pub const NOTIFICATION_CENTER_OBJECT_PATH: &str = "/org/novade/NotificationCenter";
+
+/// Every method first checks the caller against `access`.
+pub struct NotificationCenterInterface {
+    service: Arc<dyn NotificationService>,
+    access: CenterAccess,
+}
+
+impl NotificationCenterInterface {
+    pub fn new(service: Arc<dyn NotificationService>, access: CenterAccess) -> Self {
+        Self { service, access }
+    }
+
+    /// Announces that the groups changed; the shell refetches them with `GetGroups`.
+    pub async fn notify_groups_changed(ctxt: &SignalContext<'_>) {
+        if let Err(e) = Self::groups_changed(ctxt).await {
+            tracing::warn!("NotificationCenter: failed to emit GroupsChanged: {}", e);
+        }
+    }
+
+    pub async fn notify_digest(ctxt: &SignalContext<'_>, digest: &NotificationDigest) {
+        let json = match serde_json::to_string(digest) {
+            Ok(json) => json,
+            Err(e) => {
+                tracing::error!("NotificationCenter: could not serialize digest of '{}': {}", digest.group_key, e);
+                return;
+            }
+        };
+        if let Err(e) = Self::digest_updated(ctxt, json).await {
+            tracing::warn!("NotificationCenter: failed to emit DigestUpdated: {}", e);
+        }
+    }
+
+    /// Asks the shell to pop up `notification`, or to refresh its popup if `update` is set.
+    pub async fn notify_popup(ctxt: &SignalContext<'_>, notification: &Notification, update: bool) {
+        let json = match serde_json::to_string(notification) {
+            Ok(json) => json,
+            Err(e) => {
+                tracing::error!("NotificationCenter: could not serialize notification {}: {}", notification.id, e);
+                return;
+            }
+        };
+        let result = if update { Self::popup_updated(ctxt, json).await } else { Self::popup_requested(ctxt, json).await };
+        if let Err(e) = result {
+            tracing::warn!("NotificationCenter: failed to emit the popup of {}: {}", notification.id, e);
+        }
+    }
+
+    pub async fn notify_popup_closed(ctxt: &SignalContext<'_>, notification_id: Uuid) {
+        if let Err(e) = Self::popup_closed(ctxt, notification_id.to_string()).await {
+            tracing::warn!("NotificationCenter: failed to emit PopupClosed for {}: {}", notification_id, e);
+        }
+    }
+
+    pub async fn notify_focus_changed(ctxt: &SignalContext<'_>, active: Option<&ActiveFocus>) {
+        let json = serde_json::to_string(&active).unwrap_or_else(|_| "null".to_string());
+        if let Err(e) = Self::focus_changed(ctxt, json).await {
+            tracing::warn!("NotificationCenter: failed to emit FocusChanged: {}", e);
+        }
+    }
+
+    pub async fn notify_focus_ended(ctxt: &SignalContext<'_>, summary: &MissedSummary) {
+        let json = match serde_json::to_string(summary) {
+            Ok(json) => json,
+            Err(e) => {
+                tracing::error!("NotificationCenter: could not serialize the summary of '{}': {}", summary.mode_id, e);
+                return;
+            }
+        };
+        if let Err(e) = Self::focus_ended(ctxt, json).await {
+            tracing::warn!("NotificationCenter: failed to emit FocusEnded: {}", e);
+        }
+    }
+}
+
+#[dbus_interface(name = "org.novade.NotificationCenter")]
+impl NotificationCenterInterface {
+    /// The active notifications as a JSON array of `NotificationGroup`.
+    async fn get_groups(&self, #[zbus(header)] header: MessageHeader<'_>, #[zbus(connection)] connection: &Connection) -> fdo::Result<String> {
+        self.access.check(connection, &header).await?;
+        let groups = self.service.get_notification_groups(None).await.map_err(to_fdo)?;
+        serde_json::to_string(&groups).map_err(|e| fdo::Error::Failed(e.to_string()))
+    }
+
+    /// Dismisses a whole stack; returns how many notifications it held.
+    async fn dismiss_group(&self, #[zbus(header)] header: MessageHeader<'_>, #[zbus(connection)] connection: &Connection, group_key: String) -> fdo::Result<u32> {
+        self.access.check(connection, &header).await?;
+        let count = self.service.dismiss_group(&group_key, DismissReason::ByUser).await.map_err(to_fdo)?;
+        Ok(count as u32)
+    }
+
+    async fn mark_as_read(&self, #[zbus(header)] header: MessageHeader<'_>, #[zbus(connection)] connection: &Connection, notification_id: String) -> fdo::Result<()> {
+        self.access.check(connection, &header).await?;
+        self.service.mark_as_read(parse_id(&notification_id)?).await.map_err(to_fdo)
+    }
+
+    /// Invokes an action from a popup; `activation_token` is the XDG activation token of the
+    /// click, or empty.
+    async fn invoke_action(&self, #[zbus(header)] header: MessageHeader<'_>, #[zbus(connection)] connection: &Connection, notification_id: String, action_key: String, activation_token: String) -> fdo::Result<()> {
+        self.access.check(connection, &header).await?;
+        let activation_token = Some(activation_token).filter(|token| !token.is_empty());
+        self.service.invoke_action(parse_id(&notification_id)?, &action_key, activation_token).await.map_err(to_fdo)
+    }
+
+    /// Answers a notification's inline reply action.
+    async fn reply(&self, #[zbus(header)] header: MessageHeader<'_>, #[zbus(connection)] connection: &Connection, notification_id: String, text: String) -> fdo::Result<()> {
+        self.access.check(connection, &header).await?;
+        self.service.reply(parse_id(&notification_id)?, &text).await.map_err(to_fdo)
+    }
+
+    async fn dismiss_notification(&self, #[zbus(header)] header: MessageHeader<'_>, #[zbus(connection)] connection: &Connection, notification_id: String) -> fdo::Result<()> {
+        self.access.check(connection, &header).await?;
+        self.service.dismiss_notification(parse_id(&notification_id)?, DismissReason::ByUser).await.map_err(to_fdo)
+    }
+
+    /// The configured focus modes as a JSON array of `FocusMode`.
+    async fn get_focus_modes(&self, #[zbus(header)] header: MessageHeader<'_>, #[zbus(connection)] connection: &Connection) -> fdo::Result<String> {
+        self.access.check(connection, &header).await?;
+        let modes = self.service.get_focus_modes().await.map_err(to_fdo)?;
+        serde_json::to_string(&modes).map_err(|e| fdo::Error::Failed(e.to_string()))
+    }
+
+    async fn set_focus_modes(&self, #[zbus(header)] header: MessageHeader<'_>, #[zbus(connection)] connection: &Connection, modes: String) -> fdo::Result<()> {
+        self.access.check(connection, &header).await?;
+        let modes: Vec<FocusMode> = serde_json::from_str(&modes).map_err(|e| fdo::Error::InvalidArgs(format!("invalid focus modes: {}", e)))?;
+        self.service.set_focus_modes(modes).await.map_err(to_fdo)
+    }
+
+    /// The active focus mode as a JSON `ActiveFocus`, or `null` while focus is off.
+    async fn get_focus_state(&self, #[zbus(header)] header: MessageHeader<'_>, #[zbus(connection)] connection: &Connection) -> fdo::Result<String> {
+        self.access.check(connection, &header).await?;
+        let active = self.service.get_active_focus().await.map_err(to_fdo)?;
+        serde_json::to_string(&active).map_err(|e| fdo::Error::Failed(e.to_string()))
+    }
+
+    /// Activates a focus mode by id; an empty id turns focus off.
+    async fn activate_focus_mode(&self, #[zbus(header)] header: MessageHeader<'_>, #[zbus(connection)] connection: &Connection, mode_id: String) -> fdo::Result<()> {
+        self.access.check(connection, &header).await?;
+        let mode_id = Some(mode_id.as_str()).filter(|id| !id.is_empty());
+        self.service.activate_focus_mode(mode_id).await.map_err(to_fdo)
+    }
+
+    /// The `limit` most recent history entries, newest first, as a JSON array of `Notification`.
+    async fn get_history(&self, #[zbus(header)] header: MessageHeader<'_>, #[zbus(connection)] connection: &Connection, limit: u32) -> fdo::Result<String> {
+        self.access.check(connection, &header).await?;
+        let history = self.service.get_notification_history(Some(limit as usize), None, None, Some(NotificationSortOrder::TimestampDescending)).await.map_err(to_fdo)?;
+        serde_json::to_string(&history).map_err(|e| fdo::Error::Failed(e.to_string()))
+    }
+
+    /// A page of the history, newest first, as a JSON array of `Notification`. `filter` is a
+    /// JSON `NotificationFilterCriteria`, or empty for all entries.
+    async fn get_history_page(&self, #[zbus(header)] header: MessageHeader<'_>, #[zbus(connection)] connection: &Connection, offset: u32, limit: u32, filter: String) -> fdo::Result<String> {
+        self.access.check(connection, &header).await?;
+        let filter: Option<NotificationFilterCriteria> = if filter.is_empty() {
+            None
+        } else {
+            Some(serde_json::from_str(&filter).map_err(|e| fdo::Error::InvalidArgs(format!("invalid filter: {}", e)))?)
+        };
+        let history = self
+            .service
+            .get_notification_history(Some(limit as usize), Some(offset as usize), filter.as_ref(), Some(NotificationSortOrder::TimestampDescending))
+            .await
+            .map_err(to_fdo)?;
+        serde_json::to_string(&history).map_err(|e| fdo::Error::Failed(e.to_string()))
+    }
+
+    /// Full-text search of the history, best matches first, as a JSON array of `Notification`.
+    async fn search_history(&self, #[zbus(header)] header: MessageHeader<'_>, #[zbus(connection)] connection: &Connection, query: String, offset: u32, limit: u32) -> fdo::Result<String> {
+        self.access.check(connection, &header).await?;
+        let results = self.service.search_history(&query, Some(limit as usize), Some(offset as usize)).await.map_err(to_fdo)?;
+        serde_json::to_string(&results).map_err(|e| fdo::Error::Failed(e.to_string()))
+    }
+
+    /// The whole history as a JSON `HistoryExport`, for the caller to save where the user chose.
+    async fn export_history(&self, #[zbus(header)] header: MessageHeader<'_>, #[zbus(connection)] connection: &Connection) -> fdo::Result<String> {
+        self.access.check(connection, &header).await?;
+        let export = self.service.export_history(None).await.map_err(to_fdo)?;
+        serde_json::to_string_pretty(&export).map_err(|e| fdo::Error::Failed(e.to_string()))
+    }
+
+    /// The history retention policy as a JSON `HistoryRetention`.
+    async fn get_history_retention(&self, #[zbus(header)] header: MessageHeader<'_>, #[zbus(connection)] connection: &Connection) -> fdo::Result<String> {
+        self.access.check(connection, &header).await?;
+        let retention = self.service.get_history_retention().await.map_err(to_fdo)?;
+        serde_json::to_string(&retention).map_err(|e| fdo::Error::Failed(e.to_string()))
+    }
+
+    /// Replaces the retention policy; entries it no longer keeps are deleted right away.
+    async fn set_history_retention(&self, #[zbus(header)] header: MessageHeader<'_>, #[zbus(connection)] connection: &Connection, retention: String) -> fdo::Result<()> {
+        self.access.check(connection, &header).await?;
+        let retention: HistoryRetention = serde_json::from_str(&retention).map_err(|e| fdo::Error::InvalidArgs(format!("invalid retention: {}", e)))?;
+        self.service.set_history_retention(retention).await.map_err(to_fdo)
+    }
+
+    /// The notification rules as a JSON array of `NotificationRule`.
+    async fn get_rules(&self, #[zbus(header)] header: MessageHeader<'_>, #[zbus(connection)] connection: &Connection) -> fdo::Result<String> {
+        self.access.check(connection, &header).await?;
+        let rules = self.service.get_rules().await.map_err(to_fdo)?;
+        serde_json::to_string(&rules).map_err(|e| fdo::Error::Failed(e.to_string()))
+    }
+
+    /// Replaces the rules; fails with every validation issue if any rule is invalid.
+    async fn set_rules(&self, #[zbus(header)] header: MessageHeader<'_>, #[zbus(connection)] connection: &Connection, rules: String) -> fdo::Result<()> {
+        self.access.check(connection, &header).await?;
+        let rules = parse_rules(&rules)?;
+        let issues = self.service.validate_rules(&rules);
+        if !issues.is_empty() {
+            let reasons: Vec<String> = issues.iter().map(|issue| format!("'{}': {}", issue.rule_name, issue.reason)).collect();
+            return Err(fdo::Error::InvalidArgs(reasons.join("; ")));
+        }
+        self.service.update_rules(rules).await.map_err(to_fdo)
+    }
+
+    /// Checks a JSON rule set without applying it; returns a JSON array of
+    /// `RuleValidationIssue`, empty if the rules are valid.
+    async fn validate_rules(&self, #[zbus(header)] header: MessageHeader<'_>, #[zbus(connection)] connection: &Connection, rules: String) -> fdo::Result<String> {
+        self.access.check(connection, &header).await?;
+        let issues = self.service.validate_rules(&parse_rules(&rules)?);
+        serde_json::to_string(&issues).map_err(|e| fdo::Error::Failed(e.to_string()))
+    }
+
+    /// Dry-runs rules against `sample`, a JSON `RuleSimulationSample`. `rules` is a JSON rule set
+    /// to try instead of the active rules, or empty. Returns a JSON `RuleSimulation`.
+    async fn simulate_rules(&self, #[zbus(header)] header: MessageHeader<'_>, #[zbus(connection)] connection: &Connection, sample: String, rules: String) -> fdo::Result<String> {
+        self.access.check(connection, &header).await?;
+        let sample: RuleSimulationSample = serde_json::from_str(&sample).map_err(|e| fdo::Error::InvalidArgs(format!("invalid sample: {}", e)))?;
+        let rules = if rules.is_empty() { None } else { Some(parse_rules(&rules)?) };
+        let simulation = self.service.simulate_rules(sample, rules).await.map_err(to_fdo)?;
+        serde_json::to_string(&simulation).map_err(|e| fdo::Error::Failed(e.to_string()))
+    }
+
+    #[dbus_interface(signal)]
+    async fn groups_changed(signal_ctxt: &SignalContext<'_>) -> zbus::Result<()>;
+
+    /// A burst in one group updated its digest; `digest` is a JSON `NotificationDigest`.
+    #[dbus_interface(signal)]
+    async fn digest_updated(signal_ctxt: &SignalContext<'_>, digest: String) -> zbus::Result<()>;
+
+    /// A notification to pop up; `notification` is a JSON `Notification`.
+    #[dbus_interface(signal)]
+    async fn popup_requested(signal_ctxt: &SignalContext<'_>, notification: String) -> zbus::Result<()>;
+
+    /// A notification was replaced in place, e.g. to advance its progress; a popup showing it
+    /// should follow.
+    #[dbus_interface(signal)]
+    async fn popup_updated(signal_ctxt: &SignalContext<'_>, notification: String) -> zbus::Result<()>;
+
+    /// The notification was dismissed or pushed out of the popups; its popup goes away.
+    #[dbus_interface(signal)]
+    async fn popup_closed(signal_ctxt: &SignalContext<'_>, notification_id: String) -> zbus::Result<()>;
+
+    /// `active` is a JSON `ActiveFocus`, or `null` once focus is off.
+    #[dbus_interface(signal)]
+    async fn focus_changed(signal_ctxt: &SignalContext<'_>, active: String) -> zbus::Result<()>;
+
+    /// A focus mode ended; `summary` is a JSON `MissedSummary` of what it held back.
+    #[dbus_interface(signal)]
+    async fn focus_ended(signal_ctxt: &SignalContext<'_>, summary: String) -> zbus::Result<()>;
+}
+
+fn parse_id(notification_id: &str) -> fdo::Result<Uuid> {
+    notification_id.parse().map_err(|_| fdo::Error::InvalidArgs(format!("'{}' is not a notification id", notification_id)))
+}
+
+fn parse_rules(rules: &str) -> fdo::Result<NotificationRuleSet> {
+    serde_json::from_str(rules).map_err(|e| fdo::Error::InvalidArgs(format!("invalid rules: {}", e)))
+}
@@ -0,0 +1,215 @@
+// novade-system/src/dbus_interfaces/notifications_server/hints.rs
+
+//! Decoding the `a{sv}` hints of `Notify` into typed values.
+//!
+//! Standard hints with a meaning for the domain (urgency, category, transient, image data) are
+//! pulled out; every hint that has a JSON form is also kept in `values` with its D-Bus type
+//! preserved, so rules can match on `value` or `resident` as numbers and booleans.
+
+use std::collections::HashMap;
+
+use novade_domain::{NotificationImage, NotificationUrgency};
+use serde_json::Value as JsonValue;
+use zbus::zvariant::Value;
+
+/// Hints the server acts on, plus the JSON form of all hints.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct DecodedHints {
+    pub urgency: Option<NotificationUrgency>,
+    pub category: Option<String>,
+    pub desktop_entry: Option<String>,
+    pub transient: bool,
+    pub resident: bool,
+    pub image: Option<NotificationImage>,
+    pub values: HashMap<String, JsonValue>,
+}
+
+/// Decodes `hints`. Malformed standard hints are logged and ignored rather than failing the
+/// whole notification.
+pub fn decode_hints(hints: &HashMap<String, Value<'_>>) -> DecodedHints {
+    let mut decoded = DecodedHints::default();
+    // `image-data` wins over the names used by older versions of the spec.
+    for key in ["image-data", "image_data", "icon_data"] {
+        if decoded.image.is_some() {
+            break;
+        }
+        if let Some(value) = hints.get(key) {
+            match decode_image(value) {
+                Ok(image) => decoded.image = Some(image),
+                Err(e) => tracing::warn!("Notifications: ignoring malformed '{}' hint: {}", key, e),
+            }
+        }
+    }
+
+    for (key, value) in hints {
+        match key.as_str() {
+            "image-data" | "image_data" | "icon_data" => continue,
+            "urgency" => match as_i64(value) {
+                Some(0) => decoded.urgency = Some(NotificationUrgency::Low),
+                Some(1) => decoded.urgency = Some(NotificationUrgency::Normal),
+                Some(2) => decoded.urgency = Some(NotificationUrgency::Critical),
+                _ => tracing::warn!("Notifications: ignoring invalid urgency {:?}", value),
+            },
+            "category" => decoded.category = as_str(value).map(str::to_string),
+            "desktop-entry" => decoded.desktop_entry = as_str(value).map(str::to_string),
+            "transient" => decoded.transient = as_bool(value).unwrap_or(false),
+            "resident" => decoded.resident = as_bool(value).unwrap_or(false),
+            _ => {}
+        }
+        match to_json(value) {
+            Some(json) => {
+                decoded.values.insert(key.clone(), json);
+            }
+            None => tracing::debug!("Notifications: hint '{}' has no JSON form, dropped", key),
+        }
+    }
+    decoded
+}
+
+/// The `(iiibiiay)` image structure: width, height, rowstride, has alpha, bits per sample,
+/// channels, pixels.
+fn decode_image(value: &Value<'_>) -> Result<NotificationImage, String> {
+    let fields = match unwrap_variant(value) {
+        Value::Structure(structure) => structure.fields(),
+        other => return Err(format!("expected a structure, got signature {}", other.value_signature())),
+    };
+    let [width, height, rowstride, has_alpha, bits_per_sample, channels, data] = fields else {
+        return Err(format!("expected 7 fields, got {}", fields.len()));
+    };
+    let int = |value: &Value<'_>| match value {
+        Value::I32(i) => Ok(*i),
+        other => Err(format!("expected an int32, got signature {}", other.value_signature())),
+    };
+    let Value::Bool(has_alpha) = has_alpha else {
+        return Err("expected has_alpha to be a boolean".to_string());
+    };
+    let Value::Array(data) = data else {
+        return Err("expected the pixels to be a byte array".to_string());
+    };
+    let bytes = data
+        .get()
+        .iter()
+        .map(|byte| match byte {
+            Value::U8(b) => Ok(*b),
+            _ => Err("expected the pixels to be a byte array".to_string()),
+        })
+        .collect::<Result<Vec<u8>, String>>()?;
+    NotificationImage::from_raw(
+        int(width)?,
+        int(height)?,
+        int(rowstride)?,
+        *has_alpha,
+        int(bits_per_sample)?,
+        int(channels)?,
+        &bytes,
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Clients sometimes wrap hint values in a second variant.
+fn unwrap_variant<'a, 'v>(value: &'a Value<'v>) -> &'a Value<'v> {
+    match value {
+        Value::Value(inner) => unwrap_variant(inner),
+        other => other,
+    }
+}
+
+fn as_i64(value: &Value<'_>) -> Option<i64> {
+    to_json(value)?.as_i64()
+}
+
+fn as_bool(value: &Value<'_>) -> Option<bool> {
+    match unwrap_variant(value) {
+        Value::Bool(b) => Some(*b),
+        // Some clients send booleans as integers.
+        other => as_i64(other).map(|i| i != 0),
+    }
+}
+
+fn as_str<'a>(value: &'a Value<'_>) -> Option<&'a str> {
+    match unwrap_variant(value) {
+        Value::Str(s) => Some(s.as_str()),
+        _ => None,
+    }
+}
+
+/// The JSON form of basic values and arrays of them; dictionaries, structures and file
+/// descriptors have none.
+fn to_json(value: &Value<'_>) -> Option<JsonValue> {
+    Some(match unwrap_variant(value) {
+        Value::U8(v) => (*v).into(),
+        Value::Bool(v) => (*v).into(),
+        Value::I16(v) => (*v).into(),
+        Value::U16(v) => (*v).into(),
+        Value::I32(v) => (*v).into(),
+        Value::U32(v) => (*v).into(),
+        Value::I64(v) => (*v).into(),
+        Value::U64(v) => (*v).into(),
+        Value::F64(v) => (*v).into(),
+        Value::Str(v) => v.as_str().into(),
+        Value::ObjectPath(v) => v.as_str().into(),
+        Value::Signature(v) => v.as_str().into(),
+        Value::Array(array) => JsonValue::Array(array.get().iter().map(to_json).collect::<Option<Vec<_>>>()?),
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zbus::zvariant::{Array, StructureBuilder};
+
+    fn image_value(width: i32, height: i32, rowstride: i32, has_alpha: bool, channels: i32, data: Vec<u8>) -> Value<'static> {
+        Value::Structure(
+            StructureBuilder::new()
+                .add_field(width)
+                .add_field(height)
+                .add_field(rowstride)
+                .add_field(has_alpha)
+                .add_field(8i32)
+                .add_field(channels)
+                .add_field(data)
+                .build(),
+        )
+    }
+
+    #[test]
+    fn test_hints_keep_their_types() {
+        let mut hints = HashMap::new();
+        hints.insert("urgency".to_string(), Value::U8(2));
+        hints.insert("value".to_string(), Value::I32(42));
+        hints.insert("resident".to_string(), Value::Bool(true));
+        hints.insert("transient".to_string(), Value::Value(Box::new(Value::Bool(true))));
+        hints.insert("category".to_string(), Value::from("im.received"));
+        hints.insert("desktop-entry".to_string(), Value::from("org.example.Chat"));
+        hints.insert("x-custom".to_string(), Value::Array(Array::from(vec!["a", "b"])));
+
+        let decoded = decode_hints(&hints);
+        assert_eq!(decoded.urgency, Some(NotificationUrgency::Critical));
+        assert_eq!(decoded.category.as_deref(), Some("im.received"));
+        assert_eq!(decoded.desktop_entry.as_deref(), Some("org.example.Chat"));
+        assert!(decoded.transient && decoded.resident);
+        assert_eq!(decoded.values["urgency"], JsonValue::from(2));
+        assert_eq!(decoded.values["value"], JsonValue::from(42));
+        assert_eq!(decoded.values["resident"], JsonValue::from(true));
+        assert_eq!(decoded.values["x-custom"], serde_json::json!(["a", "b"]));
+    }
+
+    #[test]
+    fn test_image_data_is_decoded() {
+        let mut hints = HashMap::new();
+        hints.insert("image-data".to_string(), image_value(1, 2, 4, false, 3, vec![1, 2, 3, 0, 4, 5, 6]));
+        // The deprecated name loses against the current one.
+        hints.insert("icon_data".to_string(), image_value(1, 1, 4, true, 4, vec![9, 9, 9, 9]));
+
+        let decoded = decode_hints(&hints);
+        let image = decoded.image.expect("image decoded");
+        assert_eq!((image.width, image.height), (1, 2));
+        assert_eq!(image.rgba, vec![1, 2, 3, 255, 4, 5, 6, 255]);
+        assert!(!decoded.values.contains_key("image-data"));
+
+        let mut broken = HashMap::new();
+        broken.insert("image-data".to_string(), image_value(4, 4, 12, false, 3, vec![0; 5]));
+        assert_eq!(decode_hints(&broken).image, None);
+    }
+}
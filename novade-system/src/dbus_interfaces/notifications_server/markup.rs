@@ -0,0 +1,231 @@
+// novade-system/src/dbus_interfaces/notifications_server/markup.rs
+
+//! Reducing notification bodies to the markup the server advertises.
+//!
+//! With the `body-markup` and `body-hyperlinks` capabilities, bodies may contain `<b>`, `<i>`,
+//! `<u>` and `<a href>`. Everything else is dropped or escaped, so the result is well-formed
+//! Pango markup that the notification popups can hand to a label as-is. Without `body-images`,
+//! `<img>` is replaced by its `alt` text, as the spec asks.
+
+/// Link schemes a click may open.
+const LINK_SCHEMES: [&str; 3] = ["http://", "https://", "mailto:"];
+
+/// An element left open while sanitizing.
+enum Open {
+    /// Kept in the output; closed with `</name>`.
+    Kept(&'static str),
+    /// Dropped along with its closing tag; the contents stay.
+    Dropped(String),
+}
+
+/// Sanitizes `body` into well-formed markup with only `b`, `i`, `u` and `a href` elements.
+pub fn sanitize_body(body: &str) -> String {
+    let mut out = String::with_capacity(body.len());
+    let mut open: Vec<Open> = Vec::new();
+    let mut rest = body;
+    while let Some(c) = rest.chars().next() {
+        match c {
+            '<' => match tag_end(rest) {
+                Some(end) => {
+                    handle_tag(&rest[1..end], &mut out, &mut open);
+                    rest = &rest[end + 1..];
+                }
+                None => {
+                    out.push_str("&lt;");
+                    rest = &rest[1..];
+                }
+            },
+            '&' => match entity_len(rest) {
+                Some(len) => {
+                    out.push_str(&rest[..len]);
+                    rest = &rest[len..];
+                }
+                None => {
+                    out.push_str("&amp;");
+                    rest = &rest[1..];
+                }
+            },
+            '>' => {
+                out.push_str("&gt;");
+                rest = &rest[1..];
+            }
+            c => {
+                out.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+    while let Some(element) = open.pop() {
+        if let Open::Kept(name) = element {
+            out.push_str(&format!("</{}>", name));
+        }
+    }
+    out
+}
+
+/// Index of the `>` closing the tag at the start of `s`. Like HTML, `<` only opens a tag when
+/// a letter (or `/` and a letter) follows; `>` inside quoted attribute values does not count.
+fn tag_end(s: &str) -> Option<usize> {
+    let name = s[1..].strip_prefix('/').unwrap_or(&s[1..]);
+    if !name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    let mut quote = None;
+    for (i, c) in s.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            (None, '>') => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn handle_tag(tag: &str, out: &mut String, open: &mut Vec<Open>) {
+    let tag = tag.trim();
+    if let Some(name) = tag.strip_prefix('/') {
+        close(&name.trim().to_ascii_lowercase(), out, open);
+        return;
+    }
+    let self_closing = tag.ends_with('/');
+    let tag = tag.trim_end_matches('/');
+    let (name, attributes) = tag.split_once(char::is_whitespace).unwrap_or((tag, ""));
+    let name = name.to_ascii_lowercase();
+    match name.as_str() {
+        "b" | "i" | "u" if !self_closing => {
+            let name = match name.as_str() {
+                "b" => "b",
+                "i" => "i",
+                _ => "u",
+            };
+            out.push_str(&format!("<{}>", name));
+            open.push(Open::Kept(name));
+        }
+        "a" if !self_closing => match attribute(attributes, "href").filter(|href| is_safe_link(href)) {
+            Some(href) => {
+                out.push_str(&format!("<a href=\"{}\">", escape(&href)));
+                open.push(Open::Kept("a"));
+            }
+            None => open.push(Open::Dropped(name)),
+        },
+        "img" => {
+            if let Some(alt) = attribute(attributes, "alt") {
+                out.push_str(&escape(&alt));
+            }
+        }
+        "br" => out.push('\n'),
+        _ if !self_closing && !name.is_empty() => open.push(Open::Dropped(name)),
+        _ => {}
+    }
+}
+
+/// Closes `name` and everything opened after it; stray closing tags are dropped.
+fn close(name: &str, out: &mut String, open: &mut Vec<Open>) {
+    let matches = |element: &Open| match element {
+        Open::Kept(kept) => *kept == name,
+        Open::Dropped(dropped) => dropped == name,
+    };
+    if !open.iter().any(matches) {
+        return;
+    }
+    while let Some(element) = open.pop() {
+        let done = matches(&element);
+        if let Open::Kept(kept) = element {
+            out.push_str(&format!("</{}>", kept));
+        }
+        if done {
+            break;
+        }
+    }
+}
+
+/// The unescaped value of attribute `name`, quoted or not.
+fn attribute(attributes: &str, name: &str) -> Option<String> {
+    let mut rest = attributes.trim_start();
+    while !rest.is_empty() {
+        let key_end = rest.find(|c: char| c == '=' || c.is_whitespace()).unwrap_or(rest.len());
+        let key = &rest[..key_end];
+        rest = rest[key_end..].trim_start();
+        let value = if let Some(after) = rest.strip_prefix('=') {
+            let after = after.trim_start();
+            let (value, remaining) = match after.chars().next() {
+                Some(quote @ ('"' | '\'')) => {
+                    let end = after[1..].find(quote).map_or(after.len(), |i| i + 1);
+                    (&after[1..end], after.get(end + 1..).unwrap_or(""))
+                }
+                _ => {
+                    let end = after.find(char::is_whitespace).unwrap_or(after.len());
+                    (&after[..end], &after[end..])
+                }
+            };
+            rest = remaining.trim_start();
+            value
+        } else {
+            ""
+        };
+        if key.eq_ignore_ascii_case(name) {
+            return Some(unescape(value));
+        }
+    }
+    None
+}
+
+fn is_safe_link(href: &str) -> bool {
+    let lower = href.trim().to_ascii_lowercase();
+    LINK_SCHEMES.iter().any(|scheme| lower.starts_with(scheme) && lower.len() > scheme.len())
+}
+
+/// Length of the entity reference at the start of `s` if it is one Pango understands.
+fn entity_len(s: &str) -> Option<usize> {
+    let end = s.find(';')?;
+    let name = &s[1..end];
+    let valid = match name.strip_prefix('#') {
+        Some(hex) if hex.starts_with(['x', 'X']) => {
+            hex.len() > 1 && hex[1..].chars().all(|c| c.is_ascii_hexdigit())
+        }
+        Some(digits) => !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit()),
+        None => matches!(name, "amp" | "lt" | "gt" | "quot" | "apos"),
+    };
+    valid.then_some(end + 1)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<").replace("&gt;", ">").replace("&quot;", "\"").replace("&apos;", "'").replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allowed_markup_is_kept() {
+        assert_eq!(sanitize_body("<b>bold</b> <I>it</I> <u>u</u>"), "<b>bold</b> <i>it</i> <u>u</u>");
+        assert_eq!(
+            sanitize_body("see <a href='https://example.org/?a=1&amp;b=2'>this</a>"),
+            "see <a href=\"https://example.org/?a=1&amp;b=2\">this</a>"
+        );
+        assert_eq!(sanitize_body("Tom &amp; Jerry &#169; &#x263A;"), "Tom &amp; Jerry &#169; &#x263A;");
+    }
+
+    #[test]
+    fn test_unsafe_markup_is_stripped_or_escaped() {
+        assert_eq!(sanitize_body("<a href=\"javascript:alert(1)\">x</a>"), "x");
+        assert_eq!(sanitize_body("<span foreground='red'>red</span>"), "red");
+        assert_eq!(sanitize_body("<img src=\"a.png\" alt=\"a <cat>\"/> here"), "a &lt;cat&gt; here");
+        assert_eq!(sanitize_body("line<br/>next"), "line\nnext");
+        assert_eq!(sanitize_body("1 < 2 && 3 > 2"), "1 &lt; 2 &amp;&amp; 3 &gt; 2");
+        assert_eq!(sanitize_body("R&D; &nbsp;"), "R&amp;D; &amp;nbsp;");
+    }
+
+    #[test]
+    fn test_output_is_well_formed() {
+        assert_eq!(sanitize_body("<b>open"), "<b>open</b>");
+        assert_eq!(sanitize_body("<b><i>x</b>y</i>"), "<b><i>x</i></b>y");
+        assert_eq!(sanitize_body("stray</u> <div><b>in</div>"), "stray <b>in</b>");
+    }
+}
@@ -0,0 +1,484 @@
+// novade-system/src/dbus_interfaces/notifications_server/mod.rs
+
+//! The `org.freedesktop.Notifications` server (Desktop Notifications Specification 1.2) in
+//! front of the domain [`NotificationService`].
+//!
+//! Clients address notifications by `u32` ids while the domain uses UUIDs; [`IdMap`] pairs
+//! them for as long as a notification is open. The `NotificationClosed`, `ActionInvoked`,
+//! `NotificationReplied` and `ActivationToken` signals are driven by the domain's event
+//! stream, so a notification closed or clicked in the notification center is reported exactly
+//! like one closed over D-Bus.
+//!
+//! The shell's notification center talks to [`NotificationCenterInterface`], served under its
+//! own name and path and closed to callers other than the shell.
+
+mod access;
+mod center;
+mod hints;
+mod markup;
+
+pub use access::{CenterAccess, SHELL_EXECUTABLES};
+pub use center::{
+    NotificationCenterInterface, NOTIFICATION_CENTER_INTERFACE, NOTIFICATION_CENTER_OBJECT_PATH,
+    NOTIFICATION_CENTER_SERVICE_NAME,
+};
+pub use hints::{decode_hints, DecodedHints};
+pub use markup::sanitize_body;
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use novade_domain::{
+    DismissReason, NotificationAction, NotificationActionType, NotificationError, NotificationEventEnum,
+    NotificationInput, NotificationService, NotificationUrgency, INLINE_REPLY_ACTION_KEY,
+};
+use tokio::sync::{broadcast, mpsc};
+use uuid::Uuid;
+use zbus::{dbus_interface, fdo, zvariant::Value, Connection, SignalContext};
+
+pub const NOTIFICATIONS_SERVICE_NAME: &str = "org.freedesktop.Notifications";
+pub const NOTIFICATIONS_OBJECT_PATH: &str = "/org/freedesktop/Notifications";
+
+/// Version of the specification implemented.
+const SPEC_VERSION: &str = "1.2";
+/// How long a notification stays open when the client leaves the timeout to the server.
+const DEFAULT_EXPIRE_TIMEOUT: Duration = Duration::from_secs(8);
+const CAPABILITIES: [&str; 8] =
+    ["actions", "body", "body-hyperlinks", "body-markup", "icon-static", "inline-reply", "persistence", "sound"];
+
+// ANCHOR: CloseReason
+/// The `reason` argument of `NotificationClosed`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub enum CloseReason {
+    Expired = 1,
+    Dismissed = 2,
+    /// Closed through `CloseNotification`.
+    Closed = 3,
+    Undefined = 4,
+}
+
+impl CloseReason {
+    /// The reason reported to clients, or `None` for replacements, which keep the
+    /// notification open under the same id.
+    pub fn from_dismiss(reason: DismissReason) -> Option<Self> {
+        match reason {
+            DismissReason::Expired => Some(Self::Expired),
+            DismissReason::ByUser => Some(Self::Dismissed),
+            DismissReason::AppClosed => Some(Self::Closed),
+            DismissReason::SystemShutdown | DismissReason::AppScopeClear => Some(Self::Undefined),
+            DismissReason::Replaced => None,
+        }
+    }
+}
+// ANCHOR_END: CloseReason
+
+// ANCHOR: IdMap
+/// Pairs the `u32` ids handed to clients with domain notification ids.
+#[derive(Debug, Default)]
+struct IdMap {
+    last_id: u32,
+    by_id: HashMap<u32, IdEntry>,
+    by_uuid: HashMap<Uuid, u32>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct IdEntry {
+    uuid: Uuid,
+    /// Bumped on every replacement so expiry timers of earlier versions do nothing.
+    serial: u64,
+}
+
+impl IdMap {
+    /// The id for a `Notify` call with `replaces_id`, the domain notification it replaces and
+    /// the serial of this version. Unknown or closed `replaces_id`s get a fresh id, as the spec
+    /// asks.
+    fn reserve(&mut self, replaces_id: u32) -> (u32, Option<Uuid>, u64) {
+        if let Some(entry) = self.by_id.get_mut(&replaces_id) {
+            entry.serial += 1;
+            return (replaces_id, Some(entry.uuid), entry.serial);
+        }
+        loop {
+            self.last_id = self.last_id.wrapping_add(1);
+            if self.last_id != 0 && !self.by_id.contains_key(&self.last_id) {
+                return (self.last_id, None, 0);
+            }
+        }
+    }
+
+    fn bind(&mut self, id: u32, uuid: Uuid, serial: u64) {
+        if let Some(old) = self.by_id.insert(id, IdEntry { uuid, serial }) {
+            self.by_uuid.remove(&old.uuid);
+        }
+        self.by_uuid.insert(uuid, id);
+    }
+
+    fn uuid(&self, id: u32) -> Option<Uuid> {
+        self.by_id.get(&id).map(|entry| entry.uuid)
+    }
+
+    fn id(&self, uuid: Uuid) -> Option<u32> {
+        self.by_uuid.get(&uuid).copied()
+    }
+
+    fn is_current(&self, id: u32, serial: u64) -> bool {
+        self.by_id.get(&id).is_some_and(|entry| entry.serial == serial)
+    }
+
+    fn remove(&mut self, uuid: Uuid) -> Option<u32> {
+        let id = self.by_uuid.remove(&uuid)?;
+        self.by_id.remove(&id);
+        Some(id)
+    }
+}
+// ANCHOR_END: IdMap
+
+/// A notification version to close once its timeout runs out.
+#[derive(Debug)]
+struct Expiry {
+    id: u32,
+    serial: u64,
+    after: Duration,
+}
+
+pub struct NotificationsDBusService {
+    service: Arc<dyn NotificationService>,
+    ids: Arc<Mutex<IdMap>>,
+    expiries: mpsc::UnboundedSender<Expiry>,
+}
+
+impl NotificationsDBusService {
+    /// Serves the interface at [`NOTIFICATIONS_OBJECT_PATH`] and [`NotificationCenterInterface`],
+    /// open to the callers `access` allows, at [`NOTIFICATION_CENTER_OBJECT_PATH`] on
+    /// `connection`, and starts the task emitting their signals. Requesting
+    /// [`NOTIFICATIONS_SERVICE_NAME`] and [`NOTIFICATION_CENTER_SERVICE_NAME`] is left to the
+    /// caller.
+    ///
+    /// Must be called from within a Tokio runtime.
+    pub async fn serve(connection: &Connection, service: Arc<dyn NotificationService>, access: CenterAccess) -> zbus::Result<()> {
+        // Subscribe before serving so no event of the first notifications is missed.
+        let events = service.subscribe_to_notification_events();
+        let (expiries, expiry_requests) = mpsc::unbounded_channel();
+        let ids = Arc::new(Mutex::new(IdMap::default()));
+        let server = Self { service: service.clone(), ids: ids.clone(), expiries };
+        connection.object_server().at(NOTIFICATIONS_OBJECT_PATH, server).await?;
+        connection.object_server().at(NOTIFICATION_CENTER_OBJECT_PATH, NotificationCenterInterface::new(service.clone(), access)).await?;
+        let ctxt = SignalContext::new(connection, NOTIFICATIONS_OBJECT_PATH)?.into_owned();
+        let center_ctxt = SignalContext::new(connection, NOTIFICATION_CENTER_OBJECT_PATH)?.into_owned();
+        tokio::spawn(run_signals(service, ids, events, expiry_requests, ctxt, center_ctxt));
+        Ok(())
+    }
+
+    fn input(
+        app_name: String,
+        app_icon: String,
+        summary: String,
+        body: String,
+        actions: Vec<String>,
+        hints: &HashMap<String, Value<'_>>,
+        expire_timeout: i32,
+    ) -> fdo::Result<NotificationInput> {
+        if actions.len() % 2 != 0 {
+            return Err(fdo::Error::InvalidArgs("actions must be pairs of key and label".to_string()));
+        }
+        let actions = actions
+            .chunks_exact(2)
+            .map(|pair| NotificationAction {
+                key: pair[0].clone(),
+                label: pair[1].clone(),
+                action_type: if pair[0] == INLINE_REPLY_ACTION_KEY {
+                    NotificationActionType::InlineReply
+                } else {
+                    NotificationActionType::Callback
+                },
+            })
+            .collect();
+        let hints = decode_hints(hints);
+        let body = sanitize_body(&body);
+        Ok(NotificationInput {
+            application_name: app_name,
+            application_icon: Some(app_icon).filter(|icon| !icon.is_empty()),
+            summary,
+            body: Some(body).filter(|body| !body.is_empty()),
+            actions: Some(actions),
+            urgency: hints.urgency,
+            transient: Some(hints.transient),
+            category: hints.category,
+            hints: Some(hints.values),
+            timeout_ms: u32::try_from(expire_timeout).ok(),
+            replaces_id: None,
+            image: hints.image,
+        })
+    }
+
+    /// When the notification should expire: never for a timeout of 0 or for critical ones
+    /// left to the server, after [`DEFAULT_EXPIRE_TIMEOUT`] for others left to the server.
+    fn expire_after(input: &NotificationInput) -> Option<Duration> {
+        match input.timeout_ms {
+            Some(0) => None,
+            Some(ms) => Some(Duration::from_millis(ms.into())),
+            None if input.urgency == Some(NotificationUrgency::Critical) => None,
+            None => Some(DEFAULT_EXPIRE_TIMEOUT),
+        }
+    }
+}
+
+fn to_fdo(error: NotificationError) -> fdo::Error {
+    match error {
+        NotificationError::NotFound(_)
+        | NotificationError::InvalidInputData { .. }
+        | NotificationError::ActionNotFound { .. } => fdo::Error::InvalidArgs(error.to_string()),
+        other => fdo::Error::Failed(other.to_string()),
+    }
+}
+
+#[dbus_interface(name = "org.freedesktop.Notifications")]
+impl NotificationsDBusService {
+    #[allow(clippy::too_many_arguments)]
+    async fn notify(
+        &self,
+        app_name: String,
+        replaces_id: u32,
+        app_icon: String,
+        summary: String,
+        body: String,
+        actions: Vec<String>,
+        hints: HashMap<String, Value<'_>>,
+        expire_timeout: i32,
+    ) -> fdo::Result<u32> {
+        let mut input = Self::input(app_name, app_icon, summary, body, actions, &hints, expire_timeout)?;
+        let expire_after = Self::expire_after(&input);
+        let (id, replaces, serial) = self.ids.lock().unwrap().reserve(replaces_id);
+        input.replaces_id = replaces;
+
+        let uuid = self.service.post_notification(input).await.map_err(to_fdo)?;
+        self.ids.lock().unwrap().bind(id, uuid, serial);
+        if let Some(after) = expire_after {
+            let _ = self.expiries.send(Expiry { id, serial, after });
+        }
+        Ok(id)
+    }
+
+    async fn close_notification(&self, id: u32) -> fdo::Result<()> {
+        // Closing a notification that is already gone is not an error.
+        let Some(uuid) = self.ids.lock().unwrap().uuid(id) else {
+            return Ok(());
+        };
+        match self.service.dismiss_notification(uuid, DismissReason::AppClosed).await {
+            Ok(()) | Err(NotificationError::NotFound(_)) => Ok(()),
+            Err(e) => Err(to_fdo(e)),
+        }
+    }
+
+    async fn get_capabilities(&self) -> Vec<String> {
+        CAPABILITIES.iter().map(|c| c.to_string()).collect()
+    }
+
+    async fn get_server_information(&self) -> (String, String, String, String) {
+        ("NovaDE".to_string(), "NovaDE".to_string(), env!("CARGO_PKG_VERSION").to_string(), SPEC_VERSION.to_string())
+    }
+
+    #[dbus_interface(signal)]
+    async fn notification_closed(signal_ctxt: &SignalContext<'_>, id: u32, reason: u32) -> zbus::Result<()>;
+
+    #[dbus_interface(signal)]
+    async fn action_invoked(signal_ctxt: &SignalContext<'_>, id: u32, action_key: String) -> zbus::Result<()>;
+
+    #[dbus_interface(signal)]
+    async fn notification_replied(signal_ctxt: &SignalContext<'_>, id: u32, text: String) -> zbus::Result<()>;
+
+    #[dbus_interface(signal)]
+    async fn activation_token(signal_ctxt: &SignalContext<'_>, id: u32, activation_token: String) -> zbus::Result<()>;
+}
+
+// ANCHOR: RunSignals
+/// Turns domain events into signals and closes notifications whose timeout ran out.
+async fn run_signals(
+    service: Arc<dyn NotificationService>,
+    ids: Arc<Mutex<IdMap>>,
+    mut events: broadcast::Receiver<NotificationEventEnum>,
+    mut expiry_requests: mpsc::UnboundedReceiver<Expiry>,
+    ctxt: SignalContext<'static>,
+    center_ctxt: SignalContext<'static>,
+) {
+    loop {
+        tokio::select! {
+            event = events.recv() => match event {
+                Ok(event) => handle_event(&service, &ids, &ctxt, &center_ctxt, event).await,
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    tracing::warn!("Notifications: signal task missed {} domain events", missed);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            Some(expiry) = expiry_requests.recv() => {
+                let (service, ids) = (service.clone(), ids.clone());
+                tokio::spawn(async move {
+                    tokio::time::sleep(expiry.after).await;
+                    let uuid = {
+                        let ids = ids.lock().unwrap();
+                        if !ids.is_current(expiry.id, expiry.serial) {
+                            return;
+                        }
+                        ids.uuid(expiry.id)
+                    };
+                    if let Some(uuid) = uuid {
+                        if let Err(e) = service.dismiss_notification(uuid, DismissReason::Expired).await {
+                            tracing::debug!("Notifications: could not expire {}: {}", expiry.id, e);
+                        }
+                    }
+                });
+            }
+        }
+    }
+    tracing::info!("Notifications: domain event stream closed, signal task stopped");
+}
+
+async fn handle_event(
+    service: &Arc<dyn NotificationService>,
+    ids: &Mutex<IdMap>,
+    ctxt: &SignalContext<'static>,
+    center_ctxt: &SignalContext<'static>,
+    event: NotificationEventEnum,
+) {
+    let groups_changed = matches!(
+        event,
+        NotificationEventEnum::NotificationPosted { .. }
+            | NotificationEventEnum::NotificationDigested { .. }
+            | NotificationEventEnum::NotificationUpdated { .. }
+            | NotificationEventEnum::NotificationDismissed { .. }
+            | NotificationEventEnum::NotificationRead { .. }
+            | NotificationEventEnum::NotificationPopupExpired { .. }
+    );
+    if groups_changed {
+        NotificationCenterInterface::notify_groups_changed(center_ctxt).await;
+    }
+    match &event {
+        NotificationEventEnum::NotificationPosted { notification, suppressed_by_dnd: false } => {
+            NotificationCenterInterface::notify_popup(center_ctxt, notification, false).await;
+        }
+        NotificationEventEnum::NotificationUpdated { notification } => {
+            NotificationCenterInterface::notify_popup(center_ctxt, notification, true).await;
+        }
+        NotificationEventEnum::NotificationDismissed { notification_id, .. }
+        | NotificationEventEnum::NotificationPopupExpired { notification_id } => {
+            NotificationCenterInterface::notify_popup_closed(center_ctxt, *notification_id).await;
+        }
+        _ => {}
+    }
+    match event {
+        NotificationEventEnum::NotificationDismissed { notification_id, reason } => {
+            let Some(reason) = CloseReason::from_dismiss(reason) else {
+                return;
+            };
+            let Some(id) = ids.lock().unwrap().remove(notification_id) else {
+                return;
+            };
+            if let Err(e) = NotificationsDBusService::notification_closed(ctxt, id, reason as u32).await {
+                tracing::warn!("Notifications: failed to emit NotificationClosed for {}: {}", id, e);
+            }
+        }
+        NotificationEventEnum::NotificationActionInvoked { notification_id, action_key, activation_token } => {
+            let Some(id) = ids.lock().unwrap().id(notification_id) else {
+                return;
+            };
+            // The token goes first so the client has it when it handles the action.
+            if let Some(token) = activation_token {
+                if let Err(e) = NotificationsDBusService::activation_token(ctxt, id, token).await {
+                    tracing::warn!("Notifications: failed to emit ActivationToken for {}: {}", id, e);
+                }
+            }
+            if let Err(e) = NotificationsDBusService::action_invoked(ctxt, id, action_key).await {
+                tracing::warn!("Notifications: failed to emit ActionInvoked for {}: {}", id, e);
+            }
+            close_unless_resident(service, notification_id, id).await;
+        }
+        NotificationEventEnum::NotificationReplied { notification_id, text } => {
+            let Some(id) = ids.lock().unwrap().id(notification_id) else {
+                return;
+            };
+            if let Err(e) = NotificationsDBusService::notification_replied(ctxt, id, text).await {
+                tracing::warn!("Notifications: failed to emit NotificationReplied for {}: {}", id, e);
+            }
+            close_unless_resident(service, notification_id, id).await;
+        }
+        NotificationEventEnum::NotificationDigested { digest, .. } => {
+            NotificationCenterInterface::notify_digest(center_ctxt, &digest).await;
+        }
+        NotificationEventEnum::FocusModeChanged { active } => {
+            NotificationCenterInterface::notify_focus_changed(center_ctxt, active.as_ref()).await;
+        }
+        NotificationEventEnum::FocusModeEnded { summary } => {
+            NotificationCenterInterface::notify_focus_ended(center_ctxt, &summary).await;
+        }
+        _ => {}
+    }
+}
+
+/// Unless marked resident, a notification closes once one of its actions is used.
+async fn close_unless_resident(service: &Arc<dyn NotificationService>, notification_id: Uuid, id: u32) {
+    let resident = match service.get_notification(notification_id).await {
+        Ok(Some(notification)) => notification.hints.get("resident").and_then(|v| v.as_bool()).unwrap_or(false),
+        _ => false,
+    };
+    if !resident {
+        if let Err(e) = service.dismiss_notification(notification_id, DismissReason::ByUser).await {
+            tracing::debug!("Notifications: could not close {} after its action: {}", id, e);
+        }
+    }
+}
+// ANCHOR_END: RunSignals
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_id_map_replacement_and_reuse() {
+        let mut ids = IdMap::default();
+        let (first, replaces, serial) = ids.reserve(0);
+        assert_eq!((first, replaces, serial), (1, None, 0));
+        let uuid = Uuid::new_v4();
+        ids.bind(first, uuid, serial);
+
+        // Replacing keeps the id and the domain notification, and retires the old timer.
+        assert_eq!(ids.reserve(first), (first, Some(uuid), 1));
+        ids.bind(first, uuid, 1);
+        assert!(!ids.is_current(first, 0) && ids.is_current(first, 1));
+
+        // An unknown id to replace gets a fresh one.
+        assert_eq!(ids.reserve(77).0, 2);
+        assert_eq!(ids.remove(uuid), Some(first));
+        assert_eq!(ids.reserve(first).1, None);
+    }
+
+    #[test]
+    fn test_close_reasons() {
+        assert_eq!(CloseReason::from_dismiss(DismissReason::Expired), Some(CloseReason::Expired));
+        assert_eq!(CloseReason::from_dismiss(DismissReason::ByUser).map(|r| r as u32), Some(2));
+        assert_eq!(CloseReason::from_dismiss(DismissReason::AppClosed).map(|r| r as u32), Some(3));
+        assert_eq!(CloseReason::from_dismiss(DismissReason::SystemShutdown), Some(CloseReason::Undefined));
+        assert_eq!(CloseReason::from_dismiss(DismissReason::Replaced), None);
+    }
+
+    #[test]
+    fn test_inline_reply_action() {
+        let actions = vec!["default".to_string(), "Open".to_string(), "inline-reply".to_string(), "Send".to_string()];
+        let input = NotificationsDBusService::input(
+            "Chat".to_string(), String::new(), "Alice".to_string(), "lunch?".to_string(), actions, &HashMap::new(), -1,
+        )
+        .unwrap();
+        let types: Vec<_> = input.actions.unwrap().into_iter().map(|a| a.action_type).collect();
+        assert_eq!(types, [NotificationActionType::Callback, NotificationActionType::InlineReply]);
+        assert!(CAPABILITIES.contains(&"inline-reply"));
+    }
+
+    #[test]
+    fn test_expire_timeouts() {
+        let input = |timeout_ms, urgency| NotificationInput { timeout_ms, urgency, ..Default::default() };
+        assert_eq!(NotificationsDBusService::expire_after(&input(None, None)), Some(DEFAULT_EXPIRE_TIMEOUT));
+        assert_eq!(NotificationsDBusService::expire_after(&input(Some(0), None)), None);
+        assert_eq!(NotificationsDBusService::expire_after(&input(Some(1500), None)), Some(Duration::from_millis(1500)));
+        assert_eq!(NotificationsDBusService::expire_after(&input(None, Some(NotificationUrgency::Critical))), None);
+    }
+}
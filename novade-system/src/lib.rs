@@ -1,4 +1,6 @@
 pub mod application_manager; // Added for assistant integration
+pub mod audio_management;
+pub mod audio_profiles;
 pub mod dbus_integration;
 pub mod dbus_interfaces; // Added new module
 pub mod input;
@@ -6,7 +8,11 @@ pub mod network_manager; // New module path
 pub mod system_health_collectors;
 pub mod power_management; // New module path
 pub mod renderer; // Added this line
+pub mod sound_theme;
 pub mod filesystem_service; // Added for assistant integration
+pub mod notification_context;
+pub mod notification_history;
+pub mod notification_sounds;
 pub mod system_services;
 pub mod system_settings_service; // Added for assistant integration
 pub mod window_info_provider;
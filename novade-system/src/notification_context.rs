@@ -0,0 +1,321 @@
+// novade-system/src/notification_context.rs
+
+//! Feeds the desktop state the notification service decides on.
+//!
+//! A thread follows the compositor over IPC, as the shell's recording indicator does, and
+//! reports which windows are fullscreen, the focused app, the workspace on the focused output and
+//! whether the screen is being recorded. A task forwards those to the [`NotificationService`] as
+//! focus mode triggers and notification rule context. Once a minute it also reports the battery
+//! from the [`PowerManager`], re-evaluates focus schedules and releases snoozed notifications.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+use novade_domain::{FocusTrigger, NotificationService, RuleContextChange};
+use tokio::sync::mpsc;
+
+use crate::compositor::ipc::protocol::{default_socket_path, IpcEvent, IpcEventKind, IpcRequest, IpcWindow, WindowChange, WorkspaceSummary};
+use crate::compositor::ipc::server::IpcClient;
+use crate::compositor::recorder::RecordingStatus;
+use crate::power_management::{BatteryState, PowerManager};
+use crate::window_mechanics::data_types::{WindowId, WindowState};
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(2);
+const SCHEDULE_TICK: Duration = Duration::from_secs(60);
+
+/// The compositor state behind [`FocusTrigger`]s and notification rule context.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct NotificationDesktopContext {
+    fullscreen: HashSet<WindowId>,
+    recording: bool,
+    focused_window: Option<WindowId>,
+    focused_app: Option<String>,
+    active_workspace: Option<String>,
+}
+
+impl NotificationDesktopContext {
+    /// Starts over from the compositor's current windows, workspaces and recording.
+    pub fn reset(&mut self, windows: &[IpcWindow], workspaces: &[WorkspaceSummary], recording: &RecordingStatus) {
+        self.fullscreen = windows.iter().filter(|w| Self::is_fullscreen(w)).map(|w| w.info.id).collect();
+        let focused = windows.iter().find(|w| w.mapped && w.focused);
+        self.focused_window = focused.map(|w| w.info.id);
+        self.focused_app = focused.and_then(|w| w.app_id.clone());
+        self.set_workspaces(workspaces);
+        self.recording = recording.active;
+    }
+
+    pub fn set_workspaces(&mut self, workspaces: &[WorkspaceSummary]) {
+        self.active_workspace = workspaces.iter().find(|w| w.focused).map(|w| w.name.clone());
+    }
+
+    pub fn apply(&mut self, event: &IpcEvent) {
+        match event {
+            IpcEvent::Window { id, window, .. } => {
+                match window {
+                    Some(window) if Self::is_fullscreen(window) => self.fullscreen.insert(*id),
+                    _ => self.fullscreen.remove(id),
+                };
+                match window {
+                    Some(window) if window.mapped && window.focused => {
+                        self.focused_window = Some(*id);
+                        self.focused_app = window.app_id.clone();
+                    }
+                    _ if self.focused_window == Some(*id) => {
+                        self.focused_window = None;
+                        self.focused_app = None;
+                    }
+                    _ => {}
+                }
+            }
+            IpcEvent::Recording { status } => self.recording = status.active,
+            _ => {}
+        }
+    }
+
+    pub fn is_active(&self, trigger: FocusTrigger) -> bool {
+        match trigger {
+            FocusTrigger::Fullscreen => !self.fullscreen.is_empty(),
+            FocusTrigger::Screencast => self.recording,
+        }
+    }
+
+    /// What changed for notification rules since `reported`.
+    pub fn rule_context_changes(&self, reported: &NotificationDesktopContext) -> Vec<RuleContextChange> {
+        let mut changes = Vec::new();
+        if self.focused_app != reported.focused_app {
+            changes.push(RuleContextChange::FocusedApp(self.focused_app.clone()));
+        }
+        let fullscreen = self.is_active(FocusTrigger::Fullscreen);
+        if fullscreen != reported.is_active(FocusTrigger::Fullscreen) {
+            changes.push(RuleContextChange::Fullscreen(fullscreen));
+        }
+        if self.active_workspace != reported.active_workspace {
+            changes.push(RuleContextChange::ActiveWorkspace(self.active_workspace.clone()));
+        }
+        changes
+    }
+
+    fn is_fullscreen(window: &IpcWindow) -> bool {
+        window.mapped && window.info.state == WindowState::Fullscreen
+    }
+}
+
+/// Starts following the compositor and the battery for `service`, and ticking its focus
+/// schedules and snoozes.
+pub fn spawn_notification_context(service: Arc<dyn NotificationService>, power_manager: Arc<dyn PowerManager>) {
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    if let Err(e) = std::thread::Builder::new().name("notification-context".to_string()).spawn(move || follow_compositor(sender)) {
+        tracing::error!("NotificationContext: could not start the compositor thread: {}", e);
+    }
+
+    tokio::spawn(async move {
+        let mut tick = tokio::time::interval(SCHEDULE_TICK);
+        let mut reported = NotificationDesktopContext::default();
+        let mut reported_battery = None;
+        loop {
+            tokio::select! {
+                state = receiver.recv() => {
+                    let Some(state) = state else { break };
+                    for trigger in [FocusTrigger::Fullscreen, FocusTrigger::Screencast] {
+                        let active = state.is_active(trigger);
+                        if active == reported.is_active(trigger) {
+                            continue;
+                        }
+                        if let Err(e) = service.set_focus_trigger(trigger, active).await {
+                            tracing::warn!("NotificationContext: could not report {:?}: {}", trigger, e);
+                        }
+                    }
+                    for change in state.rule_context_changes(&reported) {
+                        if let Err(e) = service.update_rule_context(change).await {
+                            tracing::warn!("NotificationContext: could not update the rule context: {}", e);
+                        }
+                    }
+                    reported = state;
+                }
+                _ = tick.tick() => {
+                    let battery = battery_context(power_manager.as_ref()).await;
+                    if reported_battery.as_ref() != Some(&battery) {
+                        match service.update_rule_context(battery.clone()).await {
+                            Ok(()) => reported_battery = Some(battery),
+                            Err(e) => tracing::warn!("NotificationContext: could not report the battery: {}", e),
+                        }
+                    }
+                    if let Err(e) = service.refresh_focus().await {
+                        tracing::warn!("NotificationContext: could not refresh focus schedules: {}", e);
+                    }
+                    match service.release_snoozed().await {
+                        Ok(0) => {}
+                        Ok(released) => tracing::debug!("NotificationContext: released {} snoozed notifications", released),
+                        Err(e) => tracing::warn!("NotificationContext: could not release snoozed notifications: {}", e),
+                    }
+                }
+            }
+        }
+        tracing::info!("NotificationContext: compositor thread stopped");
+    });
+}
+
+/// The battery as rule context. Without a readable battery the system counts as on line power.
+async fn battery_context(power_manager: &dyn PowerManager) -> RuleContextChange {
+    match power_manager.get_battery_info().await {
+        Ok(battery) if battery.present => RuleContextChange::Battery {
+            percent: Some(battery.percentage.clamp(0.0, 100.0).round() as u8),
+            on_battery: battery.state == BatteryState::Discharging,
+        },
+        Ok(_) => RuleContextChange::Battery { percent: None, on_battery: false },
+        Err(e) => {
+            tracing::debug!("NotificationContext: could not read the battery: {}", e);
+            RuleContextChange::Battery { percent: None, on_battery: false }
+        }
+    }
+}
+
+fn compositor_snapshot(client: &mut IpcClient) -> Result<(Vec<IpcWindow>, Vec<WorkspaceSummary>, RecordingStatus), String> {
+    let windows = client.request(&IpcRequest::GetWindows).map_err(|e| e.to_string())?.into_data()?;
+    let workspaces = workspaces(client)?;
+    let recording = client.request(&IpcRequest::GetRecording).map_err(|e| e.to_string())?.into_data()?;
+    Ok((windows, workspaces, recording))
+}
+
+fn workspaces(client: &mut IpcClient) -> Result<Vec<WorkspaceSummary>, String> {
+    client.request(&IpcRequest::GetWorkspaces).map_err(|e| e.to_string())?.into_data()
+}
+
+fn connect() -> Result<IpcClient, String> {
+    let path = default_socket_path().ok_or_else(|| "compositor IPC socket not found".to_string())?;
+    IpcClient::connect(&path).map_err(|e| e.to_string())
+}
+
+/// Sends the desktop state after every relevant change until the receiver is gone. Events do not
+/// carry workspace names, so a second connection fetches the workspaces after workspace and
+/// focus changes. Reconnects when the compositor restarts; while it is gone no trigger holds.
+fn follow_compositor(sender: mpsc::UnboundedSender<NotificationDesktopContext>) {
+    let mut state = NotificationDesktopContext::default();
+    loop {
+        let connected = connect().and_then(|mut requests| Ok((compositor_snapshot(&mut requests)?, requests, connect()?)));
+        match connected {
+            Ok(((windows, workspaces, recording), mut requests, client)) => {
+                state.reset(&windows, &workspaces, &recording);
+                if sender.send(state.clone()).is_err() {
+                    return;
+                }
+                match client.subscribe(vec![IpcEventKind::Window, IpcEventKind::Workspace, IpcEventKind::Recording]) {
+                    Ok(events) => {
+                        for event in events {
+                            match event {
+                                Ok(event) => {
+                                    let before = state.clone();
+                                    state.apply(&event);
+                                    if matches!(event, IpcEvent::Workspace { .. } | IpcEvent::Window { change: WindowChange::Focus, .. }) {
+                                        match workspaces(&mut requests) {
+                                            Ok(workspaces) => state.set_workspaces(&workspaces),
+                                            Err(e) => tracing::debug!("NotificationContext: could not fetch workspaces: {}", e),
+                                        }
+                                    }
+                                    if state != before && sender.send(state.clone()).is_err() {
+                                        return;
+                                    }
+                                }
+                                Err(e) => {
+                                    tracing::debug!("NotificationContext: event stream ended: {}", e);
+                                    break;
+                                }
+                            }
+                        }
+                    }
+                    Err(e) => tracing::debug!("NotificationContext: could not subscribe to compositor events: {}", e),
+                }
+            }
+            Err(e) => tracing::debug!("NotificationContext: could not reach the compositor: {}", e),
+        }
+        state = NotificationDesktopContext::default();
+        if sender.send(state.clone()).is_err() {
+            return;
+        }
+        std::thread::sleep(RECONNECT_DELAY);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::window_mechanics::data_types::{WindowInfo, WindowRect};
+    use uuid::Uuid;
+
+    fn window(id: WindowId, state: WindowState) -> IpcWindow {
+        IpcWindow {
+            info: WindowInfo::new(id, "video".to_string(), WindowRect::new(0.0, 0.0, 1920.0, 1080.0), state),
+            app_id: Some("mpv".to_string()),
+            workspace_id: None,
+            output: None,
+            focused: true,
+            mapped: true,
+        }
+    }
+
+    fn workspace(name: &str, focused: bool) -> WorkspaceSummary {
+        WorkspaceSummary {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            output: Some("eDP-1".to_string()),
+            index: Some(1),
+            active: focused,
+            focused,
+            layout: "tiling".to_string(),
+            windows: Vec::new(),
+            persistent_id: None,
+            icon_name: None,
+            accent_color_hex: None,
+        }
+    }
+
+    #[test]
+    fn test_fullscreen_and_recording_triggers() {
+        let (video, editor) = (WindowId::new_v4(), WindowId::new_v4());
+        let mut state = NotificationDesktopContext::default();
+        state.reset(&[window(video, WindowState::Fullscreen), window(editor, WindowState::Tiled)], &[], &RecordingStatus::default());
+        assert!(state.is_active(FocusTrigger::Fullscreen));
+        assert!(!state.is_active(FocusTrigger::Screencast));
+
+        let leave = IpcEvent::Window { change: WindowChange::State, id: video, window: Some(window(video, WindowState::Floating)) };
+        state.apply(&leave);
+        assert!(!state.is_active(FocusTrigger::Fullscreen));
+        let enter = IpcEvent::Window { change: WindowChange::State, id: editor, window: Some(window(editor, WindowState::Fullscreen)) };
+        state.apply(&enter);
+        assert!(state.is_active(FocusTrigger::Fullscreen));
+        state.apply(&IpcEvent::Window { change: WindowChange::Close, id: editor, window: None });
+        assert!(!state.is_active(FocusTrigger::Fullscreen));
+
+        state.apply(&IpcEvent::Recording { status: RecordingStatus { active: true, ..Default::default() } });
+        assert!(state.is_active(FocusTrigger::Screencast));
+    }
+
+    #[test]
+    fn test_rule_context_changes() {
+        let video = WindowId::new_v4();
+        let mut state = NotificationDesktopContext::default();
+        state.reset(&[window(video, WindowState::Fullscreen)], &[workspace("Web", false), workspace("Media", true)], &RecordingStatus::default());
+        assert_eq!(
+            state.rule_context_changes(&NotificationDesktopContext::default()),
+            [
+                RuleContextChange::FocusedApp(Some("mpv".to_string())),
+                RuleContextChange::Fullscreen(true),
+                RuleContextChange::ActiveWorkspace(Some("Media".to_string())),
+            ]
+        );
+
+        let before = state.clone();
+        state.set_workspaces(&[workspace("Web", true), workspace("Media", false)]);
+        state.apply(&IpcEvent::Window { change: WindowChange::Close, id: video, window: None });
+        assert_eq!(
+            state.rule_context_changes(&before),
+            [
+                RuleContextChange::FocusedApp(None),
+                RuleContextChange::Fullscreen(false),
+                RuleContextChange::ActiveWorkspace(Some("Web".to_string())),
+            ]
+        );
+        assert!(state.rule_context_changes(&state.clone()).is_empty());
+    }
+}
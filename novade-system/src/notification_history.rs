@@ -0,0 +1,217 @@
+// novade-system/src/notification_history.rs
+
+//! SQLite home of the notification history, attached to the domain service at startup.
+//!
+//! Entries are stored as the JSON of the domain `Notification`. An FTS5 table indexes their
+//! summary and body for `search_history`, and it is updated in the same transaction as the
+//! entries. The tokenizer keeps diacritics so the index agrees with the in-memory matching of
+//! the domain.
+
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use novade_domain::{Notification, NotificationError, NotificationHistoryStore};
+use rusqlite::{params, Connection};
+use uuid::Uuid;
+
+/// File name of the history in the application data directory.
+pub const NOTIFICATION_HISTORY_FILE: &str = "notification-history.sqlite3";
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS notifications (
+        id TEXT PRIMARY KEY,
+        timestamp_ms INTEGER NOT NULL,
+        data TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS notifications_by_time ON notifications (timestamp_ms);
+    CREATE VIRTUAL TABLE IF NOT EXISTS notification_text
+        USING fts5(id UNINDEXED, summary, body, tokenize = 'unicode61 remove_diacritics 0');
+";
+
+pub struct SqliteNotificationHistoryStore {
+    connection: Arc<Mutex<Connection>>,
+}
+
+impl SqliteNotificationHistoryStore {
+    /// Opens the history in the application data directory, creating it on first use.
+    pub fn open_default() -> Result<Self, NotificationError> {
+        let error = |e| NotificationError::PersistenceError {
+            operation: "open_history".to_string(),
+            source_message: "No data directory for the notification history".to_string(),
+            source: Some(e),
+        };
+        let dir = novade_core::get_app_data_dir().map_err(error)?;
+        novade_core::ensure_dir_exists(&dir).map_err(error)?;
+        Self::open(&dir.join(NOTIFICATION_HISTORY_FILE))
+    }
+
+    pub fn open(path: &Path) -> Result<Self, NotificationError> {
+        let connection = Connection::open(path).map_err(|e| sqlite_error("open_history", e))?;
+        tracing::info!("Notification history at {}", path.display());
+        Self::init(connection)
+    }
+
+    /// A history that lives as long as the store, for tests.
+    pub fn open_in_memory() -> Result<Self, NotificationError> {
+        Self::init(Connection::open_in_memory().map_err(|e| sqlite_error("open_history", e))?)
+    }
+
+    fn init(connection: Connection) -> Result<Self, NotificationError> {
+        connection.execute_batch(SCHEMA).map_err(|e| sqlite_error("create_history_schema", e))?;
+        Ok(Self { connection: Arc::new(Mutex::new(connection)) })
+    }
+
+    /// Runs `f` on a blocking thread, SQLite calls being synchronous.
+    async fn with_connection<T, F>(&self, operation: &'static str, f: F) -> Result<T, NotificationError>
+    where
+        F: FnOnce(&mut Connection) -> rusqlite::Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let connection = self.connection.clone();
+        tokio::task::spawn_blocking(move || f(&mut connection.lock().unwrap()))
+            .await
+            .map_err(|e| NotificationError::persistence_error_no_source(operation, e.to_string()))?
+            .map_err(|e| sqlite_error(operation, e))
+    }
+}
+
+fn sqlite_error(operation: &str, e: rusqlite::Error) -> NotificationError {
+    NotificationError::persistence_error_no_source(operation, format!("SQLite: {}", e))
+}
+
+/// An FTS5 query matching entries with a word starting with each term.
+fn match_expression(terms: &[String]) -> String {
+    terms.iter().map(|term| format!("\"{}\"*", term.replace('"', "\"\""))).collect::<Vec<_>>().join(" ")
+}
+
+#[async_trait]
+impl NotificationHistoryStore for SqliteNotificationHistoryStore {
+    async fn load(&self) -> Result<Vec<Notification>, NotificationError> {
+        let rows = self
+            .with_connection("load_history", |connection| {
+                let mut statement = connection.prepare("SELECT id, data FROM notifications ORDER BY timestamp_ms, rowid")?;
+                let rows = statement.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?;
+                rows.collect::<rusqlite::Result<Vec<_>>>()
+            })
+            .await?;
+        Ok(rows
+            .into_iter()
+            .filter_map(|(id, data)| match serde_json::from_str(&data) {
+                Ok(notification) => Some(notification),
+                Err(e) => {
+                    tracing::warn!("Notification history: skipping unreadable entry {}: {}", id, e);
+                    None
+                }
+            })
+            .collect())
+    }
+
+    async fn upsert(&self, notifications: &[Notification]) -> Result<(), NotificationError> {
+        let rows = notifications
+            .iter()
+            .map(|n| {
+                let data = serde_json::to_string(n).map_err(|e| NotificationError::InternalError(format!("History serialization failed: {}", e)))?;
+                Ok((n.id.to_string(), n.timestamp.timestamp_millis(), data, n.summary.clone(), n.body.clone().unwrap_or_default()))
+            })
+            .collect::<Result<Vec<_>, NotificationError>>()?;
+        self.with_connection("store_history", move |connection| {
+            let transaction = connection.transaction()?;
+            for (id, timestamp_ms, data, summary, body) in &rows {
+                transaction.execute(
+                    "INSERT INTO notifications (id, timestamp_ms, data) VALUES (?1, ?2, ?3)
+                     ON CONFLICT(id) DO UPDATE SET timestamp_ms = excluded.timestamp_ms, data = excluded.data",
+                    params![id, timestamp_ms, data],
+                )?;
+                transaction.execute("DELETE FROM notification_text WHERE id = ?1", params![id])?;
+                transaction.execute("INSERT INTO notification_text (id, summary, body) VALUES (?1, ?2, ?3)", params![id, summary, body])?;
+            }
+            transaction.commit()
+        })
+        .await
+    }
+
+    async fn remove(&self, ids: &[Uuid]) -> Result<(), NotificationError> {
+        let ids: Vec<String> = ids.iter().map(Uuid::to_string).collect();
+        self.with_connection("remove_history", move |connection| {
+            let transaction = connection.transaction()?;
+            for id in &ids {
+                transaction.execute("DELETE FROM notifications WHERE id = ?1", params![id])?;
+                transaction.execute("DELETE FROM notification_text WHERE id = ?1", params![id])?;
+            }
+            transaction.commit()
+        })
+        .await
+    }
+
+    async fn clear(&self) -> Result<(), NotificationError> {
+        self.with_connection("clear_history", |connection| {
+            connection.execute_batch("DELETE FROM notifications; DELETE FROM notification_text;")
+        })
+        .await
+    }
+
+    async fn search(&self, terms: &[String], limit: usize) -> Result<Vec<Uuid>, NotificationError> {
+        if terms.is_empty() {
+            return Ok(Vec::new());
+        }
+        let expression = match_expression(terms);
+        let limit = i64::try_from(limit).unwrap_or(i64::MAX);
+        let ids = self
+            .with_connection("search_history", move |connection| {
+                let mut statement =
+                    connection.prepare("SELECT id FROM notification_text WHERE notification_text MATCH ?1 ORDER BY rank LIMIT ?2")?;
+                let rows = statement.query_map(params![expression, limit], |row| row.get::<_, String>(0))?;
+                rows.collect::<rusqlite::Result<Vec<_>>>()
+            })
+            .await?;
+        Ok(ids.iter().filter_map(|id| id.parse().ok()).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, Utc};
+    use novade_domain::{search_terms, NotificationUrgency};
+
+    fn notification(summary: &str, body: &str, minutes_ago: i64) -> Notification {
+        let mut notification = Notification::new("Bank".to_string(), summary.to_string(), NotificationUrgency::Normal);
+        notification.body = Some(body.to_string());
+        notification.timestamp = Utc::now() - Duration::minutes(minutes_ago);
+        notification
+    }
+
+    #[tokio::test]
+    async fn test_store_load_and_search() {
+        let store = SqliteNotificationHistoryStore::open_in_memory().unwrap();
+        let code = notification("Sign-in", "Your 2FA code is 491204", 60);
+        let mut statement = notification("Statement", "Your statement is ready", 120);
+        store.upsert(&[code.clone(), statement.clone()]).await.unwrap();
+
+        let loaded = store.load().await.unwrap();
+        assert_eq!(loaded.iter().map(|n| n.id).collect::<Vec<_>>(), [statement.id, code.id]);
+        assert_eq!(loaded[1], code);
+        assert_eq!(store.search(&search_terms("2fa COD"), 10).await.unwrap(), [code.id]);
+        assert_eq!(store.search(&search_terms("your"), 10).await.unwrap().len(), 2);
+        assert!(store.search(&search_terms("ode"), 10).await.unwrap().is_empty());
+
+        // Replacing an entry re-indexes it.
+        statement.body = Some("Your 2FA device changed".to_string());
+        store.upsert(&[statement.clone()]).await.unwrap();
+        assert_eq!(store.load().await.unwrap().len(), 2);
+        assert_eq!(store.search(&search_terms("2fa"), 10).await.unwrap().len(), 2);
+        assert_eq!(store.search(&search_terms("2fa"), 1).await.unwrap().len(), 1);
+
+        store.remove(&[code.id]).await.unwrap();
+        assert_eq!(store.search(&search_terms("2fa"), 10).await.unwrap(), [statement.id]);
+        store.clear().await.unwrap();
+        assert!(store.load().await.unwrap().is_empty());
+        assert!(store.search(&search_terms("2fa"), 10).await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_match_expression() {
+        assert_eq!(match_expression(&search_terms("2FA code")), r#""2fa"* "code"*"#);
+    }
+}
@@ -0,0 +1,215 @@
+// novade-system/src/notification_sounds.rs
+
+//! Plays notification sounds through the [`AudioManager`].
+//!
+//! A task follows the events of the [`NotificationService`]. Popups that are shown and
+//! notifications routed to the sound-only channel play the sound their hints ask for, looked up
+//! in the [`SoundTheme`], or the default sound. The service already decided on Do Not Disturb,
+//! focus modes and bursts: held-back, digested and history-only notifications stay silent.
+//! Sounds play with the notification role, so they follow its volume, and no more than
+//! `max_overlapping` play at once; others are dropped rather than played late.
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use novade_domain::{Notification, NotificationChannel, NotificationEventEnum, NotificationService, NotificationSound, NotificationUrgency};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::audio_management::{AudioManager, AudioRole};
+use crate::sound_theme::{SoundTheme, FALLBACK_SOUND_THEME};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct NotificationSoundConfig {
+    pub enabled: bool,
+    pub theme: String,
+    /// Played for normal and critical notifications without a sound of their own; `None`
+    /// keeps those silent.
+    pub default_sound: Option<String>,
+    pub max_overlapping: usize,
+}
+
+impl Default for NotificationSoundConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            theme: FALLBACK_SOUND_THEME.to_string(),
+            default_sound: Some("message-new-instant".to_string()),
+            max_overlapping: 2,
+        }
+    }
+}
+
+pub struct NotificationSoundPlayer {
+    audio: Arc<dyn AudioManager>,
+    theme: SoundTheme,
+    config: NotificationSoundConfig,
+    playing: Arc<AtomicUsize>,
+}
+
+/// Counts a sound as playing until dropped.
+struct PlayingGuard(Arc<AtomicUsize>);
+
+impl Drop for PlayingGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl NotificationSoundPlayer {
+    pub fn new(audio: Arc<dyn AudioManager>, config: NotificationSoundConfig) -> Self {
+        let theme = SoundTheme::new(config.theme.clone());
+        Self::with_theme(audio, theme, config)
+    }
+
+    pub fn with_theme(audio: Arc<dyn AudioManager>, theme: SoundTheme, config: NotificationSoundConfig) -> Self {
+        Self { audio, theme, config, playing: Arc::new(AtomicUsize::new(0)) }
+    }
+
+    /// The file to play for `event`, if it calls for a sound.
+    pub fn sound_for(&self, event: &NotificationEventEnum) -> Option<PathBuf> {
+        let notification = match event {
+            NotificationEventEnum::NotificationPosted { notification, suppressed_by_dnd: false } => notification,
+            NotificationEventEnum::NotificationRouted { notification, channel: NotificationChannel::SoundOnly } => notification,
+            _ => return None,
+        };
+        if !self.config.enabled {
+            return None;
+        }
+        self.resolve(notification)
+    }
+
+    fn resolve(&self, notification: &Notification) -> Option<PathBuf> {
+        let default = || {
+            let name = self.config.default_sound.as_deref().filter(|_| notification.urgency != NotificationUrgency::Low)?;
+            self.theme.lookup(name)
+        };
+        match notification.sound() {
+            NotificationSound::Silent => None,
+            NotificationSound::Default => default(),
+            NotificationSound::File(path) if path.is_file() => Some(path),
+            NotificationSound::File(path) => {
+                tracing::debug!("NotificationSounds: {} of notification {} does not exist", path.display(), notification.id);
+                None
+            }
+            NotificationSound::Named(name) => self.theme.lookup(&name).or_else(|| {
+                tracing::debug!("NotificationSounds: theme '{}' has no sound '{}', playing the default", self.theme.name(), name);
+                default()
+            }),
+        }
+    }
+
+    /// Starts playing `path` unless `max_overlapping` sounds are already playing; returns
+    /// whether it started.
+    pub fn play(&self, path: PathBuf) -> bool {
+        if self.playing.fetch_add(1, Ordering::SeqCst) >= self.config.max_overlapping {
+            self.playing.fetch_sub(1, Ordering::SeqCst);
+            tracing::debug!("NotificationSounds: {} sounds already playing, dropping {}", self.config.max_overlapping, path.display());
+            return false;
+        }
+        let guard = PlayingGuard(self.playing.clone());
+        let audio = self.audio.clone();
+        tokio::spawn(async move {
+            let _guard = guard;
+            if let Err(e) = audio.play_sound(&path, AudioRole::Notification).await {
+                tracing::warn!("NotificationSounds: could not play {}: {}", path.display(), e);
+            }
+        });
+        true
+    }
+}
+
+/// Starts playing the sounds of `service`'s notifications.
+pub fn spawn_notification_sounds(service: Arc<dyn NotificationService>, player: NotificationSoundPlayer) {
+    let mut events = service.subscribe_to_notification_events();
+    tokio::spawn(async move {
+        loop {
+            match events.recv().await {
+                Ok(event) => {
+                    if let Some(path) = player.sound_for(&event) {
+                        player.play(path);
+                    }
+                }
+                Err(RecvError::Lagged(skipped)) => tracing::warn!("NotificationSounds: missed {} notification events", skipped),
+                Err(RecvError::Closed) => break,
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio_management::NullAudioManager;
+    use novade_domain::{SOUND_FILE_HINT, SOUND_NAME_HINT, SUPPRESS_SOUND_HINT};
+    use std::time::Duration;
+
+    fn notification(urgency: NotificationUrgency, hints: &[(&str, serde_json::Value)]) -> Notification {
+        let mut notification = Notification::new("Chat".to_string(), "Alice".to_string(), urgency);
+        notification.hints.extend(hints.iter().map(|(key, value)| (key.to_string(), value.clone())));
+        notification
+    }
+
+    fn posted(notification: Notification, suppressed_by_dnd: bool) -> NotificationEventEnum {
+        NotificationEventEnum::NotificationPosted { notification, suppressed_by_dnd }
+    }
+
+    fn install(base: &std::path::Path, name: &str) -> PathBuf {
+        let path = base.join("freedesktop/stereo").join(format!("{}.oga", name));
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, "").unwrap();
+        path
+    }
+
+    #[tokio::test]
+    async fn test_sound_for_events() {
+        let sounds = tempfile::tempdir().unwrap();
+        let (instant, bell) = (install(sounds.path(), "message-new-instant"), install(sounds.path(), "bell"));
+        let theme = SoundTheme::with_base_dirs(FALLBACK_SOUND_THEME, vec![sounds.path().to_path_buf()]);
+        let mut player = NotificationSoundPlayer::with_theme(Arc::new(NullAudioManager::new()), theme, NotificationSoundConfig::default());
+        let normal = notification(NotificationUrgency::Normal, &[]);
+
+        assert_eq!(player.sound_for(&posted(normal.clone(), false)), Some(instant.clone()));
+        assert_eq!(player.sound_for(&posted(normal.clone(), true)), None);
+        assert_eq!(player.sound_for(&NotificationEventEnum::NotificationUpdated { notification: normal.clone() }), None);
+        assert_eq!(player.sound_for(&posted(notification(NotificationUrgency::Low, &[]), false)), None);
+        let named = notification(NotificationUrgency::Low, &[(SOUND_NAME_HINT, "bell".into())]);
+        assert_eq!(player.sound_for(&posted(named.clone(), false)), Some(bell.clone()));
+        let unknown = notification(NotificationUrgency::Normal, &[(SOUND_NAME_HINT, "trumpet".into())]);
+        assert_eq!(player.sound_for(&posted(unknown, false)), Some(instant.clone()));
+        let file = notification(NotificationUrgency::Normal, &[(SOUND_FILE_HINT, bell.to_str().unwrap().into())]);
+        assert_eq!(player.sound_for(&posted(file, false)), Some(bell.clone()));
+        let missing_file = notification(NotificationUrgency::Normal, &[(SOUND_FILE_HINT, "/nonexistent/ping.oga".into())]);
+        assert_eq!(player.sound_for(&posted(missing_file, false)), None);
+        let suppressed = notification(NotificationUrgency::Critical, &[(SOUND_NAME_HINT, "bell".into()), (SUPPRESS_SOUND_HINT, true.into())]);
+        assert_eq!(player.sound_for(&posted(suppressed, false)), None);
+
+        let routed = |channel| NotificationEventEnum::NotificationRouted { notification: named.clone(), channel };
+        assert_eq!(player.sound_for(&routed(NotificationChannel::SoundOnly)), Some(bell));
+        assert_eq!(player.sound_for(&routed(NotificationChannel::HistoryOnly)), None);
+
+        player.config.enabled = false;
+        assert_eq!(player.sound_for(&posted(normal, false)), None);
+    }
+
+    #[tokio::test]
+    async fn test_overlapping_sounds_are_limited() {
+        let audio = Arc::new(NullAudioManager::with_playback_duration(Duration::from_millis(100)));
+        let theme = SoundTheme::with_base_dirs(FALLBACK_SOUND_THEME, Vec::new());
+        let player = NotificationSoundPlayer::with_theme(audio.clone(), theme, NotificationSoundConfig::default());
+
+        assert!(player.play(PathBuf::from("/a.oga")));
+        assert!(player.play(PathBuf::from("/b.oga")));
+        assert!(!player.play(PathBuf::from("/c.oga")));
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert!(player.play(PathBuf::from("/d.oga")));
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        let played: Vec<PathBuf> = audio.played().into_iter().map(|(path, role)| {
+            assert_eq!(role, AudioRole::Notification);
+            path
+        }).collect();
+        assert_eq!(played, [PathBuf::from("/a.oga"), PathBuf::from("/b.oga"), PathBuf::from("/d.oga")]);
+    }
+}
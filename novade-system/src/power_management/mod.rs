@@ -130,6 +130,72 @@ impl LogindProxyHandler {
     }
 }
 
+/// Reads the battery from UPower's display device, the aggregate of all power sources.
+struct UPowerProxyHandler {
+    upower_proxy: Proxy<'static>,
+    display_device_proxy: Proxy<'static>,
+}
+
+impl UPowerProxyHandler {
+    const UPOWER_DEST: &'static str = "org.freedesktop.UPower";
+    const UPOWER_PATH: &'static str = "/org/freedesktop/UPower";
+    const UPOWER_IFACE: &'static str = "org.freedesktop.UPower";
+    const DISPLAY_DEVICE_PATH: &'static str = "/org/freedesktop/UPower/devices/DisplayDevice";
+    const DEVICE_IFACE: &'static str = "org.freedesktop.UPower.Device";
+
+    async fn new(connection: &ZbusConnection) -> SystemResult<Self> {
+        let upower_proxy = Proxy::new(connection, Self::UPOWER_DEST, Self::UPOWER_PATH, Self::UPOWER_IFACE)
+            .await
+            .map_err(|e| SystemError::new(SystemErrorKind::DBus, format!("Failed to create UPower proxy: {}", e)))?;
+        let display_device_proxy = Proxy::new(connection, Self::UPOWER_DEST, Self::DISPLAY_DEVICE_PATH, Self::DEVICE_IFACE)
+            .await
+            .map_err(|e| SystemError::new(SystemErrorKind::DBus, format!("Failed to create UPower display device proxy: {}", e)))?;
+        Ok(Self { upower_proxy, display_device_proxy })
+    }
+
+    async fn property<T>(proxy: &Proxy<'static>, name: &str) -> SystemResult<T>
+    where
+        T: TryFrom<zbus::zvariant::OwnedValue>,
+        T::Error: Into<zbus::Error>,
+    {
+        proxy
+            .get_property::<T>(name)
+            .await
+            .map_err(|e| SystemError::new(SystemErrorKind::DBus, format!("Failed to read UPower property {}: {}", name, e)))
+    }
+
+    async fn on_battery(&self) -> SystemResult<bool> {
+        Self::property(&self.upower_proxy, "OnBattery").await
+    }
+
+    async fn battery_info(&self) -> SystemResult<BatteryInfo> {
+        let present: bool = Self::property(&self.display_device_proxy, "IsPresent").await?;
+        let percentage: f64 = Self::property(&self.display_device_proxy, "Percentage").await?;
+        let state: u32 = Self::property(&self.display_device_proxy, "State").await?;
+        let time_to_empty: i64 = Self::property(&self.display_device_proxy, "TimeToEmpty").await?;
+        Ok(BatteryInfo {
+            present,
+            percentage,
+            state: battery_state_from_upower(state),
+            time_remaining: u64::try_from(time_to_empty).ok().filter(|secs| *secs > 0).map(Duration::from_secs),
+        })
+    }
+}
+
+/// Maps UPower's `State` property onto [`BatteryState`].
+fn battery_state_from_upower(state: u32) -> BatteryState {
+    match state {
+        1 | 5 => BatteryState::Charging,
+        2 | 3 | 6 => BatteryState::Discharging,
+        4 => BatteryState::Full,
+        _ => BatteryState::Unknown,
+    }
+}
+
+/// Charge at or below which a system on battery is in [`PowerState::LowBattery`].
+const LOW_BATTERY_PERCENTAGE: f64 = 15.0;
+/// Charge at or below which a system on battery is in [`PowerState::CriticalBattery`].
+const CRITICAL_BATTERY_PERCENTAGE: f64 = 5.0;
 
 /// Power state.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -256,15 +322,18 @@ pub trait PowerManager: Send + Sync {
 /// System power manager implementation.
 pub struct SystemPowerManager {
     logind_handler: Arc<LogindProxyHandler>,
+    upower_handler: UPowerProxyHandler,
 }
 
 impl SystemPowerManager {
     pub async fn new(
         dbus_connection: Arc<ZbusConnection>,
     ) -> SystemResult<Self> {
+        let upower_handler = UPowerProxyHandler::new(dbus_connection.as_ref()).await?;
         let logind_handler = Arc::new(LogindProxyHandler::new(dbus_connection).await?);
         Ok(SystemPowerManager {
             logind_handler,
+            upower_handler,
         })
     }
 
@@ -276,11 +345,19 @@ impl SystemPowerManager {
 #[async_trait]
 impl PowerManager for SystemPowerManager {
     async fn get_power_state(&self) -> SystemResult<PowerState> {
-        Err(SystemError::new(SystemErrorKind::NotSupported, "get_power_state not supported by logind backend".to_string()))
+        if !self.upower_handler.on_battery().await? {
+            return Ok(PowerState::AC);
+        }
+        let battery = self.upower_handler.battery_info().await?;
+        Ok(match battery.percentage {
+            p if p <= CRITICAL_BATTERY_PERCENTAGE => PowerState::CriticalBattery,
+            p if p <= LOW_BATTERY_PERCENTAGE => PowerState::LowBattery,
+            _ => PowerState::Battery,
+        })
     }
 
     async fn get_battery_info(&self) -> SystemResult<BatteryInfo> {
-        Err(SystemError::new(SystemErrorKind::NotSupported, "get_battery_info not supported by logind backend".to_string()))
+        self.upower_handler.battery_info().await
     }
 
     async fn perform_action(&self, action: PowerAction) -> SystemResult<()> {
@@ -345,10 +422,18 @@ mod tests {
         let can_logout = manager.is_action_supported(PowerAction::Logout).await;
         assert_eq!(can_logout, Ok(false));
 
-        let get_state_err = manager.get_power_state().await;
-        assert!(get_state_err.is_err());
-        if let Err(e) = get_state_err {
-            assert_eq!(e.kind(), SystemErrorKind::NotSupported);
+        // Without UPower on the bus this is a D-Bus error rather than a state.
+        match manager.get_power_state().await {
+            Ok(state) => tracing::info!("Power state reported: {:?}", state),
+            Err(e) => assert_eq!(e.kind(), SystemErrorKind::DBus),
         }
     }
+
+    #[test]
+    fn test_battery_state_from_upower() {
+        assert_eq!(battery_state_from_upower(1), BatteryState::Charging);
+        assert_eq!(battery_state_from_upower(2), BatteryState::Discharging);
+        assert_eq!(battery_state_from_upower(4), BatteryState::Full);
+        assert_eq!(battery_state_from_upower(0), BatteryState::Unknown);
+    }
 }
@@ -6,6 +6,8 @@
 pub mod wgpu_renderer;
 pub mod wgpu_texture; // Add this line
 pub mod vulkan_frame_renderer;
+#[cfg(feature = "renderer_software")]
+pub mod software;
 
 pub use wgpu_renderer::NovaWgpuRenderer;
 pub use wgpu_texture::WgpuRenderableTexture; // Add this line
@@ -14,3 +16,5 @@ pub use vulkan_frame_renderer::FrameRenderer;
 pub use vulkan_frame_renderer::VulkanTexture;
 pub use vulkan_frame_renderer::RenderElement;
 pub use vulkan_frame_renderer::VulkanError;
+#[cfg(feature = "renderer_software")]
+pub use software::{SoftwareRenderer, SoftwareTexture};
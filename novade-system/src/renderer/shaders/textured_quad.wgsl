@@ -34,7 +34,42 @@ var t_diffuse: texture_2d<f32>;
 @group(0) @binding(1)
 var s_diffuse: sampler;
 
+// Rounded corners and opacity of the drawn surface.
+struct SurfaceShape {
+    // Size of the texture in texels; the corner radius is in texels too.
+    size: vec2<f32>,
+    corner_radius: f32,
+    alpha: f32,
+};
+@group(2) @binding(0)
+var<uniform> shape: SurfaceShape;
+
+// Same as `corner_coverage` in the software renderer.
+fn shape_coverage(tex_coords: vec2<f32>) -> f32 {
+    let p = tex_coords * shape.size;
+    let radius = min(shape.corner_radius, min(shape.size.x, shape.size.y) / 2.0);
+    if (radius <= 0.0) {
+        return shape.alpha;
+    }
+    let d = max(max(vec2<f32>(radius) - p, p - (shape.size - vec2<f32>(radius))), vec2<f32>(0.0));
+    if (d.x == 0.0 || d.y == 0.0) {
+        return shape.alpha;
+    }
+    return clamp(radius - length(d) + 0.5, 0.0, 1.0) * shape.alpha;
+}
+
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    return textureSample(t_diffuse, s_diffuse, in.tex_coords);
+    return textureSample(t_diffuse, s_diffuse, in.tex_coords) * shape_coverage(in.tex_coords);
+}
+
+// Colour-managed variant: converts samples into the composition space with a 3D LUT.
+// `convert_premultiplied` comes from assets/shaders/color_lut.wgsl, appended to this source.
+@group(3) @binding(0)
+var t_color_lut: texture_3d<f32>;
+
+@fragment
+fn fs_main_color_managed(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = convert_premultiplied(t_color_lut, textureSample(t_diffuse, s_diffuse, in.tex_coords));
+    return color * shape_coverage(in.tex_coords);
 }
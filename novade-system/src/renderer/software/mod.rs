@@ -0,0 +1,659 @@
+// novade-system/src/renderer/software/mod.rs
+
+//! CPU renderer implementing [`FrameRenderer`].
+//!
+//! Renders into a [`SoftwareImage`] kept in system memory, so it works without any GPU driver.
+//! It is the fallback when no hardware renderer can be created and the reference renderer for
+//! pixel tests: the rasteriser is plain Rust, so its output is the same on every machine.
+//! Backends present a frame by copying [`SoftwareRenderer::framebuffer`] into a dumb buffer or
+//! window; [`SoftwareRenderer::read_pixels`] reads it back.
+//!
+//! The framebuffer persists between frames, so after the first frame the buffer age is 1 and
+//! only the damage of the last frame needs repainting.
+
+mod raster;
+
+pub use raster::{blur_backdrop, convert_premultiplied, draw_shadow, draw_texture, premultiply, Filter, SoftwareImage, TextureDraw};
+
+use std::any::Any;
+use std::sync::Arc;
+
+use novade_core::types::geometry::Rect as NovaRect;
+use smithay::backend::allocator::dmabuf::Dmabuf;
+use smithay::backend::renderer::utils::Fourcc;
+use smithay::reexports::wayland_server::protocol::{wl_buffer::WlBuffer, wl_shm};
+use smithay::utils::{Physical, Point, Rectangle, Size};
+use smithay::wayland::shm::with_buffer_contents;
+use uuid::Uuid;
+
+use crate::compositor::color::Lut3d;
+use crate::compositor::renderer_interface::abstraction::{
+    BufferContent, BufferFormat, ClientBuffer, FrameRenderer, RenderElement, RenderableTexture, RendererError, SurfaceId,
+};
+use raster::{pack, unpack, Affine};
+
+/// Reference white of SDR content in nits, used to normalise HDR luminance.
+const SDR_REFERENCE_WHITE_NITS: f32 = 203.0;
+
+// ANCHOR: SoftwareTexture
+/// A texture of the software renderer: an immutable image in system memory.
+#[derive(Debug, Clone)]
+pub struct SoftwareTexture {
+    id: Uuid,
+    image: Arc<SoftwareImage>,
+    format: Fourcc,
+}
+
+impl SoftwareTexture {
+    pub fn new(image: SoftwareImage, format: Fourcc) -> Self {
+        Self { id: Uuid::new_v4(), image: Arc::new(image), format }
+    }
+
+    pub fn image(&self) -> &SoftwareImage {
+        &self.image
+    }
+}
+
+impl RenderableTexture for SoftwareTexture {
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn bind(&self, _slot: u32) -> Result<(), RendererError> {
+        // Nothing to bind: the rasteriser reads the image directly.
+        Ok(())
+    }
+
+    fn width_px(&self) -> u32 {
+        self.image.width()
+    }
+
+    fn height_px(&self) -> u32 {
+        self.image.height()
+    }
+
+    fn format(&self) -> Option<Fourcc> {
+        Some(self.format)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+// ANCHOR_END: SoftwareTexture
+
+// ANCHOR: SoftwareRenderer
+pub struct SoftwareRenderer {
+    id: Uuid,
+    framebuffer: SoftwareImage,
+    clear_color: [f32; 4],
+    filter: Filter,
+    /// Damage for the next frame in output coordinates, `None` for the whole output.
+    damage: Option<Vec<NovaRect<f32>>>,
+    /// Whether the framebuffer holds the previously presented frame.
+    contents_valid: bool,
+    frame_in_progress: bool,
+    presented_frames: u64,
+}
+
+impl SoftwareRenderer {
+    /// Creates a renderer with an initial framebuffer of `size`, resized by `render_frame` to
+    /// match the output.
+    pub fn new(size: Size<i32, Physical>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            framebuffer: SoftwareImage::new(size.w.max(0) as u32, size.h.max(0) as u32),
+            clear_color: [0.0, 0.0, 0.0, 1.0],
+            filter: Filter::default(),
+            damage: None,
+            contents_valid: false,
+            frame_in_progress: false,
+            presented_frames: 0,
+        }
+    }
+
+    /// Colour the damaged parts are cleared to before drawing, straight (not premultiplied) RGBA.
+    pub fn set_clear_color(&mut self, color: [f32; 4]) {
+        self.clear_color = color;
+    }
+
+    /// Filter used when textures are scaled or rotated.
+    pub fn set_filter(&mut self, filter: Filter) {
+        self.filter = filter;
+    }
+
+    /// The last rendered frame.
+    pub fn framebuffer(&self) -> &SoftwareImage {
+        &self.framebuffer
+    }
+
+    pub fn presented_frames(&self) -> u64 {
+        self.presented_frames
+    }
+
+    fn import_shm(data: &[u8], width: u32, height: u32, stride: u32, format: wl_shm::Format) -> Result<SoftwareTexture, RendererError> {
+        let (opaque, fourcc) = match format {
+            wl_shm::Format::Argb8888 => (false, Fourcc::Argb8888),
+            wl_shm::Format::Xrgb8888 => (true, Fourcc::Xrgb8888),
+            other => return Err(RendererError::InvalidBufferType(format!("SHM format {:?} is not supported by the software renderer", other))),
+        };
+        Ok(SoftwareTexture::new(SoftwareImage::from_shm(data, width, height, stride, opaque)?, fourcc))
+    }
+
+    /// Converts a rectangle in output coordinates to framebuffer pixels. With `outward` partial
+    /// pixels are included (for damage); otherwise edges are rounded (for clipping).
+    fn to_pixels(rect: &NovaRect<f32>, origin: Point<i32, Physical>, scale: f64, outward: bool) -> Rectangle<i32, Physical> {
+        let scale = scale as f32;
+        let (x0, y0) = (rect.origin.x * scale - origin.x as f32, rect.origin.y * scale - origin.y as f32);
+        let (x1, y1) = (x0 + rect.size.width * scale, y0 + rect.size.height * scale);
+        let (x0, y0, x1, y1) = if outward {
+            (x0.floor(), y0.floor(), x1.ceil(), y1.ceil())
+        } else {
+            (x0.round(), y0.round(), x1.round(), y1.round())
+        };
+        Rectangle::from_loc_and_size(Point::from((x0 as i32, y0 as i32)), Size::from(((x1 - x0) as i32, (y1 - y0) as i32)))
+    }
+
+    /// Converts a rectangle in output coordinates to `[x, y, width, height]` in framebuffer
+    /// pixels, without rounding.
+    fn to_target_rect(rect: &NovaRect<f32>, origin: Point<i32, Physical>, scale: f32) -> [f32; 4] {
+        [rect.origin.x * scale - origin.x as f32, rect.origin.y * scale - origin.y as f32, rect.size.width * scale, rect.size.height * scale]
+    }
+
+    /// Limits `rects` to the damaged area of the frame.
+    fn clip_to_damage(rects: impl IntoIterator<Item = Rectangle<i32, Physical>>, damage: &[Rectangle<i32, Physical>]) -> Vec<Rectangle<i32, Physical>> {
+        rects.into_iter().flat_map(|r| damage.iter().filter_map(move |d| r.intersection(*d))).collect()
+    }
+
+    fn draw_texture_element(
+        &mut self,
+        texture: &dyn RenderableTexture,
+        to_target: Affine,
+        source: Option<&NovaRect<f32>>,
+        alpha: f32,
+        corner_radius: f32,
+        color_transform: Option<&Lut3d>,
+        clip: &[Rectangle<i32, Physical>],
+    ) {
+        let Some(texture) = texture.as_any().downcast_ref::<SoftwareTexture>() else {
+            tracing::warn!("Software renderer cannot draw texture {} created by another renderer.", texture.id());
+            return;
+        };
+        let (tw, th) = (texture.image().width() as f32, texture.image().height() as f32);
+        // Source rectangles are normalised to the texture size.
+        let (origin, size) = match source {
+            Some(src) => ((src.origin.x * tw, src.origin.y * th), (src.size.width * tw, src.size.height * th)),
+            None => ((0.0, 0.0), (tw, th)),
+        };
+        let draw = TextureDraw { to_target, size, source_origin: origin, alpha, corner_radius, filter: self.filter, clip, color_transform };
+        draw_texture(&mut self.framebuffer, texture.image(), &draw);
+    }
+}
+// ANCHOR_END: SoftwareRenderer
+
+impl FrameRenderer for SoftwareRenderer {
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn render_frame<'a>(
+        &mut self,
+        elements: impl IntoIterator<Item = RenderElement<'a>>,
+        output_geometry: Rectangle<i32, Physical>,
+        output_scale: f64,
+    ) -> Result<(), RendererError> {
+        let size = output_geometry.size;
+        if size.w <= 0 || size.h <= 0 {
+            return Err(RendererError::Generic(format!("Invalid output size {:?}", size)));
+        }
+        if self.framebuffer.size() != size {
+            self.framebuffer = SoftwareImage::new(size.w as u32, size.h as u32);
+            self.contents_valid = false;
+        }
+        let origin = output_geometry.loc;
+        let bounds = self.framebuffer.bounds();
+        let damage: Vec<Rectangle<i32, Physical>> = match self.damage.take() {
+            Some(rects) if self.contents_valid => rects.iter().filter_map(|r| Self::to_pixels(r, origin, output_scale, true).intersection(bounds)).collect(),
+            _ => vec![bounds],
+        };
+
+        let clear = pack(premultiply(self.clear_color));
+        for rect in &damage {
+            self.framebuffer.fill(*rect, clear);
+        }
+
+        let scale = output_scale as f32;
+        for element in elements {
+            match element {
+                RenderElement::SolidColor { color, geometry } => {
+                    let rect = geometry.to_physical_precise_round(output_scale);
+                    let rect = Rectangle::from_loc_and_size(rect.loc - origin, rect.size);
+                    for area in Self::clip_to_damage([rect], &damage) {
+                        self.framebuffer.blend_rect(area, premultiply(color));
+                    }
+                }
+                RenderElement::TextureNode(params) => {
+                    let m = params.transform.matrix;
+                    // World coordinates are scaled to physical pixels relative to the output.
+                    let to_target = [
+                        [m[0][0] * scale, m[0][1] * scale, m[0][2] * scale - origin.x as f32],
+                        [m[1][0] * scale, m[1][1] * scale, m[1][2] * scale - origin.y as f32],
+                    ];
+                    let visible = if params.visible_region.is_empty() { std::slice::from_ref(&params.clip_rect) } else { params.visible_region.as_slice() };
+                    let clip = Self::clip_to_damage(visible.iter().map(|r| Self::to_pixels(r, origin, output_scale, false)), &damage);
+                    self.draw_texture_element(params.texture.as_ref(), to_target, Some(&params.source_rect), params.alpha, params.corner_radius, params.color_transform.as_deref(), &clip);
+                }
+                RenderElement::Cursor { texture_arc, position_logical, hotspot_logical } => {
+                    let top_left = (position_logical - hotspot_logical).to_f64().to_physical(output_scale);
+                    let to_target = [[scale, 0.0, top_left.x as f32 - origin.x as f32], [0.0, scale, top_left.y as f32 - origin.y as f32]];
+                    self.draw_texture_element(texture_arc.as_ref(), to_target, None, 1.0, 0.0, None, &damage);
+                }
+                RenderElement::Shadow(params) => {
+                    let rect = Self::to_target_rect(&params.geometry, origin, scale);
+                    draw_shadow(&mut self.framebuffer, rect, params.corner_radius * scale, &params.style.scaled(scale), &damage);
+                }
+                RenderElement::BackdropBlur(params) => {
+                    let rect = Self::to_target_rect(&params.geometry, origin, scale);
+                    let region = if params.region.is_empty() { std::slice::from_ref(&params.geometry) } else { params.region.as_slice() };
+                    let clip = Self::clip_to_damage(region.iter().map(|r| Self::to_pixels(r, origin, output_scale, false)), &damage);
+                    blur_backdrop(&mut self.framebuffer, rect, params.corner_radius * scale, &params.style, &clip);
+                }
+                RenderElement::WaylandSurface { surface_wl, .. } => {
+                    // Surfaces reach this renderer as TextureNodes from the composition engine.
+                    tracing::debug!("Software renderer skips raw Wayland surface element {:?}.", surface_wl);
+                }
+            }
+        }
+        self.frame_in_progress = true;
+        Ok(())
+    }
+
+    fn submit_and_present_frame(&mut self) -> Result<(), RendererError> {
+        if !std::mem::take(&mut self.frame_in_progress) {
+            return Err(RendererError::BufferSwapFailed("No frame was rendered before presenting".to_string()));
+        }
+        self.contents_valid = true;
+        self.presented_frames += 1;
+        Ok(())
+    }
+
+    fn buffer_age(&self) -> usize {
+        usize::from(self.contents_valid)
+    }
+
+    fn set_damage_region(&mut self, damage: Option<&[NovaRect<f32>]>) {
+        self.damage = damage.map(|rects| rects.to_vec());
+    }
+
+    fn create_texture_from_shm(&mut self, buffer: &WlBuffer) -> Result<Box<dyn RenderableTexture>, RendererError> {
+        let texture = with_buffer_contents(buffer, |ptr, len, data| {
+            // SAFETY: smithay keeps the pool mapped and `len` bytes valid for the closure.
+            let bytes = unsafe { std::slice::from_raw_parts(ptr, len) };
+            let start = data.offset.max(0) as usize;
+            let pixels = bytes.get(start..).ok_or_else(|| RendererError::TextureUploadFailed("SHM buffer offset out of range".to_string()))?;
+            Self::import_shm(pixels, data.width as u32, data.height as u32, data.stride as u32, data.format)
+        })
+        .map_err(|e| RendererError::TextureUploadFailed(format!("Failed to access SHM buffer: {:?}", e)))??;
+        Ok(Box::new(texture))
+    }
+
+    fn create_texture_from_dmabuf(&mut self, _dmabuf: &Dmabuf) -> Result<Box<dyn RenderableTexture>, RendererError> {
+        Err(RendererError::DmabufImportFailed("The software renderer only imports SHM buffers".to_string()))
+    }
+
+    fn screen_size(&self) -> Size<i32, Physical> {
+        self.framebuffer.size()
+    }
+
+    fn read_pixels(&self, region: Rectangle<i32, Physical>) -> Result<Vec<u32>, RendererError> {
+        self.framebuffer.read_region(region)
+    }
+
+    fn upload_surface_texture(&mut self, _surface_id: SurfaceId, buffer: &ClientBuffer<'_>) -> Result<Box<dyn RenderableTexture>, RendererError> {
+        match &buffer.content {
+            BufferContent::Shm { data, width, height, stride, format, .. } => {
+                let format = match format {
+                    BufferFormat::Argb8888 => wl_shm::Format::Argb8888,
+                    BufferFormat::Xrgb8888 => wl_shm::Format::Xrgb8888,
+                };
+                Ok(Box::new(Self::import_shm(data, *width, *height, *stride, format)?))
+            }
+            BufferContent::Dmabuf { .. } => Err(RendererError::DmabufImportFailed("The software renderer only imports SHM buffers".to_string())),
+        }
+    }
+
+    fn apply_gamma_correction(&mut self, gamma_value: f32) -> Result<(), RendererError> {
+        if !(gamma_value.is_finite() && gamma_value > 0.0) {
+            return Err(RendererError::Generic(format!("Invalid gamma value {}", gamma_value)));
+        }
+        let exponent = 1.0 / gamma_value;
+        self.framebuffer.map_colors(|c| {
+            if c[3] <= 0.0 {
+                return c;
+            }
+            let a = c[3];
+            [(c[0] / a).powf(exponent) * a, (c[1] / a).powf(exponent) * a, (c[2] / a).powf(exponent) * a, a]
+        });
+        Ok(())
+    }
+
+    fn apply_hdr_to_sdr_tone_mapping(&mut self, max_luminance: f32, exposure: f32) -> Result<(), RendererError> {
+        // Extended Reinhard: the content's peak maps to SDR white.
+        let white = (max_luminance / SDR_REFERENCE_WHITE_NITS).max(1.0);
+        let white_sq = white * white;
+        let map = |c: f32| {
+            let c = c * exposure;
+            c * (1.0 + c / white_sq) / (1.0 + c)
+        };
+        self.framebuffer.map_colors(|c| {
+            if c[3] <= 0.0 {
+                return c;
+            }
+            let a = c[3];
+            [map(c[0] / a) * a, map(c[1] / a) * a, map(c[2] / a) * a, a]
+        });
+        Ok(())
+    }
+
+    fn apply_color_space_conversion(&mut self, lut: &Lut3d) -> Result<(), RendererError> {
+        self.framebuffer.map_colors(|c| convert_premultiplied(lut, c));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compositor::effects::{BlurStyle, ShadowStyle};
+    use crate::compositor::renderer_interface::abstraction::{BlurRenderParams, ShadowRenderParams, TextureRenderParams};
+    use crate::compositor::scene_graph::Transform;
+    use smithay::utils::Logical;
+    use std::path::PathBuf;
+
+    const RED: u32 = 0xffff0000;
+    const GREEN: u32 = 0xff00ff00;
+    const BLUE: u32 = 0xff0000ff;
+    const BLACK: u32 = 0xff000000;
+    const WHITE: u32 = 0xffffffff;
+
+    fn output(w: i32, h: i32) -> Rectangle<i32, Physical> {
+        Rectangle::from_loc_and_size(Point::from((0, 0)), Size::from((w, h)))
+    }
+
+    fn rect(x: f32, y: f32, w: f32, h: f32) -> NovaRect<f32> {
+        NovaRect::from_coords(x, y, w, h)
+    }
+
+    fn texture(width: u32, height: u32, pixels: &[u32]) -> SoftwareTexture {
+        let data: Vec<u8> = pixels.iter().flat_map(|p| p.to_le_bytes()).collect();
+        SoftwareRenderer::import_shm(&data, width, height, width * 4, wl_shm::Format::Argb8888).unwrap()
+    }
+
+    fn node(texture: SoftwareTexture, matrix: [[f32; 3]; 2], clip: NovaRect<f32>) -> RenderElement<'static> {
+        RenderElement::TextureNode(TextureRenderParams {
+            texture: Box::new(texture),
+            transform: Transform { matrix },
+            alpha: 1.0,
+            clip_rect: clip,
+            source_rect: rect(0.0, 0.0, 1.0, 1.0),
+            visible_region: Vec::new(),
+            corner_radius: 0.0,
+            color_transform: None,
+        })
+    }
+
+    fn translate(x: f32, y: f32) -> [[f32; 3]; 2] {
+        [[1.0, 0.0, x], [0.0, 1.0, y]]
+    }
+
+    fn solid(color: [f32; 4], x: i32, y: i32, w: i32, h: i32) -> RenderElement<'static> {
+        RenderElement::SolidColor { color, geometry: Rectangle::<i32, Logical>::from_loc_and_size((x, y), (w, h)) }
+    }
+
+    /// A window of solid `pixel` with rounded corners, one texel per world unit.
+    fn window(x: f32, y: f32, w: f32, h: f32, radius: f32, alpha: f32, pixel: u32) -> RenderElement<'static> {
+        RenderElement::TextureNode(TextureRenderParams {
+            texture: Box::new(SoftwareTexture::new(SoftwareImage::filled(w as u32, h as u32, pixel), Fourcc::Argb8888)),
+            transform: Transform { matrix: translate(x, y) },
+            alpha,
+            clip_rect: rect(x, y, w, h),
+            source_rect: rect(0.0, 0.0, 1.0, 1.0),
+            visible_region: Vec::new(),
+            corner_radius: radius,
+            color_transform: None,
+        })
+    }
+
+    fn shadow(geometry: NovaRect<f32>, corner_radius: f32, style: ShadowStyle) -> RenderElement<'static> {
+        RenderElement::Shadow(ShadowRenderParams { geometry, corner_radius, style })
+    }
+
+    fn blur(geometry: NovaRect<f32>, corner_radius: f32, region: Vec<NovaRect<f32>>, style: BlurStyle) -> RenderElement<'static> {
+        RenderElement::BackdropBlur(BlurRenderParams { geometry, corner_radius, region, style })
+    }
+
+    /// Vertical stripes in four colours crossed by a yellow bar, so blurs have edges in both
+    /// directions.
+    fn stripes(w: i32, h: i32) -> Vec<RenderElement<'static>> {
+        let colors = [[0.9, 0.1, 0.1, 1.0], [0.1, 0.3, 0.9, 1.0], [1.0, 1.0, 1.0, 1.0], [0.1, 0.7, 0.2, 1.0]];
+        let mut elements: Vec<_> = (0..(w + 7) / 8).map(|i| solid(colors[i as usize % 4], i * 8, 0, 8, h)).collect();
+        elements.push(solid([1.0, 0.9, 0.0, 1.0], 0, h / 2 - 3, w, 6));
+        elements
+    }
+
+    /// Compares the framebuffer with `tests/golden/effects/<name>.pam`, allowing for rounding
+    /// differences between platforms. Set `NOVADE_UPDATE_GOLDEN=1` to rewrite the file.
+    fn assert_golden(renderer: &SoftwareRenderer, name: &str) {
+        let fb = renderer.framebuffer();
+        let path: PathBuf = [env!("CARGO_MANIFEST_DIR"), "tests", "golden", "effects", &format!("{}.pam", name)].iter().collect();
+        let rgba: Vec<u8> = fb.pixels().iter().flat_map(|p| [(p >> 16) as u8, (p >> 8) as u8, *p as u8, (p >> 24) as u8]).collect();
+        if std::env::var_os("NOVADE_UPDATE_GOLDEN").is_some() {
+            let mut pam = format!("P7\nWIDTH {}\nHEIGHT {}\nDEPTH 4\nMAXVAL 255\nTUPLTYPE RGB_ALPHA\nENDHDR\n", fb.width(), fb.height()).into_bytes();
+            pam.extend_from_slice(&rgba);
+            std::fs::write(&path, pam).unwrap();
+            return;
+        }
+        let golden = std::fs::read(&path).unwrap_or_else(|e| panic!("cannot read {}: {}", path.display(), e));
+        let header_end = golden.windows(7).position(|w| w == b"ENDHDR\n").expect("PAM header") + 7;
+        let header = String::from_utf8_lossy(&golden[..header_end]);
+        assert!(header.contains(&format!("WIDTH {}\n", fb.width())) && header.contains(&format!("HEIGHT {}\n", fb.height())), "{}: size differs", name);
+        let expected = &golden[header_end..];
+        assert_eq!(expected.len(), rgba.len(), "{}: size differs", name);
+        for (i, (a, b)) in rgba.iter().zip(expected).enumerate() {
+            let pixel = i / 4;
+            assert!(a.abs_diff(*b) <= 2, "{}: pixel ({}, {}) channel {} is {}, expected {}", name, pixel as u32 % fb.width(), pixel as u32 / fb.width(), i % 4, a, b);
+        }
+    }
+
+    fn render(renderer: &mut SoftwareRenderer, elements: Vec<RenderElement<'static>>, w: i32, h: i32) {
+        renderer.render_frame(elements, output(w, h), 1.0).unwrap();
+        renderer.submit_and_present_frame().unwrap();
+    }
+
+    #[test]
+    fn test_solid_fill_and_alpha_blending() {
+        let mut renderer = SoftwareRenderer::new(Size::from((4, 4)));
+        renderer.set_clear_color([0.0, 0.0, 1.0, 1.0]);
+        render(&mut renderer, vec![solid([1.0, 0.0, 0.0, 1.0], 0, 0, 2, 4), solid([1.0, 0.0, 0.0, 0.5], 2, 0, 2, 4)], 4, 4);
+        let fb = renderer.framebuffer();
+        assert_eq!(fb.pixel(0, 0), RED);
+        assert_eq!(fb.pixel(3, 3), 0xff800080);
+    }
+
+    #[test]
+    fn test_shm_texture_upload_and_translation() {
+        let mut renderer = SoftwareRenderer::new(Size::from((4, 4)));
+        // XRGB buffers are opaque whatever their padding byte says.
+        let data: Vec<u8> = [0x00ff0000u32, 0x0000ff00].iter().flat_map(|p| p.to_le_bytes()).collect();
+        let tex = SoftwareRenderer::import_shm(&data, 2, 1, 8, wl_shm::Format::Xrgb8888).unwrap();
+        assert_eq!(tex.image().pixels(), &[RED, GREEN]);
+        render(&mut renderer, vec![node(tex, translate(1.0, 2.0), rect(0.0, 0.0, 4.0, 4.0))], 4, 4);
+        assert_eq!(renderer.read_pixels(output(4, 4)).unwrap()[2 * 4..3 * 4], [BLACK, RED, GREEN, BLACK]);
+        assert!(SoftwareRenderer::import_shm(&data, 2, 2, 8, wl_shm::Format::Xrgb8888).is_err());
+    }
+
+    #[test]
+    fn test_rotation_and_clip() {
+        let mut renderer = SoftwareRenderer::new(Size::from((2, 2)));
+        renderer.set_filter(Filter::Nearest);
+        // 90° clockwise: texel (x, y) lands at (1 - y, x).
+        let rotate = [[0.0, -1.0, 1.0], [1.0, 0.0, 0.0]];
+        render(&mut renderer, vec![node(texture(2, 1, &[RED, GREEN]), rotate, rect(0.0, 0.0, 1.0, 1.0))], 2, 2);
+        let fb = renderer.framebuffer();
+        assert_eq!(fb.pixel(0, 0), RED);
+        // Clipped away.
+        assert_eq!(fb.pixel(0, 1), BLACK);
+    }
+
+    #[test]
+    fn test_scaling_filters() {
+        let scale2 = [[2.0, 0.0, 0.0], [0.0, 2.0, 0.0]];
+        let mut renderer = SoftwareRenderer::new(Size::from((4, 2)));
+        renderer.set_filter(Filter::Nearest);
+        render(&mut renderer, vec![node(texture(2, 1, &[RED, BLUE]), scale2, rect(0.0, 0.0, 4.0, 2.0))], 4, 2);
+        assert_eq!(renderer.framebuffer().pixels()[..4], [RED, RED, BLUE, BLUE]);
+
+        renderer.set_filter(Filter::Bilinear);
+        render(&mut renderer, vec![node(texture(2, 1, &[RED, BLUE]), scale2, rect(0.0, 0.0, 4.0, 2.0))], 4, 2);
+        // Edges clamp to the outer texels, the middle pixels mix 3:1.
+        assert_eq!(renderer.framebuffer().pixels()[..4], [RED, 0xffbf0040, 0xff4000bf, BLUE]);
+    }
+
+    #[test]
+    fn test_rounded_corners_and_alpha() {
+        let mut renderer = SoftwareRenderer::new(Size::from((16, 16)));
+        let params = TextureRenderParams {
+            texture: Box::new(texture(16, 16, &[RED; 256])),
+            transform: Transform { matrix: translate(0.0, 0.0) },
+            alpha: 1.0,
+            clip_rect: rect(0.0, 0.0, 16.0, 16.0),
+            source_rect: rect(0.0, 0.0, 1.0, 1.0),
+            visible_region: Vec::new(),
+            corner_radius: 6.0,
+            color_transform: None,
+        };
+        render(&mut renderer, vec![RenderElement::TextureNode(params)], 16, 16);
+        let fb = renderer.framebuffer();
+        assert_eq!(fb.pixel(0, 0), BLACK);
+        assert_eq!(fb.pixel(15, 15), BLACK);
+        assert_eq!(fb.pixel(8, 8), RED);
+        assert_eq!(fb.pixel(8, 0), RED);
+        // Anti-aliased edge pixel on the arc.
+        let edge = unpack(fb.pixel(1, 2));
+        assert!(edge[0] > 0.0 && edge[0] < 1.0, "{:?}", edge);
+
+        let params = TextureRenderParams {
+            texture: Box::new(texture(1, 1, &[GREEN])),
+            transform: Transform { matrix: [[16.0, 0.0, 0.0], [0.0, 16.0, 0.0]] },
+            alpha: 0.5,
+            clip_rect: rect(0.0, 0.0, 16.0, 16.0),
+            source_rect: rect(0.0, 0.0, 1.0, 1.0),
+            visible_region: Vec::new(),
+            corner_radius: 0.0,
+            color_transform: None,
+        };
+        render(&mut renderer, vec![RenderElement::TextureNode(params)], 16, 16);
+        assert_eq!(renderer.framebuffer().pixel(8, 8), 0xff008000);
+    }
+
+    #[test]
+    fn test_color_transforms() {
+        let swap = Arc::new(Lut3d::bake(2, |[r, g, b]| [b, g, r]));
+        let mut renderer = SoftwareRenderer::new(Size::from((4, 4)));
+        let params = TextureRenderParams {
+            texture: Box::new(texture(1, 1, &[RED])),
+            transform: Transform { matrix: [[4.0, 0.0, 0.0], [0.0, 4.0, 0.0]] },
+            alpha: 0.5,
+            clip_rect: rect(0.0, 0.0, 4.0, 4.0),
+            source_rect: rect(0.0, 0.0, 1.0, 1.0),
+            visible_region: Vec::new(),
+            corner_radius: 0.0,
+            color_transform: Some(swap.clone()),
+        };
+        // The surface transform applies to the sample before it is blended.
+        render(&mut renderer, vec![RenderElement::TextureNode(params)], 4, 4);
+        assert_eq!(renderer.framebuffer().pixel(1, 1), 0xff000080);
+        // The output transform applies to the composited frame.
+        renderer.apply_color_space_conversion(&swap).unwrap();
+        assert_eq!(renderer.framebuffer().pixel(1, 1), 0xff800000);
+    }
+
+    #[test]
+    fn test_damage_limited_rendering() {
+        let mut renderer = SoftwareRenderer::new(Size::from((4, 4)));
+        assert_eq!(renderer.buffer_age(), 0);
+        render(&mut renderer, vec![solid([1.0, 0.0, 0.0, 1.0], 0, 0, 4, 4)], 4, 4);
+        assert_eq!(renderer.buffer_age(), 1);
+
+        // Only the damaged column is repainted; the rest keeps the previous frame.
+        renderer.set_damage_region(Some(&[rect(1.0, 0.0, 1.0, 4.0)]));
+        render(&mut renderer, vec![solid([0.0, 1.0, 0.0, 1.0], 0, 0, 4, 4)], 4, 4);
+        assert_eq!(renderer.framebuffer().pixels()[..4], [RED, GREEN, RED, RED]);
+
+        // A resize invalidates the contents and forces a full repaint.
+        render(&mut renderer, vec![solid([0.0, 1.0, 0.0, 1.0], 0, 0, 8, 8)], 8, 8);
+        assert!(renderer.framebuffer().pixels().iter().all(|p| *p == GREEN));
+        assert_eq!(renderer.presented_frames(), 3);
+    }
+
+    #[test]
+    fn test_readback_and_gamma() {
+        let mut renderer = SoftwareRenderer::new(Size::from((2, 2)));
+        renderer.set_clear_color([0.25, 0.25, 0.25, 1.0]);
+        render(&mut renderer, Vec::new(), 2, 2);
+        renderer.apply_gamma_correction(2.0).unwrap();
+        assert_eq!(renderer.read_pixels(Rectangle::from_loc_and_size((1, 1), (1, 1))).unwrap(), vec![0xff808080]);
+        assert!(renderer.read_pixels(Rectangle::from_loc_and_size((1, 1), (2, 1))).is_err());
+        assert!(renderer.apply_gamma_correction(0.0).is_err());
+        assert!(renderer.submit_and_present_frame().is_err());
+    }
+
+    #[test]
+    fn test_shadow_golden() {
+        let mut renderer = SoftwareRenderer::new(Size::from((64, 48)));
+        renderer.set_clear_color([0.9, 0.9, 0.9, 1.0]);
+        let style = ShadowStyle { offset: (0.0, 4.0), blur_radius: 8.0, spread: 0.0, color: [0.0, 0.0, 0.0, 0.6] };
+        render(&mut renderer, vec![shadow(rect(16.0, 10.0, 32.0, 20.0), 6.0, style), window(16.0, 10.0, 32.0, 20.0, 6.0, 1.0, WHITE)], 64, 48);
+        assert_golden(&renderer, "shadow");
+        // Darkest just below the window, nothing far away.
+        let fb = renderer.framebuffer();
+        assert!(unpack(fb.pixel(32, 31))[0] < unpack(fb.pixel(32, 40))[0]);
+        assert_eq!(fb.pixel(2, 2), 0xffe6e6e6);
+    }
+
+    #[test]
+    fn test_backdrop_blur_golden() {
+        let style = BlurStyle { passes: 2, offset: 1.5 };
+        let mut renderer = SoftwareRenderer::new(Size::from((64, 48)));
+        let mut elements = stripes(64, 48);
+        elements.push(blur(rect(8.0, 8.0, 48.0, 32.0), 8.0, Vec::new(), style));
+        render(&mut renderer, elements, 64, 48);
+        assert_golden(&renderer, "backdrop_blur");
+
+        // A region limits the blur; the rest of the surface shows the sharp stripes.
+        let mut elements = stripes(64, 48);
+        elements.push(blur(rect(8.0, 8.0, 48.0, 32.0), 8.0, vec![rect(8.0, 8.0, 24.0, 32.0)], style));
+        let mut sharp = SoftwareRenderer::new(Size::from((64, 48)));
+        render(&mut sharp, stripes(64, 48), 64, 48);
+        render(&mut renderer, elements, 64, 48);
+        let (fb, sharp) = (renderer.framebuffer(), sharp.framebuffer());
+        assert_eq!(fb.pixel(40, 20), sharp.pixel(40, 20));
+        assert_ne!(fb.pixel(20, 20), sharp.pixel(20, 20));
+        // Rounded corners of the blurred area.
+        assert_eq!(fb.pixel(8, 8), sharp.pixel(8, 8));
+    }
+
+    #[test]
+    fn test_frosted_panel_golden() {
+        // Shadow, blur and a translucent rounded window at output scale 2.
+        let mut renderer = SoftwareRenderer::new(Size::from((64, 48)));
+        let geometry = rect(6.0, 4.0, 20.0, 14.0);
+        let mut elements = stripes(32, 24);
+        elements.push(shadow(geometry, 4.0, ShadowStyle { offset: (0.0, 2.0), blur_radius: 4.0, spread: 1.0, color: [0.0, 0.0, 0.0, 0.5] }));
+        elements.push(blur(geometry, 4.0, Vec::new(), BlurStyle { passes: 3, offset: 1.0 }));
+        elements.push(window(6.0, 4.0, 20.0, 14.0, 4.0, 0.35, WHITE));
+        renderer.render_frame(elements, output(64, 48), 2.0).unwrap();
+        renderer.submit_and_present_frame().unwrap();
+        assert_golden(&renderer, "frosted_panel");
+    }
+}
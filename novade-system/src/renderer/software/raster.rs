@@ -0,0 +1,433 @@
+// novade-system/src/renderer/software/raster.rs
+
+//! Pixel storage and the rasterisation routines of the software renderer.
+//!
+//! Pixels are premultiplied ARGB packed as `0xAARRGGBB`, the in-memory layout of
+//! `WL_SHM_FORMAT_ARGB8888` on little-endian machines, so SHM buffers are copied row by row.
+//! Blending happens in `f32` and is rounded once per pixel, which keeps results deterministic
+//! across platforms.
+
+use smithay::utils::{Physical, Point, Rectangle, Size};
+
+use crate::compositor::color::Lut3d;
+use crate::compositor::effects::blur::{level_sizes, BlurStyle};
+use crate::compositor::effects::shape::{rounded_rect_coverage, shadow_bounds, shadow_coverage};
+use crate::compositor::effects::ShadowStyle;
+use crate::compositor::renderer_interface::abstraction::RendererError;
+
+/// Row-major 2x3 affine matrix, same layout as the scene graph's `Transform`.
+pub type Affine = [[f32; 3]; 2];
+
+/// Premultiplied colour with components in `0.0..=1.0`, ordered R, G, B, A.
+pub type Color = [f32; 4];
+
+/// Texture sampling filter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Filter {
+    Nearest,
+    #[default]
+    Bilinear,
+}
+
+pub fn pack(color: Color) -> u32 {
+    let channel = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u32;
+    (channel(color[3]) << 24) | (channel(color[0]) << 16) | (channel(color[1]) << 8) | channel(color[2])
+}
+
+pub fn unpack(pixel: u32) -> Color {
+    let channel = |shift: u32| ((pixel >> shift) & 0xff) as f32 / 255.0;
+    [channel(16), channel(8), channel(0), channel(24)]
+}
+
+/// Premultiplies a straight-alpha RGBA colour.
+pub fn premultiply(color: [f32; 4]) -> Color {
+    [color[0] * color[3], color[1] * color[3], color[2] * color[3], color[3]]
+}
+
+// ANCHOR: SoftwareImage
+/// A CPU-side image of premultiplied ARGB8888 pixels.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SoftwareImage {
+    width: u32,
+    height: u32,
+    pixels: Vec<u32>,
+}
+
+impl SoftwareImage {
+    /// A fully transparent image.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self::filled(width, height, 0)
+    }
+
+    pub fn filled(width: u32, height: u32, pixel: u32) -> Self {
+        Self { width, height, pixels: vec![pixel; width as usize * height as usize] }
+    }
+
+    /// Wraps premultiplied pixels, row by row; there must be `width * height` of them.
+    pub fn from_pixels(width: u32, height: u32, pixels: Vec<u32>) -> Self {
+        assert_eq!(pixels.len(), width as usize * height as usize, "pixel count does not match the image size");
+        Self { width, height, pixels }
+    }
+
+    /// Copies an ARGB8888 or, when `opaque`, XRGB8888 SHM buffer.
+    pub fn from_shm(data: &[u8], width: u32, height: u32, stride: u32, opaque: bool) -> Result<Self, RendererError> {
+        let row_bytes = width as usize * 4;
+        if (stride as usize) < row_bytes || data.len() < stride as usize * height.saturating_sub(1) as usize + row_bytes {
+            return Err(RendererError::TextureUploadFailed(format!(
+                "SHM buffer of {} bytes too small for {}x{} with stride {}",
+                data.len(),
+                width,
+                height,
+                stride
+            )));
+        }
+        let alpha_mask = if opaque { 0xff00_0000 } else { 0 };
+        let mut pixels = Vec::with_capacity(width as usize * height as usize);
+        for row in data.chunks(stride as usize).take(height as usize) {
+            pixels.extend(row[..row_bytes].chunks_exact(4).map(|p| u32::from_le_bytes([p[0], p[1], p[2], p[3]]) | alpha_mask));
+        }
+        Ok(Self { width, height, pixels })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn size(&self) -> Size<i32, Physical> {
+        Size::from((self.width as i32, self.height as i32))
+    }
+
+    pub fn bounds(&self) -> Rectangle<i32, Physical> {
+        Rectangle::from_loc_and_size(Point::from((0, 0)), self.size())
+    }
+
+    pub fn pixels(&self) -> &[u32] {
+        &self.pixels
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> u32 {
+        self.pixels[(y * self.width + x) as usize]
+    }
+
+    /// Pixels of `region`, row by row. `region` must lie inside the image.
+    pub fn read_region(&self, region: Rectangle<i32, Physical>) -> Result<Vec<u32>, RendererError> {
+        if region.loc.x < 0 || region.loc.y < 0 || region.size.w < 0 || region.size.h < 0 || !self.bounds().contains_rect(region) {
+            return Err(RendererError::Generic(format!("Readback region {:?} outside of {}x{} image", region, self.width, self.height)));
+        }
+        let mut out = Vec::with_capacity((region.size.w * region.size.h) as usize);
+        for y in region.loc.y..region.loc.y + region.size.h {
+            let start = (y as u32 * self.width + region.loc.x as u32) as usize;
+            out.extend_from_slice(&self.pixels[start..start + region.size.w as usize]);
+        }
+        Ok(out)
+    }
+
+    /// The image as little-endian ARGB8888 bytes, e.g. for writing into an SHM buffer.
+    pub fn to_argb8888_bytes(&self) -> Vec<u8> {
+        self.pixels.iter().flat_map(|p| p.to_le_bytes()).collect()
+    }
+
+    /// Replaces every pixel with `f` applied to its colour.
+    pub fn map_colors(&mut self, mut f: impl FnMut(Color) -> Color) {
+        for pixel in &mut self.pixels {
+            *pixel = pack(f(unpack(*pixel)));
+        }
+    }
+
+    /// Overwrites `rect` (clipped to the image) with `pixel`.
+    pub fn fill(&mut self, rect: Rectangle<i32, Physical>, pixel: u32) {
+        let Some(rect) = rect.intersection(self.bounds()) else { return };
+        for y in rect.loc.y..rect.loc.y + rect.size.h {
+            let start = (y as u32 * self.width + rect.loc.x as u32) as usize;
+            self.pixels[start..start + rect.size.w as usize].fill(pixel);
+        }
+    }
+
+    /// Blends `color` over `rect` (clipped to the image).
+    pub fn blend_rect(&mut self, rect: Rectangle<i32, Physical>, color: Color) {
+        let Some(rect) = rect.intersection(self.bounds()) else { return };
+        for y in rect.loc.y..rect.loc.y + rect.size.h {
+            for x in rect.loc.x..rect.loc.x + rect.size.w {
+                self.blend(x as u32, y as u32, color);
+            }
+        }
+    }
+
+    /// Source-over blends a premultiplied colour onto one pixel.
+    fn blend(&mut self, x: u32, y: u32, src: Color) {
+        if src[3] <= 0.0 {
+            return;
+        }
+        let index = (y * self.width + x) as usize;
+        if src[3] >= 1.0 {
+            self.pixels[index] = pack(src);
+            return;
+        }
+        let dst = unpack(self.pixels[index]);
+        let inv = 1.0 - src[3];
+        self.pixels[index] = pack([src[0] + dst[0] * inv, src[1] + dst[1] * inv, src[2] + dst[2] * inv, src[3] + dst[3] * inv]);
+    }
+
+    fn texel(&self, x: i32, y: i32) -> Color {
+        unpack(self.pixels[(y as u32 * self.width + x as u32) as usize])
+    }
+}
+// ANCHOR_END: SoftwareImage
+
+/// How a texture region is drawn onto an image.
+#[derive(Debug, Clone)]
+pub struct TextureDraw<'a> {
+    /// Maps quad-local coordinates, `(0, 0)..size`, to target pixels.
+    pub to_target: Affine,
+    /// Size of the sampled region in texels; also the quad's local size.
+    pub size: (f32, f32),
+    /// Texel coordinates of the sampled region's top-left corner.
+    pub source_origin: (f32, f32),
+    pub alpha: f32,
+    /// Radius of the quad's rounded corners in quad-local units; 0 for square corners.
+    pub corner_radius: f32,
+    pub filter: Filter,
+    /// Disjoint target rectangles drawing is limited to.
+    pub clip: &'a [Rectangle<i32, Physical>],
+    /// Colour conversion applied to each unpremultiplied sample.
+    pub color_transform: Option<&'a Lut3d>,
+}
+
+fn apply(m: &Affine, x: f32, y: f32) -> (f32, f32) {
+    (m[0][0] * x + m[0][1] * y + m[0][2], m[1][0] * x + m[1][1] * y + m[1][2])
+}
+
+fn invert(m: &Affine) -> Option<Affine> {
+    let det = m[0][0] * m[1][1] - m[0][1] * m[1][0];
+    if det.abs() < f32::EPSILON {
+        return None;
+    }
+    let (a, b, c, d) = (m[1][1] / det, -m[0][1] / det, -m[1][0] / det, m[0][0] / det);
+    Some([[a, b, -(a * m[0][2] + b * m[1][2])], [c, d, -(c * m[0][2] + d * m[1][2])]])
+}
+
+/// Maps a premultiplied colour through `lut`, which works on unpremultiplied values.
+pub fn convert_premultiplied(lut: &Lut3d, color: Color) -> Color {
+    let a = color[3];
+    if a <= 0.0 {
+        return color;
+    }
+    let [r, g, b] = lut.sample([color[0] / a, color[1] / a, color[2] / a]);
+    [r * a, g * a, b * a, a]
+}
+
+/// Coverage of a rounded-rectangle pixel at quad-local `(x, y)`, anti-aliased over one unit.
+fn corner_coverage(x: f32, y: f32, (w, h): (f32, f32), radius: f32) -> f32 {
+    let radius = radius.min(w / 2.0).min(h / 2.0);
+    if radius <= 0.0 {
+        return 1.0;
+    }
+    let dx = (radius - x).max(x - (w - radius)).max(0.0);
+    let dy = (radius - y).max(y - (h - radius)).max(0.0);
+    if dx == 0.0 || dy == 0.0 {
+        return 1.0;
+    }
+    (radius - (dx * dx + dy * dy).sqrt() + 0.5).clamp(0.0, 1.0)
+}
+
+fn lerp(a: Color, b: Color, t: f32) -> Color {
+    [a[0] + (b[0] - a[0]) * t, a[1] + (b[1] - a[1]) * t, a[2] + (b[2] - a[2]) * t, a[3] + (b[3] - a[3]) * t]
+}
+
+/// Samples `texture` at texel coordinates `(tx, ty)`, clamped to the region `min..max`.
+fn sample(texture: &SoftwareImage, tx: f32, ty: f32, min: (i32, i32), max: (i32, i32), filter: Filter) -> Color {
+    let clamp_x = |x: i32| x.clamp(min.0, max.0);
+    let clamp_y = |y: i32| y.clamp(min.1, max.1);
+    match filter {
+        Filter::Nearest => texture.texel(clamp_x(tx.floor() as i32), clamp_y(ty.floor() as i32)),
+        Filter::Bilinear => {
+            let (u, v) = (tx - 0.5, ty - 0.5);
+            let (x0, y0) = (u.floor(), v.floor());
+            let (fx, fy) = (u - x0, v - y0);
+            let (x0, y0) = (x0 as i32, y0 as i32);
+            let top = lerp(texture.texel(clamp_x(x0), clamp_y(y0)), texture.texel(clamp_x(x0 + 1), clamp_y(y0)), fx);
+            let bottom = lerp(texture.texel(clamp_x(x0), clamp_y(y0 + 1)), texture.texel(clamp_x(x0 + 1), clamp_y(y0 + 1)), fx);
+            lerp(top, bottom, fy)
+        }
+    }
+}
+
+// ANCHOR: DrawTexture
+/// Draws a region of `texture` onto `target`, sampling at pixel centres.
+pub fn draw_texture(target: &mut SoftwareImage, texture: &SoftwareImage, draw: &TextureDraw<'_>) {
+    let (w, h) = draw.size;
+    if w <= 0.0 || h <= 0.0 || draw.alpha <= 0.0 || texture.width == 0 || texture.height == 0 {
+        return;
+    }
+    let Some(inverse) = invert(&draw.to_target) else { return };
+
+    // Pixel bounds of the transformed quad.
+    let corners = [(0.0, 0.0), (w, 0.0), (0.0, h), (w, h)].map(|(x, y)| apply(&draw.to_target, x, y));
+    let min_x = corners.iter().map(|c| c.0).fold(f32::INFINITY, f32::min).floor() as i32;
+    let min_y = corners.iter().map(|c| c.1).fold(f32::INFINITY, f32::min).floor() as i32;
+    let max_x = corners.iter().map(|c| c.0).fold(f32::NEG_INFINITY, f32::max).ceil() as i32;
+    let max_y = corners.iter().map(|c| c.1).fold(f32::NEG_INFINITY, f32::max).ceil() as i32;
+    let Some(bounds) = Rectangle::from_loc_and_size(Point::from((min_x, min_y)), Size::from((max_x - min_x, max_y - min_y))).intersection(target.bounds()) else {
+        return;
+    };
+
+    let region_min = (draw.source_origin.0.floor() as i32, draw.source_origin.1.floor() as i32);
+    let region_max = (
+        ((draw.source_origin.0 + w).ceil() as i32 - 1).min(texture.width as i32 - 1),
+        ((draw.source_origin.1 + h).ceil() as i32 - 1).min(texture.height as i32 - 1),
+    );
+    let region_min = (region_min.0.clamp(0, region_max.0), region_min.1.clamp(0, region_max.1));
+
+    for clip in draw.clip {
+        let Some(area) = clip.intersection(bounds) else { continue };
+        for y in area.loc.y..area.loc.y + area.size.h {
+            for x in area.loc.x..area.loc.x + area.size.w {
+                let (lx, ly) = apply(&inverse, x as f32 + 0.5, y as f32 + 0.5);
+                if lx < 0.0 || ly < 0.0 || lx >= w || ly >= h {
+                    continue;
+                }
+                let coverage = corner_coverage(lx, ly, draw.size, draw.corner_radius) * draw.alpha;
+                if coverage <= 0.0 {
+                    continue;
+                }
+                let mut color = sample(texture, draw.source_origin.0 + lx, draw.source_origin.1 + ly, region_min, region_max, draw.filter);
+                if let Some(lut) = draw.color_transform {
+                    color = convert_premultiplied(lut, color);
+                }
+                target.blend(x as u32, y as u32, color.map(|c| c * coverage));
+            }
+        }
+    }
+}
+// ANCHOR_END: DrawTexture
+
+/// Smallest pixel rectangle containing `[x, y, width, height]`.
+fn pixel_bounds([x, y, w, h]: [f32; 4]) -> Rectangle<i32, Physical> {
+    let (x0, y0, x1, y1) = (x.floor() as i32, y.floor() as i32, (x + w).ceil() as i32, (y + h).ceil() as i32);
+    Rectangle::from_loc_and_size(Point::from((x0, y0)), Size::from((x1 - x0, y1 - y0)))
+}
+
+// ANCHOR: DrawShadow
+/// Draws the shadow cast by `rect`, in target pixels with corners rounded by `radius`, within
+/// `clip`. The style's lengths are in target pixels too.
+pub fn draw_shadow(target: &mut SoftwareImage, rect: [f32; 4], radius: f32, style: &ShadowStyle, clip: &[Rectangle<i32, Physical>]) {
+    let Some(bounds) = shadow_bounds(rect, radius, style).and_then(|b| pixel_bounds(b).intersection(target.bounds())) else {
+        return;
+    };
+    let color = premultiply(style.color);
+    for clip in clip {
+        let Some(area) = clip.intersection(bounds) else { continue };
+        for y in area.loc.y..area.loc.y + area.size.h {
+            for x in area.loc.x..area.loc.x + area.size.w {
+                let coverage = shadow_coverage(x as f32 + 0.5, y as f32 + 0.5, rect, radius, style);
+                target.blend(x as u32, y as u32, color.map(|c| c * coverage));
+            }
+        }
+    }
+}
+// ANCHOR_END: DrawShadow
+
+/// One level of the blur pyramid in unquantised premultiplied colour.
+struct Level {
+    width: usize,
+    height: usize,
+    texels: Vec<Color>,
+}
+
+impl Level {
+    fn from_image(image: &SoftwareImage) -> Self {
+        Self { width: image.width as usize, height: image.height as usize, texels: image.pixels.iter().map(|p| unpack(*p)).collect() }
+    }
+
+    /// Builds a `width` x `height` level by evaluating `f` at each texel centre, given in
+    /// texels of `source`.
+    fn resample(source: &Level, width: usize, height: usize, f: impl Fn(f32, f32) -> Color) -> Self {
+        let (sx, sy) = (source.width as f32 / width as f32, source.height as f32 / height as f32);
+        let texels = (0..height).flat_map(|y| (0..width).map(move |x| (x, y))).map(|(x, y)| f((x as f32 + 0.5) * sx, (y as f32 + 0.5) * sy)).collect();
+        Self { width, height, texels }
+    }
+
+    fn texel(&self, x: i32, y: i32) -> Color {
+        let x = x.clamp(0, self.width as i32 - 1) as usize;
+        let y = y.clamp(0, self.height as i32 - 1) as usize;
+        self.texels[y * self.width + x]
+    }
+
+    /// Bilinear sample at `(x, y)` in texels, clamping to the edge.
+    fn sample(&self, x: f32, y: f32) -> Color {
+        let (u, v) = (x - 0.5, y - 0.5);
+        let (x0, y0) = (u.floor(), v.floor());
+        let (fx, fy) = (u - x0, v - y0);
+        let (x0, y0) = (x0 as i32, y0 as i32);
+        let top = lerp(self.texel(x0, y0), self.texel(x0 + 1, y0), fx);
+        let bottom = lerp(self.texel(x0, y0 + 1), self.texel(x0 + 1, y0 + 1), fx);
+        lerp(top, bottom, fy)
+    }
+
+    /// Weighted average of samples at `(x, y)` plus the given offsets.
+    fn filter(&self, x: f32, y: f32, taps: &[(f32, f32, f32)]) -> Color {
+        let mut sum = [0.0; 4];
+        let mut total = 0.0;
+        for &(dx, dy, weight) in taps {
+            let c = self.sample(x + dx, y + dy);
+            for (s, c) in sum.iter_mut().zip(c) {
+                *s += c * weight;
+            }
+            total += weight;
+        }
+        sum.map(|s| s / total)
+    }
+}
+
+fn down_taps(o: f32) -> [(f32, f32, f32); 5] {
+    [(0.0, 0.0, 4.0), (-o, -o, 1.0), (o, -o, 1.0), (-o, o, 1.0), (o, o, 1.0)]
+}
+
+fn up_taps(o: f32) -> [(f32, f32, f32); 8] {
+    let d = 2.0 * o;
+    [(-d, 0.0, 1.0), (d, 0.0, 1.0), (0.0, -d, 1.0), (0.0, d, 1.0), (-o, -o, 2.0), (o, -o, 2.0), (-o, o, 2.0), (o, o, 2.0)]
+}
+
+// ANCHOR: BlurBackdrop
+/// Replaces the pixels of `target` behind `rect` (target pixels, corners rounded by `radius`)
+/// and within `clip` with a dual-Kawase blur of `target`.
+///
+/// The whole image is downsampled, as the GPU renderer does, so results do not depend on the
+/// clip. The final upsample to full size only runs for the pixels that are replaced.
+pub fn blur_backdrop(target: &mut SoftwareImage, rect: [f32; 4], radius: f32, style: &BlurStyle, clip: &[Rectangle<i32, Physical>]) {
+    let Some(bounds) = pixel_bounds(rect).intersection(target.bounds()) else { return };
+    let offset = style.offset;
+    let mut levels = vec![Level::from_image(target)];
+    for (width, height) in level_sizes(target.width, target.height, style.clamped_passes()) {
+        let source = levels.last().unwrap();
+        let down = Level::resample(source, width as usize, height as usize, |x, y| source.filter(x, y, &down_taps(offset)));
+        levels.push(down);
+    }
+    let mut up = levels.pop().unwrap();
+    while levels.len() > 1 {
+        let size = levels.pop().unwrap();
+        up = Level::resample(&up, size.width, size.height, |x, y| up.filter(x, y, &up_taps(offset)));
+    }
+
+    let (sx, sy) = (up.width as f32 / target.width as f32, up.height as f32 / target.height as f32);
+    for clip in clip {
+        let Some(area) = clip.intersection(bounds) else { continue };
+        for y in area.loc.y..area.loc.y + area.size.h {
+            for x in area.loc.x..area.loc.x + area.size.w {
+                let (cx, cy) = (x as f32 + 0.5, y as f32 + 0.5);
+                let coverage = rounded_rect_coverage(cx, cy, rect, radius);
+                if coverage <= 0.0 {
+                    continue;
+                }
+                let blurred = up.filter(cx * sx, cy * sy, &up_taps(offset));
+                let index = (y as u32 * target.width + x as u32) as usize;
+                target.pixels[index] = pack(lerp(unpack(target.pixels[index]), blurred, coverage));
+            }
+        }
+    }
+}
+// ANCHOR_END: BlurBackdrop
@@ -1,18 +1,23 @@
 // novade-system/src/renderer/wgpu_renderer.rs
 
 use crate::compositor::renderer_interface::abstraction::{
-    FrameRenderer, RenderElement, RenderableTexture, RendererError,
-    ClientBuffer, BufferContent,
+    FrameRenderer, RenderElement, RenderableTexture, RendererError, TextureRenderParams,
+    ShadowRenderParams, BlurRenderParams, ClientBuffer, BufferContent,
     BufferFormat as AbstractionBufferFormat, DmabufDescriptor, DmabufPlaneFormat
 };
-use novade_compositor_core::surface::SurfaceId;
+use crate::compositor::renderer_interface::abstraction::SurfaceId;
 use raw_window_handle::{HasRawWindowHandle, HasRawDisplayHandle};
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::sync::{Arc, Weak};
 use uuid::Uuid;
 use anyhow::Result;
-use smithay::utils::{Physical, Rectangle, Size};
+use smithay::utils::{Physical, Point, Rectangle, Size};
 use std::borrow::Cow;
 use crate::renderer::wgpu_texture::WgpuRenderableTexture;
+use crate::compositor::color::Lut3d;
+use crate::compositor::effects::blur::{level_sizes, MAX_BLUR_PASSES};
+use crate::compositor::effects::shape::{shadow_bounds, shadow_shape};
+use novade_core::types::geometry::Rect as NovaRect;
 use smithay::reexports::wayland_server::protocol::wl_shm::Format as WlShmFormat;
 use smithay::wayland::shm::with_buffer_contents_data;
 use wgpu::util::DeviceExt;
@@ -86,8 +91,29 @@ pub struct NovaWgpuRenderer {
     tone_mapping_uniform_bgl: Option<Arc<wgpu::BindGroupLayout>>,
     tone_mapping_params_buffer: Option<wgpu::Buffer>,
 
+    /// Layout of a 3D LUT binding, shared by per-surface and output colour conversion.
+    color_lut_bgl: Arc<wgpu::BindGroupLayout>,
+    /// Main pipeline variant converting samples through a per-surface LUT.
+    color_managed_pipeline: wgpu::RenderPipeline,
+    color_conversion_pipeline: Option<wgpu::RenderPipeline>,
+    /// Uploaded LUTs by address; entries whose LUT was dropped are pruned every frame.
+    lut_bind_groups: HashMap<usize, (Weak<Lut3d>, wgpu::BindGroup)>,
+
     blit_to_swapchain_pipeline: Option<wgpu::RenderPipeline>,
 
+    /// Texture size, corner radius and opacity of a textured quad, group 2 of the main pipelines.
+    surface_shape_bgl: wgpu::BindGroupLayout,
+    /// Per-draw `EffectUniformsPod` of the effect pipelines.
+    effect_uniform_bgl: wgpu::BindGroupLayout,
+    shadow_pipeline: wgpu::RenderPipeline,
+    blur_down_pipeline: wgpu::RenderPipeline,
+    blur_up_pipeline: wgpu::RenderPipeline,
+    blur_composite_pipeline: wgpu::RenderPipeline,
+    /// Bilinear, clamp-to-edge sampler the blur kernels are written for.
+    linear_sampler: wgpu::Sampler,
+    /// Downsampled copies of the scene for backdrop blur, level 1 (half size) first.
+    blur_levels: Vec<(wgpu::Texture, wgpu::TextureView)>,
+
     // YUV-to-RGB conversion for multi-planar DMABUFs
     yuv_to_rgb_pipeline: Option<wgpu::RenderPipeline>,
     yuv_to_rgb_bind_group_layout_textures: Option<wgpu::BindGroupLayout>,
@@ -101,13 +127,31 @@ struct ToneMapUniformsPod {
     _padding: [f32; 3],
 }
 
+/// Uniforms of `assets/shaders/effects.wgsl`; rectangles are `[x, y, width, height]` in target pixels.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct EffectUniformsPod {
+    bounds: [f32; 4],
+    rect: [f32; 4],
+    shadow_shape: [f32; 4],
+    color: [f32; 4],
+    target_size: [f32; 2],
+    radius: f32,
+    shadow_radius: f32,
+    sigma: f32,
+    offset: f32,
+    _padding: [f32; 2],
+}
+
 const SOLID_COLOR_VS_MAIN_WGSL: &str = include_str!("shaders/solid_color.vert.wgsl");
 const SOLID_COLOR_FS_MAIN_WGSL: &str = include_str!("shaders/solid_color.frag.wgsl");
-const TEXTURED_QUAD_WGSL: &str = include_str!("shaders/textured_quad.wgsl");
+const TEXTURED_QUAD_WGSL: &str = concat!(include_str!("shaders/textured_quad.wgsl"), include_str!("../../assets/shaders/color_lut.wgsl"));
 const FULLSCREEN_QUAD_VERT_WGSL: &str = include_str!("../../assets/shaders/fullscreen_quad.vert");
 const GAMMA_CORRECTION_FRAG_WGSL: &str = include_str!("../../assets/shaders/gamma_correction.frag");
 const TONEMAP_FRAG_WGSL: &str = include_str!("../../assets/shaders/tonemap.frag");
+const COLOR_LUT_FRAG_WGSL: &str = concat!(include_str!("../../assets/shaders/color_lut_pass.wgsl"), include_str!("../../assets/shaders/color_lut.wgsl"));
 const COPY_TEXTURE_FRAG_WGSL: &str = include_str!("../../assets/shaders/copy_texture.frag");
+const EFFECTS_WGSL: &str = include_str!("../../assets/shaders/effects.wgsl");
 
 // ANCHOR [YuvToRgbFragmentShaderPlaceholder]
 // TODO [DmabufYuvShader]: This is a placeholder for novade-system/assets/shaders/yuv_to_rgb.frag
@@ -229,17 +273,30 @@ impl NovaWgpuRenderer {
             ]});
         let transform_bgl = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             label: Some("Main Transform BGL"), entries: &[wgpu::BindGroupLayoutEntry { binding: 0, visibility: wgpu::ShaderStages::VERTEX, ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Uniform, has_dynamic_offset: false, min_binding_size: None }, count: None }]});
-        let main_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor { label: Some("Main Pipeline Layout"), bind_group_layouts: &[&texture_bgl, &transform_bgl], push_constant_ranges: &[] });
+        let surface_shape_bgl = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Surface Shape BGL"), entries: &[wgpu::BindGroupLayoutEntry { binding: 0, visibility: wgpu::ShaderStages::FRAGMENT, ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Uniform, has_dynamic_offset: false, min_binding_size: wgpu::BufferSize::new(16) }, count: None }]});
+        let main_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor { label: Some("Main Pipeline Layout"), bind_group_layouts: &[&texture_bgl, &transform_bgl, &surface_shape_bgl], push_constant_ranges: &[] });
         let main_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
             label: Some("Main Pipeline"), layout: Some(&main_pipeline_layout),
             vertex: wgpu::VertexState { module: &main_shader_module, entry_point: "vs_main", buffers: &[Vertex::desc()] },
-            // TODO [AdvancedBlendingModes]: The current blend state is REPLACE. For transparency and other effects,
-            // this could be configured to `wgpu::BlendState::ALPHA_BLENDING` or custom blend factors/operations.
-            // This would likely involve:
+            // Surfaces are premultiplied and blended "over" so rounded corners and translucent windows show what is behind.
+            // TODO [AdvancedBlendingModes]: Other blend modes would likely involve:
             // 1. Storing `BlendState` as part of `RenderElement` or deriving it from surface properties.
             // 2. Potentially creating multiple pipelines for different blend states or dynamically setting blend state if supported.
             // ANCHOR [AdvancedBlendingModesOutline]
-            fragment: Some(wgpu::FragmentState { module: &main_shader_module, entry_point: "fs_main", targets: &[Some(wgpu::ColorTargetState { format: surface_format, blend: Some(wgpu::BlendState::REPLACE), write_mask: wgpu::ColorWrites::ALL })] }),
+            fragment: Some(wgpu::FragmentState { module: &main_shader_module, entry_point: "fs_main", targets: &[Some(wgpu::ColorTargetState { format: surface_format, blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING), write_mask: wgpu::ColorWrites::ALL })] }),
+            primitive: wgpu::PrimitiveState::default(), depth_stencil: None, multisample: wgpu::MultisampleState::default(), multiview: None,
+        });
+
+        // Colour-managed variant of the main pipeline (see `color_lut_bgl`).
+        let color_lut_bgl = Arc::new(device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Color LUT BGL"), entries: &[wgpu::BindGroupLayoutEntry { binding: 0, visibility: wgpu::ShaderStages::FRAGMENT, ty: wgpu::BindingType::Texture { sample_type: wgpu::TextureSampleType::Float { filterable: false }, view_dimension: wgpu::TextureViewDimension::D3, multisampled: false }, count: None }],
+        }));
+        let color_managed_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor { label: Some("Color Managed Pipeline Layout"), bind_group_layouts: &[&texture_bgl, &transform_bgl, &surface_shape_bgl, &color_lut_bgl], push_constant_ranges: &[] });
+        let color_managed_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Color Managed Pipeline"), layout: Some(&color_managed_pipeline_layout),
+            vertex: wgpu::VertexState { module: &main_shader_module, entry_point: "vs_main", buffers: &[Vertex::desc()] },
+            fragment: Some(wgpu::FragmentState { module: &main_shader_module, entry_point: "fs_main_color_managed", targets: &[Some(wgpu::ColorTargetState { format: surface_format, blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING), write_mask: wgpu::ColorWrites::ALL })] }),
             primitive: wgpu::PrimitiveState::default(), depth_stencil: None, multisample: wgpu::MultisampleState::default(), multiview: None,
         });
 
@@ -304,6 +361,11 @@ impl NovaWgpuRenderer {
         let tonemap_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor { label: Some("ToneMap Pipeline"), layout: Some(&tonemap_pipeline_layout), vertex: wgpu::VertexState { module: &fs_quad_shader_module, entry_point: "vs_main", buffers: &[] }, fragment: Some(wgpu::FragmentState { module: &tonemap_frag_module, entry_point: "fs_main", targets: &[Some(wgpu::ColorTargetState { format: surface_format, blend: Some(wgpu::BlendState::REPLACE), write_mask: wgpu::ColorWrites::ALL })] }), primitive: wgpu::PrimitiveState::default(), depth_stencil: None, multisample: wgpu::MultisampleState::default(), multiview: None });
         let tonemap_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor { label: Some("ToneMap Uniform Buffer"), contents: bytemuck::cast_slice(&[ToneMapUniformsPod{exposure: 1.0, _padding: [0.0;3]}]), usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST });
 
+        // Output Colour Conversion
+        let color_lut_frag_module = device.create_shader_module(wgpu::ShaderModuleDescriptor { label: Some("Color LUT FS"), source: wgpu::ShaderSource::Wgsl(COLOR_LUT_FRAG_WGSL.into()) });
+        let color_lut_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor { label: Some("Color LUT Pipeline Layout"), bind_group_layouts: &[&pp_texture_bgl, &color_lut_bgl], push_constant_ranges: &[] });
+        let color_lut_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor { label: Some("Color LUT Pipeline"), layout: Some(&color_lut_pipeline_layout), vertex: wgpu::VertexState { module: &fs_quad_shader_module, entry_point: "vs_main", buffers: &[] }, fragment: Some(wgpu::FragmentState { module: &color_lut_frag_module, entry_point: "fs_main", targets: &[Some(wgpu::ColorTargetState { format: surface_format, blend: Some(wgpu::BlendState::REPLACE), write_mask: wgpu::ColorWrites::ALL })] }), primitive: wgpu::PrimitiveState::default(), depth_stencil: None, multisample: wgpu::MultisampleState::default(), multiview: None });
+
         // Blit Pipeline
         let copy_frag_module = device.create_shader_module(wgpu::ShaderModuleDescriptor { label: Some("Copy FS"), source: wgpu::ShaderSource::Wgsl(COPY_TEXTURE_FRAG_WGSL.into()) });
         let blit_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor { label: Some("Blit Pipeline Layout"), bind_group_layouts: &[&pp_texture_bgl], push_constant_ranges: &[] });
@@ -314,6 +376,26 @@ impl NovaWgpuRenderer {
             primitive: wgpu::PrimitiveState::default(), depth_stencil: None, multisample: wgpu::MultisampleState::default(), multiview: None,
         });
 
+        // Window Effects
+        let effects_module = device.create_shader_module(wgpu::ShaderModuleDescriptor { label: Some("Effects Shader"), source: wgpu::ShaderSource::Wgsl(EFFECTS_WGSL.into()) });
+        let effect_uniform_bgl = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Effect Uniform BGL"), entries: &[wgpu::BindGroupLayoutEntry { binding: 0, visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT, ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Uniform, has_dynamic_offset: false, min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<EffectUniformsPod>() as u64) }, count: None }],
+        });
+        let shadow_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor { label: Some("Shadow Pipeline Layout"), bind_group_layouts: &[&effect_uniform_bgl], push_constant_ranges: &[] });
+        let blur_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor { label: Some("Blur Pipeline Layout"), bind_group_layouts: &[&effect_uniform_bgl, &pp_texture_bgl], push_constant_ranges: &[] });
+        let effect_pipeline = |label: &str, layout: &wgpu::PipelineLayout, entry_point: &str, blend: wgpu::BlendState| device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(label), layout: Some(layout),
+            vertex: wgpu::VertexState { module: &effects_module, entry_point: "vs_main", buffers: &[] },
+            fragment: Some(wgpu::FragmentState { module: &effects_module, entry_point, targets: &[Some(wgpu::ColorTargetState { format: surface_format, blend: Some(blend), write_mask: wgpu::ColorWrites::ALL })] }),
+            primitive: wgpu::PrimitiveState::default(), depth_stencil: None, multisample: wgpu::MultisampleState::default(), multiview: None,
+        });
+        let shadow_pipeline = effect_pipeline("Shadow Pipeline", &shadow_pipeline_layout, "fs_shadow", wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING);
+        let blur_down_pipeline = effect_pipeline("Blur Down Pipeline", &blur_pipeline_layout, "fs_blur_down", wgpu::BlendState::REPLACE);
+        let blur_up_pipeline = effect_pipeline("Blur Up Pipeline", &blur_pipeline_layout, "fs_blur_up", wgpu::BlendState::REPLACE);
+        let blur_composite_pipeline = effect_pipeline("Blur Composite Pipeline", &blur_pipeline_layout, "fs_blur_composite", wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING);
+        let linear_sampler = device.create_sampler(&wgpu::SamplerDescriptor { label: Some("Linear Clamp Sampler"), mag_filter: wgpu::FilterMode::Linear, min_filter: wgpu::FilterMode::Linear, ..Default::default() });
+        let blur_levels = Self::create_blur_levels(&device, surface_format, initial_size);
+
         // Scene and Ping-Pong Textures
         let scene_render_target_desc = wgpu::TextureDescriptor {
             label: Some("Scene Render Target"), size: wgpu::Extent3d { width: initial_size.w, height: initial_size.h, depth_or_array_layers: 1 },
@@ -368,7 +450,13 @@ impl NovaWgpuRenderer {
             tone_mapping_pipeline: Some(tonemap_pipeline),
             tone_mapping_uniform_bgl: Some(tonemap_uniform_bgl),
             tone_mapping_params_buffer: Some(tonemap_buffer),
+            color_lut_bgl, color_managed_pipeline,
+            color_conversion_pipeline: Some(color_lut_pipeline),
+            lut_bind_groups: HashMap::new(),
             blit_to_swapchain_pipeline: Some(blit_pipeline),
+            surface_shape_bgl, effect_uniform_bgl,
+            shadow_pipeline, blur_down_pipeline, blur_up_pipeline, blur_composite_pipeline,
+            linear_sampler, blur_levels,
             yuv_to_rgb_pipeline: None, // Initialize as None, to be created with shader
             yuv_to_rgb_bind_group_layout_textures: Some(yuv_to_rgb_bgl_textures),
             // TODO [ErrorHandlingAndRecovery]: WGPU operations can fail (e.g., device lost).
@@ -413,12 +501,148 @@ impl NovaWgpuRenderer {
                     self.post_processing_textures[i] = Some(self.device.create_texture(&pp_desc));
                     self.post_processing_texture_views[i] = self.post_processing_textures[i].as_ref().map(|t| t.create_view(&wgpu::TextureViewDescriptor::default()));
                 }
+                self.blur_levels = Self::create_blur_levels(&self.device, config.format, new_size);
                 tracing::info!("Resized offscreen targets to: {}x{}", new_size.w, new_size.h);
             }
         } else {
             tracing::warn!("WGPU surface resize requested with zero dimension: {}x{}", new_size.w, new_size.h);
         }
     }
+
+    /// Uploads `lut` as an `Rgba32Float` 3D texture bound through `color_lut_bgl`.
+    fn create_lut_bind_group(&self, lut: &Lut3d) -> wgpu::BindGroup {
+        let size = lut.size() as u32;
+        let extent = wgpu::Extent3d { width: size, height: size, depth_or_array_layers: size };
+        let texture = self.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Color LUT Texture"), size: extent, mip_level_count: 1, sample_count: 1, dimension: wgpu::TextureDimension::D3,
+            format: wgpu::TextureFormat::Rgba32Float, usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST, view_formats: &[],
+        });
+        self.queue.write_texture(wgpu::ImageCopyTexture { texture: &texture, mip_level: 0, origin: wgpu::Origin3d::ZERO, aspect: wgpu::TextureAspect::All },
+            bytemuck::cast_slice(&lut.to_rgba_f32()), wgpu::ImageDataLayout { offset: 0, bytes_per_row: Some(size * 16), rows_per_image: Some(size) }, extent);
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        self.device.create_bind_group(&wgpu::BindGroupDescriptor { label: Some("Color LUT BG"), layout: &self.color_lut_bgl, entries: &[wgpu::BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(&view) }] })
+    }
+
+    /// Uploads a per-surface LUT unless it is already cached.
+    fn upload_lut(&mut self, lut: &Arc<Lut3d>) {
+        let key = Arc::as_ptr(lut) as usize;
+        if !self.lut_bind_groups.contains_key(&key) {
+            let bind_group = self.create_lut_bind_group(lut);
+            self.lut_bind_groups.insert(key, (Arc::downgrade(lut), bind_group));
+        }
+    }
+
+    /// Allocates the blur pyramid for a `size` target, enough for `MAX_BLUR_PASSES`.
+    fn create_blur_levels(device: &wgpu::Device, format: wgpu::TextureFormat, size: Size<u32, Physical>) -> Vec<(wgpu::Texture, wgpu::TextureView)> {
+        level_sizes(size.w, size.h, MAX_BLUR_PASSES).into_iter().enumerate().map(|(i, (width, height))| {
+            let texture = device.create_texture(&wgpu::TextureDescriptor {
+                label: Some(&format!("Blur Level {}", i + 1)), size: wgpu::Extent3d { width, height, depth_or_array_layers: 1 },
+                mip_level_count: 1, sample_count: 1, dimension: wgpu::TextureDimension::D2, format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING, view_formats: &[],
+            });
+            let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+            (texture, view)
+        }).collect()
+    }
+
+    /// Scissor rectangle covering `[x, y, width, height]` in target pixels, limited to the
+    /// target; `None` if none of it is on the target.
+    fn scissor_rect(&self, [x, y, w, h]: [f32; 4]) -> Option<(u32, u32, u32, u32)> {
+        let size = self.screen_size_physical;
+        let (x0, y0) = (x.floor().max(0.0), y.floor().max(0.0));
+        let (x1, y1) = ((x + w).ceil().min(size.w as f32), (y + h).ceil().min(size.h as f32));
+        (x1 > x0 && y1 > y0).then(|| (x0 as u32, y0 as u32, (x1 - x0) as u32, (y1 - y0) as u32))
+    }
+
+    fn effect_uniform_bind_group(&self, uniforms: &EffectUniformsPod) -> wgpu::BindGroup {
+        let buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor { label: Some("Effect Uniform Buffer"), contents: bytemuck::bytes_of(uniforms), usage: wgpu::BufferUsages::UNIFORM });
+        self.device.create_bind_group(&wgpu::BindGroupDescriptor { label: Some("Effect Uniform BG"), layout: &self.effect_uniform_bgl, entries: &[wgpu::BindGroupEntry { binding: 0, resource: buffer.as_entire_binding() }] })
+    }
+
+    /// Draws a `RenderElement::Shadow` into the scene pass.
+    fn draw_shadow<'pass>(&'pass self, rpass: &mut wgpu::RenderPass<'pass>, params: &ShadowRenderParams, origin: Point<i32, Physical>, scale: f32) {
+        let rect = to_target_rect(&params.geometry, origin, scale);
+        let radius = params.corner_radius * scale;
+        let style = params.style.scaled(scale);
+        let (Some((shape, shadow_radius)), Some(bounds)) = (shadow_shape(rect, radius, &style), shadow_bounds(rect, radius, &style)) else { return };
+        let Some((x, y, w, h)) = self.scissor_rect(bounds) else { return };
+        let [r, g, b, a] = style.color;
+        let uniforms = EffectUniformsPod {
+            bounds, rect, shadow_shape: shape, color: [r * a, g * a, b * a, a],
+            target_size: [self.screen_size_physical.w as f32, self.screen_size_physical.h as f32],
+            radius, shadow_radius, sigma: style.sigma(), ..bytemuck::Zeroable::zeroed()
+        };
+        let bind_group = self.effect_uniform_bind_group(&uniforms);
+        rpass.set_pipeline(&self.shadow_pipeline);
+        rpass.set_bind_group(0, &bind_group, &[]);
+        rpass.set_scissor_rect(x, y, w, h);
+        rpass.draw(0..6, 0..1);
+    }
+
+    /// Draws `pipeline` over `uniforms.bounds` of `target`, sampling `source`. Without
+    /// `scissors` the target is cleared first; with them it is drawn over, within each.
+    fn effect_pass(&self, encoder: &mut wgpu::CommandEncoder, target: &wgpu::TextureView, pipeline: &wgpu::RenderPipeline, uniforms: &EffectUniformsPod, source: &wgpu::TextureView, scissors: Option<&[(u32, u32, u32, u32)]>) {
+        let uniform_bg = self.effect_uniform_bind_group(uniforms);
+        let texture_bg = self.device.create_bind_group(&wgpu::BindGroupDescriptor { label: Some("Effect Source BG"), layout: self.post_processing_texture_bgl.as_ref().unwrap(), entries: &[ wgpu::BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(source) }, wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::Sampler(&self.linear_sampler) } ]});
+        let load = if scissors.is_some() { wgpu::LoadOp::Load } else { wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT) };
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor { label: Some("Effect Pass"), color_attachments: &[Some(wgpu::RenderPassColorAttachment { view: target, resolve_target: None, ops: wgpu::Operations { load, store: wgpu::StoreOp::Store }})], ..Default::default()});
+        rpass.set_pipeline(pipeline);
+        rpass.set_bind_group(0, &uniform_bg, &[]);
+        rpass.set_bind_group(1, &texture_bg, &[]);
+        match scissors {
+            Some(scissors) => for &(x, y, w, h) in scissors {
+                rpass.set_scissor_rect(x, y, w, h);
+                rpass.draw(0..6, 0..1);
+            },
+            None => rpass.draw(0..6, 0..1),
+        }
+    }
+
+    // ANCHOR [BackdropBlur]
+    /// Replaces the scene behind a `RenderElement::BackdropBlur` with a dual-Kawase blur of it.
+    ///
+    /// The whole scene is downsampled into `blur_levels` and scaled back up to level 1, like
+    /// the software renderer does; the last upsample is drawn straight into the scene, within
+    /// the blurred region and rounded by the corner radius.
+    fn blur_backdrop(&self, encoder: &mut wgpu::CommandEncoder, scene: &wgpu::TextureView, params: &BlurRenderParams, origin: Point<i32, Physical>, scale: f32) {
+        let round_edges = |r: &NovaRect<f32>| {
+            let [x, y, w, h] = to_target_rect(r, origin, scale);
+            let (x0, y0) = (x.round(), y.round());
+            [x0, y0, (x + w).round() - x0, (y + h).round() - y0]
+        };
+        let region = if params.region.is_empty() { std::slice::from_ref(&params.geometry) } else { params.region.as_slice() };
+        let scissors: Vec<_> = region.iter().filter_map(|r| self.scissor_rect(round_edges(r))).collect();
+        if scissors.is_empty() {
+            return;
+        }
+        let levels = &self.blur_levels[..params.style.clamped_passes() as usize];
+        let level_uniforms = |texture: &wgpu::Texture| {
+            let size = [texture.width() as f32, texture.height() as f32];
+            EffectUniformsPod { bounds: [0.0, 0.0, size[0], size[1]], target_size: size, offset: params.style.offset, ..bytemuck::Zeroable::zeroed() }
+        };
+
+        let mut source = scene;
+        for (texture, view) in levels {
+            self.effect_pass(encoder, view, &self.blur_down_pipeline, &level_uniforms(texture), source, None);
+            source = view;
+        }
+        for (texture, view) in levels[..levels.len() - 1].iter().rev() {
+            self.effect_pass(encoder, view, &self.blur_up_pipeline, &level_uniforms(texture), source, None);
+            source = view;
+        }
+        let rect = to_target_rect(&params.geometry, origin, scale);
+        let composite = EffectUniformsPod {
+            bounds: rect, rect, radius: params.corner_radius * scale,
+            target_size: [self.screen_size_physical.w as f32, self.screen_size_physical.h as f32],
+            offset: params.style.offset, ..bytemuck::Zeroable::zeroed()
+        };
+        self.effect_pass(encoder, scene, &self.blur_composite_pipeline, &composite, source, Some(&scissors));
+    }
+}
+
+/// Converts a rectangle in world coordinates to `[x, y, width, height]` in target pixels.
+fn to_target_rect(rect: &NovaRect<f32>, origin: Point<i32, Physical>, scale: f32) -> [f32; 4] {
+    [rect.origin.x * scale - origin.x as f32, rect.origin.y * scale - origin.y as f32, rect.size.width * scale, rect.size.height * scale]
 }
 
 impl FrameRenderer for NovaWgpuRenderer {
@@ -427,8 +651,8 @@ impl FrameRenderer for NovaWgpuRenderer {
     fn render_frame<'iter_elements>(
         &mut self,
         elements: impl IntoIterator<Item = RenderElement<'iter_elements>>,
-        _output_geometry_physical: Rectangle<i32, Physical>,
-        _output_scale: f64,
+        output_geometry_physical: Rectangle<i32, Physical>,
+        output_scale: f64,
     ) -> Result<(), RendererError> {
         if self.current_encoder.is_some() || self.current_surface_texture.is_some() {
             tracing::warn!("render_frame called while previous frame resources were not cleared.");
@@ -451,109 +675,153 @@ impl FrameRenderer for NovaWgpuRenderer {
             Err(e) => return Err(RendererError::BufferSwapFailed(format!("Get WGPU texture: {}", e))),
         };
 
+        // LUT bind groups must exist before the render pass borrows them.
+        let elements: Vec<RenderElement<'iter_elements>> = elements.into_iter().collect();
+        self.lut_bind_groups.retain(|_, (lut, _)| lut.strong_count() > 0);
+        for element in &elements {
+            if let RenderElement::TextureNode(TextureRenderParams { color_transform: Some(lut), .. }) = element {
+                self.upload_lut(lut);
+            }
+        }
+
         let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("Main Encoder") });
 
         if let Some(target_view) = &self.scene_render_target_view {
-            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Main Scene Pass (to scene_render_target)"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: target_view, ops: wgpu::Operations { load: wgpu::LoadOp::Clear(wgpu::Color::BLACK), store: wgpu::StoreOp::Store }, resolve_target: None,
-                })],
-                // TODO [MultiTargetRendering]: For effects requiring multiple outputs (e.g., deferred shading G-Buffer),
-                // `color_attachments` would be an array of `Option<RenderPassColorAttachment>` targeting different texture views.
-                // The fragment shader would then output multiple color values (`@location(0) out_color0: vec4<f32>, @location(1) out_color1: vec4<f32>`).
-                // This would require separate `TextureView`s and likely different `TextureFormat`s for each target.
-                // ANCHOR [MultiTargetRenderingOutline]
-                depth_stencil_attachment: None, timestamp_writes: None, occlusion_query_set: None,
-            });
-            rpass.set_pipeline(&self.render_pipeline);
-            // TODO [AdvancedBlendingModes]: If different blend states are needed per element, this might involve
-            // switching pipelines here or using a more advanced system if WGPU supports dynamic blend states without pipeline switches.
-            // (see ANCHOR [AdvancedBlendingModesOutline] in new())
-            rpass.set_vertex_buffer(0, self.quad_vertex_buffer.slice(..));
-            rpass.set_index_buffer(self.quad_index_buffer.slice(..), wgpu::IndexFormat::Uint16);
-
-            // TODO [InstancedRenderingImpl]: For scenarios with many identical elements (e.g., icons, particles,
-            // or even identical window decorations if rendered as separate quads), instanced rendering
-            // could significantly reduce draw calls. This would involve:
-            // 1. Identifying batches of `RenderElement::TextureNode` that share the same texture, sampler,
-            //    and basic geometry but differ in transform or other per-instance attributes (color tint, clip_rect etc.).
-            // 2. Creating a new `RenderElement::TextureInstanceBatch { texture, instances: Vec<InstanceData> }` variant or similar.
-            //    `InstanceData` would hold transform, color tint, source_rect, clip_rect_id (if clipping is complex).
-            // 3. Modifying the vertex shader to accept per-instance attributes from an instance buffer (using `@builtin(instance_index)`
-            //    and `wgpu::VertexStepMode::Instance`).
-            // 4. Creating a new `wgpu::RenderPipeline` configured for instanced drawing (vertex_buffers would describe instance data layout).
-            // 5. In `render_frame`, collecting instance data into a `wgpu::Buffer` (updated each frame or if data changes)
-            //    and using `render_pass.set_vertex_buffer(slot, instance_buffer_slice)`
-            //    before calling `render_pass.draw_indexed(indices, base_vertex, 0..instance_count)`.
-            // ANCHOR [InstancedRenderingOutline]
-            for element in elements {
-                 match element {
-                    RenderElement::TextureNode(params) => {
-                        if let Some(wgpu_tex) = params.texture.as_any().downcast_ref::<WgpuRenderableTexture>() {
-                            if wgpu_tex.is_multi_planar {
-                                // TODO [DmabufYuvRendering]: Implement YUV rendering path
-                                // 1. Ensure yuv_to_rgb_pipeline is compiled.
-                                // 2. Get Y, U, V (or Y, UV) plane views from wgpu_tex.plane_views or specific accessors.
-                                // 3. Create a bind group using yuv_to_rgb_bind_group_layout_textures with these plane views and a sampler.
-                                // 4. Set the yuv_to_rgb_pipeline.
-                                // 5. Set the YUV bind group.
-                                // 6. Set transform bind group (as below).
-                                // 7. Draw indexed.
-                                tracing::warn!("Multi-planar DMABUF rendering for texture ID {} not yet implemented, skipping.", wgpu_tex.id());
-                                // Fallthrough or skip rendering this element for now.
-                                // For now, let's try to render the primary plane with the standard pipeline as a fallback.
-                                // This will likely look wrong for YUV.
-                                rpass.set_pipeline(&self.render_pipeline);
-                                let texture_bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
-                                    label: Some("Element Texture BG (Primary Plane of Multi-Planar)"),
-                                    layout: &self.texture_bind_group_layout,
-                                    entries: &[
-                                        wgpu::BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(wgpu_tex.view()) }, // .view() gets primary_view
-                                        wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::Sampler(wgpu_tex.sampler()) },
-                                    ],
+            let origin = output_geometry_physical.loc;
+            let scale = output_scale as f32;
+            let mut elements = elements.into_iter();
+            // Backdrop blurs sample what was drawn before them, so the scene pass is split at each.
+            let mut load = wgpu::LoadOp::Clear(wgpu::Color::BLACK);
+            loop {
+                let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("Main Scene Pass (to scene_render_target)"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: target_view, ops: wgpu::Operations { load, store: wgpu::StoreOp::Store }, resolve_target: None,
+                    })],
+                    // TODO [MultiTargetRendering]: For effects requiring multiple outputs (e.g., deferred shading G-Buffer),
+                    // `color_attachments` would be an array of `Option<RenderPassColorAttachment>` targeting different texture views.
+                    // The fragment shader would then output multiple color values (`@location(0) out_color0: vec4<f32>, @location(1) out_color1: vec4<f32>`).
+                    // This would require separate `TextureView`s and likely different `TextureFormat`s for each target.
+                    // ANCHOR [MultiTargetRenderingOutline]
+                    depth_stencil_attachment: None, timestamp_writes: None, occlusion_query_set: None,
+                });
+                rpass.set_pipeline(&self.render_pipeline);
+                // TODO [AdvancedBlendingModes]: If different blend states are needed per element, this might involve
+                // switching pipelines here or using a more advanced system if WGPU supports dynamic blend states without pipeline switches.
+                // (see ANCHOR [AdvancedBlendingModesOutline] in new())
+                rpass.set_vertex_buffer(0, self.quad_vertex_buffer.slice(..));
+                rpass.set_index_buffer(self.quad_index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+
+                // TODO [InstancedRenderingImpl]: For scenarios with many identical elements (e.g., icons, particles,
+                // or even identical window decorations if rendered as separate quads), instanced rendering
+                // could significantly reduce draw calls. This would involve:
+                // 1. Identifying batches of `RenderElement::TextureNode` that share the same texture, sampler,
+                //    and basic geometry but differ in transform or other per-instance attributes (color tint, clip_rect etc.).
+                // 2. Creating a new `RenderElement::TextureInstanceBatch { texture, instances: Vec<InstanceData> }` variant or similar.
+                //    `InstanceData` would hold transform, color tint, source_rect, clip_rect_id (if clipping is complex).
+                // 3. Modifying the vertex shader to accept per-instance attributes from an instance buffer (using `@builtin(instance_index)`
+                //    and `wgpu::VertexStepMode::Instance`).
+                // 4. Creating a new `wgpu::RenderPipeline` configured for instanced drawing (vertex_buffers would describe instance data layout).
+                // 5. In `render_frame`, collecting instance data into a `wgpu::Buffer` (updated each frame or if data changes)
+                //    and using `render_pass.set_vertex_buffer(slot, instance_buffer_slice)`
+                //    before calling `render_pass.draw_indexed(indices, base_vertex, 0..instance_count)`.
+                // ANCHOR [InstancedRenderingOutline]
+                let mut blur = None;
+                for element in elements.by_ref() {
+                     match element {
+                        RenderElement::TextureNode(params) => {
+                            if let Some(wgpu_tex) = params.texture.as_any().downcast_ref::<WgpuRenderableTexture>() {
+                                if wgpu_tex.is_multi_planar {
+                                    // TODO [DmabufYuvRendering]: Implement YUV rendering path
+                                    // 1. Ensure yuv_to_rgb_pipeline is compiled.
+                                    // 2. Get Y, U, V (or Y, UV) plane views from wgpu_tex.plane_views or specific accessors.
+                                    // 3. Create a bind group using yuv_to_rgb_bind_group_layout_textures with these plane views and a sampler.
+                                    // 4. Set the yuv_to_rgb_pipeline.
+                                    // 5. Set the YUV bind group.
+                                    // 6. Set transform bind group (as below).
+                                    // 7. Draw indexed.
+                                    tracing::warn!("Multi-planar DMABUF rendering for texture ID {} not yet implemented, skipping.", wgpu_tex.id());
+                                    // Fallthrough or skip rendering this element for now.
+                                    // For now, let's try to render the primary plane with the standard pipeline as a fallback.
+                                    // This will likely look wrong for YUV.
+                                    rpass.set_pipeline(&self.render_pipeline);
+                                    let texture_bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                                        label: Some("Element Texture BG (Primary Plane of Multi-Planar)"),
+                                        layout: &self.texture_bind_group_layout,
+                                        entries: &[
+                                            wgpu::BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(wgpu_tex.view()) }, // .view() gets primary_view
+                                            wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::Sampler(wgpu_tex.sampler()) },
+                                        ],
+                                    });
+                                    rpass.set_bind_group(0, &texture_bind_group, &[]);
+                                } else {
+                                    match params.color_transform.as_ref().and_then(|lut| self.lut_bind_groups.get(&(Arc::as_ptr(lut) as usize))) {
+                                        Some((_, lut_bind_group)) => {
+                                            rpass.set_pipeline(&self.color_managed_pipeline);
+                                            rpass.set_bind_group(3, lut_bind_group, &[]);
+                                        }
+                                        None => rpass.set_pipeline(&self.render_pipeline),
+                                    }
+                                    let texture_bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                                        label: Some("Element Texture BG (Single-Planar)"), layout: &self.texture_bind_group_layout,
+                                        entries: &[
+                                            wgpu::BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(wgpu_tex.view()) },
+                                            wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::Sampler(wgpu_tex.sampler()) },
+                                        ],
+                                    });
+                                    rpass.set_bind_group(0, &texture_bind_group, &[]);
+                                }
+
+                                let sg_matrix = params.transform.matrix;
+                                let transform_uniform_data: [f32; 9] = [ sg_matrix[0][0], sg_matrix[1][0], 0.0, sg_matrix[0][1], sg_matrix[1][1], 0.0, sg_matrix[0][2], sg_matrix[1][2], 1.0 ];
+                                // TODO [DynamicUniformBufferManagement]: This transform_buffer is created per-element, per-frame.
+                                // For high element counts, this is inefficient (many small buffers, many bind group creations).
+                                // Better approaches:
+                                // 1. Instanced rendering (see ANCHOR [InstancedRenderingOutline]): Instance data (including transforms)
+                                //    is uploaded once per frame to a larger buffer.
+                                // 2. Uniform buffer with dynamic offsets: Upload all transforms to one large buffer, use `set_bind_group`
+                                //    with dynamic offsets for each draw call. Requires `min_uniform_buffer_offset_alignment`.
+                                // 3. Storage buffers: If transforms are numerous and complex, store them in a storage buffer accessible
+                                //    by the vertex shader, indexed by `vertex_index` or `instance_index`.
+                                // ANCHOR [DynamicUniformBufferOutlineInRenderFrame]
+                                let transform_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                                    label: Some("Element Transform Uniform Buffer"), contents: bytemuck::cast_slice(&transform_uniform_data), usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
                                 });
-                                rpass.set_bind_group(0, &texture_bind_group, &[]);
-                            } else {
-                                rpass.set_pipeline(&self.render_pipeline);
-                                let texture_bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
-                                    label: Some("Element Texture BG (Single-Planar)"), layout: &self.texture_bind_group_layout,
-                                    entries: &[
-                                        wgpu::BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(wgpu_tex.view()) },
-                                        wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::Sampler(wgpu_tex.sampler()) },
-                                    ],
+                                let transform_bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                                    label: Some("Element Transform BG"), layout: &self.transform_bind_group_layout,
+                                    entries: &[wgpu::BindGroupEntry { binding: 0, resource: transform_buffer.as_entire_binding() }],
+                                });
+                                let shape_uniform_data: [f32; 4] = [wgpu_tex.width_px() as f32, wgpu_tex.height_px() as f32, params.corner_radius, params.alpha];
+                                let shape_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                                    label: Some("Element Shape Uniform Buffer"), contents: bytemuck::cast_slice(&shape_uniform_data), usage: wgpu::BufferUsages::UNIFORM,
+                                });
+                                let shape_bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                                    label: Some("Element Shape BG"), layout: &self.surface_shape_bgl,
+                                    entries: &[wgpu::BindGroupEntry { binding: 0, resource: shape_buffer.as_entire_binding() }],
                                 });
                                 rpass.set_bind_group(0, &texture_bind_group, &[]);
+                                rpass.set_bind_group(1, &transform_bind_group, &[]);
+                                rpass.set_bind_group(2, &shape_bind_group, &[]);
+                                // One scissored draw per visible part, so occluded pixels are never shaded.
+                                let clips = if params.visible_region.is_empty() { std::slice::from_ref(&params.clip_rect) } else { params.visible_region.as_slice() };
+                                for clip in clips { // Assuming physical pixels
+                                    rpass.set_scissor_rect(clip.origin.x as u32, clip.origin.y as u32, clip.size.width as u32, clip.size.height as u32);
+                                    rpass.draw_indexed(0..self.quad_num_indices, 0, 0..1);
+                                }
                             }
-
-                            let sg_matrix = params.transform.matrix;
-                            let transform_uniform_data: [f32; 9] = [ sg_matrix[0][0], sg_matrix[1][0], 0.0, sg_matrix[0][1], sg_matrix[1][1], 0.0, sg_matrix[0][2], sg_matrix[1][2], 1.0 ];
-                            // TODO [DynamicUniformBufferManagement]: This transform_buffer is created per-element, per-frame.
-                            // For high element counts, this is inefficient (many small buffers, many bind group creations).
-                            // Better approaches:
-                            // 1. Instanced rendering (see ANCHOR [InstancedRenderingOutline]): Instance data (including transforms)
-                            //    is uploaded once per frame to a larger buffer.
-                            // 2. Uniform buffer with dynamic offsets: Upload all transforms to one large buffer, use `set_bind_group`
-                            //    with dynamic offsets for each draw call. Requires `min_uniform_buffer_offset_alignment`.
-                            // 3. Storage buffers: If transforms are numerous and complex, store them in a storage buffer accessible
-                            //    by the vertex shader, indexed by `vertex_index` or `instance_index`.
-                            // ANCHOR [DynamicUniformBufferOutlineInRenderFrame]
-                            let transform_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                                label: Some("Element Transform Uniform Buffer"), contents: bytemuck::cast_slice(&transform_uniform_data), usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-                            });
-                            let transform_bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
-                                label: Some("Element Transform BG"), layout: &self.transform_bind_group_layout,
-                                entries: &[wgpu::BindGroupEntry { binding: 0, resource: transform_buffer.as_entire_binding() }],
-                            });
-                            rpass.set_bind_group(0, &texture_bind_group, &[]);
-                            rpass.set_bind_group(1, &transform_bind_group, &[]);
-                            let clip = params.clip_rect; // Assuming physical pixels
-                            rpass.set_scissor_rect(clip.origin.x as u32, clip.origin.y as u32, clip.size.width as u32, clip.size.height as u32);
-                            rpass.draw_indexed(0..self.quad_num_indices, 0, 0..1);
                         }
+                        RenderElement::Shadow(params) => self.draw_shadow(&mut rpass, &params, origin, scale),
+                        RenderElement::BackdropBlur(params) => {
+                            blur = Some(params);
+                            break;
+                        }
+                        _ => {}
                     }
-                    _ => {}
                 }
+                drop(rpass);
+                let Some(params) = blur else { break };
+                self.blur_backdrop(&mut encoder, target_view, &params, origin, scale);
+                load = wgpu::LoadOp::Load;
             }
         } else {
             return Err(RendererError::Generic("Scene render target view not available".to_string()));
@@ -693,6 +961,31 @@ impl FrameRenderer for NovaWgpuRenderer {
         Ok(())
     }
 
+    fn apply_color_space_conversion(&mut self, lut: &Lut3d) -> Result<(), RendererError> {
+        // Output LUTs change rarely but are passed by reference, so they are uploaded per call.
+        let lut_bind_group = self.create_lut_bind_group(lut);
+        let encoder = self.current_encoder.as_mut().ok_or_else(|| RendererError::Generic("Encoder missing for ColorConversion".to_string()))?;
+        let input_view = if !self.post_processing_active { self.scene_render_target_view.as_ref() }
+                         else { self.post_processing_texture_views[self.current_pp_input_idx].as_ref() }
+                         .ok_or_else(|| RendererError::Generic("ColorConversion input view missing".to_string()))?;
+        let output_idx = if self.post_processing_active { 1 - self.current_pp_input_idx } else { 0 };
+        let output_view = self.post_processing_texture_views[output_idx].as_ref().unwrap();
+
+        let pipeline = self.color_conversion_pipeline.as_ref().unwrap();
+        let texture_bgl = self.post_processing_texture_bgl.as_ref().unwrap();
+        let tex_bg = self.device.create_bind_group(&wgpu::BindGroupDescriptor { label: Some("ColorConversion Source BG"), layout: texture_bgl, entries: &[ wgpu::BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(input_view) }, wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::Sampler(self.default_sampler.as_ref()) } ]});
+
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor { label: Some("ColorConversion Pass"), color_attachments: &[Some(wgpu::RenderPassColorAttachment { view: output_view, resolve_target: None, ops: wgpu::Operations { load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT), store: wgpu::StoreOp::Store }})], ..Default::default()});
+        rpass.set_pipeline(pipeline);
+        rpass.set_bind_group(0, &tex_bg, &[]);
+        rpass.set_bind_group(1, &lut_bind_group, &[]);
+        rpass.draw(0..6, 0..1);
+        drop(rpass);
+        self.current_pp_input_idx = output_idx;
+        self.post_processing_active = true;
+        Ok(())
+    }
+
     // TODO [CustomPostProcessingEffects]: To add a new post-processing effect (e.g., "InvertColors"):
     // 1. Create a new fragment shader (e.g., `assets/shaders/invert_colors.frag`).
     //    It would sample an input texture and output inverted colors.
@@ -730,7 +1023,7 @@ impl FrameRenderer for NovaWgpuRenderer {
     // e. Ensure proper synchronization with rendering passes if compute outputs are used in rendering (barriers).
     // ANCHOR [ComputeShaderIntegrationOutline]
 
-    fn upload_surface_texture( &mut self, _surface_id: SurfaceId, client_buffer: &ClientBuffer<'_>, ) -> Result<Box<dyn RenderableTexture>, RendererError> {
+    fn upload_surface_texture( &mut self, surface_id: SurfaceId, client_buffer: &ClientBuffer<'_>, ) -> Result<Box<dyn RenderableTexture>, RendererError> {
         match &client_buffer.content {
             BufferContent::Shm { id, data, width, height, stride, format } => {
                 let wgpu_texture_format = match format {
@@ -764,7 +1057,7 @@ impl FrameRenderer for NovaWgpuRenderer {
                 )))
             }
             BufferContent::Dmabuf { id, descriptors, width: buffer_overall_width, height: buffer_overall_height } => {
-                tracing::info!( "Attempting DMABUF texture upload for surface_id: {}, buffer_id: {}, overall_size: {}x{}", _surface_id.id(), id, buffer_overall_width, buffer_overall_height);
+                tracing::info!( "Attempting DMABUF texture upload for surface_id: {}, buffer_id: {}, overall_size: {}x{}", surface_id.protocol_id(), id, buffer_overall_width, buffer_overall_height);
 
                 // ANCHOR [DmabufImportMultiPlane]
                 // This section attempts to import multiple DMABUF planes.
@@ -790,7 +1083,7 @@ impl FrameRenderer for NovaWgpuRenderer {
                         if idx == 0 { primary_plane_format_wgpu = Some(plane_texture_format_wgpu); }
 
                         let plane_texture_descriptor = wgpu::TextureDescriptor {
-                            label: Some(&format!("dmabuf_surface_{}_b{}_p{}", _surface_id.id(), id, desc.plane_index)),
+                            label: Some(&format!("dmabuf_surface_{}_b{}_p{}", surface_id.protocol_id(), id, desc.plane_index)),
                             size: wgpu::Extent3d { width: desc.width, height: desc.height, depth_or_array_layers: 1 },
                             mip_level_count: 1, sample_count: 1, dimension: wgpu::TextureDimension::D2,
                             format: plane_texture_format_wgpu,
@@ -829,7 +1122,7 @@ impl FrameRenderer for NovaWgpuRenderer {
                 }
 
                 if plane_wgpu_textures.is_empty() {
-                    tracing::error!("No valid DMABUF planes found for surface {}, buffer {}", _surface_id.id(), id);
+                    tracing::error!("No valid DMABUF planes found for surface {}, buffer {}", surface_id.protocol_id(), id);
                     return Err(RendererError::Generic("No valid DMABUF planes processed".to_string()));
                 }
 
@@ -868,7 +1161,7 @@ impl FrameRenderer for NovaWgpuRenderer {
                 let primary_descriptor = match descriptors[0] {
                     Some(ref desc) => desc,
                     None => {
-                        eprintln!("DMABUF import error for surface {}: No primary plane descriptor provided.", _surface_id.id());
+                        eprintln!("DMABUF import error for surface {}: No primary plane descriptor provided.", surface_id.protocol_id());
                         return Err(RendererError::Generic("Missing primary DMABUF descriptor".to_string()));
                     }
                 };
@@ -882,13 +1175,13 @@ impl FrameRenderer for NovaWgpuRenderer {
                     DmabufPlaneFormat::Xrgb8888 => wgpu::TextureFormat::Bgra8Unorm,
                     // Add other single-plane formats if necessary, e.g. R8, Rg8 etc.
                     _ => {
-                        eprintln!("DMABUF import error for surface {}: Unsupported single-plane format: {:?}", _surface_id.id(), primary_descriptor.format);
+                        eprintln!("DMABUF import error for surface {}: Unsupported single-plane format: {:?}", surface_id.protocol_id(), primary_descriptor.format);
                         return Err(RendererError::Generic(format!("Unsupported DMABUF single-plane format: {:?}", primary_descriptor.format)));
                     }
                 };
 
                 let texture_descriptor = wgpu::TextureDescriptor {
-                    label: Some(&format!("dmabuf_surface_{}_b{}", _surface_id.id(), id)),
+                    label: Some(&format!("dmabuf_surface_{}_b{}", surface_id.protocol_id(), id)),
                     size: wgpu::Extent3d {
                         width: primary_descriptor.width,
                         height: primary_descriptor.height,
@@ -957,11 +1250,11 @@ impl FrameRenderer for NovaWgpuRenderer {
 
                 // Fallback to magenta placeholder if import_texture is None
                 let final_texture = if let Some(tex) = imported_texture {
-                    tracing::info!("DMABUF for surface {} buffer {} successfully imported (conceptually).", _surface_id.id(), id);
+                    tracing::info!("DMABUF for surface {} buffer {} successfully imported (conceptually).", surface_id.protocol_id(), id);
                     tex
                 } else {
                     // ANCHOR [DmabufUploadPlaceholderActive]
-                    tracing::warn!("DMABUF import for surface {} buffer {} failed or not implemented, using placeholder.", _surface_id.id(), id);
+                    tracing::warn!("DMABUF import for surface {} buffer {} failed or not implemented, using placeholder.", surface_id.protocol_id(), id);
 
                     let placeholder_texture = self.device.create_texture(&texture_descriptor); // Use the descriptor already defined
 
@@ -0,0 +1,192 @@
+// novade-system/src/sound_theme.rs
+
+//! Lookup of sounds by name in freedesktop sound themes.
+//!
+//! Themes live in `sounds/` below the XDG data directories. A theme's `index.theme` lists the
+//! directories holding its sounds and the themes it inherits from; every chain ends with the
+//! `freedesktop` theme. A name that no theme has is retried without its last dash-separated
+//! part, so `message-new-instant` falls back to `message-new` and then `message`.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// The theme every theme falls back to.
+pub const FALLBACK_SOUND_THEME: &str = "freedesktop";
+
+const SOUND_EXTENSIONS: [&str; 3] = ["oga", "ogg", "wav"];
+/// Directory searched in themes without an `index.theme`.
+const DEFAULT_SUBDIRECTORY: &str = "stereo";
+
+#[derive(Debug, Clone)]
+pub struct SoundTheme {
+    name: String,
+    /// `sounds/` directories, most important first.
+    base_dirs: Vec<PathBuf>,
+}
+
+/// What a theme's `index.theme` says.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct ThemeIndex {
+    inherits: Vec<String>,
+    directories: Vec<String>,
+}
+
+impl SoundTheme {
+    /// The theme `name`, searched in `$XDG_DATA_HOME/sounds` and `$XDG_DATA_DIRS/sounds`.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self::with_base_dirs(name, default_base_dirs())
+    }
+
+    pub fn with_base_dirs(name: impl Into<String>, base_dirs: Vec<PathBuf>) -> Self {
+        Self { name: name.into(), base_dirs }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The file of the sound `name`, if this theme, a theme it inherits from or the unthemed
+    /// sounds have it.
+    pub fn lookup(&self, name: &str) -> Option<PathBuf> {
+        let themes = self.inheritance_chain();
+        let mut candidate = name;
+        loop {
+            for (theme, index) in &themes {
+                if let Some(path) = self.find_in_theme(theme, index, candidate) {
+                    return Some(path);
+                }
+            }
+            if let Some(path) = self.base_dirs.iter().find_map(|dir| find_sound_file(dir, candidate)) {
+                return Some(path);
+            }
+            candidate = &candidate[..candidate.rfind('-')?];
+        }
+    }
+
+    /// This theme and the ones it inherits from, breadth first, ending with the fallback theme.
+    fn inheritance_chain(&self) -> Vec<(String, ThemeIndex)> {
+        let mut chain = Vec::new();
+        let mut seen = HashSet::new();
+        let mut pending = vec![self.name.clone()];
+        while !pending.is_empty() {
+            let mut next = Vec::new();
+            for theme in pending {
+                if !seen.insert(theme.clone()) {
+                    continue;
+                }
+                let Some(index) = self.read_index(&theme) else {
+                    tracing::debug!("Sound theme '{}' is not installed", theme);
+                    continue;
+                };
+                next.extend(index.inherits.iter().cloned());
+                chain.push((theme, index));
+            }
+            pending = next;
+        }
+        if !seen.contains(FALLBACK_SOUND_THEME) {
+            if let Some(index) = self.read_index(FALLBACK_SOUND_THEME) {
+                chain.push((FALLBACK_SOUND_THEME.to_string(), index));
+            }
+        }
+        chain
+    }
+
+    /// The index of an installed theme; themes without `index.theme` use the default directory.
+    fn read_index(&self, theme: &str) -> Option<ThemeIndex> {
+        let dir = self.base_dirs.iter().map(|base| base.join(theme)).find(|dir| dir.is_dir())?;
+        let index = match std::fs::read_to_string(dir.join("index.theme")) {
+            Ok(contents) => parse_index(&contents),
+            Err(_) => ThemeIndex::default(),
+        };
+        Some(index)
+    }
+
+    fn find_in_theme(&self, theme: &str, index: &ThemeIndex, name: &str) -> Option<PathBuf> {
+        let default_directories = [DEFAULT_SUBDIRECTORY.to_string()];
+        let directories = if index.directories.is_empty() { &default_directories[..] } else { &index.directories[..] };
+        self.base_dirs
+            .iter()
+            .flat_map(|base| directories.iter().map(move |directory| base.join(theme).join(directory)))
+            .find_map(|dir| find_sound_file(&dir, name))
+    }
+}
+
+fn find_sound_file(dir: &Path, name: &str) -> Option<PathBuf> {
+    SOUND_EXTENSIONS.iter().map(|extension| dir.join(format!("{}.{}", name, extension))).find(|path| path.is_file())
+}
+
+/// Reads `Inherits` and `Directories` of the `[Sound Theme]` group.
+fn parse_index(contents: &str) -> ThemeIndex {
+    let mut index = ThemeIndex::default();
+    let mut in_theme_group = false;
+    for line in contents.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_theme_group = line == "[Sound Theme]";
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=').filter(|_| in_theme_group) else { continue };
+        let list = || value.split(',').map(str::trim).filter(|item| !item.is_empty()).map(str::to_string).collect();
+        match key.trim() {
+            "Inherits" => index.inherits = list(),
+            "Directories" => index.directories = list(),
+            _ => {}
+        }
+    }
+    index
+}
+
+fn default_base_dirs() -> Vec<PathBuf> {
+    let data_home = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")));
+    let data_dirs = std::env::var("XDG_DATA_DIRS").ok().filter(|dirs| !dirs.is_empty()).unwrap_or_else(|| "/usr/local/share:/usr/share".to_string());
+    data_home
+        .into_iter()
+        .chain(data_dirs.split(':').filter(|dir| !dir.is_empty()).map(PathBuf::from))
+        .map(|dir| dir.join("sounds"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn install(base: &Path, relative: &str, contents: &str) -> PathBuf {
+        let path = base.join(relative);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_lookup_follows_inheritance_and_name_fallback() {
+        let (user, system) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        install(user.path(), "ocean/index.theme", "[Sound Theme]\nName=Ocean\nInherits=waves\nDirectories=stereo, 5.1\n\n[stereo]\nOutputProfile=stereo\n");
+        let bell = install(user.path(), "ocean/5.1/bell.oga", "");
+        install(system.path(), "waves/index.theme", "[Sound Theme]\nName=Waves\n");
+        let complete = install(system.path(), "waves/stereo/complete.ogg", "");
+        let message = install(system.path(), "freedesktop/stereo/message.oga", "");
+        let instant = install(system.path(), "freedesktop/stereo/message-new-instant.oga", "");
+        let unthemed = install(system.path(), "camera-shutter.wav", "");
+
+        let theme = SoundTheme::with_base_dirs("ocean", vec![user.path().to_path_buf(), system.path().to_path_buf()]);
+        assert_eq!(theme.lookup("bell"), Some(bell));
+        assert_eq!(theme.lookup("complete"), Some(complete));
+        assert_eq!(theme.lookup("message-new-instant"), Some(instant.clone()));
+        assert_eq!(theme.lookup("message-new-email"), Some(message));
+        assert_eq!(theme.lookup("camera-shutter"), Some(unthemed));
+        assert_eq!(theme.lookup("alarm-clock-elapsed"), None);
+
+        // An uninstalled theme still gets the fallback theme.
+        let missing = SoundTheme::with_base_dirs("missing", vec![system.path().to_path_buf()]);
+        assert_eq!(missing.lookup("message-new-instant"), Some(instant));
+        assert_eq!(missing.lookup("bell"), None);
+    }
+
+    #[test]
+    fn test_parse_index() {
+        let index = parse_index("[Sound Theme]\nInherits=a, b\nDirectories=stereo\n[stereo]\nInherits=ignored\n");
+        assert_eq!(index, ThemeIndex { inherits: vec!["a".to_string(), "b".to_string()], directories: vec!["stereo".to_string()] });
+    }
+}
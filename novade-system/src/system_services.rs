@@ -3,6 +3,9 @@
 use crate::dbus_integration::manager::DbusServiceManager;
 use crate::power_management::{SystemPowerManager, PowerManager};
 use crate::network_manager::{NetworkManagerIntegration, NetworkManager}; // NetworkDBusConnection removed as it's internal to NMI::new_production
+use crate::audio_management::{AudioManager, NullAudioManager, PulseAudioManager};
+use crate::audio_profiles::AudioProfileRouter;
+use crate::notification_sounds::{NotificationSoundConfig, NotificationSoundPlayer};
 use novade_domain::DomainServices;
 use std::sync::Arc;
 use crate::error::{SystemResult, SystemError, SystemErrorKind};
@@ -19,8 +22,12 @@ use crate::error::{SystemResult, SystemError, SystemErrorKind};
 #[derive(Clone)]
 pub struct SystemServices {
     pub dbus_manager: Arc<DbusServiceManager>,
+    /// Session bus connection serving `org.freedesktop.Notifications`; `None` without a
+    /// session bus.
+    pub session_dbus_manager: Option<Arc<DbusServiceManager>>,
     pub power_manager: Arc<dyn PowerManager>,
     pub network_manager: Arc<dyn NetworkManager>,
+    pub audio_manager: Arc<dyn AudioManager>,
     // pub domain_services: Arc<DomainServices>, // Optionally store if needed
 }
 
@@ -56,22 +63,66 @@ impl SystemServices {
         })?);
         tracing::info!("NetworkManagerIntegration initialized.");
 
-        // 4. Serve NotificationsServer using DbusServiceManager
-        if let Some(notification_service) = domain_services.notification_service.as_ref() {
-            let notification_service_arc = Arc::clone(notification_service);
-            if let Err(e) = dbus_manager.serve_notifications_server(notification_service_arc).await {
-                tracing::error!("Failed to serve NotificationsServer: {}. Notifications D-Bus service will not be available.", e);
-            } else {
-                tracing::info!("NotificationsServer is being served by DbusServiceManager.");
+        // 4. Keep the notification history on disk, before clients can ask for it
+        match crate::notification_history::SqliteNotificationHistoryStore::open_default() {
+            Ok(store) => match domain_services.notification_service.attach_history_store(Arc::new(store)).await {
+                Ok(count) => tracing::info!("Notification history restored with {} entries.", count),
+                Err(e) => tracing::error!("Failed to restore the notification history: {}", e),
+            },
+            Err(e) => tracing::error!("Notification history store unavailable, history will not persist: {}", e),
+        }
+
+        // 5. Serve NotificationsServer on the session bus
+        let session_dbus_manager = match DbusServiceManager::new_session().await {
+            Ok(manager) => {
+                let notification_service = domain_services.notification_service.clone();
+                if let Err(e) = manager.serve_notifications_server(notification_service).await {
+                    tracing::error!("Failed to serve NotificationsServer: {}. Notifications D-Bus service will not be available.", e);
+                } else {
+                    tracing::info!("NotificationsServer is being served on the session bus.");
+                }
+                Some(Arc::new(manager))
+            }
+            Err(e) => {
+                tracing::warn!("No session bus, notifications D-Bus service disabled: {}", e);
+                None
+            }
+        };
+
+        // 6. Follow the compositor and the battery for focus modes and notification rules
+        crate::notification_context::spawn_notification_context(
+            domain_services.notification_service.clone(),
+            system_power_manager.clone() as Arc<dyn PowerManager>,
+        );
+
+        // 7. Connect to the sound server and play notification sounds
+        let audio_manager: Arc<dyn AudioManager> = match PulseAudioManager::new().await {
+            Ok(manager) => Arc::new(manager),
+            Err(e) => {
+                tracing::warn!("No sound server, audio control and sounds disabled: {}", e);
+                Arc::new(NullAudioManager::new())
+            }
+        };
+        crate::notification_sounds::spawn_notification_sounds(
+            domain_services.notification_service.clone(),
+            NotificationSoundPlayer::new(audio_manager.clone(), NotificationSoundConfig::default()),
+        );
+
+        // 8. Route applications to their remembered devices and let the shell's mixer change them
+        let audio_profiles = Arc::new(AudioProfileRouter::new(audio_manager.clone(), domain_services.settings_service.clone()).await);
+        crate::audio_profiles::spawn_audio_profiles(audio_profiles.clone());
+        if let Some(manager) = &session_dbus_manager {
+            if let Err(e) = manager.serve_audio_profiles(audio_profiles).await {
+                tracing::error!("Failed to serve AudioProfiles: {}. The shell's mixer will not remember per-app settings.", e);
             }
-        } else {
-            tracing::warn!("DomainNotificationService not available, cannot serve D-Bus NotificationsServer.");
         }
 
         Ok(Self {
             dbus_manager,
+            session_dbus_manager,
             power_manager: system_power_manager as Arc<dyn PowerManager>,
             network_manager: network_manager as Arc<dyn NetworkManager>,
+            audio_manager,
             // domain_services, // Optionally store
         })
     }
@@ -1,12 +1,14 @@
 // novade-system/src/window_mechanics/data_types.rs
 
 use novade_core::types::geometry::{Point, Size};
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 /// Unique identifier for a window.
 ///
 /// Wraps a `uuid::Uuid` to provide strong typing.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(transparent)]
 pub struct WindowId(Uuid);
 
 impl WindowId {
@@ -14,6 +16,16 @@ impl WindowId {
     pub fn new_v4() -> Self {
         WindowId(Uuid::new_v4())
     }
+
+    /// Wraps an existing UUID, e.g. a compositor `DomainWindowIdentifier`.
+    pub fn from_uuid(uuid: Uuid) -> Self {
+        WindowId(uuid)
+    }
+
+    /// Returns the underlying UUID.
+    pub fn as_uuid(&self) -> Uuid {
+        self.0
+    }
 }
 
 impl Default for WindowId {
@@ -31,7 +43,7 @@ impl std::fmt::Display for WindowId {
 /// Represents the geometry of a window using floating-point coordinates and dimensions.
 ///
 /// This uses `Point<f64>` for origin and `Size<f64>` for dimensions from `novade_core`.
-#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
 pub struct WindowRect {
     /// The top-left corner of the window.
     pub origin: Point<f64>,
@@ -50,7 +62,8 @@ impl WindowRect {
 }
 
 /// Represents the various states a window can be in.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum WindowState {
     /// The window is managed by a tiling algorithm.
     Tiled,
@@ -74,7 +87,7 @@ impl Default for WindowState {
 ///
 /// This structure holds metadata like the window's ID, title, geometry,
 /// and its current state (e.g., tiled, floating).
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct WindowInfo {
     /// Unique identifier for this window.
     pub id: WindowId,
@@ -97,7 +110,8 @@ impl WindowInfo {
 /// Unique identifier for a workspace.
 ///
 /// Wraps a `uuid::Uuid` for type safety.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(transparent)]
 pub struct WorkspaceId(Uuid);
 
 impl WorkspaceId {
@@ -105,6 +119,16 @@ impl WorkspaceId {
     pub fn new_v4() -> Self {
         WorkspaceId(Uuid::new_v4())
     }
+
+    /// Wraps an existing UUID.
+    pub fn from_uuid(uuid: Uuid) -> Self {
+        WorkspaceId(uuid)
+    }
+
+    /// Returns the underlying UUID.
+    pub fn as_uuid(&self) -> Uuid {
+        self.0
+    }
 }
 
 impl Default for WorkspaceId {
@@ -120,7 +144,8 @@ impl std::fmt::Display for WorkspaceId {
 }
 
 /// Represents the available tiling layout algorithms.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum TilingLayout {
     /// A layout that typically has a main "master" area and a stacking area.
     Tall,
@@ -141,7 +166,7 @@ impl Default for TilingLayout {
 ///
 /// This includes its ID, a human-readable name, the list of windows it contains,
 /// and the current tiling layout algorithm being applied.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct WorkspaceInfo {
     /// Unique identifier for this workspace.
     pub id: WorkspaceId,
@@ -170,7 +195,7 @@ impl WorkspaceInfo {
 /// Contains information about a connected display screen.
 ///
 /// This primarily includes its resolution.
-#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
 pub struct ScreenInfo {
     /// The width of the screen in pixels (or logical units if scaled).
     pub width: f64,
@@ -274,6 +299,17 @@ mod tests {
         assert_eq!(info.windows[0], window_id);
     }
 
+    #[test]
+    fn window_info_serde_roundtrip() {
+        let id = WindowId::new_v4();
+        let info = WindowInfo::new(id, "Term".to_string(), WindowRect::new(1.0, 2.0, 3.0, 4.0), WindowState::Maximized);
+        let json = serde_json::to_value(&info).unwrap();
+        assert_eq!(json["id"], serde_json::json!(id.as_uuid().to_string()));
+        assert_eq!(json["state"], "maximized");
+        let back: WindowInfo = serde_json::from_value(json).unwrap();
+        assert_eq!(back, info);
+    }
+
     #[test]
     fn screen_info_creation() {
         let screen = ScreenInfo::new(1920.0, 1080.0);
@@ -0,0 +1,685 @@
+// novade-system/tests/notifications_server_test.rs
+
+//! Talks to the `org.freedesktop.Notifications` server as a client would, over a private
+//! session bus started for the test, with a recording fake in place of the domain service.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use futures_util::StreamExt;
+use novade_domain::{
+    group_key, group_notifications, search_terms, ActiveFocus, ApplicationId, DismissReason, FocusActivation, FocusMode,
+    FocusTrigger, HistoryExport, HistoryRetention, MissedSummary, NotificationHistoryStore, Notification, NotificationError, NotificationEventEnum, NotificationFilterCriteria,
+    NotificationGroup, NotificationInput, NotificationRule, NotificationRuleSet, NotificationService,
+    NotificationSortOrder, NotificationStats, NotificationUrgency, RuleCondition, RuleConditionField,
+    RuleConditionOperator, RuleConditionValue, RuleContextChange, RuleSimulation, RuleSimulationSample, RuleValidationIssue,
+    SimpleRuleCondition, SimulationOutcome, THREAD_ID_HINT,
+};
+use novade_system::dbus_interfaces::notifications_server::{
+    CenterAccess, NotificationsDBusService, NOTIFICATIONS_SERVICE_NAME, NOTIFICATION_CENTER_SERVICE_NAME, SHELL_EXECUTABLES,
+};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader};
+use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::time::timeout;
+use uuid::Uuid;
+use zbus::zvariant::{StructureBuilder, Value};
+use zbus::{dbus_proxy, Connection, ConnectionBuilder};
+
+// ANCHOR: TestProxy
+#[dbus_proxy(
+    interface = "org.freedesktop.Notifications",
+    default_service = "org.freedesktop.Notifications",
+    default_path = "/org/freedesktop/Notifications"
+)]
+trait Notifications {
+    #[allow(clippy::too_many_arguments)]
+    fn notify(
+        &self,
+        app_name: &str,
+        replaces_id: u32,
+        app_icon: &str,
+        summary: &str,
+        body: &str,
+        actions: &[&str],
+        hints: HashMap<&str, Value<'_>>,
+        expire_timeout: i32,
+    ) -> zbus::Result<u32>;
+    fn close_notification(&self, id: u32) -> zbus::Result<()>;
+    fn get_capabilities(&self) -> zbus::Result<Vec<String>>;
+    fn get_server_information(&self) -> zbus::Result<(String, String, String, String)>;
+
+    #[dbus_proxy(signal)]
+    fn notification_closed(&self, id: u32, reason: u32) -> zbus::Result<()>;
+    #[dbus_proxy(signal)]
+    fn action_invoked(&self, id: u32, action_key: String) -> zbus::Result<()>;
+    #[dbus_proxy(signal)]
+    fn notification_replied(&self, id: u32, text: String) -> zbus::Result<()>;
+    #[dbus_proxy(signal)]
+    fn activation_token(&self, id: u32, activation_token: String) -> zbus::Result<()>;
+}
+
+#[dbus_proxy(
+    interface = "org.novade.NotificationCenter",
+    default_service = "org.novade.NotificationCenter",
+    default_path = "/org/novade/NotificationCenter"
+)]
+trait NotificationCenter {
+    fn get_groups(&self) -> zbus::Result<String>;
+    fn dismiss_group(&self, group_key: &str) -> zbus::Result<u32>;
+    fn get_focus_modes(&self) -> zbus::Result<String>;
+    fn get_focus_state(&self) -> zbus::Result<String>;
+    fn activate_focus_mode(&self, mode_id: &str) -> zbus::Result<()>;
+    fn get_rules(&self) -> zbus::Result<String>;
+    fn set_rules(&self, rules: &str) -> zbus::Result<()>;
+    fn validate_rules(&self, rules: &str) -> zbus::Result<String>;
+    fn simulate_rules(&self, sample: &str, rules: &str) -> zbus::Result<String>;
+    fn reply(&self, notification_id: &str, text: &str) -> zbus::Result<()>;
+    fn search_history(&self, query: &str, offset: u32, limit: u32) -> zbus::Result<String>;
+    fn export_history(&self) -> zbus::Result<String>;
+    fn get_history_retention(&self) -> zbus::Result<String>;
+    fn set_history_retention(&self, retention: &str) -> zbus::Result<()>;
+
+    #[dbus_proxy(signal)]
+    fn groups_changed(&self) -> zbus::Result<()>;
+    #[dbus_proxy(signal)]
+    fn popup_requested(&self, notification: String) -> zbus::Result<()>;
+    #[dbus_proxy(signal)]
+    fn popup_closed(&self, notification_id: String) -> zbus::Result<()>;
+    #[dbus_proxy(signal)]
+    fn focus_changed(&self, active: String) -> zbus::Result<()>;
+    #[dbus_proxy(signal)]
+    fn focus_ended(&self, summary: String) -> zbus::Result<()>;
+}
+
+// ANCHOR: PrivateBus
+/// A `dbus-daemon --session` of our own, stopped when dropped.
+struct PrivateBus {
+    daemon: Child,
+    address: String,
+}
+
+impl PrivateBus {
+    fn start() -> Result<Self> {
+        let mut daemon = Command::new("dbus-daemon")
+            .args(["--session", "--nofork", "--print-address"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let mut address = String::new();
+        BufReader::new(daemon.stdout.take().expect("piped stdout")).read_line(&mut address)?;
+        Ok(Self { daemon, address: address.trim().to_string() })
+    }
+
+    async fn connect(&self) -> Result<Connection> {
+        Ok(ConnectionBuilder::address(self.address.as_str())?.build().await?)
+    }
+}
+
+impl Drop for PrivateBus {
+    fn drop(&mut self) {
+        let _ = self.daemon.kill();
+        let _ = self.daemon.wait();
+    }
+}
+
+// ANCHOR: FakeNotificationService
+/// Keeps posted notifications and publishes the events the real service would.
+struct FakeNotificationService {
+    notifications: Mutex<Vec<Notification>>,
+    focus: Mutex<Option<ActiveFocus>>,
+    rules: Mutex<NotificationRuleSet>,
+    retention: Mutex<HistoryRetention>,
+    events: broadcast::Sender<NotificationEventEnum>,
+}
+
+impl FakeNotificationService {
+    fn new() -> Self {
+        Self {
+            notifications: Mutex::new(Vec::new()),
+            focus: Mutex::new(None),
+            rules: Mutex::new(Vec::new()),
+            retention: Mutex::new(HistoryRetention::default()),
+            events: broadcast::channel(32).0,
+        }
+    }
+
+    fn notification_from(input: NotificationInput) -> Notification {
+        let mut notification = Notification::new(input.application_name, input.summary, input.urgency.unwrap_or_default());
+        notification.id = input.replaces_id.unwrap_or(notification.id);
+        notification.application_icon = input.application_icon;
+        notification.body = input.body;
+        notification.actions = input.actions.unwrap_or_default();
+        notification.transient = input.transient.unwrap_or(false);
+        notification.category = input.category;
+        notification.hints = input.hints.unwrap_or_default();
+        notification.timeout_ms = input.timeout_ms;
+        notification.image = input.image;
+        notification
+    }
+
+    fn only(&self) -> Notification {
+        let notifications = self.notifications.lock().unwrap();
+        assert_eq!(notifications.len(), 1, "expected exactly one open notification");
+        notifications[0].clone()
+    }
+
+    fn last(&self) -> Notification {
+        self.notifications.lock().unwrap().last().cloned().expect("a notification")
+    }
+}
+
+#[async_trait]
+impl NotificationService for FakeNotificationService {
+    async fn post_notification(&self, input: NotificationInput) -> Result<Uuid, NotificationError> {
+        let notification = Self::notification_from(input);
+        let mut notifications = self.notifications.lock().unwrap();
+        let event = match notifications.iter_mut().find(|n| n.id == notification.id) {
+            Some(existing) => {
+                *existing = notification.clone();
+                NotificationEventEnum::NotificationUpdated { notification: notification.clone() }
+            }
+            None => {
+                notifications.push(notification.clone());
+                NotificationEventEnum::NotificationPosted { notification: notification.clone(), suppressed_by_dnd: false }
+            }
+        };
+        let _ = self.events.send(event);
+        Ok(notification.id)
+    }
+
+    async fn get_notification(&self, id: Uuid) -> Result<Option<Notification>, NotificationError> {
+        Ok(self.notifications.lock().unwrap().iter().find(|n| n.id == id).cloned())
+    }
+
+    async fn mark_as_read(&self, _id: Uuid) -> Result<(), NotificationError> {
+        Ok(())
+    }
+
+    async fn dismiss_notification(&self, id: Uuid, reason: DismissReason) -> Result<(), NotificationError> {
+        let mut notifications = self.notifications.lock().unwrap();
+        let index = notifications.iter().position(|n| n.id == id).ok_or(NotificationError::NotFound(id))?;
+        notifications.remove(index);
+        let _ = self.events.send(NotificationEventEnum::NotificationDismissed { notification_id: id, reason });
+        Ok(())
+    }
+
+    async fn get_active_notifications(
+        &self,
+        _filter: Option<&NotificationFilterCriteria>,
+        _sort_order: Option<NotificationSortOrder>,
+    ) -> Result<Vec<Notification>, NotificationError> {
+        Ok(self.notifications.lock().unwrap().clone())
+    }
+
+    async fn get_notification_history(
+        &self,
+        _limit: Option<usize>,
+        _offset: Option<usize>,
+        _filter: Option<&NotificationFilterCriteria>,
+        _sort_order: Option<NotificationSortOrder>,
+    ) -> Result<Vec<Notification>, NotificationError> {
+        Ok(Vec::new())
+    }
+
+    async fn clear_history(&self) -> Result<(), NotificationError> {
+        Ok(())
+    }
+
+    /// Searches the open notifications, which stand in for the history.
+    async fn search_history(&self, query: &str, limit: Option<usize>, offset: Option<usize>) -> Result<Vec<Notification>, NotificationError> {
+        let terms = search_terms(query);
+        let notifications = self.notifications.lock().unwrap();
+        Ok(notifications
+            .iter()
+            .filter(|n| {
+                let words = search_terms(&format!("{} {}", n.summary, n.body.as_deref().unwrap_or("")));
+                terms.iter().all(|term| words.iter().any(|word| word.starts_with(term.as_str())))
+            })
+            .skip(offset.unwrap_or(0))
+            .take(limit.unwrap_or(usize::MAX))
+            .cloned()
+            .collect())
+    }
+
+    async fn export_history(&self, _filter: Option<&NotificationFilterCriteria>) -> Result<HistoryExport, NotificationError> {
+        let notifications = self.notifications.lock().unwrap().clone();
+        Ok(HistoryExport { version: novade_domain::HISTORY_EXPORT_VERSION, exported_at: chrono::Utc::now(), notifications })
+    }
+
+    async fn get_history_retention(&self) -> Result<HistoryRetention, NotificationError> {
+        Ok(self.retention.lock().unwrap().clone())
+    }
+
+    async fn set_history_retention(&self, retention: HistoryRetention) -> Result<(), NotificationError> {
+        *self.retention.lock().unwrap() = retention;
+        Ok(())
+    }
+
+    async fn attach_history_store(&self, _store: Arc<dyn NotificationHistoryStore>) -> Result<usize, NotificationError> {
+        Ok(0)
+    }
+
+    async fn clear_all_for_app(&self, _app_id: &ApplicationId, _reason: DismissReason) -> Result<usize, NotificationError> {
+        Ok(0)
+    }
+
+    async fn set_do_not_disturb(&self, enabled: bool) -> Result<(), NotificationError> {
+        self.activate_focus_mode(enabled.then_some("dnd")).await
+    }
+
+    async fn is_do_not_disturb_enabled(&self) -> Result<bool, NotificationError> {
+        Ok(self.focus.lock().unwrap().is_some())
+    }
+
+    async fn get_focus_modes(&self) -> Result<Vec<FocusMode>, NotificationError> {
+        Ok(vec![FocusMode::do_not_disturb(), FocusMode::new("meeting", "Meeting")])
+    }
+
+    async fn set_focus_modes(&self, _modes: Vec<FocusMode>) -> Result<(), NotificationError> {
+        Ok(())
+    }
+
+    async fn activate_focus_mode(&self, mode_id: Option<&str>) -> Result<(), NotificationError> {
+        let modes = self.get_focus_modes().await?;
+        let active = match mode_id {
+            Some(id) => {
+                let mode = modes.iter().find(|mode| mode.id == id).ok_or_else(|| NotificationError::InvalidInputData {
+                    field: "mode_id".to_string(),
+                    reason: format!("no focus mode '{}'", id),
+                })?;
+                let since = chrono::Utc::now();
+                Some(ActiveFocus { mode_id: mode.id.clone(), mode_name: mode.name.clone(), activation: FocusActivation::Manual, since })
+            }
+            None => None,
+        };
+        let previous = std::mem::replace(&mut *self.focus.lock().unwrap(), active.clone());
+        if let Some(previous) = previous {
+            let summary = MissedSummary {
+                mode_id: previous.mode_id,
+                mode_name: previous.mode_name,
+                since: previous.since,
+                until: chrono::Utc::now(),
+                total: 0,
+                by_app: Default::default(),
+            };
+            let _ = self.events.send(NotificationEventEnum::FocusModeEnded { summary });
+        }
+        let _ = self.events.send(NotificationEventEnum::FocusModeChanged { active });
+        Ok(())
+    }
+
+    async fn get_active_focus(&self) -> Result<Option<ActiveFocus>, NotificationError> {
+        Ok(self.focus.lock().unwrap().clone())
+    }
+
+    async fn set_focus_trigger(&self, _trigger: FocusTrigger, _active: bool) -> Result<(), NotificationError> {
+        Ok(())
+    }
+
+    async fn refresh_focus(&self) -> Result<(), NotificationError> {
+        Ok(())
+    }
+
+    async fn invoke_action(&self, id: Uuid, key: &str, activation_token: Option<String>) -> Result<(), NotificationError> {
+        let _ = self.events.send(NotificationEventEnum::NotificationActionInvoked {
+            notification_id: id,
+            action_key: key.to_string(),
+            activation_token,
+        });
+        Ok(())
+    }
+
+    async fn reply(&self, id: Uuid, text: &str) -> Result<(), NotificationError> {
+        let _ = self.events.send(NotificationEventEnum::NotificationReplied { notification_id: id, text: text.to_string() });
+        Ok(())
+    }
+
+    async fn get_stats(&self) -> Result<NotificationStats, NotificationError> {
+        Ok(NotificationStats::default())
+    }
+
+    async fn get_notification_groups(
+        &self,
+        _filter: Option<&NotificationFilterCriteria>,
+    ) -> Result<Vec<NotificationGroup>, NotificationError> {
+        Ok(group_notifications(self.notifications.lock().unwrap().clone()))
+    }
+
+    async fn dismiss_group(&self, key: &str, reason: DismissReason) -> Result<usize, NotificationError> {
+        let ids: Vec<Uuid> =
+            self.notifications.lock().unwrap().iter().filter(|n| group_key(n) == key).map(|n| n.id).collect();
+        for id in &ids {
+            self.dismiss_notification(*id, reason).await?;
+        }
+        Ok(ids.len())
+    }
+
+    async fn get_rules(&self) -> Result<NotificationRuleSet, NotificationError> {
+        Ok(self.rules.lock().unwrap().clone())
+    }
+
+    async fn update_rules(&self, rules: NotificationRuleSet) -> Result<(), NotificationError> {
+        *self.rules.lock().unwrap() = rules;
+        Ok(())
+    }
+
+    fn validate_rules(&self, rules: &NotificationRuleSet) -> Vec<RuleValidationIssue> {
+        novade_domain::validate_rules(rules)
+    }
+
+    /// Traces no rules; the sample passes through unchanged.
+    async fn simulate_rules(
+        &self,
+        sample: RuleSimulationSample,
+        _rules: Option<NotificationRuleSet>,
+    ) -> Result<RuleSimulation, NotificationError> {
+        let input = match sample {
+            RuleSimulationSample::Input(input) => Self::notification_from(input),
+            RuleSimulationSample::History(id) => self.get_notification(id).await?.ok_or(NotificationError::NotFound(id))?,
+        };
+        let outcome = SimulationOutcome::Allow { notification: Box::new(input.clone()) };
+        Ok(RuleSimulation { input, rules: Vec::new(), outcome })
+    }
+
+    async fn update_rule_context(&self, _change: RuleContextChange) -> Result<(), NotificationError> {
+        Ok(())
+    }
+
+    async fn release_snoozed(&self) -> Result<usize, NotificationError> {
+        Ok(0)
+    }
+
+    fn subscribe_to_notification_events(&self) -> broadcast::Receiver<NotificationEventEnum> {
+        self.events.subscribe()
+    }
+}
+
+/// The bus, the server's connection (kept for the test's duration), the fake behind it and a
+/// client proxy.
+/// The test process itself is the shell as far as the center's allow-list goes.
+async fn setup() -> Result<(PrivateBus, Connection, Arc<FakeNotificationService>, NotificationsProxy<'static>)> {
+    setup_with_access(CenterAccess::session()).await
+}
+
+async fn setup_with_access(
+    access: CenterAccess,
+) -> Result<(PrivateBus, Connection, Arc<FakeNotificationService>, NotificationsProxy<'static>)> {
+    let bus = PrivateBus::start()?;
+    let service = Arc::new(FakeNotificationService::new());
+    let server = bus.connect().await?;
+    NotificationsDBusService::serve(&server, service.clone(), access).await?;
+    server.request_name(NOTIFICATIONS_SERVICE_NAME).await?;
+    server.request_name(NOTIFICATION_CENTER_SERVICE_NAME).await?;
+
+    let client = bus.connect().await?;
+    let proxy = NotificationsProxy::new(&client).await?;
+    Ok((bus, server, service, proxy))
+}
+
+const WAIT: Duration = Duration::from_secs(5);
+
+// ANCHOR: TestNotifyDecodesHints
+#[tokio::test]
+async fn test_notify_decodes_hints_and_replaces() -> Result<()> {
+    let (_bus, _server, service, proxy) = setup().await?;
+
+    let capabilities = proxy.get_capabilities().await?;
+    assert!(capabilities.iter().any(|c| c == "body-markup") && capabilities.iter().any(|c| c == "actions"));
+    assert_eq!(proxy.get_server_information().await?.3, "1.2");
+
+    let image = StructureBuilder::new()
+        .add_field(1i32)
+        .add_field(1i32)
+        .add_field(4i32)
+        .add_field(true)
+        .add_field(8i32)
+        .add_field(4i32)
+        .add_field(vec![10u8, 20, 30, 40])
+        .build();
+    let mut hints = HashMap::new();
+    hints.insert("urgency", Value::U8(2));
+    hints.insert("value", Value::I32(42));
+    hints.insert("category", Value::from("transfer"));
+    hints.insert("image-data", Value::Structure(image));
+    let id = proxy
+        .notify("Files", 0, "", "Copying", "<b>1 file</b> <script>left</script>", &["default", "Open"], hints, -1)
+        .await?;
+    assert_ne!(id, 0);
+
+    let notification = service.only();
+    assert_eq!(notification.urgency, NotificationUrgency::Critical);
+    assert_eq!(notification.category.as_deref(), Some("transfer"));
+    assert_eq!(notification.hints["value"], serde_json::json!(42));
+    assert_eq!(notification.hints["urgency"], serde_json::json!(2));
+    assert_eq!(notification.body.as_deref(), Some("<b>1 file</b> left"));
+    assert_eq!(notification.actions[0].key, "default");
+    assert_eq!(notification.image.expect("image decoded").rgba, vec![10, 20, 30, 40]);
+
+    // Replacing keeps the id and updates the same domain notification.
+    let replaced = proxy.notify("Files", id, "", "Copied", "", &[], HashMap::new(), 0).await?;
+    assert_eq!(replaced, id);
+    assert_eq!(service.only().summary, "Copied");
+    assert_eq!(service.only().id, notification.id);
+
+    // An odd number of action strings is invalid.
+    assert!(proxy.notify("Files", 0, "", "Odd", "", &["default"], HashMap::new(), 0).await.is_err());
+    Ok(())
+}
+
+// ANCHOR: TestSignals
+#[tokio::test]
+async fn test_actions_and_closing_emit_signals() -> Result<()> {
+    let (_bus, _server, service, proxy) = setup().await?;
+    let mut closed = proxy.receive_notification_closed().await?;
+    let mut invoked = proxy.receive_action_invoked().await?;
+    let mut tokens = proxy.receive_activation_token().await?;
+
+    // An action: the activation token first, then the action, then the notification closes.
+    let id = proxy.notify("Chat", 0, "", "Hi", "", &["default", "Reply"], HashMap::new(), 0).await?;
+    service.invoke_action(service.last().id, "default", Some("token-1".to_string())).await?;
+    let token = timeout(WAIT, tokens.next()).await?.expect("ActivationToken").args()?;
+    assert_eq!((token.id, token.activation_token.as_str()), (id, "token-1"));
+    let action = timeout(WAIT, invoked.next()).await?.expect("ActionInvoked").args()?;
+    assert_eq!((action.id, action.action_key.as_str()), (id, "default"));
+    let close = timeout(WAIT, closed.next()).await?.expect("NotificationClosed").args()?;
+    assert_eq!((close.id, close.reason), (id, 2));
+
+    // CloseNotification reports reason 3; closing it again is not an error.
+    let id = proxy.notify("Chat", 0, "", "Bye", "", &[], HashMap::new(), 0).await?;
+    proxy.close_notification(id).await?;
+    let close = timeout(WAIT, closed.next()).await?.expect("NotificationClosed").args()?;
+    assert_eq!((close.id, close.reason), (id, 3));
+    proxy.close_notification(id).await?;
+
+    // A timeout runs out with reason 1.
+    let id = proxy.notify("Chat", 0, "", "Brief", "", &[], HashMap::new(), 50).await?;
+    let close = timeout(WAIT, closed.next()).await?.expect("NotificationClosed").args()?;
+    assert_eq!((close.id, close.reason), (id, 1));
+    assert!(service.notifications.lock().unwrap().is_empty());
+    Ok(())
+}
+
+// ANCHOR: TestInlineReply
+#[tokio::test]
+async fn test_inline_reply_from_a_popup() -> Result<()> {
+    let (_bus, _server, service, proxy) = setup().await?;
+    let center = NotificationCenterProxy::new(proxy.inner().connection()).await?;
+    let mut popups = center.receive_popup_requested().await?;
+    let mut popups_closed = center.receive_popup_closed().await?;
+    let mut replied = proxy.receive_notification_replied().await?;
+    let mut closed = proxy.receive_notification_closed().await?;
+    assert!(proxy.get_capabilities().await?.iter().any(|c| c == "inline-reply"));
+
+    let hints = HashMap::from([("x-kde-reply-placeholder-text", Value::from("Reply to Alice"))]);
+    let id = proxy.notify("Chat", 0, "", "Alice", "lunch?", &["inline-reply", "Send"], hints, 0).await?;
+    let popup: Notification = serde_json::from_str(&timeout(WAIT, popups.next()).await?.expect("PopupRequested").args()?.notification)?;
+    assert_eq!(popup.reply_action().map(|action| action.label.as_str()), Some("Send"));
+    assert_eq!(popup.reply_placeholder(), Some("Reply to Alice"));
+
+    // The text reaches the client, then the notification closes like after any other action.
+    center.reply(&popup.id.to_string(), "on my way").await?;
+    let reply = timeout(WAIT, replied.next()).await?.expect("NotificationReplied").args()?;
+    assert_eq!((reply.id, reply.text.as_str()), (id, "on my way"));
+    let close = timeout(WAIT, closed.next()).await?.expect("NotificationClosed").args()?;
+    assert_eq!((close.id, close.reason), (id, 2));
+    let gone = timeout(WAIT, popups_closed.next()).await?.expect("PopupClosed").args()?;
+    assert_eq!(gone.notification_id, popup.id.to_string());
+    assert!(service.notifications.lock().unwrap().is_empty());
+    assert!(center.reply("not-a-uuid", "hi").await.is_err());
+    Ok(())
+}
+
+// ANCHOR: TestCenter
+#[tokio::test]
+async fn test_center_stacks_threads_and_dismisses_them() -> Result<()> {
+    let (_bus, _server, _service, proxy) = setup().await?;
+    let center = NotificationCenterProxy::new(proxy.inner().connection()).await?;
+    let mut changed = center.receive_groups_changed().await?;
+    let mut closed = proxy.receive_notification_closed().await?;
+
+    let thread = |id: &'static str| HashMap::from([(THREAD_ID_HINT, Value::from(id))]);
+    let first = proxy.notify("Chat", 0, "", "Alice", "hi", &[], thread("a"), 0).await?;
+    let second = proxy.notify("Chat", 0, "", "Alice", "there", &[], thread("a"), 0).await?;
+    proxy.notify("Chat", 0, "", "Bob", "yo", &[], thread("b"), 0).await?;
+    timeout(WAIT, changed.next()).await?.expect("GroupsChanged");
+
+    let groups: Vec<NotificationGroup> = serde_json::from_str(&center.get_groups().await?)?;
+    let mut stacks: Vec<(String, usize)> = groups.iter().map(|g| (g.key.clone(), g.len())).collect();
+    stacks.sort();
+    assert_eq!(stacks, [("Chat/thread/a".to_string(), 2), ("Chat/thread/b".to_string(), 1)]);
+
+    // Dismissing a stack closes each of its notifications as dismissed by the user.
+    assert_eq!(center.dismiss_group("Chat/thread/a").await?, 2);
+    let mut closed_ids = Vec::new();
+    for _ in 0..2 {
+        let close = timeout(WAIT, closed.next()).await?.expect("NotificationClosed").args()?;
+        assert_eq!(close.reason, 2);
+        closed_ids.push(close.id);
+    }
+    closed_ids.sort();
+    assert_eq!(closed_ids, [first, second]);
+    let groups: Vec<NotificationGroup> = serde_json::from_str(&center.get_groups().await?)?;
+    assert_eq!(groups.len(), 1);
+    Ok(())
+}
+
+// ANCHOR: TestFocus
+#[tokio::test]
+async fn test_focus_modes_over_the_center() -> Result<()> {
+    let (_bus, _server, _service, proxy) = setup().await?;
+    let center = NotificationCenterProxy::new(proxy.inner().connection()).await?;
+    let mut changed = center.receive_focus_changed().await?;
+    let mut ended = center.receive_focus_ended().await?;
+
+    let modes: Vec<FocusMode> = serde_json::from_str(&center.get_focus_modes().await?)?;
+    assert!(modes.iter().any(|mode| mode.id == "meeting"));
+    assert!(center.activate_focus_mode("nope").await.is_err());
+
+    center.activate_focus_mode("meeting").await?;
+    let active: Option<ActiveFocus> = serde_json::from_str(&timeout(WAIT, changed.next()).await?.expect("FocusChanged").args()?.active)?;
+    assert_eq!(active.map(|a| a.mode_id).as_deref(), Some("meeting"));
+    let state: Option<ActiveFocus> = serde_json::from_str(&center.get_focus_state().await?)?;
+    assert_eq!(state.map(|a| a.activation), Some(FocusActivation::Manual));
+
+    // An empty id turns focus off and reports what was missed.
+    center.activate_focus_mode("").await?;
+    let summary: MissedSummary = serde_json::from_str(&timeout(WAIT, ended.next()).await?.expect("FocusEnded").args()?.summary)?;
+    assert_eq!(summary.mode_id, "meeting");
+    assert_eq!(center.get_focus_state().await?, "null");
+    Ok(())
+}
+
+// ANCHOR: TestRules
+#[tokio::test]
+async fn test_rules_validate_and_simulate_over_the_center() -> Result<()> {
+    let (_bus, _server, service, proxy) = setup().await?;
+    let center = NotificationCenterProxy::new(proxy.inner().connection()).await?;
+
+    let broken = NotificationRule {
+        name: "Broken".to_string(),
+        condition: RuleCondition::Simple(SimpleRuleCondition {
+            field: RuleConditionField::Body,
+            operator: RuleConditionOperator::MatchesRegex,
+            value: RuleConditionValue::Regex("(unclosed".to_string()),
+        }),
+        ..Default::default()
+    };
+    let draft = serde_json::to_string(&vec![broken])?;
+    let issues: Vec<RuleValidationIssue> = serde_json::from_str(&center.validate_rules(&draft).await?)?;
+    assert_eq!(issues.len(), 1);
+    assert!(issues[0].reason.contains("invalid regex"));
+    // Invalid rules are rejected up front and never reach the service.
+    assert!(center.set_rules(&draft).await.is_err());
+    assert!(center.set_rules("not json").await.is_err());
+    assert_eq!(center.get_rules().await?, "[]");
+
+    let valid = serde_json::to_string(&vec![NotificationRule { name: "Fine".to_string(), ..Default::default() }])?;
+    center.set_rules(&valid).await?;
+    assert_eq!(service.rules.lock().unwrap().len(), 1);
+
+    // A notification in the notification center replays as the sample.
+    proxy.notify("Chat", 0, "", "Alice", "hi", &[], HashMap::new(), 0).await?;
+    let sample = serde_json::to_string(&RuleSimulationSample::History(service.only().id))?;
+    let simulation: RuleSimulation = serde_json::from_str(&center.simulate_rules(&sample, &draft).await?)?;
+    assert_eq!(simulation.input.summary, "Alice");
+    let sample = serde_json::to_string(&RuleSimulationSample::Input(NotificationInput {
+        application_name: "Mail".to_string(),
+        summary: "Invoice".to_string(),
+        ..Default::default()
+    }))?;
+    let simulation: RuleSimulation = serde_json::from_str(&center.simulate_rules(&sample, "").await?)?;
+    assert!(matches!(simulation.outcome, SimulationOutcome::Allow { notification } if notification.summary == "Invoice"));
+    let unknown = serde_json::to_string(&RuleSimulationSample::History(Uuid::new_v4()))?;
+    assert!(center.simulate_rules(&unknown, "").await.is_err());
+    Ok(())
+}
+
+// ANCHOR: TestCenterAccess
+#[tokio::test]
+async fn test_center_refuses_callers_other_than_the_shell() -> Result<()> {
+    // SAFETY: getuid has no preconditions and cannot fail.
+    let uid = unsafe { libc::getuid() };
+    let (_bus, _server, service, proxy) = setup_with_access(CenterAccess::new(uid, SHELL_EXECUTABLES)).await?;
+    let center = NotificationCenterProxy::new(proxy.inner().connection()).await?;
+    proxy.notify("Bank", 0, "", "Sign-in", "Your 2FA code is 491204", &[], HashMap::new(), 0).await?;
+
+    // Anyone may post, but not read back or change what the center holds.
+    let denied = |result: zbus::Result<String>| {
+        matches!(result, Err(zbus::Error::MethodError(name, _, _)) if name.as_str() == "org.freedesktop.DBus.Error.AccessDenied")
+    };
+    assert!(denied(center.get_groups().await));
+    assert!(denied(center.search_history("2fa", 0, 10).await));
+    assert!(denied(center.export_history().await));
+    assert!(center.set_rules("[]").await.is_err());
+    assert!(center.dismiss_group("Bank").await.is_err());
+    assert_eq!(service.notifications.lock().unwrap().len(), 1);
+    Ok(())
+}
+
+// ANCHOR: TestHistory
+#[tokio::test]
+async fn test_history_search_export_and_retention_over_the_center() -> Result<()> {
+    let (_bus, _server, _service, proxy) = setup().await?;
+    let center = NotificationCenterProxy::new(proxy.inner().connection()).await?;
+    proxy.notify("Bank", 0, "", "Sign-in", "Your 2FA code is 491204", &[], HashMap::new(), 0).await?;
+    proxy.notify("Mail", 0, "", "Invoice", "Your statement is ready", &[], HashMap::new(), 0).await?;
+
+    let found: Vec<Notification> = serde_json::from_str(&center.search_history("2fa cod", 0, 10).await?)?;
+    assert_eq!(found.iter().map(|n| n.summary.as_str()).collect::<Vec<_>>(), ["Sign-in"]);
+    let paged: Vec<Notification> = serde_json::from_str(&center.search_history("your", 1, 10).await?)?;
+    assert_eq!(paged.len(), 1);
+
+    let export: HistoryExport = serde_json::from_str(&center.export_history().await?)?;
+    assert_eq!(export.notifications.len(), 2);
+
+    let mut retention: HistoryRetention = serde_json::from_str(&center.get_history_retention().await?)?;
+    retention.max_age_days = None;
+    center.set_history_retention(&serde_json::to_string(&retention)?).await?;
+    let stored: HistoryRetention = serde_json::from_str(&center.get_history_retention().await?)?;
+    assert_eq!(stored, retention);
+    assert!(center.set_history_retention("{\"max-items\": -1}").await.is_err());
+    Ok(())
+}
@@ -0,0 +1,52 @@
+// novade-ui/src/audio_profiles_client.rs
+
+//! Client of `org.novade.AudioProfiles`, through which the quick settings mixer changes an
+//! application's volume and devices and has them remembered the next time it plays.
+
+use novade_domain::AppAudioProfile;
+use zbus::{dbus_proxy, Connection, Error as ZbusError};
+
+#[dbus_proxy(
+    interface = "org.novade.AudioProfiles",
+    default_service = "org.novade.AudioProfiles",
+    default_path = "/org/novade/AudioProfiles"
+)]
+trait AudioProfiles {
+    fn get_profiles(&self) -> zbus::Result<String>;
+    fn set_app_volume(&self, app_id: &str, volume: f64) -> zbus::Result<()>;
+    fn set_app_device(&self, app_id: &str, device_id: &str) -> zbus::Result<()>;
+    fn forget_app(&self, app_id: &str) -> zbus::Result<()>;
+
+    #[dbus_proxy(signal)]
+    fn profiles_changed(&self) -> zbus::Result<()>;
+}
+
+pub struct AudioProfilesClient {
+    proxy: AudioProfilesProxy<'static>,
+}
+
+impl AudioProfilesClient {
+    pub async fn new() -> Result<Self, ZbusError> {
+        let connection = Connection::session().await?;
+        Ok(Self { proxy: AudioProfilesProxy::new(&connection).await? })
+    }
+
+    pub async fn profiles(&self) -> Result<Vec<AppAudioProfile>, ZbusError> {
+        let json = self.proxy.get_profiles().await?;
+        serde_json::from_str(&json).map_err(|e| ZbusError::Failure(format!("malformed audio profiles: {}", e)))
+    }
+
+    /// Sets the playback volume (0.0-1.0) of all of the application's streams and remembers it.
+    pub async fn set_app_volume(&self, app_id: &str, volume: f64) -> Result<(), ZbusError> {
+        self.proxy.set_app_volume(app_id, volume).await
+    }
+
+    /// Moves the application's streams of the device's type to it and prefers it from now on.
+    pub async fn set_app_device(&self, app_id: &str, device_id: &str) -> Result<(), ZbusError> {
+        self.proxy.set_app_device(app_id, device_id).await
+    }
+
+    pub async fn forget_app(&self, app_id: &str) -> Result<(), ZbusError> {
+        self.proxy.forget_app(app_id).await
+    }
+}
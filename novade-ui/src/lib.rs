@@ -13,6 +13,8 @@ pub mod application_launcher;
 pub mod settings_ui;
 pub mod notification_ui;
 pub mod notification_client; // Added new module
+pub mod audio_profiles_client;
+pub mod notification_rules_page;
 pub mod theme_ui;
 pub mod workspace_ui;
 pub mod system_tray;
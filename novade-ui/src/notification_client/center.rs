@@ -0,0 +1,273 @@
+// novade-ui/src/notification_client/center.rs
+
+//! Client of `org.novade.NotificationCenter`, the shell-facing side of the notification server:
+//! grouped stacks for the notification center, popups with their actions and inline replies,
+//! digest popups for bursts, the searchable history, focus modes and the notification rules with
+//! their simulator.
+
+use futures_util::{stream, stream::StreamExt, FutureExt};
+use novade_domain::{
+    ActiveFocus, FocusMode, HistoryExport, HistoryRetention, MissedSummary, Notification, NotificationDigest, NotificationFilterCriteria,
+    NotificationGroup, NotificationRuleSet, RuleSimulation, RuleSimulationSample, RuleValidationIssue,
+};
+use uuid::Uuid;
+use zbus::{dbus_proxy, Connection, Error as ZbusError};
+
+#[dbus_proxy(
+    interface = "org.novade.NotificationCenter",
+    default_service = "org.novade.NotificationCenter",
+    default_path = "/org/novade/NotificationCenter"
+)]
+trait NotificationCenter {
+    fn get_groups(&self) -> zbus::Result<String>;
+    fn dismiss_group(&self, group_key: &str) -> zbus::Result<u32>;
+    fn mark_as_read(&self, notification_id: &str) -> zbus::Result<()>;
+    fn get_focus_modes(&self) -> zbus::Result<String>;
+    fn get_focus_state(&self) -> zbus::Result<String>;
+    fn activate_focus_mode(&self, mode_id: &str) -> zbus::Result<()>;
+    fn get_history(&self, limit: u32) -> zbus::Result<String>;
+    fn get_history_page(&self, offset: u32, limit: u32, filter: &str) -> zbus::Result<String>;
+    fn search_history(&self, query: &str, offset: u32, limit: u32) -> zbus::Result<String>;
+    fn export_history(&self) -> zbus::Result<String>;
+    fn get_history_retention(&self) -> zbus::Result<String>;
+    fn set_history_retention(&self, retention: &str) -> zbus::Result<()>;
+    fn get_rules(&self) -> zbus::Result<String>;
+    fn set_rules(&self, rules: &str) -> zbus::Result<()>;
+    fn validate_rules(&self, rules: &str) -> zbus::Result<String>;
+    fn simulate_rules(&self, sample: &str, rules: &str) -> zbus::Result<String>;
+    fn invoke_action(&self, notification_id: &str, action_key: &str, activation_token: &str) -> zbus::Result<()>;
+    fn reply(&self, notification_id: &str, text: &str) -> zbus::Result<()>;
+    fn dismiss_notification(&self, notification_id: &str) -> zbus::Result<()>;
+
+    #[dbus_proxy(signal)]
+    fn groups_changed(&self) -> zbus::Result<()>;
+    #[dbus_proxy(signal)]
+    fn digest_updated(&self, digest: String) -> zbus::Result<()>;
+    #[dbus_proxy(signal)]
+    fn popup_requested(&self, notification: String) -> zbus::Result<()>;
+    #[dbus_proxy(signal)]
+    fn popup_updated(&self, notification: String) -> zbus::Result<()>;
+    #[dbus_proxy(signal)]
+    fn popup_closed(&self, notification_id: String) -> zbus::Result<()>;
+    #[dbus_proxy(signal)]
+    fn focus_changed(&self, active: String) -> zbus::Result<()>;
+    #[dbus_proxy(signal)]
+    fn focus_ended(&self, summary: String) -> zbus::Result<()>;
+}
+
+/// What the notification server asks of the popups.
+#[derive(Debug, Clone)]
+pub enum PopupEvent {
+    Show(Notification),
+    /// The notification was replaced in place; only a popup already showing it follows.
+    Update(Notification),
+    Close(Uuid),
+}
+
+pub struct NotificationCenterClient {
+    proxy: NotificationCenterProxy<'static>,
+}
+
+impl NotificationCenterClient {
+    pub async fn new() -> Result<Self, ZbusError> {
+        let connection = Connection::session().await?;
+        Ok(Self { proxy: NotificationCenterProxy::new(&connection).await? })
+    }
+
+    pub async fn groups(&self) -> Result<Vec<NotificationGroup>, ZbusError> {
+        let json = self.proxy.get_groups().await?;
+        serde_json::from_str(&json).map_err(|e| ZbusError::Failure(format!("malformed groups: {}", e)))
+    }
+
+    /// Dismisses every notification of the stack; returns how many there were.
+    pub async fn dismiss_group(&self, group_key: &str) -> Result<u32, ZbusError> {
+        self.proxy.dismiss_group(group_key).await
+    }
+
+    /// Calls `callback` with the current groups, then again each time they change.
+    pub async fn watch_groups<F>(&self, mut callback: F) -> Result<(), ZbusError>
+    where
+        F: FnMut(Vec<NotificationGroup>),
+    {
+        let mut changes = self.proxy.receive_groups_changed().await?;
+        callback(self.groups().await?);
+        while changes.next().await.is_some() {
+            // Several changes may have queued up; one refetch covers them all.
+            while let Some(Some(_)) = changes.next().now_or_never() {}
+            callback(self.groups().await?);
+        }
+        Ok(())
+    }
+
+    pub async fn watch_digests<F>(&self, mut callback: F) -> Result<(), ZbusError>
+    where
+        F: FnMut(NotificationDigest),
+    {
+        let mut digests = self.proxy.receive_digest_updated().await?;
+        while let Some(signal) = digests.next().await {
+            let digest = signal.args().ok().and_then(|args| serde_json::from_str(&args.digest).ok());
+            match digest {
+                Some(digest) => callback(digest),
+                None => tracing::warn!("NotificationCenterClient: ignoring a malformed DigestUpdated signal"),
+            }
+        }
+        Ok(())
+    }
+
+    /// Calls `callback` for each popup to show, refresh or close.
+    pub async fn watch_popups<F>(&self, mut callback: F) -> Result<(), ZbusError>
+    where
+        F: FnMut(PopupEvent),
+    {
+        let requested = self.proxy.receive_popup_requested().await?.map(|signal| {
+            signal.args().ok().and_then(|args| serde_json::from_str(&args.notification).ok()).map(PopupEvent::Show)
+        });
+        let updated = self.proxy.receive_popup_updated().await?.map(|signal| {
+            signal.args().ok().and_then(|args| serde_json::from_str(&args.notification).ok()).map(PopupEvent::Update)
+        });
+        let closed = self.proxy.receive_popup_closed().await?.map(|signal| {
+            signal.args().ok().and_then(|args| args.notification_id.parse().ok()).map(PopupEvent::Close)
+        });
+        let mut events = stream::select(requested, stream::select(updated, closed));
+        while let Some(event) = events.next().await {
+            match event {
+                Some(event) => callback(event),
+                None => tracing::warn!("NotificationCenterClient: ignoring a malformed popup signal"),
+            }
+        }
+        Ok(())
+    }
+
+    /// `activation_token` is the XDG activation token of the click, if the popup got one.
+    pub async fn invoke_action(&self, notification_id: Uuid, action_key: &str, activation_token: Option<&str>) -> Result<(), ZbusError> {
+        self.proxy.invoke_action(&notification_id.to_string(), action_key, activation_token.unwrap_or("")).await
+    }
+
+    pub async fn reply(&self, notification_id: Uuid, text: &str) -> Result<(), ZbusError> {
+        self.proxy.reply(&notification_id.to_string(), text).await
+    }
+
+    pub async fn dismiss(&self, notification_id: Uuid) -> Result<(), ZbusError> {
+        self.proxy.dismiss_notification(&notification_id.to_string()).await
+    }
+
+    pub async fn focus_modes(&self) -> Result<Vec<FocusMode>, ZbusError> {
+        let json = self.proxy.get_focus_modes().await?;
+        serde_json::from_str(&json).map_err(|e| ZbusError::Failure(format!("malformed focus modes: {}", e)))
+    }
+
+    pub async fn active_focus(&self) -> Result<Option<ActiveFocus>, ZbusError> {
+        let json = self.proxy.get_focus_state().await?;
+        serde_json::from_str(&json).map_err(|e| ZbusError::Failure(format!("malformed focus state: {}", e)))
+    }
+
+    /// Activates focus mode `mode_id`, or turns focus off for `None`.
+    pub async fn activate_focus_mode(&self, mode_id: Option<&str>) -> Result<(), ZbusError> {
+        self.proxy.activate_focus_mode(mode_id.unwrap_or("")).await
+    }
+
+    /// Calls `callback` with the active focus mode, then again each time it changes.
+    pub async fn watch_focus<F>(&self, mut callback: F) -> Result<(), ZbusError>
+    where
+        F: FnMut(Option<ActiveFocus>),
+    {
+        let mut changes = self.proxy.receive_focus_changed().await?;
+        callback(self.active_focus().await?);
+        while let Some(signal) = changes.next().await {
+            match signal.args().ok().and_then(|args| serde_json::from_str(&args.active).ok()) {
+                Some(active) => callback(active),
+                None => tracing::warn!("NotificationCenterClient: ignoring a malformed FocusChanged signal"),
+            }
+        }
+        Ok(())
+    }
+
+    /// Calls `callback` with the summary of each focus mode that ends.
+    pub async fn watch_focus_summaries<F>(&self, mut callback: F) -> Result<(), ZbusError>
+    where
+        F: FnMut(MissedSummary),
+    {
+        let mut summaries = self.proxy.receive_focus_ended().await?;
+        while let Some(signal) = summaries.next().await {
+            match signal.args().ok().and_then(|args| serde_json::from_str(&args.summary).ok()) {
+                Some(summary) => callback(summary),
+                None => tracing::warn!("NotificationCenterClient: ignoring a malformed FocusEnded signal"),
+            }
+        }
+        Ok(())
+    }
+
+    /// The `limit` most recent history entries, newest first.
+    pub async fn history(&self, limit: u32) -> Result<Vec<Notification>, ZbusError> {
+        let json = self.proxy.get_history(limit).await?;
+        serde_json::from_str(&json).map_err(|e| ZbusError::Failure(format!("malformed history: {}", e)))
+    }
+
+    /// A page of the history, newest first, optionally narrowed by `filter`.
+    pub async fn history_page(&self, offset: u32, limit: u32, filter: Option<&NotificationFilterCriteria>) -> Result<Vec<Notification>, ZbusError> {
+        let filter = match filter {
+            Some(filter) => serde_json::to_string(filter).map_err(|e| ZbusError::Failure(e.to_string()))?,
+            None => String::new(),
+        };
+        let json = self.proxy.get_history_page(offset, limit, &filter).await?;
+        serde_json::from_str(&json).map_err(|e| ZbusError::Failure(format!("malformed history: {}", e)))
+    }
+
+    /// Full-text search of the history, best matches first.
+    pub async fn search_history(&self, query: &str, offset: u32, limit: u32) -> Result<Vec<Notification>, ZbusError> {
+        let json = self.proxy.search_history(query, offset, limit).await?;
+        serde_json::from_str(&json).map_err(|e| ZbusError::Failure(format!("malformed search results: {}", e)))
+    }
+
+    /// Saves the history as JSON to `path`; returns how many entries it wrote.
+    pub async fn export_history(&self, path: &std::path::Path) -> Result<u32, ZbusError> {
+        let json = self.proxy.export_history().await?;
+        let export: HistoryExport =
+            serde_json::from_str(&json).map_err(|e| ZbusError::Failure(format!("malformed history export: {}", e)))?;
+        std::fs::write(path, json).map_err(|e| ZbusError::Failure(format!("{}: {}", path.display(), e)))?;
+        Ok(export.notifications.len() as u32)
+    }
+
+    pub async fn history_retention(&self) -> Result<HistoryRetention, ZbusError> {
+        let json = self.proxy.get_history_retention().await?;
+        serde_json::from_str(&json).map_err(|e| ZbusError::Failure(format!("malformed retention: {}", e)))
+    }
+
+    /// Replaces the retention policy; entries it no longer keeps are deleted right away.
+    pub async fn set_history_retention(&self, retention: &HistoryRetention) -> Result<(), ZbusError> {
+        let json = serde_json::to_string(retention).map_err(|e| ZbusError::Failure(e.to_string()))?;
+        self.proxy.set_history_retention(&json).await
+    }
+
+    pub async fn rules(&self) -> Result<NotificationRuleSet, ZbusError> {
+        let json = self.proxy.get_rules().await?;
+        serde_json::from_str(&json).map_err(|e| ZbusError::Failure(format!("malformed rules: {}", e)))
+    }
+
+    /// Replaces the rules; the server rejects the whole set if any rule is invalid.
+    pub async fn set_rules(&self, rules: &NotificationRuleSet) -> Result<(), ZbusError> {
+        let json = serde_json::to_string(rules).map_err(|e| ZbusError::Failure(e.to_string()))?;
+        self.proxy.set_rules(&json).await
+    }
+
+    pub async fn validate_rules(&self, rules: &NotificationRuleSet) -> Result<Vec<RuleValidationIssue>, ZbusError> {
+        let json = serde_json::to_string(rules).map_err(|e| ZbusError::Failure(e.to_string()))?;
+        let issues = self.proxy.validate_rules(&json).await?;
+        serde_json::from_str(&issues).map_err(|e| ZbusError::Failure(format!("malformed validation issues: {}", e)))
+    }
+
+    /// Dry-runs `rules`, or the active rules for `None`, against `sample`.
+    pub async fn simulate_rules(
+        &self,
+        sample: &RuleSimulationSample,
+        rules: Option<&NotificationRuleSet>,
+    ) -> Result<RuleSimulation, ZbusError> {
+        let sample = serde_json::to_string(sample).map_err(|e| ZbusError::Failure(e.to_string()))?;
+        let rules = match rules {
+            Some(rules) => serde_json::to_string(rules).map_err(|e| ZbusError::Failure(e.to_string()))?,
+            None => String::new(),
+        };
+        let json = self.proxy.simulate_rules(&sample, &rules).await?;
+        serde_json::from_str(&json).map_err(|e| ZbusError::Failure(format!("malformed simulation: {}", e)))
+    }
+}
@@ -1,3 +1,7 @@
+pub mod center;
+
+pub use center::{NotificationCenterClient, PopupEvent};
+
 use zbus::{Proxy, Connection, Error as ZbusError, zvariant::{Value, Dict}};
 use std::collections::HashMap;
 use futures_util::stream::StreamExt; // For signal stream
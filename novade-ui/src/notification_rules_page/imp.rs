@@ -0,0 +1,239 @@
+use gtk::glib;
+use gtk::subclass::prelude::*;
+use gtk::{prelude::*, Align, Box, Button, DropDown, Entry, Label, Orientation, PolicyType, ScrolledWindow, StringList, TextView, WrapMode};
+use novade_domain::{Notification, NotificationInput, NotificationRuleSet, RuleSimulationSample};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use crate::notification_client::NotificationCenterClient;
+
+#[derive(Default)]
+pub struct NotificationRulesPage {
+    pub client: RefCell<Option<Rc<NotificationCenterClient>>>,
+    pub rules_view: RefCell<Option<TextView>>,
+    pub issues_label: RefCell<Option<Label>>,
+    pub save_button: RefCell<Option<Button>>,
+    pub sample_dropdown: RefCell<Option<DropDown>>,
+    /// The typed-in sample, used while the dropdown's first entry is selected.
+    pub custom_box: RefCell<Option<Box>>,
+    pub app_entry: RefCell<Option<Entry>>,
+    pub summary_entry: RefCell<Option<Entry>>,
+    pub body_entry: RefCell<Option<Entry>>,
+    pub trace_box: RefCell<Option<Box>>,
+    /// The notification behind each dropdown entry after the first.
+    pub samples: RefCell<Vec<Notification>>,
+    /// Bumped for every simulation, so that a slow reply never replaces a newer one.
+    pub simulation_serial: Cell<u64>,
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for NotificationRulesPage {
+    const NAME: &'static str = "NovaDENotificationRulesPage";
+    type Type = super::NotificationRulesPage;
+    type ParentType = gtk::Box;
+
+    fn class_init(klass: &mut Self::Class) {
+        klass.set_css_name("notificationrulespage");
+    }
+}
+
+impl ObjectImpl for NotificationRulesPage {
+    fn constructed(&self) {
+        self.parent_constructed();
+        let obj = self.obj();
+
+        obj.set_orientation(Orientation::Vertical);
+        obj.set_spacing(8);
+        obj.set_margin_top(12);
+        obj.set_margin_bottom(12);
+        obj.set_margin_start(12);
+        obj.set_margin_end(12);
+
+        let rules_heading = Label::builder().label("Rules").halign(Align::Start).build();
+        rules_heading.add_css_class("heading");
+        let rules_view = TextView::builder().monospace(true).wrap_mode(WrapMode::WordChar).build();
+        let rules_scroll = ScrolledWindow::builder()
+            .child(&rules_view)
+            .hscrollbar_policy(PolicyType::Never)
+            .min_content_height(240)
+            .vexpand(true)
+            .build();
+        let issues_label = Label::builder().halign(Align::Start).wrap(true).visible(false).build();
+        issues_label.add_css_class("error");
+        let save_button = Button::builder().label("Save rules").halign(Align::End).sensitive(false).build();
+        save_button.add_css_class("suggested-action");
+
+        let sample_row = Box::new(Orientation::Horizontal, 6);
+        let sample_label = Label::builder().label("Try against").halign(Align::Start).build();
+        let sample_dropdown = DropDown::from_strings(&["Typed in"]);
+        sample_dropdown.set_hexpand(true);
+        sample_row.append(&sample_label);
+        sample_row.append(&sample_dropdown);
+
+        let custom_box = Box::new(Orientation::Horizontal, 6);
+        let app_entry = Entry::builder().placeholder_text("Application").build();
+        let summary_entry = Entry::builder().placeholder_text("Summary").hexpand(true).build();
+        let body_entry = Entry::builder().placeholder_text("Body").hexpand(true).build();
+        custom_box.append(&app_entry);
+        custom_box.append(&summary_entry);
+        custom_box.append(&body_entry);
+
+        let trace_box = Box::new(Orientation::Vertical, 2);
+        trace_box.add_css_class("rule-trace");
+
+        let self_obj = obj.clone();
+        rules_view.buffer().connect_changed(move |_buffer| self_obj.imp().refresh());
+        let self_obj = obj.clone();
+        sample_dropdown.connect_selected_notify(move |_dropdown| self_obj.imp().refresh());
+        for entry in [&app_entry, &summary_entry, &body_entry] {
+            let self_obj = obj.clone();
+            entry.connect_changed(move |_entry| self_obj.imp().refresh());
+        }
+        let self_obj = obj.clone();
+        save_button.connect_clicked(move |_btn| self_obj.imp().save());
+
+        obj.append(&rules_heading);
+        obj.append(&rules_scroll);
+        obj.append(&issues_label);
+        obj.append(&save_button);
+        obj.append(&sample_row);
+        obj.append(&custom_box);
+        obj.append(&trace_box);
+        self.rules_view.replace(Some(rules_view));
+        self.issues_label.replace(Some(issues_label));
+        self.save_button.replace(Some(save_button));
+        self.sample_dropdown.replace(Some(sample_dropdown));
+        self.custom_box.replace(Some(custom_box));
+        self.app_entry.replace(Some(app_entry));
+        self.summary_entry.replace(Some(summary_entry));
+        self.body_entry.replace(Some(body_entry));
+        self.trace_box.replace(Some(trace_box));
+    }
+}
+
+impl WidgetImpl for NotificationRulesPage {}
+impl BoxImpl for NotificationRulesPage {}
+
+impl NotificationRulesPage {
+    pub fn show_rules(&self, rules: &NotificationRuleSet) {
+        let text = serde_json::to_string_pretty(rules).unwrap_or_else(|_| "[]".to_string());
+        if let Some(view) = self.rules_view.borrow().as_ref() {
+            view.buffer().set_text(&text);
+        }
+    }
+
+    /// Offers `history` as samples, preselecting the most recent one.
+    pub fn show_samples(&self, history: Vec<Notification>) {
+        let mut labels = vec!["Typed in".to_string()];
+        labels.extend(history.iter().map(super::sample_label));
+        let has_samples = !history.is_empty();
+        self.samples.replace(history);
+        if let Some(dropdown) = self.sample_dropdown.borrow().as_ref() {
+            let labels: Vec<&str> = labels.iter().map(String::as_str).collect();
+            dropdown.set_model(Some(&StringList::new(&labels)));
+            dropdown.set_selected(if has_samples { 1 } else { 0 });
+        }
+    }
+
+    fn draft_text(&self) -> String {
+        match self.rules_view.borrow().as_ref() {
+            Some(view) => {
+                let buffer = view.buffer();
+                buffer.text(&buffer.start_iter(), &buffer.end_iter(), false).to_string()
+            }
+            None => String::new(),
+        }
+    }
+
+    fn entry_text(entry: &RefCell<Option<Entry>>) -> String {
+        entry.borrow().as_ref().map(|e| e.text().to_string()).unwrap_or_default()
+    }
+
+    fn sample(&self) -> RuleSimulationSample {
+        let selected = self.sample_dropdown.borrow().as_ref().map_or(0, |d| d.selected() as usize);
+        let history_id = selected.checked_sub(1).and_then(|index| self.samples.borrow().get(index).map(|n| n.id));
+        if let Some(custom_box) = self.custom_box.borrow().as_ref() {
+            custom_box.set_visible(history_id.is_none());
+        }
+        match history_id {
+            Some(id) => RuleSimulationSample::History(id),
+            None => {
+                let body = Self::entry_text(&self.body_entry);
+                RuleSimulationSample::Input(NotificationInput {
+                    application_name: Self::entry_text(&self.app_entry),
+                    summary: Self::entry_text(&self.summary_entry),
+                    body: Some(body).filter(|b| !b.is_empty()),
+                    ..Default::default()
+                })
+            }
+        }
+    }
+
+    /// Checks the draft and runs it against the sample.
+    fn refresh(&self) {
+        let text = self.draft_text();
+        let draft = super::parse_draft(&text);
+        self.show_issues(draft.as_ref().err().map_or(&[][..], Vec::as_slice));
+        if let Some(button) = self.save_button.borrow().as_ref() {
+            button.set_sensitive(draft.is_ok());
+        }
+
+        // Rules that fail validation still run, so the trace shows where they break; only text
+        // that is not a rule set at all cannot.
+        let sample = self.sample();
+        let (Ok(rules), Some(client)) = (serde_json::from_str::<NotificationRuleSet>(&text), self.client.borrow().clone()) else {
+            self.show_trace(&[]);
+            return;
+        };
+        let serial = self.simulation_serial.get() + 1;
+        self.simulation_serial.set(serial);
+        let obj = self.obj().clone();
+        glib::MainContext::default().spawn_local(async move {
+            let result = client.simulate_rules(&sample, Some(&rules)).await;
+            if obj.imp().simulation_serial.get() != serial {
+                return;
+            }
+            match result {
+                Ok(simulation) => obj.imp().show_trace(&super::simulation_lines(&simulation)),
+                Err(e) => obj.imp().show_trace(&[format!("Could not simulate the rules: {}", e)]),
+            }
+        });
+    }
+
+    fn save(&self) {
+        let Ok(rules) = super::parse_draft(&self.draft_text()) else {
+            return;
+        };
+        let Some(client) = self.client.borrow().clone() else {
+            return;
+        };
+        let obj = self.obj().clone();
+        glib::MainContext::default().spawn_local(async move {
+            match client.set_rules(&rules).await {
+                Ok(()) => tracing::info!("NotificationRulesPage: saved {} rules", rules.len()),
+                Err(e) => obj.imp().show_issues(&[format!("Could not save the rules: {}", e)]),
+            }
+        });
+    }
+
+    fn show_issues(&self, issues: &[String]) {
+        if let Some(label) = self.issues_label.borrow().as_ref() {
+            label.set_text(&issues.join("\n"));
+            label.set_visible(!issues.is_empty());
+        }
+    }
+
+    fn show_trace(&self, lines: &[String]) {
+        let Some(trace_box) = self.trace_box.borrow().clone() else {
+            return;
+        };
+        while let Some(child) = trace_box.first_child() {
+            trace_box.remove(&child);
+        }
+        for line in lines {
+            let label = Label::builder().label(line).halign(Align::Start).wrap(true).build();
+            label.add_css_class("monospace");
+            trace_box.append(&label);
+        }
+    }
+}
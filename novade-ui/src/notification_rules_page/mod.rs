@@ -0,0 +1,178 @@
+// novade-ui/src/notification_rules_page/mod.rs
+
+//! The notification rules page of the settings window: the rules as editable JSON, checked on
+//! every change, and a dry run of the draft against a live sample picked from the notification
+//! history or typed in.
+
+use gtk::glib;
+use gtk::glib::subclass::prelude::*;
+use std::rc::Rc;
+use novade_domain::{
+    validate_rules, ConditionTrace, Notification, NotificationRuleSet, RuleSimulation, RuleTraceStatus,
+    SimulationOutcome,
+};
+
+use crate::notification_client::NotificationCenterClient;
+
+mod imp;
+
+/// How many recent notifications are offered as samples.
+const SAMPLE_COUNT: u32 = 20;
+
+glib::wrapper! {
+    pub struct NotificationRulesPage(ObjectSubclass<imp::NotificationRulesPage>)
+        @extends gtk::Widget, gtk::Box, @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget, gtk::Orientable;
+}
+
+impl NotificationRulesPage {
+    pub fn new() -> Self {
+        glib::Object::new(&[])
+    }
+
+    /// Connects to the notification server and loads the active rules and recent notifications.
+    pub fn load(&self) {
+        let page = self.clone();
+        glib::MainContext::default().spawn_local(async move {
+            let client = match NotificationCenterClient::new().await {
+                Ok(client) => Rc::new(client),
+                Err(e) => {
+                    tracing::warn!("NotificationRulesPage: could not reach the notification server: {}", e);
+                    return;
+                }
+            };
+            page.imp().client.replace(Some(client.clone()));
+            let loaded = match client.rules().await {
+                Ok(rules) => client.history(SAMPLE_COUNT).await.map(|history| (rules, history)),
+                Err(e) => Err(e),
+            };
+            match loaded {
+                Ok((rules, history)) => {
+                    page.imp().show_samples(history);
+                    page.imp().show_rules(&rules);
+                }
+                Err(e) => tracing::warn!("NotificationRulesPage: could not load the rules: {}", e),
+            }
+        });
+    }
+}
+
+impl Default for NotificationRulesPage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Parses the editor's text into rules; every problem found if it is not a valid rule set.
+pub fn parse_draft(text: &str) -> Result<NotificationRuleSet, Vec<String>> {
+    let rules: NotificationRuleSet = serde_json::from_str(text).map_err(|e| vec![format!("not valid rules JSON: {}", e)])?;
+    let issues = validate_rules(&rules);
+    if issues.is_empty() {
+        Ok(rules)
+    } else {
+        Err(issues.iter().map(|issue| format!("{}: {}", issue.rule_name, issue.reason)).collect())
+    }
+}
+
+/// A sample in the sample dropdown.
+pub fn sample_label(notification: &Notification) -> String {
+    format!("{}: {}", notification.application_name, notification.summary)
+}
+
+/// The simulation as lines of text: each rule with its condition tree, then the outcome.
+pub fn simulation_lines(simulation: &RuleSimulation) -> Vec<String> {
+    let mut lines = Vec::new();
+    for rule in &simulation.rules {
+        let status = match rule.status {
+            RuleTraceStatus::Matched => "matched",
+            RuleTraceStatus::NotMatched => "did not match",
+            RuleTraceStatus::Disabled => "disabled",
+            RuleTraceStatus::Error => "failed",
+            RuleTraceStatus::NotReached => "not reached",
+        };
+        lines.push(format!("{} (priority {}): {}", rule.rule_name, rule.priority, status));
+        if let Some(condition) = &rule.condition {
+            condition_lines(condition, 1, &mut lines);
+        }
+    }
+    lines.push(match &simulation.outcome {
+        SimulationOutcome::Suppress { rule_name, .. } => format!("Suppressed by {}", rule_name),
+        SimulationOutcome::Allow { notification } => {
+            let urgency = serde_json::to_string(&notification.urgency).unwrap_or_default();
+            format!("Posted as \"{}\" with {} urgency", notification.summary, urgency.trim_matches('"'))
+        }
+    });
+    lines
+}
+
+fn condition_lines(condition: &ConditionTrace, depth: usize, lines: &mut Vec<String>) {
+    let mark = match condition.result {
+        Some(true) => "✓",
+        Some(false) => "✗",
+        None => "!",
+    };
+    let mut line = format!("{}{} {}", "  ".repeat(depth), mark, condition.description);
+    if let Some(error) = &condition.error {
+        line.push_str(&format!(" ({})", error));
+    }
+    lines.push(line);
+    for child in &condition.children {
+        condition_lines(child, depth + 1, lines);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use novade_domain::{NotificationUrgency, RuleTrace};
+    use uuid::Uuid;
+
+    #[test]
+    fn test_parse_draft_reports_invalid_regexes() {
+        assert!(parse_draft("[]").unwrap().is_empty());
+        assert!(parse_draft("[{").unwrap_err()[0].starts_with("not valid rules JSON"));
+        let draft = r#"[{"id": "4f0c5d8e-2a9b-4c1e-9d3f-0a6b7c8d9e10", "name": "Codes", "actions": [],
+            "condition": {"simple": {"field": "body", "operator": "matches-regex", "value": {"regex": "(\\d+"}}}}]"#;
+        match parse_draft(draft) {
+            Err(problems) => assert!(problems[0].starts_with("Codes: body matches /(\\d+/: invalid regex"), "{:?}", problems),
+            Ok(rules) => panic!("accepted {:?}", rules),
+        }
+    }
+
+    #[test]
+    fn test_simulation_lines() {
+        let input = Notification::new("Chat".to_string(), "Alice".to_string(), NotificationUrgency::Normal);
+        let condition = ConditionTrace {
+            description: "any of".to_string(),
+            result: Some(true),
+            error: None,
+            children: vec![ConditionTrace {
+                description: "application name is \"Chat\"".to_string(),
+                result: Some(true),
+                error: None,
+                children: Vec::new(),
+            }],
+        };
+        let simulation = RuleSimulation {
+            input: input.clone(),
+            rules: vec![RuleTrace {
+                rule_id: Uuid::new_v4(),
+                rule_name: "Mute chat".to_string(),
+                priority: 5,
+                status: RuleTraceStatus::Matched,
+                condition: Some(condition),
+                applied_actions: Vec::new(),
+            }],
+            outcome: SimulationOutcome::Suppress { rule_id: Uuid::new_v4(), rule_name: "Mute chat".to_string() },
+        };
+        assert_eq!(
+            simulation_lines(&simulation),
+            [
+                "Mute chat (priority 5): matched",
+                "  ✓ any of",
+                "    ✓ application name is \"Chat\"",
+                "Suppressed by Mute chat",
+            ]
+        );
+        assert_eq!(sample_label(&input), "Chat: Alice");
+    }
+}
@@ -12,6 +12,7 @@ use gtk::prelude::*;
 use gtk::glib; // Required for glib::timeout_add_local and glib::Sender (if used directly here)
 use std::sync::{Arc, Mutex as StdMutex}; // Using StdMutex for active_notifications
 use std::collections::HashMap;
+use std::rc::Rc;
 use std::time::Duration; // For glib::timeout_add_local
 use tracing::{debug, error, info, warn};
 
@@ -29,6 +30,10 @@ use crate::widgets::notification_popup::{
 };
 // For interacting with the D-Bus service layer
 use crate::shell::ui_notification_service::UINotificationService;
+use crate::notification_client::{NotificationCenterClient, PopupEvent};
+use crate::widgets::notification_popup::NotificationPopupWidget;
+use novade_domain::{MissedSummary, Notification, NotificationDigest, NotificationUrgency};
+use uuid::Uuid;
 
 
 // NotificationSettings and NotificationPosition enums can remain as they are.
@@ -62,6 +67,37 @@ impl Default for NotificationSettings {
     }
 }
 
+/// The popup of one notification from the notification server.
+struct ServerPopup {
+    window: gtk::Window,
+    widget: NotificationPopupWidget,
+}
+
+/// The popup standing in for a burst in one notification group.
+struct DigestPopup {
+    window: gtk::Window,
+    widget: NotificationPopupWidget,
+    /// The digest count last shown, to tell whether the burst went on since a timer was set.
+    count: usize,
+}
+
+/// Body text of a digest popup.
+pub fn digest_body(digest: &NotificationDigest) -> String {
+    format!("{} new notifications\nLatest: {}", digest.count, digest.latest_summary)
+}
+
+/// Body text of the popup shown when a focus mode ends, or `None` if it held nothing back.
+pub fn focus_summary_body(summary: &MissedSummary) -> Option<String> {
+    if summary.total == 0 {
+        return None;
+    }
+    let mut by_app: Vec<(&String, &usize)> = summary.by_app.iter().collect();
+    by_app.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+    let apps: Vec<String> = by_app.iter().map(|(app, count)| format!("{} ({})", app, count)).collect();
+    let noun = if summary.total == 1 { "notification" } else { "notifications" };
+    Some(format!("You missed {} {}\n{}", summary.total, noun, apps.join(", ")))
+}
+
 /// Notification UI manager
 pub struct NotificationUi {
     app: gtk::Application,
@@ -69,6 +105,14 @@ pub struct NotificationUi {
     compositor: Arc<CompositorIntegration>,
     // Key is D-Bus notification ID (u32). Value is the GTK Window hosting the popup.
     active_popups: Arc<StdMutex<HashMap<u32, gtk::Window>>>,
+    // Key is the domain notification id. Popups the notification server asks for, with rich
+    // content and inline replies.
+    server_popups: Arc<StdMutex<HashMap<Uuid, ServerPopup>>>,
+    // Key is the group key. One popup per group stands in for a burst of notifications and is
+    // updated in place as the burst goes on.
+    digest_popups: Arc<StdMutex<HashMap<String, DigestPopup>>>,
+    // What the last focus mode held back, shown once it ends.
+    focus_summary_popup: Arc<StdMutex<Option<gtk::Window>>>,
     settings: Arc<StdMutex<NotificationSettings>>,
     // To call back for actions/close, this needs to be set after UINotificationService is created.
     ui_notification_service: Arc<StdMutex<Option<Arc<UINotificationService>>>>,
@@ -99,6 +143,9 @@ impl NotificationUi {
             style_manager,
             compositor,
             active_popups: Arc::new(StdMutex::new(HashMap::new())),
+            server_popups: Arc::new(StdMutex::new(HashMap::new())),
+            digest_popups: Arc::new(StdMutex::new(HashMap::new())),
+            focus_summary_popup: Arc::new(StdMutex::new(None)),
             settings: Arc::new(StdMutex::new(NotificationSettings::default())),
             ui_notification_service: Arc::new(StdMutex::new(None)),
             screen_width,
@@ -347,6 +394,261 @@ impl NotificationUi {
         }
     }
     
+    /// Pops up `notification`, or refreshes its popup if it is already up. Its buttons, reply
+    /// field and Escape go back to the server through `client`.
+    pub fn show_popup(&self, notification: &Notification, client: &Rc<NotificationCenterClient>) {
+        if self.update_popup(notification) {
+            return;
+        }
+        let settings = self.settings.lock().unwrap().clone();
+        let widget = NotificationPopupWidget::new();
+        widget.set_notification(notification);
+        widget.add_css_class("notification-popup");
+        self.connect_popup(&widget, notification.id, client);
+        // Only a popup with a reply field takes keyboard focus; the others stay out of the way.
+        let accepts_reply = widget.accepts_reply();
+        let window = gtk::Window::builder()
+            .application(&self.app)
+            .child(&widget)
+            .decorated(false)
+            .resizable(false)
+            .can_focus(accepts_reply)
+            .default_width(settings.width)
+            .build();
+        if let Err(e) = self.compositor.create_surface(&window, SurfaceType::Notification) {
+            error!("Failed to create compositor surface for notification {}: {}. Showing window directly.", notification.id, e);
+        }
+        window.present();
+        if accepts_reply {
+            widget.focus_first();
+        }
+        self.server_popups.lock().unwrap().insert(notification.id, ServerPopup { window, widget });
+
+        // Critical notifications stay up until they are dealt with.
+        if notification.urgency != NotificationUrgency::Critical {
+            self.hide_popup_later(notification.id, Duration::from_secs(settings.timeout_secs.into()));
+        }
+    }
+
+    /// Shows the new content of a notification replaced in place, e.g. its progress; returns
+    /// whether it had a popup.
+    pub fn update_popup(&self, notification: &Notification) -> bool {
+        match self.server_popups.lock().unwrap().get(&notification.id) {
+            Some(popup) => {
+                popup.widget.set_notification(notification);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn close_popup(&self, notification_id: Uuid) {
+        if let Some(popup) = self.server_popups.lock().unwrap().remove(&notification_id) {
+            popup.window.destroy();
+        }
+    }
+
+    /// Takes the popup down after `after`, leaving the notification in the notification center.
+    fn hide_popup_later(&self, notification_id: Uuid, after: Duration) {
+        let server_popups = self.server_popups.clone();
+        glib::timeout_add_local(after, move || {
+            let mut popups_guard = server_popups.lock().unwrap();
+            // A reply being written keeps the popup up; look again after another timeout.
+            if popups_guard.get(&notification_id).map_or(false, |popup| popup.widget.is_composing()) {
+                return glib::Continue(true);
+            }
+            if let Some(popup) = popups_guard.remove(&notification_id) {
+                popup.window.destroy();
+            }
+            glib::Continue(false)
+        });
+    }
+
+    fn connect_popup(&self, widget: &NotificationPopupWidget, notification_id: Uuid, client: &Rc<NotificationCenterClient>) {
+        let action_client = client.clone();
+        widget.connect_closure(
+            "action-invoked",
+            false,
+            glib::closure_local!(move |_popup: NotificationPopupWidget, action_key: String| {
+                let client = action_client.clone();
+                glib::MainContext::default().spawn_local(async move {
+                    if let Err(e) = client.invoke_action(notification_id, &action_key, None).await {
+                        error!("NotificationUi: could not invoke '{}' on {}: {}", action_key, notification_id, e);
+                    }
+                });
+            }),
+        );
+        let reply_client = client.clone();
+        widget.connect_closure(
+            "replied",
+            false,
+            glib::closure_local!(move |_popup: NotificationPopupWidget, text: String| {
+                let client = reply_client.clone();
+                glib::MainContext::default().spawn_local(async move {
+                    if let Err(e) = client.reply(notification_id, &text).await {
+                        error!("NotificationUi: could not send the reply to {}: {}", notification_id, e);
+                    }
+                });
+            }),
+        );
+        let dismiss_client = client.clone();
+        widget.connect_closure(
+            "dismissed",
+            false,
+            glib::closure_local!(move |_popup: NotificationPopupWidget| {
+                let client = dismiss_client.clone();
+                glib::MainContext::default().spawn_local(async move {
+                    if let Err(e) = client.dismiss(notification_id).await {
+                        error!("NotificationUi: could not dismiss {}: {}", notification_id, e);
+                    }
+                });
+            }),
+        );
+    }
+
+    /// Follows the popups the notification server asks for.
+    pub fn watch_popups(&self) {
+        let ui = self.clone();
+        glib::MainContext::default().spawn_local(async move {
+            let client = match NotificationCenterClient::new().await {
+                Ok(client) => Rc::new(client),
+                Err(e) => {
+                    warn!("NotificationUi: could not connect to the notification center, no popups: {}", e);
+                    return;
+                }
+            };
+            let result = client
+                .watch_popups(|event| match event {
+                    PopupEvent::Show(notification) => ui.show_popup(&notification, &client),
+                    PopupEvent::Update(notification) => {
+                        ui.update_popup(&notification);
+                    }
+                    PopupEvent::Close(notification_id) => ui.close_popup(notification_id),
+                })
+                .await;
+            if let Err(e) = result {
+                warn!("NotificationUi: stopped following popups: {}", e);
+            }
+        });
+    }
+
+    /// Shows `digest` in its group's digest popup, creating the popup for a new burst. The popup
+    /// closes once the burst has been quiet for the popup timeout.
+    pub fn show_digest(&self, digest: &NotificationDigest) {
+        let settings = self.settings.lock().unwrap().clone();
+        let mut digests_guard = self.digest_popups.lock().unwrap();
+        match digests_guard.get_mut(&digest.group_key) {
+            Some(popup) => {
+                popup.widget.set_content(0, &digest.application_name, &digest.title, &digest_body(digest));
+                popup.count = digest.count;
+            }
+            None => {
+                let widget = NotificationPopupWidget::new();
+                widget.set_content(0, &digest.application_name, &digest.title, &digest_body(digest));
+                widget.add_css_class("notification-digest");
+                let window = gtk::Window::builder()
+                    .application(&self.app)
+                    .child(&widget)
+                    .decorated(false)
+                    .resizable(false)
+                    .can_focus(false)
+                    .default_width(settings.width)
+                    .build();
+                if let Err(e) = self.compositor.create_surface(&window, SurfaceType::Notification) {
+                    error!("Failed to create compositor surface for the digest of '{}': {}. Showing window directly.", digest.group_key, e);
+                }
+                window.present();
+                digests_guard.insert(digest.group_key.clone(), DigestPopup { window, widget, count: digest.count });
+            }
+        }
+        drop(digests_guard);
+        debug!("NotificationUi: digest of '{}' now at {} notifications", digest.group_key, digest.count);
+
+        let digest_popups = self.digest_popups.clone();
+        let (key, count) = (digest.group_key.clone(), digest.count);
+        glib::timeout_add_local(Duration::from_secs(settings.timeout_secs.into()), move || {
+            let mut digests_guard = digest_popups.lock().unwrap();
+            if digests_guard.get(&key).map_or(false, |popup| popup.count == count) {
+                if let Some(popup) = digests_guard.remove(&key) {
+                    popup.window.destroy();
+                }
+            }
+            glib::Continue(false)
+        });
+    }
+
+    /// Follows digest updates from the notification server and shows them.
+    pub fn watch_digests(&self) {
+        let ui = self.clone();
+        glib::MainContext::default().spawn_local(async move {
+            let client = match NotificationCenterClient::new().await {
+                Ok(client) => client,
+                Err(e) => {
+                    warn!("NotificationUi: could not connect to the notification center, no digests: {}", e);
+                    return;
+                }
+            };
+            if let Err(e) = client.watch_digests(|digest| ui.show_digest(&digest)).await {
+                warn!("NotificationUi: stopped following digests: {}", e);
+            }
+        });
+    }
+
+    /// Shows what a focus mode held back, replacing the summary of an earlier one.
+    pub fn show_focus_summary(&self, summary: &MissedSummary) {
+        let Some(body) = focus_summary_body(summary) else {
+            debug!("NotificationUi: focus mode '{}' ended without missed notifications", summary.mode_id);
+            return;
+        };
+        let settings = self.settings.lock().unwrap().clone();
+        let widget = NotificationPopupWidget::new();
+        widget.set_content(0, "Notifications", &format!("{} ended", summary.mode_name), &body);
+        widget.add_css_class("notification-focus-summary");
+        let window = gtk::Window::builder()
+            .application(&self.app)
+            .child(&widget)
+            .decorated(false)
+            .resizable(false)
+            .can_focus(false)
+            .default_width(settings.width)
+            .build();
+        if let Err(e) = self.compositor.create_surface(&window, SurfaceType::Notification) {
+            error!("Failed to create compositor surface for the focus summary: {}. Showing window directly.", e);
+        }
+        window.present();
+        if let Some(previous) = self.focus_summary_popup.lock().unwrap().replace(window.clone()) {
+            previous.destroy();
+        }
+
+        let focus_summary_popup = self.focus_summary_popup.clone();
+        // The summary stays up longer than a regular popup; it stands in for many of them.
+        glib::timeout_add_local(Duration::from_secs(u64::from(settings.timeout_secs) * 3), move || {
+            let mut popup_guard = focus_summary_popup.lock().unwrap();
+            if popup_guard.as_ref() == Some(&window) {
+                popup_guard.take();
+                window.destroy();
+            }
+            glib::Continue(false)
+        });
+    }
+
+    /// Follows focus modes ending on the notification server and shows what they held back.
+    pub fn watch_focus_summaries(&self) {
+        let ui = self.clone();
+        glib::MainContext::default().spawn_local(async move {
+            let client = match NotificationCenterClient::new().await {
+                Ok(client) => client,
+                Err(e) => {
+                    warn!("NotificationUi: could not connect to the notification center, no focus summaries: {}", e);
+                    return;
+                }
+            };
+            if let Err(e) = client.watch_focus_summaries(|summary| ui.show_focus_summary(&summary)).await {
+                warn!("NotificationUi: stopped following focus summaries: {}", e);
+            }
+        });
+    }
+
     pub fn update_settings(&self, settings_update: impl FnOnce(&mut NotificationSettings)) -> UiResult<()> {
         let mut settings_guard = self.settings.lock().unwrap();
         settings_update(&mut *settings_guard);
@@ -361,6 +663,9 @@ impl NotificationUi {
 impl UiComponent for NotificationUi {
     fn init(&self) -> UiResult<()> {
         info!("NotificationUi initialized.");
+        self.watch_popups();
+        self.watch_digests();
+        self.watch_focus_summaries();
         // The old timer logic for expiration was per-popup and handled via glib::timeout_add_seconds_local
         // in the old `init`. Now, timeouts are set when a popup is shown.
         // Any global periodic checks could go here if needed.
@@ -376,6 +681,15 @@ impl UiComponent for NotificationUi {
         }
         drop(popups_guard);
         self.active_popups.lock().unwrap().clear();
+        for (_id, popup) in self.server_popups.lock().unwrap().drain() {
+            popup.window.destroy();
+        }
+        for (_key, popup) in self.digest_popups.lock().unwrap().drain() {
+            popup.window.destroy();
+        }
+        if let Some(window) = self.focus_summary_popup.lock().unwrap().take() {
+            window.destroy();
+        }
         info!("All active notification popups destroyed.");
         Ok(())
     }
@@ -389,6 +703,9 @@ impl Clone for NotificationUi {
             style_manager: self.style_manager.clone(),
             compositor: self.compositor.clone(),
             active_popups: self.active_popups.clone(),
+            server_popups: self.server_popups.clone(),
+            digest_popups: self.digest_popups.clone(),
+            focus_summary_popup: self.focus_summary_popup.clone(),
             settings: self.settings.clone(),
             ui_notification_service: self.ui_notification_service.clone(),
             screen_width: self.screen_width,
@@ -3,6 +3,7 @@ use adw::{ActionRow, ComboRow, PreferencesGroup, PreferencesPage, PreferencesWin
 use gtk::{glib, StringList, Switch};
 use gtk::subclass::prelude::*;
 use tracing; // For logging interactions
+use crate::notification_rules_page::NotificationRulesPage;
 
 mod imp {
     use super::*;
@@ -121,6 +122,21 @@ impl NovaSettingsWindow {
         placeholder_row.set_activatable_widget(Some(&placeholder_button));
         behavior_group.add(&placeholder_row);
 
+        // --- Notifications Page ---
+        let notifications_page = PreferencesPage::builder()
+            .title(&gettext("Notifications")) // i18n
+            .icon_name("preferences-system-notifications-symbolic")
+            .build();
+        self.add(&notifications_page);
+        let rules_group = PreferencesGroup::builder()
+            .title(&gettext("Notification Rules")) // i18n
+            .description(&gettext("Edit the rules and try them on recent notifications before saving.")) // i18n
+            .build();
+        notifications_page.add(&rules_group);
+        let rules_page = NotificationRulesPage::new();
+        rules_group.add(&rules_page);
+        rules_page.load();
+
         self.set_search_enabled(true); // Allow searching through preferences
         self.set_title(Some(&gettext("NovaDE Settings"))); // i18n Set window title
     }
@@ -2,6 +2,8 @@ use gtk::glib; // Ensure gtk::glib is used for glib, not just glib directly
 use gtk::glib::subclass::prelude::*;
 use gtk::{Application, prelude::*}; // Removed gtk::glib from here as it's imported above
 use gtk4_layer_shell;
+use novade_system::compositor::ipc::protocol::{default_socket_path, IpcRequest, IpcResponse};
+use novade_system::compositor::ipc::server::IpcClient;
 
 // Re-export enums from imp
 pub use self::imp::{ModulePosition, PanelPosition};
@@ -33,6 +35,9 @@ pub use network_management_widget::NetworkManagementWidget;
 pub mod cpu_usage_widget; // Added cpu_usage_widget module
 // pub use cpu_usage_widget::CpuUsageWidget; // Optional: re-export if needed for direct access
 
+pub mod recording_indicator_widget;
+pub use recording_indicator_widget::RecordingIndicatorWidget;
+
 mod imp;
 
 glib::wrapper! {
@@ -115,3 +120,10 @@ impl PanelWidget {
         self.set_property("leuchtakzent-intensity", intensity).unwrap();
     }
 }
+
+/// Sends one request to the compositor IPC socket. Blocks, so call it off the main thread.
+pub(crate) fn compositor_request(request: &IpcRequest) -> Result<IpcResponse, String> {
+    let path = default_socket_path().ok_or("compositor IPC socket not found")?;
+    let mut client = IpcClient::connect(&path).map_err(|e| e.to_string())?;
+    client.request(request).map_err(|e| e.to_string())
+}
@@ -57,6 +57,7 @@ impl ObjectImpl for NotificationCenterButtonWidget {
                 }
             } else {
                 let panel_content = NotificationCenterPanelWidget::new();
+                panel_content.start_monitoring();
                 let new_popover = Popover::builder()
                     .child(&panel_content)
                     .autohide(true) 
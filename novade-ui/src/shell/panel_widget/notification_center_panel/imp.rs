@@ -1,13 +1,21 @@
 use gtk::glib; // Ensure glib is imported for closure_local!
 use gtk::subclass::prelude::*;
-use gtk::{Box, CompositeTemplate, Orientation, prelude::*};
-use super::notification_widget_stub::NotificationWidgetStub; 
-use tracing; // Ensure tracing is imported for logging
+use gtk::{Box, CompositeTemplate, Label, Orientation, ScrolledWindow, prelude::*};
+use std::cell::{Cell, RefCell};
+use std::collections::HashSet;
+use std::rc::Rc;
+
+use crate::notification_client::NotificationCenterClient;
 
 #[derive(CompositeTemplate, Default)]
 #[template(string = "")] 
 pub struct NotificationCenterPanelWidget {
-    // Struct can be empty for this stub
+    pub stacks_box: RefCell<Option<Box>>,
+    pub placeholder_label: RefCell<Option<Label>>,
+    /// Keys of the groups the user expanded, kept across refreshes.
+    pub expanded_keys: RefCell<HashSet<String>>,
+    pub client: RefCell<Option<Rc<NotificationCenterClient>>>,
+    pub monitoring: Cell<bool>,
 }
 
 #[glib::object_subclass]
@@ -40,29 +48,19 @@ impl ObjectImpl for NotificationCenterPanelWidget {
         
         obj.set_width_request(300); 
 
-        // Add dummy NotificationWidgetStub instances and connect to their "closed" signal
-        let notifications_data = vec![
-            ("System Update", "Updates are available. Click to install."),
-            ("Email Client", "You have 3 new messages from John Doe."),
-            ("Calendar", "Reminder: Team Meeting in 15 minutes."),
-        ];
-
-        for (app_name, summary) in notifications_data {
-            let stub_instance = NotificationWidgetStub::new(app_name, summary);
-            
-            let container_box = obj.clone(); // Clone obj (NotificationCenterPanelWidget) for the closure
-            stub_instance.connect_closure(
-                "closed",
-                false, // after = false
-                glib::closure_local!(move |stub: super::notification_widget_stub::NotificationWidgetStub| {
-                    tracing::info!("'closed' signal received for notification: '{}'", stub.imp().app_name_label.text());
-                    container_box.remove(&stub);
-                })
-            );
-            obj.append(&stub_instance);
-        }
-
-        // Future: Logic to show "No Notifications" label if the container_box becomes empty.
+        let placeholder_label = Label::new(Some("No Notifications"));
+        placeholder_label.add_css_class("dim-label");
+        let stacks_box = Box::new(Orientation::Vertical, 6);
+        let scrolled = ScrolledWindow::builder()
+            .child(&stacks_box)
+            .hscrollbar_policy(gtk::PolicyType::Never)
+            .propagate_natural_height(true)
+            .max_content_height(600)
+            .build();
+        obj.append(&placeholder_label);
+        obj.append(&scrolled);
+        self.stacks_box.replace(Some(stacks_box));
+        self.placeholder_label.replace(Some(placeholder_label));
     }
 }
 
@@ -1,10 +1,16 @@
 use glib;
 use gtk::glib::subclass::prelude::*;
 use gtk::{prelude::*, Box}; 
+use novade_domain::NotificationGroup;
+use std::rc::Rc;
+
+use crate::notification_client::NotificationCenterClient;
 
 // Declare and use NotificationWidgetStub
 pub mod notification_widget_stub;
 pub use notification_widget_stub::NotificationWidgetStub;
+pub mod notification_stack_widget;
+pub use notification_stack_widget::NotificationStackWidget;
 
 mod imp;
 
@@ -17,4 +23,97 @@ impl NotificationCenterPanelWidget {
     pub fn new() -> Self {
         glib::Object::new(&[])
     }
+
+    /// Shows `groups` as stacks, keeping expanded the ones the user expanded before.
+    pub fn set_groups(&self, groups: &[NotificationGroup]) {
+        let imp = self.imp();
+        let Some(stacks_box) = imp.stacks_box.borrow().clone() else {
+            return;
+        };
+        while let Some(child) = stacks_box.first_child() {
+            stacks_box.remove(&child);
+        }
+        imp.expanded_keys.borrow_mut().retain(|key| groups.iter().any(|g| &g.key == key));
+
+        for group in groups {
+            let expanded = imp.expanded_keys.borrow().contains(&group.key);
+            let stack = NotificationStackWidget::new(group, expanded);
+
+            let panel = self.downgrade();
+            stack.connect_closure(
+                "dismiss-group",
+                false,
+                glib::closure_local!(move |stack: NotificationStackWidget| {
+                    if let Some(panel) = panel.upgrade() {
+                        panel.dismiss_group(&stack);
+                    }
+                }),
+            );
+            let panel = self.downgrade();
+            stack.connect_closure(
+                "expanded",
+                false,
+                glib::closure_local!(move |stack: NotificationStackWidget, expanded: bool| {
+                    if let Some(panel) = panel.upgrade() {
+                        let mut keys = panel.imp().expanded_keys.borrow_mut();
+                        if expanded { keys.insert(stack.group_key()); } else { keys.remove(&stack.group_key()); }
+                    }
+                }),
+            );
+            stacks_box.append(&stack);
+        }
+        if let Some(label) = imp.placeholder_label.borrow().as_ref() {
+            label.set_visible(groups.is_empty());
+        }
+    }
+
+    /// Connects to the notification center service and keeps the stacks up to date.
+    pub fn start_monitoring(&self) {
+        let imp = self.imp();
+        if imp.monitoring.replace(true) {
+            return;
+        }
+        let panel = self.downgrade();
+        glib::MainContext::default().spawn_local(async move {
+            let client = match NotificationCenterClient::new().await {
+                Ok(client) => Rc::new(client),
+                Err(e) => {
+                    tracing::warn!("NotificationCenterPanel: could not connect to the notification center: {}", e);
+                    return;
+                }
+            };
+            match panel.upgrade() {
+                Some(panel) => {
+                    panel.imp().client.replace(Some(client.clone()));
+                }
+                None => return,
+            }
+            let result = client
+                .watch_groups(|groups| {
+                    if let Some(panel) = panel.upgrade() {
+                        panel.set_groups(&groups);
+                    }
+                })
+                .await;
+            if let Err(e) = result {
+                tracing::warn!("NotificationCenterPanel: stopped following notification groups: {}", e);
+            }
+        });
+    }
+
+    fn dismiss_group(&self, stack: &NotificationStackWidget) {
+        let key = stack.group_key();
+        // Hide the stack right away; the refresh after the dismissal removes it for good.
+        stack.set_visible(false);
+        let Some(client) = self.imp().client.borrow().clone() else {
+            tracing::warn!("NotificationCenterPanel: not connected, cannot dismiss group '{}'", key);
+            return;
+        };
+        glib::MainContext::default().spawn_local(async move {
+            match client.dismiss_group(&key).await {
+                Ok(count) => tracing::info!("NotificationCenterPanel: dismissed {} notifications of '{}'", count, key),
+                Err(e) => tracing::warn!("NotificationCenterPanel: could not dismiss group '{}': {}", key, e),
+            }
+        });
+    }
 }
@@ -0,0 +1,168 @@
+use gtk::glib::{self, subclass::Signal};
+use gtk::subclass::prelude::*;
+use gtk::{prelude::*, Align, Box, Button, CompositeTemplate, Label, Orientation, Revealer, ToggleButton};
+use novade_domain::{Notification, NotificationGroup, NotificationUrgency};
+use once_cell::sync::Lazy;
+use std::cell::RefCell;
+
+static SIGNALS: Lazy<Vec<Signal>> = Lazy::new(|| {
+    vec![
+        Signal::builder("dismiss-group").action().build(),
+        Signal::builder("expanded").param_types([bool::static_type()]).build(),
+    ]
+});
+
+#[derive(CompositeTemplate, Default)]
+#[template(string = "")]
+pub struct NotificationStackWidget {
+    pub group_key: RefCell<String>,
+    pub app_name_label: RefCell<Option<Label>>,
+    pub count_label: RefCell<Option<Label>>,
+    pub expand_button: RefCell<Option<ToggleButton>>,
+    /// The latest notification, shown while collapsed.
+    pub latest_box: RefCell<Option<Box>>,
+    pub revealer: RefCell<Option<Revealer>>,
+    /// Every notification of the group, shown while expanded.
+    pub list_box: RefCell<Option<Box>>,
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for NotificationStackWidget {
+    const NAME: &'static str = "NovaDENotificationStackWidget";
+    type Type = super::NotificationStackWidget;
+    type ParentType = gtk::Box;
+
+    fn class_init(klass: &mut Self::Class) {
+        klass.set_css_name("notificationstackwidget");
+        klass.install_signals(&SIGNALS);
+    }
+
+    fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+        obj.init_template();
+    }
+}
+
+impl ObjectImpl for NotificationStackWidget {
+    fn constructed(&self) {
+        self.parent_constructed();
+        let obj = self.obj();
+
+        obj.set_orientation(Orientation::Vertical);
+        obj.set_spacing(4);
+        obj.set_margin_top(6);
+        obj.set_margin_bottom(6);
+        obj.set_margin_start(6);
+        obj.set_margin_end(6);
+        obj.add_css_class("notification-stack");
+
+        let header = Box::new(Orientation::Horizontal, 6);
+        let app_name_label = Label::builder().halign(Align::Start).hexpand(true).build();
+        app_name_label.add_css_class("notification-app-name");
+        let count_label = Label::new(None);
+        count_label.add_css_class("notification-count-badge");
+        count_label.set_visible(false);
+        let expand_button = ToggleButton::builder().icon_name("pan-down-symbolic").tooltip_text("Show all").build();
+        expand_button.add_css_class("flat");
+        let clear_button = Button::builder().icon_name("edit-clear-all-symbolic").tooltip_text("Dismiss all").build();
+        clear_button.add_css_class("flat");
+        clear_button.add_css_class("notification-close-button");
+        header.append(&app_name_label);
+        header.append(&count_label);
+        header.append(&expand_button);
+        header.append(&clear_button);
+
+        let latest_box = Box::new(Orientation::Vertical, 2);
+        let list_box = Box::new(Orientation::Vertical, 6);
+        let revealer = Revealer::builder().child(&list_box).reveal_child(false).build();
+
+        let self_obj = obj.clone();
+        clear_button.connect_clicked(move |_btn| {
+            tracing::info!("Dismissing notification group '{}'", self_obj.imp().group_key.borrow());
+            self_obj.emit_by_name::<()>("dismiss-group", &[]);
+        });
+        let self_obj = obj.clone();
+        expand_button.connect_toggled(move |button| {
+            self_obj.imp().show_expanded(button.is_active());
+            self_obj.emit_by_name::<()>("expanded", &[&button.is_active()]);
+        });
+
+        obj.append(&header);
+        obj.append(&latest_box);
+        obj.append(&revealer);
+        self.app_name_label.replace(Some(app_name_label));
+        self.count_label.replace(Some(count_label));
+        self.expand_button.replace(Some(expand_button));
+        self.latest_box.replace(Some(latest_box));
+        self.revealer.replace(Some(revealer));
+        self.list_box.replace(Some(list_box));
+    }
+}
+
+impl WidgetImpl for NotificationStackWidget {}
+impl BoxImpl for NotificationStackWidget {}
+
+impl NotificationStackWidget {
+    pub fn set_group(&self, group: &NotificationGroup, expanded: bool) {
+        let obj = self.obj();
+        self.group_key.replace(group.key.clone());
+        if group.urgency == NotificationUrgency::Critical {
+            obj.add_css_class("critical");
+        }
+
+        let heading = if group.title == group.application_name {
+            group.application_name.clone()
+        } else {
+            format!("{} · {}", group.application_name, group.title)
+        };
+        if let Some(label) = self.app_name_label.borrow().as_ref() {
+            label.set_text(&heading);
+        }
+        if let Some(label) = self.count_label.borrow().as_ref() {
+            let badge = super::count_badge(group.len());
+            label.set_text(badge.as_deref().unwrap_or(""));
+            label.set_visible(badge.is_some());
+            label.set_tooltip_text(Some(&format!("{} unread", group.unread_count)));
+        }
+        if let Some(button) = self.expand_button.borrow().as_ref() {
+            button.set_visible(group.len() > 1);
+            button.set_active(expanded && group.len() > 1);
+        }
+        if let Some(latest_box) = self.latest_box.borrow().as_ref() {
+            latest_box.append(&notification_row(group.latest()));
+        }
+        if let Some(list_box) = self.list_box.borrow().as_ref() {
+            for notification in &group.notifications {
+                list_box.append(&notification_row(notification));
+            }
+        }
+        self.show_expanded(expanded && group.len() > 1);
+    }
+
+    fn show_expanded(&self, expanded: bool) {
+        if let Some(latest_box) = self.latest_box.borrow().as_ref() {
+            latest_box.set_visible(!expanded);
+        }
+        if let Some(revealer) = self.revealer.borrow().as_ref() {
+            revealer.set_reveal_child(expanded);
+        }
+    }
+}
+
+fn notification_row(notification: &Notification) -> Box {
+    let row = Box::new(Orientation::Vertical, 2);
+    row.add_css_class("notification-item");
+    if !notification.is_read {
+        row.add_css_class("unread");
+    }
+    let summary = Label::builder().label(&notification.summary).halign(Align::Start).wrap(true).build();
+    summary.add_css_class("notification-summary");
+    row.append(&summary);
+    if let Some(body) = notification.body.as_deref().filter(|b| !b.is_empty()) {
+        // Bodies arrive sanitized to the markup the server advertises.
+        let body_label = Label::builder().halign(Align::Start).wrap(true).lines(3).build();
+        body_label.set_markup(body);
+        body_label.add_css_class("notification-body");
+        row.append(&body_label);
+    }
+    row
+}
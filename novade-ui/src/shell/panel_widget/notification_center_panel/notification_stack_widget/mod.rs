@@ -0,0 +1,49 @@
+use gtk::glib;
+use gtk::glib::subclass::prelude::*;
+use novade_domain::NotificationGroup;
+
+mod imp;
+
+glib::wrapper! {
+    pub struct NotificationStackWidget(ObjectSubclass<imp::NotificationStackWidget>)
+        @extends gtk::Widget, gtk::Box, @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget, gtk::Orientable;
+}
+
+impl NotificationStackWidget {
+    /// A group of notifications collapsed into one stack: the latest notification with a count,
+    /// expanding to list all of them. Emits `dismiss-group` when its clear button is clicked and
+    /// `expanded` (bool) when it is expanded or collapsed.
+    pub fn new(group: &NotificationGroup, expanded: bool) -> Self {
+        let obj: Self = glib::Object::new(&[]);
+        obj.imp().set_group(group, expanded);
+        obj
+    }
+
+    pub fn group_key(&self) -> String {
+        self.imp().group_key.borrow().clone()
+    }
+}
+
+/// Most notifications a count badge spells out.
+const MAX_BADGE_COUNT: usize = 99;
+
+/// The count badge of a stack, or `None` for a single notification.
+pub fn count_badge(count: usize) -> Option<String> {
+    match count {
+        0 | 1 => None,
+        n if n > MAX_BADGE_COUNT => Some(format!("{}+", MAX_BADGE_COUNT)),
+        n => Some(n.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count_badge() {
+        assert_eq!(count_badge(1), None);
+        assert_eq!(count_badge(30).as_deref(), Some("30"));
+        assert_eq!(count_badge(250).as_deref(), Some("99+"));
+    }
+}
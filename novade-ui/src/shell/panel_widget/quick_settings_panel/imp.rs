@@ -1,10 +1,16 @@
 use gtk::glib;
 use gtk::subclass::prelude::*;
-use gtk::{Box, Label, Button, Switch, Scale, Spinner, CompositeTemplate, Orientation, Adjustment, Align, prelude::*};
+use gtk::{Box, Label, Button, Switch, Scale, Spinner, DropDown, CompositeTemplate, Orientation, Adjustment, Align, prelude::*};
 use tracing;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::rc::Rc;
+use std::sync::Arc;
+use tokio::runtime::Handle;
 use crate::shell::ui_settings_service::UISettingsService;
+use novade_system::audio_management::{AudioDeviceType, AudioManager};
+use novade_system::compositor::ipc::protocol::IpcRequest;
+use novade_system::compositor::keybindings::KeybindingAction;
+use novade_system::compositor::night_light::NightLightMode;
 
 // Using RefCell<Option<WidgetType>> for widgets stored in the struct,
 // as they are initialized in `constructed`.
@@ -13,7 +19,24 @@ use crate::shell::ui_settings_service::UISettingsService;
 pub struct QuickSettingsPanelWidget {
     // Store widgets to interact with them for setting initial state and connecting signals
     pub dark_mode_switch: RefCell<Option<Switch>>,
+    pub night_light_switch: RefCell<Option<Switch>>,
     pub volume_scale: RefCell<Option<Scale>>,
+    pub focus_dropdown: RefCell<Option<DropDown>>,
+    pub focus_status_label: RefCell<Option<Label>>,
+    /// Focus mode per dropdown entry; the first entry, `None`, turns focus off.
+    pub focus_mode_ids: RefCell<Vec<Option<String>>>,
+    /// Set while the dropdown is updated to match the server, so it is not sent back.
+    pub updating_focus: Cell<bool>,
+    /// The sound server the volume slider controls, and the runtime its requests run on.
+    pub audio_manager: RefCell<Option<Arc<dyn AudioManager>>>,
+    pub audio_tokio_handle: RefCell<Option<Handle>>,
+    /// Set while the slider is updated to match the sound server, so it is not sent back.
+    pub updating_volume: Cell<bool>,
+    /// The per-application mixer, one row per application playing sound.
+    pub app_mixer_box: RefCell<Option<Box>>,
+    pub app_mixer_rows: RefCell<Vec<super::AppMixerRow>>,
+    /// Set while the mixer rows are updated to match the sound server, so they are not sent back.
+    pub updating_app_mixer: Cell<bool>,
     // wifi_button is not managed by UISettingsService in this phase
     
     pub ui_settings_service: RefCell<Option<Rc<UISettingsService>>>,
@@ -28,7 +51,18 @@ impl ObjectSubclass for QuickSettingsPanelWidget {
     fn new() -> Self {
         Self {
             dark_mode_switch: RefCell::new(None),
+            night_light_switch: RefCell::new(None),
             volume_scale: RefCell::new(None),
+            focus_dropdown: RefCell::new(None),
+            focus_status_label: RefCell::new(None),
+            focus_mode_ids: RefCell::new(Vec::new()),
+            updating_focus: Cell::new(false),
+            audio_manager: RefCell::new(None),
+            audio_tokio_handle: RefCell::new(None),
+            updating_volume: Cell::new(false),
+            app_mixer_box: RefCell::new(None),
+            app_mixer_rows: RefCell::new(Vec::new()),
+            updating_app_mixer: Cell::new(false),
             ui_settings_service: RefCell::new(None),
         }
     }
@@ -86,6 +120,77 @@ impl ObjectImpl for QuickSettingsPanelWidget {
         dark_mode_box.append(&dark_mode_switch_widget); 
         obj.append(&dark_mode_box);
 
+        // --- Night Light Section ---
+        // Talks to the compositor directly; toggling overrides the schedule until its next
+        // sunset or sunrise.
+        let night_light_box = Box::new(Orientation::Horizontal, 6);
+        night_light_box.set_halign(Align::Fill);
+        let night_light_label = Label::new(Some("Night Light"));
+        night_light_label.set_halign(Align::Start);
+        night_light_label.set_hexpand(true);
+
+        let night_light_switch_widget = Switch::new();
+        night_light_switch_widget.set_halign(Align::End);
+        self.night_light_switch.replace(Some(night_light_switch_widget.clone()));
+
+        night_light_switch_widget.connect_state_set(|_switch, active| {
+            tracing::info!("Night Light Switch toggled by UI: {}", active);
+            let mode = if active { NightLightMode::On } else { NightLightMode::Off };
+            gio::spawn_blocking(move || {
+                let request = IpcRequest::RunAction { action: KeybindingAction::SetNightLight { mode } };
+                if let Err(e) = super::compositor_request(&request).and_then(|reply| reply.into_data::<()>()) {
+                    tracing::warn!("Failed to switch night light: {}", e);
+                }
+            });
+            glib::Propagation::Proceed
+        });
+        // The schedule may have switched night light since the panel was last shown.
+        obj.connect_map(|panel| panel.refresh_night_light());
+
+        night_light_box.append(&night_light_label);
+        night_light_box.append(&night_light_switch_widget);
+        obj.append(&night_light_box);
+
+        // --- Focus Section ---
+        // Talks to the notification server; a manual choice holds until a schedule or trigger
+        // changes.
+        let focus_box = Box::new(Orientation::Vertical, 2);
+        let focus_row = Box::new(Orientation::Horizontal, 6);
+        focus_row.set_halign(Align::Fill);
+        let focus_label = Label::new(Some("Focus"));
+        focus_label.set_halign(Align::Start);
+        focus_label.set_hexpand(true);
+
+        let focus_dropdown_widget = DropDown::from_strings(&["Off"]);
+        focus_dropdown_widget.set_halign(Align::End);
+        self.focus_dropdown.replace(Some(focus_dropdown_widget.clone()));
+        let focus_status_label_widget = Label::new(None);
+        focus_status_label_widget.set_halign(Align::Start);
+        focus_status_label_widget.add_css_class("dim-label");
+        focus_status_label_widget.set_visible(false);
+        self.focus_status_label.replace(Some(focus_status_label_widget.clone()));
+
+        let self_obj = obj.clone();
+        focus_dropdown_widget.connect_selected_notify(move |dropdown| {
+            let imp = self_obj.imp();
+            if imp.updating_focus.get() {
+                return;
+            }
+            let Some(mode_id) = imp.focus_mode_ids.borrow().get(dropdown.selected() as usize).cloned() else {
+                return;
+            };
+            tracing::info!("Focus mode selected by UI: {:?}", mode_id);
+            self_obj.activate_focus_mode(mode_id);
+        });
+        // Schedules and triggers may have switched focus since the panel was last shown.
+        obj.connect_map(|panel| panel.refresh_focus());
+
+        focus_row.append(&focus_label);
+        focus_row.append(&focus_dropdown_widget);
+        focus_box.append(&focus_row);
+        focus_box.append(&focus_status_label_widget);
+        obj.append(&focus_box);
+
         // --- Volume Section ---
         let volume_box = Box::new(Orientation::Horizontal, 6);
         volume_box.set_halign(Align::Fill);
@@ -100,11 +205,29 @@ impl ObjectImpl for QuickSettingsPanelWidget {
         // Store the widget in the RefCell
         self.volume_scale.replace(Some(volume_scale_widget.clone()));
         
-        let service_ref_vol = self.ui_settings_service.clone();
+        // Sets the default output's volume once connected to the sound server, the volume
+        // setting until then.
+        let self_obj = obj.clone();
         volume_scale_widget.connect_value_changed(move |scale| {
+            let imp = self_obj.imp();
+            if imp.updating_volume.get() {
+                return;
+            }
             let value = scale.value();
             tracing::info!("Volume Scale changed by UI: {}", value);
-            if let Some(service_rc) = service_ref_vol.borrow().as_ref() {
+            let audio = imp.audio_manager.borrow().clone();
+            let handle = imp.audio_tokio_handle.borrow().clone();
+            if let (Some(audio), Some(handle)) = (audio, handle) {
+                handle.spawn(async move {
+                    let result = match audio.get_default_device(AudioDeviceType::Output).await {
+                        Ok(device) => audio.set_device_volume(device.id(), value / 100.0).await,
+                        Err(e) => Err(e),
+                    };
+                    if let Err(e) = result {
+                        tracing::warn!("Failed to set the volume: {}", e);
+                    }
+                });
+            } else if let Some(service_rc) = imp.ui_settings_service.borrow().as_ref() {
                 let service_clone = service_rc.clone();
                 service_clone.tokio_handle().spawn(async move {
                     service_clone.set_volume(value).await;
@@ -116,6 +239,15 @@ impl ObjectImpl for QuickSettingsPanelWidget {
         volume_box.append(&volume_scale_widget); 
         obj.append(&volume_box);
 
+        // --- Per-Application Mixer Section ---
+        // Filled with the applications playing sound once connected to the sound server; hidden
+        // while there are none.
+        let app_mixer_box_widget = Box::new(Orientation::Vertical, 6);
+        app_mixer_box_widget.set_visible(false);
+        self.app_mixer_box.replace(Some(app_mixer_box_widget.clone()));
+        obj.append(&app_mixer_box_widget);
+        obj.connect_audio();
+
         // --- WiFi Section (unchanged functionality for this task) ---
         let wifi_box = Box::new(Orientation::Horizontal, 6);
         wifi_box.set_halign(Align::Fill);
@@ -1,12 +1,81 @@
 use gtk::glib;
 use gtk::glib::subclass::prelude::*;
-use gtk::{prelude::*, Box}; 
+use gtk::{prelude::*, Box, DropDown, Label, Orientation, Scale}; 
 use std::rc::Rc;
+use std::sync::Arc;
+use tokio::runtime::Handle;
+use tokio::sync::broadcast::error::RecvError;
 use crate::shell::ui_settings_service::UISettingsService;
+use super::compositor_request;
+use novade_system::compositor::ipc::protocol::IpcRequest;
+use novade_system::compositor::night_light::NightLightStatus;
+use novade_system::audio_management::{AudioDevice, AudioDeviceType, AudioEvent, AudioManager, AudioStream, PulseAudioManager};
+use novade_domain::{ActiveFocus, FocusActivation, FocusMode, FocusTrigger};
+use crate::notification_client::NotificationCenterClient;
+use crate::audio_profiles_client::AudioProfilesClient;
 use tracing; // For logging
 
 mod imp;
 
+/// Why the active focus mode is on, shown under the focus dropdown.
+pub fn focus_status_text(active: &ActiveFocus) -> String {
+    let reason = match active.activation {
+        FocusActivation::Manual => return format!("{} until turned off", active.mode_name),
+        FocusActivation::Schedule => "scheduled",
+        FocusActivation::Trigger(FocusTrigger::Fullscreen) => "while an app is fullscreen",
+        FocusActivation::Trigger(FocusTrigger::Screencast) => "while the screen is recorded",
+    };
+    format!("{} {}", active.mode_name, reason)
+}
+
+/// The playback streams of one application, shown as one row of the per-application mixer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AppStreams {
+    pub app_id: String,
+    /// The application's display name.
+    pub name: String,
+    pub stream_ids: Vec<String>,
+    /// Volume and device of the application's first stream, which the row shows.
+    pub volume: f64,
+    pub device_id: Option<String>,
+}
+
+/// Groups playback streams by application, in the order the applications started playing.
+/// Streams without an application ID cannot be remembered and are left out.
+pub fn group_app_streams(streams: &[AudioStream]) -> Vec<AppStreams> {
+    let mut apps: Vec<AppStreams> = Vec::new();
+    for stream in streams.iter().filter(|s| s.stream_type() == AudioDeviceType::Output && !s.app_id().is_empty()) {
+        if let Some(app) = apps.iter_mut().find(|app| app.app_id == stream.app_id()) {
+            app.stream_ids.push(stream.id().to_string());
+            continue;
+        }
+        apps.push(AppStreams {
+            app_id: stream.app_id().to_string(),
+            name: stream.application().to_string(),
+            stream_ids: vec![stream.id().to_string()],
+            volume: stream.volume(),
+            device_id: stream.device_id().map(str::to_string),
+        });
+    }
+    apps
+}
+
+/// The widgets of one mixer row, kept to follow changes made elsewhere.
+pub struct AppMixerRow {
+    stream_ids: Vec<String>,
+    /// Output device ID per dropdown entry.
+    device_ids: Vec<String>,
+    scale: Scale,
+    dropdown: DropDown,
+}
+
+/// A change made in the per-application mixer.
+#[derive(Debug, Clone)]
+enum AppMixerChange {
+    Volume(f64),
+    Device(String),
+}
+
 glib::wrapper! {
     pub struct QuickSettingsPanelWidget(ObjectSubclass<imp::QuickSettingsPanelWidget>)
         @extends gtk::Widget, gtk::Box, @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget, gtk::Orientable;
@@ -61,4 +130,375 @@ impl QuickSettingsPanelWidget {
             }));
         }
     }
+
+    /// Connects the volume slider to the user's sound server; needs a Tokio runtime to run
+    /// the connection on.
+    pub fn connect_audio(&self) {
+        let Ok(handle) = Handle::try_current() else {
+            tracing::warn!("QuickSettingsPanel: no Tokio runtime, volume slider not connected to the sound server");
+            return;
+        };
+        let panel = self.clone();
+        glib::MainContext::default().spawn_local(async move {
+            match handle.spawn(PulseAudioManager::new()).await {
+                Ok(Ok(manager)) => panel.set_audio_manager(Arc::new(manager), handle),
+                Ok(Err(e)) => tracing::warn!("QuickSettingsPanel: could not connect to the sound server: {}", e),
+                Err(e) => tracing::warn!("QuickSettingsPanel: sound server connection task failed: {}", e),
+            }
+        });
+    }
+
+    /// Makes the volume slider show and set the default output's volume, following changes
+    /// made by other mixers, hotkeys or device switches as they happen.
+    pub fn set_audio_manager(&self, audio: Arc<dyn AudioManager>, tokio_handle: Handle) {
+        let imp = self.imp();
+        imp.audio_manager.replace(Some(audio.clone()));
+        imp.audio_tokio_handle.replace(Some(tokio_handle));
+        self.refresh_volume();
+        self.refresh_app_mixer();
+
+        let panel = self.downgrade();
+        glib::MainContext::default().spawn_local(async move {
+            let mut events = match audio.subscribe().await {
+                Ok(events) => events,
+                Err(e) => {
+                    tracing::warn!("QuickSettingsPanel: could not follow volume changes: {}", e);
+                    return;
+                }
+            };
+            loop {
+                let event = events.recv().await;
+                let Some(panel) = panel.upgrade() else {
+                    break;
+                };
+                match event {
+                    Ok(event) => panel.follow_audio_event(&event),
+                    Err(RecvError::Lagged(_)) => {
+                        panel.refresh_volume();
+                        panel.refresh_app_mixer();
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        });
+    }
+
+    fn follow_audio_event(&self, event: &AudioEvent) {
+        let is_default_output = |device: &AudioDevice| device.device_type() == AudioDeviceType::Output && device.is_default();
+        match event {
+            AudioEvent::DeviceChanged(device) => {
+                if is_default_output(device) {
+                    self.show_volume(device);
+                }
+            }
+            AudioEvent::DeviceAdded(device) => {
+                if is_default_output(device) {
+                    self.show_volume(device);
+                }
+                // A new output is a new choice in the mixer's dropdowns.
+                self.refresh_app_mixer();
+            }
+            AudioEvent::DeviceRemoved(_) | AudioEvent::StreamAdded(_) | AudioEvent::StreamRemoved(_) => self.refresh_app_mixer(),
+            AudioEvent::StreamChanged(stream) => self.show_app_stream(stream),
+        }
+    }
+
+    /// Shows the default output's current volume on the slider.
+    pub fn refresh_volume(&self) {
+        let imp = self.imp();
+        let audio = imp.audio_manager.borrow().clone();
+        let handle = imp.audio_tokio_handle.borrow().clone();
+        let (Some(audio), Some(handle)) = (audio, handle) else {
+            return;
+        };
+        let panel = self.clone();
+        glib::MainContext::default().spawn_local(async move {
+            match handle.spawn(async move { audio.get_default_device(AudioDeviceType::Output).await }).await {
+                Ok(Ok(device)) => panel.show_volume(&device),
+                Ok(Err(e)) => tracing::warn!("QuickSettingsPanel: could not query the volume: {}", e),
+                Err(e) => tracing::warn!("QuickSettingsPanel: volume query task failed: {}", e),
+            }
+        });
+    }
+
+    fn show_volume(&self, device: &AudioDevice) {
+        let imp = self.imp();
+        let Some(scale) = imp.volume_scale.borrow().clone() else {
+            return;
+        };
+        imp.updating_volume.set(true);
+        scale.set_value(device.volume() * 100.0);
+        imp.updating_volume.set(false);
+    }
+
+    /// Rebuilds the per-application mixer from the sound server's playback streams.
+    pub fn refresh_app_mixer(&self) {
+        let imp = self.imp();
+        let audio = imp.audio_manager.borrow().clone();
+        let handle = imp.audio_tokio_handle.borrow().clone();
+        let (Some(audio), Some(handle)) = (audio, handle) else {
+            return;
+        };
+        let panel = self.clone();
+        glib::MainContext::default().spawn_local(async move {
+            let state = handle
+                .spawn(async move {
+                    let streams = audio.get_streams().await;
+                    let devices = audio.get_devices().await;
+                    streams.and_then(|streams| devices.map(|devices| (streams, devices)))
+                })
+                .await;
+            match state {
+                Ok(Ok((streams, devices))) => {
+                    let outputs: Vec<AudioDevice> = devices.into_iter().filter(|d| d.device_type() == AudioDeviceType::Output).collect();
+                    panel.show_app_mixer(&group_app_streams(&streams), &outputs);
+                }
+                Ok(Err(e)) => tracing::warn!("QuickSettingsPanel: could not query the playing applications: {}", e),
+                Err(e) => tracing::warn!("QuickSettingsPanel: playing applications query task failed: {}", e),
+            }
+        });
+    }
+
+    fn show_app_mixer(&self, apps: &[AppStreams], outputs: &[AudioDevice]) {
+        let imp = self.imp();
+        let Some(container) = imp.app_mixer_box.borrow().clone() else {
+            return;
+        };
+        while let Some(child) = container.first_child() {
+            container.remove(&child);
+        }
+
+        let device_ids: Vec<String> = outputs.iter().map(|d| d.id().to_string()).collect();
+        let device_names: Vec<&str> = outputs.iter().map(AudioDevice::name).collect();
+        let mut rows = Vec::new();
+        for app in apps {
+            let row_box = Box::new(Orientation::Vertical, 2);
+            let header = Box::new(Orientation::Horizontal, 6);
+            let label = Label::new(Some(&app.name));
+            label.set_halign(gtk::Align::Start);
+            label.set_hexpand(true);
+            label.set_ellipsize(gtk::pango::EllipsizeMode::End);
+
+            let dropdown = DropDown::from_strings(&device_names);
+            dropdown.set_halign(gtk::Align::End);
+            if let Some(position) = app.device_id.as_deref().and_then(|id| device_ids.iter().position(|d| d == id)) {
+                dropdown.set_selected(position as u32);
+            }
+            let scale = Scale::with_range(Orientation::Horizontal, 0.0, 100.0, 1.0);
+            scale.set_hexpand(true);
+            scale.set_draw_value(false);
+            scale.set_value(app.volume * 100.0);
+
+            let panel = self.downgrade();
+            let (app_id, stream_ids) = (app.app_id.clone(), app.stream_ids.clone());
+            scale.connect_value_changed(move |scale| {
+                let Some(panel) = panel.upgrade() else {
+                    return;
+                };
+                if !panel.imp().updating_app_mixer.get() {
+                    panel.change_app(app_id.clone(), stream_ids.clone(), AppMixerChange::Volume(scale.value() / 100.0));
+                }
+            });
+            let panel = self.downgrade();
+            let (app_id, stream_ids, ids) = (app.app_id.clone(), app.stream_ids.clone(), device_ids.clone());
+            dropdown.connect_selected_notify(move |dropdown| {
+                let Some(panel) = panel.upgrade() else {
+                    return;
+                };
+                let Some(device_id) = ids.get(dropdown.selected() as usize) else {
+                    return;
+                };
+                if !panel.imp().updating_app_mixer.get() {
+                    tracing::info!("Output of {} selected by UI: {}", app_id, device_id);
+                    panel.change_app(app_id.clone(), stream_ids.clone(), AppMixerChange::Device(device_id.clone()));
+                }
+            });
+
+            header.append(&label);
+            header.append(&dropdown);
+            row_box.append(&header);
+            row_box.append(&scale);
+            container.append(&row_box);
+            rows.push(AppMixerRow { stream_ids: app.stream_ids.clone(), device_ids: device_ids.clone(), scale, dropdown });
+        }
+        imp.app_mixer_rows.replace(rows);
+        container.set_visible(!apps.is_empty());
+    }
+
+    /// Shows a stream's volume and device on its application's row, if the row follows it.
+    fn show_app_stream(&self, stream: &AudioStream) {
+        let imp = self.imp();
+        let rows = imp.app_mixer_rows.borrow();
+        let Some(row) = rows.iter().find(|row| row.stream_ids.first().map(String::as_str) == Some(stream.id())) else {
+            return;
+        };
+        imp.updating_app_mixer.set(true);
+        row.scale.set_value(stream.volume() * 100.0);
+        if let Some(position) = stream.device_id().and_then(|id| row.device_ids.iter().position(|d| d == id)) {
+            row.dropdown.set_selected(position as u32);
+        }
+        imp.updating_app_mixer.set(false);
+    }
+
+    /// Applies a mixer change through the audio profiles service, which remembers it for the
+    /// application's future streams. Without the service, the change applies to the current
+    /// streams only.
+    fn change_app(&self, app_id: String, stream_ids: Vec<String>, change: AppMixerChange) {
+        let imp = self.imp();
+        let audio = imp.audio_manager.borrow().clone();
+        let handle = imp.audio_tokio_handle.borrow().clone();
+        glib::MainContext::default().spawn_local(async move {
+            let result = match AudioProfilesClient::new().await {
+                Ok(client) => match &change {
+                    AppMixerChange::Volume(volume) => client.set_app_volume(&app_id, *volume).await,
+                    AppMixerChange::Device(device_id) => client.set_app_device(&app_id, device_id).await,
+                },
+                Err(e) => Err(e),
+            };
+            let Err(e) = result else {
+                return;
+            };
+            tracing::debug!("QuickSettingsPanel: {:?} of {} applies without being remembered: {}", change, app_id, e);
+            let (Some(audio), Some(handle)) = (audio, handle) else {
+                return;
+            };
+            handle.spawn(async move {
+                for id in &stream_ids {
+                    let result = match &change {
+                        AppMixerChange::Volume(volume) => audio.set_stream_volume(id, *volume).await,
+                        AppMixerChange::Device(device_id) => audio.move_stream(id, device_id).await,
+                    };
+                    if let Err(e) = result {
+                        tracing::warn!("QuickSettingsPanel: could not apply {:?} to stream {}: {}", change, id, e);
+                    }
+                }
+            });
+        });
+    }
+
+    /// Shows the compositor's current night light state on the switch.
+    pub fn refresh_night_light(&self) {
+        let Some(switch) = self.imp().night_light_switch.borrow().clone() else {
+            return;
+        };
+        glib::MainContext::default().spawn_local(glib::clone!(@weak switch => async move {
+            let status = gio::spawn_blocking(|| {
+                compositor_request(&IpcRequest::GetNightLight).and_then(|reply| reply.into_data::<NightLightStatus>())
+            })
+            .await;
+            match status {
+                Ok(Ok(status)) => {
+                    // Block signal to prevent sending the state back to the compositor
+                    let handler_id = switch.block_signal_by_name("state-set");
+                    switch.set_active(status.active);
+                    if let Some(id) = handler_id {
+                        switch.unblock_signal(id);
+                    }
+                }
+                Ok(Err(e)) => tracing::warn!("QuickSettingsPanel: could not query night light: {}", e),
+                Err(_) => tracing::warn!("QuickSettingsPanel: night light query panicked"),
+            }
+        }));
+    }
+
+    /// Fills the focus dropdown with the server's modes and selects the active one.
+    pub fn refresh_focus(&self) {
+        let panel = self.clone();
+        glib::MainContext::default().spawn_local(async move {
+            let state = match NotificationCenterClient::new().await {
+                Ok(client) => match client.focus_modes().await {
+                    Ok(modes) => client.active_focus().await.map(|active| (modes, active)),
+                    Err(e) => Err(e),
+                },
+                Err(e) => Err(e),
+            };
+            match state {
+                Ok((modes, active)) => panel.show_focus(&modes, active.as_ref()),
+                Err(e) => tracing::warn!("QuickSettingsPanel: could not query focus modes: {}", e),
+            }
+        });
+    }
+
+    fn show_focus(&self, modes: &[FocusMode], active: Option<&ActiveFocus>) {
+        let imp = self.imp();
+        let Some(dropdown) = imp.focus_dropdown.borrow().clone() else {
+            return;
+        };
+        let mut ids = vec![None];
+        let mut names = vec!["Off".to_string()];
+        for mode in modes {
+            ids.push(Some(mode.id.clone()));
+            names.push(mode.name.clone());
+        }
+        let selected = active.and_then(|a| ids.iter().position(|id| id.as_deref() == Some(a.mode_id.as_str()))).unwrap_or(0);
+        let names: Vec<&str> = names.iter().map(String::as_str).collect();
+
+        imp.updating_focus.set(true);
+        imp.focus_mode_ids.replace(ids);
+        dropdown.set_model(Some(&gtk::StringList::new(&names)));
+        dropdown.set_selected(selected as u32);
+        imp.updating_focus.set(false);
+
+        if let Some(label) = imp.focus_status_label.borrow().as_ref() {
+            label.set_text(&active.map(focus_status_text).unwrap_or_default());
+            label.set_visible(active.is_some());
+        }
+    }
+
+    fn activate_focus_mode(&self, mode_id: Option<String>) {
+        let panel = self.clone();
+        glib::MainContext::default().spawn_local(async move {
+            let result = match NotificationCenterClient::new().await {
+                Ok(client) => client.activate_focus_mode(mode_id.as_deref()).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                tracing::warn!("QuickSettingsPanel: could not switch focus mode to {:?}: {}", mode_id, e);
+            }
+            // Show what the server made of it, e.g. the previous mode after a failure.
+            panel.refresh_focus();
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_focus_status_text() {
+        let mut active = ActiveFocus {
+            mode_id: "sleep".to_string(),
+            mode_name: "Sleep".to_string(),
+            activation: FocusActivation::Schedule,
+            since: chrono::Utc::now(),
+        };
+        assert_eq!(focus_status_text(&active), "Sleep scheduled");
+        active.activation = FocusActivation::Trigger(FocusTrigger::Screencast);
+        assert_eq!(focus_status_text(&active), "Sleep while the screen is recorded");
+        active.activation = FocusActivation::Manual;
+        assert_eq!(focus_status_text(&active), "Sleep until turned off");
+    }
+
+    #[test]
+    fn test_group_app_streams() {
+        let stream = |id: &str, app_id: &str, volume: f64| {
+            AudioStream::new(id, "Playback", app_id, volume, false).with_device("headphones")
+        };
+        let streams = vec![
+            stream("sink-input:1", "spotify", 0.4),
+            stream("sink-input:2", "firefox", 0.8),
+            stream("sink-input:3", "spotify", 0.6),
+            stream("source-output:4", "zoom", 1.0).with_type(AudioDeviceType::Input),
+            stream("sink-input:5", "", 1.0),
+        ];
+
+        let apps = group_app_streams(&streams);
+        assert_eq!(apps.len(), 2);
+        assert_eq!(apps[0].app_id, "spotify");
+        assert_eq!(apps[0].stream_ids, vec!["sink-input:1", "sink-input:3"]);
+        assert_eq!(apps[0].volume, 0.4);
+        assert_eq!(apps[0].device_id.as_deref(), Some("headphones"));
+        assert_eq!(apps[1].app_id, "firefox");
+    }
 }
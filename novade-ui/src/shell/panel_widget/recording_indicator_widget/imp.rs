@@ -0,0 +1,183 @@
+use gtk::glib;
+use gtk::subclass::prelude::*;
+use gtk::{prelude::*, Box, Button, CompositeTemplate, Label, Orientation};
+use std::cell::{Cell, RefCell};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use novade_system::compositor::ipc::protocol::{default_socket_path, IpcEvent, IpcEventKind, IpcRequest};
+use novade_system::compositor::ipc::server::IpcClient;
+use novade_system::compositor::keybindings::KeybindingAction;
+use novade_system::compositor::recorder::RecordingStatus;
+use tracing;
+
+use super::super::compositor_request;
+
+/// Wait before reconnecting when the compositor socket goes away.
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+#[derive(CompositeTemplate, Default)]
+#[template(string = "")]
+pub struct RecordingIndicatorWidget {
+    pub label: RefCell<Option<Label>>,
+    /// When the running recording started, by the panel's clock.
+    pub started_at: Cell<Option<Instant>>,
+    pub status_receiver: RefCell<Option<mpsc::Receiver<RecordingStatus>>>,
+    pub update_source_id: RefCell<Option<glib::SourceId>>,
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for RecordingIndicatorWidget {
+    const NAME: &'static str = "NovaDERecordingIndicatorWidget";
+    type Type = super::RecordingIndicatorWidget;
+    type ParentType = gtk::Button;
+
+    fn class_init(klass: &mut Self::Class) {
+        klass.set_css_name("recordingindicatorwidget");
+    }
+
+    fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+        obj.init_template();
+    }
+}
+
+impl ObjectImpl for RecordingIndicatorWidget {
+    fn constructed(&self) {
+        self.parent_constructed();
+        let obj = self.obj();
+
+        let content = Box::new(Orientation::Horizontal, 6);
+        let dot = Label::new(Some("●"));
+        dot.add_css_class("error"); // Red in the default theme
+        let label = Label::new(Some("0:00"));
+        label.add_css_class("numeric");
+        content.append(&dot);
+        content.append(&label);
+        obj.set_child(Some(&content));
+        obj.add_css_class("flat");
+        obj.set_visible(false);
+        self.label.replace(Some(label));
+
+        obj.connect_clicked(|_button| {
+            std::thread::spawn(|| {
+                let request = IpcRequest::RunAction { action: KeybindingAction::StopRecording };
+                match compositor_request(&request) {
+                    Ok(reply) if !reply.success => {
+                        tracing::warn!("RecordingIndicator: compositor refused to stop the recording: {:?}", reply.error)
+                    }
+                    Ok(_) => {}
+                    Err(e) => tracing::warn!("RecordingIndicator: could not stop the recording: {}", e),
+                }
+            });
+        });
+    }
+
+    fn dispose(&self) {
+        if let Some(source_id) = self.update_source_id.take() {
+            source_id.remove();
+        }
+    }
+}
+
+impl WidgetImpl for RecordingIndicatorWidget {}
+impl ButtonImpl for RecordingIndicatorWidget {}
+
+impl RecordingIndicatorWidget {
+    pub fn start_monitoring(&self) {
+        if self.update_source_id.borrow().is_some() {
+            return;
+        }
+        let (sender, receiver) = mpsc::channel();
+        self.status_receiver.replace(Some(receiver));
+        std::thread::Builder::new()
+            .name("recording-indicator".into())
+            .spawn(move || follow_recording(sender))
+            .expect("failed to spawn the recording indicator thread");
+
+        let widget_weak = self.obj().downgrade();
+        let source_id = glib::timeout_add_seconds_local(1, move || {
+            let Some(widget) = widget_weak.upgrade() else {
+                return glib::ControlFlow::Break;
+            };
+            let imp = widget.imp();
+            let statuses: Vec<RecordingStatus> = match imp.status_receiver.borrow().as_ref() {
+                Some(receiver) => receiver.try_iter().collect(),
+                None => Vec::new(),
+            };
+            for status in statuses {
+                imp.apply_status(&status);
+            }
+            imp.update_elapsed();
+            glib::ControlFlow::Continue
+        });
+        self.update_source_id.replace(Some(source_id));
+    }
+
+    fn apply_status(&self, status: &RecordingStatus) {
+        let obj = self.obj();
+        if let Some(error) = &status.error {
+            tracing::warn!("RecordingIndicator: recording failed: {}", error);
+        }
+        if !status.active {
+            self.started_at.set(None);
+            obj.set_visible(false);
+            return;
+        }
+        let started_at = Instant::now().checked_sub(Duration::from_millis(status.duration_ms)).unwrap_or_else(Instant::now);
+        self.started_at.set(Some(started_at));
+        let path = status.path.as_ref().map(|p| p.display().to_string()).unwrap_or_default();
+        obj.set_tooltip_text(Some(&format!("Recording to {}\nClick to stop", path)));
+        obj.set_visible(true);
+    }
+
+    fn update_elapsed(&self) {
+        if let (Some(started_at), Some(label)) = (self.started_at.get(), self.label.borrow().as_ref()) {
+            label.set_text(&super::format_elapsed(started_at.elapsed()));
+        }
+    }
+}
+
+/// Sends the current recording status, then every change, until the widget is gone.
+/// Reconnects when the compositor restarts.
+fn follow_recording(sender: mpsc::Sender<RecordingStatus>) {
+    loop {
+        let initial = compositor_request(&IpcRequest::GetRecording).and_then(|reply| reply.into_data::<RecordingStatus>());
+        match initial {
+            Ok(status) => {
+                if sender.send(status).is_err() {
+                    return;
+                }
+            }
+            Err(e) => tracing::debug!("RecordingIndicator: could not query the recording: {}", e),
+        }
+
+        let events = default_socket_path()
+            .ok_or_else(|| "compositor IPC socket not found".to_string())
+            .and_then(|path| IpcClient::connect(&path).map_err(|e| e.to_string()))
+            .and_then(|client| client.subscribe(vec![IpcEventKind::Recording]).map_err(|e| e.to_string()));
+        match events {
+            Ok(events) => {
+                for event in events {
+                    match event {
+                        Ok(IpcEvent::Recording { status }) => {
+                            if sender.send(status).is_err() {
+                                return;
+                            }
+                        }
+                        Ok(_) => {}
+                        Err(e) => {
+                            tracing::debug!("RecordingIndicator: event stream ended: {}", e);
+                            break;
+                        }
+                    }
+                }
+            }
+            Err(e) => tracing::debug!("RecordingIndicator: could not subscribe to recording events: {}", e),
+        }
+        // A recording cannot outlive the compositor; clear the indicator while it is gone.
+        if sender.send(RecordingStatus::default()).is_err() {
+            return;
+        }
+        std::thread::sleep(RECONNECT_DELAY);
+    }
+}
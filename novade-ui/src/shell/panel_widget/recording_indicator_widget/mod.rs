@@ -0,0 +1,46 @@
+use gtk::glib;
+use gtk::glib::subclass::prelude::*;
+use std::time::Duration;
+
+mod imp;
+
+glib::wrapper! {
+    pub struct RecordingIndicatorWidget(ObjectSubclass<imp::RecordingIndicatorWidget>)
+        @extends gtk::Widget, gtk::Button, @implements gtk::Accessible, gtk::Actionable, gtk::Buildable, gtk::ConstraintTarget;
+}
+
+impl RecordingIndicatorWidget {
+    /// A panel indicator shown while the compositor records the screen. Clicking it stops the
+    /// recording.
+    pub fn new() -> Self {
+        glib::Object::new(&[])
+    }
+
+    /// Queries the current recording and follows `recording` events from the compositor.
+    pub fn start_monitoring(&self) {
+        self.imp().start_monitoring();
+    }
+}
+
+/// Elapsed recording time as `M:SS`, or `H:MM:SS` from an hour on.
+pub fn format_elapsed(elapsed: Duration) -> String {
+    let seconds = elapsed.as_secs();
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{}:{:02}", minutes, seconds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_elapsed() {
+        assert_eq!(format_elapsed(Duration::from_millis(59_900)), "0:59");
+        assert_eq!(format_elapsed(Duration::from_secs(61)), "1:01");
+        assert_eq!(format_elapsed(Duration::from_secs(3600 + 5 * 60 + 7)), "1:05:07");
+    }
+}
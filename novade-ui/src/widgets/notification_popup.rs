@@ -1,7 +1,13 @@
 // novade-ui/src/widgets/notification_popup.rs
+use gtk::gdk;
+use gtk::gio;
 use gtk::glib;
 use gtk::prelude::*;
+use gtk::subclass::prelude::*;
 use gtk::{Box, Label, Orientation};
+use novade_domain::{Notification, NotificationAction, NotificationActionType};
+use std::path::PathBuf;
+use uuid::Uuid;
 
 glib::wrapper! {
     pub struct NotificationPopupWidget(ObjectSubclass<imp::NotificationPopupWidget>)
@@ -9,6 +15,13 @@ glib::wrapper! {
         @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget, gtk::Orientable;
 }
 
+/// Key of the action invoked by clicking the popup itself rather than one of its buttons.
+pub const DEFAULT_ACTION_KEY: &str = "default";
+/// Most attachment thumbnails a popup shows.
+const MAX_THUMBNAILS: usize = 3;
+const THUMBNAIL_SIZE: i32 = 64;
+const IMAGE_SIZE: i32 = 48;
+
 impl NotificationPopupWidget {
     pub fn new() -> Self {
         glib::Object::new()
@@ -26,13 +39,157 @@ impl NotificationPopupWidget {
     pub fn get_id(&self) -> u32 {
         *self.imp().notification_id.borrow()
     }
+
+    /// Shows `notification` with its image, progress, attachment thumbnails, action buttons and
+    /// inline reply field. Emits `action-invoked` (action key) for a button or a click on the
+    /// popup, `replied` (text) when a reply is sent and `dismissed` on Escape.
+    pub fn set_notification(&self, notification: &Notification) {
+        let imp = self.imp();
+        imp.notification_uuid.replace(Some(notification.id));
+        imp.app_name_label.set_text(&notification.application_name);
+        imp.summary_label.set_text(&notification.summary);
+        // The server only lets the markup subset of the notification spec through.
+        imp.body_label.set_markup(notification.body.as_deref().unwrap_or(""));
+        imp.body_label.set_visible(notification.body.is_some());
+
+        match &notification.image {
+            Some(image) => {
+                let bytes = glib::Bytes::from(&image.rgba);
+                let texture = gdk::MemoryTexture::new(
+                    image.width as i32,
+                    image.height as i32,
+                    gdk::MemoryFormat::R8g8b8a8,
+                    &bytes,
+                    image.width as usize * 4,
+                );
+                imp.image.set_paintable(Some(&texture));
+                imp.image.set_visible(true);
+            }
+            None => imp.image.set_visible(false),
+        }
+
+        match notification.progress() {
+            Some(percent) => {
+                imp.progress_bar.set_fraction(f64::from(percent) / 100.0);
+                imp.progress_bar.set_text(Some(&format!("{}%", percent)));
+                imp.progress_bar.set_visible(true);
+            }
+            None => imp.progress_bar.set_visible(false),
+        }
+
+        clear(&imp.thumbnails_box);
+        for path in thumbnail_paths(notification) {
+            let picture = gtk::Picture::for_filename(&path);
+            picture.set_size_request(THUMBNAIL_SIZE, THUMBNAIL_SIZE);
+            picture.set_content_fit(gtk::ContentFit::Cover);
+            picture.set_tooltip_text(path.file_name().and_then(|name| name.to_str()));
+            imp.thumbnails_box.append(&picture);
+        }
+        imp.thumbnails_box.set_visible(imp.thumbnails_box.first_child().is_some());
+
+        clear(&imp.actions_box);
+        for action in button_actions(notification) {
+            let button = gtk::Button::with_label(&action.label);
+            let (popup, key) = (self.downgrade(), action.key.clone());
+            button.connect_clicked(move |_| {
+                if let Some(popup) = popup.upgrade() {
+                    popup.emit_by_name::<()>("action-invoked", &[&key]);
+                }
+            });
+            imp.actions_box.append(&button);
+        }
+        imp.actions_box.set_visible(imp.actions_box.first_child().is_some());
+
+        match notification.reply_action() {
+            Some(action) => {
+                imp.reply_entry.set_placeholder_text(Some(notification.reply_placeholder().unwrap_or("Reply…")));
+                imp.reply_button.set_label(&action.label);
+                imp.reply_box.set_visible(true);
+            }
+            None => imp.reply_box.set_visible(false),
+        }
+        imp.has_default_action.set(notification.actions.iter().any(|action| action.key == DEFAULT_ACTION_KEY));
+    }
+
+    /// The domain id of the notification shown with [`Self::set_notification`].
+    pub fn notification_uuid(&self) -> Option<Uuid> {
+        *self.imp().notification_uuid.borrow()
+    }
+
+    pub fn accepts_reply(&self) -> bool {
+        self.imp().reply_box.is_visible()
+    }
+
+    /// Whether the user is writing a reply, so the popup should stay up.
+    pub fn is_composing(&self) -> bool {
+        let entry = &self.imp().reply_entry;
+        entry.has_focus() || !entry.text().is_empty()
+    }
+
+    /// Moves keyboard focus to the reply field, or else to the first action button.
+    pub fn focus_first(&self) -> bool {
+        let imp = self.imp();
+        if imp.reply_box.is_visible() {
+            return imp.reply_entry.grab_focus();
+        }
+        imp.actions_box.first_child().map_or(false, |button| button.grab_focus())
+    }
+
+    fn send_reply(&self) {
+        let entry = &self.imp().reply_entry;
+        if let Some(text) = reply_text(&entry.text()) {
+            self.emit_by_name::<()>("replied", &[&text]);
+            entry.set_text("");
+        }
+    }
+}
+
+fn clear(container: &Box) {
+    while let Some(child) = container.first_child() {
+        container.remove(&child);
+    }
+}
+
+/// Actions that get a button: all but the default action, which a click on the popup invokes,
+/// and the inline reply, which has its own field.
+pub fn button_actions(notification: &Notification) -> Vec<&NotificationAction> {
+    notification
+        .actions
+        .iter()
+        .filter(|action| action.key != DEFAULT_ACTION_KEY && action.action_type != NotificationActionType::InlineReply)
+        .collect()
+}
+
+/// Local files among the notification's attachments; remote URLs get no thumbnail.
+pub fn thumbnail_paths(notification: &Notification) -> Vec<PathBuf> {
+    notification
+        .attachment_urls()
+        .into_iter()
+        .filter(|url| url.starts_with("file:"))
+        .filter_map(|url| gio::File::for_uri(url).path())
+        .take(MAX_THUMBNAILS)
+        .collect()
+}
+
+/// The text to send for what was typed, or `None` if there is nothing to send.
+pub fn reply_text(typed: &str) -> Option<String> {
+    let text = typed.trim();
+    (!text.is_empty()).then(|| text.to_string())
 }
 
 mod imp {
     use super::*;
-    // No need for InitializingObject with current glib versions for ObjectImpl::constructed
-    use gtk::subclass::prelude::*;
-    use std::cell::RefCell;
+    use gtk::glib::subclass::Signal;
+    use once_cell::sync::Lazy;
+    use std::cell::{Cell, RefCell};
+
+    static SIGNALS: Lazy<Vec<Signal>> = Lazy::new(|| {
+        vec![
+            Signal::builder("action-invoked").param_types([String::static_type()]).build(),
+            Signal::builder("replied").param_types([String::static_type()]).build(),
+            Signal::builder("dismissed").build(),
+        ]
+    });
 
     // This defines the internal state of our widget.
     #[derive(Default)]
@@ -40,7 +197,16 @@ mod imp {
         pub app_name_label: Label,
         pub summary_label: Label,
         pub body_label: Label,
+        pub image: gtk::Picture,
+        pub progress_bar: gtk::ProgressBar,
+        pub thumbnails_box: Box,
+        pub actions_box: Box,
+        pub reply_box: Box,
+        pub reply_entry: gtk::Entry,
+        pub reply_button: gtk::Button,
         pub notification_id: RefCell<u32>,
+        pub notification_uuid: RefCell<Option<Uuid>>,
+        pub has_default_action: Cell<bool>,
     }
 
     #[glib::object_subclass]
@@ -49,16 +215,37 @@ mod imp {
         type Type = super::NotificationPopupWidget;
         type ParentType = gtk::Box;
 
+        fn class_init(klass: &mut Self::Class) {
+            klass.install_signals(&SIGNALS);
+        }
+
         //implicitly calls parent's default new, then this.
         fn new() -> Self {
             let app_name_label = Label::builder().halign(gtk::Align::Start).wrap(true).build();
             let summary_label = Label::builder().halign(gtk::Align::Start).wrap(true).build();
             let body_label = Label::builder().halign(gtk::Align::Start).wrap(true).build();
+            let image = gtk::Picture::builder().can_shrink(true).visible(false).build();
+            image.set_size_request(IMAGE_SIZE, IMAGE_SIZE);
+            let progress_bar = gtk::ProgressBar::builder().show_text(true).visible(false).build();
+            let thumbnails_box = Box::builder().orientation(Orientation::Horizontal).spacing(4).visible(false).build();
+            let actions_box = Box::builder().orientation(Orientation::Horizontal).spacing(4).homogeneous(true).visible(false).build();
+            let reply_entry = gtk::Entry::builder().hexpand(true).build();
+            let reply_button = gtk::Button::with_label("Send");
+            let reply_box = Box::builder().orientation(Orientation::Horizontal).spacing(4).visible(false).build();
             Self {
                 app_name_label,
                 summary_label,
                 body_label,
+                image,
+                progress_bar,
+                thumbnails_box,
+                actions_box,
+                reply_box,
+                reply_entry,
+                reply_button,
                 notification_id: RefCell::new(0),
+                notification_uuid: RefCell::new(None),
+                has_default_action: Cell::new(false),
             }
         }
     }
@@ -74,10 +261,62 @@ mod imp {
             obj.set_orientation(Orientation::Vertical);
             obj.set_spacing(5); // Set some spacing between elements
 
-            // Add the labels to the Box container
-            obj.append(&self.app_name_label);
-            obj.append(&self.summary_label);
-            obj.append(&self.body_label);
+            // The image sits next to the text; everything else goes below.
+            let text_box = Box::new(Orientation::Vertical, 2);
+            text_box.set_hexpand(true);
+            text_box.append(&self.app_name_label);
+            text_box.append(&self.summary_label);
+            text_box.append(&self.body_label);
+            let content_box = Box::new(Orientation::Horizontal, 8);
+            content_box.append(&self.image);
+            content_box.append(&text_box);
+            obj.append(&content_box);
+            obj.append(&self.progress_bar);
+            obj.append(&self.thumbnails_box);
+            obj.append(&self.actions_box);
+            self.reply_box.append(&self.reply_entry);
+            self.reply_box.append(&self.reply_button);
+            obj.append(&self.reply_box);
+
+            // Clicking the popup's content invokes the default action, if it has one.
+            let click = gtk::GestureClick::new();
+            let popup = obj.downgrade();
+            click.connect_released(move |_, _, _, _| {
+                if let Some(popup) = popup.upgrade() {
+                    if popup.imp().has_default_action.get() {
+                        popup.emit_by_name::<()>("action-invoked", &[&DEFAULT_ACTION_KEY.to_string()]);
+                    }
+                }
+            });
+            content_box.add_controller(click);
+
+            // Enter in the field sends the reply just like the button does.
+            let popup = obj.downgrade();
+            self.reply_entry.connect_activate(move |_| {
+                if let Some(popup) = popup.upgrade() {
+                    popup.send_reply();
+                }
+            });
+            let popup = obj.downgrade();
+            self.reply_button.connect_clicked(move |_| {
+                if let Some(popup) = popup.upgrade() {
+                    popup.send_reply();
+                }
+            });
+
+            // Tab moves between the buttons and the reply field; Escape dismisses.
+            let keys = gtk::EventControllerKey::new();
+            let popup = obj.downgrade();
+            keys.connect_key_pressed(move |_, key, _, _| {
+                if key != gdk::Key::Escape {
+                    return glib::Propagation::Proceed;
+                }
+                if let Some(popup) = popup.upgrade() {
+                    popup.emit_by_name::<()>("dismissed", &[]);
+                }
+                glib::Propagation::Stop
+            });
+            obj.add_controller(keys);
         }
     }
 
@@ -90,3 +329,41 @@ mod imp {
     // Trait shared by all GtkOrientable widgets (Box is one)
     impl OrientableImpl for NotificationPopupWidget {}
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use novade_domain::{NotificationUrgency, ATTACHMENT_URLS_HINT};
+
+    fn action(key: &str, action_type: NotificationActionType) -> NotificationAction {
+        NotificationAction { key: key.to_string(), label: key.to_string(), action_type }
+    }
+
+    #[test]
+    fn test_button_actions_leave_out_default_and_reply() {
+        let mut notification = Notification::new("Chat".to_string(), "Alice".to_string(), NotificationUrgency::Normal);
+        notification.actions = vec![
+            action(DEFAULT_ACTION_KEY, NotificationActionType::Callback),
+            action("mark-read", NotificationActionType::Callback),
+            action("inline-reply", NotificationActionType::InlineReply),
+        ];
+        let keys: Vec<&str> = button_actions(&notification).iter().map(|a| a.key.as_str()).collect();
+        assert_eq!(keys, ["mark-read"]);
+    }
+
+    #[test]
+    fn test_thumbnails_are_local_files_only() {
+        let mut notification = Notification::new("Files".to_string(), "Saved".to_string(), NotificationUrgency::Normal);
+        notification.hints.insert(
+            ATTACHMENT_URLS_HINT.to_string(),
+            serde_json::json!(["file:///tmp/a%20b.png", "https://example.org/c.png", "file:///tmp/d.png"]),
+        );
+        assert_eq!(thumbnail_paths(&notification), [PathBuf::from("/tmp/a b.png"), PathBuf::from("/tmp/d.png")]);
+    }
+
+    #[test]
+    fn test_reply_text() {
+        assert_eq!(reply_text("  on my way \n").as_deref(), Some("on my way"));
+        assert_eq!(reply_text("   "), None);
+    }
+}